@@ -0,0 +1,255 @@
+//! Running bindings step by step.
+//!
+//! [`Executor`] walks an expanded binding: it sleeps through delays, expands a conditional's
+//! branch once the condition is known, applies the per-step timeout and stops early when its
+//! [`CancelToken`] is cancelled. A [`StepHandler`] does the work of the plugin and builtin steps
+//! and evaluates conditions; every step's start and outcome is reported as [`Progress`]. A
+//! failed step is reported and the sequence carries on with the next one.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::sync::Notify;
+
+use crate::{expand_with, ActionBinding, ActionStep, Condition, ExpandError, ExpandLimits};
+
+/// Does the work of the steps an [`Executor`] runs.
+#[async_trait]
+pub trait StepHandler: Send + Sync {
+    /// Run a plugin or builtin step; delays and branches never get here.
+    async fn run(&self, step: ActionStep) -> anyhow::Result<()>;
+
+    /// Whether the `condition` of a branch step holds.
+    async fn evaluate(&self, condition: &Condition) -> anyhow::Result<bool>;
+
+    /// One line naming `step` in [`Progress::Started`].
+    fn describe(&self, step: &ActionStep) -> String {
+        format!("{step:?}")
+    }
+}
+
+/// What an [`Executor`] is doing, reported as it goes.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// Step `index` (counted over the whole run) started. Steps of a conditional's branch are
+    /// one `depth` deeper than the conditional.
+    Started {
+        index: usize,
+        depth: usize,
+        label: String,
+    },
+    Finished {
+        index: usize,
+        result: Result<(), String>,
+        duration: Duration,
+    },
+    /// A conditional was evaluated; its branch's steps follow at `depth`. `has_actions` is
+    /// false when the branch taken is empty.
+    BranchTaken {
+        depth: usize,
+        matched: bool,
+        has_actions: bool,
+    },
+    /// The run was cancelled; no further steps start.
+    Cancelled,
+}
+
+/// Stops the runs it was given to. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // Created before the check so a `cancel` in between still wakes it.
+            let notified = self.0.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Executor {
+    limits: ExpandLimits,
+    step_timeout: Option<Duration>,
+    skip_delays: bool,
+    cancel: CancelToken,
+}
+
+impl Executor {
+    pub fn new(limits: ExpandLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Fail steps that run longer than `timeout`.
+    pub fn step_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.step_timeout = timeout;
+        self
+    }
+
+    /// Go straight past delays, e.g. for dry runs.
+    pub fn skip_delays(mut self, skip: bool) -> Self {
+        self.skip_delays = skip;
+        self
+    }
+
+    /// Stop running (between steps, or during a delay) once `cancel` is cancelled.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Expand `binding` and run its steps. Fails only if the binding cannot be expanded; step
+    /// failures are reported through `on_progress`.
+    pub async fn run(
+        &self,
+        binding: &ActionBinding,
+        handler: &dyn StepHandler,
+        on_progress: &(dyn Fn(Progress) + Send + Sync),
+    ) -> Result<(), ExpandError> {
+        let steps = expand_with(binding, &self.limits)?;
+        self.run_steps(steps, handler, on_progress).await;
+        Ok(())
+    }
+
+    /// Run already expanded `steps`. Returns `false` if the run was cancelled.
+    pub async fn run_steps(
+        &self,
+        steps: Vec<ActionStep>,
+        handler: &dyn StepHandler,
+        on_progress: &(dyn Fn(Progress) + Send + Sync),
+    ) -> bool {
+        let mut next_index = 0;
+        let completed = self
+            .run_level(steps, 0, &mut next_index, handler, on_progress)
+            .await;
+        if !completed {
+            on_progress(Progress::Cancelled);
+        }
+        completed
+    }
+
+    /// Boxed because branches run their steps through here recursively.
+    fn run_level<'a>(
+        &'a self,
+        steps: Vec<ActionStep>,
+        depth: usize,
+        next_index: &'a mut usize,
+        handler: &'a dyn StepHandler,
+        on_progress: &'a (dyn Fn(Progress) + Send + Sync),
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(async move {
+            for step in steps {
+                if self.cancel.is_cancelled() {
+                    return false;
+                }
+                let index = *next_index;
+                *next_index += 1;
+                on_progress(Progress::Started {
+                    index,
+                    depth,
+                    label: handler.describe(&step),
+                });
+                let started = Instant::now();
+
+                let result = match step {
+                    ActionStep::DelayMs(_) if self.skip_delays => Ok(()),
+                    ActionStep::DelayMs(ms) => {
+                        tokio::select! {
+                            _ = tokio::time::sleep(Duration::from_millis(ms)) => Ok(()),
+                            _ = self.cancel.cancelled() => Err("cancelled".to_string()),
+                        }
+                    }
+                    ActionStep::Branch {
+                        condition,
+                        then,
+                        otherwise,
+                    } => match handler.evaluate(&condition).await {
+                        Ok(matched) => {
+                            let branch = if matched { then } else { otherwise };
+                            on_progress(Progress::BranchTaken {
+                                depth: depth + 1,
+                                matched,
+                                has_actions: branch.is_some(),
+                            });
+                            match branch.map(|b| expand_with(&b, &self.limits)) {
+                                Some(Ok(steps)) => {
+                                    let completed = self
+                                        .run_level(
+                                            steps,
+                                            depth + 1,
+                                            next_index,
+                                            handler,
+                                            on_progress,
+                                        )
+                                        .await;
+                                    if completed {
+                                        Ok(())
+                                    } else {
+                                        Err("cancelled".to_string())
+                                    }
+                                }
+                                Some(Err(e)) => Err(e.to_string()),
+                                None => Ok(()),
+                            }
+                        }
+                        Err(e) => Err(e.to_string()),
+                    },
+                    step => self.run_step(step, handler).await,
+                };
+
+                on_progress(Progress::Finished {
+                    index,
+                    result,
+                    duration: started.elapsed(),
+                });
+            }
+            !self.cancel.is_cancelled()
+        })
+    }
+
+    async fn run_step(&self, step: ActionStep, handler: &dyn StepHandler) -> Result<(), String> {
+        let run = handler.run(step);
+        let result = match self.step_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, run).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!(
+                    "timed out after {} ms",
+                    timeout.as_millis()
+                )),
+            },
+            None => run.await,
+        };
+        result.map_err(|e| e.to_string())
+    }
+}
@@ -2,11 +2,14 @@
 //!
 //! This crate defines:
 //! - The serializable action model (`ActionBinding`, `BuiltinAction`)
-//! - A lightweight executor that expands bindings (e.g., Macro) into a linear
-//!   sequence of `ActionStep`s that the UI/runtime can execute.
+//! - A lightweight expander that turns bindings (e.g., Macro) into a linear
+//!   sequence of `ActionStep`s
+//! - An [`executor::Executor`] that runs those steps through a host-provided handler
 
 use serde::{Deserialize, Serialize};
 
+pub mod executor;
+
 /// A plugin action binding (OpenAction-style), matching existing on-disk profiles.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PluginActionBinding {
@@ -22,7 +25,8 @@ pub struct PluginActionBinding {
 pub enum BuiltinAction {
     /// Runs multiple actions in order.
     Macro { steps: Vec<MacroStep> },
-    /// Runs a shell command.
+    /// Runs a shell command (`bash -lc` on Linux and macOS, `cmd /C` on Windows, unless
+    /// configured; AppleScript can be picked on macOS).
     IssueCommand {
         command: String,
         #[serde(default)]
@@ -32,27 +36,297 @@ pub enum BuiltinAction {
     },
     /// Sends keyboard input.
     ///
-    /// Linux MVP: uses an external tool configured by the host app.
+    /// Uses an external tool configured by the host app (wtype by default); Windows and macOS
+    /// send the input themselves unless a tool is configured.
     /// - If `text` is set, it will be typed.
-    /// - If `keys` is set, a chord/sequence will be sent (tool-dependent).
+    /// - Else if `chord` is set, it is pressed, in the form the keyboard tool takes.
+    /// - Else if `keys` is set, a chord/sequence will be sent (wtype arguments, e.g. `-k Return`).
     KeyboardInput {
         #[serde(default)]
         text: Option<String>,
         #[serde(default)]
         keys: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chord: Option<KeyChord>,
+    },
+    /// Play an audio file without waiting for it to finish; overlapping sounds are mixed.
+    PlaySound {
+        path: String,
+        /// Volume in percent (default 100).
+        #[serde(default)]
+        volume: Option<u8>,
+    },
+    /// Stop playback of `path`, or of every sound when `path` is unset.
+    StopSound {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// Soundboard pad: plays `path` and highlights its key while the sound plays.
+    Soundboard {
+        path: String,
+        /// Volume in percent (default 100).
+        #[serde(default)]
+        volume: Option<u8>,
+        #[serde(default)]
+        mode: SoundboardMode,
+    },
+    /// Tally key: adds `step` to a persisted per-key count that is shown on the key.
+    Counter {
+        #[serde(default = "default_counter_step")]
+        step: i64,
+        /// Holding the key resets the count; the key then counts on release instead of press.
+        #[serde(default)]
+        reset_on_long_press: bool,
+    },
+    /// Set a shared variable; `value` may reference other variables (`{{name}}`).
+    SetVariable { name: String, value: String },
+    /// Run `then` or `otherwise` depending on `condition`, checked when the binding fires.
+    Conditional {
+        condition: Condition,
+        #[serde(default)]
+        then: Option<Box<ActionBinding>>,
+        #[serde(default)]
+        otherwise: Option<Box<ActionBinding>>,
+    },
+    /// Open an application entry, a file/directory or a URL with the platform's opener.
+    Open { target: String },
+    /// Put text on the clipboard, paste it, or go back through clipboard history.
+    Clipboard { mode: ClipboardMode },
+    /// Call a Home Assistant service (`domain.service`, e.g. `light.toggle`) on an entity.
+    HomeAssistant {
+        service: String,
+        #[serde(default)]
+        entity_id: String,
+        /// Service data as JSON, e.g. `{"brightness_pct": 40}`; empty for none.
+        #[serde(default)]
+        data: String,
+    },
+    /// Control Spotify playback on the signed-in account.
+    Spotify { command: SpotifyCommand },
+    /// Capture the screen to a PNG in `save_dir` (the Pictures directory when unset).
+    Screenshot {
+        #[serde(default)]
+        region: ScreenshotRegion,
+        #[serde(default)]
+        save_dir: Option<String>,
+        /// Also put the image on the clipboard.
+        #[serde(default)]
+        clipboard: bool,
+    },
+    /// Start recording the screen to `save_dir` (the Videos directory when unset); press again
+    /// to stop.
+    ScreenRecord {
+        #[serde(default)]
+        save_dir: Option<String>,
     },
-    /// Play an audio file.
-    PlaySound { path: String },
     /// Switch to a specific profile or cycle.
     SwitchProfile { mode: SwitchProfileMode },
     /// Adjust device brightness.
     DeviceBrightness { mode: BrightnessMode },
-    /// Live system monitoring (display in UI first).
+    /// Live system monitoring: the value of `kind` on a key, or a dashboard of graphs across
+    /// the touch strip when bound there.
     SystemMonitoring {
         kind: MonitorKind,
+        /// How often the value is sampled (500 ms when unset).
         #[serde(default)]
         refresh_ms: Option<u64>,
+        /// Panels of the touch strip dashboard, left to right; just `kind` when empty.
+        #[serde(default)]
+        metrics: Vec<MonitorKind>,
+    },
+    /// The time in `timezone` (an IANA name such as `America/New_York`; local time when unset),
+    /// optionally with the date and ISO week number, redrawn every minute.
+    Clock {
+        #[serde(default)]
+        timezone: Option<String>,
+        #[serde(default)]
+        format: ClockFormat,
+        #[serde(default)]
+        show_date: bool,
+        #[serde(default)]
+        show_week: bool,
+    },
+    /// Current weather at `location` (a place name, or `latitude,longitude`) from Open-Meteo:
+    /// the temperature and a conditions icon on the key, fetched every `refresh_min` minutes
+    /// (15 when unset). The last report stays on the key while offline.
+    Weather {
+        location: String,
+        #[serde(default)]
+        units: WeatherUnits,
+        #[serde(default)]
+        refresh_min: Option<u32>,
+    },
+    /// A Pomodoro timer: pressing the key starts, pauses or resumes the current phase. Work
+    /// phases of `work_min` minutes alternate with breaks of `break_min`; every
+    /// `long_break_every`th break lasts `long_break_min` instead (never when 0). The key's
+    /// colour follows the phase, and the timer survives restarts.
+    Pomodoro {
+        #[serde(default = "default_pomodoro_work")]
+        work_min: u32,
+        #[serde(default = "default_pomodoro_break")]
+        break_min: u32,
+        #[serde(default = "default_pomodoro_long_break_every")]
+        long_break_every: u32,
+        #[serde(default = "default_pomodoro_long_break")]
+        long_break_min: u32,
+        /// Played when a phase ends.
+        #[serde(default)]
+        sound: Option<String>,
+        /// Show a desktop notification when a phase ends.
+        #[serde(default)]
+        notify: bool,
+    },
+    /// Show a desktop notification; `title` and `body` may reference variables (`{{name}}`).
+    Notify {
+        title: String,
+        #[serde(default)]
+        body: String,
+    },
+    /// Turn the desktop's do-not-disturb on or off; the key shows whether it is on.
+    DoNotDisturb {
+        #[serde(default)]
+        mode: DoNotDisturbMode,
+    },
+    /// Turn a wireless radio on when it is off and off when it is on; the key shows which.
+    Radio { radio: RadioKind },
+    /// Lock the session, suspend or shut down. With `require_long_press` the key has to be
+    /// held for a moment first, shown as a ring filling up, so a stray press does nothing.
+    Power {
+        command: PowerCommand,
+        #[serde(default = "default_power_long_press")]
+        require_long_press: bool,
+    },
+    /// Open a folder in VS Code, or run one of the tasks in its `.vscode/tasks.json`; a key
+    /// running a task shows whether it is running, succeeded or failed.
+    VsCode {
+        workspace: String,
+        #[serde(default)]
+        command: VsCodeCommand,
+    },
+    /// Show a git repository's branch, uncommitted changes and commits ahead of and behind its
+    /// upstream; a press runs `command` in it.
+    Git {
+        repo: String,
+        #[serde(default)]
+        command: GitCommand,
+    },
+    /// Show the latest GitHub Actions run of `repo` (`owner/name`), of one workflow (file name
+    /// or id) and branch when set; a press opens the run on github.com.
+    CiStatus {
+        repo: String,
+        #[serde(default)]
+        workflow: String,
+        #[serde(default)]
+        branch: String,
+    },
+    /// Switch, dim or color a Philips Hue light (through the bridge paired in the settings), a
+    /// WLED controller or an Elgato Key Light; the key or dial shows whether it is on and how
+    /// bright.
+    Light {
+        light: LightTarget,
+        #[serde(default)]
+        command: LightCommand,
+    },
+    /// Mute, turn the camera on or off in, or leave a Zoom or Microsoft Teams meeting; the key
+    /// shows whether a meeting is on and, in Teams, whether the microphone or camera is.
+    Meeting {
+        app: MeetingApp,
+        #[serde(default)]
+        command: MeetingCommand,
     },
+    /// Start, stop or restart a Docker or Podman container (by name or id); the key shows
+    /// whether it is running.
+    Container {
+        container: String,
+        command: ContainerCommand,
+    },
+    /// Send a MIDI message to an output port, e.g. to control a DAW. `port` is matched against
+    /// the port names (case-insensitively, in part); empty picks the first port.
+    Midi {
+        #[serde(default)]
+        port: String,
+        message: MidiMessage,
+    },
+    /// Send an OSC message over UDP to `target` (`host:port`), e.g. to a lighting desk or mixer.
+    /// The address and arguments may use variables; arguments are separated by spaces and sent as
+    /// integers, floats (with a `.`), `true`/`false` or strings (double quotes keep spaces).
+    Osc {
+        target: String,
+        address: String,
+        #[serde(default)]
+        args: String,
+    },
+    /// Write `payload` to the serial port `port` (e.g. `/dev/ttyACM0` or `COM3`) at `baud`, e.g.
+    /// to drive an Arduino. A text payload may use variables.
+    Serial {
+        port: String,
+        #[serde(default = "default_serial_baud")]
+        baud: u32,
+        payload: String,
+        #[serde(default)]
+        format: SerialFormat,
+    },
+    /// Wake a computer by sending a magic packet for its network card's `mac` address to
+    /// `broadcast` (an IPv4 broadcast address, with `:port` to use another UDP port than 9).
+    WakeOnLan {
+        mac: String,
+        #[serde(default = "default_wake_on_lan_broadcast")]
+        broadcast: String,
+    },
+    /// Run a small script in an embedded interpreter that can run commands, set variables,
+    /// change the key's text and sleep.
+    Script {
+        #[serde(default)]
+        language: ScriptLanguage,
+        source: String,
+    },
+}
+
+fn default_counter_step() -> i64 {
+    1
+}
+
+fn default_pomodoro_work() -> u32 {
+    25
+}
+
+fn default_pomodoro_break() -> u32 {
+    5
+}
+
+fn default_pomodoro_long_break_every() -> u32 {
+    4
+}
+
+fn default_pomodoro_long_break() -> u32 {
+    15
+}
+
+fn default_power_long_press() -> bool {
+    true
+}
+
+fn default_serial_baud() -> u32 {
+    9600
+}
+
+fn default_wake_on_lan_broadcast() -> String {
+    "255.255.255.255".to_string()
+}
+
+/// The six bytes of a MAC address written as hex pairs, separated by `:` or `-` or not at all
+/// (e.g. `00:1A:2B:3C:4D:5E`).
+pub fn parse_mac_address(mac: &str) -> anyhow::Result<[u8; 6]> {
+    let mac = mac.trim();
+    let digits: String = mac.chars().filter(|c| !matches!(c, ':' | '-')).collect();
+    let mut bytes = [0u8; 6];
+    if digits.len() != 12 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("\"{mac}\" is not a MAC address, like 00:1A:2B:3C:4D:5E");
+    }
+    for (byte, pair) in bytes.iter_mut().zip(digits.as_bytes().chunks(2)) {
+        *byte = hex_byte(pair)?;
+    }
+    Ok(bytes)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -62,6 +336,144 @@ pub struct MacroStep {
     pub delay_ms: Option<u64>,
 }
 
+/// A key pressed while modifiers are held, e.g. Ctrl+Shift+P.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyChord {
+    /// Held in this order, and released the other way round.
+    #[serde(default)]
+    pub modifiers: Vec<KeyModifier>,
+    /// X keysym name of the key, e.g. `Return`, `F5` or `p`.
+    pub key: String,
+}
+
+impl KeyChord {
+    /// The chord as wtype arguments, e.g. `-M ctrl -k p -m ctrl`.
+    pub fn wtype_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for m in &self.modifiers {
+            args.extend(["-M".to_string(), m.wtype_name().to_string()]);
+        }
+        args.extend(["-k".to_string(), self.key.clone()]);
+        for m in self.modifiers.iter().rev() {
+            args.extend(["-m".to_string(), m.wtype_name().to_string()]);
+        }
+        args
+    }
+}
+
+impl std::fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.modifiers {
+            write!(f, "{m}+")?;
+        }
+        match self.key.chars().next() {
+            Some(c) if self.key.len() == 1 => write!(f, "{}", c.to_ascii_uppercase()),
+            _ => f.write_str(&self.key),
+        }
+    }
+}
+
+/// A modifier of a [`KeyChord`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyModifier {
+    Ctrl,
+    Alt,
+    Shift,
+    /// The Windows, Command or Super key.
+    Logo,
+}
+
+impl KeyModifier {
+    /// The name wtype and xdotool know the modifier by.
+    pub fn wtype_name(self) -> &'static str {
+        match self {
+            KeyModifier::Ctrl => "ctrl",
+            KeyModifier::Alt => "alt",
+            KeyModifier::Shift => "shift",
+            KeyModifier::Logo => "logo",
+        }
+    }
+}
+
+impl std::fmt::Display for KeyModifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeyModifier::Ctrl => "Ctrl",
+            KeyModifier::Alt => "Alt",
+            KeyModifier::Shift => "Shift",
+            KeyModifier::Logo => "Super",
+        })
+    }
+}
+
+/// What a soundboard press does when sounds are already playing.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundboardMode {
+    /// Start another instance; overlapping instances are mixed.
+    #[default]
+    PlayOverlap,
+    /// Stop this pad's sound and play it from the start.
+    Restart,
+    /// Stop every other sound first.
+    StopOthers,
+}
+
+/// A check evaluated at dispatch time by `conditional` actions.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// A process with this executable name is running.
+    ProcessRunning { name: String },
+    /// The path exists (file or directory).
+    FileExists { path: String },
+    /// Local time is within `start..end` (`HH:MM`); wraps past midnight when `end < start`.
+    TimeRange { start: String, end: String },
+    /// The variable `name` currently has `value`.
+    VariableEquals { name: String, value: String },
+    /// The shell command exits with `code`.
+    ShellExitCode {
+        command: String,
+        #[serde(default)]
+        code: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpotifyCommand {
+    /// Pause when playing, resume otherwise.
+    PlayPause,
+    Next,
+    Previous,
+    /// Add the current track to Liked Songs, or remove it when already liked.
+    Like,
+}
+
+/// What a `screenshot` action captures.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenshotRegion {
+    #[default]
+    FullScreen,
+    /// A rectangle picked with the mouse when the action runs.
+    Selection,
+    /// The focused window.
+    ActiveWindow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardMode {
+    /// Replace the clipboard contents with the text.
+    CopyText(String),
+    /// Copy the text, then paste it into the focused window (uses the keyboard input tool).
+    PasteText(String),
+    /// Restore the previous clipboard entry; repeated presses go further back.
+    CyclePrevious,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum SwitchProfileMode {
@@ -81,12 +493,291 @@ pub enum BrightnessMode {
     Decrease { delta: u8 },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum MonitorKind {
     Cpu,
     Memory,
     LoadAverage,
+    /// Bytes received per second on `interface`; summed over every interface but loopback
+    /// when unset.
+    NetworkRx {
+        #[serde(default)]
+        interface: Option<String>,
+    },
+    /// Bytes sent per second, like [`MonitorKind::NetworkRx`].
+    NetworkTx {
+        #[serde(default)]
+        interface: Option<String>,
+    },
+    /// Used space of the filesystem mounted at `mount` (`/`, or `C:\` on Windows, when unset).
+    DiskUsage {
+        #[serde(default)]
+        mount: Option<String>,
+    },
+    /// Temperature of the sensor labelled `sensor`; the hottest one when unset.
+    Temperature {
+        #[serde(default)]
+        sensor: Option<String>,
+    },
+    /// Utilization of GPU number `gpu` (NVIDIA cards first, then AMD ones); the first when
+    /// unset.
+    GpuUsage {
+        #[serde(default)]
+        gpu: Option<u32>,
+    },
+    /// Used video memory of a GPU, like [`MonitorKind::GpuUsage`].
+    GpuMemory {
+        #[serde(default)]
+        gpu: Option<u32>,
+    },
+}
+
+/// How `clock` actions show the hour.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClockFormat {
+    /// `14:05`.
+    #[default]
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+    /// `2:05 PM`.
+    #[serde(rename = "12h")]
+    TwelveHour,
+}
+
+/// What a `do_not_disturb` action does.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DoNotDisturbMode {
+    #[default]
+    Toggle,
+    On,
+    Off,
+}
+
+/// How the payload of a `serial` action is written.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialFormat {
+    /// UTF-8 text with the escapes `\n`, `\r`, `\t`, `\\` and `\xHH` (a byte in hex).
+    #[default]
+    Text,
+    /// Bytes in hex, e.g. `FF 01 0a`; spaces between bytes are optional.
+    Hex,
+}
+
+impl SerialFormat {
+    /// The bytes `payload` stands for.
+    pub fn bytes(self, payload: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            SerialFormat::Text => unescape(payload),
+            SerialFormat::Hex => {
+                let digits: Vec<u8> = payload
+                    .bytes()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                if digits.len() % 2 != 0 {
+                    anyhow::bail!("odd number of hex digits");
+                }
+                digits.chunks(2).map(hex_byte).collect()
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        let Some((&escape, tail)) = rest.split_first() else {
+            anyhow::bail!("\\ at the end of the payload");
+        };
+        rest = tail;
+        bytes.push(match escape {
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'\\' => b'\\',
+            b'x' => {
+                let Some(digits) = rest.get(..2) else {
+                    anyhow::bail!("\\x needs two hex digits");
+                };
+                rest = &rest[2..];
+                hex_byte(digits)?
+            }
+            _ => anyhow::bail!("unknown escape \\{}", escape as char),
+        });
+    }
+    Ok(bytes)
+}
+
+fn hex_byte(digits: &[u8]) -> anyhow::Result<u8> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|d| u8::from_str_radix(d, 16).ok())
+        .ok_or_else(|| {
+            let digits = String::from_utf8_lossy(digits);
+            anyhow::anyhow!("\"{digits}\" is not a hex byte")
+        })
+}
+
+/// Wireless radios `radio` actions switch.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RadioKind {
+    Wifi,
+    Bluetooth,
+}
+
+/// What a `power` action does.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerCommand {
+    Lock,
+    Suspend,
+    Shutdown,
+}
+
+/// What a `vs_code` action does with its folder.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VsCodeCommand {
+    #[default]
+    Open,
+    /// Run the task with this label.
+    RunTask(String),
+}
+
+/// What pressing a `git` key runs.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GitCommand {
+    #[default]
+    Fetch,
+    Pull,
+    Push,
+}
+
+/// What a `container` action does with its container.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerCommand {
+    Start,
+    Stop,
+    Restart,
+}
+
+/// What a `midi` action sends. Channels are 1 to 16; notes, controllers, velocities and values
+/// are 0 to 127.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MidiMessage {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+}
+
+impl MidiMessage {
+    /// The message as sent on the wire; numbers out of range are clamped.
+    pub fn bytes(self) -> [u8; 3] {
+        let status = |kind: u8, channel: u8| kind | (channel.clamp(1, 16) - 1);
+        match self {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => [status(0x90, channel), note.min(127), velocity.min(127)],
+            MidiMessage::NoteOff { channel, note } => [status(0x80, channel), note.min(127), 0],
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => [status(0xB0, channel), controller.min(127), value.min(127)],
+        }
+    }
+}
+
+/// The light a `light` action controls.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LightTarget {
+    /// A light of the paired Hue bridge, by its id on the bridge.
+    Hue { light: String },
+    /// A WLED controller, by host name or address.
+    Wled { host: String },
+    /// An Elgato Key Light (or Key Light Air or Ring Light), by host name or address.
+    KeyLight { host: String },
+}
+
+/// What a `light` action does with its light.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LightCommand {
+    #[default]
+    Toggle,
+    On,
+    Off,
+    /// Set the brightness, in percent.
+    SetBrightness(u8),
+    /// Change the brightness by this many percent; bound to a dial's rotation, per detent in
+    /// the direction it turns.
+    StepBrightness(i8),
+    /// Turn the light on in this color.
+    Color([u8; 3]),
+    /// Turn the light on in white of this color temperature, in kelvin.
+    ColorTemperature(u16),
+}
+
+/// The app a `meeting` action controls.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MeetingApp {
+    Zoom,
+    Teams,
+}
+
+/// What a `meeting` action does in the current meeting.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeetingCommand {
+    #[default]
+    ToggleMute,
+    ToggleVideo,
+    Leave,
+}
+
+/// Units of `weather` reports.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherUnits {
+    /// Degrees Celsius.
+    #[default]
+    Metric,
+    /// Degrees Fahrenheit.
+    Imperial,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptLanguage {
+    #[default]
+    Rhai,
 }
 
 /// A key binding can point to either a plugin action or a builtin action.
@@ -100,65 +791,310 @@ pub enum ActionBinding {
     Builtin(BuiltinAction),
 }
 
+impl ActionBinding {
+    /// Returns true if this binding (or any nested macro step or branch) invokes `plugin_id`.
+    pub fn references_plugin(&self, plugin_id: &str) -> bool {
+        match self {
+            ActionBinding::Plugin(p) => p.plugin_id == plugin_id,
+            ActionBinding::Builtin(BuiltinAction::Macro { steps }) => {
+                steps.iter().any(|s| s.action.references_plugin(plugin_id))
+            }
+            ActionBinding::Builtin(BuiltinAction::Conditional {
+                then, otherwise, ..
+            }) => [then, otherwise]
+                .into_iter()
+                .flatten()
+                .any(|b| b.references_plugin(plugin_id)),
+            ActionBinding::Builtin(_) => false,
+        }
+    }
+
+    /// Ids of the plugins this binding (or any nested macro step or branch) invokes, in order
+    /// of appearance and possibly repeated.
+    pub fn plugin_ids(&self) -> Vec<&str> {
+        match self {
+            ActionBinding::Plugin(p) => vec![p.plugin_id.as_str()],
+            ActionBinding::Builtin(BuiltinAction::Macro { steps }) => {
+                steps.iter().flat_map(|s| s.action.plugin_ids()).collect()
+            }
+            ActionBinding::Builtin(BuiltinAction::Conditional {
+                then, otherwise, ..
+            }) => [then, otherwise]
+                .into_iter()
+                .flatten()
+                .flat_map(|b| b.plugin_ids())
+                .collect(),
+            ActionBinding::Builtin(_) => vec![],
+        }
+    }
+
+    /// Returns true if this binding (or any nested macro step, branch or condition) runs a shell
+    /// command or a script.
+    pub fn runs_commands(&self) -> bool {
+        match self {
+            ActionBinding::Plugin(_) => false,
+            ActionBinding::Builtin(
+                BuiltinAction::IssueCommand { .. } | BuiltinAction::Script { .. },
+            ) => true,
+            ActionBinding::Builtin(BuiltinAction::Macro { steps }) => {
+                steps.iter().any(|s| s.action.runs_commands())
+            }
+            ActionBinding::Builtin(BuiltinAction::Conditional {
+                condition,
+                then,
+                otherwise,
+            }) => {
+                matches!(condition, Condition::ShellExitCode { .. })
+                    || [then, otherwise]
+                        .into_iter()
+                        .flatten()
+                        .any(|b| b.runs_commands())
+            }
+            ActionBinding::Builtin(_) => false,
+        }
+    }
+
+    /// Replace the settings of every invocation of `action_id` of `plugin_id` in this binding
+    /// (including nested macro steps and branches). Returns how many were replaced.
+    pub fn set_plugin_settings(
+        &mut self,
+        plugin_id: &str,
+        action_id: &str,
+        settings: &serde_json::Value,
+    ) -> usize {
+        match self {
+            ActionBinding::Plugin(p) if p.plugin_id == plugin_id && p.action_id == action_id => {
+                p.settings = settings.clone();
+                1
+            }
+            ActionBinding::Plugin(_) => 0,
+            ActionBinding::Builtin(BuiltinAction::Macro { steps }) => steps
+                .iter_mut()
+                .map(|s| s.action.set_plugin_settings(plugin_id, action_id, settings))
+                .sum(),
+            ActionBinding::Builtin(BuiltinAction::Conditional {
+                then, otherwise, ..
+            }) => [then, otherwise]
+                .into_iter()
+                .flatten()
+                .map(|b| b.set_plugin_settings(plugin_id, action_id, settings))
+                .sum(),
+            ActionBinding::Builtin(_) => 0,
+        }
+    }
+}
+
+/// Removes every reference to `plugin_id` from a binding slot.
+///
+/// - A direct plugin binding is cleared (`None`).
+/// - Macro steps invoking the plugin are dropped; the macro itself is kept.
+/// - Conditional branches invoking the plugin are cleared; the conditional itself is kept.
+///
+/// Returns the number of removed references.
+pub fn strip_plugin(slot: &mut Option<ActionBinding>, plugin_id: &str) -> usize {
+    fn strip_steps(steps: &mut Vec<MacroStep>, plugin_id: &str) -> usize {
+        let mut removed = 0;
+        steps.retain_mut(|s| match s.action.as_mut() {
+            ActionBinding::Plugin(p) if p.plugin_id == plugin_id => {
+                removed += 1;
+                false
+            }
+            b => {
+                removed += strip_nested(b, plugin_id);
+                true
+            }
+        });
+        removed
+    }
+
+    fn strip_branch(branch: &mut Option<Box<ActionBinding>>, plugin_id: &str) -> usize {
+        match branch.as_deref_mut() {
+            Some(ActionBinding::Plugin(p)) if p.plugin_id == plugin_id => {
+                *branch = None;
+                1
+            }
+            Some(b) => strip_nested(b, plugin_id),
+            None => 0,
+        }
+    }
+
+    fn strip_nested(b: &mut ActionBinding, plugin_id: &str) -> usize {
+        match b {
+            ActionBinding::Builtin(BuiltinAction::Macro { steps }) => strip_steps(steps, plugin_id),
+            ActionBinding::Builtin(BuiltinAction::Conditional {
+                then, otherwise, ..
+            }) => strip_branch(then, plugin_id) + strip_branch(otherwise, plugin_id),
+            _ => 0,
+        }
+    }
+
+    match slot {
+        Some(ActionBinding::Plugin(p)) if p.plugin_id == plugin_id => {
+            *slot = None;
+            1
+        }
+        Some(b) => strip_nested(b, plugin_id),
+        None => 0,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ActionStep {
     DelayMs(u64),
     Builtin(BuiltinAction),
     Plugin(PluginActionBinding),
+    /// Evaluate `condition` when the step runs, then expand and run the chosen branch.
+    Branch {
+        condition: Condition,
+        then: Option<Box<ActionBinding>>,
+        otherwise: Option<Box<ActionBinding>>,
+    },
+}
+
+/// Bounds on how far [`expand_with`] expands one binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpandLimits {
+    /// Most steps (delays included) a binding may expand to.
+    pub max_steps: usize,
+    /// Deepest nesting of macros inside macros.
+    pub max_depth: usize,
+}
+
+impl ExpandLimits {
+    pub const DEFAULT_MAX_STEPS: usize = 1024;
+    pub const DEFAULT_MAX_DEPTH: usize = 16;
+}
+
+impl Default for ExpandLimits {
+    fn default() -> Self {
+        Self {
+            max_steps: Self::DEFAULT_MAX_STEPS,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ExpandError {
     #[error("macro step count exceeded limit ({0})")]
     MacroTooLarge(usize),
+    /// `path` names the macro steps leading to the innermost macro, e.g. `step 2 > step 1`.
+    #[error("macros nested deeper than {limit} levels (at {path})")]
+    TooDeep { limit: usize, path: String },
+    /// Profile ids in the order the sequence switches to them; the last one closes the loop.
+    #[error("switches profiles in a loop ({})", format_profile_cycle(.0))]
+    ProfileCycle(Vec<u64>),
+}
+
+fn format_profile_cycle(ids: &[u64]) -> String {
+    ids.iter()
+        .map(|id| format!("profile {id}"))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Expands an `ActionBinding` into a linear sequence of executable steps, within the default
+/// [`ExpandLimits`].
+pub fn expand(binding: &ActionBinding) -> Result<Vec<ActionStep>, ExpandError> {
+    expand_with(binding, &ExpandLimits::default())
 }
 
 /// Expands an `ActionBinding` into a linear sequence of executable steps.
 ///
 /// - Macro steps are expanded depth-first.
-/// - A hard limit prevents runaway recursion.
-pub fn expand(binding: &ActionBinding) -> Result<Vec<ActionStep>, ExpandError> {
-    const MAX_STEPS: usize = 128;
+/// - Conditionals become a single `Branch` step; the branch taken is only known (and
+///   expanded) at dispatch time.
+/// - `limits` bound the step count and macro nesting, so a huge binding is refused up front.
+/// - A sequence that switches back to a profile it already switched away from is refused as a
+///   profile loop.
+pub fn expand_with(
+    binding: &ActionBinding,
+    limits: &ExpandLimits,
+) -> Result<Vec<ActionStep>, ExpandError> {
+    struct Expansion<'a> {
+        limits: &'a ExpandLimits,
+        out: Vec<ActionStep>,
+        /// 1-based index of the step being expanded, for each macro we are inside of.
+        path: Vec<usize>,
+        /// Targets of the `switch_profile` steps so far, in order.
+        switches: Vec<u64>,
+    }
 
-    fn push_binding(
-        out: &mut Vec<ActionStep>,
-        b: &ActionBinding,
-        steps: &mut usize,
-    ) -> Result<(), ExpandError> {
-        if *steps >= MAX_STEPS {
-            return Err(ExpandError::MacroTooLarge(MAX_STEPS));
+    impl Expansion<'_> {
+        fn push(&mut self, step: ActionStep) -> Result<(), ExpandError> {
+            if self.out.len() >= self.limits.max_steps {
+                return Err(ExpandError::MacroTooLarge(self.limits.max_steps));
+            }
+            self.out.push(step);
+            Ok(())
         }
 
-        match b {
-            ActionBinding::Plugin(p) => {
-                out.push(ActionStep::Plugin(p.clone()));
-                *steps += 1;
+        fn switch_to(&mut self, profile_id: u64) -> Result<(), ExpandError> {
+            if self.switches.last() == Some(&profile_id) {
+                return Ok(());
             }
-            ActionBinding::Builtin(BuiltinAction::Macro { steps: macro_steps }) => {
-                for s in macro_steps {
-                    if let Some(d) = s.delay_ms {
-                        out.push(ActionStep::DelayMs(d));
-                        *steps += 1;
-                        if *steps >= MAX_STEPS {
-                            return Err(ExpandError::MacroTooLarge(MAX_STEPS));
+            if let Some(first) = self.switches.iter().position(|&id| id == profile_id) {
+                let mut cycle = self.switches[first..].to_vec();
+                cycle.push(profile_id);
+                return Err(ExpandError::ProfileCycle(cycle));
+            }
+            self.switches.push(profile_id);
+            Ok(())
+        }
+
+        fn binding(&mut self, b: &ActionBinding) -> Result<(), ExpandError> {
+            match b {
+                ActionBinding::Plugin(p) => self.push(ActionStep::Plugin(p.clone())),
+                ActionBinding::Builtin(BuiltinAction::Macro { steps }) => {
+                    if self.path.len() >= self.limits.max_depth {
+                        return Err(ExpandError::TooDeep {
+                            limit: self.limits.max_depth,
+                            path: self
+                                .path
+                                .iter()
+                                .map(|i| format!("step {i}"))
+                                .collect::<Vec<_>>()
+                                .join(" > "),
+                        });
+                    }
+                    for (i, s) in steps.iter().enumerate() {
+                        self.path.push(i + 1);
+                        if let Some(d) = s.delay_ms {
+                            self.push(ActionStep::DelayMs(d))?;
                         }
+                        self.binding(&s.action)?;
+                        self.path.pop();
                     }
-                    push_binding(out, &s.action, steps)?;
+                    Ok(())
+                }
+                ActionBinding::Builtin(BuiltinAction::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                }) => self.push(ActionStep::Branch {
+                    condition: condition.clone(),
+                    then: then.clone(),
+                    otherwise: otherwise.clone(),
+                }),
+                ActionBinding::Builtin(bi) => {
+                    if let BuiltinAction::SwitchProfile {
+                        mode: SwitchProfileMode::To { profile_id },
+                    } = bi
+                    {
+                        self.switch_to(*profile_id)?;
+                    }
+                    self.push(ActionStep::Builtin(bi.clone()))
                 }
-            }
-            ActionBinding::Builtin(bi) => {
-                out.push(ActionStep::Builtin(bi.clone()));
-                *steps += 1;
             }
         }
-
-        Ok(())
     }
 
-    let mut out = Vec::new();
-    let mut steps = 0;
-    push_binding(&mut out, binding, &mut steps)?;
-    Ok(out)
+    let mut expansion = Expansion {
+        limits,
+        out: Vec::new(),
+        path: Vec::new(),
+        switches: Vec::new(),
+    };
+    expansion.binding(binding)?;
+    Ok(expansion.out)
 }
-
-
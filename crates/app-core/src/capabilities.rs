@@ -0,0 +1,57 @@
+//! What a device can show, reported by the device layer when it connects.
+//!
+//! Rendering and the UI size their images and layouts from this instead of guessing from the key
+//! count.
+
+/// Format the device takes key images in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    /// 24-bit bitmaps (the original Stream Deck and the Mini).
+    Bmp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    pub key_count: u8,
+    /// Key layout as `(columns, rows)`.
+    pub key_grid: (u8, u8),
+    /// Key image size in pixels.
+    pub key_size: (u32, u32),
+    /// Size of the LCD touch strip in pixels, on devices that have one (Stream Deck+).
+    pub lcd_strip_size: Option<(u32, u32)>,
+    /// Number of dials; each one owns an equal section of the LCD strip.
+    pub dials: u8,
+    pub image_format: ImageFormat,
+}
+
+impl DeviceCapabilities {
+    /// Best guess for a device with `key_count` keys, for when the device itself is not at hand.
+    pub fn for_key_count(key_count: u8) -> Self {
+        let (key_grid, key_size, image_format) = match key_count {
+            6 => ((3, 2), (80, 80), ImageFormat::Bmp),
+            8 => ((4, 2), (120, 120), ImageFormat::Jpeg),
+            32 => ((8, 4), (96, 96), ImageFormat::Jpeg),
+            _ => ((5, 3), (72, 72), ImageFormat::Jpeg),
+        };
+        let plus = key_count == 8;
+        Self {
+            key_count,
+            key_grid,
+            key_size,
+            lcd_strip_size: plus.then_some((800, 100)),
+            dials: if plus { 4 } else { 0 },
+            image_format,
+        }
+    }
+
+    pub fn supports_dials(&self) -> bool {
+        self.dials > 0
+    }
+
+    /// Size of the strip section above one dial.
+    pub fn dial_segment_size(&self) -> Option<(u32, u32)> {
+        let (width, height) = self.lcd_strip_size?;
+        (self.dials > 0).then(|| (width / u32::from(self.dials), height))
+    }
+}
@@ -1,4 +1,6 @@
+pub mod capabilities;
 pub mod ids;
+pub mod metrics;
 
 use ids::{ActionId, DeviceId, ProfileId};
 
@@ -0,0 +1,133 @@
+//! Process-wide counters and timings for monitoring.
+//!
+//! Every crate records into the same [`metrics()`] instance; the webhook listener serves them in
+//! the Prometheus text format on `GET /metrics` when enabled.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Upper bounds of the histogram buckets, in seconds.
+const BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Bindings started by a control, schedule, webhook or the editor's "Test" button.
+    pub actions_dispatched: Counter,
+    /// Action steps that failed.
+    pub action_failures: Counter,
+    /// Rendering of LCD images (images already rendered are not counted).
+    pub render_seconds: Histogram,
+    /// Writes to the device: key frames, dial and touch strip images, brightness.
+    pub hid_write_seconds: Histogram,
+    /// Plugin processes started; a plugin runs as a new process for every invocation.
+    pub plugin_starts: Counter,
+    /// Plugin processes that could not start or exited with an error.
+    pub plugin_failures: Counter,
+    /// Plugin invocations refused because the plugin's queue was full.
+    pub plugin_queue_rejections: Counter,
+}
+
+impl Metrics {
+    /// All metrics in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        self.actions_dispatched.encode(
+            &mut out,
+            "riverdeck_actions_dispatched_total",
+            "Bindings started.",
+        );
+        self.action_failures.encode(
+            &mut out,
+            "riverdeck_action_failures_total",
+            "Action steps that failed.",
+        );
+        self.render_seconds.encode(
+            &mut out,
+            "riverdeck_render_seconds",
+            "Time spent rendering LCD images.",
+        );
+        self.hid_write_seconds.encode(
+            &mut out,
+            "riverdeck_hid_write_seconds",
+            "Latency of writes to the device.",
+        );
+        self.plugin_starts.encode(
+            &mut out,
+            "riverdeck_plugin_starts_total",
+            "Plugin processes started.",
+        );
+        self.plugin_failures.encode(
+            &mut out,
+            "riverdeck_plugin_failures_total",
+            "Plugin processes that failed to start or exited with an error.",
+        );
+        self.plugin_queue_rejections.encode(
+            &mut out,
+            "riverdeck_plugin_queue_rejections_total",
+            "Plugin invocations refused because the plugin's queue was full.",
+        );
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn encode(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {}", self.get());
+    }
+}
+
+/// Durations sorted into [`BUCKETS`], plus their count and sum.
+#[derive(Debug, Default)]
+pub struct Histogram {
+    /// Observations per bucket (not cumulative); slower ones only count towards `count`.
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&le| seconds <= le) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn encode(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (le, bucket) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {count}");
+    }
+}
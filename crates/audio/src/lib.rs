@@ -0,0 +1,229 @@
+//! Audio playback subsystem.
+//!
+//! A dedicated thread owns the output stream (cpal streams are not `Send` on every platform) and
+//! keeps it open between sounds. Every sound plays on its own sink, so overlapping sounds are
+//! mixed, and [`AudioPlayer::play`] returns as soon as playback has started.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::Context;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+/// How often the audio thread checks for finished sounds while any are playing.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Names of the available output devices, for [`AudioPlayer::set_output_device`].
+pub fn output_devices() -> anyhow::Result<Vec<String>> {
+    let host = rodio::cpal::default_host();
+    Ok(host
+        .output_devices()?
+        .filter_map(|d| d.name().ok())
+        .collect())
+}
+
+/// A sound that started playing.
+#[derive(Debug)]
+pub struct Playback {
+    pub id: u64,
+    /// Resolves when the sound finished or was stopped.
+    pub finished: oneshot::Receiver<()>,
+}
+
+enum Command {
+    Play {
+        path: PathBuf,
+        volume: f32,
+        resp: oneshot::Sender<anyhow::Result<Playback>>,
+    },
+    Stop {
+        path: PathBuf,
+    },
+    StopAll,
+    SetDevice(Option<String>),
+}
+
+/// Handle to the audio thread. Cheap to clone; the thread exits when the last handle is dropped.
+#[derive(Clone)]
+pub struct AudioPlayer {
+    tx: mpsc::Sender<Command>,
+}
+
+impl AudioPlayer {
+    /// Start the audio thread.
+    ///
+    /// `device` is a name from [`output_devices`] (`None` = system default). The stream is only
+    /// opened when the first sound plays.
+    pub fn new(device: Option<String>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || run(rx, device))
+            .expect("failed to spawn audio thread");
+        Self { tx }
+    }
+
+    /// Start playing `path` at `volume` (1.0 = unchanged) without waiting for it to finish.
+    pub async fn play(&self, path: impl Into<PathBuf>, volume: f32) -> anyhow::Result<Playback> {
+        let (resp, rx) = oneshot::channel();
+        self.send(Command::Play {
+            path: path.into(),
+            volume,
+            resp,
+        })?;
+        rx.await
+            .map_err(|_| anyhow::anyhow!("audio thread stopped"))?
+    }
+
+    /// Stop every playing instance of `path`.
+    pub fn stop(&self, path: impl Into<PathBuf>) -> anyhow::Result<()> {
+        self.send(Command::Stop { path: path.into() })
+    }
+
+    pub fn stop_all(&self) -> anyhow::Result<()> {
+        self.send(Command::StopAll)
+    }
+
+    /// Switch the output device (`None` = system default). Playing sounds are stopped.
+    pub fn set_output_device(&self, device: Option<String>) -> anyhow::Result<()> {
+        self.send(Command::SetDevice(device))
+    }
+
+    fn send(&self, cmd: Command) -> anyhow::Result<()> {
+        self.tx
+            .send(cmd)
+            .map_err(|_| anyhow::anyhow!("audio thread stopped"))
+    }
+}
+
+struct Output {
+    // Dropping the stream stops all sinks created from its handle.
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+}
+
+struct Playing {
+    path: PathBuf,
+    sink: rodio::Sink,
+    finished: oneshot::Sender<()>,
+}
+
+impl Playing {
+    fn finish(self) {
+        self.sink.stop();
+        let _ = self.finished.send(());
+    }
+}
+
+fn run(rx: mpsc::Receiver<Command>, mut device: Option<String>) {
+    let mut output: Option<Output> = None;
+    let mut playing: Vec<Playing> = vec![];
+    let mut next_id: u64 = 1;
+
+    loop {
+        let cmd = if playing.is_empty() {
+            match rx.recv() {
+                Ok(cmd) => Some(cmd),
+                Err(_) => break,
+            }
+        } else {
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(cmd) => Some(cmd),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        };
+
+        match cmd {
+            Some(Command::Play { path, volume, resp }) => {
+                let res = start(&mut output, device.as_deref(), &path, volume).map(|sink| {
+                    let id = next_id;
+                    next_id += 1;
+                    debug!(id, path = %path.display(), volume, "playing sound");
+                    let (finished, rx) = oneshot::channel();
+                    playing.push(Playing {
+                        path,
+                        sink,
+                        finished,
+                    });
+                    Playback { id, finished: rx }
+                });
+                let _ = resp.send(res);
+            }
+            Some(Command::Stop { path }) => {
+                let (stopped, rest): (Vec<_>, Vec<_>) =
+                    playing.drain(..).partition(|p| p.path == path);
+                playing = rest;
+                stopped.into_iter().for_each(Playing::finish);
+            }
+            Some(Command::StopAll) => playing.drain(..).for_each(Playing::finish),
+            Some(Command::SetDevice(d)) if d != device => {
+                playing.drain(..).for_each(Playing::finish);
+                output = None;
+                device = d;
+            }
+            Some(Command::SetDevice(_)) | None => {}
+        }
+
+        let (done, rest): (Vec<_>, Vec<_>) = playing.drain(..).partition(|p| p.sink.empty());
+        playing = rest;
+        done.into_iter().for_each(Playing::finish);
+    }
+    debug!("audio thread finished");
+}
+
+fn start(
+    output: &mut Option<Output>,
+    device: Option<&str>,
+    path: &Path,
+    volume: f32,
+) -> anyhow::Result<rodio::Sink> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let source = rodio::Decoder::new(BufReader::new(file))?;
+
+    if output.is_none() {
+        *output = Some(open_output(device)?);
+    }
+    let handle = &output.as_ref().expect("output opened above").handle;
+    let sink = match rodio::Sink::try_new(handle) {
+        Ok(sink) => sink,
+        Err(e) => {
+            // The device may have gone away; reopen it on the next attempt.
+            *output = None;
+            return Err(e.into());
+        }
+    };
+    sink.set_volume(volume);
+    sink.append(source);
+    Ok(sink)
+}
+
+fn open_output(device: Option<&str>) -> anyhow::Result<Output> {
+    let (stream, handle) = match device {
+        None => rodio::OutputStream::try_default()?,
+        Some(name) => {
+            let found = rodio::cpal::default_host()
+                .output_devices()?
+                .find(|d| d.name().is_ok_and(|n| n == name));
+            match found {
+                Some(d) => rodio::OutputStream::try_from_device(&d)?,
+                None => {
+                    warn!(
+                        device = name,
+                        "audio output device not found; using default"
+                    );
+                    rodio::OutputStream::try_default()?
+                }
+            }
+        }
+    };
+    Ok(Output {
+        _stream: stream,
+        handle,
+    })
+}
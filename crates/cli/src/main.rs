@@ -1,4 +1,6 @@
-use app_core::ids::DeviceId;
+use std::path::PathBuf;
+
+use app_core::ids::{DeviceId, ProfileId};
 use device::DeviceService;
 
 #[tokio::main]
@@ -8,7 +10,9 @@ async fn main() -> anyhow::Result<()> {
         .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
         .init();
 
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let (config_dir, portable) = take_data_options(&mut args)?;
+    storage::paths::init(config_dir, portable)?;
     let cmd = args.get(1).map(|s| s.as_str()).unwrap_or("help");
 
     match cmd {
@@ -20,7 +24,9 @@ async fn main() -> anyhow::Result<()> {
         "events" => cmd_events(&args).await,
         "brightness" => cmd_brightness(&args).await,
         "test-image" => cmd_test_image(&args).await,
+        "hid-replay" => cmd_hid_replay(&args),
         "plugins" => cmd_plugins(&args).await,
+        "profile" => cmd_profile(&args),
         other => anyhow::bail!("unknown command: {other} (run `cli help`)"),
     }
 }
@@ -30,13 +36,26 @@ fn print_help() {
         r#"riverdeck-redux cli
 
 USAGE:
+  cli [--config-dir <dir> | --portable] <command>
+
+  --config-dir <dir>  keep profiles, plugins and settings in <dir>
+                      (or set RIVERDECK_CONFIG_DIR)
+  --portable          keep them in a `data` folder next to the executable
+
+COMMANDS:
   cli list
   cli events <device_id>
   cli brightness <device_id> <percent>
   cli test-image <device_id> <key> <r> <g> <b>
+  cli hid-replay <capture_file>
+                      (record one with RIVERDECK_HID_CAPTURE=<file>)
   cli plugins list
-  cli plugins install <url> [--id <expected_id>]
+  cli plugins install <url|file> [--id <expected_id>] [--sha256 <hex>]
+                             [--pubkey <minisign_key>] [--signature <file|url>] [--insecure]
   cli plugins uninstall <plugin_id>
+  cli plugins enable <plugin_id>
+  cli plugins disable <plugin_id>
+  cli profile validate <profile_id|file>
 "#
     );
 }
@@ -83,12 +102,7 @@ async fn cmd_test_image(args: &[String]) -> anyhow::Result<()> {
     let svc = device::HidDeviceService::new()?;
     let dev = svc.connect(id).await?;
 
-    let (w, h) = match dev.key_count {
-        6 => (80, 80),  // common mini size
-        32 => (96, 96), // common XL size
-        8 => (120, 120), // Stream Deck+ keys (best-effort default)
-        _ => (72, 72),  // common original/mk2 size
-    };
+    let (w, h) = dev.capabilities.key_size;
 
     let jpeg = render::test_patterns::solid_color_jpeg(w, h, [r, g, b])?;
     dev.set_key_image_jpeg(key, jpeg).await?;
@@ -96,6 +110,20 @@ async fn cmd_test_image(args: &[String]) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Print what the input reports of a HID capture parse into, without the device.
+fn cmd_hid_replay(args: &[String]) -> anyhow::Result<()> {
+    let path = args
+        .get(2)
+        .ok_or_else(|| anyhow::anyhow!("missing capture file (usage: cli hid-replay <file>)"))?;
+    let capture = transport_hid::capture::read_capture(std::path::Path::new(path))?;
+    let updates = device::input::replay(&capture)?;
+    for (at, update) in &updates {
+        println!("{:>10.3}s  {update:?}", at.as_secs_f64());
+    }
+    println!("{} update(s)", updates.len());
+    Ok(())
+}
+
 async fn cmd_plugins(args: &[String]) -> anyhow::Result<()> {
     let sub = args.get(2).map(|s| s.as_str()).unwrap_or("help");
     match sub {
@@ -105,8 +133,11 @@ async fn cmd_plugins(args: &[String]) -> anyhow::Result<()> {
 
 USAGE:
   cli plugins list
-  cli plugins install <url> [--id <expected_id>]
+  cli plugins install <url|file> [--id <expected_id>] [--sha256 <hex>]
+                             [--pubkey <minisign_key>] [--signature <file|url>] [--insecure]
   cli plugins uninstall <plugin_id>
+  cli plugins enable <plugin_id>
+  cli plugins disable <plugin_id>
 "#
             );
             Ok(())
@@ -114,6 +145,8 @@ USAGE:
         "list" => cmd_plugins_list(),
         "install" => cmd_plugins_install(args).await,
         "uninstall" => cmd_plugins_uninstall(args),
+        "enable" => cmd_plugins_set_enabled(args, true),
+        "disable" => cmd_plugins_set_enabled(args, false),
         other => anyhow::bail!("unknown plugins subcommand: {other} (run `cli plugins help`)"),
     }
 }
@@ -130,31 +163,56 @@ fn cmd_plugins_list() -> anyhow::Result<()> {
         } else {
             format!(" v{}", p.manifest.version)
         };
-        println!("{}  {}{}", p.manifest.id, p.manifest.name, v);
+        let state = if p.enabled { "" } else { "  (disabled)" };
+        println!("{}  {}{}{}", p.manifest.id, p.manifest.name, v, state);
     }
     Ok(())
 }
 
 async fn cmd_plugins_install(args: &[String]) -> anyhow::Result<()> {
-    let url = args
+    let source = args
         .get(3)
-        .ok_or_else(|| anyhow::anyhow!("missing url (usage: cli plugins install <url> [--id <expected_id>])"))?
+        .ok_or_else(|| anyhow::anyhow!("missing url or file (usage: cli plugins install <url|file> [--id <expected_id>])"))?
         .as_str();
 
     let mut expected_id: Option<&str> = None;
+    let mut verification = openaction::verify::Verification::default();
     let mut i = 4;
     while i < args.len() {
-        match args[i].as_str() {
-            "--id" => {
-                let v = args.get(i + 1).ok_or_else(|| anyhow::anyhow!("--id requires a value"))?;
-                expected_id = Some(v.as_str());
-                i += 2;
+        let flag = args[i].as_str();
+        if flag == "--insecure" {
+            verification.allow_mismatch = true;
+            i += 1;
+            continue;
+        }
+        let v = args
+            .get(i + 1)
+            .ok_or_else(|| anyhow::anyhow!("{flag} requires a value"))?;
+        match flag {
+            "--id" => expected_id = Some(v.as_str()),
+            "--sha256" => verification.sha256 = Some(v.clone()),
+            "--pubkey" => verification.public_key = Some(v.clone()),
+            "--signature" => {
+                // Local `.minisig` files are read here; URLs are fetched by the installer.
+                let is_url = v.starts_with("http://") || v.starts_with("https://");
+                verification.signature = Some(if is_url {
+                    v.clone()
+                } else {
+                    std::fs::read_to_string(v)?
+                });
             }
             other => anyhow::bail!("unknown flag for plugins install: {other}"),
         }
+        i += 2;
     }
 
-    let id = openaction::installer::install_from_url(url, expected_id).await?;
+    let is_url = source.starts_with("http://") || source.starts_with("https://");
+    let id = if is_url {
+        openaction::installer::install_from_url(source, expected_id, &verification).await?
+    } else {
+        let path = std::path::Path::new(source);
+        openaction::installer::install_from_file(path, expected_id, &verification).await?
+    };
     println!("installed: {id}");
     Ok(())
 }
@@ -164,11 +222,111 @@ fn cmd_plugins_uninstall(args: &[String]) -> anyhow::Result<()> {
         .get(3)
         .ok_or_else(|| anyhow::anyhow!("missing plugin_id (usage: cli plugins uninstall <plugin_id>)"))?
         .as_str();
-    openaction::registry::uninstall(id)?;
+    let report = openaction::registry::uninstall(id)?;
     println!("uninstalled: {id}");
+    if report.cleared_bindings > 0 {
+        println!(
+            "warning: cleared {} binding(s) across {} profile(s)",
+            report.cleared_bindings, report.profiles_updated
+        );
+    }
+    if report.cleared_triggers > 0 {
+        println!(
+            "warning: cleared {} binding(s) in schedules and other triggers",
+            report.cleared_triggers
+        );
+    }
+    Ok(())
+}
+
+fn cmd_plugins_set_enabled(args: &[String], enabled: bool) -> anyhow::Result<()> {
+    let verb = if enabled { "enable" } else { "disable" };
+    let id = args
+        .get(3)
+        .ok_or_else(|| anyhow::anyhow!("missing plugin_id (usage: cli plugins {verb} <plugin_id>)"))?
+        .as_str();
+    openaction::registry::set_enabled(id, enabled)?;
+    println!("{verb}d: {id}");
+    Ok(())
+}
+
+fn cmd_profile(args: &[String]) -> anyhow::Result<()> {
+    let sub = args.get(2).map(|s| s.as_str()).unwrap_or("help");
+    match sub {
+        "help" | "--help" | "-h" => {
+            eprintln!(
+                r#"cli profile
+
+USAGE:
+  cli profile validate <profile_id|file>
+"#
+            );
+            Ok(())
+        }
+        "validate" => cmd_profile_validate(args),
+        other => anyhow::bail!("unknown profile subcommand: {other} (run `cli profile help`)"),
+    }
+}
+
+/// Print the problems found in a profile; fails when it would not load.
+fn cmd_profile_validate(args: &[String]) -> anyhow::Result<()> {
+    let target = args.get(3).ok_or_else(|| {
+        anyhow::anyhow!("missing profile (usage: cli profile validate <profile_id|file>)")
+    })?;
+    // A bare number names a profile in the profiles folder, anything else a file.
+    let path = match target.parse::<u64>() {
+        Ok(id) => storage::profiles::profile_path(ProfileId(id))?,
+        Err(_) => PathBuf::from(target),
+    };
+
+    let installed: Vec<String> = openaction::registry::list_installed()?
+        .into_iter()
+        .map(|p| p.manifest.id)
+        .collect();
+    let diagnostics = storage::validate::validate_profile_file(&path, &|id| {
+        installed.iter().any(|p| p == id)
+    })?;
+
+    for d in &diagnostics {
+        println!("{}: {d}", path.display());
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == storage::validate::Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    if errors > 0 {
+        anyhow::bail!("{} is invalid: {errors} error(s), {warnings} warning(s)", path.display());
+    }
+    println!("{}: ok ({warnings} warning(s))", path.display());
     Ok(())
 }
 
+/// Remove the data location options from `args`, wherever they appear.
+fn take_data_options(args: &mut Vec<String>) -> anyhow::Result<(Option<PathBuf>, bool)> {
+    let mut config_dir = None;
+    let mut portable = false;
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == "--portable" {
+            portable = true;
+            args.remove(i);
+        } else if args[i] == "--config-dir" {
+            args.remove(i);
+            if i >= args.len() {
+                anyhow::bail!("--config-dir needs a directory");
+            }
+            config_dir = Some(PathBuf::from(args.remove(i)));
+        } else if let Some(dir) = args[i].strip_prefix("--config-dir=") {
+            config_dir = Some(PathBuf::from(dir));
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    Ok((config_dir, portable))
+}
+
 fn parse_device_id(args: &[String], idx: usize) -> anyhow::Result<DeviceId> {
     let raw = args
         .get(idx)
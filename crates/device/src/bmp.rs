@@ -0,0 +1,180 @@
+//! Key images for the original 15-key Stream Deck and the Mini (v1 protocol).
+//!
+//! These decks don't take JPEG. Each key image is a 24-bit BMP, rotated/mirrored the way the
+//! panel is mounted, and sent in pages: output report `0x02` with a 16-byte header (command,
+//! page number, last-page flag, key) followed by a slice of the bitmap, zero-padded to the full
+//! report length. The model is picked from the USB product ID.
+
+use std::sync::{Arc, Mutex};
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbImage};
+use transport_hid::HidDeviceHandle;
+
+/// Length of the header in front of every image page.
+const PAGE_HEADER_LEN: usize = 16;
+
+/// Size of the BMP file and info headers.
+const BMP_HEADER_LEN: usize = 54;
+
+/// A deck that takes BMP key images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BmpModel {
+    /// The original 15-key Stream Deck (72x72 keys, two pages per image).
+    Original,
+    /// Stream Deck Mini and Mini MK.2 (80x80 keys, 1 KiB pages).
+    Mini,
+}
+
+impl BmpModel {
+    pub fn from_product_id(product_id: u16) -> Option<Self> {
+        match product_id {
+            0x0060 => Some(BmpModel::Original),
+            0x0063 | 0x0090 => Some(BmpModel::Mini),
+            _ => None,
+        }
+    }
+
+    /// Width and height of a key image.
+    fn key_size(self) -> u32 {
+        match self {
+            BmpModel::Original => 72,
+            BmpModel::Mini => 80,
+        }
+    }
+
+    /// Length of every image output report, header included.
+    fn report_len(self) -> usize {
+        match self {
+            BmpModel::Original => 8191,
+            BmpModel::Mini => 1024,
+        }
+    }
+
+    /// The key number the device uses for `key` (counted row by row from the top left).
+    fn device_key(self, key: u8) -> u8 {
+        match self {
+            // The original numbers the keys of each row from the right.
+            BmpModel::Original => {
+                let column = key % 5;
+                key - column + (4 - column)
+            }
+            BmpModel::Mini => key,
+        }
+    }
+
+    /// `image` as the bitmap the device expects for a key.
+    pub fn encode_key_image(self, image: &DynamicImage) -> Vec<u8> {
+        let size = self.key_size();
+        let rgb = image
+            .resize_exact(size, size, FilterType::Triangle)
+            .to_rgb8();
+        let native = match self {
+            BmpModel::Original => imageops::rotate180(&rgb),
+            BmpModel::Mini => imageops::flip_vertical(&imageops::rotate270(&rgb)),
+        };
+        encode_bmp(&native)
+    }
+
+    /// The output reports that write `bmp` (from [`Self::encode_key_image`]) to `key`.
+    pub fn key_reports(self, key: u8, bmp: &[u8]) -> Vec<Vec<u8>> {
+        let (pages, first_page): (Vec<&[u8]>, u8) = match self {
+            // The firmware expects the image split after exactly 7749 bytes.
+            BmpModel::Original => {
+                let (first, second) = bmp.split_at(bmp.len().min(7749));
+                (vec![first, second], 1)
+            }
+            BmpModel::Mini => (bmp.chunks(self.report_len() - PAGE_HEADER_LEN).collect(), 0),
+        };
+
+        let key = self.device_key(key);
+        let last = pages.len() - 1;
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let mut report = vec![0u8; self.report_len()];
+                report[..6].copy_from_slice(&[
+                    0x02,
+                    0x01,
+                    first_page + i as u8,
+                    0x00,
+                    u8::from(i == last),
+                    key + 1,
+                ]);
+                report[PAGE_HEADER_LEN..PAGE_HEADER_LEN + page.len()].copy_from_slice(page);
+                report
+            })
+            .collect()
+    }
+}
+
+/// Raw HID handle writing key images with the v1 protocol.
+#[derive(Clone)]
+pub(crate) struct BmpKeys {
+    pub model: BmpModel,
+    device: Arc<Mutex<HidDeviceHandle>>,
+}
+
+impl BmpKeys {
+    pub fn new(model: BmpModel, device: HidDeviceHandle) -> Self {
+        Self {
+            model,
+            device: Arc::new(Mutex::new(device)),
+        }
+    }
+
+    /// Decode `jpeg` and show it on `key`.
+    pub async fn write_key(&self, key: u8, jpeg: &[u8]) -> anyhow::Result<()> {
+        let image = image::load_from_memory(jpeg)?;
+        let reports = self
+            .model
+            .key_reports(key, &self.model.encode_key_image(&image));
+        let device = self.device.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut device = device
+                .lock()
+                .map_err(|_| anyhow::anyhow!("HID device lock poisoned"))?;
+            for report in &reports {
+                device.write(report)?;
+            }
+            Ok(())
+        })
+        .await?
+    }
+}
+
+/// 24-bit bottom-up BMP of `image`, rows padded to 4 bytes.
+fn encode_bmp(image: &RgbImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let row_len = (width as usize * 3 + 3) & !3;
+    let pixels_len = row_len * height as usize;
+    let mut out = Vec::with_capacity(BMP_HEADER_LEN + pixels_len);
+
+    // BITMAPFILEHEADER
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&((BMP_HEADER_LEN + pixels_len) as u32).to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(BMP_HEADER_LEN as u32).to_le_bytes());
+    // BITMAPINFOHEADER: a positive height means the rows are stored bottom-up.
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(width as i32).to_le_bytes());
+    out.extend_from_slice(&(height as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(pixels_len as u32).to_le_bytes());
+    out.extend_from_slice(&2835u32.to_le_bytes());
+    out.extend_from_slice(&2835u32.to_le_bytes());
+    out.extend_from_slice(&[0; 8]);
+
+    for y in (0..height).rev() {
+        let start = out.len();
+        for x in 0..width {
+            let [r, g, b] = image.get_pixel(x, y).0;
+            out.extend_from_slice(&[b, g, r]);
+        }
+        out.resize(start + row_len, 0);
+    }
+    out
+}
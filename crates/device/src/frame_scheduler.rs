@@ -0,0 +1,88 @@
+//! Frame scheduler for key image writes.
+//!
+//! Key images are queued instead of being written (and flushed) one by one. Updates for the same
+//! key that arrive before the next frame replace each other, and every frame writes all pending
+//! keys followed by a single flush. Frames are spaced at least `min_interval` apart, so a burst of
+//! updates (profile switch, animated keys) can't monopolize the HID connection and starve input.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use tracing::trace;
+
+/// Default spacing between frames (~40 fps).
+pub(crate) const FRAME_INTERVAL: Duration = Duration::from_millis(25);
+
+pub(crate) type Responder = oneshot::Sender<anyhow::Result<()>>;
+
+/// The latest image queued for a key, plus everyone waiting for it to reach the device.
+pub(crate) struct PendingKey {
+    pub jpeg: Vec<u8>,
+    pub waiters: Vec<Responder>,
+}
+
+pub(crate) struct FrameScheduler {
+    min_interval: Duration,
+    last_frame: Option<Instant>,
+    pending: BTreeMap<u8, PendingKey>,
+}
+
+impl FrameScheduler {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_frame: None,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Queue `jpeg` for `key`, replacing any image still waiting for the next frame.
+    pub fn queue(&mut self, key: u8, jpeg: Vec<u8>, resp: Responder) {
+        match self.pending.get_mut(&key) {
+            Some(p) => {
+                trace!(key, "coalesced key image update");
+                p.jpeg = jpeg;
+                p.waiters.push(resp);
+            }
+            None => {
+                self.pending.insert(
+                    key,
+                    PendingKey {
+                        jpeg,
+                        waiters: vec![resp],
+                    },
+                );
+            }
+        }
+    }
+
+    /// When the next frame may be written, or `None` if nothing is pending.
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        Some(match self.last_frame {
+            Some(t) => t + self.min_interval,
+            None => Instant::now(),
+        })
+    }
+
+    /// Take every pending key for writing as one frame.
+    pub fn take_frame(&mut self) -> Vec<(u8, PendingKey)> {
+        self.last_frame = Some(Instant::now());
+        std::mem::take(&mut self.pending).into_iter().collect()
+    }
+}
+
+/// Report `res` to every waiter of a key.
+pub(crate) fn respond(waiters: Vec<Responder>, res: &anyhow::Result<()>) {
+    for w in waiters {
+        let r = match res {
+            Ok(()) => Ok(()),
+            Err(e) => Err(anyhow::anyhow!("{e:#}")),
+        };
+        let _ = w.send(r);
+    }
+}
@@ -0,0 +1,177 @@
+//! Touch strip gesture recognition.
+//!
+//! The Stream Deck+ reports touches as presses, long presses and strokes (start and end point).
+//! A finger moving along the strip produces a burst of strokes; reports that follow each other
+//! within [`STROKE_GAP`] belong to the same gesture. Every stroke is forwarded as a drag right
+//! away, tagged with the half of the strip the gesture started in, and once the gesture is over
+//! a long and quick enough one is reported as a swipe as well.
+//!
+//! Long presses are recognized by the device firmware; their duration is not configurable.
+
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+use crate::{ControlEvent, ControlEventKind, ControlId, DeviceEvent};
+
+/// Width of the touch strip in pixels (touch coordinates use the same scale).
+pub const TOUCH_STRIP_WIDTH: u16 = 800;
+
+/// Strokes less than this apart continue the same gesture.
+const STROKE_GAP: Duration = Duration::from_millis(150);
+
+/// When a gesture counts as a swipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureThresholds {
+    /// Minimum horizontal travel, in pixels.
+    pub swipe_distance: u16,
+    /// Longest a swipe may take; slower gestures are only drags.
+    pub swipe_max_duration: Duration,
+}
+
+impl Default for GestureThresholds {
+    fn default() -> Self {
+        Self {
+            swipe_distance: 200,
+            swipe_max_duration: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Half of the touch strip, for bindings that treat the halves as separate sliders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchZone {
+    Left,
+    Right,
+}
+
+impl TouchZone {
+    pub fn of(x: u16) -> Self {
+        if x < TOUCH_STRIP_WIDTH / 2 {
+            TouchZone::Left
+        } else {
+            TouchZone::Right
+        }
+    }
+}
+
+/// Raw touch input as reported by the device.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TouchInput {
+    Press { x: u16 },
+    LongPress { x: u16 },
+    Stroke { start_x: u16, end_x: u16 },
+}
+
+#[derive(Debug)]
+struct Gesture {
+    zone: TouchZone,
+    start_x: u16,
+    end_x: u16,
+    started: Instant,
+    last: Instant,
+}
+
+struct GestureRecognizer {
+    thresholds: GestureThresholds,
+    gesture: Option<Gesture>,
+}
+
+impl GestureRecognizer {
+    fn input(&mut self, input: TouchInput, now: Instant) -> Vec<ControlEventKind> {
+        let mut out = vec![];
+        match input {
+            TouchInput::Press { x } => {
+                out.extend(self.finish());
+                out.push(ControlEventKind::Tap { x });
+            }
+            TouchInput::LongPress { x } => {
+                out.extend(self.finish());
+                out.push(ControlEventKind::LongPress { x });
+            }
+            TouchInput::Stroke { start_x, end_x } => {
+                let continues = self
+                    .gesture
+                    .as_ref()
+                    .is_some_and(|g| now.duration_since(g.last) <= STROKE_GAP);
+                if !continues {
+                    out.extend(self.finish());
+                }
+                let gesture = self.gesture.get_or_insert(Gesture {
+                    zone: TouchZone::of(start_x),
+                    start_x,
+                    end_x,
+                    started: now,
+                    last: now,
+                });
+                gesture.end_x = end_x;
+                gesture.last = now;
+                out.push(ControlEventKind::Drag {
+                    delta_x: end_x as i16 - start_x as i16,
+                    zone: gesture.zone,
+                });
+            }
+        }
+        out
+    }
+
+    /// When the current gesture is over unless another stroke arrives.
+    fn deadline(&self) -> Option<Instant> {
+        self.gesture.as_ref().map(|g| g.last + STROKE_GAP)
+    }
+
+    /// End the current gesture; a swipe if it travelled far enough, quickly enough.
+    fn finish(&mut self) -> Option<ControlEventKind> {
+        let g = self.gesture.take()?;
+        let travel = g.end_x as i32 - g.start_x as i32;
+        let quick = g.last.duration_since(g.started) <= self.thresholds.swipe_max_duration;
+        if !quick || travel.unsigned_abs() < u32::from(self.thresholds.swipe_distance) {
+            return None;
+        }
+        Some(if travel < 0 {
+            ControlEventKind::SwipeLeft
+        } else {
+            ControlEventKind::SwipeRight
+        })
+    }
+}
+
+/// Turn touch input into touch strip events until the device goes away.
+pub(crate) fn spawn(
+    mut inputs: mpsc::UnboundedReceiver<TouchInput>,
+    thresholds: watch::Receiver<GestureThresholds>,
+    events: mpsc::Sender<DeviceEvent>,
+) {
+    tokio::spawn(async move {
+        let mut recognizer = GestureRecognizer {
+            thresholds: *thresholds.borrow(),
+            gesture: None,
+        };
+        loop {
+            recognizer.thresholds = *thresholds.borrow();
+            let kinds = match recognizer.deadline() {
+                Some(at) => tokio::select! {
+                    input = inputs.recv() => match input {
+                        Some(input) => recognizer.input(input, Instant::now()),
+                        None => break,
+                    },
+                    _ = tokio::time::sleep_until(at) => recognizer.finish().into_iter().collect(),
+                },
+                None => match inputs.recv().await {
+                    Some(input) => recognizer.input(input, Instant::now()),
+                    None => break,
+                },
+            };
+            for kind in kinds {
+                let ev = ControlEvent {
+                    control: ControlId::TouchStrip,
+                    kind,
+                };
+                if events.send(DeviceEvent::Control(ev)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
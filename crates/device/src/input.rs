@@ -0,0 +1,227 @@
+//! The decks' input reports, parsed into key, dial and touch strip updates.
+//!
+//! Every input report starts with report id `0x01`. After that, by model:
+//!
+//! - v1 (the original 15-key Stream Deck and the Mini): one byte per key. The original numbers
+//!   the keys of each row from the right.
+//! - v2 (MK.2, XL, Neo, Pedal, original V2): a zero byte and a 16-bit length, then one byte per
+//!   key.
+//! - Stream Deck+: byte 1 says what follows. `0x00` is a v2 key report, `0x02` a touch strip
+//!   event (kind at byte 4, then little-endian x/y at 6 and 8, and the end of a swipe at 10 and
+//!   12), `0x03` a dial report (byte 4 is `0x00` for the press state of every dial, `0x01` for
+//!   how far each one turned, as signed bytes from byte 5 on).
+//!
+//! Key and dial press reports carry the state of every control, so [`InputParser`] remembers
+//! the last one and reports what changed.
+
+use std::time::Duration;
+
+use elgato_streamdeck::info::Kind;
+use transport_hid::capture::Capture;
+
+/// Report id of every input report.
+const INPUT_REPORT_ID: u8 = 0x01;
+
+/// Where v2 key states start.
+const V2_KEYS_OFFSET: usize = 4;
+
+/// Dials of the Stream Deck+.
+const PLUS_DIALS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputUpdate {
+    KeyDown(u8),
+    KeyUp(u8),
+    DialDown(u8),
+    DialUp(u8),
+    /// A dial turned by this many steps, negative to the left.
+    DialTurn(u8, i8),
+    TouchPress {
+        x: u16,
+        y: u16,
+    },
+    TouchLongPress {
+        x: u16,
+        y: u16,
+    },
+    TouchSwipe {
+        start: (u16, u16),
+        end: (u16, u16),
+    },
+}
+
+/// How a model lays out its input reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputProtocol {
+    /// One byte per key right after the report id; `mirrored` decks number each row's keys
+    /// from the right, in rows of 5.
+    V1 {
+        mirrored: bool,
+    },
+    V2,
+    Plus,
+}
+
+impl InputProtocol {
+    pub fn from_product_id(product_id: u16) -> Self {
+        match product_id {
+            0x0060 => InputProtocol::V1 { mirrored: true },
+            0x0063 | 0x0090 => InputProtocol::V1 { mirrored: false },
+            0x0084 => InputProtocol::Plus,
+            _ => InputProtocol::V2,
+        }
+    }
+}
+
+/// Turns one deck's input reports into updates.
+#[derive(Debug, Clone)]
+pub struct InputParser {
+    protocol: InputProtocol,
+    keys: Vec<bool>,
+    dials: Vec<bool>,
+}
+
+impl InputParser {
+    /// A parser for the deck with `product_id` and `key_count` keys; further key states in
+    /// its reports (e.g. the Neo's touch points) are ignored.
+    pub fn new(product_id: u16, key_count: u8) -> Self {
+        let protocol = InputProtocol::from_product_id(product_id);
+        let dials = if protocol == InputProtocol::Plus {
+            PLUS_DIALS
+        } else {
+            0
+        };
+        Self {
+            protocol,
+            keys: vec![false; usize::from(key_count)],
+            dials: vec![false; dials],
+        }
+    }
+
+    /// What changed according to `report`. Reports that aren't input or are cut short give
+    /// nothing.
+    pub fn parse(&mut self, report: &[u8]) -> Vec<InputUpdate> {
+        if report.first() != Some(&INPUT_REPORT_ID) {
+            return Vec::new();
+        }
+        let key_count = self.keys.len();
+        match self.protocol {
+            InputProtocol::V1 { mirrored } => {
+                let states = parse_key_states_v1(report, key_count, mirrored);
+                emit_changes(
+                    &mut self.keys,
+                    &states,
+                    InputUpdate::KeyDown,
+                    InputUpdate::KeyUp,
+                )
+            }
+            InputProtocol::V2 => {
+                let states = parse_key_states_v2(report, key_count);
+                emit_changes(
+                    &mut self.keys,
+                    &states,
+                    InputUpdate::KeyDown,
+                    InputUpdate::KeyUp,
+                )
+            }
+            InputProtocol::Plus => emit_plus_events(report, &mut self.keys, &mut self.dials),
+        }
+    }
+}
+
+/// Key states of a v1 report, by key index (rows from the top left).
+pub fn parse_key_states_v1(report: &[u8], key_count: usize, mirrored: bool) -> Vec<bool> {
+    (0..key_count)
+        .map(|key| {
+            let at = if mirrored {
+                let column = key % 5;
+                key - column + (4 - column)
+            } else {
+                key
+            };
+            report.get(1 + at).is_some_and(|&b| b != 0)
+        })
+        .collect()
+}
+
+/// Key states of a v2 report, by key index.
+pub fn parse_key_states_v2(report: &[u8], key_count: usize) -> Vec<bool> {
+    (0..key_count)
+        .map(|key| report.get(V2_KEYS_OFFSET + key).is_some_and(|&b| b != 0))
+        .collect()
+}
+
+/// Updates from a Stream Deck+ report, given the last key and dial press states (which are
+/// updated).
+pub fn emit_plus_events(report: &[u8], keys: &mut [bool], dials: &mut [bool]) -> Vec<InputUpdate> {
+    match report.get(1) {
+        Some(0x00) => {
+            let states = parse_key_states_v2(report, keys.len());
+            emit_changes(keys, &states, InputUpdate::KeyDown, InputUpdate::KeyUp)
+        }
+        Some(0x02) => touch_event(report).into_iter().collect(),
+        Some(0x03) => match report.get(4) {
+            Some(0x00) => {
+                let states: Vec<bool> = (0..dials.len())
+                    .map(|dial| report.get(5 + dial).is_some_and(|&b| b != 0))
+                    .collect();
+                emit_changes(dials, &states, InputUpdate::DialDown, InputUpdate::DialUp)
+            }
+            Some(0x01) => (0..dials.len())
+                .filter_map(|dial| {
+                    let steps = *report.get(5 + dial)? as i8;
+                    (steps != 0).then_some(InputUpdate::DialTurn(dial as u8, steps))
+                })
+                .collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn touch_event(report: &[u8]) -> Option<InputUpdate> {
+    let le16 = |at: usize| Some(u16::from_le_bytes([*report.get(at)?, *report.get(at + 1)?]));
+    let (x, y) = (le16(6)?, le16(8)?);
+    match report.get(4)? {
+        0x01 => Some(InputUpdate::TouchPress { x, y }),
+        0x02 => Some(InputUpdate::TouchLongPress { x, y }),
+        0x03 => Some(InputUpdate::TouchSwipe {
+            start: (x, y),
+            end: (le16(10)?, le16(12)?),
+        }),
+        _ => None,
+    }
+}
+
+/// Store `states` in `last` and report each control that went down or up.
+fn emit_changes(
+    last: &mut [bool],
+    states: &[bool],
+    down: fn(u8) -> InputUpdate,
+    up: fn(u8) -> InputUpdate,
+) -> Vec<InputUpdate> {
+    let mut updates = Vec::new();
+    for (i, (last, &now)) in last.iter_mut().zip(states).enumerate() {
+        if *last != now {
+            *last = now;
+            updates.push(if now { down(i as u8) } else { up(i as u8) });
+        }
+    }
+    updates
+}
+
+/// Run the input reports of a capture (see [`transport_hid::capture`]) through the parser, as
+/// the input thread would have. Reads the first handle opened on a known deck.
+pub fn replay(capture: &Capture) -> anyhow::Result<Vec<(Duration, InputUpdate)>> {
+    let Some((handle, kind)) = capture.devices.iter().find_map(|d| {
+        let (vendor, product) = d.ids?;
+        Some((d.handle, Kind::from_vid_pid(vendor, product)?))
+    }) else {
+        anyhow::bail!("the capture has no handle opened on a known Stream Deck");
+    };
+    let mut parser = InputParser::new(kind.product_id(), kind.key_count());
+    Ok(capture
+        .reads(handle)
+        .flat_map(|r| parser.parse(&r.data).into_iter().map(move |u| (r.at, u)))
+        .collect())
+}
@@ -1,10 +1,17 @@
 
+mod bmp;
+mod frame_scheduler;
+pub mod gestures;
+pub mod input;
 mod stream_deck;
 
+use app_core::capabilities::DeviceCapabilities;
 use app_core::ids::DeviceId;
 use async_trait::async_trait;
 use elgato_streamdeck::info::Kind;
 
+pub use gestures::{GestureThresholds, TouchZone};
+
 #[derive(Debug, Clone)]
 pub struct DiscoveredDevice {
     pub id: DeviceId,
@@ -24,7 +31,11 @@ pub enum ControlEventKind {
     Up,
     Rotate { delta: i32 },
     Tap { x: u16 },
-    Drag { delta_x: i16 },
+    LongPress { x: u16 },
+    /// Movement along the touch strip; `zone` is the half the gesture started in.
+    Drag { delta_x: i16, zone: TouchZone },
+    SwipeLeft,
+    SwipeRight,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -71,6 +82,8 @@ pub struct ConnectedDevice {
     pub id: DeviceId,
     pub name: String,
     pub key_count: u8,
+    /// Key and strip sizes, dials and image format of this deck.
+    pub capabilities: DeviceCapabilities,
     pub events: tokio::sync::mpsc::Receiver<DeviceEvent>,
     handle: stream_deck::StreamDeckHandle,
 }
@@ -97,6 +110,11 @@ impl ConnectedDevice {
     pub async fn set_touch_strip_image_jpeg(&self, jpeg_bytes: Vec<u8>) -> anyhow::Result<()> {
         self.handle.set_touch_strip_image_jpeg(jpeg_bytes).await
     }
+
+    /// Change when touch strip gestures count as swipes; applies from the next gesture.
+    pub fn set_gesture_thresholds(&self, thresholds: GestureThresholds) {
+        self.handle.set_gesture_thresholds(thresholds);
+    }
 }
 
 #[derive(Clone)]
@@ -113,6 +131,21 @@ impl DeviceController {
         self.handle.set_key_image_jpeg(key, jpeg_bytes).await
     }
 
+    /// Blank `key`, e.g. one the loaded profile doesn't cover.
+    pub async fn clear_key(&self, key: u8) -> anyhow::Result<()> {
+        self.handle.clear_key(key).await
+    }
+
+    /// Blank every key, and the touch strip on Stream Deck+, so no stale images stay behind.
+    pub async fn clear_all(&self) -> anyhow::Result<()> {
+        self.handle.clear_all().await
+    }
+
+    /// Fill every key, and the touch strip on Stream Deck+, with one color.
+    pub async fn fill_color(&self, rgb: [u8; 3]) -> anyhow::Result<()> {
+        self.handle.fill_color(rgb).await
+    }
+
     pub async fn set_dial_image_jpeg(&self, dial: u8, jpeg_bytes: Vec<u8>) -> anyhow::Result<()> {
         self.handle.set_dial_image_jpeg(dial, jpeg_bytes).await
     }
@@ -120,4 +153,9 @@ impl DeviceController {
     pub async fn set_touch_strip_image_jpeg(&self, jpeg_bytes: Vec<u8>) -> anyhow::Result<()> {
         self.handle.set_touch_strip_image_jpeg(jpeg_bytes).await
     }
+
+    /// Change when touch strip gestures count as swipes; applies from the next gesture.
+    pub fn set_gesture_thresholds(&self, thresholds: GestureThresholds) {
+        self.handle.set_gesture_thresholds(thresholds);
+    }
 }
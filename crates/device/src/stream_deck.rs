@@ -2,19 +2,54 @@
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use app_core::capabilities::{DeviceCapabilities, ImageFormat};
 use app_core::ids::DeviceId;
+use app_core::metrics::metrics;
+use anyhow::Context;
 use elgato_streamdeck::{
-    DeviceStateUpdate, list_devices, new_hidapi, AsyncStreamDeck, StreamDeckError,
+    list_devices, new_hidapi, AsyncStreamDeck, StreamDeckError,
     images::{convert_image_with_format, ImageRect},
     info::Kind,
 };
 use image::DynamicImage;
-use tokio::sync::{mpsc, oneshot};
-use tracing::{debug, warn};
+use tokio::sync::{mpsc, oneshot, watch};
+use tracing::{debug, info, warn};
+use transport_hid::{HidContext, HidDeviceHandle};
 
+use crate::bmp::{BmpKeys, BmpModel};
+use crate::frame_scheduler::{self, FrameScheduler, PendingKey, FRAME_INTERVAL};
+use crate::gestures::{self, GestureThresholds, TouchInput};
+use crate::input::{InputParser, InputUpdate};
 use crate::{ConnectedDevice, ControlEvent, ControlEventKind, ControlId, DeviceEvent, DiscoveredDevice};
 
+/// Tries at bringing a deck into a known state before giving up on it.
+const INIT_ATTEMPTS: u32 = 3;
+
+/// Wait before the second try; doubled for every further one.
+const INIT_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Brightness right after the reset, until the app applies the one remembered for the deck.
+const IDLE_BRIGHTNESS: u8 = 50;
+
+/// Size of the Stream Deck+ touch strip; each dial owns a quarter of it.
+const PLUS_STRIP_SIZE: (u32, u32) = (800, 100);
+
+/// What `kind` can show. `bmp` says whether key images go out as BMP (see [`crate::bmp`]).
+fn capabilities(kind: Kind, bmp: bool) -> DeviceCapabilities {
+    let (width, height) = kind.key_image_format().size;
+    let is_plus = kind == Kind::Plus;
+    DeviceCapabilities {
+        key_count: kind.key_count(),
+        key_grid: (kind.column_count(), kind.row_count()),
+        key_size: (width as u32, height as u32),
+        lcd_strip_size: is_plus.then_some(PLUS_STRIP_SIZE),
+        dials: if is_plus { kind.encoder_count() } else { 0 },
+        image_format: if bmp { ImageFormat::Bmp } else { ImageFormat::Jpeg },
+    }
+}
+
 fn stable_device_id(kind: Kind, serial: &str) -> DeviceId {
     let mut h = DefaultHasher::new();
     format!("{:?}:{}", kind, serial).hash(&mut h);
@@ -27,9 +62,11 @@ pub struct StreamDeckService {
 
 impl StreamDeckService {
     pub fn new() -> anyhow::Result<Self> {
-        Ok(Self {
-            hid: Arc::new(new_hidapi()?),
-        })
+        let hid = new_hidapi()?;
+        // Shared, so our own handles on the deck (see `open_raw`) can open next to this one.
+        #[cfg(target_os = "macos")]
+        hid.set_open_exclusive(false);
+        Ok(Self { hid: Arc::new(hid) })
     }
 
     pub async fn list_devices(&self) -> anyhow::Result<Vec<DiscoveredDevice>> {
@@ -58,97 +95,78 @@ impl StreamDeckService {
             anyhow::bail!("device not found");
         };
 
-        let device = AsyncStreamDeck::connect(&self.hid, kind, &serial)?;
-        let product_name = device.product().await?;
-        let key_count = (kind.row_count() * kind.column_count()) as u8;
-        let is_plus = kind == Kind::Plus;
-
-        // Spawn device event handler
-        let event_tx_clone = event_tx.clone();
-        let reader = device.get_reader();
-        tokio::spawn(async move {
-            loop {
-                match reader.read(100.0).await {
-                    Ok(updates) => {
-                        for update in updates {
-                            match update {
-                                DeviceStateUpdate::ButtonDown(key) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::Key(key),
-                                        kind: ControlEventKind::Down,
-                                    }));
-                                }
-                                DeviceStateUpdate::ButtonUp(key) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::Key(key),
-                                        kind: ControlEventKind::Up,
-                                    }));
-                                }
-                                DeviceStateUpdate::EncoderTwist(dial, ticks) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::Dial(dial),
-                                        kind: ControlEventKind::Rotate { delta: ticks as i32 },
-                                    }));
-                                }
-                                DeviceStateUpdate::EncoderDown(dial) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::Dial(dial),
-                                        kind: ControlEventKind::Down,
-                                    }));
-                                }
-                                DeviceStateUpdate::EncoderUp(dial) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::Dial(dial),
-                                        kind: ControlEventKind::Up,
-                                    }));
-                                }
-                                DeviceStateUpdate::TouchPointDown(_) | DeviceStateUpdate::TouchPointUp(_) => {}
-                                DeviceStateUpdate::TouchScreenPress(x, y) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::TouchStrip,
-                                        kind: ControlEventKind::Tap { x },
-                                    }));
-                                }
-                                DeviceStateUpdate::TouchScreenLongPress(x, y) => {
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::TouchStrip,
-                                        kind: ControlEventKind::Tap { x },
-                                    }));
-                                }
-                                DeviceStateUpdate::TouchScreenSwipe(start, end) => {
-                                    let dx = (end.0 as i16) - (start.0 as i16);
-                                    let _ = event_tx_clone.send(DeviceEvent::Control(ControlEvent {
-                                        control: ControlId::TouchStrip,
-                                        kind: ControlEventKind::Drag { delta_x: dx },
-                                    }));
-                                }
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        let _ = event_tx_clone.send(DeviceEvent::Disconnected);
-                        break;
-                    }
+        let device = self.open(kind, &serial).await?;
+        // The original and the Mini take BMP key images, written over a handle of our own.
+        let bmp_keys = BmpModel::from_product_id(kind.product_id()).and_then(|model| {
+            match open_raw(kind, &serial) {
+                Ok(hid) => Some(BmpKeys::new(model, hid)),
+                Err(e) => {
+                    warn!(?model, error = %e, "failed to open the deck for BMP key images");
+                    None
                 }
             }
         });
+        let product_name = device.product().await?;
+        let capabilities = capabilities(kind, bmp_keys.is_some());
+        let key_count = capabilities.key_count;
+
+        // Touch strip input goes through gesture recognition first.
+        let (touch_tx, touch_rx) = mpsc::unbounded_channel();
+        let (gestures_tx, gestures_rx) = watch::channel(GestureThresholds::default());
+        gestures::spawn(touch_rx, gestures_rx, event_tx.clone());
+
+        // Input is read on a thread of its own, through a separate handle, so blocking reads
+        // never wait for image writes or hold them up.
+        let input = open_raw(kind, &serial).context("failed to open the deck for input")?;
+        let parser = InputParser::new(kind.product_id(), key_count);
+        spawn_input_thread(input, parser, event_tx.clone(), touch_tx)?;
 
         // Spawn command handler
         let device_clone = device.clone();
-        let is_plus_clone = is_plus;
         tokio::spawn(async move {
-            while let Some(cmd) = cmd_rx.recv().await {
+            let mut frames = FrameScheduler::new(FRAME_INTERVAL);
+            loop {
+                // Key images are only written when a frame is due; meanwhile keep accepting
+                // commands so updates for the same key coalesce.
+                let cmd = match frames.next_frame_at() {
+                    Some(at) => tokio::select! {
+                        cmd = cmd_rx.recv() => cmd,
+                        _ = tokio::time::sleep_until(at) => {
+                            let frame = frames.take_frame();
+                            Self::write_key_frame(&device_clone, bmp_keys.as_ref(), frame).await;
+                            continue;
+                        }
+                    },
+                    None => cmd_rx.recv().await,
+                };
+                let Some(cmd) = cmd else {
+                    if frames.next_frame_at().is_some() {
+                        let frame = frames.take_frame();
+                        Self::write_key_frame(&device_clone, bmp_keys.as_ref(), frame).await;
+                    }
+                    break;
+                };
                 match cmd {
                     DeviceCommand::SetBrightness { percent, resp } => {
+                        let started = Instant::now();
                         let r = device_clone.set_brightness(percent.clamp(0, 100)).await;
+                        metrics().hid_write_seconds.observe(started.elapsed());
                         let _ = resp.send(r.map_err(|e| anyhow::anyhow!(e)));
                     }
                     DeviceCommand::SetKeyImageJpeg { key, jpeg, resp } => {
-                        let r = Self::set_key_image(&device_clone, key, jpeg).await;
-                        let _ = resp.send(r);
+                        frames.queue(key, jpeg, resp);
+                    }
+                    DeviceCommand::Fill { key, rgb, resp } => {
+                        let keys = match key {
+                            Some(key) => vec![key],
+                            None => (0..key_count).collect(),
+                        };
+                        let strip = key.is_none();
+                        let caps = &capabilities;
+                        Self::fill(&device_clone, &mut frames, caps, keys, strip, rgb, resp).await;
                     }
                     DeviceCommand::SetDialImageJpeg { dial, jpeg, resp } => {
-                        let r = if is_plus_clone {
+                        let r = if capabilities.supports_dials() {
                             Self::set_dial_image(&device_clone, dial, jpeg).await
                         } else {
                             Err(anyhow::anyhow!("dial images are only supported on Stream Deck+"))
@@ -156,7 +174,7 @@ impl StreamDeckService {
                         let _ = resp.send(r);
                     }
                     DeviceCommand::SetTouchStripImageJpeg { jpeg, resp } => {
-                        let r = if is_plus_clone {
+                        let r = if capabilities.lcd_strip_size.is_some() {
                             Self::set_touch_strip_image(&device_clone, jpeg).await
                         } else {
                             Err(anyhow::anyhow!("touch strip images are only supported on Stream Deck+"))
@@ -171,15 +189,131 @@ impl StreamDeckService {
             id,
             name: product_name,
             key_count,
+            capabilities,
             events: event_rx,
-            handle: StreamDeckHandle { cmd_tx },
+            handle: StreamDeckHandle {
+                cmd_tx,
+                gestures: Arc::new(gestures_tx),
+            },
         })
     }
 
-    async fn set_key_image(device: &AsyncStreamDeck, key: u8, jpeg: Vec<u8>) -> anyhow::Result<()> {
-        let dyn_img = image::load_from_memory(&jpeg)?;
+    /// Open the deck and bring it into a known state, retrying with a growing delay.
+    ///
+    /// Decks that another program left mid-transfer or asleep often miss the first try.
+    async fn open(&self, kind: Kind, serial: &str) -> anyhow::Result<AsyncStreamDeck> {
+        let mut delay = INIT_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            match self.try_open(kind, serial).await {
+                Ok(device) => return Ok(device),
+                Err(e) if attempt < INIT_ATTEMPTS => {
+                    warn!(?kind, attempt, error = %e, "device initialization failed; retrying");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "device did not initialize after {INIT_ATTEMPTS} attempts"
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Reset (wakes the deck and clears images left by other software), read the firmware
+    /// version to make sure it answers, then turn the screens on at [`IDLE_BRIGHTNESS`].
+    async fn try_open(&self, kind: Kind, serial: &str) -> anyhow::Result<AsyncStreamDeck> {
+        let device = AsyncStreamDeck::connect(&self.hid, kind, serial)?;
+        device.reset().await?;
+        let firmware = device.firmware_version().await?;
+        device.set_brightness(IDLE_BRIGHTNESS).await?;
+        info!(?kind, %firmware, "device initialized");
+        Ok(device)
+    }
+
+    /// Queue `keys` filled with `rgb` like any other key image (and fill the touch strip right
+    /// away when `strip` is set); `resp` gets the outcome once every key was written.
+    async fn fill(
+        device: &AsyncStreamDeck,
+        frames: &mut FrameScheduler,
+        capabilities: &DeviceCapabilities,
+        keys: Vec<u8>,
+        strip: bool,
+        rgb: [u8; 3],
+        resp: oneshot::Sender<anyhow::Result<()>>,
+    ) {
+        let (w, h) = capabilities.key_size;
+        let jpeg = match render::test_patterns::solid_color_jpeg(w, h, rgb) {
+            Ok(jpeg) => jpeg,
+            Err(e) => {
+                let _ = resp.send(Err(e));
+                return;
+            }
+        };
+        if let Some((strip_w, strip_h)) = capabilities.lcd_strip_size.filter(|_| strip) {
+            let r = match render::test_patterns::solid_color_jpeg(strip_w, strip_h, rgb) {
+                Ok(jpeg) => Self::set_touch_strip_image(device, jpeg).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = r {
+                let _ = resp.send(Err(e));
+                return;
+            }
+        }
+
+        let written: Vec<_> = keys
+            .into_iter()
+            .map(|key| {
+                let (tx, rx) = oneshot::channel();
+                frames.queue(key, jpeg.clone(), tx);
+                rx
+            })
+            .collect();
+        tokio::spawn(async move {
+            let mut r = Ok(());
+            for rx in written {
+                match rx.await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => r = Err(e),
+                    Err(_) => r = Err(anyhow::anyhow!("device thread stopped")),
+                }
+            }
+            let _ = resp.send(r);
+        });
+    }
+
+    /// Write every key of a frame, then flush once.
+    async fn write_key_frame(
+        device: &AsyncStreamDeck,
+        bmp_keys: Option<&BmpKeys>,
+        frame: Vec<(u8, PendingKey)>,
+    ) {
+        debug!(keys = frame.len(), "writing key frame");
+        let started = Instant::now();
+        let mut written = Vec::with_capacity(frame.len());
+        for (key, pending) in frame {
+            let r = match bmp_keys {
+                Some(bmp_keys) => bmp_keys.write_key(key, &pending.jpeg).await,
+                None => Self::set_key_image(device, key, &pending.jpeg).await,
+            };
+            written.push((pending.waiters, r));
+        }
+        let flushed = device.flush().await.map_err(|e| anyhow::anyhow!(e));
+        metrics().hid_write_seconds.observe(started.elapsed());
+        for (waiters, r) in written {
+            let r = r.and_then(|()| match &flushed {
+                Ok(()) => Ok(()),
+                Err(e) => Err(anyhow::anyhow!("{e:#}")),
+            });
+            frame_scheduler::respond(waiters, &r);
+        }
+    }
+
+    async fn set_key_image(device: &AsyncStreamDeck, key: u8, jpeg: &[u8]) -> anyhow::Result<()> {
+        let dyn_img = image::load_from_memory(jpeg)?;
         device.set_button_image(key, dyn_img).await?;
-        device.flush().await?;
         Ok(())
     }
 
@@ -187,21 +321,117 @@ impl StreamDeckService {
         let dyn_img = image::load_from_memory(&jpeg)?;
         let overlay = render::plus_strip::make_segment_overlay(Some(dyn_img), None);
         let rect = ImageRect::from_image(overlay)?;
-        device.write_lcd(dial as u16 * 200, 0, &rect).await?;
+        let started = Instant::now();
+        let segment_width = PLUS_STRIP_SIZE.0 / 4;
+        device.write_lcd((u32::from(dial) * segment_width) as u16, 0, &rect).await?;
         device.flush().await?;
+        metrics().hid_write_seconds.observe(started.elapsed());
         Ok(())
     }
 
     async fn set_touch_strip_image(device: &AsyncStreamDeck, jpeg: Vec<u8>) -> anyhow::Result<()> {
         let dyn_img = image::load_from_memory(&jpeg)?;
-        let resized = dyn_img.resize_exact(800, 100, image::imageops::FilterType::Nearest);
+        let (width, height) = PLUS_STRIP_SIZE;
+        let resized = dyn_img.resize_exact(width, height, image::imageops::FilterType::Nearest);
         let rect = ImageRect::from_image(resized)?;
+        let started = Instant::now();
         device.write_lcd_fill(&rect.data).await?;
         device.flush().await?;
+        metrics().hid_write_seconds.observe(started.elapsed());
         Ok(())
     }
 }
 
+/// Longest a blocking read waits before checking whether the device is still wanted.
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Longest input report read; no deck sends more.
+const MAX_INPUT_REPORT_LEN: usize = 1024;
+
+/// Open the deck's HID interface through `transport-hid` (so `RIVERDECK_HID_CAPTURE` records
+/// its traffic), for raw reads and writes next to the `elgato-streamdeck` handle.
+fn open_raw(kind: Kind, serial: &str) -> anyhow::Result<HidDeviceHandle> {
+    let hid = HidContext::new()?;
+    let device = hid
+        .list_all()
+        .into_iter()
+        .find(|d| {
+            d.vendor_id == kind.vendor_id()
+                && d.product_id == kind.product_id()
+                && d.serial_number.as_deref() == Some(serial)
+        })
+        .context("device not found")?;
+    hid.open_device(&device)
+}
+
+/// Forward the deck's input until it disconnects or the device is dropped.
+///
+/// Reads block until the deck reports something, so presses arrive without polling delay and an
+/// idle deck costs no CPU; the timeout only bounds how long a dropped device keeps the thread.
+fn spawn_input_thread(
+    mut input: HidDeviceHandle,
+    mut parser: InputParser,
+    event_tx: mpsc::Sender<DeviceEvent>,
+    touch_tx: mpsc::UnboundedSender<TouchInput>,
+) -> anyhow::Result<()> {
+    std::thread::Builder::new()
+        .name("stream-deck-input".to_string())
+        .spawn(move || {
+            let mut report = [0u8; MAX_INPUT_REPORT_LEN];
+            while !event_tx.is_closed() {
+                match input.read_timeout(&mut report, READ_TIMEOUT.as_millis() as i32) {
+                    Ok(len) => {
+                        for update in parser.parse(&report[..len]) {
+                            forward_update(update, &event_tx, &touch_tx);
+                        }
+                    }
+                    Err(e) => {
+                        debug!(error = %e, "device input stopped");
+                        let _ = event_tx.blocking_send(DeviceEvent::Disconnected);
+                        break;
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn forward_update(
+    update: InputUpdate,
+    event_tx: &mpsc::Sender<DeviceEvent>,
+    touch_tx: &mpsc::UnboundedSender<TouchInput>,
+) {
+    let control = |control, kind| DeviceEvent::Control(ControlEvent { control, kind });
+    let event = match update {
+        InputUpdate::KeyDown(key) => control(ControlId::Key(key), ControlEventKind::Down),
+        InputUpdate::KeyUp(key) => control(ControlId::Key(key), ControlEventKind::Up),
+        InputUpdate::DialTurn(dial, steps) => control(
+            ControlId::Dial(dial),
+            ControlEventKind::Rotate { delta: steps as i32 },
+        ),
+        InputUpdate::DialDown(dial) => control(ControlId::Dial(dial), ControlEventKind::Down),
+        InputUpdate::DialUp(dial) => control(ControlId::Dial(dial), ControlEventKind::Up),
+        // Touch strip input is turned into events by gesture recognition.
+        InputUpdate::TouchPress { x, .. } => {
+            let _ = touch_tx.send(TouchInput::Press { x });
+            return;
+        }
+        InputUpdate::TouchLongPress { x, .. } => {
+            let _ = touch_tx.send(TouchInput::LongPress { x });
+            return;
+        }
+        InputUpdate::TouchSwipe { start, end } => {
+            let _ = touch_tx.send(TouchInput::Stroke {
+                start_x: start.0,
+                end_x: end.0,
+            });
+            return;
+        }
+    };
+    // Fails only once the device was dropped; the loop notices that on its next turn.
+    let _ = event_tx.blocking_send(event);
+}
+
 enum DeviceCommand {
     SetBrightness {
         percent: u8,
@@ -212,6 +442,12 @@ enum DeviceCommand {
         jpeg: Vec<u8>,
         resp: oneshot::Sender<anyhow::Result<()>>,
     },
+    /// Fill one key (`None`: every key, and the touch strip on Stream Deck+) with a color.
+    Fill {
+        key: Option<u8>,
+        rgb: [u8; 3],
+        resp: oneshot::Sender<anyhow::Result<()>>,
+    },
     SetDialImageJpeg {
         dial: u8,
         jpeg: Vec<u8>,
@@ -226,9 +462,15 @@ enum DeviceCommand {
 #[derive(Clone)]
 pub struct StreamDeckHandle {
     cmd_tx: mpsc::Sender<DeviceCommand>,
+    gestures: Arc<watch::Sender<GestureThresholds>>,
 }
 
 impl StreamDeckHandle {
+    pub fn set_gesture_thresholds(&self, thresholds: GestureThresholds) {
+        // Fails only once the device (and its gesture task) is gone.
+        let _ = self.gestures.send(thresholds);
+    }
+
     pub async fn set_brightness(&self, percent: u8) -> anyhow::Result<()> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
@@ -253,6 +495,31 @@ impl StreamDeckHandle {
             .map_err(|_| anyhow::anyhow!("device thread stopped"))?
     }
 
+    /// Blank `key`.
+    pub async fn clear_key(&self, key: u8) -> anyhow::Result<()> {
+        self.fill(Some(key), [0, 0, 0]).await
+    }
+
+    /// Blank every key, and the touch strip on Stream Deck+.
+    pub async fn clear_all(&self) -> anyhow::Result<()> {
+        self.fill(None, [0, 0, 0]).await
+    }
+
+    /// Fill every key, and the touch strip on Stream Deck+, with `rgb`.
+    pub async fn fill_color(&self, rgb: [u8; 3]) -> anyhow::Result<()> {
+        self.fill(None, rgb).await
+    }
+
+    async fn fill(&self, key: Option<u8>, rgb: [u8; 3]) -> anyhow::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .send(DeviceCommand::Fill { key, rgb, resp: tx })
+            .await
+            .map_err(|_| anyhow::anyhow!("device thread stopped"))?;
+        rx.await
+            .map_err(|_| anyhow::anyhow!("device thread stopped"))?
+    }
+
     pub async fn set_dial_image_jpeg(&self, dial: u8, jpeg: Vec<u8>) -> anyhow::Result<()> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
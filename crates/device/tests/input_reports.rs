@@ -0,0 +1,84 @@
+//! Properties of the input report parsers for arbitrary reports (see also `fuzz/`).
+
+use device::input::{emit_plus_events, parse_key_states_v1, parse_key_states_v2, InputUpdate};
+use proptest::prelude::*;
+
+/// Keys and dials of the Stream Deck+.
+const PLUS_KEYS: usize = 8;
+const PLUS_DIALS: usize = 4;
+
+/// A Stream Deck+ report, mostly of the kinds it sends: keys (`0x00`), touches (`0x02`) and
+/// dial presses or turns (`0x03`, then `0x00` or `0x01` at byte 4).
+fn plus_report() -> impl Strategy<Value = Vec<u8>> {
+    let kind = prop_oneof![Just(0x00), Just(0x02), Just(0x03), any::<u8>()];
+    let dial_kind = prop_oneof![Just(0x00), Just(0x01), any::<u8>()];
+    let rest = prop::collection::vec(any::<u8>(), 0..16);
+    (any::<[u8; 3]>(), kind, dial_kind, rest).prop_map(|(head, kind, dial_kind, rest)| {
+        let mut report = vec![head[0], kind, head[1], head[2], dial_kind];
+        report.extend(rest);
+        report
+    })
+}
+
+proptest! {
+    #[test]
+    fn v1_reads_one_byte_per_key(
+        report in prop::collection::vec(any::<u8>(), 0..64),
+        key_count in 0usize..40,
+    ) {
+        let states = parse_key_states_v1(&report, key_count, false);
+        prop_assert_eq!(states.len(), key_count);
+        for (key, pressed) in states.into_iter().enumerate() {
+            prop_assert_eq!(pressed, report.get(1 + key).is_some_and(|&b| b != 0));
+        }
+    }
+
+    #[test]
+    fn v1_mirroring_only_reorders_rows(
+        report in prop::collection::vec(any::<u8>(), 0..64),
+        rows in 0usize..7,
+    ) {
+        let key_count = rows * 5;
+        let plain = parse_key_states_v1(&report, key_count, false);
+        let mirrored = parse_key_states_v1(&report, key_count, true);
+        prop_assert_eq!(mirrored.len(), key_count);
+        for (row, plain) in plain.chunks(5).enumerate() {
+            let mut mirrored_row = mirrored[row * 5..row * 5 + 5].to_vec();
+            mirrored_row.reverse();
+            prop_assert_eq!(&mirrored_row[..], plain);
+        }
+    }
+
+    #[test]
+    fn plus_updates_follow_the_press_states(
+        reports in prop::collection::vec(
+            prop_oneof![plus_report(), prop::collection::vec(any::<u8>(), 0..8)],
+            0..16,
+        ),
+    ) {
+        let mut keys = [false; PLUS_KEYS];
+        let mut dials = [false; PLUS_DIALS];
+        for report in reports {
+            let (keys_before, dials_before) = (keys, dials);
+            for update in emit_plus_events(&report, &mut keys, &mut dials) {
+                match update {
+                    InputUpdate::KeyDown(key) => prop_assert!(!keys_before[usize::from(key)]),
+                    InputUpdate::KeyUp(key) => prop_assert!(keys_before[usize::from(key)]),
+                    InputUpdate::DialDown(dial) => prop_assert!(!dials_before[usize::from(dial)]),
+                    InputUpdate::DialUp(dial) => prop_assert!(dials_before[usize::from(dial)]),
+                    InputUpdate::DialTurn(dial, steps) => {
+                        prop_assert!(usize::from(dial) < PLUS_DIALS && steps != 0);
+                    }
+                    InputUpdate::TouchPress { .. }
+                    | InputUpdate::TouchLongPress { .. }
+                    | InputUpdate::TouchSwipe { .. } => {}
+                }
+            }
+            if report.get(1) == Some(&0x00) {
+                prop_assert_eq!(&keys[..], &parse_key_states_v2(&report, PLUS_KEYS)[..]);
+            } else {
+                prop_assert_eq!(keys, keys_before);
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Entries of the activity log (see [`storage::activity`]), one per run of a binding.
+//!
+//! The engine only reports entries; keeping and persisting the log is up to the UI.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actions::ActionBinding;
+use app_core::ids::ProfileId;
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use storage::activity::ActivityEntry;
+use storage::usage::{BindingUse, UsageControl};
+
+use crate::{describe_builtin, EngineNotification, Shared, Trace};
+
+/// An entry for `binding` starting now; the outcome is filled in by [`record`].
+pub(crate) fn entry(
+    shared: &Shared,
+    control: &InvocationControl,
+    event: &InvocationEvent,
+    binding: &ActionBinding,
+) -> ActivityEntry {
+    let at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+    ActivityEntry {
+        at_ms,
+        control: describe_control(control),
+        event: describe_event(event),
+        binding: describe_binding(binding),
+        error: None,
+        duration_ms: 0,
+        test: false,
+        dry_run: shared.state().dry_run,
+    }
+}
+
+/// Report `entry` once its steps ran; the first failed step (at any depth) is its error.
+pub(crate) fn record(shared: &Shared, mut entry: ActivityEntry, duration: Duration, trace: &Trace) {
+    entry.duration_ms = duration.as_millis() as u64;
+    entry.error = trace
+        .reports()
+        .iter()
+        .find_map(|r| r.result.as_ref().err().cloned());
+    shared.emit(EngineNotification::Activity(entry));
+}
+
+/// Report the run `entry` of a binding of `profile` for the usage statistics. Only runs from the
+/// device's controls count, and dry runs don't.
+pub(crate) fn record_use(
+    shared: &Shared,
+    profile: Option<ProfileId>,
+    control: &InvocationControl,
+    entry: &ActivityEntry,
+    duration: Duration,
+) {
+    let control = match *control {
+        InvocationControl::Key { index } => UsageControl::Key { index },
+        InvocationControl::Dial { index } => UsageControl::Dial { index },
+        InvocationControl::TouchStrip => UsageControl::TouchStrip,
+        _ => return,
+    };
+    let Some(profile) = profile.filter(|_| !entry.dry_run) else {
+        return;
+    };
+    shared.emit(EngineNotification::BindingUsed(BindingUse {
+        profile: profile.0,
+        control,
+        event: entry.event.clone(),
+        at_ms: entry.at_ms,
+        duration_ms: duration.as_millis() as u64,
+    }));
+}
+
+/// Report `entry` for a binding that could not start.
+pub(crate) fn record_failure(shared: &Shared, mut entry: ActivityEntry, error: String) {
+    entry.error = Some(error);
+    shared.emit(EngineNotification::Activity(entry));
+}
+
+fn describe_control(control: &InvocationControl) -> String {
+    match control {
+        InvocationControl::Key { index } => format!("Key {index}"),
+        InvocationControl::Dial { index } => format!("Dial {index}"),
+        InvocationControl::TouchStrip => "Touch strip".to_string(),
+        InvocationControl::Schedule { id } => format!("Schedule {id}"),
+        InvocationControl::Webhook { id } => format!("Webhook {id}"),
+        InvocationControl::TypedTrigger { id } => format!("Typed trigger {id}"),
+        InvocationControl::FileWatcher { id } => format!("File watcher {id}"),
+        InvocationControl::ProcessWatcher { id } => format!("Process watcher {id}"),
+        InvocationControl::MidiTrigger { id } => format!("MIDI trigger {id}"),
+        InvocationControl::OscTrigger { id } => format!("OSC trigger {id}"),
+    }
+}
+
+fn describe_event(event: &InvocationEvent) -> String {
+    match event {
+        InvocationEvent::KeyDown | InvocationEvent::DialDown => "press".to_string(),
+        InvocationEvent::KeyUp | InvocationEvent::DialUp => "release".to_string(),
+        InvocationEvent::DialRotate { delta } => format!("rotate {delta:+}"),
+        InvocationEvent::TouchTap { .. } => "tap".to_string(),
+        InvocationEvent::TouchLongPress { .. } => "long press".to_string(),
+        InvocationEvent::TouchDrag { delta_x } => format!("drag {delta_x:+}"),
+        InvocationEvent::TouchSwipeLeft => "swipe left".to_string(),
+        InvocationEvent::TouchSwipeRight => "swipe right".to_string(),
+        InvocationEvent::ScheduleFired => "schedule".to_string(),
+        InvocationEvent::WebhookReceived => "request".to_string(),
+        InvocationEvent::AbbreviationTyped => "typed".to_string(),
+        InvocationEvent::FileCreated { path } => format!("created {path}"),
+        InvocationEvent::FileModified { path } => format!("modified {path}"),
+        InvocationEvent::FileDeleted { path } => format!("deleted {path}"),
+        InvocationEvent::ProcessStarted { process } => format!("{process} started"),
+        InvocationEvent::ProcessExited { process } => format!("{process} exited"),
+        InvocationEvent::MidiNote { channel, note, .. } => format!("note {note} ch {channel}"),
+        InvocationEvent::MidiControlChange {
+            channel,
+            controller,
+            value,
+        } => format!("CC {controller} = {value} ch {channel}"),
+        InvocationEvent::OscReceived { address, .. } => format!("OSC {address}"),
+    }
+}
+
+fn describe_binding(binding: &ActionBinding) -> String {
+    match binding {
+        ActionBinding::Plugin(p) => format!("Plugin action {}/{}", p.plugin_id, p.action_id),
+        ActionBinding::Builtin(b) => describe_builtin(b),
+    }
+}
@@ -0,0 +1,279 @@
+//! Remote-control API for companion apps, served by the webhook listener under `/api`.
+//!
+//! Requests use the webhook token. Routes:
+//!
+//! - `GET /api/device`: connected device, active profile, brightness and pause state
+//! - `GET /api/profiles`: all profiles; `PUT /api/profile` with a profile id as body activates one
+//! - `GET /api/keys`: keys of the active profile
+//! - `GET /api/keys/<index>/image`: a key's image as JPEG (its appearance, without live overlays)
+//! - `POST /api/keys/<index>/press`: press and release a key
+//! - `GET /api/events`: WebSocket streaming engine events as JSON text messages
+
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::WebSocketUpgrade;
+use axum::http::{header, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use device::{ControlEvent, ControlEventKind, ControlId};
+use serde::Serialize;
+use storage::profiles::Profile;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tracing::debug;
+use variables::VariableChange;
+
+use crate::webhook::{ensure_running, no_content, ErrorResponse};
+use crate::{
+    activate_profile, dispatch_control, ensure_unlocked, switch_profile, EngineNotification, Shared,
+};
+
+#[derive(Serialize)]
+struct DeviceStatus {
+    name: String,
+    brightness: u8,
+    paused: bool,
+    profile: Option<ProfileSummary>,
+}
+
+#[derive(Serialize)]
+struct ProfileSummary {
+    id: u64,
+    name: String,
+    key_count: u8,
+}
+
+#[derive(Serialize)]
+struct KeyStatus {
+    index: u8,
+    label: String,
+    /// Display text with variables expanded.
+    text: Option<String>,
+    has_action: bool,
+}
+
+/// A message on the `/api/events` WebSocket.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event {
+    KeyDown { index: u8 },
+    KeyUp { index: u8 },
+    ProfileSwitched { profile: ProfileSummary },
+    BrightnessChanged { percent: u8 },
+    VariableChanged { name: String, value: Option<String> },
+    ActionFailed { message: String },
+    Disconnected,
+    Reconnected,
+}
+
+pub(crate) fn handle(
+    shared: &Arc<Shared>,
+    method: Method,
+    path: &str,
+    ws: Option<WebSocketUpgrade>,
+    body: String,
+) -> Result<Response, ErrorResponse> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        (Method::GET, ["device"]) => device(shared),
+        (Method::GET, ["profiles"]) => profiles(),
+        (Method::PUT, ["profile"]) => set_profile(shared, &body).map(no_content),
+        (Method::GET, ["keys"]) => keys(shared),
+        (Method::GET, ["keys", index, "image"]) => key_image(shared, index),
+        (Method::POST, ["keys", index, "press"]) => press_key(shared, index).map(no_content),
+        (Method::GET, ["events"]) => {
+            let Some(ws) = ws else {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "expected a WebSocket upgrade".to_string(),
+                ));
+            };
+            let events = shared.events.subscribe();
+            let variables = shared.variables.subscribe();
+            Ok(ws.on_upgrade(move |socket| stream_events(socket, events, variables)))
+        }
+        _ => Err((StatusCode::NOT_FOUND, "unknown route".to_string())),
+    }
+}
+
+fn json(value: &impl Serialize) -> Result<Response, ErrorResponse> {
+    let body = serde_json::to_string(value)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "application/json")], body).into_response())
+}
+
+fn summary(profile: &Profile) -> ProfileSummary {
+    ProfileSummary {
+        id: profile.id.0,
+        name: profile.name.clone(),
+        key_count: profile.key_count,
+    }
+}
+
+fn device(shared: &Shared) -> Result<Response, ErrorResponse> {
+    let status = {
+        let state = shared.state();
+        DeviceStatus {
+            name: shared.device_name.clone(),
+            brightness: state.brightness,
+            paused: state.paused,
+            profile: state.profile.as_ref().map(summary),
+        }
+    };
+    json(&status)
+}
+
+fn profiles() -> Result<Response, ErrorResponse> {
+    let metas = storage::profiles::list_profiles()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    let profiles: Vec<_> = metas
+        .into_iter()
+        .map(|m| ProfileSummary {
+            id: m.id.0,
+            name: m.name,
+            key_count: m.key_count,
+        })
+        .collect();
+    json(&profiles)
+}
+
+fn set_profile(shared: &Shared, body: &str) -> Result<(), ErrorResponse> {
+    let Ok(profile_id) = body.trim().parse::<u64>() else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("invalid profile id: {}", body.trim()),
+        ));
+    };
+    ensure_unlocked(shared).map_err(|e| (StatusCode::FORBIDDEN, format!("{e:#}")))?;
+    let mode = actions::SwitchProfileMode::To { profile_id };
+    let profile =
+        switch_profile(shared, mode).map_err(|e| (StatusCode::NOT_FOUND, format!("{e:#}")))?;
+    activate_profile(shared, profile);
+    Ok(())
+}
+
+fn keys(shared: &Shared) -> Result<Response, ErrorResponse> {
+    let keys: Vec<_> = {
+        let state = shared.state();
+        let Some(p) = state.profile.as_ref() else {
+            return json(&Vec::<KeyStatus>::new());
+        };
+        p.keys
+            .iter()
+            .enumerate()
+            .map(|(idx, k)| KeyStatus {
+                index: idx as u8,
+                label: k.label.clone(),
+                text: k
+                    .appearance
+                    .text
+                    .as_deref()
+                    .map(|t| shared.variables.render(t)),
+                has_action: k.action.is_some(),
+            })
+            .collect()
+    };
+    json(&keys)
+}
+
+/// Index of a key of the active profile.
+fn parse_key(shared: &Shared, index: &str) -> Result<u8, ErrorResponse> {
+    let key_count = shared.state().profile.as_ref().map_or(0, |p| p.keys.len());
+    match index.parse::<u8>() {
+        Ok(key) if (key as usize) < key_count => Ok(key),
+        _ => Err((StatusCode::NOT_FOUND, format!("no key {index}"))),
+    }
+}
+
+fn key_image(shared: &Shared, index: &str) -> Result<Response, ErrorResponse> {
+    let key = parse_key(shared, index)?;
+    let image = {
+        let state = shared.state();
+        let Some(k) = state
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(key as usize))
+        else {
+            return Err((StatusCode::NOT_FOUND, format!("no key {index}")));
+        };
+        shared.key_image(&k.appearance)
+    };
+    let jpeg = image
+        .render()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    Ok(([(header::CONTENT_TYPE, "image/jpeg")], jpeg).into_response())
+}
+
+fn press_key(shared: &Arc<Shared>, index: &str) -> Result<(), ErrorResponse> {
+    ensure_running(shared)?;
+    let key = parse_key(shared, index)?;
+    for kind in [ControlEventKind::Down, ControlEventKind::Up] {
+        dispatch_control(
+            shared,
+            ControlEvent {
+                control: ControlId::Key(key),
+                kind,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Forward engine events and variable changes until the client or the engine goes away.
+async fn stream_events(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<EngineNotification>,
+    mut variables: mpsc::UnboundedReceiver<VariableChange>,
+) {
+    loop {
+        let event = tokio::select! {
+            n = events.recv() => match n {
+                Ok(n) => event_for(n),
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "API client fell behind; dropped events");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            Some(change) = variables.recv() => Some(Event::VariableChanged {
+                name: change.name,
+                value: change.value,
+            }),
+            msg = socket.recv() => match msg {
+                Some(Ok(_)) => continue,
+                _ => break,
+            },
+        };
+        let Some(event) = event else {
+            continue;
+        };
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+    debug!("API event stream closed");
+}
+
+fn event_for(n: EngineNotification) -> Option<Event> {
+    Some(match n {
+        EngineNotification::Control(ControlEvent {
+            control: ControlId::Key(index),
+            kind,
+        }) => match kind {
+            ControlEventKind::Down => Event::KeyDown { index },
+            ControlEventKind::Up => Event::KeyUp { index },
+            _ => return None,
+        },
+        EngineNotification::ProfileSwitched(profile) => Event::ProfileSwitched {
+            profile: summary(&profile),
+        },
+        EngineNotification::BrightnessChanged(percent) => Event::BrightnessChanged { percent },
+        EngineNotification::ActionFailed(message) => Event::ActionFailed { message },
+        EngineNotification::Disconnected => Event::Disconnected,
+        EngineNotification::Reconnected => Event::Reconnected,
+        _ => return None,
+    })
+}
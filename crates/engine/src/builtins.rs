@@ -0,0 +1,96 @@
+//! Executors for builtin actions that shell out to the host (commands, keyboard input), through
+//! the shell and keyboard of the platform (see [`crate::platform`]).
+
+use std::path::Path;
+use std::time::Duration;
+
+use actions::KeyChord;
+use tokio::process::Command;
+
+use crate::platform::{Keyboard, Shell};
+
+/// How long the keyboard tool gets to type.
+const KEYBOARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn issue_command(
+    command: String,
+    cwd: Option<String>,
+    timeout_ms: Option<u64>,
+) -> anyhow::Result<()> {
+    let mut cmd = Shell::current().command(&command);
+    if let Some(cwd) = cwd {
+        cmd.current_dir(cwd);
+    }
+    run(cmd, timeout_ms.map(Duration::from_millis), "Command").await
+}
+
+/// Type `text`, or else send `keys` (arguments for the keyboard tool, e.g. for wtype:
+/// `-k Return`).
+pub async fn keyboard_input(text: Option<String>, keys: Vec<String>) -> anyhow::Result<()> {
+    if text.is_none() && keys.is_empty() {
+        return Ok(());
+    }
+    match Keyboard::current()? {
+        Keyboard::Tool(argv) => {
+            let mut cmd = Command::new(&argv[0]);
+            cmd.args(&argv[1..]);
+            match text {
+                Some(text) => cmd.arg(text),
+                None => cmd.args(keys),
+            };
+            run(cmd, Some(KEYBOARD_TIMEOUT), &argv[0]).await
+        }
+        #[cfg(any(windows, target_os = "macos"))]
+        Keyboard::Native => {
+            tokio::task::spawn_blocking(move || match text {
+                Some(text) => crate::platform::type_text(&text),
+                None => crate::platform::send_keys(&keys),
+            })
+            .await?
+        }
+    }
+}
+
+/// Press `chord` in the form the keyboard tool takes: `key ctrl+shift+p` for xdotool, wtype
+/// arguments for wtype and the platforms that send keys themselves.
+pub async fn press_chord(chord: &KeyChord) -> anyhow::Result<()> {
+    let xdotool = match Keyboard::current()? {
+        Keyboard::Tool(argv) => Path::new(&argv[0])
+            .file_stem()
+            .is_some_and(|name| name == "xdotool"),
+        #[cfg(any(windows, target_os = "macos"))]
+        Keyboard::Native => false,
+    };
+    let keys = if xdotool {
+        let mut names: Vec<&str> = chord.modifiers.iter().map(|m| m.wtype_name()).collect();
+        names.push(&chord.key);
+        vec!["key".to_string(), names.join("+")]
+    } else {
+        chord.wtype_args()
+    };
+    keyboard_input(None, keys).await
+}
+
+/// Run `cmd` without input or output and fail unless it exits successfully in time.
+async fn run(mut cmd: Command, timeout: Option<Duration>, what: &str) -> anyhow::Result<()> {
+    cmd.stdin(std::process::Stdio::null());
+    cmd.stdout(std::process::Stdio::null());
+    cmd.stderr(std::process::Stdio::null());
+
+    let fut = async move {
+        let status = cmd.status().await?;
+        if status.success() {
+            Ok(())
+        } else {
+            anyhow::bail!("{what} exited with status: {status}")
+        }
+    };
+
+    if let Some(timeout) = timeout {
+        tokio::time::timeout(timeout, fut)
+            .await
+            .map_err(|_| anyhow::anyhow!("{what} timed out"))?
+    } else {
+        fut.await
+    }
+}
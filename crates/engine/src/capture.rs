@@ -0,0 +1,232 @@
+//! Screenshots and screen recordings for `screenshot` and `screen_record` actions.
+//!
+//! Capturing goes through the platform's tools: grim, slurp, wf-recorder and wl-copy on
+//! Wayland; maim, xdotool, ffmpeg and xclip on X11; `screencapture` and `osascript` on macOS.
+//! Files are named after the local time and saved to the user's Pictures (screenshots) or
+//! Videos (recordings) directory unless the action names another one.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use actions::ScreenshotRegion;
+use anyhow::Context;
+use directories::UserDirs;
+use tokio::process::{Child, Command};
+use tracing::{debug, info};
+
+fn wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// `save_dir` (created if missing), or the user directory picked by `default`.
+fn output_dir(
+    save_dir: Option<String>,
+    default: fn(&UserDirs) -> Option<&Path>,
+) -> anyhow::Result<PathBuf> {
+    let dir = match save_dir.filter(|d| !d.trim().is_empty()) {
+        Some(dir) => PathBuf::from(dir.trim()),
+        None => UserDirs::new()
+            .and_then(|dirs| default(&dirs).map(Path::to_path_buf))
+            .context("no default directory; set one on the action")?,
+    };
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn file_name(prefix: &str, extension: &str) -> String {
+    let stamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    format!("{prefix}_{stamp}.{extension}")
+}
+
+/// Run `program` to completion, returning whether it succeeded and its trimmed stdout.
+async fn output(program: &str, args: &[&str]) -> anyhow::Result<(bool, String)> {
+    let out = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("failed to run {program}"))?;
+    let stdout = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    Ok((out.status.success(), stdout))
+}
+
+/// Capture `region` to a new PNG, optionally copying it to the clipboard as well.
+///
+/// Cancelling an interactive selection is not an error; nothing is saved then.
+pub async fn screenshot(
+    region: ScreenshotRegion,
+    save_dir: Option<String>,
+    clipboard: bool,
+) -> anyhow::Result<()> {
+    let path = output_dir(save_dir, UserDirs::picture_dir)?.join(file_name("Screenshot", "png"));
+    let file = path.to_string_lossy().to_string();
+
+    let (program, args): (&str, Vec<String>) = if cfg!(target_os = "macos") {
+        let mut args = vec!["-x".to_string()];
+        match region {
+            ScreenshotRegion::FullScreen => {}
+            ScreenshotRegion::Selection => args.push("-i".into()),
+            ScreenshotRegion::ActiveWindow => args.extend(["-i".into(), "-w".into()]),
+        }
+        args.push(file);
+        ("screencapture", args)
+    } else if cfg!(windows) {
+        anyhow::bail!("[Screenshot] Not supported on Windows yet");
+    } else if wayland() {
+        match region {
+            ScreenshotRegion::FullScreen => ("grim", vec![file]),
+            ScreenshotRegion::Selection => {
+                let (picked, geometry) = output("slurp", &[]).await?;
+                if !picked {
+                    debug!("screenshot selection cancelled");
+                    return Ok(());
+                }
+                ("grim", vec!["-g".into(), geometry, file])
+            }
+            ScreenshotRegion::ActiveWindow => {
+                anyhow::bail!(
+                    "[Screenshot] Capturing the active window is not supported on Wayland"
+                )
+            }
+        }
+    } else {
+        match region {
+            ScreenshotRegion::FullScreen => ("maim", vec![file]),
+            ScreenshotRegion::Selection => ("maim", vec!["-s".into(), file]),
+            ScreenshotRegion::ActiveWindow => {
+                let (_, window) = output("xdotool", &["getactivewindow"]).await?;
+                if window.is_empty() {
+                    anyhow::bail!("[Screenshot] No active window");
+                }
+                ("maim", vec!["-i".into(), window, file])
+            }
+        }
+    };
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    output(program, &args).await?;
+    if !path.exists() {
+        // maim and screencapture end like this when the selection is cancelled.
+        if region == ScreenshotRegion::Selection || cfg!(target_os = "macos") {
+            debug!("screenshot selection cancelled");
+            return Ok(());
+        }
+        anyhow::bail!("[Screenshot] {program} did not save a screenshot");
+    }
+    info!(path = %path.display(), "saved screenshot");
+
+    if clipboard {
+        copy_image(&path).await?;
+    }
+    Ok(())
+}
+
+/// Put the PNG at `path` on the clipboard.
+async fn copy_image(path: &Path) -> anyhow::Result<()> {
+    let file = path.to_string_lossy();
+    let status = if cfg!(target_os = "macos") {
+        let script = format!(
+            "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+            file.replace('"', "\\\"")
+        );
+        Command::new("osascript")
+            .args(["-e", &script])
+            .status()
+            .await
+            .context("failed to run osascript")?
+    } else if wayland() {
+        let png = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        Command::new("wl-copy")
+            .args(["--type", "image/png"])
+            .stdin(png)
+            .status()
+            .await
+            .context("failed to run wl-copy")?
+    } else {
+        Command::new("xclip")
+            .args(["-selection", "clipboard", "-t", "image/png", "-i", &file])
+            .stdin(Stdio::null())
+            .status()
+            .await
+            .context("failed to run xclip")?
+    };
+    if !status.success() {
+        anyhow::bail!("[Screenshot] Copying to the clipboard failed ({status})");
+    }
+    Ok(())
+}
+
+/// A running screen recorder, stopped with [`interrupt`].
+pub(crate) struct Recording {
+    child: Child,
+    pid: u32,
+    path: PathBuf,
+}
+
+impl Recording {
+    /// Start recording the whole screen to a new file in `save_dir`.
+    pub(crate) fn start(save_dir: Option<String>) -> anyhow::Result<Self> {
+        let dir = output_dir(save_dir, UserDirs::video_dir)?;
+        let (mut cmd, path) = if cfg!(target_os = "macos") {
+            let path = dir.join(file_name("Recording", "mov"));
+            let mut cmd = Command::new("screencapture");
+            cmd.arg("-v").arg(&path);
+            (cmd, path)
+        } else if cfg!(windows) {
+            anyhow::bail!("[Screen recording] Not supported on Windows yet");
+        } else if wayland() {
+            let path = dir.join(file_name("Recording", "mp4"));
+            let mut cmd = Command::new("wf-recorder");
+            cmd.arg("-f").arg(&path);
+            (cmd, path)
+        } else {
+            let path = dir.join(file_name("Recording", "mp4"));
+            let display = std::env::var("DISPLAY").unwrap_or_else(|_| ":0".into());
+            let mut cmd = Command::new("ffmpeg");
+            cmd.args(["-nostdin", "-loglevel", "error", "-y", "-f", "x11grab"])
+                .args(["-framerate", "30", "-i", &display, "-pix_fmt", "yuv420p"])
+                .arg(&path);
+            (cmd, path)
+        };
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let program = cmd.as_std().get_program().to_string_lossy().to_string();
+        let child = cmd
+            .spawn()
+            .with_context(|| format!("[Screen recording] Failed to run {program}"))?;
+        let pid = child
+            .id()
+            .with_context(|| format!("[Screen recording] {program} exited immediately"))?;
+        info!(pid, path = %path.display(), "screen recording started");
+        Ok(Self { child, pid, path })
+    }
+
+    pub(crate) fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Wait for the recorder to finish, returning the written file.
+    pub(crate) async fn wait(mut self) -> anyhow::Result<PathBuf> {
+        // Recorders stopped with SIGINT may report a failure status but still finalize the file.
+        let status = self.child.wait().await?;
+        if !self.path.exists() {
+            anyhow::bail!("[Screen recording] The recorder stopped without saving ({status})");
+        }
+        Ok(self.path)
+    }
+}
+
+/// Ask the recorder `pid` to stop; it finalizes the file before exiting.
+pub(crate) fn interrupt(pid: u32) -> anyhow::Result<()> {
+    let status = std::process::Command::new("kill")
+        .args(["-INT", &pid.to_string()])
+        .status()
+        .context("failed to run kill")?;
+    if !status.success() {
+        anyhow::bail!("[Screen recording] Failed to stop the recorder ({status})");
+    }
+    Ok(())
+}
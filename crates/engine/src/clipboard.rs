@@ -0,0 +1,113 @@
+//! Clipboard access for `clipboard` actions, with a short history for `cycle_previous`.
+//!
+//! arboard talks to the display server synchronously, so calls run on the blocking pool. The
+//! handle lives for the whole process: on Linux the process that set the clipboard also serves
+//! its contents, so dropping the handle would clear what an action copied.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use tracing::debug;
+
+/// Texts remembered for `cycle_previous`.
+const HISTORY_LEN: usize = 16;
+
+/// Key arguments for the keyboard tool that paste the clipboard (wtype syntax by default).
+///
+/// Override with `RIVERDECK_PASTE_KEYS`, e.g. `key ctrl+v` for xdotool. macOS pastes with Cmd.
+const DEFAULT_PASTE_KEYS: &str = if cfg!(target_os = "macos") {
+    "-M logo v -m logo"
+} else {
+    "-M ctrl v -m ctrl"
+};
+
+struct ClipboardState {
+    backend: Option<arboard::Clipboard>,
+    /// Most recent first.
+    history: VecDeque<String>,
+    /// Index in `history` of the text currently on the clipboard.
+    cursor: usize,
+}
+
+static STATE: Mutex<ClipboardState> = Mutex::new(ClipboardState {
+    backend: None,
+    history: VecDeque::new(),
+    cursor: 0,
+});
+
+impl ClipboardState {
+    fn backend(&mut self) -> anyhow::Result<&mut arboard::Clipboard> {
+        if self.backend.is_none() {
+            self.backend = Some(arboard::Clipboard::new().context("clipboard unavailable")?);
+        }
+        Ok(self.backend.as_mut().expect("backend initialized above"))
+    }
+
+    /// Record text copied by other applications since our last change.
+    fn remember_current(&mut self) -> anyhow::Result<()> {
+        let current = match self.backend()?.get_text() {
+            Ok(text) => text,
+            Err(arboard::Error::ContentNotAvailable) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if self.history.get(self.cursor) != Some(&current) {
+            self.push(current);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, text: String) {
+        self.history.retain(|t| *t != text);
+        self.history.push_front(text);
+        self.history.truncate(HISTORY_LEN);
+        self.cursor = 0;
+    }
+
+    fn copy(&mut self, text: String) -> anyhow::Result<()> {
+        self.remember_current()?;
+        self.backend()?.set_text(text.clone())?;
+        self.push(text);
+        Ok(())
+    }
+
+    /// Put the next older history entry on the clipboard, wrapping around to the newest.
+    fn cycle_previous(&mut self) -> anyhow::Result<()> {
+        self.remember_current()?;
+        if self.history.len() < 2 {
+            debug!("clipboard history has nothing to cycle to");
+            return Ok(());
+        }
+        let next = (self.cursor + 1) % self.history.len();
+        let text = self.history[next].clone();
+        self.backend()?.set_text(text)?;
+        self.cursor = next;
+        Ok(())
+    }
+}
+
+async fn with_state(
+    f: impl FnOnce(&mut ClipboardState) -> anyhow::Result<()> + Send + 'static,
+) -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut state = STATE.lock().expect("clipboard mutex poisoned");
+        f(&mut state)
+    })
+    .await?
+}
+
+pub async fn copy_text(text: String) -> anyhow::Result<()> {
+    with_state(move |s| s.copy(text)).await
+}
+
+/// Copy `text`, then paste it into the focused window through the keyboard tool.
+pub async fn paste_text(text: String) -> anyhow::Result<()> {
+    copy_text(text).await?;
+    let keys = std::env::var("RIVERDECK_PASTE_KEYS").unwrap_or_else(|_| DEFAULT_PASTE_KEYS.into());
+    let keys = keys.split_whitespace().map(str::to_string).collect();
+    crate::builtins::keyboard_input(None, keys).await
+}
+
+pub async fn cycle_previous() -> anyhow::Result<()> {
+    with_state(ClipboardState::cycle_previous).await
+}
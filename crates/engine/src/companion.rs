@@ -0,0 +1,253 @@
+//! Bitfocus Companion Satellite client: surfaces the connected device to a Companion installation.
+//!
+//! While the device is registered with Companion, Companion owns its keys: presses are forwarded
+//! instead of running local bindings, and the key bitmaps Companion sends are shown on the device.
+//! The client reconnects a few seconds after the connection fails or drops.
+//!
+//! The protocol is line based: `COMMAND KEY=value ...`, values quoted when they contain spaces.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use device::{ControlEvent, ControlEventKind, ControlId};
+use render::cache::LcdImage;
+use storage::settings::CompanionSettings;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::{EngineNotification, Shared};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Companion drops satellites it has not heard from in a while.
+const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Connect (and keep reconnecting) until the task is aborted.
+pub(crate) fn spawn(
+    shared: Arc<Shared>,
+    settings: CompanionSettings,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match session(&shared, &settings).await {
+                Ok(()) => info!("Companion closed the connection"),
+                Err(e) => warn!(error = %format!("{e:#}"), "Companion connection failed"),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+/// Routes key presses to Companion while alive; dropping it (also on abort) hands the keys back.
+struct Registration {
+    shared: Arc<Shared>,
+}
+
+impl Registration {
+    fn new(shared: &Arc<Shared>, presses: mpsc::UnboundedSender<ControlEvent>) -> Self {
+        shared.state().companion = Some(presses);
+        shared.emit(EngineNotification::CompanionConnected(true));
+        Self {
+            shared: shared.clone(),
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.shared.state().companion = None;
+        self.shared
+            .emit(EngineNotification::CompanionConnected(false));
+    }
+}
+
+/// Satellite device id: stable for a device name, without spaces.
+fn device_id(shared: &Shared) -> String {
+    let name: String = shared
+        .device_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("riverdeck-{name}")
+}
+
+async fn session(shared: &Arc<Shared>, settings: &CompanionSettings) -> anyhow::Result<()> {
+    let addr = (settings.host.as_str(), settings.port);
+    let stream = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("failed to connect to {}:{}", settings.host, settings.port))?;
+    info!(host = %settings.host, port = settings.port, "connected to Companion");
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let (presses_tx, mut presses) = mpsc::unbounded_channel();
+    let mut presses_tx = Some(presses_tx);
+    // Keeps the keys routed to Companion until the session ends.
+    let mut _registration = None;
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let id = device_id(shared);
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                let (command, params) = parse_line(&line);
+                match command {
+                    "BEGIN" => {
+                        debug!(%line, "Companion handshake");
+                        send(&mut writer, &add_device(shared, &id)).await?;
+                    }
+                    "ADD-DEVICE" if line.starts_with("ADD-DEVICE OK") => {
+                        info!(device = %id, "registered with Companion");
+                        if let Some(tx) = presses_tx.take() {
+                            _registration = Some(Registration::new(shared, tx));
+                        }
+                    }
+                    "ADD-DEVICE" => {
+                        let message = params.get("MESSAGE").map_or("", String::as_str);
+                        anyhow::bail!("Companion rejected the device: {message}");
+                    }
+                    "KEY-STATE" => key_state(shared, &params).await,
+                    "KEYS-CLEAR" => clear_keys(shared).await,
+                    "BRIGHTNESS" => brightness(shared, &params).await,
+                    "PING" => {
+                        let payload = line.strip_prefix("PING").unwrap_or_default();
+                        send(&mut writer, &format!("PONG{payload}")).await?;
+                    }
+                    "PONG" => {}
+                    _ => debug!(%line, "ignoring Companion message"),
+                }
+            }
+            Some(ev) = presses.recv() => {
+                let (ControlId::Key(key), ControlEventKind::Down | ControlEventKind::Up) =
+                    (ev.control, ev.kind)
+                else {
+                    continue;
+                };
+                let pressed = ev.kind == ControlEventKind::Down;
+                send(
+                    &mut writer,
+                    &format!("KEY-PRESS DEVICEID={id} KEY={key} PRESSED={pressed}"),
+                )
+                .await?;
+            }
+            _ = ping.tick() => send(&mut writer, "PING riverdeck").await?,
+        }
+    }
+}
+
+async fn send(writer: &mut OwnedWriteHalf, line: &str) -> anyhow::Result<()> {
+    writer.write_all(format!("{line}\n").as_bytes()).await?;
+    Ok(())
+}
+
+fn add_device(shared: &Shared, id: &str) -> String {
+    let (columns, _) = shared.capabilities.key_grid;
+    let (size, _) = shared.capabilities.key_size;
+    format!(
+        "ADD-DEVICE DEVICEID={id} PRODUCT_NAME=\"{}\" KEYS_TOTAL={} KEYS_PER_ROW={columns} \
+         BITMAPS={size} COLORS=hex TEXT=true",
+        shared.device_name.replace('"', "'"),
+        shared.capabilities.key_count,
+    )
+}
+
+/// Split `COMMAND KEY=value KEY="quoted value" ...` into the command and its parameters.
+fn parse_line(line: &str) -> (&str, HashMap<String, String>) {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let mut params = HashMap::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in rest.chars().chain([' ']) {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                if let Some((key, value)) = token.split_once('=') {
+                    params.insert(key.to_string(), value.to_string());
+                }
+                token.clear();
+            }
+            c => token.push(c),
+        }
+    }
+    (command, params)
+}
+
+/// Show the bitmap (or, without one, the color and text) Companion sent for a key.
+async fn key_state(shared: &Shared, params: &HashMap<String, String>) {
+    let Some(key) = params.get("KEY").and_then(|k| k.parse::<u8>().ok()) else {
+        return;
+    };
+    if key >= shared.capabilities.key_count {
+        return;
+    }
+    let (width, height) = shared.capabilities.key_size;
+
+    let jpeg = match params.get("BITMAP").and_then(|b| BASE64.decode(b).ok()) {
+        Some(rgb) if !rgb.is_empty() => {
+            // Square bitmaps; the size is implied by the data length.
+            let side = ((rgb.len() / 3) as f64).sqrt() as u32;
+            render::lcd::rgb_bitmap_jpeg(side, side, rgb, width, height)
+        }
+        _ => LcdImage {
+            width,
+            height,
+            background_rgb: params.get("COLOR").and_then(|c| parse_color(c)),
+            icon_path: None,
+            text: params
+                .get("TEXT")
+                .and_then(|t| BASE64.decode(t).ok())
+                .and_then(|t| String::from_utf8(t).ok())
+                .filter(|t| !t.is_empty()),
+            text_scale: None,
+            flash: None,
+            progress: None,
+            dashboard: None,
+        }
+        .render(),
+    };
+    let res = match jpeg {
+        Ok(jpeg) => shared.controller().set_key_image_jpeg(key, jpeg).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = res {
+        warn!(key, error = %e, "failed to show Companion key image");
+    }
+}
+
+async fn clear_keys(shared: &Shared) {
+    if let Err(e) = shared.controller().clear_all().await {
+        warn!(error = %e, "failed to clear keys");
+    }
+}
+
+async fn brightness(shared: &Shared, params: &HashMap<String, String>) {
+    let Some(percent) = params.get("VALUE").and_then(|v| v.parse::<u8>().ok()) else {
+        return;
+    };
+    let percent = percent.min(100);
+    if let Err(e) = shared.controller().set_brightness(percent).await {
+        warn!(error = %e, "failed to apply Companion brightness");
+        return;
+    }
+    shared.state().brightness = percent;
+    shared.emit(EngineNotification::BrightnessChanged(percent));
+}
+
+/// `#rrggbb` (as sent with `COLORS=hex`).
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
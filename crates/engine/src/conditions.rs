@@ -0,0 +1,78 @@
+//! Evaluation of the checks used by `conditional` actions.
+
+use std::path::Path;
+use std::time::Duration;
+
+use actions::Condition;
+use chrono::NaiveTime;
+use variables::VariableStore;
+
+use crate::platform::Shell;
+
+/// Upper bound for a `shell_exit_code` check; a hung command counts as an error.
+const SHELL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Evaluate `condition`; string operands may contain `{{name}}` variable placeholders.
+pub async fn evaluate(condition: &Condition, variables: &VariableStore) -> anyhow::Result<bool> {
+    match condition {
+        Condition::ProcessRunning { name } => {
+            let name = variables.render(name);
+            Ok(tokio::task::spawn_blocking(move || process_running(&name)).await?)
+        }
+        Condition::FileExists { path } => Ok(Path::new(&variables.render(path)).exists()),
+        Condition::VariableEquals { name, value } => {
+            Ok(variables.get(name).unwrap_or_default() == variables.render(value))
+        }
+        Condition::TimeRange { start, end } => {
+            let now = chrono::Local::now().time();
+            Ok(in_time_range(now, parse_time(start)?, parse_time(end)?))
+        }
+        Condition::ShellExitCode { command, code } => {
+            let mut cmd = Shell::current().command(&variables.render(command));
+            cmd.stdin(std::process::Stdio::null());
+            cmd.stdout(std::process::Stdio::null());
+            cmd.stderr(std::process::Stdio::null());
+            cmd.kill_on_drop(true);
+            let status = tokio::time::timeout(SHELL_TIMEOUT, cmd.status())
+                .await
+                .map_err(|_| anyhow::anyhow!("Condition command timed out"))??;
+            Ok(status.code() == Some(*code))
+        }
+    }
+}
+
+fn process_running(name: &str) -> bool {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+    sys.processes().values().any(|p| process_matches(p, name))
+}
+
+/// Whether `process` is called `name`, as `process_running` conditions compare them.
+pub(crate) fn process_matches(process: &sysinfo::Process, name: &str) -> bool {
+    // Windows reports `foo.exe`; accept the bare name as well.
+    let matches = |candidate: &str| {
+        candidate.eq_ignore_ascii_case(name)
+            || candidate
+                .strip_suffix(".exe")
+                .is_some_and(|c| c.eq_ignore_ascii_case(name))
+    };
+    // Linux truncates process names to 15 bytes, so check the executable too.
+    matches(&process.name().to_string_lossy())
+        || process
+            .exe()
+            .and_then(|e| e.file_name())
+            .is_some_and(|f| matches(&f.to_string_lossy()))
+}
+
+fn parse_time(s: &str) -> anyhow::Result<NaiveTime> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map_err(|_| anyhow::anyhow!("Invalid time (expected HH:MM): {s}"))
+}
+
+fn in_time_range(now: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
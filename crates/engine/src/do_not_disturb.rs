@@ -0,0 +1,143 @@
+//! The desktop's do-not-disturb switch, for `do_not_disturb` actions and the keys showing it.
+//!
+//! GNOME keeps it in the `show-banners` setting (changed with `gsettings`, which goes through
+//! dconf on the session bus). Plasma keeps it in `plasmanotifyrc` as the time it ends; the key
+//! sets that far ahead and notifies the notification server of the change
+//! (`kwriteconfig6 --notify`, or `kwriteconfig5`). Windows has no public API for Focus
+//! Assist, so the switch there turns toast notifications off (`ToastEnabled` in the registry).
+
+use std::process::Stdio;
+
+use anyhow::Context;
+use tokio::process::Command;
+
+/// Whether do-not-disturb is on.
+pub async fn enabled() -> anyhow::Result<bool> {
+    imp::enabled().await
+}
+
+/// Turn do-not-disturb on or off.
+pub async fn set_enabled(on: bool) -> anyhow::Result<()> {
+    imp::set_enabled(on).await
+}
+
+/// Run `program` to completion and return its trimmed stdout; failing to run or a non-zero exit
+/// is an error.
+async fn output(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let out = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("failed to run {program}"))?;
+    if !out.status.success() {
+        anyhow::bail!("[Do Not Disturb] {program} failed ({})", out.status);
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use chrono::{Local, NaiveDateTime};
+
+    use super::output;
+
+    const GNOME_SCHEMA: &str = "org.gnome.desktop.notifications";
+
+    /// Plasma's do-not-disturb entry: the time it ends, in KConfig's date format.
+    const PLASMA_ARGS: [&str; 6] = [
+        "--file",
+        "plasmanotifyrc",
+        "--group",
+        "DoNotDisturb",
+        "--key",
+        "Until",
+    ];
+    const PLASMA_DATE_FORMAT: &str = "%Y,%m,%d,%H,%M,%S";
+    /// End of a do-not-disturb turned on by a key; Plasma's own "until turned off" is as long.
+    const PLASMA_FOREVER: &str = "2999,1,1,0,0,0";
+
+    fn plasma() -> bool {
+        std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|d| d.to_ascii_uppercase().contains("KDE"))
+    }
+
+    pub(super) async fn enabled() -> anyhow::Result<bool> {
+        if plasma() {
+            let until = kconfig("kreadconfig", &PLASMA_ARGS).await?;
+            let until = NaiveDateTime::parse_from_str(&until, PLASMA_DATE_FORMAT);
+            return Ok(until.is_ok_and(|until| until > Local::now().naive_local()));
+        }
+        let banners = output("gsettings", &["get", GNOME_SCHEMA, "show-banners"]).await?;
+        Ok(banners == "false")
+    }
+
+    pub(super) async fn set_enabled(on: bool) -> anyhow::Result<()> {
+        if plasma() {
+            let mut args = PLASMA_ARGS.to_vec();
+            args.push("--notify");
+            args.extend(if on { [PLASMA_FOREVER] } else { ["--delete"] });
+            return kconfig("kwriteconfig", &args).await.map(drop);
+        }
+        let banners = if on { "false" } else { "true" };
+        output("gsettings", &["set", GNOME_SCHEMA, "show-banners", banners])
+            .await
+            .map(drop)
+    }
+
+    /// Run the Plasma 6 version of the KConfig tool `name`, or the Plasma 5 one when that is
+    /// missing.
+    async fn kconfig(name: &str, args: &[&str]) -> anyhow::Result<String> {
+        match output(&format!("{name}6"), args).await {
+            Ok(out) => Ok(out),
+            Err(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+                output(&format!("{name}5"), args).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::output;
+
+    const KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\PushNotifications";
+
+    pub(super) async fn enabled() -> anyhow::Result<bool> {
+        // A missing value means toasts are on.
+        let out = output("reg", &["query", KEY, "/v", "ToastEnabled"])
+            .await
+            .unwrap_or_default();
+        Ok(out
+            .lines()
+            .any(|l| l.contains("ToastEnabled") && l.trim_end().ends_with("0x0")))
+    }
+
+    pub(super) async fn set_enabled(on: bool) -> anyhow::Result<()> {
+        let toasts = if on { "0" } else { "1" };
+        let args = [
+            "add",
+            KEY,
+            "/v",
+            "ToastEnabled",
+            "/t",
+            "REG_DWORD",
+            "/d",
+            toasts,
+            "/f",
+        ];
+        output("reg", &args).await.map(drop)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod imp {
+    pub(super) async fn enabled() -> anyhow::Result<bool> {
+        anyhow::bail!("[Do Not Disturb] Not supported on this platform yet")
+    }
+
+    pub(super) async fn set_enabled(_on: bool) -> anyhow::Result<()> {
+        anyhow::bail!("[Do Not Disturb] Not supported on this platform yet")
+    }
+}
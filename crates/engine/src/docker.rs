@@ -0,0 +1,168 @@
+//! Docker and Podman containers for `container` actions, through the engine's API socket.
+//!
+//! The socket is `DOCKER_HOST` when it names one (`unix://` or, on Windows, `npipe://`), else
+//! Docker's default, else Podman's rootless and then system socket; Podman serves the same API.
+//! Requests are plain HTTP/1.0 written to the socket, so the engine closes the connection after
+//! each response and never chunks it.
+
+use std::path::PathBuf;
+
+use actions::ContainerCommand;
+use anyhow::Context;
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// A container, running or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub running: bool,
+    /// The engine's summary, e.g. `Up 2 hours` or `Exited (0) 3 days ago`.
+    pub status: String,
+}
+
+impl Container {
+    /// Whether `container` (a name, or an id or its prefix) is this one.
+    pub fn is(&self, container: &str) -> bool {
+        let container = container.trim();
+        !container.is_empty() && (self.name == container || self.id.starts_with(container))
+    }
+}
+
+impl std::fmt::Display for Container {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiContainer {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    status: String,
+}
+
+/// Every container, running or not, sorted by name.
+pub async fn containers() -> anyhow::Result<Vec<Container>> {
+    let body = request("GET", "/containers/json?all=true").await?;
+    let found: Vec<ApiContainer> =
+        serde_json::from_slice(&body).context("unexpected container list")?;
+    let mut containers: Vec<Container> = found
+        .into_iter()
+        .map(|c| Container {
+            // Names start with a slash.
+            name: c
+                .names
+                .first()
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_else(|| c.id.chars().take(12).collect()),
+            id: c.id,
+            image: c.image,
+            running: c.state == "running",
+            status: c.status,
+        })
+        .collect();
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(containers)
+}
+
+/// Start, stop or restart `container`; whether it runs afterwards.
+pub async fn control(container: &str, command: ContainerCommand) -> anyhow::Result<bool> {
+    let container = container.trim();
+    if container.is_empty() {
+        anyhow::bail!("[Container] No container set");
+    }
+    let (verb, running) = match command {
+        ContainerCommand::Start => ("start", true),
+        ContainerCommand::Stop => ("stop", false),
+        ContainerCommand::Restart => ("restart", true),
+    };
+    request("POST", &format!("/containers/{container}/{verb}")).await?;
+    Ok(running)
+}
+
+/// Send a request without a body and return the response body; a status other than 2xx or 304
+/// (already started or stopped) is an error carrying the engine's message.
+async fn request(method: &str, path: &str) -> anyhow::Result<Vec<u8>> {
+    let mut stream = connect().await?;
+    let head = format!("{method} {path} HTTP/1.0\r\nHost: docker\r\nContent-Length: 0\r\n\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .context("malformed response from the container engine")?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let body = response[split + 4..].to_vec();
+    let status: u16 = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .context("malformed response from the container engine")?;
+    if (200..300).contains(&status) || status == 304 {
+        return Ok(body);
+    }
+    #[derive(Deserialize)]
+    struct ApiError {
+        message: String,
+    }
+    let message = serde_json::from_slice::<ApiError>(&body)
+        .map(|e| e.message)
+        .unwrap_or_else(|_| format!("HTTP {status}"));
+    anyhow::bail!("[Container] {message}")
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// The socket `DOCKER_HOST` names, if it names a local one.
+fn configured_socket(scheme: &str) -> Option<PathBuf> {
+    let host = std::env::var("DOCKER_HOST").ok()?;
+    host.strip_prefix(scheme).map(PathBuf::from)
+}
+
+#[cfg(unix)]
+async fn connect() -> anyhow::Result<Box<dyn Stream>> {
+    let socket = match configured_socket("unix://") {
+        Some(socket) => socket,
+        None => {
+            let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+            let defaults = [
+                Some(PathBuf::from("/var/run/docker.sock")),
+                runtime_dir.map(|d| d.join("podman/podman.sock")),
+                Some(PathBuf::from("/run/podman/podman.sock")),
+            ];
+            let found = defaults.into_iter().flatten().find(|p| p.exists());
+            found.context("[Container] No Docker or Podman socket found")?
+        }
+    };
+    let stream = tokio::net::UnixStream::connect(&socket)
+        .await
+        .with_context(|| format!("[Container] Cannot reach {}", socket.display()))?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(windows)]
+async fn connect() -> anyhow::Result<Box<dyn Stream>> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+
+    let pipe = configured_socket("npipe://")
+        .map(|p| p.to_string_lossy().replace('/', "\\"))
+        .unwrap_or_else(|| r"\\.\pipe\docker_engine".to_string());
+    let client = ClientOptions::new()
+        .open(&pipe)
+        .with_context(|| format!("[Container] Cannot reach Docker at {pipe}"))?;
+    Ok(Box::new(client))
+}
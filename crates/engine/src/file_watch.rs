@@ -0,0 +1,217 @@
+//! File watchers: run an action of the active profile when a file it watches is created,
+//! modified or deleted (see [`storage::profiles::FileWatcher`]).
+//!
+//! Changes come from the OS through the notify crate (inotify, FSEvents or
+//! ReadDirectoryChangesW). A watched file is watched through its directory, so it can be created
+//! later and survives being replaced. Programs write files in bursts, so a watcher fires once a
+//! file has been left alone for [`QUIET`], with what happened to it over the whole burst.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use storage::profiles::{FileChange, FileWatcher};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::{start_sequence, EngineNotification, Shared};
+
+/// How often the active profile's watchers are looked up and quiet files fired.
+const TICK: Duration = Duration::from_millis(250);
+/// How long a file must not change before its watchers fire.
+const QUIET: Duration = Duration::from_millis(500);
+
+/// Changes of one file for one watcher, waiting for the file to be quiet.
+struct Pending {
+    change: FileChange,
+    last: Instant,
+}
+
+/// Watch the files of the active profile's enabled watchers until the task is aborted.
+pub(crate) async fn run(shared: Arc<Shared>) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watchers: Vec<FileWatcher> = vec![];
+    let mut watched = BTreeMap::new();
+    // Only held: the watches end when it is dropped.
+    let mut _os_watcher: Option<RecommendedWatcher> = None;
+    let mut pending: HashMap<(u64, PathBuf), Pending> = HashMap::new();
+    let mut tick = tokio::time::interval(TICK);
+    loop {
+        tokio::select! {
+            Some(res) = rx.recv() => match res {
+                Ok(event) => note(&watchers, event, &mut pending),
+                Err(e) => warn!(error = %e, "file watch error"),
+            },
+            _ = tick.tick() => {
+                watchers = active_watchers(&shared);
+                let wanted = directories(&watchers);
+                if wanted != watched {
+                    // Drop the old watches before adding the new ones.
+                    _os_watcher = None;
+                    if !wanted.is_empty() {
+                        _os_watcher = watch(&shared, &wanted, tx.clone());
+                    }
+                    watched = wanted;
+                    pending.clear();
+                }
+                fire_quiet(&shared, &watchers, &mut pending);
+            }
+        }
+    }
+}
+
+fn active_watchers(shared: &Shared) -> Vec<FileWatcher> {
+    let state = shared.state();
+    let Some(profile) = state.profile.as_ref() else {
+        return vec![];
+    };
+    profile
+        .file_watchers
+        .iter()
+        .filter(|w| w.enabled && !w.path.trim().is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Directories to watch for `watchers`, and whether to watch what is below them too.
+fn directories(watchers: &[FileWatcher]) -> BTreeMap<PathBuf, bool> {
+    let mut dirs = BTreeMap::new();
+    for w in watchers {
+        let path = Path::new(&w.path);
+        let dir = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let recursive = dirs.entry(dir.to_path_buf()).or_insert(false);
+        *recursive |= w.recursive && path.is_dir();
+    }
+    dirs
+}
+
+/// Start watching `dirs`, reporting the ones that cannot be watched.
+fn watch(
+    shared: &Shared,
+    dirs: &BTreeMap<PathBuf, bool>,
+    tx: mpsc::UnboundedSender<notify::Result<Event>>,
+) -> Option<RecommendedWatcher> {
+    let mut watcher = match notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    }) {
+        Ok(w) => w,
+        Err(e) => {
+            shared.emit(EngineNotification::ActionFailed(format!(
+                "[File Watcher] cannot watch files: {e}"
+            )));
+            return None;
+        }
+    };
+    for (dir, &recursive) in dirs {
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        match watcher.watch(dir, mode) {
+            Ok(()) => debug!(dir = %dir.display(), recursive, "watching directory"),
+            Err(e) => shared.emit(EngineNotification::ActionFailed(format!(
+                "[File Watcher] cannot watch {}: {e}",
+                dir.display()
+            ))),
+        }
+    }
+    Some(watcher)
+}
+
+/// Add the changes in `event` to the pending changes of the watchers covering them.
+fn note(watchers: &[FileWatcher], event: Event, pending: &mut HashMap<(u64, PathBuf), Pending>) {
+    let now = Instant::now();
+    for (change, path) in changes(event) {
+        for w in watchers.iter().filter(|w| w.covers(&path)) {
+            pending
+                .entry((w.id, path.clone()))
+                .and_modify(|p| {
+                    p.change = combine(p.change, change);
+                    p.last = now;
+                })
+                .or_insert(Pending { change, last: now });
+        }
+    }
+}
+
+/// What happened to which file in `event`; reads and metadata-only changes are left out.
+fn changes(event: Event) -> Vec<(FileChange, PathBuf)> {
+    let change = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            FileChange::Create
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            FileChange::Delete
+        }
+        // The first path moved away, to the second.
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let mut paths = event.paths.into_iter();
+            let from = paths.next().map(|p| (FileChange::Delete, p));
+            let to = paths.next().map(|p| (FileChange::Create, p));
+            return from.into_iter().chain(to).collect();
+        }
+        EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) => return vec![],
+        EventKind::Modify(_) | EventKind::Any | EventKind::Other => FileChange::Modify,
+    };
+    event.paths.into_iter().map(|p| (change, p)).collect()
+}
+
+/// What happened to a file that went through `earlier` and then `later`.
+fn combine(earlier: FileChange, later: FileChange) -> FileChange {
+    match (earlier, later) {
+        // Still new, however often it was written.
+        (FileChange::Create, FileChange::Modify) => FileChange::Create,
+        // Replaced, like editors save files.
+        (FileChange::Delete, FileChange::Create) => FileChange::Modify,
+        (_, later) => later,
+    }
+}
+
+/// Run the actions for files that have been quiet long enough.
+fn fire_quiet(
+    shared: &Arc<Shared>,
+    watchers: &[FileWatcher],
+    pending: &mut HashMap<(u64, PathBuf), Pending>,
+) {
+    let quiet: Vec<(u64, PathBuf)> = pending
+        .iter()
+        .filter(|(_, p)| p.last.elapsed() >= QUIET)
+        .map(|(k, _)| k.clone())
+        .collect();
+    let paused = shared.state().paused;
+    for key in quiet {
+        let Some(p) = pending.remove(&key) else {
+            continue;
+        };
+        let (id, path) = key;
+        let Some(w) = watchers.iter().find(|w| w.id == id) else {
+            continue;
+        };
+        if paused || !w.changes.contains(&p.change) {
+            continue;
+        }
+        let path = path.display().to_string();
+        debug!(watcher = id, %path, change = ?p.change, "file watcher fired");
+        let event = match p.change {
+            FileChange::Create => InvocationEvent::FileCreated { path },
+            FileChange::Modify => InvocationEvent::FileModified { path },
+            FileChange::Delete => InvocationEvent::FileDeleted { path },
+        };
+        start_sequence(
+            shared,
+            InvocationControl::FileWatcher { id },
+            event,
+            &w.action,
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Git repositories for `git` actions: the status their keys show and the command a press runs.
+//!
+//! Everything goes through the `git` command line, so the user's own configuration (remotes,
+//! credential helpers, SSH keys) applies. Git is told not to ask for credentials on the terminal,
+//! so a remote that needs a password fails instead of waiting on a prompt nobody sees.
+
+use std::process::Stdio;
+
+use actions::GitCommand;
+use anyhow::Context;
+use tokio::process::Command;
+
+/// Where a repository's checked-out branch is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitStatus {
+    /// Branch name, or the short commit id when the head is detached.
+    pub branch: String,
+    /// There are uncommitted changes or untracked files.
+    pub dirty: bool,
+    /// Commits not pushed to, and not pulled from, the branch's upstream; 0 without one.
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+impl GitStatus {
+    /// Branch and state on separate lines, e.g. `main*` over `+2 -1` for a dirty branch two
+    /// commits ahead of its upstream and one behind.
+    pub fn text(&self) -> String {
+        let dirty = if self.dirty { "*" } else { "" };
+        let mut counts = Vec::new();
+        if self.ahead > 0 {
+            counts.push(format!("+{}", self.ahead));
+        }
+        if self.behind > 0 {
+            counts.push(format!("-{}", self.behind));
+        }
+        if counts.is_empty() {
+            format!("{}{dirty}", self.branch)
+        } else {
+            format!("{}{dirty}\n{}", self.branch, counts.join(" "))
+        }
+    }
+}
+
+/// Read the status of the repository at `repo`.
+pub async fn status(repo: &str) -> anyhow::Result<GitStatus> {
+    let out = git(repo, &["status", "--porcelain=v2", "--branch"])
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr);
+        anyhow::bail!("[Git] {}", err.trim());
+    }
+    Ok(parse_status(&String::from_utf8_lossy(&out.stdout)))
+}
+
+fn parse_status(out: &str) -> GitStatus {
+    let mut status = GitStatus {
+        branch: String::new(),
+        dirty: false,
+        ahead: 0,
+        behind: 0,
+    };
+    let mut oid = "";
+    for line in out.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = head.to_string();
+        } else if let Some(id) = line.strip_prefix("# branch.oid ") {
+            oid = id;
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            for count in ab.split_whitespace() {
+                if let Some(n) = count.strip_prefix('+') {
+                    status.ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = count.strip_prefix('-') {
+                    status.behind = n.parse().unwrap_or(0);
+                }
+            }
+        } else if !line.starts_with('#') && !line.is_empty() {
+            status.dirty = true;
+        }
+    }
+    if status.branch == "(detached)" {
+        status.branch = oid.chars().take(7).collect();
+    }
+    status
+}
+
+/// Run `command` in the repository at `repo` and wait for it.
+pub async fn run(repo: &str, command: GitCommand) -> anyhow::Result<()> {
+    let arg = match command {
+        GitCommand::Fetch => "fetch",
+        GitCommand::Pull => "pull",
+        GitCommand::Push => "push",
+    };
+    let out = git(repo, &[arg])
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !out.status.success() {
+        // git's last line names the problem, e.g. a rejected push.
+        let err = String::from_utf8_lossy(&out.stderr);
+        let reason = err.lines().rev().find(|l| !l.trim().is_empty());
+        anyhow::bail!("[Git] git {arg} failed: {}", reason.unwrap_or("").trim());
+    }
+    Ok(())
+}
+
+fn git(repo: &str, args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C")
+        .arg(repo.trim())
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    cmd
+}
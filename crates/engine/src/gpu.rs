@@ -0,0 +1,116 @@
+//! GPU utilization and memory for the metrics sampler.
+//!
+//! NVIDIA cards are read through NVML, which the driver installs (`libnvidia-ml`, loaded at
+//! runtime, so machines without it just report no NVIDIA GPUs). AMD cards are read from the
+//! amdgpu driver's sysfs files on Linux. Other GPUs are not reported.
+
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+
+/// One GPU in a [`crate::monitoring::Sample`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpuStats {
+    pub name: String,
+    /// Percent of time the GPU was busy.
+    pub utilization: f32,
+    pub vram_used: u64,
+    pub vram_total: u64,
+}
+
+/// The GPUs found at start, NVIDIA ones first.
+pub(crate) struct GpuProbe {
+    #[cfg(any(target_os = "linux", windows))]
+    nvml: Option<nvml_wrapper::Nvml>,
+    /// Name and sysfs device directory of each AMD card.
+    #[cfg(target_os = "linux")]
+    amd: Vec<(String, PathBuf)>,
+}
+
+impl GpuProbe {
+    pub(crate) fn new() -> Self {
+        Self {
+            #[cfg(any(target_os = "linux", windows))]
+            nvml: match nvml_wrapper::Nvml::init() {
+                Ok(nvml) => Some(nvml),
+                Err(e) => {
+                    tracing::debug!(error = %e, "NVML unavailable; no NVIDIA GPU metrics");
+                    None
+                }
+            },
+            #[cfg(target_os = "linux")]
+            amd: amd_cards(),
+        }
+    }
+
+    pub(crate) fn sample(&self) -> Vec<GpuStats> {
+        // Nothing is probed on macOS.
+        #[allow(unused_mut)]
+        let mut gpus = vec![];
+        #[cfg(any(target_os = "linux", windows))]
+        if let Some(nvml) = &self.nvml {
+            gpus.extend(nvidia(nvml));
+        }
+        #[cfg(target_os = "linux")]
+        gpus.extend(self.amd.iter().filter_map(|(name, dir)| amd(name, dir)));
+        gpus
+    }
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn nvidia(nvml: &nvml_wrapper::Nvml) -> Vec<GpuStats> {
+    let count = nvml.device_count().unwrap_or(0);
+    (0..count)
+        .filter_map(|i| {
+            let device = nvml.device_by_index(i).ok()?;
+            let memory = device.memory_info().ok()?;
+            Some(GpuStats {
+                name: device.name().unwrap_or_else(|_| format!("NVIDIA GPU {i}")),
+                utilization: device.utilization_rates().ok()?.gpu as f32,
+                vram_used: memory.used,
+                vram_total: memory.total,
+            })
+        })
+        .collect()
+}
+
+/// PCI vendor ID of AMD, as in the sysfs `vendor` file.
+#[cfg(target_os = "linux")]
+const AMD_VENDOR: &str = "0x1002";
+
+#[cfg(target_os = "linux")]
+fn amd_cards() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+        return vec![];
+    };
+    let mut cards: Vec<(String, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let card = e.file_name().to_string_lossy().into_owned();
+            // `card0`, not its connectors like `card0-DP-1`.
+            let is_card = card.starts_with("card") && !card.contains('-');
+            let dir = e.path().join("device");
+            let is_amd = read_sysfs(&dir, "vendor").as_deref() == Some(AMD_VENDOR);
+            (is_card && is_amd && dir.join("gpu_busy_percent").exists())
+                .then(|| (format!("AMD {card}"), dir))
+        })
+        .collect();
+    cards.sort();
+    cards
+}
+
+#[cfg(target_os = "linux")]
+fn amd(name: &str, dir: &Path) -> Option<GpuStats> {
+    let read = |file| read_sysfs(dir, file)?.parse::<u64>().ok();
+    Some(GpuStats {
+        name: name.to_string(),
+        utilization: read("gpu_busy_percent")? as f32,
+        vram_used: read("mem_info_vram_used")?,
+        vram_total: read("mem_info_vram_total")?,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn read_sysfs(dir: &Path, file: &str) -> Option<String> {
+    let text = std::fs::read_to_string(dir.join(file)).ok()?;
+    Some(text.trim().to_string())
+}
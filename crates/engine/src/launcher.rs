@@ -0,0 +1,264 @@
+//! Launching applications, files and URLs for `open` actions, plus discovery of installed apps.
+//!
+//! Nothing goes through a shell: files, URLs and macOS `.app` bundles are handed to the platform
+//! opener (xdg-open, ShellExecute, `open`), and Linux `.desktop` entries are launched by running
+//! their `Exec` line directly.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use tracing::debug;
+
+/// An installed application the UI can offer as an `open` target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppEntry {
+    pub name: String,
+    /// Value for the action's `target` (a `.desktop` file or Start Menu shortcut).
+    pub target: String,
+}
+
+impl std::fmt::Display for AppEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Open `target`: an application entry, a file/directory path or a URL.
+pub async fn open(target: String) -> anyhow::Result<()> {
+    let target = target.trim().to_string();
+    if target.is_empty() {
+        anyhow::bail!("[Action] Nothing to open");
+    }
+    tokio::task::spawn_blocking(move || {
+        if cfg!(target_os = "linux") && target.ends_with(".desktop") {
+            launch_desktop_entry(Path::new(&target))
+        } else {
+            debug!(target = %target, "opening with the system opener");
+            opener::open(&target).with_context(|| format!("failed to open {target}"))
+        }
+    })
+    .await?
+}
+
+/// Installed applications, sorted by name.
+///
+/// Linux: visible `.desktop` entries from the XDG data dirs. Windows: Start Menu shortcuts.
+/// macOS: `.app` bundles in the Applications folders.
+pub fn installed_apps() -> Vec<AppEntry> {
+    let mut apps = if cfg!(windows) {
+        start_menu_apps()
+    } else if cfg!(target_os = "macos") {
+        app_bundles()
+    } else {
+        desktop_apps()
+    };
+    apps.sort_by_cached_key(|a| a.name.to_lowercase());
+    apps
+}
+
+fn launch_desktop_entry(path: &Path) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let entry = parse_desktop_entry(&contents)
+        .with_context(|| format!("{} is not an application entry", path.display()))?;
+    let exec = entry
+        .exec
+        .with_context(|| format!("{} has no Exec line", path.display()))?;
+
+    let argv = shlex::split(&strip_field_codes(&exec))
+        .filter(|argv| !argv.is_empty())
+        .with_context(|| format!("invalid Exec line in {}: {exec}", path.display()))?;
+    debug!(entry = %path.display(), ?argv, "launching desktop entry");
+
+    let mut cmd = std::process::Command::new(&argv[0]);
+    cmd.args(&argv[1..]);
+    if let Some(dir) = entry.working_dir {
+        cmd.current_dir(dir);
+    }
+    cmd.stdin(std::process::Stdio::null());
+    cmd.stdout(std::process::Stdio::null());
+    cmd.stderr(std::process::Stdio::null());
+    // Not waited for: the app keeps running after the action finishes.
+    cmd.spawn()
+        .with_context(|| format!("failed to launch {}", argv[0]))?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct DesktopEntry {
+    name: Option<String>,
+    exec: Option<String>,
+    working_dir: Option<String>,
+    hidden: bool,
+}
+
+/// The `[Desktop Entry]` group of an application entry; `None` for other entry types.
+fn parse_desktop_entry(contents: &str) -> Option<DesktopEntry> {
+    let mut entry = DesktopEntry::default();
+    let mut in_main_group = false;
+    let mut is_app = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "Type" => is_app = value == "Application",
+            "Name" => entry.name = Some(value),
+            "Exec" => entry.exec = Some(value),
+            "Path" if !value.is_empty() => entry.working_dir = Some(value),
+            "NoDisplay" | "Hidden" if value == "true" => entry.hidden = true,
+            _ => {}
+        }
+    }
+    is_app.then_some(entry)
+}
+
+/// Remove `%f`-style field codes from an `Exec` value (no files/URLs are passed).
+fn strip_field_codes(exec: &str) -> String {
+    let mut out = String::with_capacity(exec.len());
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        // `%%` is a literal percent sign; every other code expands to nothing here.
+        if let Some('%') = chars.next() {
+            out.push('%');
+        }
+    }
+    out
+}
+
+fn desktop_apps() -> Vec<AppEntry> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|h| h.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    let dirs = data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|d| d.join("applications"));
+
+    // The first entry with a given file name wins (user entries override system ones).
+    let mut seen = std::collections::HashSet::new();
+    let mut apps = vec![];
+    for dir in dirs {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in read_dir.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+                continue;
+            }
+            let Some(file_name) = path.file_name().map(|n| n.to_os_string()) else {
+                continue;
+            };
+            if !seen.insert(file_name) {
+                continue;
+            }
+            let Some(entry) = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|c| parse_desktop_entry(&c))
+            else {
+                continue;
+            };
+            if entry.hidden || entry.exec.is_none() {
+                continue;
+            }
+            let Some(name) = entry.name else {
+                continue;
+            };
+            apps.push(AppEntry {
+                name,
+                target: path.to_string_lossy().into_owned(),
+            });
+        }
+    }
+    apps
+}
+
+fn start_menu_apps() -> Vec<AppEntry> {
+    let roots = ["APPDATA", "ProgramData"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .map(|d| PathBuf::from(d).join(r"Microsoft\Windows\Start Menu\Programs"));
+
+    let mut apps = vec![];
+    for root in roots {
+        collect_shortcuts(&root, &mut apps);
+    }
+    apps
+}
+
+fn collect_shortcuts(dir: &Path, out: &mut Vec<AppEntry>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in read_dir.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            collect_shortcuts(&path, out);
+            continue;
+        }
+        let is_shortcut = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("lnk") || e.eq_ignore_ascii_case("url"));
+        if !is_shortcut {
+            continue;
+        }
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        out.push(AppEntry {
+            name,
+            target: path.to_string_lossy().into_owned(),
+        });
+    }
+}
+
+fn app_bundles() -> Vec<AppEntry> {
+    let home = std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Applications"));
+    let dirs = [
+        "/Applications",
+        "/Applications/Utilities",
+        "/System/Applications",
+        "/System/Applications/Utilities",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .chain(home);
+
+    let mut apps = vec![];
+    for dir in dirs {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in read_dir.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("app") {
+                continue;
+            }
+            let Some(name) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            apps.push(AppEntry {
+                name,
+                target: path.to_string_lossy().into_owned(),
+            });
+        }
+    }
+    apps
+}
@@ -0,0 +1,1844 @@
+//! Action dispatch engine.
+//!
+//! The engine owns a connected device's event receiver and runs bound actions on tokio tasks,
+//! so key presses fire with no dependency on the UI's tick/redraw cadence (and keep firing
+//! while the window is busy or minimized). UIs push the active profile and installed plugins
+//! in through [`EngineHandle`] and observe [`EngineNotification`]s.
+//!
+//! Schedules pushed in the same way run alongside, on a task that wakes once per minute, and an
+//! optional webhook listener lets other programs trigger actions over HTTP (and, when enabled,
+//! serves the remote-control API for companion apps). A Bitfocus Companion client can hand the
+//! keys over to a Companion installation instead.
+//!
+//! When the device disconnects the engine waits for it to come back, then restores its
+//! brightness and the active profile's key images and carries on with the new connection.
+//!
+//! A profile can target a surface of several decks; the engine then opens the other decks too
+//! and dispatches their presses as keys of the surface (see [`surface`]).
+//!
+//! [`EngineHandle::test_fire`] runs a control's binding on demand (the editor's "Test" button)
+//! through the same path as a press, reporting the outcome of every step.
+//!
+//! Steps are run by an [`actions::executor::Executor`], which waits out delays and runs
+//! conditional branches; pausing actions cancels the sequences still running.
+
+mod activity;
+mod api;
+pub mod builtins;
+mod capture;
+mod clipboard;
+mod companion;
+mod conditions;
+pub mod do_not_disturb;
+pub mod docker;
+mod file_watch;
+pub mod git;
+mod gpu;
+pub mod launcher;
+pub mod midi;
+pub mod monitoring;
+pub mod notification;
+pub mod osc;
+pub mod platform;
+pub mod plugin_feedback;
+mod process_watch;
+mod reconnect;
+pub mod scheduler;
+mod script;
+pub mod serial;
+pub mod surface;
+mod text_expander;
+pub mod vscode;
+mod wake_on_lan;
+pub mod webhook;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::Timelike;
+
+use actions::executor::{CancelToken, Executor, Progress, StepHandler};
+use actions::{
+    ActionBinding, ActionStep, BuiltinAction, DoNotDisturbMode, MeetingApp, RadioKind,
+    ScriptLanguage, SerialFormat, VsCodeCommand,
+};
+use app_core::capabilities::DeviceCapabilities;
+use app_core::ids::{DeviceId, ProfileId};
+use app_core::metrics::metrics;
+use audio::{AudioPlayer, Playback};
+use device::{ControlEvent, ControlEventKind, ControlId, DeviceController, DeviceEvent, TouchZone};
+use home_assistant::HomeAssistant;
+use lighting::{LightState, Lights};
+use meetings::Meetings;
+use openaction::registry::InstalledPlugin;
+use plugin_feedback::KeyChange;
+use plugin_runtime::feedback::PluginFeedback;
+use plugin_runtime::{ActionRuntime, InvocationControl, InvocationEvent};
+use render::cache::LcdImage;
+use secrets::SecretStore;
+use spotify::Spotify;
+use storage::activity::ActivityEntry;
+use storage::midi_triggers::MidiTrigger;
+use storage::osc_triggers::OscTrigger;
+use storage::pomodoro::{PomodoroLengths, PomodoroPhase, PomodoroTimer};
+use storage::process_watchers::ProcessWatcher;
+use storage::profiles::{Appearance, Background, FlashEffect, Profile, TextSize};
+use storage::schedules::Schedule;
+use storage::settings::{
+    CompanionSettings, MidiSettings, NotificationMirrorSettings, OscSettings, TextExpanderSettings,
+    WebhookSettings,
+};
+use storage::surfaces::Surface;
+use storage::typed_triggers::TypedTrigger;
+use storage::usage::BindingUse;
+use storage::webhooks::Webhook;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+use variables::VariableStore;
+
+/// Hold time after which a `counter` key with `reset_on_long_press` resets instead of counting.
+const LONG_PRESS: Duration = Duration::from_millis(600);
+
+/// How long a `power` key with `require_long_press` has to be held before its action runs.
+pub const POWER_HOLD: Duration = Duration::from_millis(1500);
+
+/// How often running Pomodoro timers are checked for the end of their phase.
+const POMODORO_TICK: Duration = Duration::from_secs(1);
+
+/// Events emitted by the engine for UIs to mirror.
+#[derive(Debug, Clone)]
+pub enum EngineNotification {
+    /// Raw control event (after dispatch), e.g. for key highlight state.
+    Control(ControlEvent),
+    /// An action step failed; the rest of its sequence still runs.
+    ActionFailed(String),
+    /// A sandboxed plugin needs a network permission decision before it can run.
+    PermissionRequired {
+        plugin_id: String,
+    },
+    /// A `switch_profile` action loaded and activated another profile.
+    ProfileSwitched(Box<Profile>),
+    /// A `device_brightness` action changed the brightness.
+    BrightnessChanged(u8),
+    /// A soundboard key started playing (`true`) or its last sound finished (`false`).
+    SoundPlaying {
+        key: u8,
+        playing: bool,
+    },
+    /// The device was registered with (`true`) or lost its connection to Bitfocus Companion.
+    ///
+    /// While registered, Companion draws the keys; UIs should not push key images.
+    CompanionConnected(bool),
+    /// A `screen_record` action started (`true`) or finished a recording.
+    ScreenRecording(bool),
+    /// A `do_not_disturb` action turned the desktop's do-not-disturb on (`true`) or off.
+    DoNotDisturbChanged(bool),
+    /// A `radio` action turned `radio` on or off.
+    RadioChanged { radio: RadioKind, on: bool },
+    /// A hold-to-confirm `power` key was pressed (`held`), or released or confirmed; UIs can
+    /// show the hold filling up over [`POWER_HOLD`].
+    PowerHold { key: u8, held: bool },
+    /// A `light` action switched or dimmed `light`, which is now in `state`.
+    LightChanged {
+        light: actions::LightTarget,
+        state: LightState,
+    },
+    /// A `container` action started (`running`) or stopped `container`.
+    ContainerChanged { container: String, running: bool },
+    /// A `git` action ran its command in `repo`, which now has `status` (or can't be read).
+    GitStatus {
+        repo: String,
+        status: Result<git::GitStatus, String>,
+    },
+    /// A `vs_code` task of `workspace` started or finished.
+    VsCodeTask {
+        workspace: String,
+        label: String,
+        status: vscode::TaskStatus,
+    },
+    /// A binding of `control` fired and its appearance asks for a flash as feedback.
+    Flash {
+        control: ControlId,
+        effect: FlashEffect,
+    },
+    /// A `counter` key of `profile` changed its persisted value.
+    CounterChanged {
+        profile: ProfileId,
+        key: u8,
+        value: i64,
+    },
+    /// A `pomodoro` key of `profile` started, paused, reset or finished a phase.
+    PomodoroChanged {
+        profile: ProfileId,
+        key: u8,
+        timer: PomodoroTimer,
+    },
+    /// A plugin action (`setImage`/`setTitle`/`setState`) or a script (`set_key_text`) redrew
+    /// `key` of `profile`.
+    PluginKeyChanged {
+        profile: ProfileId,
+        key: u8,
+        change: KeyChange,
+    },
+    /// A plugin action replaced its settings (`setSettings`) in the bindings of `control` in
+    /// `profile`. The engine's copy is updated already; UIs should do the same and save it
+    /// (see [`plugin_feedback::set_binding_settings`]).
+    PluginSettingsChanged {
+        profile: ProfileId,
+        control: InvocationControl,
+        plugin_id: String,
+        action_id: String,
+        settings: serde_json::Value,
+    },
+    /// An app showed a desktop notification, while notification mirroring is on.
+    DesktopNotification(notification::DesktopNotification),
+    /// A binding finished running (or failed to start), for the activity log.
+    Activity(ActivityEntry),
+    /// A binding of the active profile ran from its control, for the usage statistics.
+    BindingUsed(BindingUse),
+    /// The device went away; the engine keeps trying to reopen it.
+    Disconnected,
+    /// The device is back, with its brightness and key images restored. UIs should take the new
+    /// controller from [`EngineHandle::controller`] and redraw what they show on it.
+    Reconnected,
+    /// A member deck of the surface was opened (`connected`) or went away. UIs should draw its
+    /// keys on the decks from [`EngineHandle::surface_decks`].
+    SurfaceDeck { device: DeviceId, connected: bool },
+}
+
+/// Outcome of one step of a binding, as reported by [`EngineHandle::test_fire`].
+#[derive(Debug, Clone)]
+pub struct StepReport {
+    /// Steps of a conditional's branch are one level deeper than the conditional.
+    pub depth: usize,
+    pub step: String,
+    pub result: Result<(), String>,
+    pub duration: Duration,
+}
+
+/// Reports of the steps of a running binding, in the order they started.
+#[derive(Default)]
+struct Trace {
+    reports: Mutex<Vec<StepReport>>,
+    /// Position in `reports` of each step, by its index in the executor's run.
+    positions: Mutex<HashMap<usize, usize>>,
+}
+
+impl Trace {
+    fn reports(&self) -> std::sync::MutexGuard<'_, Vec<StepReport>> {
+        self.reports.lock().expect("trace mutex poisoned")
+    }
+
+    fn push(&self, depth: usize, step: String) -> usize {
+        let mut reports = self.reports();
+        reports.push(StepReport {
+            depth,
+            step,
+            result: Ok(()),
+            duration: Duration::ZERO,
+        });
+        reports.len() - 1
+    }
+
+    /// Record that step `index` of the run started.
+    fn start(&self, index: usize, depth: usize, step: String) {
+        let position = self.push(depth, step);
+        self.positions
+            .lock()
+            .expect("trace mutex poisoned")
+            .insert(index, position);
+    }
+
+    fn finish(&self, index: usize, result: Result<(), String>, duration: Duration) {
+        let Some(position) = self
+            .positions
+            .lock()
+            .expect("trace mutex poisoned")
+            .get(&index)
+            .copied()
+        else {
+            return;
+        };
+        if let Some(report) = self.reports().get_mut(position) {
+            report.result = result;
+            report.duration = duration;
+        }
+    }
+
+    /// Record which way a conditional went, as a line above its branch's steps.
+    fn branch(&self, depth: usize, matched: bool, has_actions: bool) {
+        let taken = if matched { "then" } else { "otherwise" };
+        let outcome = if has_actions {
+            format!("running the \"{taken}\" actions")
+        } else {
+            format!("no \"{taken}\" actions")
+        };
+        let verdict = if matched { "met" } else { "not met" };
+        self.push(depth, format!("Condition {verdict}; {outcome}"));
+    }
+
+    fn into_reports(self) -> Vec<StepReport> {
+        self.reports.into_inner().expect("trace mutex poisoned")
+    }
+}
+
+#[derive(Default)]
+struct EngineState {
+    profile: Option<Profile>,
+    plugins: Vec<InstalledPlugin>,
+    schedules: Vec<Schedule>,
+    webhooks: Vec<Webhook>,
+    typed_triggers: Vec<TypedTrigger>,
+    process_watchers: Vec<ProcessWatcher>,
+    midi_triggers: Vec<MidiTrigger>,
+    osc_triggers: Vec<OscTrigger>,
+    brightness: u8,
+    /// Control events are still reported, but no actions run (scheduled ones included).
+    paused: bool,
+    /// Actions are expanded and logged but have no effect (see [`EngineHandle::set_dry_run`]).
+    dry_run: bool,
+    /// Set while registered with Bitfocus Companion: key presses go there instead of bindings.
+    companion: Option<mpsc::UnboundedSender<ControlEvent>>,
+    /// Sounds currently playing per soundboard key.
+    playing_sounds: HashMap<u8, usize>,
+    /// Press start of held long-press counter keys.
+    counter_down_at: HashMap<u8, Instant>,
+    /// Press start of held hold-to-confirm power keys.
+    power_down_at: HashMap<u8, Instant>,
+    /// `vs_code` tasks running now, as (workspace, label).
+    vscode_tasks: HashSet<(String, String)>,
+    /// Timers of the profile's `pomodoro` keys, as persisted; keys never started are absent.
+    pomodoros: BTreeMap<u8, PomodoroTimer>,
+    /// Process id of the running screen recorder.
+    recording: Option<u32>,
+    /// Bounds on expanding a binding into steps.
+    expand_limits: actions::ExpandLimits,
+    /// Profiles that can't be switched away from (see [`EngineHandle::set_locked_profiles`]).
+    locked_profiles: HashSet<ProfileId>,
+    /// Surface the active profile targets; keys are surface keys while set.
+    surface: Option<Surface>,
+    /// Cancels the sequences running now; replaced by a fresh token when actions are paused.
+    cancel: CancelToken,
+}
+
+struct Shared {
+    state: Mutex<EngineState>,
+    /// Id, display name and capabilities of the connected device.
+    device_id: DeviceId,
+    device_name: String,
+    capabilities: DeviceCapabilities,
+    /// Replaced when the device reconnects.
+    controller: Mutex<DeviceController>,
+    notify: mpsc::UnboundedSender<EngineNotification>,
+    /// Copies of every notification for remote-control API clients.
+    events: broadcast::Sender<EngineNotification>,
+    runtime: ActionRuntime,
+    audio: AudioPlayer,
+    variables: VariableStore,
+    home_assistant: HomeAssistant,
+    lights: Lights,
+    meetings: Meetings,
+    spotify: Spotify,
+    secrets: SecretStore,
+    /// Open member decks of the surface besides this one.
+    surface_decks: Mutex<HashMap<DeviceId, surface::SurfaceDeck>>,
+}
+
+impl Shared {
+    fn state(&self) -> std::sync::MutexGuard<'_, EngineState> {
+        self.state.lock().expect("engine state mutex poisoned")
+    }
+
+    fn surface_decks(&self) -> std::sync::MutexGuard<'_, HashMap<DeviceId, surface::SurfaceDeck>> {
+        self.surface_decks
+            .lock()
+            .expect("surface decks mutex poisoned")
+    }
+
+    fn controller(&self) -> DeviceController {
+        self.controller
+            .lock()
+            .expect("device controller mutex poisoned")
+            .clone()
+    }
+
+    /// `appearance` as a key image, without live overlays.
+    fn key_image(&self, appearance: &Appearance) -> LcdImage {
+        let (width, height) = self.capabilities.key_size;
+        LcdImage {
+            width,
+            height,
+            background_rgb: match appearance.background {
+                Background::Solid { rgb } => Some(rgb),
+                Background::None => None,
+            },
+            icon_path: appearance.icon_path.as_ref().map(PathBuf::from),
+            text: appearance.text.as_deref().map(|t| self.variables.render(t)),
+            text_scale: appearance.text_size.map(TextSize::scale),
+            flash: None,
+            progress: None,
+            dashboard: None,
+        }
+    }
+
+    fn emit(&self, n: EngineNotification) {
+        // Fails only while no API client is connected.
+        let _ = self.events.send(n.clone());
+        // The UI may have gone away; dispatch keeps working regardless.
+        let _ = self.notify.send(n);
+    }
+}
+
+/// The connected device an engine dispatches for.
+pub struct DeviceInfo {
+    /// Used to find the device again after it disconnects.
+    pub id: DeviceId,
+    pub name: String,
+    pub capabilities: DeviceCapabilities,
+    pub controller: DeviceController,
+}
+
+/// Clients of outside services, shared with the UI so both use the same connections and see the
+/// same states.
+pub struct Integrations {
+    pub home_assistant: HomeAssistant,
+    pub lights: Lights,
+    pub meetings: Meetings,
+    pub spotify: Spotify,
+    pub secrets: SecretStore,
+}
+
+/// Handle to a running engine. Dropping it stops dispatch for the device.
+pub struct EngineHandle {
+    shared: Arc<Shared>,
+    task: tokio::task::JoinHandle<()>,
+    schedules_task: tokio::task::JoinHandle<()>,
+    pomodoro_task: tokio::task::JoinHandle<()>,
+    file_watch_task: tokio::task::JoinHandle<()>,
+    process_watch_task: tokio::task::JoinHandle<()>,
+    /// Running webhook listener and the settings it was started with.
+    webhook_listener: Mutex<Option<(WebhookSettings, tokio::task::JoinHandle<()>)>>,
+    /// Running Companion client and the settings it was started with.
+    companion_client: Mutex<Option<(CompanionSettings, tokio::task::JoinHandle<()>)>>,
+    /// Listener of the MIDI input port and the settings it was started with.
+    midi_listener: Mutex<Option<(MidiSettings, tokio::task::JoinHandle<()>)>>,
+    /// OSC listener and the settings it was started with.
+    osc_listener: Mutex<Option<(OscSettings, tokio::task::JoinHandle<()>)>>,
+    /// Keyboard watch of the typed triggers, while enabled.
+    text_expander: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Watch of the desktop's notifications, while mirroring them is enabled.
+    notification_mirror: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Tasks keeping the other member decks of the surface open.
+    surface_tasks: Mutex<Vec<tokio::task::JoinHandle<()>>>,
+}
+
+impl EngineHandle {
+    /// Start dispatching `events` for a connected device.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn spawn(
+        events: mpsc::Receiver<DeviceEvent>,
+        device: DeviceInfo,
+        brightness: u8,
+        audio: AudioPlayer,
+        variables: VariableStore,
+        integrations: Integrations,
+    ) -> (Self, mpsc::UnboundedReceiver<EngineNotification>) {
+        let (notify, notifications) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(EngineState {
+                brightness,
+                ..EngineState::default()
+            }),
+            device_id: device.id,
+            device_name: device.name,
+            capabilities: device.capabilities,
+            controller: Mutex::new(device.controller),
+            notify,
+            events: broadcast::channel(64).0,
+            runtime: ActionRuntime::new(),
+            audio,
+            variables,
+            home_assistant: integrations.home_assistant,
+            lights: integrations.lights,
+            meetings: integrations.meetings,
+            spotify: integrations.spotify,
+            secrets: integrations.secrets,
+            surface_decks: Mutex::new(HashMap::new()),
+        });
+        let task = tokio::spawn(run(shared.clone(), events));
+        let schedules_task = tokio::spawn(run_schedules(shared.clone()));
+        let pomodoro_task = tokio::spawn(run_pomodoros(shared.clone()));
+        let file_watch_task = tokio::spawn(file_watch::run(shared.clone()));
+        let process_watch_task = tokio::spawn(process_watch::run(shared.clone()));
+        (
+            Self {
+                shared,
+                task,
+                schedules_task,
+                pomodoro_task,
+                file_watch_task,
+                process_watch_task,
+                webhook_listener: Mutex::new(None),
+                companion_client: Mutex::new(None),
+                midi_listener: Mutex::new(None),
+                osc_listener: Mutex::new(None),
+                text_expander: Mutex::new(None),
+                notification_mirror: Mutex::new(None),
+                surface_tasks: Mutex::new(vec![]),
+            },
+            notifications,
+        )
+    }
+
+    /// Replace the profile whose bindings are dispatched.
+    pub fn set_profile(&self, profile: Option<Profile>) {
+        let pomodoros = match profile.as_ref().map(|p| p.id) {
+            Some(id) => storage::pomodoro::load_timers(id).unwrap_or_else(|e| {
+                warn!(error = %e, "failed to load the Pomodoro timers");
+                BTreeMap::new()
+            }),
+            None => BTreeMap::new(),
+        };
+        let mut state = self.shared.state();
+        state.profile = profile;
+        state.pomodoros = pomodoros;
+    }
+
+    /// Put the Pomodoro timer of `key` in the active profile back to a work phase not started.
+    pub fn reset_pomodoro(&self, key: u8) -> anyhow::Result<()> {
+        let Some(profile) = self.shared.state().profile.as_ref().map(|p| p.id) else {
+            return Ok(());
+        };
+        storage::pomodoro::reset_timer(profile, key)?;
+        self.shared.state().pomodoros.remove(&key);
+        self.shared.emit(EngineNotification::PomodoroChanged {
+            profile,
+            key,
+            timer: PomodoroTimer::default(),
+        });
+        Ok(())
+    }
+
+    /// Replace the installed plugin snapshot used to resolve plugin actions.
+    pub fn set_plugins(&self, plugins: Vec<InstalledPlugin>) {
+        self.shared.state().plugins = plugins;
+    }
+
+    /// Replace the schedules whose actions run when their time comes.
+    pub fn set_schedules(&self, schedules: Vec<Schedule>) {
+        self.shared.state().schedules = schedules;
+    }
+
+    /// Replace the process watchers whose actions run when programs start or exit.
+    pub fn set_process_watchers(&self, watchers: Vec<ProcessWatcher>) {
+        self.shared.state().process_watchers = watchers;
+    }
+
+    /// Replace the webhook routes, and start, restart or stop the listener to match `settings`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn set_webhooks(&self, settings: WebhookSettings, webhooks: Vec<Webhook>) {
+        self.shared.state().webhooks = webhooks;
+
+        let mut listener = self
+            .webhook_listener
+            .lock()
+            .expect("webhook listener mutex poisoned");
+        let wanted = settings.enabled.then_some(settings);
+        if listener.as_ref().map(|(s, _)| s) == wanted.as_ref() {
+            return;
+        }
+        if let Some((_, task)) = listener.take() {
+            task.abort();
+        }
+        *listener = wanted.map(|s| (s.clone(), webhook::spawn(self.shared.clone(), s)));
+    }
+
+    /// Start, restart or stop the Bitfocus Companion client to match `settings`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn set_companion(&self, settings: CompanionSettings) {
+        let mut client = self
+            .companion_client
+            .lock()
+            .expect("companion client mutex poisoned");
+        let wanted = settings.enabled.then_some(settings);
+        if client.as_ref().map(|(s, _)| s) == wanted.as_ref() {
+            return;
+        }
+        if let Some((_, task)) = client.take() {
+            task.abort();
+        }
+        *client = wanted.map(|s| (s.clone(), companion::spawn(self.shared.clone(), s)));
+    }
+
+    /// Replace the typed triggers, and start or stop watching the keyboard to match
+    /// `settings`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn set_typed_triggers(&self, settings: TextExpanderSettings, triggers: Vec<TypedTrigger>) {
+        self.shared.state().typed_triggers = triggers;
+
+        let mut watch = self
+            .text_expander
+            .lock()
+            .expect("text expander mutex poisoned");
+        if !settings.enabled {
+            if let Some(task) = watch.take() {
+                task.abort();
+            }
+        } else if watch.is_none() {
+            *watch = Some(text_expander::spawn(self.shared.clone()));
+        }
+    }
+
+    /// Replace the MIDI triggers, and start, restart or stop listening to the MIDI input port
+    /// to match `settings`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn set_midi_triggers(&self, settings: MidiSettings, triggers: Vec<MidiTrigger>) {
+        self.shared.state().midi_triggers = triggers;
+
+        let mut listener = self
+            .midi_listener
+            .lock()
+            .expect("MIDI listener mutex poisoned");
+        let wanted = settings.enabled.then_some(settings);
+        if listener.as_ref().map(|(s, _)| s) == wanted.as_ref() {
+            return;
+        }
+        if let Some((_, task)) = listener.take() {
+            task.abort();
+        }
+        *listener = wanted.map(|s| (s.clone(), midi::spawn(self.shared.clone(), s)));
+    }
+
+    /// Replace the OSC triggers, and start, restart or stop the OSC listener to match
+    /// `settings`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn set_osc_triggers(&self, settings: OscSettings, triggers: Vec<OscTrigger>) {
+        self.shared.state().osc_triggers = triggers;
+
+        let mut listener = self
+            .osc_listener
+            .lock()
+            .expect("OSC listener mutex poisoned");
+        let wanted = settings.enabled.then_some(settings);
+        if listener.as_ref().map(|(s, _)| s) == wanted.as_ref() {
+            return;
+        }
+        if let Some((_, task)) = listener.take() {
+            task.abort();
+        }
+        *listener = wanted.map(|s| (s.clone(), osc::spawn(self.shared.clone(), s)));
+    }
+
+    /// Start or stop watching the desktop's notifications to match `settings`; they are
+    /// reported as [`EngineNotification::DesktopNotification`].
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn set_notification_mirror(&self, settings: NotificationMirrorSettings) {
+        let mut watch = self
+            .notification_mirror
+            .lock()
+            .expect("notification mirror mutex poisoned");
+        if !settings.enabled {
+            if let Some(task) = watch.take() {
+                task.abort();
+            }
+        } else if watch.is_none() {
+            *watch = Some(notification::spawn_mirror(self.shared.clone()));
+        }
+    }
+
+    /// Handle a control event from another input (e.g. a global hotkey) as if the device sent it.
+    pub fn press_control(&self, ev: ControlEvent) {
+        dispatch_control(&self.shared, ev);
+    }
+
+    /// Run the binding `ev` resolves to in the active profile, like the control would, and
+    /// report each step once the sequence is done.
+    ///
+    /// Unlike a press this runs while actions are paused and never goes to Companion: it is an
+    /// explicit request from the user.
+    pub fn test_fire(
+        &self,
+        ev: ControlEvent,
+    ) -> impl Future<Output = anyhow::Result<Vec<StepReport>>> + Send + 'static {
+        let shared = self.shared.clone();
+        async move {
+            let Some((control, event, binding)) = resolve_binding(&shared, ev) else {
+                anyhow::bail!("[Test] Nothing is bound to this control");
+            };
+            metrics().actions_dispatched.inc();
+            let mut entry = activity::entry(&shared, &control, &event, &binding);
+            entry.test = true;
+            let limits = shared.state().expand_limits;
+            let steps = match actions::expand_with(&binding, &limits) {
+                Ok(steps) => steps,
+                Err(e) => {
+                    activity::record_failure(&shared, entry, e.to_string());
+                    return Err(e.into());
+                }
+            };
+            info!(
+                ?control,
+                ?event,
+                steps = steps.len(),
+                "test-firing action sequence"
+            );
+            flash(&shared, ev.control);
+            let trace = Trace::default();
+            let started = Instant::now();
+            run_steps(&shared, &control, &event, steps, &trace).await;
+            activity::record(&shared, entry, started.elapsed(), &trace);
+            Ok(trace.into_reports())
+        }
+    }
+
+    /// Stop (or resume) running bound actions, e.g. from a tray "Pause" toggle.
+    ///
+    /// Pausing also stops the sequences still running, before their next step.
+    pub fn set_paused(&self, paused: bool) {
+        let mut state = self.shared.state();
+        if paused && !state.paused {
+            std::mem::take(&mut state.cancel).cancel();
+        }
+        state.paused = paused;
+    }
+
+    /// Simulate actions instead of running them, e.g. to try out an imported profile.
+    ///
+    /// Bindings still resolve and expand, plugins are checked and conditions are evaluated
+    /// (except shell commands, which count as not met), but every step is only logged.
+    pub fn set_dry_run(&self, dry_run: bool) {
+        self.shared.state().dry_run = dry_run;
+    }
+
+    /// Bound how many steps, and how deeply nested macros, a binding may expand to.
+    pub fn set_expand_limits(&self, limits: actions::ExpandLimits) {
+        self.shared.state().expand_limits = limits;
+    }
+
+    /// Lock profiles for kiosk use: while one of them is active, `switch_profile` actions and the
+    /// remote-control API can't switch away from it. Empty while the lock is off or unlocked.
+    pub fn set_locked_profiles(&self, profiles: impl IntoIterator<Item = ProfileId>) {
+        self.shared.state().locked_profiles = profiles.into_iter().collect();
+    }
+
+    /// Compose the keys of `surface`'s decks, this one included, for a profile targeting it; the
+    /// other decks are opened and kept open until the surface changes. `None` goes back to this
+    /// deck's own keys and blanks the others.
+    pub fn set_surface(&self, surface: Option<Surface>) {
+        let mut tasks = self
+            .surface_tasks
+            .lock()
+            .expect("surface tasks mutex poisoned");
+        for task in tasks.drain(..) {
+            task.abort();
+        }
+        for (_, deck) in self.shared.surface_decks().drain() {
+            tokio::spawn(async move {
+                if let Err(e) = deck.controller.clear_all().await {
+                    warn!(error = %e, "failed to blank a surface deck");
+                }
+            });
+        }
+        let own = self.shared.device_id;
+        if let Some(surface) = &surface {
+            for m in surface.members.iter().filter(|m| m.device != own.0) {
+                let member = surface::run_member(self.shared.clone(), DeviceId(m.device));
+                tasks.push(tokio::spawn(member));
+            }
+        }
+        self.shared.state().surface = surface;
+    }
+
+    /// The other member decks of the surface that are open now.
+    pub fn surface_decks(&self) -> Vec<surface::SurfaceDeck> {
+        self.shared.surface_decks().values().cloned().collect()
+    }
+
+    /// Record a brightness change applied outside the engine (e.g. a UI slider).
+    pub fn set_brightness(&self, percent: u8) {
+        self.shared.state().brightness = percent;
+    }
+
+    /// Controller of the device as connected now; changes when the device reconnects.
+    pub fn controller(&self) -> DeviceController {
+        self.shared.controller()
+    }
+}
+
+impl Drop for EngineHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.schedules_task.abort();
+        self.pomodoro_task.abort();
+        self.file_watch_task.abort();
+        self.process_watch_task.abort();
+        let listener = self.webhook_listener.get_mut();
+        if let Ok(Some((_, task))) = listener.map(Option::take) {
+            task.abort();
+        }
+        let client = self.companion_client.get_mut();
+        if let Ok(Some((_, task))) = client.map(Option::take) {
+            task.abort();
+        }
+        let listener = self.midi_listener.get_mut();
+        if let Ok(Some((_, task))) = listener.map(Option::take) {
+            task.abort();
+        }
+        let listener = self.osc_listener.get_mut();
+        if let Ok(Some((_, task))) = listener.map(Option::take) {
+            task.abort();
+        }
+        if let Ok(Some(task)) = self.text_expander.get_mut().map(Option::take) {
+            task.abort();
+        }
+        if let Ok(Some(task)) = self.notification_mirror.get_mut().map(Option::take) {
+            task.abort();
+        }
+        if let Ok(tasks) = self.surface_tasks.get_mut() {
+            for task in tasks.drain(..) {
+                task.abort();
+            }
+        }
+        if let Some(pid) = self.shared.state().recording {
+            if let Err(e) = capture::interrupt(pid) {
+                warn!(error = %e, "failed to stop the screen recording");
+            }
+        }
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: mpsc::Receiver<DeviceEvent>) {
+    loop {
+        while let Some(ev) = events.recv().await {
+            match ev {
+                DeviceEvent::Control(ev) => {
+                    if let Some(ev) = surface::compose(&shared, shared.device_id, ev) {
+                        dispatch_control(&shared, ev);
+                    }
+                }
+                DeviceEvent::Disconnected => break,
+            }
+        }
+        shared.emit(EngineNotification::Disconnected);
+        events = reconnect::wait_for_device(&shared).await;
+        shared.emit(EngineNotification::Reconnected);
+    }
+}
+
+/// Run the binding for a control event and report the event to the UI.
+fn dispatch_control(shared: &Arc<Shared>, ev: ControlEvent) {
+    let (paused, dry_run, companion) = {
+        let state = shared.state();
+        (state.paused, state.dry_run, state.companion.clone())
+    };
+    if paused {
+        debug!(?ev, "actions paused; not dispatching");
+    } else if let (Some(companion), ControlId::Key(_)) = (companion, ev.control) {
+        let _ = companion.send(ev);
+    } else if !dry_run && counter_long_press(shared, ev) {
+        debug!(?ev, "handled long-press counter event");
+    } else if !dry_run && power_hold(shared, ev) {
+        debug!(?ev, "handled hold-to-confirm power event");
+    } else if let Some((control, event, binding)) = resolve_binding(shared, ev) {
+        flash(shared, ev.control);
+        start_sequence(shared, control, event, &binding);
+    }
+    shared.emit(EngineNotification::Control(ev));
+}
+
+/// Start the actions of due schedules at the top of every minute.
+async fn run_schedules(shared: Arc<Shared>) {
+    let mut last_minute = None;
+    loop {
+        let now = chrono::Local::now();
+        let into_minute = Duration::from_secs(u64::from(now.second()))
+            + Duration::from_nanos(u64::from(now.nanosecond().min(999_999_999)));
+        // A little past the boundary, so the clock reads the new minute on wake-up.
+        let wait = Duration::from_secs(60).saturating_sub(into_minute) + Duration::from_millis(50);
+        tokio::time::sleep(wait).await;
+
+        let minute = scheduler::minute_of(chrono::Local::now());
+        if last_minute == Some(minute) {
+            continue;
+        }
+        last_minute = Some(minute);
+
+        let due = {
+            let state = shared.state();
+            if state.paused {
+                continue;
+            }
+            scheduler::due(&state.schedules, minute)
+        };
+        for s in due {
+            debug!(schedule = s.id, name = %s.name, "schedule fired");
+            start_sequence(
+                &shared,
+                InvocationControl::Schedule { id: s.id },
+                InvocationEvent::ScheduleFired,
+                &s.action,
+            );
+        }
+    }
+}
+
+/// Press/release handling for `counter` keys with `reset_on_long_press`.
+///
+/// The press is recorded on key down and resolved on key up: held for at least [`LONG_PRESS`]
+/// resets the count, anything shorter counts. Returns whether `ev` was consumed.
+fn counter_long_press(shared: &Shared, ev: ControlEvent) -> bool {
+    let ControlId::Key(key) = ev.control else {
+        return false;
+    };
+    let mut state = shared.state();
+    let step = match state
+        .profile
+        .as_ref()
+        .and_then(|p| p.keys.get(key as usize))
+        .and_then(|k| k.action.as_ref())
+    {
+        Some(ActionBinding::Builtin(BuiltinAction::Counter {
+            step,
+            reset_on_long_press: true,
+        })) => Some(*step),
+        _ => None,
+    };
+
+    match ev.kind {
+        ControlEventKind::Down if step.is_some() => {
+            state.counter_down_at.insert(key, Instant::now());
+            true
+        }
+        ControlEventKind::Up => {
+            let Some(down_at) = state.counter_down_at.remove(&key) else {
+                return false;
+            };
+            drop(state);
+            // The binding may have changed while the key was held.
+            let Some(step) = step else {
+                return true;
+            };
+            let delta = (down_at.elapsed() < LONG_PRESS).then_some(step);
+            flash(shared, ev.control);
+            if let Err(e) = update_counter(shared, key, delta) {
+                error!(key, error = %e, "counter update failed");
+                shared.emit(EngineNotification::ActionFailed(e.to_string()));
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Press/release handling for `power` keys with `require_long_press`.
+///
+/// Nothing runs on key down; the action runs once the key has been held for [`POWER_HOLD`],
+/// and releasing it earlier cancels it. Returns whether `ev` was consumed.
+fn power_hold(shared: &Arc<Shared>, ev: ControlEvent) -> bool {
+    let ControlId::Key(key) = ev.control else {
+        return false;
+    };
+    let mut state = shared.state();
+    let guarded = matches!(
+        state
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(key as usize))
+            .and_then(|k| k.action.as_ref()),
+        Some(ActionBinding::Builtin(BuiltinAction::Power {
+            require_long_press: true,
+            ..
+        }))
+    );
+
+    match ev.kind {
+        ControlEventKind::Down if guarded => {
+            let down_at = Instant::now();
+            state.power_down_at.insert(key, down_at);
+            drop(state);
+            shared.emit(EngineNotification::PowerHold { key, held: true });
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(POWER_HOLD).await;
+                {
+                    let mut state = shared.state();
+                    // Released, or released and pressed again, in the meantime.
+                    if state.power_down_at.get(&key) != Some(&down_at) {
+                        return;
+                    }
+                    state.power_down_at.remove(&key);
+                }
+                shared.emit(EngineNotification::PowerHold { key, held: false });
+                // The binding may have changed while the key was held.
+                let Some((control, event, binding)) = resolve_binding(&shared, ev) else {
+                    return;
+                };
+                if matches!(binding, ActionBinding::Builtin(BuiltinAction::Power { .. })) {
+                    flash(&shared, ev.control);
+                    start_sequence(&shared, control, event, &binding);
+                }
+            });
+            true
+        }
+        ControlEventKind::Up => {
+            if state.power_down_at.remove(&key).is_none() {
+                return false;
+            }
+            drop(state);
+            shared.emit(EngineNotification::PowerHold { key, held: false });
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Report a flash of `control` if the active profile gives it one.
+fn flash(shared: &Shared, control: ControlId) {
+    let effect = {
+        let state = shared.state();
+        let Some(p) = state.profile.as_ref() else {
+            return;
+        };
+        let appearance = match control {
+            ControlId::Key(key) => p.keys.get(key as usize).map(|k| &k.appearance),
+            ControlId::Dial(dial) => p.dials.get(dial as usize).map(|d| &d.appearance),
+            ControlId::TouchStrip => Some(&p.touch_strip.appearance),
+        };
+        appearance.and_then(|a| a.flash)
+    };
+    if let Some(effect) = effect {
+        shared.emit(EngineNotification::Flash { control, effect });
+    }
+}
+
+/// Add `delta` to the counter of `key` in the active profile, or reset it for `None`.
+fn update_counter(shared: &Shared, key: u8, delta: Option<i64>) -> anyhow::Result<()> {
+    let Some(profile) = shared.state().profile.as_ref().map(|p| p.id) else {
+        anyhow::bail!("[Action] Counter needs an active profile");
+    };
+    let value = match delta {
+        Some(delta) => storage::counters::add_to_counter(profile, key, delta)?,
+        None => {
+            storage::counters::reset_counter(profile, key)?;
+            0
+        }
+    };
+    debug!(key, value, "counter updated");
+    shared.emit(EngineNotification::CounterChanged {
+        profile,
+        key,
+        value,
+    });
+    Ok(())
+}
+
+/// Start, pause or resume the Pomodoro timer of `key` in the active profile.
+fn toggle_pomodoro(shared: &Shared, key: u8, action: &BuiltinAction) -> anyhow::Result<()> {
+    let Some(lengths) = PomodoroLengths::of(action) else {
+        return Ok(());
+    };
+    let (profile, timer) = {
+        let mut state = shared.state();
+        let Some(profile) = state.profile.as_ref().map(|p| p.id) else {
+            anyhow::bail!("[Action] Pomodoro needs an active profile");
+        };
+        let timer = state.pomodoros.entry(key).or_default();
+        timer.toggle(&lengths, SystemTime::now());
+        (profile, *timer)
+    };
+    storage::pomodoro::save_timer(profile, key, timer)?;
+    debug!(key, ?timer, "pomodoro toggled");
+    shared.emit(EngineNotification::PomodoroChanged {
+        profile,
+        key,
+        timer,
+    });
+    Ok(())
+}
+
+/// A Pomodoro phase that ended, with the timer moved on to the next one.
+struct PhaseEnd {
+    key: u8,
+    ended: PomodoroPhase,
+    timer: PomodoroTimer,
+    action: BuiltinAction,
+}
+
+/// Move running Pomodoro timers on to their next phase when their phase ends, with the sound
+/// and notification their binding asks for.
+async fn run_pomodoros(shared: Arc<Shared>) {
+    let mut tick = tokio::time::interval(POMODORO_TICK);
+    loop {
+        tick.tick().await;
+        for end in end_pomodoro_phases(&shared) {
+            let key = end.key;
+            let (ended, next) = (end.ended.label(), end.timer.phase.label());
+            info!(key, ended, next, "pomodoro phase ended");
+            let BuiltinAction::Pomodoro { sound, notify, .. } = end.action else {
+                continue;
+            };
+            if let Some(path) = sound {
+                if let Err(e) = shared.audio.play(path, 1.0).await {
+                    warn!(key, error = %e, "failed to play the Pomodoro sound");
+                }
+            }
+            if notify {
+                let title = format!("{ended} finished");
+                let body = format!("{next} is next; press the key to start it.");
+                tokio::spawn(async move {
+                    if let Err(e) = notification::show(&title, &body).await {
+                        warn!(error = %e, "failed to show the Pomodoro notification");
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Advance the timers of the active profile whose phase has ended, saving and reporting them.
+fn end_pomodoro_phases(shared: &Shared) -> Vec<PhaseEnd> {
+    let now = SystemTime::now();
+    let mut ends = vec![];
+    let profile = {
+        let mut state = shared.state();
+        let EngineState {
+            profile: Some(profile),
+            pomodoros,
+            paused: false,
+            ..
+        } = &mut *state
+        else {
+            return ends;
+        };
+        for (&key, timer) in pomodoros.iter_mut() {
+            let Some(ActionBinding::Builtin(action)) =
+                profile.keys.get(key as usize).and_then(|k| k.action.as_ref())
+            else {
+                continue;
+            };
+            let Some(lengths) = PomodoroLengths::of(action) else {
+                continue;
+            };
+            if let Some(ended) = timer.advance(&lengths, now) {
+                ends.push(PhaseEnd {
+                    key,
+                    ended,
+                    timer: *timer,
+                    action: action.clone(),
+                });
+            }
+        }
+        profile.id
+    };
+    for end in &ends {
+        if let Err(e) = storage::pomodoro::save_timer(profile, end.key, end.timer) {
+            error!(key = end.key, error = %e, "failed to save the Pomodoro timer");
+        }
+        shared.emit(EngineNotification::PomodoroChanged {
+            profile,
+            key: end.key,
+            timer: end.timer,
+        });
+    }
+    ends
+}
+
+/// Map a control event to the bound action of the active profile, if any.
+fn resolve_binding(
+    shared: &Shared,
+    ev: ControlEvent,
+) -> Option<(InvocationControl, InvocationEvent, ActionBinding)> {
+    let state = shared.state();
+    let p = state.profile.as_ref()?;
+    let (control, event, binding) = match (ev.control, ev.kind) {
+        (ControlId::Key(key), ControlEventKind::Down) => (
+            InvocationControl::Key { index: key },
+            InvocationEvent::KeyDown,
+            p.keys.get(key as usize)?.action.as_ref()?,
+        ),
+        (ControlId::Dial(dial), ControlEventKind::Down) => (
+            InvocationControl::Dial { index: dial },
+            InvocationEvent::DialDown,
+            p.dials.get(dial as usize)?.press.as_ref()?,
+        ),
+        (ControlId::Dial(dial), ControlEventKind::Rotate { delta }) => (
+            InvocationControl::Dial { index: dial },
+            InvocationEvent::DialRotate { delta },
+            p.dials.get(dial as usize)?.rotate.as_ref()?,
+        ),
+        (ControlId::TouchStrip, ControlEventKind::Tap { x }) => (
+            InvocationControl::TouchStrip,
+            InvocationEvent::TouchTap { x },
+            p.touch_strip.tap.as_ref()?,
+        ),
+        (ControlId::TouchStrip, ControlEventKind::LongPress { x }) => (
+            InvocationControl::TouchStrip,
+            InvocationEvent::TouchLongPress { x },
+            p.touch_strip
+                .long_press
+                .as_ref()
+                .or(p.touch_strip.tap.as_ref())?,
+        ),
+        (ControlId::TouchStrip, ControlEventKind::Drag { delta_x, zone }) => {
+            let zone_binding = match zone {
+                TouchZone::Left => p.touch_strip.drag_left.as_ref(),
+                TouchZone::Right => p.touch_strip.drag_right.as_ref(),
+            };
+            (
+                InvocationControl::TouchStrip,
+                InvocationEvent::TouchDrag { delta_x },
+                zone_binding.or(p.touch_strip.drag.as_ref())?,
+            )
+        }
+        (ControlId::TouchStrip, ControlEventKind::SwipeLeft) => (
+            InvocationControl::TouchStrip,
+            InvocationEvent::TouchSwipeLeft,
+            p.touch_strip.swipe_left.as_ref()?,
+        ),
+        (ControlId::TouchStrip, ControlEventKind::SwipeRight) => (
+            InvocationControl::TouchStrip,
+            InvocationEvent::TouchSwipeRight,
+            p.touch_strip.swipe_right.as_ref()?,
+        ),
+        _ => return None,
+    };
+    Some((control, event, binding.clone()))
+}
+
+/// Expand `binding` and run its steps in order on a dedicated task.
+///
+/// Sequences run concurrently with each other; a failed step is reported and the sequence
+/// continues with the next step.
+fn start_sequence(
+    shared: &Arc<Shared>,
+    control: InvocationControl,
+    event: InvocationEvent,
+    binding: &ActionBinding,
+) {
+    metrics().actions_dispatched.inc();
+    let entry = activity::entry(shared, &control, &event, binding);
+    let profile = shared.state().profile.as_ref().map(|p| p.id);
+    let limits = shared.state().expand_limits;
+    let steps = match actions::expand_with(binding, &limits) {
+        Ok(steps) => steps,
+        Err(e) => {
+            error!(?control, ?event, error = %e, "failed to expand action binding");
+            shared.emit(EngineNotification::ActionFailed(e.to_string()));
+            activity::record_failure(shared, entry, e.to_string());
+            return;
+        }
+    };
+
+    info!(
+        ?control,
+        ?event,
+        steps = steps.len(),
+        "starting action sequence"
+    );
+    let shared = shared.clone();
+    tokio::spawn(async move {
+        let trace = Trace::default();
+        let started = Instant::now();
+        run_steps(&shared, &control, &event, steps, &trace).await;
+        debug!(?control, "action sequence finished");
+        let duration = started.elapsed();
+        activity::record_use(&shared, profile, &control, &entry, duration);
+        activity::record(&shared, entry, duration, &trace);
+    });
+}
+
+/// Run `steps` in order through an [`Executor`]; a failed step is reported and the rest still
+/// run. Every step's outcome is recorded in `trace` as well.
+async fn run_steps(
+    shared: &Arc<Shared>,
+    control: &InvocationControl,
+    event: &InvocationEvent,
+    steps: Vec<ActionStep>,
+    trace: &Trace,
+) {
+    let (dry_run, limits, cancel) = {
+        let state = shared.state();
+        (state.dry_run, state.expand_limits, state.cancel.clone())
+    };
+    let executor = Executor::new(limits)
+        .skip_delays(dry_run)
+        .cancel_token(cancel.clone());
+    let handler = SequenceSteps {
+        shared,
+        control,
+        event,
+        dry_run,
+    };
+    let on_progress = |progress: Progress| match progress {
+        Progress::Started {
+            index,
+            depth,
+            label,
+        } => trace.start(index, depth, label),
+        Progress::BranchTaken {
+            depth,
+            matched,
+            has_actions,
+        } => trace.branch(depth, matched, has_actions),
+        Progress::Finished {
+            index,
+            result,
+            duration,
+        } => {
+            // Steps cut short by pausing are not failures worth reporting.
+            if let (Err(e), false) = (&result, cancel.is_cancelled()) {
+                metrics().action_failures.inc();
+                error!(?control, error = %e, "action step failed");
+                shared.emit(EngineNotification::ActionFailed(e.clone()));
+            }
+            trace.finish(index, result, duration);
+        }
+        Progress::Cancelled => info!(?control, "action sequence cancelled"),
+    };
+    executor.run_steps(steps, &handler, &on_progress).await;
+}
+
+/// The steps of one sequence, run for the [`Executor`].
+struct SequenceSteps<'a> {
+    shared: &'a Arc<Shared>,
+    control: &'a InvocationControl,
+    event: &'a InvocationEvent,
+    dry_run: bool,
+}
+
+#[async_trait::async_trait]
+impl StepHandler for SequenceSteps<'_> {
+    async fn run(&self, step: ActionStep) -> anyhow::Result<()> {
+        run_step(self.shared, self.control, self.event, step, self.dry_run).await
+    }
+
+    async fn evaluate(&self, condition: &actions::Condition) -> anyhow::Result<bool> {
+        let matched = match condition {
+            actions::Condition::ShellExitCode { .. } if self.dry_run => {
+                info!(
+                    ?condition,
+                    "dry run: not running condition command; not met"
+                );
+                false
+            }
+            _ => conditions::evaluate(condition, &self.shared.variables).await?,
+        };
+        debug!(control = ?self.control, ?condition, matched, "evaluated condition");
+        Ok(matched)
+    }
+
+    fn describe(&self, step: &ActionStep) -> String {
+        let mut label = describe_step(step);
+        if self.dry_run && !matches!(step, ActionStep::Branch { .. }) {
+            label.push_str(" (dry run)");
+        }
+        label
+    }
+}
+
+/// One line describing `step`, for step reports.
+fn describe_step(step: &ActionStep) -> String {
+    match step {
+        ActionStep::DelayMs(ms) => format!("Wait {ms} ms"),
+        ActionStep::Plugin(p) => format!("Plugin action {}/{}", p.plugin_id, p.action_id),
+        ActionStep::Branch { condition, .. } => format!("Check {condition:?}"),
+        ActionStep::Builtin(b) => describe_builtin(b),
+    }
+}
+
+fn describe_builtin(b: &BuiltinAction) -> String {
+    match b {
+        BuiltinAction::Macro { steps } => format!("Macro of {} steps", steps.len()),
+        BuiltinAction::Conditional { condition, .. } => format!("If {condition:?}"),
+        BuiltinAction::IssueCommand { command, .. } => format!("Run `{command}`"),
+        BuiltinAction::Open { target } => format!("Open {target}"),
+        BuiltinAction::SetVariable { name, value } => format!("Set {name} to \"{value}\""),
+        BuiltinAction::HomeAssistant {
+            service, entity_id, ..
+        } => format!("Home Assistant {service} {entity_id}"),
+        BuiltinAction::Script { source, .. } => {
+            format!("Script of {} lines", source.lines().count())
+        }
+        b => format!("{b:?}"),
+    }
+}
+
+async fn run_step(
+    shared: &Arc<Shared>,
+    control: &InvocationControl,
+    event: &InvocationEvent,
+    step: ActionStep,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    match step {
+        // The executor waits out delays and runs branches itself.
+        ActionStep::DelayMs(_) | ActionStep::Branch { .. } => {
+            anyhow::bail!("Internal: delay or branch step passed to run_step")
+        }
+        ActionStep::Plugin(p) => {
+            info!(
+                ?control,
+                ?event,
+                plugin_id = %p.plugin_id,
+                action_id = %p.action_id,
+                "executing plugin action"
+            );
+            let plugin = shared
+                .state()
+                .plugins
+                .iter()
+                .find(|pl| pl.manifest.id == p.plugin_id)
+                .cloned();
+            let Some(plugin) = plugin else {
+                anyhow::bail!("[Action] Plugin not installed: {}", p.plugin_id);
+            };
+            if !plugin.enabled {
+                anyhow::bail!("[Action] Plugin is disabled: {}", p.plugin_id);
+            }
+            if dry_run {
+                info!(
+                    plugin_id = %p.plugin_id,
+                    action_id = %p.action_id,
+                    settings = %p.settings,
+                    "dry run: not invoking plugin action"
+                );
+                return Ok(());
+            }
+            if plugin.needs_permission_prompt() {
+                shared.emit(EngineNotification::PermissionRequired {
+                    plugin_id: p.plugin_id.clone(),
+                });
+                anyhow::bail!(
+                    "[Action] Plugin needs a permission decision: {}",
+                    p.plugin_id
+                );
+            }
+            let settings = shared.secrets.render_json(&p.settings).await?;
+            shared
+                .runtime
+                .invoke(
+                    &plugin,
+                    &p.action_id,
+                    control.clone(),
+                    event.clone(),
+                    settings.clone(),
+                    &|feedback| {
+                        // Settings saved back keep referencing the secrets.
+                        let feedback = match feedback {
+                            PluginFeedback::SetSettings(saved) => PluginFeedback::SetSettings(
+                                secrets::conceal(&p.settings, &settings, saved),
+                            ),
+                            feedback => feedback,
+                        };
+                        plugin_feedback::apply(shared, &plugin, &p.action_id, control, feedback)
+                    },
+                )
+                .await
+        }
+        ActionStep::Builtin(b) if dry_run => {
+            info!(?control, builtin = ?b, "dry run: not executing builtin action");
+            Ok(())
+        }
+        ActionStep::Builtin(b) => {
+            info!(?control, builtin = ?b, "executing builtin action");
+            run_builtin(shared, control, event, b).await
+        }
+    }
+}
+
+/// Run a single builtin. Text fields (commands, typed/copied text, open targets, variable
+/// values) have their `{{name}}` placeholders expanded first; those that are not shown or
+/// stored anywhere also their `{{secret:name}}` references (see [`expand`]).
+async fn run_builtin(
+    shared: &Arc<Shared>,
+    control: &InvocationControl,
+    event: &InvocationEvent,
+    b: BuiltinAction,
+) -> anyhow::Result<()> {
+    let vars = &shared.variables;
+    match b {
+        // Macro should have been expanded away by `actions::expand`.
+        BuiltinAction::Macro { .. } => anyhow::bail!("Internal: macro was not expanded"),
+        BuiltinAction::Conditional { .. } => {
+            anyhow::bail!("Internal: conditional was not expanded")
+        }
+        BuiltinAction::IssueCommand {
+            command,
+            cwd,
+            timeout_ms,
+        } => builtins::issue_command(expand(shared, &command).await?, cwd, timeout_ms).await,
+        BuiltinAction::KeyboardInput { text, keys, chord } => {
+            let text = match text {
+                Some(t) => Some(expand(shared, &t).await?),
+                None => None,
+            };
+            match chord {
+                Some(chord) if text.is_none() => builtins::press_chord(&chord).await,
+                _ => builtins::keyboard_input(text, keys).await,
+            }
+        }
+        BuiltinAction::PlaySound { path, volume } => {
+            let volume = f32::from(volume.unwrap_or(100)) / 100.0;
+            shared.audio.play(path, volume).await?;
+            Ok(())
+        }
+        BuiltinAction::StopSound { path: Some(path) } => shared.audio.stop(path),
+        BuiltinAction::StopSound { path: None } => shared.audio.stop_all(),
+        BuiltinAction::Soundboard { path, volume, mode } => {
+            match mode {
+                actions::SoundboardMode::PlayOverlap => {}
+                actions::SoundboardMode::Restart => shared.audio.stop(path.clone())?,
+                actions::SoundboardMode::StopOthers => shared.audio.stop_all()?,
+            }
+            let volume = f32::from(volume.unwrap_or(100)) / 100.0;
+            let playback = shared.audio.play(path, volume).await?;
+            if let InvocationControl::Key { index } = control {
+                track_sound(shared, *index, playback);
+            }
+            Ok(())
+        }
+        BuiltinAction::Counter { step, .. } => match control {
+            InvocationControl::Key { index } => update_counter(shared, *index, Some(step)),
+            _ => anyhow::bail!("[Action] Counter can only be bound to a key"),
+        },
+        pomodoro @ BuiltinAction::Pomodoro { .. } => match control {
+            InvocationControl::Key { index } => toggle_pomodoro(shared, *index, &pomodoro),
+            _ => anyhow::bail!("[Action] Pomodoro can only be bound to a key"),
+        },
+        BuiltinAction::SetVariable { name, value } => vars.set(&name, Some(vars.render(&value))),
+        BuiltinAction::Open { target } => launcher::open(expand(shared, &target).await?).await,
+        BuiltinAction::Notify { title, body } => {
+            notification::show(&vars.render(&title), &vars.render(&body)).await
+        }
+        BuiltinAction::DoNotDisturb { mode } => {
+            let on = match mode {
+                DoNotDisturbMode::Toggle => !do_not_disturb::enabled().await?,
+                DoNotDisturbMode::On => true,
+                DoNotDisturbMode::Off => false,
+            };
+            do_not_disturb::set_enabled(on).await?;
+            shared.emit(EngineNotification::DoNotDisturbChanged(on));
+            Ok(())
+        }
+        BuiltinAction::Power { command, .. } => platform::power(command).await,
+        ci @ BuiltinAction::CiStatus { .. } => {
+            let Some(query) = github_actions::CiQuery::of(&ci) else {
+                anyhow::bail!("[GitHub Actions] No repository set");
+            };
+            // The marketplace token, so private repositories work here too.
+            let settings = storage::settings::load_settings()?;
+            let client = github_actions::GithubActions::new();
+            client.set_token(settings.marketplace.github_token().map(str::to_string));
+            let run = client.fetch_latest_run(&query).await?;
+            launcher::open(run.map_or_else(|| query.runs_url(), |r| r.url)).await
+        }
+        BuiltinAction::Light { light, command } => {
+            // Bound to a dial, a brightness step counts once per detent it turned.
+            let steps = match event {
+                InvocationEvent::DialRotate { delta } => *delta,
+                _ => 1,
+            };
+            let state = shared.lights.apply(&light, command, steps).await?;
+            shared.emit(EngineNotification::LightChanged { light, state });
+            Ok(())
+        }
+        BuiltinAction::Meeting { app, command } => match app {
+            MeetingApp::Zoom => {
+                builtins::keyboard_input(None, meetings::zoom_shortcut(command)).await
+            }
+            MeetingApp::Teams => shared.meetings.teams(command),
+        },
+        BuiltinAction::Container { container, command } => {
+            let container = vars.render(&container);
+            let running = docker::control(&container, command).await?;
+            shared.emit(EngineNotification::ContainerChanged { container, running });
+            Ok(())
+        }
+        BuiltinAction::Midi { port, message } => midi::send(&vars.render(&port), message).await,
+        BuiltinAction::Osc {
+            target,
+            address,
+            args,
+        } => {
+            let target = vars.render(&target);
+            osc::send(&target, &vars.render(&address), &vars.render(&args)).await
+        }
+        BuiltinAction::Serial {
+            port,
+            baud,
+            payload,
+            format,
+        } => {
+            let payload = match format {
+                SerialFormat::Text => vars.render(&payload),
+                SerialFormat::Hex => payload,
+            };
+            serial::write(&vars.render(&port), baud, format.bytes(&payload)?).await
+        }
+        BuiltinAction::WakeOnLan { mac, broadcast } => {
+            wake_on_lan::wake(&vars.render(&mac), &vars.render(&broadcast)).await
+        }
+        BuiltinAction::Git { repo, command } => {
+            let repo = vars.render(&repo);
+            let res = git::run(&repo, command).await;
+            // Pulls and pushes change what the key shows even when they fail halfway.
+            let status = git::status(&repo).await.map_err(|e| format!("{e:#}"));
+            shared.emit(EngineNotification::GitStatus { repo, status });
+            res
+        }
+        BuiltinAction::VsCode { workspace, command } => {
+            let workspace = vars.render(&workspace);
+            match command {
+                VsCodeCommand::Open => vscode::open(&workspace).await,
+                VsCodeCommand::RunTask(label) => run_vscode_task(shared, workspace, label).await,
+            }
+        }
+        BuiltinAction::Radio { radio } => {
+            let on = !platform::radio_enabled(radio).await?;
+            platform::set_radio_enabled(radio, on).await?;
+            shared.emit(EngineNotification::RadioChanged { radio, on });
+            Ok(())
+        }
+        BuiltinAction::Clipboard { mode } => match mode {
+            actions::ClipboardMode::CopyText(text) => {
+                clipboard::copy_text(expand(shared, &text).await?).await
+            }
+            actions::ClipboardMode::PasteText(text) => {
+                clipboard::paste_text(expand(shared, &text).await?).await
+            }
+            actions::ClipboardMode::CyclePrevious => clipboard::cycle_previous().await,
+        },
+        BuiltinAction::Spotify { command } => match command {
+            actions::SpotifyCommand::PlayPause => shared.spotify.play_pause().await,
+            actions::SpotifyCommand::Next => shared.spotify.next().await,
+            actions::SpotifyCommand::Previous => shared.spotify.previous().await,
+            actions::SpotifyCommand::Like => shared.spotify.toggle_like().await,
+        },
+        BuiltinAction::Screenshot {
+            region,
+            save_dir,
+            clipboard,
+        } => capture::screenshot(region, save_dir.map(|d| vars.render(&d)), clipboard).await,
+        BuiltinAction::ScreenRecord { save_dir } => {
+            let save_dir = save_dir.map(|d| vars.render(&d));
+            let recording = {
+                let mut state = shared.state();
+                if let Some(pid) = state.recording {
+                    return capture::interrupt(pid);
+                }
+                let recording = capture::Recording::start(save_dir)?;
+                state.recording = Some(recording.pid());
+                recording
+            };
+            shared.emit(EngineNotification::ScreenRecording(true));
+            track_recording(shared, recording);
+            Ok(())
+        }
+        BuiltinAction::SwitchProfile { mode } => {
+            activate_profile(shared, switch_profile(shared, mode)?);
+            Ok(())
+        }
+        BuiltinAction::DeviceBrightness { mode } => {
+            let new_val = {
+                let mut state = shared.state();
+                let v = match mode {
+                    actions::BrightnessMode::Set { percent } => percent,
+                    actions::BrightnessMode::Increase { delta } => {
+                        state.brightness.saturating_add(delta)
+                    }
+                    actions::BrightnessMode::Decrease { delta } => {
+                        state.brightness.saturating_sub(delta)
+                    }
+                }
+                .clamp(0, 100);
+                state.brightness = v;
+                v
+            };
+            shared.controller().set_brightness(new_val).await?;
+            shared.emit(EngineNotification::BrightnessChanged(new_val));
+            Ok(())
+        }
+        BuiltinAction::HomeAssistant {
+            service,
+            entity_id,
+            data,
+        } => {
+            let entity_id = vars.render(&entity_id);
+            let data = expand(shared, &data).await?;
+            let data = if data.trim().is_empty() {
+                serde_json::Value::Null
+            } else {
+                serde_json::from_str(&data)
+                    .map_err(|e| anyhow::anyhow!("[Home Assistant] Invalid service data: {e}"))?
+            };
+            let entity_id = Some(entity_id.trim()).filter(|e| !e.is_empty());
+            shared
+                .home_assistant
+                .call_service(&vars.render(&service), entity_id, data)
+                .await
+        }
+        // Live display is handled by the UI's binding hints; executing it is a no-op.
+        BuiltinAction::SystemMonitoring { .. }
+        | BuiltinAction::Clock { .. }
+        | BuiltinAction::Weather { .. } => Ok(()),
+        BuiltinAction::Script {
+            language: ScriptLanguage::Rhai,
+            source,
+        } => script::run(shared, control, source).await,
+    }
+}
+
+/// Expand the `{{name}}` variables and `{{secret:name}}` secrets in a text an action is about to
+/// use. Secrets are only expanded here, so they never end up on a key or in a variable.
+async fn expand(shared: &Shared, template: &str) -> anyhow::Result<String> {
+    let values = shared.secrets.resolve(template).await?;
+    let lookup = |name: &str| match secrets::reference(name) {
+        Some(secret) => values.get(secret).cloned(),
+        None => shared.variables.get(name),
+    };
+    Ok(variables::render_template(template, lookup))
+}
+
+/// Run a `vs_code` task to completion, reporting it as running until then. A task can't run
+/// twice at once.
+async fn run_vscode_task(shared: &Shared, workspace: String, label: String) -> anyhow::Result<()> {
+    let task = (workspace.clone(), label.clone());
+    if !shared.state().vscode_tasks.insert(task.clone()) {
+        anyhow::bail!("[VS Code] Task {label:?} is already running");
+    }
+    let emit = |status| {
+        shared.emit(EngineNotification::VsCodeTask {
+            workspace: workspace.clone(),
+            label: label.clone(),
+            status,
+        })
+    };
+    emit(vscode::TaskStatus::Running);
+    let res = vscode::run_task(&workspace, &label).await;
+    shared.state().vscode_tasks.remove(&task);
+    emit(match res {
+        Ok(()) => vscode::TaskStatus::Succeeded,
+        Err(_) => vscode::TaskStatus::Failed,
+    });
+    res
+}
+
+/// Report the recording as running until the recorder exits.
+fn track_recording(shared: &Arc<Shared>, recording: capture::Recording) {
+    let shared = shared.clone();
+    tokio::spawn(async move {
+        let res = recording.wait().await;
+        shared.state().recording = None;
+        shared.emit(EngineNotification::ScreenRecording(false));
+        match res {
+            Ok(path) => info!(path = %path.display(), "saved screen recording"),
+            Err(e) => shared.emit(EngineNotification::ActionFailed(format!("{e:#}"))),
+        }
+    });
+}
+
+/// Report `key` as playing until every sound it started has finished.
+fn track_sound(shared: &Arc<Shared>, key: u8, playback: Playback) {
+    let started = {
+        let mut state = shared.state();
+        let n = state.playing_sounds.entry(key).or_default();
+        *n += 1;
+        *n == 1
+    };
+    if started {
+        shared.emit(EngineNotification::SoundPlaying { key, playing: true });
+    }
+
+    let shared = shared.clone();
+    tokio::spawn(async move {
+        // A dropped sender (audio thread gone) counts as finished too.
+        let _ = playback.finished.await;
+        let stopped = {
+            let mut state = shared.state();
+            match state.playing_sounds.get_mut(&key) {
+                Some(n) if *n > 1 => {
+                    *n -= 1;
+                    false
+                }
+                _ => {
+                    state.playing_sounds.remove(&key);
+                    true
+                }
+            }
+        };
+        if stopped {
+            debug!(
+                key,
+                playback = playback.id,
+                "soundboard key finished playing"
+            );
+            shared.emit(EngineNotification::SoundPlaying {
+                key,
+                playing: false,
+            });
+        }
+    });
+}
+
+/// Make `profile` the active profile and tell the UI.
+fn activate_profile(shared: &Shared, profile: Profile) {
+    info!(profile = %profile.name, "switched profile");
+    shared.state().profile = Some(profile.clone());
+    shared.emit(EngineNotification::ProfileSwitched(Box::new(profile)));
+}
+
+/// Fails while the active profile is locked for kiosk use.
+fn ensure_unlocked(shared: &Shared) -> anyhow::Result<()> {
+    let state = shared.state();
+    let active = state.profile.as_ref();
+    if let Some(p) = active.filter(|p| state.locked_profiles.contains(&p.id)) {
+        anyhow::bail!("[Kiosk] {} is locked; enter the PIN to switch profiles.", p.name);
+    }
+    Ok(())
+}
+
+/// Resolve and load the target of a `switch_profile` action.
+///
+/// Next/previous cycle through the profiles with the same key count as the active one.
+fn switch_profile(shared: &Shared, mode: actions::SwitchProfileMode) -> anyhow::Result<Profile> {
+    ensure_unlocked(shared)?;
+    let (current, key_count) = {
+        let state = shared.state();
+        let p = state.profile.as_ref();
+        (p.map(|p| p.id), p.map(|p| p.key_count))
+    };
+
+    let target = match mode {
+        actions::SwitchProfileMode::To { profile_id } => Some(ProfileId(profile_id)),
+        actions::SwitchProfileMode::Next | actions::SwitchProfileMode::Prev => {
+            let mut metas = storage::profiles::list_profiles()?;
+            if let Some(kc) = key_count {
+                metas.retain(|m| m.key_count == kc);
+            }
+            let n = metas.len();
+            metas
+                .iter()
+                .position(|m| Some(m.id) == current)
+                .map(|i| match mode {
+                    actions::SwitchProfileMode::Next => metas[(i + 1) % n].id,
+                    _ => metas[(i + n - 1) % n].id,
+                })
+        }
+    };
+
+    let Some(id) = target else {
+        anyhow::bail!("[Action] No profiles available to switch.");
+    };
+    let path = storage::profiles::profile_path(id)?;
+    storage::profiles::load_profile(&path)
+}
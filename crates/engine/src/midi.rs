@@ -0,0 +1,176 @@
+//! MIDI: `midi` actions send notes and controller changes to an output port, and MIDI triggers
+//! run actions for what a controller plays on an input port (see [`storage::midi_triggers`]).
+//!
+//! Ports come from the OS through midir (the ALSA sequencer on Linux, CoreMIDI on macOS and the
+//! Windows multimedia API) and are picked by name, since their numbers change as devices come and
+//! go. An action opens its port for each message it sends.
+
+use std::sync::Arc;
+
+use actions::MidiMessage;
+use anyhow::Context;
+use midir::{Ignore, MidiInput, MidiOutput};
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use storage::midi_triggers::{self, MidiTrigger};
+use storage::settings::MidiSettings;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+use crate::{start_sequence, EngineNotification, Shared};
+
+/// Name of this app's MIDI client, as other programs list it.
+const CLIENT_NAME: &str = "RiverDeck-Redux";
+
+/// Names of the output ports `midi` actions can send to.
+pub fn output_ports() -> anyhow::Result<Vec<String>> {
+    let output = MidiOutput::new(CLIENT_NAME).context("MIDI is not available")?;
+    let ports = output.ports();
+    Ok(ports
+        .iter()
+        .filter_map(|p| output.port_name(p).ok())
+        .collect())
+}
+
+/// Names of the input ports MIDI triggers can listen to.
+pub fn input_ports() -> anyhow::Result<Vec<String>> {
+    let input = MidiInput::new(CLIENT_NAME).context("MIDI is not available")?;
+    let ports = input.ports();
+    Ok(ports
+        .iter()
+        .filter_map(|p| input.port_name(p).ok())
+        .collect())
+}
+
+/// Index of the first of `names` containing `wanted`, case-insensitively; empty matches the
+/// first port.
+fn find_port(names: &[String], wanted: &str) -> anyhow::Result<usize> {
+    let wanted = wanted.trim().to_lowercase();
+    names
+        .iter()
+        .position(|n| n.to_lowercase().contains(&wanted))
+        .with_context(|| match wanted.as_str() {
+            "" => "no MIDI port found".to_string(),
+            _ => format!("no MIDI port matches \"{wanted}\""),
+        })
+}
+
+/// Send `message` to the output port `port` names.
+pub async fn send(port: &str, message: MidiMessage) -> anyhow::Result<()> {
+    let port = port.to_string();
+    tokio::task::spawn_blocking(move || {
+        let output = MidiOutput::new(CLIENT_NAME).context("MIDI is not available")?;
+        let ports = output.ports();
+        let names: Vec<String> = ports
+            .iter()
+            .map(|p| output.port_name(p).unwrap_or_default())
+            .collect();
+        let index = find_port(&names, &port)?;
+        let name = &names[index];
+        let mut connection = output
+            .connect(&ports[index], "riverdeck-out")
+            .map_err(|e| anyhow::anyhow!("cannot open MIDI port {name}: {e}"))?;
+        connection
+            .send(&message.bytes())
+            .with_context(|| format!("cannot send to MIDI port {name}"))
+    })
+    .await?
+}
+
+/// Listen to the input port `settings` names until the task is aborted; failures are reported
+/// to the UI.
+pub(crate) fn spawn(shared: Arc<Shared>, settings: MidiSettings) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = listen(&shared, &settings.input_port).await {
+            error!(error = %e, "MIDI listener stopped");
+            shared.emit(EngineNotification::ActionFailed(format!("[MIDI] {e:#}")));
+        }
+    })
+}
+
+async fn listen(shared: &Arc<Shared>, port: &str) -> anyhow::Result<()> {
+    let mut input = MidiInput::new(CLIENT_NAME).context("MIDI is not available")?;
+    // Clock, active sensing and SysEx fire nothing.
+    input.ignore(Ignore::All);
+    let ports = input.ports();
+    let names: Vec<String> = ports
+        .iter()
+        .map(|p| input.port_name(p).unwrap_or_default())
+        .collect();
+    let index = find_port(&names, port)?;
+
+    let (tx, mut messages) = mpsc::unbounded_channel();
+    // Messages arrive as long as the connection is held.
+    let _connection = input
+        .connect(
+            &ports[index],
+            "riverdeck-in",
+            move |_, bytes, _| {
+                let _ = tx.send(bytes.to_vec());
+            },
+            (),
+        )
+        .map_err(|e| anyhow::anyhow!("cannot open MIDI port {}: {e}", names[index]))?;
+    info!(port = %names[index], "listening to MIDI");
+
+    while let Some(bytes) = messages.recv().await {
+        let Some(event) = parse(&bytes) else {
+            continue;
+        };
+        let fired: Vec<MidiTrigger> = {
+            let state = shared.state();
+            if state.paused {
+                continue;
+            }
+            let triggers = state.midi_triggers.iter();
+            triggers.filter(|t| fires(t, &event)).cloned().collect()
+        };
+        for t in fired {
+            start_sequence(
+                shared,
+                InvocationControl::MidiTrigger { id: t.id },
+                event.clone(),
+                &t.action,
+            );
+        }
+    }
+    anyhow::bail!("the MIDI port {} closed", names[index])
+}
+
+/// The note played or controller set above 0 in `bytes`; other messages fire nothing.
+fn parse(bytes: &[u8]) -> Option<InvocationEvent> {
+    let &[status, number, value] = bytes else {
+        return None;
+    };
+    let channel = (status & 0x0F) + 1;
+    match status & 0xF0 {
+        0x90 if value > 0 => Some(InvocationEvent::MidiNote {
+            channel,
+            note: number,
+            velocity: value,
+        }),
+        0xB0 if value > 0 => Some(InvocationEvent::MidiControlChange {
+            channel,
+            controller: number,
+            value,
+        }),
+        _ => None,
+    }
+}
+
+fn fires(trigger: &MidiTrigger, event: &InvocationEvent) -> bool {
+    let (input, channel, number) = match *event {
+        InvocationEvent::MidiNote { channel, note, .. } => {
+            (midi_triggers::MidiInput::Note, channel, note)
+        }
+        InvocationEvent::MidiControlChange {
+            channel,
+            controller,
+            ..
+        } => (midi_triggers::MidiInput::ControlChange, channel, controller),
+        _ => return false,
+    };
+    trigger.enabled
+        && trigger.input == input
+        && trigger.number == number
+        && trigger.channel.map_or(true, |c| c == channel)
+}
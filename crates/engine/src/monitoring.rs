@@ -0,0 +1,299 @@
+//! Shared sampler of the system metrics shown by `system_monitoring` bindings.
+//!
+//! One background thread samples CPU, memory, load, per-interface network traffic, per-mount
+//! disk usage, sensor temperatures and GPU utilization and memory, keeps a short history for
+//! graphs and sends every sample to subscribers, so every display of a metric reads the same
+//! numbers instead of polling `sysinfo` on its own.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use actions::MonitorKind;
+use sysinfo::{
+    Components, CpuRefreshKind, DiskRefreshKind, Disks, MemoryRefreshKind, Networks, RefreshKind,
+    System,
+};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::gpu::GpuProbe;
+pub use crate::gpu::GpuStats;
+
+/// Sampling interval until [`MetricsSampler::set_interval`] is called.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+/// Shortest interval [`MetricsSampler::set_interval`] accepts.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+/// Samples kept for graphs; a minute at the default interval.
+pub const HISTORY_LEN: usize = 120;
+
+/// Mount point of [`MonitorKind::DiskUsage`] without one.
+#[cfg(windows)]
+const DEFAULT_MOUNT: &str = "C:\\";
+#[cfg(not(windows))]
+const DEFAULT_MOUNT: &str = "/";
+
+/// Bytes received and sent per second.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetworkRate {
+    pub rx: f64,
+    pub tx: f64,
+}
+
+/// Space of a mounted filesystem, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub used: u64,
+    pub total: u64,
+}
+
+/// Every metric at one point in time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sample {
+    /// Average over all CPUs.
+    pub cpu_percent: f32,
+    pub cpus: usize,
+    pub mem_used: u64,
+    pub mem_total: u64,
+    /// One, five and fifteen minute load averages.
+    pub load: (f64, f64, f64),
+    /// By interface name.
+    pub networks: BTreeMap<String, NetworkRate>,
+    /// By mount point.
+    pub disks: BTreeMap<String, DiskSpace>,
+    /// Degrees Celsius, by sensor label.
+    pub temperatures: BTreeMap<String, f32>,
+    /// NVIDIA GPUs first, then AMD ones.
+    pub gpus: Vec<GpuStats>,
+}
+
+impl Sample {
+    /// Value of `kind`: percent for CPU and GPU usage, bytes for memory, disks and VRAM, bytes
+    /// per second for network traffic and degrees Celsius for temperatures. `None` when the
+    /// interface, mount point, sensor or GPU does not exist.
+    pub fn value(&self, kind: &MonitorKind) -> Option<f64> {
+        match kind {
+            MonitorKind::Cpu => Some(f64::from(self.cpu_percent)),
+            MonitorKind::Memory => Some(self.mem_used as f64),
+            MonitorKind::LoadAverage => Some(self.load.0),
+            MonitorKind::NetworkRx { interface } => self.network(interface.as_deref(), |r| r.rx),
+            MonitorKind::NetworkTx { interface } => self.network(interface.as_deref(), |r| r.tx),
+            MonitorKind::DiskUsage { mount } => self.disk(mount.as_deref()).map(|d| d.used as f64),
+            MonitorKind::Temperature { sensor } => match sensor {
+                Some(label) => self.temperatures.get(label).copied().map(f64::from),
+                None => self
+                    .temperatures
+                    .values()
+                    .copied()
+                    .reduce(f32::max)
+                    .map(f64::from),
+            },
+            MonitorKind::GpuUsage { gpu } => self.gpu(*gpu).map(|g| f64::from(g.utilization)),
+            MonitorKind::GpuMemory { gpu } => self.gpu(*gpu).map(|g| g.vram_used as f64),
+        }
+    }
+
+    /// Value at which a graph of `kind` is full; `None` for network traffic, which has no
+    /// upper bound and is scaled to the samples shown instead.
+    pub fn full_scale(&self, kind: &MonitorKind) -> Option<f64> {
+        match kind {
+            MonitorKind::Cpu => Some(100.0),
+            MonitorKind::Memory => Some(self.mem_total as f64),
+            MonitorKind::LoadAverage => Some(self.cpus.max(1) as f64),
+            MonitorKind::NetworkRx { .. } | MonitorKind::NetworkTx { .. } => None,
+            MonitorKind::DiskUsage { mount } => self.disk(mount.as_deref()).map(|d| d.total as f64),
+            MonitorKind::Temperature { .. } => Some(100.0),
+            MonitorKind::GpuUsage { .. } => Some(100.0),
+            MonitorKind::GpuMemory { gpu } => self.gpu(*gpu).map(|g| g.vram_total as f64),
+        }
+    }
+
+    /// Traffic of `interface`, or summed over every interface but loopback.
+    fn network(&self, interface: Option<&str>, rate: impl Fn(&NetworkRate) -> f64) -> Option<f64> {
+        match interface {
+            Some(name) => self.networks.get(name).map(rate),
+            None => Some(
+                self.networks
+                    .iter()
+                    .filter(|(name, _)| !name.starts_with("lo"))
+                    .map(|(_, r)| rate(r))
+                    .sum(),
+            ),
+        }
+    }
+
+    /// Space of the filesystem mounted at `mount`, or at the root (`C:\` on Windows).
+    pub fn disk(&self, mount: Option<&str>) -> Option<DiskSpace> {
+        self.disks.get(mount.unwrap_or(DEFAULT_MOUNT)).copied()
+    }
+
+    /// The GPU at `index` in [`Sample::gpus`], or the first one.
+    pub fn gpu(&self, index: Option<u32>) -> Option<&GpuStats> {
+        self.gpus.get(index.unwrap_or(0) as usize)
+    }
+}
+
+struct State {
+    interval: Duration,
+    /// Oldest first.
+    history: VecDeque<Sample>,
+    subscribers: Vec<mpsc::UnboundedSender<Sample>>,
+}
+
+/// Handle to the sampler. Cheap to clone; the thread stops once every handle is dropped.
+#[derive(Clone)]
+pub struct MetricsSampler {
+    state: Arc<Mutex<State>>,
+}
+
+impl MetricsSampler {
+    /// Start sampling every [`DEFAULT_INTERVAL`].
+    pub fn spawn() -> Self {
+        let state = Arc::new(Mutex::new(State {
+            interval: DEFAULT_INTERVAL,
+            history: VecDeque::new(),
+            subscribers: vec![],
+        }));
+        let weak = Arc::downgrade(&state);
+        let spawned = std::thread::Builder::new()
+            .name("metrics-sampler".to_string())
+            .spawn(move || run(weak));
+        if let Err(e) = spawned {
+            warn!(error = %e, "failed to start the metrics sampler");
+        }
+        Self { state }
+    }
+
+    /// Receive every sample from now on.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<Sample> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        lock(&self.state).subscribers.push(tx);
+        rx
+    }
+
+    /// Sample every `interval` (at least [`MIN_INTERVAL`]), starting after the next sample.
+    pub fn set_interval(&self, interval: Duration) {
+        lock(&self.state).interval = interval.max(MIN_INTERVAL);
+    }
+
+    /// The last [`HISTORY_LEN`] samples, oldest first.
+    pub fn history(&self) -> Vec<Sample> {
+        lock(&self.state).history.iter().cloned().collect()
+    }
+}
+
+fn lock(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    state.lock().expect("metrics sampler mutex poisoned")
+}
+
+fn run(state: Weak<Mutex<State>>) {
+    let mut collector = Collector::new();
+    loop {
+        let Some(interval) = state.upgrade().map(|s| lock(&s).interval) else {
+            return;
+        };
+        std::thread::sleep(interval);
+        let sample = collector.sample();
+
+        let Some(shared) = state.upgrade() else {
+            return;
+        };
+        let mut shared = lock(&shared);
+        shared.history.push_back(sample.clone());
+        if shared.history.len() > HISTORY_LEN {
+            shared.history.pop_front();
+        }
+        shared
+            .subscribers
+            .retain(|tx| tx.send(sample.clone()).is_ok());
+    }
+}
+
+struct Collector {
+    system: System,
+    networks: Networks,
+    disks: Disks,
+    components: Components,
+    gpus: GpuProbe,
+    /// When the network counters were last read.
+    last: Instant,
+}
+
+impl Collector {
+    fn new() -> Self {
+        Self {
+            system: System::new(),
+            networks: Networks::new_with_refreshed_list(),
+            disks: Disks::new_with_refreshed_list_specifics(
+                DiskRefreshKind::nothing().with_storage(),
+            ),
+            components: Components::new_with_refreshed_list(),
+            gpus: GpuProbe::new(),
+            last: Instant::now(),
+        }
+    }
+
+    fn sample(&mut self) -> Sample {
+        let refresh = RefreshKind::nothing()
+            .with_cpu(CpuRefreshKind::everything())
+            .with_memory(MemoryRefreshKind::everything());
+        self.system.refresh_specifics(refresh);
+        let cpus = self.system.cpus();
+        let cpu_percent = if cpus.is_empty() {
+            0.0
+        } else {
+            cpus.iter().map(|c| c.cpu_usage()).sum::<f32>() / (cpus.len() as f32)
+        };
+
+        // The counters hold the traffic since the last refresh.
+        self.networks.refresh(true);
+        let secs = self.last.elapsed().as_secs_f64().max(0.001);
+        self.last = Instant::now();
+        let networks = self
+            .networks
+            .iter()
+            .map(|(name, data)| {
+                let rate = NetworkRate {
+                    rx: data.received() as f64 / secs,
+                    tx: data.transmitted() as f64 / secs,
+                };
+                (name.clone(), rate)
+            })
+            .collect();
+
+        self.disks
+            .refresh_specifics(true, DiskRefreshKind::nothing().with_storage());
+        let disks = self
+            .disks
+            .iter()
+            .map(|disk| {
+                let space = DiskSpace {
+                    used: disk.total_space().saturating_sub(disk.available_space()),
+                    total: disk.total_space(),
+                };
+                (disk.mount_point().to_string_lossy().into_owned(), space)
+            })
+            .collect();
+
+        self.components.refresh(true);
+        let temperatures = self
+            .components
+            .iter()
+            .filter_map(|c| Some((c.label().to_string(), c.temperature()?)))
+            .filter(|(_, celsius)| celsius.is_finite())
+            .collect();
+
+        let load = System::load_average();
+        Sample {
+            cpu_percent,
+            cpus: cpus.len(),
+            mem_used: self.system.used_memory(),
+            mem_total: self.system.total_memory(),
+            load: (load.one, load.five, load.fifteen),
+            networks,
+            disks,
+            temperatures,
+            gpus: self.gpus.sample(),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Desktop notifications, shown through the platform's own tools: `notify-send` (libnotify) on
+//! Linux, `osascript` on macOS and a PowerShell tray balloon, shown as a toast, on Windows.
+//!
+//! The title and body reach the macOS and Windows scripts through environment variables, so
+//! they need no quoting.
+//!
+//! Notifications shown by any app can be mirrored onto a key. On Linux they are read from the
+//! session bus with `dbus-monitor`, which sees the `Notify` calls sent to the notification
+//! server; other platforms have no notification monitor yet.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use tokio::process::Command;
+use tracing::error;
+
+use crate::{EngineNotification, Shared};
+
+/// Name notifications are shown under.
+const APP_NAME: &str = "RiverDeck-Redux";
+
+/// How long the Windows balloon stays up; the script waits this long before removing its icon.
+#[cfg(windows)]
+const BALLOON_MS: u32 = 5000;
+
+/// Show a notification with `title` and `body`.
+pub async fn show(title: &str, body: &str) -> anyhow::Result<()> {
+    let status = command(title, body)
+        .status()
+        .await
+        .context("failed to run the notification tool")?;
+    if !status.success() {
+        anyhow::bail!("[Notification] Showing the notification failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn command(title: &str, body: &str) -> Command {
+    let mut cmd = Command::new("notify-send");
+    cmd.args(["--app-name", APP_NAME, "--", title, body]);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Command {
+    let script = format!(
+        "display notification (system attribute \"RIVERDECK_NOTIFY_BODY\") \
+         with title (system attribute \"RIVERDECK_NOTIFY_TITLE\") subtitle \"{APP_NAME}\""
+    );
+    let mut cmd = Command::new("osascript");
+    cmd.args(["-e", &script]).envs(script_env(title, body));
+    cmd
+}
+
+#[cfg(windows)]
+fn command(title: &str, body: &str) -> Command {
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.Text = '{APP_NAME}'; \
+         $n.Visible = $true; \
+         $title = $env:RIVERDECK_NOTIFY_TITLE; \
+         $body = $env:RIVERDECK_NOTIFY_BODY; \
+         $n.ShowBalloonTip({BALLOON_MS}, $title, $body, 'Info'); \
+         Start-Sleep -Milliseconds {BALLOON_MS}; \
+         $n.Dispose()"
+    );
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .envs(script_env(title, body));
+    cmd
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn script_env<'a>(title: &'a str, body: &'a str) -> [(&'static str, &'a str); 2] {
+    [
+        ("RIVERDECK_NOTIFY_TITLE", title),
+        ("RIVERDECK_NOTIFY_BODY", body),
+    ]
+}
+
+/// A notification some app showed on the desktop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopNotification {
+    pub app: String,
+    pub summary: String,
+    pub body: String,
+}
+
+/// Report the desktop's notifications until the task is aborted; failures are reported to the
+/// UI.
+pub(crate) fn spawn_mirror(shared: Arc<Shared>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = monitor::watch(&shared).await {
+            error!(error = %e, "notification mirroring stopped");
+            shared.emit(EngineNotification::ActionFailed(format!(
+                "[Notification mirroring] {e:#}"
+            )));
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod monitor {
+    use std::process::Stdio;
+
+    use anyhow::Context;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::Command;
+    use tracing::debug;
+
+    use super::DesktopNotification;
+    use crate::{EngineNotification, Shared};
+
+    const MATCH_RULE: &str =
+        "type='method_call',interface='org.freedesktop.Notifications',member='Notify'";
+
+    pub(super) async fn watch(shared: &Shared) -> anyhow::Result<()> {
+        let mut child = Command::new("dbus-monitor")
+            .args(["--session", MATCH_RULE])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            // Aborting the task drops the child, which stops it.
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run dbus-monitor")?;
+        let stdout = child.stdout.take().context("dbus-monitor has no output")?;
+        let mut lines = BufReader::new(stdout).lines();
+        let mut parser = NotifyParser::default();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(n) = parser.line(&line) {
+                debug!(app = %n.app, summary = %n.summary, "desktop notification");
+                shared.emit(EngineNotification::DesktopNotification(n));
+            }
+        }
+        let status = child.wait().await?;
+        anyhow::bail!("dbus-monitor exited ({status})")
+    }
+
+    /// Picks the `Notify` calls out of `dbus-monitor` output.
+    #[derive(Default)]
+    struct NotifyParser {
+        /// String arguments of the `Notify` call being read; `None` outside one.
+        args: Option<Vec<String>>,
+        /// A string argument that goes on over the next lines.
+        open: Option<String>,
+    }
+
+    impl NotifyParser {
+        fn line(&mut self, line: &str) -> Option<DesktopNotification> {
+            if let Some(mut value) = self.open.take() {
+                value.push('\n');
+                match line.strip_suffix('"') {
+                    Some(end) => {
+                        value.push_str(end);
+                        return self.push(value);
+                    }
+                    None => {
+                        value.push_str(line);
+                        self.open = Some(value);
+                        return None;
+                    }
+                }
+            }
+            // Headers start at the margin, the call's arguments three spaces in; deeper lines
+            // belong to the actions and hints.
+            if !line.starts_with(' ') {
+                let notify = line.starts_with("method call") && line.contains("member=Notify");
+                self.args = notify.then(Vec::new);
+                return None;
+            }
+            self.args.as_ref()?;
+            let value = line.strip_prefix("   string \"")?;
+            match value.strip_suffix('"') {
+                Some(value) => self.push(value.to_string()),
+                None => {
+                    self.open = Some(value.to_string());
+                    None
+                }
+            }
+        }
+
+        /// Add a string argument; the fourth completes the notification.
+        fn push(&mut self, value: String) -> Option<DesktopNotification> {
+            let args = self.args.as_mut()?;
+            args.push(value);
+            if args.len() < 4 {
+                return None;
+            }
+            // app_name, app_icon, summary and body; replaces_id between them is a number.
+            let [app, _icon, summary, body] = <[String; 4]>::try_from(self.args.take()?).ok()?;
+            Some(DesktopNotification { app, summary, body })
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod monitor {
+    use crate::Shared;
+
+    pub(super) async fn watch(_shared: &Shared) -> anyhow::Result<()> {
+        anyhow::bail!("mirroring notifications is only supported on Linux so far")
+    }
+}
@@ -0,0 +1,293 @@
+//! OSC (Open Sound Control) over UDP: `osc` actions send a message to a host and port, and OSC
+//! triggers run actions for the messages sent to this app (see [`storage::osc_triggers`]).
+//!
+//! Messages are encoded and decoded here, following OSC 1.0: the address, a type tag string and
+//! the arguments, each padded to four bytes. Received bundles are unpacked and their messages
+//! handled right away, whatever their time tag.
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use anyhow::Context;
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use serde_json::Value;
+use storage::osc_triggers::OscTrigger;
+use storage::settings::OscSettings;
+use tokio::net::UdpSocket;
+use tracing::{debug, error, info};
+
+use crate::{start_sequence, EngineNotification, Shared};
+
+/// An argument of a sent message.
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    String(String),
+}
+
+/// Send a message to `address` at `target` (`host:port`), with `args` as an `osc` action
+/// writes them.
+pub async fn send(target: &str, address: &str, args: &str) -> anyhow::Result<()> {
+    let address = address.trim();
+    if !address.starts_with('/') {
+        anyhow::bail!("OSC address \"{address}\" must start with /");
+    }
+    let packet = encode(address, &parse_args(args)?);
+    let to = tokio::net::lookup_host(target.trim())
+        .await
+        .with_context(|| format!("invalid OSC target \"{target}\" (expected host:port)"))?
+        .next()
+        .with_context(|| format!("{target} has no address"))?;
+    let from = match to {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(from).await?;
+    socket
+        .send_to(&packet, to)
+        .await
+        .with_context(|| format!("cannot send to {to}"))?;
+    Ok(())
+}
+
+/// Split `args` at spaces outside double quotes and type each one: quoted text is a string,
+/// then integers, floats (with a `.`), `true` and `false`; anything else is a string.
+fn parse_args(args: &str) -> anyhow::Result<Vec<Arg>> {
+    let mut parsed = vec![];
+    let mut chars = args.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => s.extend(chars.next()),
+                    Some(c) => s.push(c),
+                    None => anyhow::bail!("unterminated quote in OSC arguments"),
+                }
+            }
+            parsed.push(Arg::String(s));
+            continue;
+        }
+        let mut word = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            word.push(c);
+        }
+        parsed.push(if let Ok(i) = word.parse() {
+            Arg::Int(i)
+        } else if let (true, Ok(f)) = (word.contains('.'), word.parse()) {
+            Arg::Float(f)
+        } else {
+            match word.as_str() {
+                "true" => Arg::Bool(true),
+                "false" => Arg::Bool(false),
+                _ => Arg::String(word),
+            }
+        });
+    }
+    Ok(parsed)
+}
+
+fn encode(address: &str, args: &[Arg]) -> Vec<u8> {
+    let mut packet = vec![];
+    push_string(&mut packet, address);
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|a| match a {
+            Arg::Int(_) => 'i',
+            Arg::Float(_) => 'f',
+            Arg::Bool(true) => 'T',
+            Arg::Bool(false) => 'F',
+            Arg::String(_) => 's',
+        }))
+        .collect();
+    push_string(&mut packet, &tags);
+    for arg in args {
+        match arg {
+            Arg::Int(i) => packet.extend(i.to_be_bytes()),
+            Arg::Float(f) => packet.extend(f.to_be_bytes()),
+            Arg::Bool(_) => {}
+            Arg::String(s) => push_string(&mut packet, s),
+        }
+    }
+    packet
+}
+
+/// Append `s` null-terminated and padded to a multiple of four bytes.
+fn push_string(packet: &mut Vec<u8>, s: &str) {
+    packet.extend(s.as_bytes());
+    packet.push(0);
+    while packet.len() % 4 != 0 {
+        packet.push(0);
+    }
+}
+
+/// Listen for messages on the port `settings` names until the task is aborted; failures are
+/// reported to the UI.
+pub(crate) fn spawn(shared: Arc<Shared>, settings: OscSettings) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = listen(&shared, &settings).await {
+            error!(error = %e, "OSC listener stopped");
+            shared.emit(EngineNotification::ActionFailed(format!("[OSC] {e:#}")));
+        }
+    })
+}
+
+async fn listen(shared: &Arc<Shared>, settings: &OscSettings) -> anyhow::Result<()> {
+    let ip = if settings.listen_on_network {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let addr = SocketAddr::from((ip, settings.port));
+    let socket = UdpSocket::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+    info!(%addr, "OSC listener started");
+
+    let mut buf = vec![0u8; 65536];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        let mut messages = vec![];
+        if !decode(&buf[..len], &mut messages) {
+            debug!(%from, "ignoring malformed OSC packet");
+        }
+        for (address, args) in messages {
+            fire(shared, address, args);
+        }
+    }
+}
+
+/// Run the actions of the enabled triggers matching `address`.
+fn fire(shared: &Arc<Shared>, address: String, args: Vec<Value>) {
+    let fired: Vec<OscTrigger> = {
+        let state = shared.state();
+        if state.paused {
+            return;
+        }
+        let triggers = state.osc_triggers.iter();
+        triggers
+            .filter(|t| t.enabled && t.matches(&address))
+            .cloned()
+            .collect()
+    };
+    for t in fired {
+        let variable = t.variable.trim();
+        if !variable.is_empty() {
+            let value = args.first().map(|v| match v {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            });
+            if let Err(e) = shared.variables.set(variable, value) {
+                shared.emit(EngineNotification::ActionFailed(format!(
+                    "[OSC] cannot set variable {variable}: {e:#}"
+                )));
+            }
+        }
+        let event = InvocationEvent::OscReceived {
+            address: address.clone(),
+            args: args.clone(),
+        };
+        start_sequence(
+            shared,
+            InvocationControl::OscTrigger { id: t.id },
+            event,
+            &t.action,
+        );
+    }
+}
+
+/// Add the messages in `packet` (a message or a bundle) to `messages`; false if it is malformed.
+fn decode(packet: &[u8], messages: &mut Vec<(String, Vec<Value>)>) -> bool {
+    let mut r = Reader { bytes: packet };
+    if packet.starts_with(b"#bundle\0") {
+        // Skip the tag and the time tag, then each element is a size and a packet.
+        if r.take(16).is_none() {
+            return false;
+        }
+        while !r.bytes.is_empty() {
+            let Some(size) = r.int().and_then(|s| usize::try_from(s).ok()) else {
+                return false;
+            };
+            match r.take(size) {
+                Some(element) if decode(element, messages) => {}
+                _ => return false,
+            }
+        }
+        return true;
+    }
+    let Some(address) = r.string() else {
+        return false;
+    };
+    // Packets from old senders may have no type tags, and so no arguments we can read.
+    let tags = match r.string() {
+        Some(tags) if tags.starts_with(',') => tags,
+        _ => String::from(","),
+    };
+    let mut args = vec![];
+    for tag in tags.chars().skip(1) {
+        let arg = match tag {
+            'i' => r.int().map(Value::from),
+            'h' => r.take(8).map(|b| Value::from(i64::from_be_bytes(array(b)))),
+            'f' => r.take(4).map(|b| Value::from(f32::from_be_bytes(array(b)))),
+            'd' => r.take(8).map(|b| Value::from(f64::from_be_bytes(array(b)))),
+            's' | 'S' => r.string().map(Value::from),
+            'b' => r.blob().map(Value::from),
+            'T' => Some(Value::Bool(true)),
+            'F' => Some(Value::Bool(false)),
+            'N' | 'I' => Some(Value::Null),
+            _ => None,
+        };
+        let Some(arg) = arg else {
+            return false;
+        };
+        args.push(arg);
+    }
+    messages.push((address, args));
+    true
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes.try_into().expect("taken with the array's length")
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if n > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn int(&mut self) -> Option<i32> {
+        self.take(4).map(|b| i32::from_be_bytes(array(b)))
+    }
+
+    /// A null-terminated string padded to four bytes.
+    fn string(&mut self) -> Option<String> {
+        let end = self.bytes.iter().position(|&b| b == 0)?;
+        let s = String::from_utf8_lossy(&self.bytes[..end]).into_owned();
+        self.take((end + 4) & !3)?;
+        Some(s)
+    }
+
+    /// A size and that many bytes, padded to four bytes.
+    fn blob(&mut self) -> Option<Vec<u8>> {
+        let size = usize::try_from(self.int()?).ok()?;
+        let blob = self.take(size)?.to_vec();
+        self.take((4 - size % 4) % 4)?;
+        Some(blob)
+    }
+}
@@ -0,0 +1,869 @@
+//! What built-in actions need from the host OS: a shell to run commands in and a way to send
+//! key presses. Both are picked when an action runs, so the same profiles work on every OS.
+//!
+//! Commands run in `bash -lc` on Linux and macOS and `cmd /C` on Windows; `RIVERDECK_SHELL`
+//! picks another one (`bash`, `cmd`, `powershell`, `pwsh` or `osascript` for AppleScript).
+//!
+//! Keyboard input goes through an external tool (`RIVERDECK_KEYBOARD_TOOL`, default `wtype`).
+//! Windows (`SendInput`) and macOS (Core Graphics events, which need the Accessibility
+//! permission) send it themselves unless a tool is configured, and read `keys` as wtype
+//! arguments too (`-k Return`, `-M ctrl v -m ctrl`), so bindings keep working.
+//!
+//! Power commands use `loginctl` and `systemctl` on Linux, `pmset` and System Events on
+//! macOS, and `rundll32` and `shutdown` on Windows.
+//!
+//! Wireless radios are switched with NetworkManager (`nmcli`) for Wi-Fi and `rfkill` for
+//! Bluetooth, and for Wi-Fi too when NetworkManager is not installed. Only Linux has them so
+//! far.
+
+use actions::{PowerCommand, RadioKind};
+use anyhow::Context;
+use tokio::process::Command;
+use tracing::warn;
+
+/// Environment variable naming the shell commands run in.
+pub const SHELL_ENV: &str = "RIVERDECK_SHELL";
+
+/// Environment variable naming the keyboard tool, with any leading arguments.
+pub const KEYBOARD_TOOL_ENV: &str = "RIVERDECK_KEYBOARD_TOOL";
+
+#[cfg(not(any(windows, target_os = "macos")))]
+const DEFAULT_KEYBOARD_TOOL: &str = "wtype";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// `bash -lc`, so the login profile's `PATH` applies.
+    Bash,
+    /// `cmd /C`.
+    Cmd,
+    /// Windows PowerShell.
+    PowerShell,
+    /// PowerShell 7 and later.
+    Pwsh,
+    /// AppleScript, run with `osascript -e` (macOS).
+    AppleScript,
+}
+
+impl Shell {
+    /// The shell configured with `RIVERDECK_SHELL`, or the platform's.
+    pub fn current() -> Self {
+        let configured = std::env::var(SHELL_ENV).unwrap_or_default();
+        match configured.trim().to_ascii_lowercase().as_str() {
+            "bash" => Shell::Bash,
+            "cmd" => Shell::Cmd,
+            "powershell" => Shell::PowerShell,
+            "pwsh" => Shell::Pwsh,
+            "osascript" | "applescript" => Shell::AppleScript,
+            other => {
+                if !other.is_empty() {
+                    warn!(
+                        shell = other,
+                        "unknown {SHELL_ENV}, using the default shell"
+                    );
+                }
+                if cfg!(windows) {
+                    Shell::Cmd
+                } else {
+                    Shell::Bash
+                }
+            }
+        }
+    }
+
+    /// A command running `script` in this shell.
+    pub fn command(self, script: &str) -> Command {
+        let (program, args): (&str, &[&str]) = match self {
+            Shell::Bash => ("bash", &["-lc"]),
+            Shell::Cmd => ("cmd", &["/C"]),
+            Shell::PowerShell => ("powershell", &["-NoProfile", "-NonInteractive", "-Command"]),
+            Shell::Pwsh => ("pwsh", &["-NoProfile", "-NonInteractive", "-Command"]),
+            Shell::AppleScript => ("osascript", &["-e"]),
+        };
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if self == Shell::Cmd {
+            // cmd parses the rest of its command line itself and doesn't understand the
+            // quoting used for other programs' arguments.
+            #[cfg(windows)]
+            cmd.raw_arg(script);
+            #[cfg(not(windows))]
+            cmd.arg(script);
+        } else {
+            cmd.arg(script);
+        }
+        cmd
+    }
+}
+
+/// How key presses are sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keyboard {
+    /// By running a tool: the program and its leading arguments.
+    Tool(Vec<String>),
+    /// With the platform's own input: `SendInput` on Windows, Core Graphics events on macOS.
+    #[cfg(any(windows, target_os = "macos"))]
+    Native,
+}
+
+impl Keyboard {
+    /// The tool configured with `RIVERDECK_KEYBOARD_TOOL`, or the platform's way.
+    pub fn current() -> anyhow::Result<Self> {
+        let Some(tool) = std::env::var(KEYBOARD_TOOL_ENV)
+            .ok()
+            .filter(|t| !t.trim().is_empty())
+        else {
+            return Ok(Self::native());
+        };
+        let argv = shlex::split(&tool)
+            .filter(|argv| !argv.is_empty())
+            .with_context(|| format!("invalid {KEYBOARD_TOOL_ENV}: {tool}"))?;
+        Ok(Keyboard::Tool(argv))
+    }
+
+    /// The way used when no tool is configured.
+    fn native() -> Self {
+        #[cfg(any(windows, target_os = "macos"))]
+        {
+            Keyboard::Native
+        }
+        #[cfg(not(any(windows, target_os = "macos")))]
+        {
+            Keyboard::Tool(vec![DEFAULT_KEYBOARD_TOOL.to_string()])
+        }
+    }
+}
+
+/// Lock the session, suspend or shut down.
+pub async fn power(command: PowerCommand) -> anyhow::Result<()> {
+    let (program, args) = power_command(command);
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .await
+        .with_context(|| format!("failed to run {program}"))?;
+    if !status.success() {
+        anyhow::bail!("[Power] {program} failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn power_command(command: PowerCommand) -> (&'static str, &'static [&'static str]) {
+    match command {
+        PowerCommand::Lock => ("loginctl", &["lock-session"]),
+        PowerCommand::Suspend => ("systemctl", &["suspend"]),
+        PowerCommand::Shutdown => ("systemctl", &["poweroff"]),
+    }
+}
+
+/// The Lock Screen shortcut, Control-Command-Q.
+#[cfg(target_os = "macos")]
+const LOCK_SCRIPT: &str = "tell application \"System Events\" to keystroke \"q\" \
+                           using {control down, command down}";
+
+#[cfg(target_os = "macos")]
+fn power_command(command: PowerCommand) -> (&'static str, &'static [&'static str]) {
+    match command {
+        PowerCommand::Lock => ("osascript", &["-e", LOCK_SCRIPT]),
+        PowerCommand::Suspend => ("pmset", &["sleepnow"]),
+        PowerCommand::Shutdown => (
+            "osascript",
+            &["-e", r#"tell application "System Events" to shut down"#],
+        ),
+    }
+}
+
+#[cfg(windows)]
+fn power_command(command: PowerCommand) -> (&'static str, &'static [&'static str]) {
+    match command {
+        PowerCommand::Lock => ("rundll32.exe", &["user32.dll,LockWorkStation"]),
+        PowerCommand::Suspend => ("rundll32.exe", &["powrprof.dll,SetSuspendState", "0,1,0"]),
+        PowerCommand::Shutdown => ("shutdown", &["/s", "/t", "0"]),
+    }
+}
+
+/// Whether `radio` is on.
+pub async fn radio_enabled(radio: RadioKind) -> anyhow::Result<bool> {
+    radios::enabled(radio).await
+}
+
+/// Turn `radio` on or off.
+pub async fn set_radio_enabled(radio: RadioKind, on: bool) -> anyhow::Result<()> {
+    radios::set_enabled(radio, on).await
+}
+
+#[cfg(target_os = "linux")]
+mod radios {
+    use std::process::Stdio;
+
+    use actions::RadioKind;
+    use anyhow::Context;
+    use tokio::process::Command;
+
+    pub(super) async fn enabled(radio: RadioKind) -> anyhow::Result<bool> {
+        if radio == RadioKind::Wifi {
+            match output("nmcli", &["radio", "wifi"]).await {
+                Ok(state) => return Ok(state == "enabled"),
+                Err(e) if !not_installed(&e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        // One `SOFT HARD` line per device, e.g. `unblocked blocked`.
+        let args = ["-n", "-o", "SOFT,HARD", "list", rfkill_type(radio)];
+        let devices = output("rfkill", &args).await?;
+        if devices.is_empty() {
+            anyhow::bail!("[Radio] No {} device found", radio_name(radio));
+        }
+        Ok(devices
+            .lines()
+            .any(|l| l.split_whitespace().all(|state| state == "unblocked")))
+    }
+
+    pub(super) async fn set_enabled(radio: RadioKind, on: bool) -> anyhow::Result<()> {
+        if radio == RadioKind::Wifi {
+            let state = if on { "on" } else { "off" };
+            match output("nmcli", &["radio", "wifi", state]).await {
+                Ok(_) => return Ok(()),
+                Err(e) if !not_installed(&e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        let action = if on { "unblock" } else { "block" };
+        output("rfkill", &[action, rfkill_type(radio)])
+            .await
+            .map(drop)
+    }
+
+    fn rfkill_type(radio: RadioKind) -> &'static str {
+        match radio {
+            RadioKind::Wifi => "wlan",
+            RadioKind::Bluetooth => "bluetooth",
+        }
+    }
+
+    fn radio_name(radio: RadioKind) -> &'static str {
+        match radio {
+            RadioKind::Wifi => "Wi-Fi",
+            RadioKind::Bluetooth => "Bluetooth",
+        }
+    }
+
+    fn not_installed(e: &anyhow::Error) -> bool {
+        e.downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+    }
+
+    /// Run `program` to completion and return its trimmed stdout; a non-zero exit is an error
+    /// with its stderr.
+    async fn output(program: &str, args: &[&str]) -> anyhow::Result<String> {
+        let out = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .with_context(|| format!("failed to run {program}"))?;
+        if !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            anyhow::bail!("[Radio] {program} failed: {}", stderr.trim());
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod radios {
+    use actions::RadioKind;
+
+    pub(super) async fn enabled(_radio: RadioKind) -> anyhow::Result<bool> {
+        anyhow::bail!("[Radio] Not supported on this platform yet")
+    }
+
+    pub(super) async fn set_enabled(_radio: RadioKind, _on: bool) -> anyhow::Result<()> {
+        anyhow::bail!("[Radio] Not supported on this platform yet")
+    }
+}
+
+/// Type `text` with the platform's own keyboard input.
+#[cfg(windows)]
+pub fn type_text(text: &str) -> anyhow::Result<()> {
+    wtype::Keys::type_text(&mut send_input::SendInputKeys, text)
+}
+
+/// Carry out wtype arguments with the platform's own keyboard input.
+#[cfg(windows)]
+pub fn send_keys(args: &[String]) -> anyhow::Result<()> {
+    wtype::send_keys(&mut send_input::SendInputKeys, args)
+}
+
+/// Type `text` with the platform's own keyboard input.
+#[cfg(target_os = "macos")]
+pub fn type_text(text: &str) -> anyhow::Result<()> {
+    wtype::Keys::type_text(&mut cg_event::CgEventKeys::new()?, text)
+}
+
+/// Carry out wtype arguments with the platform's own keyboard input.
+#[cfg(target_os = "macos")]
+pub fn send_keys(args: &[String]) -> anyhow::Result<()> {
+    wtype::send_keys(&mut cg_event::CgEventKeys::new()?, args)
+}
+
+/// wtype arguments, for the platforms that send keys themselves.
+#[cfg(any(windows, target_os = "macos"))]
+mod wtype {
+    use std::time::Duration;
+
+    use anyhow::Context;
+
+    /// A platform's keyboard input.
+    pub(super) trait Keys {
+        type Key: Copy + PartialEq;
+
+        /// A modifier by its wtype name (`shift`, `ctrl`, `alt`, `altgr`, `logo`, `capslock`).
+        fn modifier(&self, name: &str) -> anyhow::Result<Self::Key>;
+        /// A key by its X keysym name (e.g. `Return`), or a single character.
+        fn key(&self, name: &str) -> anyhow::Result<Self::Key>;
+        fn send(&mut self, key: Self::Key, down: bool) -> anyhow::Result<()>;
+        /// Type `text` as characters, whatever the keyboard layout.
+        fn type_text(&mut self, text: &str) -> anyhow::Result<()>;
+    }
+
+    enum Step<'a> {
+        Modifier {
+            name: &'a str,
+            down: bool,
+        },
+        /// A key pressed (`Some(true)`), released (`Some(false)`) or both.
+        Key {
+            name: &'a str,
+            down: Option<bool>,
+        },
+        Sleep(Duration),
+        Type(&'a str),
+    }
+
+    /// `-M`/`-m` press and release a modifier, `-k` presses a key, `-P`/`-p` press and release
+    /// one, `-s` waits that many milliseconds and anything else is typed.
+    fn parse(args: &[String]) -> anyhow::Result<Vec<Step<'_>>> {
+        let mut steps = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .map(String::as_str)
+                    .with_context(|| format!("{arg} needs a value"))
+            };
+            let step = match arg.as_str() {
+                "-M" | "-m" => Step::Modifier {
+                    name: value()?,
+                    down: arg == "-M",
+                },
+                "-k" => Step::Key {
+                    name: value()?,
+                    down: None,
+                },
+                "-P" | "-p" => Step::Key {
+                    name: value()?,
+                    down: Some(arg == "-P"),
+                },
+                "-s" => {
+                    let ms = value()?;
+                    let ms = ms.parse().with_context(|| format!("invalid sleep {ms}"))?;
+                    Step::Sleep(Duration::from_millis(ms))
+                }
+                // Only matters to wtype, which types too fast for some apps otherwise.
+                "-d" => {
+                    value()?;
+                    continue;
+                }
+                "--" => {
+                    steps.extend(args.by_ref().map(|text| Step::Type(text)));
+                    break;
+                }
+                text => Step::Type(text),
+            };
+            steps.push(step);
+        }
+        Ok(steps)
+    }
+
+    /// Carry out wtype `args` with `keys`. Nothing is sent for invalid arguments; modifiers
+    /// still held at the end (or after a failure) are released.
+    pub(super) fn send_keys<K: Keys>(keys: &mut K, args: &[String]) -> anyhow::Result<()> {
+        let steps = parse(args)?;
+        let mut held = Vec::new();
+        let result = steps.into_iter().try_for_each(|step| match step {
+            Step::Modifier { name, down } => {
+                let key = keys.modifier(name)?;
+                keys.send(key, down)?;
+                if down {
+                    held.push(key);
+                } else {
+                    held.retain(|&k| k != key);
+                }
+                Ok(())
+            }
+            Step::Key { name, down } => {
+                let key = keys.key(name)?;
+                match down {
+                    Some(down) => keys.send(key, down),
+                    None => keys.send(key, true).and_then(|()| keys.send(key, false)),
+                }
+            }
+            Step::Sleep(duration) => {
+                std::thread::sleep(duration);
+                Ok(())
+            }
+            // With a modifier held, `v` in `-M ctrl v` is a shortcut, so press the keys.
+            Step::Type(text) if !held.is_empty() => text.chars().try_for_each(|c| {
+                let key = keys.key(c.encode_utf8(&mut [0; 4]))?;
+                keys.send(key, true).and_then(|()| keys.send(key, false))
+            }),
+            Step::Type(text) => keys.type_text(text),
+        });
+        let released = held
+            .into_iter()
+            .rev()
+            .try_for_each(|key| keys.send(key, false));
+        result.and(released)
+    }
+}
+
+#[cfg(windows)]
+mod send_input {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS,
+        KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VIRTUAL_KEY, VK_APPS, VK_BACK,
+        VK_CAPITAL, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_HOME, VK_INSERT,
+        VK_LEFT, VK_LWIN, VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK,
+        VK_MEDIA_STOP, VK_MENU, VK_NEXT, VK_NUMLOCK, VK_PAUSE, VK_PRIOR, VK_RETURN, VK_RIGHT,
+        VK_RMENU, VK_SCROLL, VK_SHIFT, VK_SNAPSHOT, VK_SPACE, VK_TAB, VK_UP, VK_VOLUME_DOWN,
+        VK_VOLUME_MUTE, VK_VOLUME_UP,
+    };
+
+    use super::wtype::Keys;
+
+    /// One key as `SendInput` presses it.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(super) struct Key {
+        vk: VIRTUAL_KEY,
+        /// A UTF-16 code unit, for keys typed as a character.
+        unit: u16,
+        flags: KEYBD_EVENT_FLAGS,
+    }
+
+    impl Key {
+        fn input(self, up: bool) -> INPUT {
+            let flags = if up {
+                self.flags | KEYEVENTF_KEYUP
+            } else {
+                self.flags
+            };
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: self.vk,
+                        wScan: self.unit,
+                        dwFlags: flags,
+                        time: 0,
+                        dwExtraInfo: 0,
+                    },
+                },
+            }
+        }
+    }
+
+    pub(super) struct SendInputKeys;
+
+    impl Keys for SendInputKeys {
+        type Key = Key;
+
+        fn modifier(&self, name: &str) -> anyhow::Result<Key> {
+            let vk = match name.to_ascii_lowercase().as_str() {
+                "shift" => VK_SHIFT,
+                "ctrl" | "control" => VK_CONTROL,
+                "alt" => VK_MENU,
+                "altgr" => VK_RMENU,
+                "logo" | "win" | "super" => VK_LWIN,
+                "capslock" => VK_CAPITAL,
+                _ => anyhow::bail!("unknown modifier {name}"),
+            };
+            Ok(virtual_key(vk))
+        }
+
+        fn key(&self, name: &str) -> anyhow::Result<Key> {
+            let lower = name.to_ascii_lowercase();
+            let vk = match lower.as_str() {
+                "return" | "enter" | "kp_enter" => VK_RETURN,
+                "tab" => VK_TAB,
+                "backspace" => VK_BACK,
+                "escape" => VK_ESCAPE,
+                "space" => VK_SPACE,
+                "delete" => VK_DELETE,
+                "insert" => VK_INSERT,
+                "home" => VK_HOME,
+                "end" => VK_END,
+                "page_up" | "prior" => VK_PRIOR,
+                "page_down" | "next" => VK_NEXT,
+                "left" => VK_LEFT,
+                "right" => VK_RIGHT,
+                "up" => VK_UP,
+                "down" => VK_DOWN,
+                "print" => VK_SNAPSHOT,
+                "pause" => VK_PAUSE,
+                "menu" => VK_APPS,
+                "caps_lock" => VK_CAPITAL,
+                "num_lock" => VK_NUMLOCK,
+                "scroll_lock" => VK_SCROLL,
+                "shift_l" | "shift_r" => VK_SHIFT,
+                "control_l" | "control_r" => VK_CONTROL,
+                "alt_l" => VK_MENU,
+                "alt_r" | "iso_level3_shift" => VK_RMENU,
+                "super_l" | "super_r" => VK_LWIN,
+                "xf86audioplay" | "xf86audiopause" => VK_MEDIA_PLAY_PAUSE,
+                "xf86audiostop" => VK_MEDIA_STOP,
+                "xf86audionext" => VK_MEDIA_NEXT_TRACK,
+                "xf86audioprev" => VK_MEDIA_PREV_TRACK,
+                "xf86audiomute" => VK_VOLUME_MUTE,
+                "xf86audiolowervolume" => VK_VOLUME_DOWN,
+                "xf86audioraisevolume" => VK_VOLUME_UP,
+                _ => return function_key(&lower).map_or_else(|| character(name), Ok),
+            };
+            Ok(virtual_key(vk))
+        }
+
+        fn send(&mut self, key: Key, down: bool) -> anyhow::Result<()> {
+            send(&[key.input(!down)])
+        }
+
+        fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+            let inputs: Vec<INPUT> = text
+                .encode_utf16()
+                .flat_map(|unit| {
+                    let key = Key {
+                        vk: 0,
+                        unit,
+                        flags: KEYEVENTF_UNICODE,
+                    };
+                    [key.input(false), key.input(true)]
+                })
+                .collect();
+            send(&inputs)
+        }
+    }
+
+    /// `f1` to `f24`.
+    fn function_key(name: &str) -> Option<Key> {
+        let n: u16 = name.strip_prefix('f')?.parse().ok()?;
+        (1..=24).contains(&n).then(|| virtual_key(VK_F1 + n - 1))
+    }
+
+    /// A key named by the character it types: letters and digits are pressed as keys (so
+    /// shortcuts like Ctrl+V work), anything else is typed.
+    fn character(name: &str) -> anyhow::Result<Key> {
+        let mut chars = name.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            anyhow::bail!("unknown key {name}");
+        };
+        if c.is_ascii_alphanumeric() {
+            return Ok(virtual_key(c.to_ascii_uppercase() as VIRTUAL_KEY));
+        }
+        let mut units = [0; 2];
+        match *c.encode_utf16(&mut units) {
+            [unit] => Ok(Key {
+                vk: 0,
+                unit,
+                flags: KEYEVENTF_UNICODE,
+            }),
+            _ => anyhow::bail!("cannot press {name} as one key"),
+        }
+    }
+
+    fn virtual_key(vk: VIRTUAL_KEY) -> Key {
+        // These are told apart from their numpad twins by the extended flag.
+        let extended = matches!(
+            vk,
+            VK_INSERT
+                | VK_DELETE
+                | VK_HOME
+                | VK_END
+                | VK_PRIOR
+                | VK_NEXT
+                | VK_LEFT
+                | VK_RIGHT
+                | VK_UP
+                | VK_DOWN
+                | VK_RMENU
+                | VK_LWIN
+                | VK_APPS
+        );
+        Key {
+            vk,
+            unit: 0,
+            flags: if extended { KEYEVENTF_EXTENDEDKEY } else { 0 },
+        }
+    }
+
+    fn send(inputs: &[INPUT]) -> anyhow::Result<()> {
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        // SAFETY: `inputs` points to `inputs.len()` initialized `INPUT`s of the size passed.
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as u32,
+                inputs.as_ptr(),
+                std::mem::size_of::<INPUT>() as i32,
+            )
+        };
+        if sent as usize != inputs.len() {
+            anyhow::bail!(
+                "SendInput sent {sent} of {} inputs: {}",
+                inputs.len(),
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod cg_event {
+    use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation, CGKeyCode, KeyCode};
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+    use super::wtype::Keys;
+
+    /// Most UTF-16 code units one keyboard event types.
+    const MAX_EVENT_UNITS: usize = 20;
+
+    /// Keypad Enter, which `KeyCode` has no name for.
+    const KEYPAD_ENTER: CGKeyCode = 0x4C;
+
+    /// Key codes of the ANSI layout's character keys.
+    const ANSI_KEYS: [(char, CGKeyCode); 47] = [
+        ('a', 0x00),
+        ('s', 0x01),
+        ('d', 0x02),
+        ('f', 0x03),
+        ('h', 0x04),
+        ('g', 0x05),
+        ('z', 0x06),
+        ('x', 0x07),
+        ('c', 0x08),
+        ('v', 0x09),
+        ('b', 0x0B),
+        ('q', 0x0C),
+        ('w', 0x0D),
+        ('e', 0x0E),
+        ('r', 0x0F),
+        ('y', 0x10),
+        ('t', 0x11),
+        ('1', 0x12),
+        ('2', 0x13),
+        ('3', 0x14),
+        ('4', 0x15),
+        ('6', 0x16),
+        ('5', 0x17),
+        ('=', 0x18),
+        ('9', 0x19),
+        ('7', 0x1A),
+        ('-', 0x1B),
+        ('8', 0x1C),
+        ('0', 0x1D),
+        (']', 0x1E),
+        ('o', 0x1F),
+        ('u', 0x20),
+        ('[', 0x21),
+        ('i', 0x22),
+        ('p', 0x23),
+        ('l', 0x25),
+        ('j', 0x26),
+        ('\'', 0x27),
+        ('k', 0x28),
+        (';', 0x29),
+        ('\\', 0x2A),
+        (',', 0x2B),
+        ('/', 0x2C),
+        ('n', 0x2D),
+        ('m', 0x2E),
+        ('.', 0x2F),
+        ('`', 0x32),
+    ];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(super) enum Key {
+        /// A key code and, for modifiers, the flag it sets while held.
+        Code(CGKeyCode, CGEventFlags),
+        /// A character typed as is.
+        Char(char),
+    }
+
+    pub(super) struct CgEventKeys {
+        source: CGEventSource,
+        /// Modifiers held, set on every event.
+        flags: CGEventFlags,
+    }
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> u8;
+    }
+
+    impl CgEventKeys {
+        pub(super) fn new() -> anyhow::Result<Self> {
+            // SAFETY: takes no arguments and only reads the process's permission.
+            if unsafe { AXIsProcessTrusted() } == 0 {
+                anyhow::bail!(
+                    "macOS drops the key presses until RiverDeck is allowed under System \
+                     Settings > Privacy & Security > Accessibility"
+                );
+            }
+            let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+                .map_err(|()| anyhow::anyhow!("failed to create a keyboard event source"))?;
+            Ok(Self {
+                source,
+                flags: CGEventFlags::CGEventFlagNull,
+            })
+        }
+
+        fn post(&self, code: CGKeyCode, down: bool, text: Option<&[u16]>) -> anyhow::Result<()> {
+            let event = CGEvent::new_keyboard_event(self.source.clone(), code, down)
+                .map_err(|()| anyhow::anyhow!("failed to create a keyboard event"))?;
+            event.set_flags(self.flags);
+            if let Some(text) = text {
+                event.set_string_from_utf16_unchecked(text);
+            }
+            event.post(CGEventTapLocation::HID);
+            Ok(())
+        }
+    }
+
+    impl Keys for CgEventKeys {
+        type Key = Key;
+
+        fn modifier(&self, name: &str) -> anyhow::Result<Key> {
+            let (code, flag) = match name.to_ascii_lowercase().as_str() {
+                "shift" => (KeyCode::SHIFT, CGEventFlags::CGEventFlagShift),
+                "ctrl" | "control" => (KeyCode::CONTROL, CGEventFlags::CGEventFlagControl),
+                "alt" | "option" => (KeyCode::OPTION, CGEventFlags::CGEventFlagAlternate),
+                "altgr" => (KeyCode::RIGHT_OPTION, CGEventFlags::CGEventFlagAlternate),
+                "logo" | "win" | "super" | "cmd" | "command" => {
+                    (KeyCode::COMMAND, CGEventFlags::CGEventFlagCommand)
+                }
+                "capslock" => (KeyCode::CAPS_LOCK, CGEventFlags::CGEventFlagAlphaShift),
+                _ => anyhow::bail!("unknown modifier {name}"),
+            };
+            Ok(Key::Code(code, flag))
+        }
+
+        fn key(&self, name: &str) -> anyhow::Result<Key> {
+            let lower = name.to_ascii_lowercase();
+            let code = match lower.as_str() {
+                "return" | "enter" => KeyCode::RETURN,
+                "kp_enter" => KEYPAD_ENTER,
+                "tab" => KeyCode::TAB,
+                "backspace" => KeyCode::DELETE,
+                "escape" => KeyCode::ESCAPE,
+                "space" => KeyCode::SPACE,
+                "delete" => KeyCode::FORWARD_DELETE,
+                "insert" | "help" => KeyCode::HELP,
+                "home" => KeyCode::HOME,
+                "end" => KeyCode::END,
+                "page_up" | "prior" => KeyCode::PAGE_UP,
+                "page_down" | "next" => KeyCode::PAGE_DOWN,
+                "left" => KeyCode::LEFT_ARROW,
+                "right" => KeyCode::RIGHT_ARROW,
+                "up" => KeyCode::UP_ARROW,
+                "down" => KeyCode::DOWN_ARROW,
+                "xf86audiomute" => KeyCode::MUTE,
+                "xf86audiolowervolume" => KeyCode::VOLUME_DOWN,
+                "xf86audioraisevolume" => KeyCode::VOLUME_UP,
+                "shift_l" => return self.modifier("shift"),
+                "control_l" | "control_r" => return self.modifier("ctrl"),
+                "alt_l" => return self.modifier("alt"),
+                "alt_r" | "iso_level3_shift" => return self.modifier("altgr"),
+                "super_l" | "super_r" | "meta_l" | "meta_r" => return self.modifier("logo"),
+                "caps_lock" => return self.modifier("capslock"),
+                "shift_r" => {
+                    return Ok(Key::Code(
+                        KeyCode::RIGHT_SHIFT,
+                        CGEventFlags::CGEventFlagShift,
+                    ))
+                }
+                _ => return function_key(&lower).map_or_else(|| character(name), Ok),
+            };
+            Ok(Key::Code(code, CGEventFlags::CGEventFlagNull))
+        }
+
+        fn send(&mut self, key: Key, down: bool) -> anyhow::Result<()> {
+            match key {
+                Key::Code(code, flag) => {
+                    self.flags.set(flag, down);
+                    self.post(code, down, None)
+                }
+                Key::Char(c) => self.post(0, down, Some(c.encode_utf16(&mut [0; 2]))),
+            }
+        }
+
+        fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+            let units: Vec<u16> = text.encode_utf16().collect();
+            let mut start = 0;
+            while start < units.len() {
+                let mut end = (start + MAX_EVENT_UNITS).min(units.len());
+                // Keep surrogate pairs in one event.
+                if end < units.len() && (0xDC00..0xE000).contains(&units[end]) {
+                    end -= 1;
+                }
+                let chunk = &units[start..end];
+                self.post(0, true, Some(chunk))?;
+                self.post(0, false, Some(chunk))?;
+                start = end;
+            }
+            Ok(())
+        }
+    }
+
+    /// `f1` to `f20`.
+    fn function_key(name: &str) -> Option<Key> {
+        const CODES: [CGKeyCode; 20] = [
+            KeyCode::F1,
+            KeyCode::F2,
+            KeyCode::F3,
+            KeyCode::F4,
+            KeyCode::F5,
+            KeyCode::F6,
+            KeyCode::F7,
+            KeyCode::F8,
+            KeyCode::F9,
+            KeyCode::F10,
+            KeyCode::F11,
+            KeyCode::F12,
+            KeyCode::F13,
+            KeyCode::F14,
+            KeyCode::F15,
+            KeyCode::F16,
+            KeyCode::F17,
+            KeyCode::F18,
+            KeyCode::F19,
+            KeyCode::F20,
+        ];
+        let n: usize = name.strip_prefix('f')?.parse().ok()?;
+        let code = *CODES.get(n.checked_sub(1)?)?;
+        Some(Key::Code(code, CGEventFlags::CGEventFlagNull))
+    }
+
+    /// A key named by the character it types: keys of the ANSI layout are pressed (so
+    /// shortcuts like Cmd+V work), anything else is typed.
+    fn character(name: &str) -> anyhow::Result<Key> {
+        let mut chars = name.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            anyhow::bail!("unknown key {name}");
+        };
+        let lower = c.to_ascii_lowercase();
+        Ok(match ANSI_KEYS.iter().find(|(key, _)| *key == lower) {
+            Some(&(_, code)) => Key::Code(code, CGEventFlags::CGEventFlagNull),
+            None => Key::Char(c),
+        })
+    }
+}
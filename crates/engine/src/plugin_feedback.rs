@@ -0,0 +1,234 @@
+//! Messages a plugin action sends back while it runs (see [`plugin_runtime::feedback`]).
+//!
+//! `setImage`, `setTitle` and `setState` redraw the key that fired the action: the engine
+//! resolves each to a [`KeyChange`] and reports it to UIs, which draw it over the key's
+//! appearance on the device and in the preview. Images sent as `data:` URLs are written to
+//! `<cache_dir>/plugin-images/` so they can be drawn like any icon file.
+//!
+//! `setSettings` replaces the settings of the action in the bindings of the control that fired
+//! it; the active profile is updated at once and UIs are told to save it. `setGlobalSettings`
+//! goes to the plugin state file and is passed to every later invocation of the plugin.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use openaction::registry::{self, InstalledPlugin};
+use plugin_runtime::feedback::PluginFeedback;
+use plugin_runtime::InvocationControl;
+use storage::profiles::Profile;
+use tracing::{debug, warn};
+
+use crate::{EngineNotification, Shared};
+
+/// How a plugin (or a script) changed a key's look, until it changes it back or another
+/// profile loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyChange {
+    /// Show this image instead of the key's icon; `None` restores the icon.
+    Image(Option<PathBuf>),
+    /// Show this text instead of the key's own; `None` restores it.
+    Title(Option<String>),
+}
+
+/// Carry out what `feedback` from `action_id` of `plugin`, fired by `control`, asks for.
+pub(crate) fn apply(
+    shared: &Shared,
+    plugin: &InstalledPlugin,
+    action_id: &str,
+    control: &InvocationControl,
+    feedback: PluginFeedback,
+) {
+    let plugin_id = &plugin.manifest.id;
+    match feedback {
+        PluginFeedback::SetGlobalSettings(settings) => {
+            if let Err(e) = registry::set_global_settings(plugin_id, settings) {
+                warn!(%plugin_id, error = %e, "failed to save plugin global settings");
+            }
+        }
+        PluginFeedback::SetSettings(settings) => {
+            save_settings(shared, plugin_id, action_id, control, settings)
+        }
+        feedback => redraw_key(shared, plugin, action_id, control, feedback),
+    }
+}
+
+/// Replace the action's settings in the bindings of `control` and report it for saving.
+fn save_settings(
+    shared: &Shared,
+    plugin_id: &str,
+    action_id: &str,
+    control: &InvocationControl,
+    settings: serde_json::Value,
+) {
+    let profile = {
+        let mut state = shared.state();
+        let Some(profile) = state.profile.as_mut() else {
+            return;
+        };
+        if set_binding_settings(profile, control, plugin_id, action_id, &settings) == 0 {
+            debug!(?control, %plugin_id, action_id, "no binding to save plugin settings to");
+            return;
+        }
+        profile.id
+    };
+    shared.emit(EngineNotification::PluginSettingsChanged {
+        profile,
+        control: control.clone(),
+        plugin_id: plugin_id.to_string(),
+        action_id: action_id.to_string(),
+        settings,
+    });
+}
+
+/// Replace the settings of every invocation of `action_id` of `plugin_id` in the bindings of
+/// `control` in `profile`. Returns how many were replaced.
+pub fn set_binding_settings(
+    profile: &mut Profile,
+    control: &InvocationControl,
+    plugin_id: &str,
+    action_id: &str,
+    settings: &serde_json::Value,
+) -> usize {
+    let slots: Vec<&mut ActionBinding> = match *control {
+        InvocationControl::Key { index } => match profile.keys.get_mut(index as usize) {
+            Some(k) => k.action.iter_mut().collect(),
+            None => vec![],
+        },
+        InvocationControl::Dial { index } => match profile.dials.get_mut(index as usize) {
+            Some(d) => d.press.iter_mut().chain(d.rotate.iter_mut()).collect(),
+            None => vec![],
+        },
+        InvocationControl::TouchStrip => profile
+            .touch_strip
+            .bindings_mut()
+            .into_iter()
+            .flatten()
+            .collect(),
+        InvocationControl::FileWatcher { id } => profile
+            .file_watchers
+            .iter_mut()
+            .filter(|w| w.id == id)
+            .map(|w| &mut w.action)
+            .collect(),
+        // Schedules, webhooks, typed triggers, process watchers and MIDI triggers keep their
+        // bindings outside the profile.
+        InvocationControl::Schedule { .. }
+        | InvocationControl::Webhook { .. }
+        | InvocationControl::TypedTrigger { .. }
+        | InvocationControl::ProcessWatcher { .. }
+        | InvocationControl::MidiTrigger { .. }
+        | InvocationControl::OscTrigger { .. } => vec![],
+    };
+    slots
+        .into_iter()
+        .map(|b| b.set_plugin_settings(plugin_id, action_id, settings))
+        .sum()
+}
+
+/// Report the change `feedback` asks for on `control`, if it is a key of the active profile.
+fn redraw_key(
+    shared: &Shared,
+    plugin: &InstalledPlugin,
+    action_id: &str,
+    control: &InvocationControl,
+    feedback: PluginFeedback,
+) {
+    let InvocationControl::Key { index: key } = *control else {
+        debug!(
+            ?control,
+            ?feedback,
+            "plugin feedback for a control other than a key"
+        );
+        return;
+    };
+    let Some(profile) = shared.state().profile.as_ref().map(|p| p.id) else {
+        return;
+    };
+    let changes = match changes(plugin, action_id, feedback) {
+        Ok(changes) => changes,
+        Err(e) => {
+            warn!(plugin_id = %plugin.manifest.id, key, error = %e, "ignoring plugin feedback");
+            return;
+        }
+    };
+    for change in changes {
+        shared.emit(EngineNotification::PluginKeyChanged {
+            profile,
+            key,
+            change,
+        });
+    }
+}
+
+fn changes(
+    plugin: &InstalledPlugin,
+    action_id: &str,
+    feedback: PluginFeedback,
+) -> anyhow::Result<Vec<KeyChange>> {
+    Ok(match feedback {
+        PluginFeedback::SetImage { image } => {
+            let image = match image.filter(|i| !i.is_empty()) {
+                Some(image) => Some(image_path(plugin, &image)?),
+                None => None,
+            };
+            vec![KeyChange::Image(image)]
+        }
+        PluginFeedback::SetTitle { title } => vec![KeyChange::Title(title)],
+        // Handled by `apply`; they don't change how the key looks.
+        PluginFeedback::SetSettings(_) | PluginFeedback::SetGlobalSettings(_) => Vec::new(),
+        PluginFeedback::SetState { state } => {
+            let action = plugin
+                .manifest
+                .actions
+                .iter()
+                .find(|a| a.id == action_id)
+                .context("action not in the plugin manifest")?;
+            let Some(look) = action.states.get(state) else {
+                anyhow::bail!("action has no state {state}");
+            };
+            vec![
+                KeyChange::Image(plugin.state_icon(action, state)),
+                KeyChange::Title(look.title.clone()),
+            ]
+        }
+    })
+}
+
+/// The file to draw for a `setImage` message's `image`.
+fn image_path(plugin: &InstalledPlugin, image: &str) -> anyhow::Result<PathBuf> {
+    let Some(data_url) = image.strip_prefix("data:") else {
+        return plugin
+            .image_file(image)
+            .with_context(|| format!("no image {image} in the plugin directory"));
+    };
+    let (mime, data) = data_url
+        .split_once(";base64,")
+        .context("only base64 data URLs are supported")?;
+    let extension = match mime {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => anyhow::bail!("unsupported image type {mime}"),
+    };
+    let bytes = BASE64
+        .decode(data.trim())
+        .context("invalid base64 image data")?;
+
+    // Named after the content, so a changed image is a new path and gets drawn again.
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let dir = storage::paths::cache_dir()?.join("plugin-images");
+    let path = dir.join(format!("{:016x}.{extension}", hasher.finish()));
+    if !path.is_file() {
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&path, &bytes)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,105 @@
+//! Process watchers: run an action when a program starts or exits (see
+//! [`storage::process_watchers`]).
+//!
+//! The running processes are listed every [`POLL`] while any watcher is enabled, and names are
+//! matched like `process_running` conditions match them. A program that already runs when its
+//! watcher is added (or the engine starts) has not started as far as watchers are concerned.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use storage::process_watchers::{ProcessTransition, ProcessWatcher};
+use tracing::{debug, warn};
+
+use crate::conditions::process_matches;
+use crate::{start_sequence, Shared};
+
+/// How often the running processes are listed.
+const POLL: Duration = Duration::from_secs(2);
+
+/// Watch the processes the enabled watchers name until the task is aborted.
+pub(crate) async fn run(shared: Arc<Shared>) {
+    let mut sys = sysinfo::System::new();
+    // Whether a process of each watched name ran at the last listing.
+    let mut running: HashMap<String, bool> = HashMap::new();
+    loop {
+        tokio::time::sleep(POLL).await;
+        let watchers: Vec<ProcessWatcher> = shared
+            .state()
+            .process_watchers
+            .iter()
+            .filter(|w| w.enabled && !w.process.trim().is_empty())
+            .cloned()
+            .collect();
+        if watchers.is_empty() {
+            running.clear();
+            continue;
+        }
+
+        let names: BTreeSet<String> = watchers
+            .iter()
+            .map(|w| w.process.trim().to_string())
+            .collect();
+        let listed = tokio::task::spawn_blocking(move || {
+            let now = running_names(&mut sys, names);
+            (sys, now)
+        })
+        .await;
+        let now = match listed {
+            Ok((s, now)) => {
+                sys = s;
+                now
+            }
+            Err(e) => {
+                warn!(error = %e, "failed to list processes");
+                sys = sysinfo::System::new();
+                continue;
+            }
+        };
+
+        let paused = shared.state().paused;
+        for (name, &is_running) in &now {
+            let transition = match (running.insert(name.clone(), is_running), is_running) {
+                (Some(false), true) => ProcessTransition::Started,
+                (Some(true), false) => ProcessTransition::Exited,
+                _ => continue,
+            };
+            debug!(process = %name, ?transition, "watched process changed");
+            if paused {
+                continue;
+            }
+            let fired = watchers
+                .iter()
+                .filter(|w| w.process.trim() == name && w.on == transition);
+            for w in fired {
+                let process = name.clone();
+                let event = match transition {
+                    ProcessTransition::Started => InvocationEvent::ProcessStarted { process },
+                    ProcessTransition::Exited => InvocationEvent::ProcessExited { process },
+                };
+                start_sequence(
+                    &shared,
+                    InvocationControl::ProcessWatcher { id: w.id },
+                    event,
+                    &w.action,
+                );
+            }
+        }
+        // Names no watcher looks for anymore start over if they come back.
+        running.retain(|name, _| now.contains_key(name));
+    }
+}
+
+/// Whether a process runs for each of `names`.
+fn running_names(sys: &mut sysinfo::System, names: BTreeSet<String>) -> HashMap<String, bool> {
+    sys.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+    names
+        .into_iter()
+        .map(|name| {
+            let is_running = sys.processes().values().any(|p| process_matches(p, &name));
+            (name, is_running)
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+//! Reopening a device that went away.
+//!
+//! A disconnected device is looked for every few seconds, by the id it had, for as long as the
+//! engine runs. Once it is back its brightness and the active profile's key images are restored
+//! (the deck is reset when it is opened), so it looks as it did before the UI redraws anything.
+
+use std::time::Duration;
+
+use device::{ConnectedDevice, DeviceEvent, HidDeviceService};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::Shared;
+
+/// Wait between attempts at reopening the device.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Wait for the device to come back, switch the engine over to it and restore what it showed.
+/// Returns the new connection's events.
+pub(crate) async fn wait_for_device(shared: &Shared) -> mpsc::Receiver<DeviceEvent> {
+    info!(device = %shared.device_name, "device disconnected; waiting for it to come back");
+    loop {
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+        match open(shared).await {
+            Ok(device) => {
+                *shared
+                    .controller
+                    .lock()
+                    .expect("device controller mutex poisoned") = device.controller();
+                info!(device = %shared.device_name, "device reconnected");
+                restore(shared).await;
+                return device.events;
+            }
+            Err(e) => debug!(error = %e, "device not back yet"),
+        }
+    }
+}
+
+async fn open(shared: &Shared) -> anyhow::Result<ConnectedDevice> {
+    // A fresh service, so the device list is read again.
+    let service = HidDeviceService::new()?;
+    service.connect(shared.device_id).await
+}
+
+/// Restore the brightness and, unless Companion draws the keys, the active profile's key images.
+async fn restore(shared: &Shared) {
+    let controller = shared.controller();
+    let (brightness, images) = {
+        let state = shared.state();
+        let images: Vec<_> = match &state.profile {
+            Some(profile) if state.companion.is_none() => (0..shared.capabilities.key_count)
+                .filter_map(|key| {
+                    // On a surface, the deck shows its part of the surface's keys.
+                    let index = match &state.surface {
+                        Some(surface) => surface.surface_key(shared.device_id, key)?,
+                        None => key,
+                    };
+                    let k = profile.keys.get(usize::from(index))?;
+                    Some((key, shared.key_image(&k.appearance)))
+                })
+                .collect(),
+            _ => vec![],
+        };
+        (state.brightness, images)
+    };
+
+    if let Err(e) = controller.set_brightness(brightness).await {
+        warn!(error = %e, "failed to restore brightness");
+    }
+    // Every write is queued before any is awaited, so the keys come back in one frame.
+    let writes = images.into_iter().map(|(key, image)| {
+        let controller = &controller;
+        async move {
+            let res = match image.render() {
+                Ok(jpeg) => controller.set_key_image_jpeg(key, jpeg).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                warn!(key, error = %e, "failed to restore key image");
+            }
+        }
+    });
+    futures::future::join_all(writes).await;
+}
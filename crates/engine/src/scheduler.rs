@@ -0,0 +1,42 @@
+//! Cron-like schedules: which schedules are due at a given minute, and when they run next.
+//!
+//! Expressions are standard five-field cron patterns (`0 9 * * 1-5` = weekdays at 09:00)
+//! evaluated in local time with minute resolution.
+
+use anyhow::Context;
+use chrono::{DateTime, Local, Timelike};
+use croner::Cron;
+use storage::schedules::Schedule;
+
+/// Parse a five-field cron expression.
+pub fn parse(expression: &str) -> anyhow::Result<Cron> {
+    Cron::new(expression.trim())
+        .parse()
+        .with_context(|| format!("invalid cron expression: {expression}"))
+}
+
+/// The first time after `after` at which `expression` fires.
+pub fn next_run(expression: &str, after: DateTime<Local>) -> anyhow::Result<DateTime<Local>> {
+    Ok(parse(expression)?.find_next_occurrence(&after, false)?)
+}
+
+/// Start of the minute containing `t`.
+pub(crate) fn minute_of(t: DateTime<Local>) -> DateTime<Local> {
+    t.with_second(0)
+        .and_then(|t| t.with_nanosecond(0))
+        .unwrap_or(t)
+}
+
+/// Enabled schedules whose expression matches `minute`. Invalid expressions never match.
+pub(crate) fn due(schedules: &[Schedule], minute: DateTime<Local>) -> Vec<Schedule> {
+    schedules
+        .iter()
+        .filter(|s| s.enabled)
+        .filter(|s| {
+            parse(&s.cron)
+                .and_then(|c| Ok(c.is_time_matching(&minute)?))
+                .unwrap_or(false)
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,125 @@
+//! The `Script` builtin: a small [Rhai](https://rhai.rs) script run in an embedded interpreter.
+//!
+//! Besides the language itself, scripts can only reach the host through these functions:
+//!
+//! - `run(command)` runs a shell command like the Issue Command action and returns whether it
+//!   exited successfully;
+//! - `get_var(name)` returns a variable's value (`()` if unset) and `set_var(name, value)` sets
+//!   it, or removes it when `value` is `()`;
+//! - `set_key_text(text)` shows `text` on the key that ran the script, and an empty string
+//!   gives the key its own text back;
+//! - `sleep(ms)` pauses the script;
+//! - `print(..)` writes to the log.
+//!
+//! A script runs on a blocking thread and is stopped after [`TIME_LIMIT`], including the time
+//! spent in commands and sleeps. Nesting, string, array and map sizes are capped too.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use plugin_runtime::InvocationControl;
+use rhai::{Dynamic, Engine, EvalAltResult};
+use tokio::runtime::Handle;
+use tracing::{debug, info};
+
+use crate::plugin_feedback::KeyChange;
+use crate::{builtins, EngineNotification, Shared};
+
+/// How long a script may run in total.
+pub const TIME_LIMIT: Duration = Duration::from_secs(60);
+
+/// Run `source` for an invocation from `control`.
+pub(crate) async fn run(
+    shared: &Arc<Shared>,
+    control: &InvocationControl,
+    source: String,
+) -> anyhow::Result<()> {
+    let (shared, control, runtime) = (shared.clone(), control.clone(), Handle::current());
+    tokio::task::spawn_blocking(move || {
+        // Rhai's errors can't leave the script thread, so only their text does.
+        engine(shared, control, runtime)
+            .run(&source)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .context("[Script] Script thread failed")?
+    .map_err(|e| anyhow::anyhow!("[Script] {e}"))
+}
+
+fn engine(shared: Arc<Shared>, control: InvocationControl, runtime: Handle) -> Engine {
+    let started = Instant::now();
+    let remaining = move || TIME_LIMIT.saturating_sub(started.elapsed());
+
+    let mut engine = Engine::new();
+    engine
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(64 * 1024)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .on_progress(move |_| {
+            let message = || Dynamic::from(format!("stopped after {TIME_LIMIT:?}"));
+            remaining().is_zero().then(message)
+        })
+        .on_print(|text| info!(target: "riverdeck::script", "{text}"))
+        .on_debug(|text, _, pos| debug!(target: "riverdeck::script", %pos, "{text}"));
+
+    engine.register_fn("run", move |command: &str| -> bool {
+        let timeout = remaining().as_millis() as u64;
+        match runtime.block_on(builtins::issue_command(
+            command.to_string(),
+            None,
+            Some(timeout),
+        )) {
+            Ok(()) => true,
+            Err(e) => {
+                debug!(command, error = %e, "script command failed");
+                false
+            }
+        }
+    });
+
+    let vars = shared.clone();
+    engine.register_fn("get_var", move |name: &str| -> Dynamic {
+        vars.variables
+            .get(name)
+            .map_or(Dynamic::UNIT, Dynamic::from)
+    });
+    let vars = shared.clone();
+    engine.register_fn(
+        "set_var",
+        move |name: &str, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let value = (!value.is_unit()).then(|| value.to_string());
+            vars.variables
+                .set(name, value)
+                .map_err(|e| e.to_string().into())
+        },
+    );
+
+    engine.register_fn(
+        "set_key_text",
+        move |text: &str| -> Result<(), Box<EvalAltResult>> {
+            let InvocationControl::Key { index: key } = control else {
+                return Err("set_key_text can only be used from a key".into());
+            };
+            let Some(profile) = shared.state().profile.as_ref().map(|p| p.id) else {
+                return Ok(());
+            };
+            let title = Some(text.to_string()).filter(|t| !t.is_empty());
+            shared.emit(EngineNotification::PluginKeyChanged {
+                profile,
+                key,
+                change: KeyChange::Title(title),
+            });
+            Ok(())
+        },
+    );
+
+    engine.register_fn("sleep", move |ms: i64| {
+        let ms = Duration::from_millis(ms.max(0) as u64);
+        std::thread::sleep(ms.min(remaining()));
+    });
+
+    engine
+}
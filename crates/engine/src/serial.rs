@@ -0,0 +1,57 @@
+//! Serial ports for `serial` actions, e.g. to drive an Arduino or another microcontroller.
+//!
+//! A port stays open after its first write, because many boards (the Arduino Uno among them)
+//! reset when their port is opened and would miss what was written meanwhile. It is reopened
+//! when the baud rate changes or a write fails, e.g. after the board was unplugged.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+use serialport::SerialPort;
+use tracing::debug;
+
+/// How long a write may block, e.g. while a board does not read.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A port left open, with the baud rate it was opened at.
+type OpenPort = (u32, Box<dyn SerialPort>);
+
+/// Ports written to so far, by name.
+static OPEN: Mutex<BTreeMap<String, OpenPort>> = Mutex::new(BTreeMap::new());
+
+/// Names of the serial ports present, e.g. `/dev/ttyACM0` or `COM3`.
+pub fn ports() -> anyhow::Result<Vec<String>> {
+    let ports = serialport::available_ports().context("cannot list serial ports")?;
+    Ok(ports.into_iter().map(|p| p.port_name).collect())
+}
+
+/// Write `bytes` to `port` at `baud`.
+pub async fn write(port: &str, baud: u32, bytes: Vec<u8>) -> anyhow::Result<()> {
+    let port = port.trim().to_string();
+    if port.is_empty() {
+        anyhow::bail!("no serial port set");
+    }
+    tokio::task::spawn_blocking(move || {
+        let mut open = OPEN.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((opened_at, mut conn)) = open.remove(&port) {
+            if opened_at == baud && conn.write_all(&bytes).and_then(|()| conn.flush()).is_ok() {
+                open.insert(port, (baud, conn));
+                return Ok(());
+            }
+            debug!(%port, "reopening serial port");
+        }
+        let mut conn = serialport::new(&port, baud)
+            .timeout(WRITE_TIMEOUT)
+            .open()
+            .with_context(|| format!("cannot open {port}"))?;
+        conn.write_all(&bytes)
+            .and_then(|()| conn.flush())
+            .with_context(|| format!("cannot write to {port}"))?;
+        open.insert(port, (baud, conn));
+        Ok(())
+    })
+    .await?
+}
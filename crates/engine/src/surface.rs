@@ -0,0 +1,102 @@
+//! Surfaces: the keys of several decks composed into one grid (see [`storage::surfaces`]).
+//!
+//! While the active profile targets a surface, presses are turned into presses of the surface
+//! key before dispatch, so bindings, counters and timers only ever see surface keys. The engine
+//! reads its own deck as before and opens the other member decks itself, each on a task of its
+//! own that reopens the deck when it comes back after a disconnect.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use app_core::capabilities::DeviceCapabilities;
+use app_core::ids::DeviceId;
+use device::{ControlEvent, ControlId, DeviceController, DeviceEvent, HidDeviceService};
+use tracing::{debug, info, warn};
+
+use crate::{dispatch_control, EngineNotification, Shared};
+
+/// Wait between attempts at opening a member deck.
+const OPEN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A member deck of the active surface other than the engine's own, while it is connected.
+#[derive(Clone)]
+pub struct SurfaceDeck {
+    pub id: DeviceId,
+    pub name: String,
+    pub capabilities: DeviceCapabilities,
+    pub controller: DeviceController,
+}
+
+/// `ev` from `device` as an event of the active surface. Without a surface it is passed on as it
+/// is; with one, keys become surface keys and the dials and touch strip of the other decks are
+/// dropped.
+pub(crate) fn compose(shared: &Shared, device: DeviceId, ev: ControlEvent) -> Option<ControlEvent> {
+    let state = shared.state();
+    let Some(surface) = &state.surface else {
+        return Some(ev);
+    };
+    match ev.control {
+        ControlId::Key(key) => Some(ControlEvent {
+            control: ControlId::Key(surface.surface_key(device, key)?),
+            ..ev
+        }),
+        _ if device == shared.device_id => Some(ev),
+        _ => None,
+    }
+}
+
+/// Keep member deck `device` open and dispatch its presses, until the surface changes.
+pub(crate) async fn run_member(shared: Arc<Shared>, device: DeviceId) {
+    loop {
+        let opened = match HidDeviceService::new() {
+            Ok(service) => service.connect(device).await,
+            Err(e) => Err(e),
+        };
+        let deck = match opened {
+            Ok(deck) => deck,
+            Err(e) => {
+                debug!(device = device.0, error = %e, "surface deck not there yet");
+                tokio::time::sleep(OPEN_INTERVAL).await;
+                continue;
+            }
+        };
+        info!(device = %deck.name, "surface deck connected");
+        let controller = deck.controller();
+        let brightness = shared.state().brightness;
+        if let Err(e) = controller.set_brightness(brightness).await {
+            warn!(error = %e, "failed to set the surface deck's brightness");
+        }
+        shared.surface_decks().insert(
+            device,
+            SurfaceDeck {
+                id: device,
+                name: deck.name.clone(),
+                capabilities: deck.capabilities,
+                controller,
+            },
+        );
+        shared.emit(EngineNotification::SurfaceDeck {
+            device,
+            connected: true,
+        });
+
+        let mut events = deck.events;
+        while let Some(ev) = events.recv().await {
+            match ev {
+                DeviceEvent::Control(ev) => {
+                    if let Some(ev) = compose(&shared, device, ev) {
+                        dispatch_control(&shared, ev);
+                    }
+                }
+                DeviceEvent::Disconnected => break,
+            }
+        }
+        info!(device = device.0, "surface deck disconnected");
+        shared.surface_decks().remove(&device);
+        shared.emit(EngineNotification::SurfaceDeck {
+            device,
+            connected: false,
+        });
+        tokio::time::sleep(OPEN_INTERVAL).await;
+    }
+}
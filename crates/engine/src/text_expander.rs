@@ -0,0 +1,282 @@
+//! Typed triggers: run an action when what is typed anywhere on the system ends with a
+//! trigger's abbreviation, like a text expander (see [`storage::typed_triggers`]).
+//!
+//! On Linux the keyboards are read from their input devices (`/dev/input/event*`, through
+//! evdev), so this works the same on X11 and Wayland; the user needs read access to them,
+//! usually by being in the `input` group. Keys are turned into characters with a US layout.
+//! The abbreviation is erased with `BackSpace` presses sent through the same keyboard tool as
+//! Keyboard Input actions (`RIVERDECK_KEYBOARD_TOOL`, default `wtype`), so an action that types
+//! text replaces it. Other platforms have no keyboard monitor yet.
+
+use std::sync::Arc;
+
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use storage::typed_triggers::TypedTrigger;
+use tracing::{error, info, warn};
+
+use crate::{builtins, start_sequence, EngineNotification, Shared};
+
+/// Characters kept of what was typed; longer abbreviations never match.
+const MAX_TYPED: usize = 64;
+
+/// A key press as far as matching abbreviations is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypedKey {
+    Char(char),
+    Backspace,
+    /// Anything that moves the cursor or is a shortcut: what was typed before no longer
+    /// counts.
+    Other,
+}
+
+/// The end of what was typed since the last key that broke the text up.
+#[derive(Debug, Default)]
+struct TypedText(String);
+
+impl TypedText {
+    fn push(&mut self, key: TypedKey) {
+        match key {
+            TypedKey::Char(c) => {
+                self.0.push(c);
+                if self.0.chars().count() > MAX_TYPED {
+                    self.0.remove(0);
+                }
+            }
+            TypedKey::Backspace => {
+                self.0.pop();
+            }
+            TypedKey::Other => self.0.clear(),
+        }
+    }
+
+    /// The enabled trigger whose abbreviation the text ends with; the longest one wins.
+    fn matching(&self, triggers: &[TypedTrigger]) -> Option<TypedTrigger> {
+        triggers
+            .iter()
+            .filter(|t| t.enabled && !t.abbreviation.is_empty())
+            .filter(|t| self.0.ends_with(&t.abbreviation))
+            .max_by_key(|t| t.abbreviation.chars().count())
+            .cloned()
+    }
+}
+
+/// Watch the keyboard until the task is aborted; failures are reported to the UI.
+pub(crate) fn spawn(shared: Arc<Shared>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = watch(&shared).await {
+            error!(error = %e, "text expander stopped");
+            shared.emit(EngineNotification::ActionFailed(format!(
+                "[Text Expander] {e:#}"
+            )));
+        }
+    })
+}
+
+async fn watch(shared: &Arc<Shared>) -> anyhow::Result<()> {
+    let mut keys = keyboard::watch()?;
+    let mut typed = TypedText::default();
+    while let Some(key) = keys.recv().await {
+        typed.push(key);
+        let (trigger, dry_run) = {
+            let state = shared.state();
+            if state.paused {
+                continue;
+            }
+            (typed.matching(&state.typed_triggers), state.dry_run)
+        };
+        let Some(trigger) = trigger else {
+            continue;
+        };
+        typed.push(TypedKey::Other);
+        info!(trigger = trigger.id, abbreviation = %trigger.abbreviation, "typed trigger fired");
+        if trigger.erase && !dry_run {
+            if let Err(e) = erase(trigger.abbreviation.chars().count()).await {
+                warn!(trigger = trigger.id, error = %e, "failed to erase the abbreviation");
+            }
+        }
+        start_sequence(
+            shared,
+            InvocationControl::TypedTrigger { id: trigger.id },
+            InvocationEvent::AbbreviationTyped,
+            &trigger.action,
+        );
+    }
+    anyhow::bail!("no keyboard to watch is left")
+}
+
+/// Press `BackSpace` `count` times.
+async fn erase(count: usize) -> anyhow::Result<()> {
+    let keys = (0..count)
+        .flat_map(|_| ["-k".to_string(), "BackSpace".to_string()])
+        .collect();
+    builtins::keyboard_input(None, keys).await
+}
+
+#[cfg(target_os = "linux")]
+mod keyboard {
+    use evdev::{InputEventKind, Key};
+    use tokio::sync::mpsc;
+    use tracing::{debug, warn};
+
+    use super::TypedKey;
+
+    /// Presses on every keyboard that can be read, until all of them are gone.
+    pub(super) fn watch() -> anyhow::Result<mpsc::UnboundedReceiver<TypedKey>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut keyboards = 0;
+        // Lists only the devices that could be opened.
+        for (path, device) in evdev::enumerate() {
+            let is_keyboard = device
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(Key::KEY_A) && keys.contains(Key::KEY_ENTER));
+            if !is_keyboard {
+                continue;
+            }
+            let mut events = match device.into_event_stream() {
+                Ok(events) => events,
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "cannot watch keyboard");
+                    continue;
+                }
+            };
+            debug!(path = %path.display(), "watching keyboard");
+            keyboards += 1;
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut modifiers = Modifiers::default();
+                loop {
+                    let event = match events.next_event().await {
+                        Ok(event) => event,
+                        Err(e) => {
+                            debug!(path = %path.display(), error = %e, "keyboard gone");
+                            return;
+                        }
+                    };
+                    let InputEventKind::Key(key) = event.kind() else {
+                        continue;
+                    };
+                    // 0 = release, 1 = press, 2 = auto-repeat.
+                    if modifiers.update(key, event.value()) || event.value() == 0 {
+                        continue;
+                    }
+                    if tx.send(modifiers.typed(key)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        if keyboards == 0 {
+            anyhow::bail!(
+                "no keyboard can be read; the user needs access to /dev/input (the `input` group)"
+            );
+        }
+        Ok(rx)
+    }
+
+    #[derive(Debug, Default)]
+    struct Modifiers {
+        shift: bool,
+        caps_lock: bool,
+        /// Control, Alt or Super, which make a press a shortcut.
+        shortcut: bool,
+    }
+
+    impl Modifiers {
+        /// Track `key` going to `value` if it is a modifier; returns whether it was one.
+        fn update(&mut self, key: Key, value: i32) -> bool {
+            let pressed = value != 0;
+            match key {
+                Key::KEY_LEFTSHIFT | Key::KEY_RIGHTSHIFT => self.shift = pressed,
+                Key::KEY_LEFTCTRL
+                | Key::KEY_RIGHTCTRL
+                | Key::KEY_LEFTALT
+                | Key::KEY_RIGHTALT
+                | Key::KEY_LEFTMETA
+                | Key::KEY_RIGHTMETA => self.shortcut = pressed,
+                Key::KEY_CAPSLOCK => {
+                    if value == 1 {
+                        self.caps_lock = !self.caps_lock;
+                    }
+                }
+                _ => return false,
+            }
+            true
+        }
+
+        fn typed(&self, key: Key) -> TypedKey {
+            if self.shortcut {
+                return TypedKey::Other;
+            }
+            if key == Key::KEY_BACKSPACE {
+                return TypedKey::Backspace;
+            }
+            match us_char(key, self.shift) {
+                Some(c) if c.is_ascii_lowercase() && self.caps_lock => {
+                    TypedKey::Char(c.to_ascii_uppercase())
+                }
+                Some(c) if c.is_ascii_uppercase() && self.caps_lock => {
+                    TypedKey::Char(c.to_ascii_lowercase())
+                }
+                Some(c) => TypedKey::Char(c),
+                None => TypedKey::Other,
+            }
+        }
+    }
+
+    /// The character `key` types on a US layout.
+    fn us_char(key: Key, shift: bool) -> Option<char> {
+        let (plain, shifted) = match key {
+            Key::KEY_SPACE => (' ', ' '),
+            Key::KEY_1 => ('1', '!'),
+            Key::KEY_2 => ('2', '@'),
+            Key::KEY_3 => ('3', '#'),
+            Key::KEY_4 => ('4', '$'),
+            Key::KEY_5 => ('5', '%'),
+            Key::KEY_6 => ('6', '^'),
+            Key::KEY_7 => ('7', '&'),
+            Key::KEY_8 => ('8', '*'),
+            Key::KEY_9 => ('9', '('),
+            Key::KEY_0 => ('0', ')'),
+            Key::KEY_MINUS => ('-', '_'),
+            Key::KEY_EQUAL => ('=', '+'),
+            Key::KEY_LEFTBRACE => ('[', '{'),
+            Key::KEY_RIGHTBRACE => (']', '}'),
+            Key::KEY_SEMICOLON => (';', ':'),
+            Key::KEY_APOSTROPHE => ('\'', '"'),
+            Key::KEY_GRAVE => ('`', '~'),
+            Key::KEY_BACKSLASH => ('\\', '|'),
+            Key::KEY_COMMA => (',', '<'),
+            Key::KEY_DOT => ('.', '>'),
+            Key::KEY_SLASH => ('/', '?'),
+            key => {
+                let c = letter(key)?;
+                (c, c.to_ascii_uppercase())
+            }
+        };
+        Some(if shift { shifted } else { plain })
+    }
+
+    fn letter(key: Key) -> Option<char> {
+        // Letter keys are numbered by keyboard row, not alphabetically.
+        const ROWS: [(Key, &str); 3] = [
+            (Key::KEY_Q, "qwertyuiop"),
+            (Key::KEY_A, "asdfghjkl"),
+            (Key::KEY_Z, "zxcvbnm"),
+        ];
+        ROWS.iter().find_map(|(first, row)| {
+            let offset = key.code().checked_sub(first.code())?;
+            row.chars().nth(usize::from(offset))
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod keyboard {
+    use tokio::sync::mpsc;
+
+    use super::TypedKey;
+
+    pub(super) fn watch() -> anyhow::Result<mpsc::UnboundedReceiver<TypedKey>> {
+        anyhow::bail!("watching the keyboard is only supported on Linux so far")
+    }
+}
@@ -0,0 +1,332 @@
+//! VS Code integration for `vs_code` actions: the folders VS Code has opened, the tasks in their
+//! `.vscode/tasks.json`, opening a folder with the `code` CLI and running a task.
+//!
+//! Recent folders come from VS Code's own `globalStorage/storage.json`. VS Code has no CLI or IPC
+//! for running a task, so tasks run the way its task runner would, outside of it: `shell` tasks
+//! in the configured shell (see [`crate::platform::Shell`]), `process` tasks directly, both in
+//! the folder with `${workspaceFolder}` and `${env:NAME}` expanded. Other task types, problem
+//! matchers and `dependsOn` are not supported.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::Context;
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::platform::Shell;
+
+/// A task from a folder's `tasks.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub label: String,
+    command: String,
+    args: Vec<String>,
+    /// `shell` task rather than `process`.
+    shell: bool,
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+}
+
+/// Where a task started from a key is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Folders VS Code has opened, sorted; ones that no longer exist are left out.
+pub fn recent_workspaces() -> Vec<String> {
+    let Some(path) = storage_path() else {
+        return Vec::new();
+    };
+    let Ok(data) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let Ok(storage) = serde_json::from_str::<Value>(&data) else {
+        debug!(path = %path.display(), "unreadable VS Code storage");
+        return Vec::new();
+    };
+
+    let mut uris: Vec<&str> = Vec::new();
+    if let Some(workspaces) = storage["profileAssociations"]["workspaces"].as_object() {
+        uris.extend(workspaces.keys().map(String::as_str));
+    }
+    let windows = &storage["windowsState"];
+    uris.extend(windows["lastActiveWindow"]["folder"].as_str());
+    if let Some(opened) = windows["openedWindows"].as_array() {
+        uris.extend(opened.iter().filter_map(|w| w["folder"].as_str()));
+    }
+    if let Some(folders) = storage["backupWorkspaces"]["folders"].as_array() {
+        uris.extend(folders.iter().filter_map(|f| f["folderUri"].as_str()));
+    }
+
+    let mut folders: Vec<String> = uris
+        .into_iter()
+        .filter_map(file_uri_path)
+        .filter(|p| Path::new(p).is_dir())
+        .collect();
+    folders.sort();
+    folders.dedup();
+    folders
+}
+
+fn storage_path() -> Option<PathBuf> {
+    let config = directories::BaseDirs::new()?.config_dir().to_path_buf();
+    Some(config.join("Code/User/globalStorage/storage.json"))
+}
+
+/// Path of a `file://` URI; `None` for remote ones (`vscode-remote://` and the like).
+fn file_uri_path(uri: &str) -> Option<String> {
+    let path = percent_decode(uri.strip_prefix("file://")?);
+    // Windows URIs look like file:///c%3A/Users/...
+    let bytes = path.as_bytes();
+    if cfg!(windows) && bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+        return Some(path[1..].to_string());
+    }
+    Some(path)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The `shell` and `process` tasks of `workspace`, in file order; none when it has no
+/// `tasks.json`.
+pub fn tasks(workspace: &str) -> anyhow::Result<Vec<Task>> {
+    let path = Path::new(workspace).join(".vscode").join("tasks.json");
+    let data = match std::fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let file: Value = serde_json::from_str(&strip_jsonc(&data))
+        .with_context(|| format!("invalid {}", path.display()))?;
+    let Some(entries) = file["tasks"].as_array() else {
+        return Ok(Vec::new());
+    };
+    Ok(entries
+        .iter()
+        .filter_map(|entry| parse_task(&file, entry))
+        .collect())
+}
+
+/// Settings are looked up in the task's own platform section, then the task, then the file's
+/// platform section and top level, as VS Code does.
+fn parse_task(file: &Value, entry: &Value) -> Option<Task> {
+    let os = if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "osx"
+    } else {
+        "linux"
+    };
+    let scopes = [&entry[os], entry, &file[os], file];
+    let setting = |key: &str| scopes.iter().map(|s| &s[key]).find(|v| !v.is_null());
+
+    let shell = match setting("type").and_then(Value::as_str) {
+        Some("shell") => true,
+        Some("process") => false,
+        _ => return None,
+    };
+    let command = string_of(setting("command")?)?;
+    let label = entry["label"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| command.clone());
+    let args = setting("args")
+        .and_then(Value::as_array)
+        .map(|args| args.iter().filter_map(string_of).collect())
+        .unwrap_or_default();
+    let options = setting("options");
+    let cwd = options.and_then(|o| o["cwd"].as_str()).map(str::to_string);
+    let env = options
+        .and_then(|o| o["env"].as_object())
+        .map(|env| {
+            env.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Task {
+        label,
+        command,
+        args,
+        shell,
+        cwd,
+        env,
+    })
+}
+
+/// A command or argument: a string, a quoted-string object or, for commands, a list of parts.
+fn string_of(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(o) => o.get("value").and_then(string_of),
+        Value::Array(parts) => {
+            let parts: Vec<String> = parts.iter().filter_map(string_of).collect();
+            Some(parts.join(" "))
+        }
+        _ => None,
+    }
+}
+
+/// `tasks.json` is JSON with comments and trailing commas; drop both.
+fn strip_jsonc(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            (']' | '}', _) => {
+                let kept = out.trim_end().len();
+                if out[..kept].ends_with(',') {
+                    out.truncate(kept - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Expand the variables a task can use that make sense outside VS Code.
+fn expand(value: &str, workspace: &str) -> String {
+    let basename = Path::new(workspace)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut out = value
+        .replace("${workspaceFolder}", workspace)
+        .replace("${workspaceRoot}", workspace)
+        .replace("${workspaceFolderBasename}", &basename)
+        .replace("${pathSeparator}", std::path::MAIN_SEPARATOR_STR);
+    while let Some(start) = out.find("${env:") {
+        let Some(len) = out[start..].find('}') else {
+            break;
+        };
+        let name = &out[start + 6..start + len];
+        let value = std::env::var(name).unwrap_or_default();
+        out.replace_range(start..=start + len, &value);
+    }
+    out
+}
+
+/// Open `workspace` in VS Code.
+pub async fn open(workspace: &str) -> anyhow::Result<()> {
+    let workspace = workspace.trim();
+    if workspace.is_empty() {
+        anyhow::bail!("[VS Code] No folder set");
+    }
+    // The CLI is a batch file on Windows, which Command only finds by its full name.
+    let program = if cfg!(windows) { "code.cmd" } else { "code" };
+    let status = Command::new(program)
+        .arg(workspace)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("failed to run code; is VS Code's command-line launcher on PATH?")?;
+    if !status.success() {
+        anyhow::bail!("[VS Code] Opening {workspace} failed ({status})");
+    }
+    Ok(())
+}
+
+/// Run the task labelled `label` in `workspace` to completion.
+pub async fn run_task(workspace: &str, label: &str) -> anyhow::Result<()> {
+    let found = tasks(workspace)?;
+    let Some(task) = found.into_iter().find(|t| t.label == label) else {
+        anyhow::bail!("[VS Code] No shell or process task {label:?} in {workspace}");
+    };
+    let command = expand(&task.command, workspace);
+    let args: Vec<String> = task.args.iter().map(|a| expand(a, workspace)).collect();
+    let mut cmd = if task.shell {
+        // VS Code quotes arguments with spaces the same way for every shell.
+        let mut line = command;
+        for arg in &args {
+            line.push(' ');
+            if arg.contains(char::is_whitespace) {
+                line.push_str(&format!("\"{arg}\""));
+            } else {
+                line.push_str(arg);
+            }
+        }
+        Shell::current().command(&line)
+    } else {
+        let mut cmd = Command::new(command);
+        cmd.args(args);
+        cmd
+    };
+    let cwd = task
+        .cwd
+        .map_or_else(|| workspace.to_string(), |c| expand(&c, workspace));
+    cmd.current_dir(cwd)
+        .envs(task.env.iter().map(|(k, v)| (k, expand(v, workspace))))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    debug!(workspace, label, "running VS Code task");
+    let status = cmd
+        .status()
+        .await
+        .with_context(|| format!("failed to start task {label:?}"))?;
+    if !status.success() {
+        anyhow::bail!("[VS Code] Task {label:?} failed ({status})");
+    }
+    Ok(())
+}
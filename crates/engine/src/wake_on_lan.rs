@@ -0,0 +1,41 @@
+//! Wake-on-LAN for `wake_on_lan` actions: a magic packet is six `0xFF` bytes followed by the
+//! target's MAC address sixteen times, broadcast over UDP so the sleeping network card sees it.
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use anyhow::Context;
+use tokio::net::UdpSocket;
+
+/// Port magic packets go to unless the broadcast address names one (the "discard" port).
+const DEFAULT_PORT: u16 = 9;
+
+/// Send a magic packet for `mac` to `broadcast` (`a.b.c.d` or `a.b.c.d:port`).
+pub async fn wake(mac: &str, broadcast: &str) -> anyhow::Result<()> {
+    let mac = actions::parse_mac_address(mac)?;
+    let to = broadcast_address(broadcast)?;
+    let mut packet = vec![0xFF; 6];
+    for _ in 0..16 {
+        packet.extend(mac);
+    }
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    socket
+        .send_to(&packet, to)
+        .await
+        .with_context(|| format!("cannot send the magic packet to {to}"))?;
+    Ok(())
+}
+
+fn broadcast_address(broadcast: &str) -> anyhow::Result<SocketAddr> {
+    let broadcast = match broadcast.trim() {
+        "" => "255.255.255.255",
+        b => b,
+    };
+    if let Ok(addr) = broadcast.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let ip: Ipv4Addr = broadcast
+        .parse()
+        .with_context(|| format!("\"{broadcast}\" is not an IPv4 address"))?;
+    Ok(SocketAddr::from((ip, DEFAULT_PORT)))
+}
@@ -0,0 +1,194 @@
+//! Embedded HTTP listener that lets external tools (Home Assistant, CI) trigger actions.
+//!
+//! Every request must present the configured token as `Authorization: Bearer <token>`; it is not
+//! accepted in the query string, which ends up in proxy and access logs. Routes:
+//!
+//! - `POST /hooks/<path>`: run the action of the webhook configured for `<path>`
+//! - `POST /keys/<index>`: run the binding of a key in the active profile, as if pressed
+//! - `PUT /variables/<name>`: set a variable to the request body; `DELETE` removes it
+//! - `/api/...`: the remote-control API (see [`crate::api`]), when enabled in the settings
+//! - `GET /metrics`: counters and timings in the Prometheus text format (see
+//!   [`app_core::metrics`]), when enabled in the settings
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::extract::{State, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use device::{ControlEvent, ControlEventKind, ControlId};
+use plugin_runtime::{InvocationControl, InvocationEvent};
+use storage::settings::WebhookSettings;
+use subtle::ConstantTimeEq;
+use tracing::{debug, error, info};
+
+use crate::{api, resolve_binding, start_sequence, EngineNotification, Shared};
+
+/// Status and plain-text message of a failed request.
+pub(crate) type ErrorResponse = (StatusCode, String);
+
+struct Listener {
+    shared: Arc<Shared>,
+    token: String,
+    api: bool,
+    metrics: bool,
+}
+
+/// A random token for [`WebhookSettings::token`] (32 hex characters).
+pub fn generate_token() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("failed to generate a webhook token: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Run the listener until the task is aborted; failures are reported to the UI.
+pub(crate) fn spawn(shared: Arc<Shared>, settings: WebhookSettings) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = serve(shared.clone(), settings).await {
+            error!(error = %e, "webhook listener stopped");
+            shared.emit(EngineNotification::ActionFailed(format!("[Webhook] {e:#}")));
+        }
+    })
+}
+
+async fn serve(shared: Arc<Shared>, settings: WebhookSettings) -> anyhow::Result<()> {
+    if settings.token.is_empty() {
+        anyhow::bail!("a token is required to start the listener");
+    }
+    let ip = if settings.listen_on_network {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let addr = SocketAddr::from((ip, settings.port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+    info!(%addr, "webhook listener started");
+
+    let app = Router::new()
+        .fallback(handle)
+        .with_state(Arc::new(Listener {
+            shared,
+            token: settings.token,
+            api: settings.api,
+            metrics: settings.metrics,
+        }));
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn handle(
+    State(listener): State<Arc<Listener>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    ws: Option<WebSocketUpgrade>,
+    body: String,
+) -> Response {
+    if !authorized(&listener.token, &headers) {
+        return (StatusCode::UNAUTHORIZED, "invalid or missing token").into_response();
+    }
+    debug!(%method, path = uri.path(), "webhook request");
+
+    let shared = &listener.shared;
+    let (route, rest) = uri
+        .path()
+        .trim_matches('/')
+        .split_once('/')
+        .unwrap_or((uri.path().trim_matches('/'), ""));
+    let res = match (method, route) {
+        (method, "api") if listener.api => api::handle(shared, method, rest, ws, body),
+        (Method::GET, "metrics") if listener.metrics && rest.is_empty() => Ok((
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            app_core::metrics::metrics().encode(),
+        )
+            .into_response()),
+        (Method::POST, "hooks") => run_hook(shared, rest).map(no_content),
+        (Method::POST, "keys") => press_key(shared, rest).map(no_content),
+        (Method::PUT, "variables") => {
+            let value = body.trim_end_matches(['\r', '\n']).to_string();
+            set_variable(shared, rest, Some(value)).map(no_content)
+        }
+        (Method::DELETE, "variables") => set_variable(shared, rest, None).map(no_content),
+        _ => Err((StatusCode::NOT_FOUND, "unknown route".to_string())),
+    };
+    res.unwrap_or_else(IntoResponse::into_response)
+}
+
+pub(crate) fn no_content(_: ()) -> Response {
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Compared in constant time, so response timings don't reveal how much of a guess was right.
+fn authorized(token: &str, headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| bool::from(t.as_bytes().ct_eq(token.as_bytes())))
+}
+
+pub(crate) fn ensure_running(shared: &Shared) -> Result<(), ErrorResponse> {
+    if shared.state().paused {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "actions are paused".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn run_hook(shared: &Arc<Shared>, path: &str) -> Result<(), ErrorResponse> {
+    ensure_running(shared)?;
+    let path = path.trim_matches('/');
+    let hook = shared
+        .state()
+        .webhooks
+        .iter()
+        .find(|w| w.path.trim_matches('/') == path)
+        .cloned();
+    let Some(hook) = hook else {
+        return Err((StatusCode::NOT_FOUND, format!("no webhook for {path}")));
+    };
+    info!(webhook = hook.id, path, "webhook fired");
+    start_sequence(
+        shared,
+        InvocationControl::Webhook { id: hook.id },
+        InvocationEvent::WebhookReceived,
+        &hook.action,
+    );
+    Ok(())
+}
+
+fn press_key(shared: &Arc<Shared>, index: &str) -> Result<(), ErrorResponse> {
+    ensure_running(shared)?;
+    let Ok(key) = index.parse::<u8>() else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("invalid key index: {index}"),
+        ));
+    };
+    let ev = ControlEvent {
+        control: ControlId::Key(key),
+        kind: ControlEventKind::Down,
+    };
+    let Some((control, event, binding)) = resolve_binding(shared, ev) else {
+        return Err((StatusCode::NOT_FOUND, format!("key {key} has no action")));
+    };
+    start_sequence(shared, control, event, &binding);
+    Ok(())
+}
+
+fn set_variable(shared: &Shared, name: &str, value: Option<String>) -> Result<(), ErrorResponse> {
+    if name.is_empty() || name.contains('/') {
+        return Err((StatusCode::BAD_REQUEST, "invalid variable name".to_string()));
+    }
+    shared
+        .variables
+        .set(name, value)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
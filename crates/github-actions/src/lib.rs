@@ -0,0 +1,267 @@
+//! Latest GitHub Actions workflow runs, for `ci_status` keys.
+//!
+//! Runs are read from the GitHub REST API with the user's token when one is set (the one in the
+//! marketplace settings), which private repositories need. Without a token GitHub allows 60
+//! requests an hour, so runs are then fetched at most every few minutes. Requests are revalidated
+//! with `If-None-Match`; GitHub does not count `304 Not Modified` answers against the limit.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actions::BuiltinAction;
+use anyhow::Context;
+use reqwest::header::{ACCEPT, AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+const API_BASE: &str = "https://api.github.com";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// How old a run may get before it is fetched again, with and without a token.
+const MAX_AGE: Duration = Duration::from_secs(60);
+const MAX_AGE_UNAUTHENTICATED: Duration = Duration::from_secs(5 * 60);
+
+/// The runs a `ci_status` binding shows.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CiQuery {
+    /// `owner/name`.
+    pub repo: String,
+    /// Workflow file name (`ci.yml`) or id; any workflow when empty.
+    pub workflow: String,
+    /// Only runs on this branch; any branch when empty.
+    pub branch: String,
+}
+
+impl CiQuery {
+    /// The query of a `ci_status` binding; `None` for other actions and without a repository.
+    pub fn of(action: &BuiltinAction) -> Option<Self> {
+        let BuiltinAction::CiStatus {
+            repo,
+            workflow,
+            branch,
+        } = action
+        else {
+            return None;
+        };
+        let repo = repo.trim().trim_matches('/');
+        (!repo.is_empty()).then(|| Self {
+            repo: repo.to_string(),
+            workflow: workflow.trim().to_string(),
+            branch: branch.trim().to_string(),
+        })
+    }
+
+    /// Page listing the query's runs on github.com.
+    pub fn runs_url(&self) -> String {
+        let mut url = format!("https://github.com/{}/actions", self.repo);
+        if !self.workflow.is_empty() {
+            url.push_str(&format!("/workflows/{}", self.workflow));
+        }
+        url
+    }
+
+    fn api_url(&self) -> String {
+        let mut url = format!("{API_BASE}/repos/{}/actions", self.repo);
+        if !self.workflow.is_empty() {
+            url.push_str(&format!("/workflows/{}", self.workflow));
+        }
+        url.push_str("/runs");
+        url
+    }
+}
+
+/// Where a run is, or how it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Queued,
+    InProgress,
+    Success,
+    Failure,
+    Cancelled,
+    /// Skipped, neutral or another ending that is neither a pass nor a failure.
+    Other,
+}
+
+impl RunState {
+    pub fn label(self) -> &'static str {
+        match self {
+            RunState::Queued => "Queued",
+            RunState::InProgress => "Running",
+            RunState::Success => "Passing",
+            RunState::Failure => "Failing",
+            RunState::Cancelled => "Cancelled",
+            RunState::Other => "Done",
+        }
+    }
+
+    fn of(status: &str, conclusion: Option<&str>) -> Self {
+        match (status, conclusion) {
+            ("completed", Some("success")) => RunState::Success,
+            ("completed", Some("failure" | "timed_out" | "startup_failure")) => RunState::Failure,
+            ("completed", Some("cancelled")) => RunState::Cancelled,
+            ("completed", _) => RunState::Other,
+            ("in_progress", _) => RunState::InProgress,
+            _ => RunState::Queued,
+        }
+    }
+}
+
+/// A workflow run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    /// Name of the workflow.
+    pub name: String,
+    pub number: u64,
+    pub branch: String,
+    pub state: RunState,
+    /// The run's page on github.com.
+    pub url: String,
+}
+
+/// Latest run of a query (`None` when it has none), when it was fetched and its `ETag`.
+struct Cached {
+    run: Option<Run>,
+    fetched: Instant,
+    etag: Option<String>,
+}
+
+struct Shared {
+    http: reqwest::Client,
+    token: Mutex<Option<String>>,
+    runs: Mutex<HashMap<CiQuery, Cached>>,
+}
+
+/// Handle to the client. Cheap to clone; all clones share one cache and token.
+#[derive(Clone)]
+pub struct GithubActions {
+    shared: Arc<Shared>,
+}
+
+impl Default for GithubActions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GithubActions {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent("RiverDeck-Redux/0.1 (GitHub Actions)")
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            shared: Arc::new(Shared {
+                http,
+                token: Mutex::new(None),
+                runs: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Use `token` (a personal access token) for requests from now on; none when `None`.
+    pub fn set_token(&self, token: Option<String>) {
+        *self.shared.token.lock().expect("token mutex poisoned") = token;
+    }
+
+    /// The latest run of `query`, fetched when the one in the cache is older than the polling
+    /// interval.
+    pub async fn latest_run(&self, query: &CiQuery) -> anyhow::Result<Option<Run>> {
+        let max_age = if self.token().is_some() {
+            MAX_AGE
+        } else {
+            MAX_AGE_UNAUTHENTICATED
+        };
+        if let Some(c) = self.runs().get(query) {
+            if c.fetched.elapsed() < max_age {
+                return Ok(c.run.clone());
+            }
+        }
+        self.fetch_latest_run(query).await
+    }
+
+    /// The latest run of `query`, fetched now.
+    pub async fn fetch_latest_run(&self, query: &CiQuery) -> anyhow::Result<Option<Run>> {
+        let etag = self.runs().get(query).and_then(|c| c.etag.clone());
+        let mut request = self
+            .shared
+            .http
+            .get(query.api_url())
+            .query(&[("per_page", "1")])
+            .header(ACCEPT, "application/vnd.github+json");
+        if !query.branch.is_empty() {
+            request = request.query(&[("branch", &query.branch)]);
+        }
+        if let Some(token) = self.token() {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            let mut runs = self.runs();
+            let cached = runs.get_mut(query).context("no cached run to revalidate")?;
+            cached.fetched = Instant::now();
+            return Ok(cached.run.clone());
+        }
+        if status == StatusCode::NOT_FOUND {
+            anyhow::bail!(
+                "[GitHub Actions] {} not found (private repositories need a token)",
+                query.repo
+            );
+        }
+        let limited = response
+            .headers()
+            .get("x-ratelimit-remaining")
+            .is_some_and(|v| v.as_bytes() == b"0");
+        if status == StatusCode::TOO_MANY_REQUESTS || (status == StatusCode::FORBIDDEN && limited) {
+            anyhow::bail!("[GitHub Actions] GitHub API rate limit reached; a token raises it");
+        }
+        let response = response.error_for_status()?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body: Value = response.json().await?;
+        let run = body["workflow_runs"].get(0).map(run_of);
+        self.runs().insert(
+            query.clone(),
+            Cached {
+                run: run.clone(),
+                fetched: Instant::now(),
+                etag,
+            },
+        );
+        Ok(run)
+    }
+
+    fn token(&self) -> Option<String> {
+        self.shared
+            .token
+            .lock()
+            .expect("token mutex poisoned")
+            .clone()
+    }
+
+    fn runs(&self) -> std::sync::MutexGuard<'_, HashMap<CiQuery, Cached>> {
+        self.shared.runs.lock().expect("runs mutex poisoned")
+    }
+}
+
+fn run_of(run: &Value) -> Run {
+    let text = |key: &str| run[key].as_str().unwrap_or_default().to_string();
+    Run {
+        name: text("name"),
+        number: run["run_number"].as_u64().unwrap_or_default(),
+        branch: text("head_branch"),
+        state: RunState::of(
+            run["status"].as_str().unwrap_or_default(),
+            run["conclusion"].as_str(),
+        ),
+        url: text("html_url"),
+    }
+}
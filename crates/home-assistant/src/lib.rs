@@ -0,0 +1,381 @@
+//! Home Assistant client over its WebSocket API.
+//!
+//! [`HomeAssistant`] keeps one connection open while enabled (reconnecting after failures),
+//! mirrors entity states so keys can show them, and calls services for `home_assistant` actions.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use anyhow::Context;
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use storage::settings::HomeAssistantSettings;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Request ids; Home Assistant requires them to increase within a connection.
+const GET_STATES_ID: u64 = 1;
+const SUBSCRIBE_ID: u64 = 2;
+const FIRST_CALL_ID: u64 = 3;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The state of one entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityState {
+    pub entity_id: String,
+    /// Raw state, e.g. `on`, `off` or `21.5`.
+    pub state: String,
+    /// The `unit_of_measurement` attribute, e.g. `°C`.
+    pub unit: Option<String>,
+}
+
+impl EntityState {
+    /// Whether the entity is in its "active" state (light on, door open, media playing, ...).
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.state.as_str(),
+            "on" | "open" | "opening" | "unlocked" | "playing" | "home"
+        )
+    }
+
+    /// Whether the state is one of two (on/off-like), so a color shows it better than text.
+    pub fn is_binary(&self) -> bool {
+        self.is_active()
+            || matches!(
+                self.state.as_str(),
+                "off" | "closed" | "closing" | "locked" | "paused" | "idle" | "not_home"
+            )
+    }
+
+    /// The state with its unit, e.g. `21.5 °C`.
+    pub fn display(&self) -> String {
+        match &self.unit {
+            Some(unit) => format!("{} {unit}", self.state),
+            None => self.state.clone(),
+        }
+    }
+}
+
+/// Connection status changes and entity updates, for UIs.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Connected,
+    /// The connection failed, dropped or was turned off (with the reason).
+    Disconnected(String),
+    State(EntityState),
+}
+
+struct ServiceCall {
+    domain: String,
+    service: String,
+    entity_id: Option<String>,
+    data: Value,
+    reply: oneshot::Sender<anyhow::Result<()>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// The connection task and the settings it was started with.
+    running: Option<(HomeAssistantSettings, tokio::task::JoinHandle<()>)>,
+    /// Set while connected and authenticated.
+    calls: Option<mpsc::UnboundedSender<ServiceCall>>,
+    states: BTreeMap<String, EntityState>,
+    subscribers: Vec<mpsc::UnboundedSender<Event>>,
+}
+
+impl Inner {
+    fn publish(&mut self, event: Event) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Handle to the client. Cheap to clone; all clones share one connection.
+#[derive(Clone, Default)]
+pub struct HomeAssistant {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl HomeAssistant {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect, reconnect or disconnect to match `settings`; no-op when unchanged.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn configure(&self, settings: HomeAssistantSettings) {
+        let mut inner = lock(&self.inner);
+        let wanted = (settings.enabled && !settings.token.is_empty()).then_some(settings);
+        if inner.running.as_ref().map(|(s, _)| s) == wanted.as_ref() {
+            return;
+        }
+        if let Some((_, task)) = inner.running.take() {
+            task.abort();
+            inner.calls = None;
+            inner.states.clear();
+            inner.publish(Event::Disconnected("Disconnected".to_string()));
+        }
+        inner.running = wanted.map(|s| {
+            let task = tokio::spawn(run(Arc::downgrade(&self.inner), s.clone()));
+            (s, task)
+        });
+    }
+
+    /// Last known state of every entity.
+    pub fn states(&self) -> BTreeMap<String, EntityState> {
+        lock(&self.inner).states.clone()
+    }
+
+    /// Receive status changes and entity updates from now on.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<Event> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        lock(&self.inner).subscribers.push(tx);
+        rx
+    }
+
+    /// Call `service` (`domain.service`, e.g. `light.toggle`), optionally targeting `entity_id`.
+    pub async fn call_service(
+        &self,
+        service: &str,
+        entity_id: Option<&str>,
+        data: Value,
+    ) -> anyhow::Result<()> {
+        let Some((domain, service)) = service.trim().split_once('.') else {
+            anyhow::bail!("[Home Assistant] Invalid service {service:?}; expected domain.service");
+        };
+        let calls = lock(&self.inner).calls.clone();
+        let Some(calls) = calls else {
+            anyhow::bail!("[Home Assistant] Not connected");
+        };
+        let (reply, result) = oneshot::channel();
+        calls
+            .send(ServiceCall {
+                domain: domain.to_string(),
+                service: service.to_string(),
+                entity_id: entity_id.map(str::to_string),
+                data,
+                reply,
+            })
+            .map_err(|_| anyhow::anyhow!("[Home Assistant] Not connected"))?;
+        tokio::time::timeout(CALL_TIMEOUT, result)
+            .await
+            .map_err(|_| anyhow::anyhow!("[Home Assistant] {domain}.{service} timed out"))?
+            .map_err(|_| {
+                anyhow::anyhow!("[Home Assistant] Connection lost during {domain}.{service}")
+            })?
+    }
+}
+
+fn lock(inner: &Mutex<Inner>) -> std::sync::MutexGuard<'_, Inner> {
+    inner.lock().expect("home assistant mutex poisoned")
+}
+
+/// Keep a session open until the client is reconfigured or dropped.
+async fn run(inner: Weak<Mutex<Inner>>, settings: HomeAssistantSettings) {
+    loop {
+        let res = session(&inner, &settings).await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let reason = match res {
+            Ok(()) => "Connection closed".to_string(),
+            Err(e) => format!("{e:#}"),
+        };
+        warn!(reason = %reason, "Home Assistant disconnected");
+        {
+            let mut inner = lock(&inner);
+            inner.calls = None;
+            inner.publish(Event::Disconnected(reason));
+        }
+        drop(inner);
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// `ws(s)://host/api/websocket` for a base URL like `http://homeassistant.local:8123`.
+fn websocket_url(base: &str) -> String {
+    let base = base.trim().trim_end_matches('/');
+    let base = if let Some(rest) = base.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = base.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else if base.starts_with("ws://") || base.starts_with("wss://") {
+        base.to_string()
+    } else {
+        format!("ws://{base}")
+    };
+    format!("{base}/api/websocket")
+}
+
+async fn session(
+    inner: &Weak<Mutex<Inner>>,
+    settings: &HomeAssistantSettings,
+) -> anyhow::Result<()> {
+    let url = websocket_url(&settings.url);
+    let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .with_context(|| format!("failed to connect to {url}"))?;
+    let (mut sink, mut stream) = socket.split();
+
+    let hello = receive(&mut stream).await?;
+    if hello["type"] != "auth_required" {
+        anyhow::bail!("unexpected greeting: {hello}");
+    }
+    send(
+        &mut sink,
+        json!({ "type": "auth", "access_token": settings.token }),
+    )
+    .await?;
+    let auth = receive(&mut stream).await?;
+    match auth["type"].as_str() {
+        Some("auth_ok") => {}
+        Some("auth_invalid") => anyhow::bail!(
+            "authentication failed: {}",
+            auth["message"].as_str().unwrap_or("invalid token")
+        ),
+        _ => anyhow::bail!("unexpected authentication reply: {auth}"),
+    }
+
+    send(
+        &mut sink,
+        json!({ "id": GET_STATES_ID, "type": "get_states" }),
+    )
+    .await?;
+    send(
+        &mut sink,
+        json!({ "id": SUBSCRIBE_ID, "type": "subscribe_events", "event_type": "state_changed" }),
+    )
+    .await?;
+
+    let (calls_tx, mut calls) = mpsc::unbounded_channel();
+    {
+        let Some(inner) = inner.upgrade() else {
+            return Ok(());
+        };
+        let mut inner = lock(&inner);
+        inner.calls = Some(calls_tx);
+        inner.publish(Event::Connected);
+    }
+    info!(%url, "connected to Home Assistant");
+
+    let mut next_id = FIRST_CALL_ID;
+    let mut pending: HashMap<u64, oneshot::Sender<anyhow::Result<()>>> = HashMap::new();
+    loop {
+        tokio::select! {
+            msg = receive(&mut stream) => {
+                let msg = msg?;
+                let Some(inner) = inner.upgrade() else {
+                    return Ok(());
+                };
+                handle_message(&inner, msg, &mut pending);
+            }
+            Some(call) = calls.recv() => {
+                let id = next_id;
+                next_id += 1;
+                let mut request = json!({
+                    "id": id,
+                    "type": "call_service",
+                    "domain": call.domain,
+                    "service": call.service,
+                });
+                if !call.data.is_null() {
+                    request["service_data"] = call.data;
+                }
+                if let Some(entity_id) = call.entity_id {
+                    request["target"] = json!({ "entity_id": entity_id });
+                }
+                pending.insert(id, call.reply);
+                send(&mut sink, request).await?;
+            }
+        }
+    }
+}
+
+fn handle_message(
+    inner: &Mutex<Inner>,
+    msg: Value,
+    pending: &mut HashMap<u64, oneshot::Sender<anyhow::Result<()>>>,
+) {
+    let id = msg["id"].as_u64();
+    match msg["type"].as_str() {
+        Some("result") if id == Some(GET_STATES_ID) => {
+            let states: Vec<_> = msg["result"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(parse_state)
+                .collect();
+            debug!(entities = states.len(), "received Home Assistant states");
+            let mut inner = lock(inner);
+            for state in states {
+                inner.states.insert(state.entity_id.clone(), state.clone());
+                inner.publish(Event::State(state));
+            }
+        }
+        Some("result") => {
+            let Some(reply) = id.and_then(|id| pending.remove(&id)) else {
+                return;
+            };
+            let res = if msg["success"].as_bool() == Some(true) {
+                Ok(())
+            } else {
+                let message = msg["error"]["message"].as_str().unwrap_or("call failed");
+                Err(anyhow::anyhow!("[Home Assistant] {message}"))
+            };
+            let _ = reply.send(res);
+        }
+        Some("event") => {
+            let Some(state) = parse_state(&msg["event"]["data"]["new_state"]) else {
+                return;
+            };
+            let mut inner = lock(inner);
+            if inner.states.get(&state.entity_id) == Some(&state) {
+                return;
+            }
+            inner.states.insert(state.entity_id.clone(), state.clone());
+            inner.publish(Event::State(state));
+        }
+        _ => debug!(%msg, "ignoring Home Assistant message"),
+    }
+}
+
+fn parse_state(state: &Value) -> Option<EntityState> {
+    Some(EntityState {
+        entity_id: state["entity_id"].as_str()?.to_string(),
+        state: state["state"].as_str()?.to_string(),
+        unit: state["attributes"]["unit_of_measurement"]
+            .as_str()
+            .map(str::to_string),
+    })
+}
+
+async fn send(sink: &mut SplitSink<Socket, Message>, msg: Value) -> anyhow::Result<()> {
+    sink.send(Message::Text(msg.to_string())).await?;
+    Ok(())
+}
+
+/// The next JSON message; fails when the connection closes.
+async fn receive(stream: &mut SplitStream<Socket>) -> anyhow::Result<Value> {
+    loop {
+        let msg = stream
+            .next()
+            .await
+            .context("connection closed")?
+            .context("connection failed")?;
+        match msg {
+            Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+            Message::Close(_) => anyhow::bail!("connection closed by Home Assistant"),
+            // Pings are answered by the library.
+            _ => {}
+        }
+    }
+}
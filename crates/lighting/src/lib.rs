@@ -0,0 +1,509 @@
+//! Philips Hue, WLED and Elgato Key Light lights for `light` actions.
+//!
+//! Hue lights are switched through the bridge's local REST API (v1, over plain HTTP) with the
+//! application key it hands out while its link button is pressed; WLED controllers and Key Lights
+//! through their own HTTP APIs. All of them announce themselves over mDNS, see [`discover`].
+//!
+//! Brightness steps from a turning dial arrive faster than a light takes requests, so they are
+//! coalesced: while one request for a light is on its way, later steps only move the brightness
+//! it should end up at, and that is sent once the request returns.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use actions::{LightCommand, LightTarget};
+use anyhow::Context;
+use serde_json::{json, Value};
+use storage::settings::LightingSettings;
+
+mod mdns;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const HUE_SERVICE: &str = "_hue._tcp.local";
+const WLED_SERVICE: &str = "_wled._tcp.local";
+const KEY_LIGHT_SERVICE: &str = "_elg._tcp.local";
+
+/// Port of the Key Light API, which Key Lights announce but never change.
+const KEY_LIGHT_PORT: u16 = 9123;
+
+/// Color temperatures Key Lights take, in kelvin.
+const KEY_LIGHT_KELVIN: std::ops::RangeInclusive<u16> = 2900..=7000;
+
+/// Whether a light is on, and how bright.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightState {
+    pub on: bool,
+    /// In percent; what the light returns to when it is off.
+    pub brightness: u8,
+}
+
+/// A kind of device [`discover`] finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    HueBridge,
+    Wled,
+    KeyLight,
+}
+
+/// A device found on the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovered {
+    pub kind: DeviceKind,
+    pub name: String,
+    /// Address, with the port when it is not the device's usual one.
+    pub host: String,
+}
+
+impl std::fmt::Display for Discovered {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.host)
+    }
+}
+
+/// A light of the paired Hue bridge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HueLight {
+    /// The bridge's id for it, e.g. `3`.
+    pub id: String,
+    pub name: String,
+}
+
+impl std::fmt::Display for HueLight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Hue bridges, WLED controllers and Key Lights answering on the local network within `wait`,
+/// sorted by name.
+pub async fn discover(wait: Duration) -> anyhow::Result<Vec<Discovered>> {
+    let services = [HUE_SERVICE, WLED_SERVICE, KEY_LIGHT_SERVICE];
+    let instances = mdns::browse(&services, wait).await?;
+    let mut found: Vec<Discovered> = Vec::new();
+    for instance in instances {
+        let (kind, usual_port) = match instance.service.as_str() {
+            // Bridges announce their HTTPS port; the v1 API is on port 80 as well.
+            HUE_SERVICE => (DeviceKind::HueBridge, instance.port),
+            KEY_LIGHT_SERVICE => (DeviceKind::KeyLight, Some(KEY_LIGHT_PORT)),
+            _ => (DeviceKind::Wled, Some(80)),
+        };
+        let host = match instance.port {
+            Some(port) if Some(port) != usual_port => format!("{}:{port}", instance.addr),
+            _ => instance.addr.to_string(),
+        };
+        if !found.iter().any(|d| d.kind == kind && d.host == host) {
+            found.push(Discovered {
+                kind,
+                name: instance.name,
+                host,
+            });
+        }
+    }
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(found)
+}
+
+/// A change to send to a light.
+#[derive(Debug, Default)]
+struct Change {
+    on: Option<bool>,
+    brightness: Option<u8>,
+    rgb: Option<[u8; 3]>,
+    /// White color temperature, in kelvin.
+    kelvin: Option<u16>,
+}
+
+impl Change {
+    fn to(state: LightState) -> Self {
+        Self {
+            on: Some(state.on),
+            brightness: state.on.then_some(state.brightness),
+            ..Self::default()
+        }
+    }
+}
+
+struct Shared {
+    http: reqwest::Client,
+    settings: Mutex<LightingSettings>,
+    /// Last known (or, while dimming, wanted) state of each light.
+    states: Mutex<HashMap<LightTarget, LightState>>,
+    /// Lights a brightness step is being sent to.
+    sending: Mutex<HashSet<LightTarget>>,
+}
+
+/// Handle to the lights. Cheap to clone; all clones share one cache and settings.
+#[derive(Clone)]
+pub struct Lights {
+    shared: Arc<Shared>,
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lights {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            shared: Arc::new(Shared {
+                http,
+                settings: Mutex::new(LightingSettings::default()),
+                states: Mutex::new(HashMap::new()),
+                sending: Mutex::new(HashSet::new()),
+            }),
+        }
+    }
+
+    /// Use the Hue bridge of `settings` from now on.
+    pub fn configure(&self, settings: LightingSettings) {
+        *lock(&self.shared.settings) = settings;
+    }
+
+    /// The state `light` was last read or set to, if any.
+    pub fn cached(&self, light: &LightTarget) -> Option<LightState> {
+        self.states().get(light).copied()
+    }
+
+    /// Read the state of `light`.
+    pub async fn state(&self, light: &LightTarget) -> anyhow::Result<LightState> {
+        let state = match light {
+            LightTarget::Hue { light } => {
+                let body = self.hue("GET", &format!("lights/{light}"), None).await?;
+                let state = &body["state"];
+                LightState {
+                    on: state["on"].as_bool().unwrap_or(false),
+                    brightness: percent_of(state["bri"].as_u64().unwrap_or(254), 254),
+                }
+            }
+            LightTarget::Wled { host } => {
+                let url = format!("http://{}/json/state", host.trim());
+                let response = self.shared.http.get(url).send().await?;
+                let body: Value = response.error_for_status()?.json().await?;
+                LightState {
+                    on: body["on"].as_bool().unwrap_or(false),
+                    brightness: percent_of(body["bri"].as_u64().unwrap_or(255), 255),
+                }
+            }
+            LightTarget::KeyLight { host } => {
+                let response = self.shared.http.get(key_light_url(host)).send().await?;
+                let body: Value = response.error_for_status()?.json().await?;
+                let light = &body["lights"][0];
+                LightState {
+                    on: light["on"].as_u64() == Some(1),
+                    brightness: light["brightness"].as_u64().unwrap_or(100).min(100) as u8,
+                }
+            }
+        };
+        // While a dial's steps are being sent, where they are going is what to show.
+        if !lock(&self.shared.sending).contains(light) {
+            self.states().insert(light.clone(), state);
+        }
+        Ok(state)
+    }
+
+    /// Run `command` on `light`; a brightness step counts `steps` times (the detents a dial
+    /// turned, negative when turned back). Returns the light's new state.
+    pub async fn apply(
+        &self,
+        light: &LightTarget,
+        command: LightCommand,
+        steps: i32,
+    ) -> anyhow::Result<LightState> {
+        if let LightCommand::StepBrightness(step) = command {
+            return self.step(light, i32::from(step) * steps).await;
+        }
+        let current = match command {
+            // Toggling from a stale state would do the opposite of what the user sees.
+            LightCommand::Toggle => Some(self.state(light).await?),
+            _ => self.cached(light),
+        };
+        let brightness = current.map_or(100, |s| s.brightness);
+        let (state, change) = match command {
+            LightCommand::Toggle | LightCommand::On | LightCommand::Off => {
+                let on = match command {
+                    LightCommand::Toggle => !current.is_some_and(|s| s.on),
+                    on_off => on_off == LightCommand::On,
+                };
+                let change = Change {
+                    on: Some(on),
+                    ..Change::default()
+                };
+                (LightState { on, brightness }, change)
+            }
+            LightCommand::SetBrightness(0) => {
+                let state = LightState {
+                    on: false,
+                    brightness,
+                };
+                (state, Change::to(state))
+            }
+            LightCommand::SetBrightness(percent) => {
+                let state = LightState {
+                    on: true,
+                    brightness: percent.min(100),
+                };
+                (state, Change::to(state))
+            }
+            LightCommand::Color(rgb) => {
+                let state = LightState {
+                    on: true,
+                    brightness,
+                };
+                let change = Change {
+                    on: Some(true),
+                    rgb: Some(rgb),
+                    ..Change::default()
+                };
+                (state, change)
+            }
+            LightCommand::ColorTemperature(kelvin) => {
+                let state = LightState {
+                    on: true,
+                    brightness,
+                };
+                let change = Change {
+                    on: Some(true),
+                    kelvin: Some(kelvin),
+                    ..Change::default()
+                };
+                (state, change)
+            }
+            LightCommand::StepBrightness(_) => unreachable!("handled above"),
+        };
+        self.write(light, &change).await?;
+        self.states().insert(light.clone(), state);
+        Ok(state)
+    }
+
+    /// Change the brightness of `light` by `percent`, turning it off at 0 and on from there.
+    async fn step(&self, light: &LightTarget, percent: i32) -> anyhow::Result<LightState> {
+        let current = match self.cached(light) {
+            Some(state) => state,
+            None => self.state(light).await?,
+        };
+        let from = if current.on { current.brightness } else { 0 };
+        let brightness = (i32::from(from) + percent).clamp(0, 100) as u8;
+        // At 0 the light goes off, keeping the brightness it comes back on at.
+        let wanted = match brightness {
+            0 => LightState {
+                on: false,
+                ..current
+            },
+            brightness => LightState {
+                on: true,
+                brightness,
+            },
+        };
+        self.states().insert(light.clone(), wanted);
+        if !lock(&self.shared.sending).insert(light.clone()) {
+            // The step on its way sends this one when it returns.
+            return Ok(wanted);
+        }
+        let result = loop {
+            let Some(target) = self.cached(light) else {
+                break Ok(wanted);
+            };
+            if let Err(e) = self.write(light, &Change::to(target)).await {
+                break Err(e);
+            }
+            if self.cached(light) == Some(target) {
+                break Ok(target);
+            }
+        };
+        lock(&self.shared.sending).remove(light);
+        if result.is_err() {
+            // What the light is at is unknown now; read it next time.
+            self.states().remove(light);
+        }
+        result
+    }
+
+    async fn write(&self, light: &LightTarget, change: &Change) -> anyhow::Result<()> {
+        match light {
+            LightTarget::Hue { light } => {
+                let mut body = json!({});
+                if let Some(on) = change.on {
+                    body["on"] = json!(on);
+                }
+                if let Some(brightness) = change.brightness {
+                    body["bri"] = json!(raw_of(brightness, 254).max(1));
+                    // Shorter than the default 400 ms, so turning a dial feels direct.
+                    body["transitiontime"] = json!(2);
+                }
+                if let Some(rgb) = change.rgb {
+                    body["xy"] = json!(xy_of(rgb));
+                }
+                if let Some(kelvin) = change.kelvin {
+                    // Hue whites range from 153 (6500 K) to 500 mired (2000 K).
+                    body["ct"] = json!((1_000_000 / u32::from(kelvin.max(1))).clamp(153, 500));
+                }
+                let path = format!("lights/{light}/state");
+                self.hue("PUT", &path, Some(body)).await?;
+            }
+            LightTarget::Wled { host } => {
+                let mut body = json!({});
+                if let Some(on) = change.on {
+                    body["on"] = json!(on);
+                }
+                if let Some(brightness) = change.brightness {
+                    body["bri"] = json!(raw_of(brightness, 255));
+                }
+                if let Some([r, g, b]) = change.rgb {
+                    body["seg"] = json!([{ "col": [[r, g, b]] }]);
+                }
+                if let Some(kelvin) = change.kelvin {
+                    // Only strips with white channels of both temperatures follow this.
+                    body["seg"] = json!([{ "cct": kelvin }]);
+                }
+                let url = format!("http://{}/json/state", host.trim());
+                let response = self.shared.http.post(url).json(&body).send().await?;
+                response.error_for_status()?;
+            }
+            LightTarget::KeyLight { host } => {
+                let mut light = json!({});
+                if let Some(on) = change.on {
+                    light["on"] = json!(u8::from(on));
+                }
+                if let Some(brightness) = change.brightness {
+                    // Key Lights go no dimmer than 3%.
+                    light["brightness"] = json!(brightness.max(3));
+                }
+                if let Some(kelvin) = change.kelvin {
+                    let kelvin = kelvin.clamp(*KEY_LIGHT_KELVIN.start(), *KEY_LIGHT_KELVIN.end());
+                    // Key Lights take the temperature in mired.
+                    light["temperature"] = json!(1_000_000 / u32::from(kelvin));
+                }
+                let body = json!({ "numberOfLights": 1, "lights": [light] });
+                let url = key_light_url(host);
+                let response = self.shared.http.put(url).json(&body).send().await?;
+                response.error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The lights of the paired Hue bridge, sorted by name.
+    pub async fn hue_lights(&self) -> anyhow::Result<Vec<HueLight>> {
+        let body = self.hue("GET", "lights", None).await?;
+        let mut lights: Vec<HueLight> = body
+            .as_object()
+            .map(|lights| {
+                lights
+                    .iter()
+                    .map(|(id, light)| HueLight {
+                        id: id.clone(),
+                        name: light["name"].as_str().unwrap_or(id).to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        lights.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(lights)
+    }
+
+    /// Ask the bridge at `bridge` for an application key; its link button has to have been
+    /// pressed within the last 30 seconds.
+    pub async fn pair_hue(&self, bridge: &str) -> anyhow::Result<String> {
+        let url = format!("http://{}/api", bridge.trim());
+        let body = json!({ "devicetype": "riverdeck_redux#deck" });
+        let response = self.shared.http.post(url).json(&body).send().await?;
+        let reply: Value = response.error_for_status()?.json().await?;
+        if let Some(key) = reply[0]["success"]["username"].as_str() {
+            return Ok(key.to_string());
+        }
+        match hue_error(&reply) {
+            Some((101, _)) => anyhow::bail!("[Hue] Press the bridge's link button, then pair"),
+            Some((_, description)) => anyhow::bail!("[Hue] {description}"),
+            None => anyhow::bail!("[Hue] Unexpected answer from the bridge"),
+        }
+    }
+
+    /// Call the paired bridge's v1 API at `path` (below the application key).
+    async fn hue(&self, method: &str, path: &str, body: Option<Value>) -> anyhow::Result<Value> {
+        let settings = lock(&self.shared.settings).clone();
+        let bridge = settings.hue_bridge.trim();
+        if bridge.is_empty() || settings.hue_app_key.is_empty() {
+            anyhow::bail!("[Hue] No bridge paired (Settings → Lighting)");
+        }
+        let url = format!("http://{bridge}/api/{}/{path}", settings.hue_app_key);
+        let mut request = match method {
+            "PUT" => self.shared.http.put(url),
+            _ => self.shared.http.get(url),
+        };
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("[Hue] Cannot reach the bridge at {bridge}"))?;
+        let reply: Value = response.error_for_status()?.json().await?;
+        if let Some((_, description)) = hue_error(&reply) {
+            anyhow::bail!("[Hue] {description}");
+        }
+        Ok(reply)
+    }
+
+    fn states(&self) -> MutexGuard<'_, HashMap<LightTarget, LightState>> {
+        lock(&self.shared.states)
+    }
+}
+
+/// The first error of a bridge reply, which lists one result per change.
+fn hue_error(reply: &Value) -> Option<(u64, String)> {
+    let error = reply.as_array()?.iter().find_map(|r| r.get("error"))?;
+    let description = error["description"].as_str().unwrap_or("unknown error");
+    Some((error["type"].as_u64().unwrap_or(0), description.to_string()))
+}
+
+/// The API of the Key Light at `host`, on its usual port unless `host` names another.
+fn key_light_url(host: &str) -> String {
+    let host = host.trim();
+    if host.contains(':') {
+        format!("http://{host}/elgato/lights")
+    } else {
+        format!("http://{host}:{KEY_LIGHT_PORT}/elgato/lights")
+    }
+}
+
+fn percent_of(raw: u64, max: u64) -> u8 {
+    ((raw.min(max) * 100 + max / 2) / max) as u8
+}
+
+fn raw_of(percent: u8, max: u64) -> u64 {
+    (u64::from(percent.min(100)) * max + 50) / 100
+}
+
+/// CIE xy coordinates of an sRGB color, which Hue lights take colors as.
+fn xy_of(rgb: [u8; 3]) -> [f64; 2] {
+    let linear = |c: u8| {
+        let c = f64::from(c) / 255.0;
+        if c > 0.04045 {
+            ((c + 0.055) / 1.055).powf(2.4)
+        } else {
+            c / 12.92
+        }
+    };
+    let [r, g, b] = rgb.map(linear);
+    let x = r * 0.4124 + g * 0.3576 + b * 0.1805;
+    let y = r * 0.2126 + g * 0.7152 + b * 0.0722;
+    let z = r * 0.0193 + g * 0.1192 + b * 0.9505;
+    let sum = x + y + z;
+    if sum == 0.0 {
+        // Black has no chromaticity; white's will do.
+        return [0.3127, 0.3290];
+    }
+    [x / sum, y / sum]
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().expect("lighting mutex poisoned")
+}
@@ -0,0 +1,149 @@
+//! Just enough mDNS (RFC 6762) to browse for services: one query for their `PTR` records, sent
+//! from an ephemeral port so responders answer it directly, and the answers collected for a
+//! while.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use tracing::debug;
+
+const GROUP: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// An instance of a browsed service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Instance {
+    /// The service type, e.g. `_wled._tcp.local`.
+    pub service: String,
+    /// The instance's name without the service type, e.g. `wled-kitchen`.
+    pub name: String,
+    /// Where the answer came from.
+    pub addr: IpAddr,
+    /// From the instance's `SRV` record, when the answer had one.
+    pub port: Option<u16>,
+}
+
+/// Ask for instances of `services` and collect the answers that arrive within `wait`.
+pub(crate) async fn browse(services: &[&str], wait: Duration) -> anyhow::Result<Vec<Instance>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .context("[Lighting] Cannot open a socket for mDNS")?;
+    socket
+        .send_to(&query(services), GROUP)
+        .await
+        .context("[Lighting] Cannot send the mDNS query")?;
+
+    let deadline = Instant::now() + wait;
+    let mut found: Vec<Instance> = Vec::new();
+    let mut buf = [0u8; 9000];
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received?;
+        let Some(answers) = parse_response(&buf[..len], services, from.ip()) else {
+            debug!(%from, "malformed mDNS answer");
+            continue;
+        };
+        for instance in answers {
+            if !found.contains(&instance) {
+                found.push(instance);
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn query(services: &[&str]) -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 0];
+    packet.extend((services.len() as u16).to_be_bytes());
+    packet.extend([0; 6]);
+    for service in services {
+        for label in service.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend(TYPE_PTR.to_be_bytes());
+        packet.extend(CLASS_IN.to_be_bytes());
+    }
+    packet
+}
+
+/// The instances of `services` a response names, with the ports of those it has `SRV`
+/// records for.
+fn parse_response(packet: &[u8], services: &[&str], addr: IpAddr) -> Option<Vec<Instance>> {
+    let count = |at: usize| Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]));
+    let questions = count(4)?;
+    let records = count(6)? + count(8)? + count(10)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+    let mut instances: Vec<Instance> = Vec::new();
+    let mut ports: Vec<(String, u16)> = Vec::new();
+    for _ in 0..records {
+        let (name, after) = read_name(packet, pos)?;
+        let kind = count(after)?;
+        let len = usize::from(count(after + 8)?);
+        let data = after + 10;
+        pos = data + len;
+        if pos > packet.len() {
+            return None;
+        }
+        match kind {
+            TYPE_PTR => {
+                let Some(service) = services.iter().find(|s| s.eq_ignore_ascii_case(&name)) else {
+                    continue;
+                };
+                let (instance, _) = read_name(packet, data)?;
+                let suffix = format!(".{service}");
+                let name = instance.strip_suffix(&suffix).unwrap_or(&instance);
+                instances.push(Instance {
+                    service: service.to_string(),
+                    name: name.to_string(),
+                    addr,
+                    port: None,
+                });
+            }
+            TYPE_SRV if len >= 6 => ports.push((name, count(data + 4)?)),
+            _ => {}
+        }
+    }
+    for instance in &mut instances {
+        let full = format!("{}.{}", instance.name, instance.service);
+        instance.port = ports.iter().find(|(n, _)| *n == full).map(|(_, p)| *p);
+    }
+    Some(instances)
+}
+
+/// The dotted name at `pos`, following compression pointers, and the position after it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    // Pointers only go back, but a malformed packet could still loop.
+    for _ in 0..64 {
+        let len = *packet.get(pos)?;
+        match len {
+            0 => {
+                let name = labels.join(".");
+                return Some((name, end.unwrap_or(pos + 1)));
+            }
+            len if len & 0xC0 == 0xC0 => {
+                let target = usize::from(u16::from_be_bytes([len & 0x3F, *packet.get(pos + 1)?]));
+                end.get_or_insert(pos + 2);
+                pos = target;
+            }
+            len => {
+                let label = packet.get(pos + 1..pos + 1 + usize::from(len))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + usize::from(len);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,154 @@
+//! Zoom and Microsoft Teams meetings for `meeting` actions.
+//!
+//! Teams has a local WebSocket API for third-party devices (turned on under Settings → Privacy →
+//! Manage API), which reports the meeting's state and takes commands. The Zoom desktop client has
+//! no such API, so `meeting` keys press its keyboard shortcuts (see [`zoom_shortcut`]) and a
+//! meeting is recognized by what Zoom runs during one: its `ZOOM VoiceEngine` recording stream on
+//! Linux, its `CptHost` helper process elsewhere. Whether Zoom's microphone or camera is on stays
+//! unknown.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use actions::{MeetingApp, MeetingCommand};
+
+mod teams;
+
+/// Where the user is in an app's meetings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MeetingState {
+    pub in_meeting: bool,
+    /// Whether the microphone is muted; `None` when the app does not tell.
+    pub muted: Option<bool>,
+    /// Whether the camera is on; `None` when the app does not tell.
+    pub video_on: Option<bool>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// The Teams connection task, started the first time Teams is needed.
+    teams: Option<tokio::task::JoinHandle<()>>,
+    /// Set while connected to Teams.
+    teams_commands: Option<tokio::sync::mpsc::UnboundedSender<&'static str>>,
+    /// The meeting state Teams last sent; `None` while not connected.
+    teams_state: Option<MeetingState>,
+    /// Why the connection to Teams is down.
+    teams_error: Option<String>,
+}
+
+/// Handle to the meeting apps. Cheap to clone; all clones share one connection to Teams.
+#[derive(Clone, Default)]
+pub struct Meetings {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Meetings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the user is in `app`'s meetings. Asking for Teams connects to it, and keeps
+    /// reconnecting while it is not running.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub async fn state(&self, app: MeetingApp) -> anyhow::Result<MeetingState> {
+        match app {
+            MeetingApp::Zoom => Ok(MeetingState {
+                in_meeting: zoom_in_meeting().await?,
+                muted: None,
+                video_on: None,
+            }),
+            MeetingApp::Teams => {
+                let mut inner = self.lock();
+                self.connect_teams(&mut inner);
+                match (inner.teams_state, &inner.teams_error) {
+                    (Some(state), _) => Ok(state),
+                    (None, Some(e)) => Err(anyhow::anyhow!("{e}")),
+                    (None, None) => anyhow::bail!("[Teams] Connecting to Teams"),
+                }
+            }
+        }
+    }
+
+    /// Carry out `command` in the current Teams meeting. The first command asks in Teams to
+    /// allow RiverDeck-Redux; Teams ignores commands outside meetings.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn teams(&self, command: MeetingCommand) -> anyhow::Result<()> {
+        let mut inner = self.lock();
+        self.connect_teams(&mut inner);
+        let action = match command {
+            MeetingCommand::ToggleMute => "toggle-mute",
+            MeetingCommand::ToggleVideo => "toggle-video",
+            MeetingCommand::Leave => "leave-call",
+        };
+        let sent = inner.teams_commands.as_ref().map(|c| c.send(action));
+        match sent {
+            Some(Ok(())) => Ok(()),
+            _ => anyhow::bail!("[Teams] Not connected to Teams; is it running?"),
+        }
+    }
+
+    fn connect_teams(&self, inner: &mut Inner) {
+        if inner.teams.is_none() {
+            let task = tokio::spawn(teams::run(Arc::downgrade(&self.inner)));
+            inner.teams = Some(task);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        lock(&self.inner)
+    }
+}
+
+fn lock(inner: &Mutex<Inner>) -> MutexGuard<'_, Inner> {
+    inner.lock().expect("meetings mutex poisoned")
+}
+
+/// Zoom's keyboard shortcut for `command`, as keyboard input arguments (wtype's, e.g.
+/// `-M alt -k a -m alt`). Zoom only sees them while its window has the focus, unless they are
+/// made global in its settings; leaving confirms Zoom's prompt with Enter.
+pub fn zoom_shortcut(command: MeetingCommand) -> Vec<String> {
+    let args: &[&str] = match (command, cfg!(target_os = "macos")) {
+        (MeetingCommand::ToggleMute, false) => &["-M", "alt", "-k", "a", "-m", "alt"],
+        (MeetingCommand::ToggleVideo, false) => &["-M", "alt", "-k", "v", "-m", "alt"],
+        (MeetingCommand::Leave, false) => &["-M", "alt", "-k", "q", "-m", "alt"],
+        // Modifiers still held at the end are released.
+        (MeetingCommand::ToggleMute, true) => &["-M", "logo", "-M", "shift", "-k", "a"],
+        (MeetingCommand::ToggleVideo, true) => &["-M", "logo", "-M", "shift", "-k", "v"],
+        (MeetingCommand::Leave, true) => &["-M", "logo", "-k", "w", "-m", "logo"],
+    };
+    let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    if command == MeetingCommand::Leave {
+        args.extend(["-s", "500", "-k", "Return"].map(String::from));
+    }
+    args
+}
+
+#[cfg(target_os = "linux")]
+async fn zoom_in_meeting() -> anyhow::Result<bool> {
+    use anyhow::Context;
+
+    let out = tokio::process::Command::new("pactl")
+        .args(["list", "source-outputs"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .context("[Zoom] Failed to run pactl")?;
+    if !out.status.success() {
+        anyhow::bail!("[Zoom] pactl failed ({})", out.status);
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).contains("ZOOM VoiceEngine"))
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn zoom_in_meeting() -> anyhow::Result<bool> {
+    let running = tokio::task::spawn_blocking(|| {
+        let mut sys = sysinfo::System::new();
+        sys.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+        sys.processes()
+            .values()
+            .any(|p| p.name().to_string_lossy().starts_with("CptHost"))
+    });
+    Ok(running.await?)
+}
@@ -0,0 +1,122 @@
+//! Client of Teams' API for third-party devices (protocol version 2).
+//!
+//! Teams listens on `localhost:8124` while its API is turned on. A device connects with the token
+//! Teams gave it before, or none at first; the first command sent during a meeting then asks the
+//! user to allow the device, after which Teams sends a token (`tokenRefresh`), kept in the
+//! settings. Teams sends the meeting's state (`meetingUpdate`) on connecting and whenever it
+//! changes.
+
+use std::sync::{Mutex, Weak};
+use std::time::Duration;
+
+use anyhow::Context;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+use crate::{lock, Inner, MeetingState};
+
+const API_URL: &str = "ws://127.0.0.1:8124";
+const PROTOCOL_VERSION: &str = "2.0.0";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Stay connected to Teams until every handle is dropped, reconnecting while it is closed.
+pub(crate) async fn run(inner: Weak<Mutex<Inner>>) {
+    let mut token = storage::settings::load_settings()
+        .map(|s| s.meetings.teams_token)
+        .unwrap_or_default();
+    loop {
+        let res = session(&inner, &mut token).await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let reason = match res {
+            Ok(()) => "[Teams] Teams closed the connection".to_string(),
+            Err(e) => format!("{e:#}"),
+        };
+        // Usually Teams is just not running.
+        debug!(reason = %reason, "not connected to Teams");
+        {
+            let mut inner = lock(&inner);
+            inner.teams_commands = None;
+            inner.teams_state = None;
+            inner.teams_error = Some(reason);
+        }
+        drop(inner);
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn session(inner: &Weak<Mutex<Inner>>, token: &mut String) -> anyhow::Result<()> {
+    let url = format!(
+        "{API_URL}?token={token}&protocol-version={PROTOCOL_VERSION}&manufacturer=RiverDeck\
+         &device=RiverDeck-Redux&app=RiverDeck-Redux&app-version={}",
+        env!("CARGO_PKG_VERSION")
+    );
+    let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .context("[Teams] Cannot reach Teams; is it running with its API turned on?")?;
+    let (mut sink, mut stream) = socket.split();
+
+    let (commands_tx, mut commands) = mpsc::unbounded_channel();
+    {
+        let Some(inner) = inner.upgrade() else {
+            return Ok(());
+        };
+        let mut inner = lock(&inner);
+        inner.teams_commands = Some(commands_tx);
+        inner.teams_state = Some(MeetingState::default());
+        inner.teams_error = None;
+    }
+    info!("connected to Teams");
+
+    let mut next_id: u64 = 1;
+    loop {
+        tokio::select! {
+            msg = stream.next() => {
+                let msg = msg.context("connection closed")?.context("connection failed")?;
+                let text = match msg {
+                    Message::Text(text) => text,
+                    Message::Close(_) => return Ok(()),
+                    // Pings are answered by the library.
+                    _ => continue,
+                };
+                let Some(inner) = inner.upgrade() else {
+                    return Ok(());
+                };
+                handle_message(&inner, &serde_json::from_str(&text)?, token);
+            }
+            Some(action) = commands.recv() => {
+                let request = json!({ "action": action, "parameters": {}, "requestId": next_id });
+                next_id += 1;
+                sink.send(Message::Text(request.to_string())).await?;
+            }
+        }
+    }
+}
+
+fn handle_message(inner: &Mutex<Inner>, msg: &Value, token: &mut String) {
+    if let Some(refreshed) = msg["tokenRefresh"].as_str() {
+        *token = refreshed.to_string();
+        let saved = storage::settings::update_settings(|s| {
+            s.meetings.teams_token = refreshed.to_string();
+        });
+        match saved {
+            Ok(_) => info!("paired with Teams"),
+            Err(e) => warn!(error = %e, "cannot save the Teams token"),
+        }
+    } else if let Some(update) = msg.get("meetingUpdate") {
+        let state = &update["meetingState"];
+        lock(inner).teams_state = Some(MeetingState {
+            in_meeting: state["isInMeeting"].as_bool().unwrap_or(false),
+            muted: state["isMuted"].as_bool(),
+            video_on: state["isVideoOn"].as_bool(),
+        });
+    } else if let Some(error) = msg["errorMsg"].as_str() {
+        warn!(error, "Teams refused a command");
+    } else {
+        debug!(%msg, "ignoring Teams message");
+    }
+}
@@ -0,0 +1,187 @@
+//! Choosing which asset of a release to install.
+//!
+//! Releases often ship several files (bundles per platform, source archives, checksums). Each
+//! asset name is scored against a declarative [`AssetRules`] table: it must end in one of the
+//! known bundle extensions, which carry a score of their own, and gains a bonus when it names
+//! the platform we run on. The highest score wins; on a tie, the asset listed first. New bundle
+//! formats are one more [`BundleFormat`] entry.
+
+/// A file type that can be installed, e.g. `.zip` or `.streamdeckplugin`.
+#[derive(Debug, Clone, Copy)]
+pub struct BundleFormat {
+    /// Lowercase file name suffix, dot included.
+    pub extension: &'static str,
+    /// Preference among formats; higher wins.
+    pub score: i32,
+}
+
+/// Words in an asset name that mark it as built for `os` (`linux`, `windows` or `macos`).
+///
+/// Keywords match whole words of the lowercased name, split at `-`, `_` and `.`: `win` matches
+/// `plugin-win.zip` but not `plugin-darwin.zip`.
+#[derive(Debug, Clone, Copy)]
+pub struct PlatformKeywords {
+    pub os: &'static str,
+    pub keywords: &'static [&'static str],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AssetRules {
+    pub formats: &'static [BundleFormat],
+    pub platforms: &'static [PlatformKeywords],
+    /// Added to the score of assets naming the current platform.
+    pub platform_score: i32,
+}
+
+/// Rules for OpenAction and Stream Deck plugin releases.
+pub const DEFAULT_RULES: AssetRules = AssetRules {
+    formats: &[
+        BundleFormat {
+            extension: ".openaction",
+            score: 14,
+        },
+        BundleFormat {
+            extension: ".streamdeckplugin",
+            score: 12,
+        },
+        BundleFormat {
+            extension: ".zip",
+            score: 10,
+        },
+        BundleFormat {
+            extension: ".opendeckplugin",
+            score: 0,
+        },
+        BundleFormat {
+            extension: ".tar.gz",
+            score: 0,
+        },
+        BundleFormat {
+            extension: ".tgz",
+            score: 0,
+        },
+    ],
+    platforms: &[
+        PlatformKeywords {
+            os: "linux",
+            keywords: &["linux"],
+        },
+        PlatformKeywords {
+            os: "windows",
+            keywords: &["windows", "win", "win32", "win64"],
+        },
+        PlatformKeywords {
+            os: "macos",
+            keywords: &["mac", "macos", "darwin", "osx"],
+        },
+    ],
+    platform_score: 50,
+};
+
+impl AssetRules {
+    /// Score of the asset `name` when installing on `os`; `None` if it is not a bundle.
+    pub fn score(&self, name: &str, os: &str) -> Option<i32> {
+        let name = name.to_ascii_lowercase();
+        let format = self.formats.iter().find(|f| name.ends_with(f.extension))?;
+        let on_platform = self
+            .platforms
+            .iter()
+            .filter(|p| p.os == os)
+            .flat_map(|p| p.keywords)
+            .any(|k| name.split(['-', '_', '.']).any(|word| word == *k));
+        Some(format.score + if on_platform { self.platform_score } else { 0 })
+    }
+
+    /// The best of `assets`, given as `(name, item)` pairs, for `os`.
+    pub fn pick<'a, T>(
+        &self,
+        assets: impl IntoIterator<Item = (&'a str, T)>,
+        os: &str,
+    ) -> Option<T> {
+        let mut best: Option<(i32, T)> = None;
+        for (name, item) in assets {
+            let Some(score) = self.score(name, os) else {
+                continue;
+            };
+            let better = match &best {
+                Some((best_score, _)) => score > *best_score,
+                None => true,
+            };
+            if better {
+                best = Some((score, item));
+            }
+        }
+        best.map(|(_, item)| item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick(names: &[&'static str], os: &str) -> Option<&'static str> {
+        DEFAULT_RULES.pick(names.iter().map(|&n| (n, n)), os)
+    }
+
+    #[test]
+    fn prefers_higher_scoring_extensions() {
+        let names = ["plugin.tar.gz", "plugin.zip", "plugin.streamdeckplugin"];
+        assert_eq!(pick(&names, "linux"), Some("plugin.streamdeckplugin"));
+        assert_eq!(
+            pick(&["plugin.zip", "plugin.openaction"], "linux"),
+            Some("plugin.openaction")
+        );
+    }
+
+    #[test]
+    fn platform_bonus_outweighs_the_extension() {
+        let names = [
+            "plugin.openaction",
+            "plugin-linux.tar.gz",
+            "plugin-windows.zip",
+        ];
+        assert_eq!(pick(&names, "linux"), Some("plugin-linux.tar.gz"));
+        assert_eq!(pick(&names, "windows"), Some("plugin-windows.zip"));
+        assert_eq!(pick(&names, "macos"), Some("plugin.openaction"));
+    }
+
+    #[test]
+    fn tie_keeps_the_first_asset() {
+        assert_eq!(pick(&["a.zip", "b.zip"], "linux"), Some("a.zip"));
+        assert_eq!(pick(&["b.tgz", "a.tar.gz"], "linux"), Some("b.tgz"));
+    }
+
+    #[test]
+    fn scores_tar_gz_and_tgz() {
+        assert_eq!(DEFAULT_RULES.score("plugin.tar.gz", "linux"), Some(0));
+        assert_eq!(DEFAULT_RULES.score("plugin-linux.tgz", "linux"), Some(50));
+    }
+
+    #[test]
+    fn skips_assets_that_are_not_bundles() {
+        assert_eq!(DEFAULT_RULES.score("SHA256SUMS", "linux"), None);
+        assert_eq!(DEFAULT_RULES.score("plugin-linux.zip.sig", "linux"), None);
+        assert_eq!(pick(&["checksums.txt", "source.tar.xz"], "linux"), None);
+        assert_eq!(
+            pick(&["checksums.txt", "plugin.zip"], "linux"),
+            Some("plugin.zip")
+        );
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(DEFAULT_RULES.score("Plugin-Linux.ZIP", "linux"), Some(60));
+        assert_eq!(DEFAULT_RULES.score("PLUGIN.TAR.GZ", "linux"), Some(0));
+    }
+
+    #[test]
+    fn matches_platform_keywords_as_whole_words() {
+        assert_eq!(
+            DEFAULT_RULES.score("plugin-darwin.zip", "windows"),
+            Some(10)
+        );
+        assert_eq!(DEFAULT_RULES.score("plugin-darwin.zip", "macos"), Some(60));
+        assert_eq!(DEFAULT_RULES.score("plugin_win64.zip", "windows"), Some(60));
+        assert_eq!(DEFAULT_RULES.score("twin-plugin.zip", "windows"), Some(10));
+    }
+}
@@ -0,0 +1,175 @@
+//! GitHub REST API requests for marketplace details (release download counts and assets).
+//!
+//! Requests carry the user's token when one is configured (5000 instead of 60 requests an hour)
+//! and are revalidated against the marketplace disk cache with `If-None-Match`; GitHub does not
+//! count `304 Not Modified` answers against the limit. When the limit is hit, `Retry-After` or
+//! `X-RateLimit-Reset` say when to try again: short waits are sat out, longer ones make every
+//! request until then fail fast, serving the cached copy where there is one.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::header::{HeaderName, ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use reqwest::StatusCode;
+use tracing::{debug, warn};
+
+use crate::assets::DEFAULT_RULES;
+use crate::marketplace::{
+    current_os, header_string, now_secs, read_cache, write_cache, CacheEntry, CacheMeta,
+};
+
+const API_BASE: &str = "https://api.github.com";
+
+/// Longest wait for the limit to lift that a request sits out before retrying once.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
+
+/// Wait after a secondary rate limit that came without a hint; GitHub asks for at least this.
+const SECONDARY_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Unix time (seconds) before which the API is not asked again, after it reported a limit.
+static LIMITED_UNTIL: Mutex<u64> = Mutex::new(0);
+
+#[derive(Debug, thiserror::Error)]
+pub enum GithubError {
+    #[error(
+        "GitHub API rate limit reached; try again in {} min (a GitHub token in Settings raises \
+         the limit)",
+        .retry_after.as_secs().div_ceil(60).max(1)
+    )]
+    RateLimited { retry_after: Duration },
+    #[error("GitHub API error: {0}")]
+    Status(StatusCode),
+}
+
+/// Releases of `owner/repo`, newest first, as the API returns them.
+pub async fn releases(
+    owner: &str,
+    repo: &str,
+    token: Option<&str>,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    let url = format!("{API_BASE}/repos/{owner}/{repo}/releases");
+    let body = api_get(&url, token).await?;
+    let value: serde_json::Value = serde_json::from_slice(&body)?;
+    match value {
+        serde_json::Value::Array(releases) => Ok(releases),
+        _ => anyhow::bail!("unexpected GitHub API response"),
+    }
+}
+
+/// Download URL of the asset of a release to install on this platform, picked from the
+/// release's `assets` with [`DEFAULT_RULES`].
+pub fn release_asset_url(assets: &[serde_json::Value]) -> Option<String> {
+    let candidates = assets.iter().filter_map(|a| {
+        let name = a.get("name")?.as_str()?;
+        let url = a.get("browser_download_url")?.as_str()?;
+        (!url.is_empty()).then_some((name, url))
+    });
+    DEFAULT_RULES
+        .pick(candidates, current_os())
+        .map(str::to_string)
+}
+
+/// GET `url` from the API (see the module docs for caching and rate limits).
+async fn api_get(url: &str, token: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let cached = read_cache(url);
+    let client = reqwest::Client::builder()
+        .user_agent("RiverDeck-Redux/0.1 (Marketplace)")
+        .build()?;
+
+    let mut retried = false;
+    loop {
+        if let Some(retry_after) = limited_for() {
+            return cached_or(cached, url, GithubError::RateLimited { retry_after });
+        }
+
+        let mut req = client
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+        if let Some(token) = token.map(str::trim).filter(|t| !t.is_empty()) {
+            req = req.bearer_auth(token);
+        }
+        if let Some(etag) = cached.as_ref().and_then(|c| c.meta.etag.as_deref()) {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        let resp = req.send().await?;
+
+        let status = resp.status();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(mut c) = cached {
+                debug!(url, "GitHub API cache revalidated");
+                c.meta.fetched_at = now_secs();
+                if let Err(e) = write_cache(&c.meta, &c.body) {
+                    warn!(url, error = %e, "failed to update marketplace cache");
+                }
+                return Ok(c.body);
+            }
+            anyhow::bail!("GitHub returned 304 Not Modified without a cached copy: {url}");
+        }
+        if let Some(wait) = rate_limit_wait(&resp) {
+            if !retried && wait <= MAX_RETRY_WAIT {
+                debug!(
+                    url,
+                    ?wait,
+                    "GitHub API rate limited; waiting before retrying"
+                );
+                tokio::time::sleep(wait).await;
+                retried = true;
+                continue;
+            }
+            *LIMITED_UNTIL.lock().expect("rate limit mutex poisoned") =
+                now_secs() + wait.as_secs().max(1);
+            return cached_or(cached, url, GithubError::RateLimited { retry_after: wait });
+        }
+        if !status.is_success() {
+            return Err(GithubError::Status(status).into());
+        }
+
+        let meta = CacheMeta {
+            url: url.to_string(),
+            etag: header_string(&resp, ETAG),
+            last_modified: None,
+            fetched_at: now_secs(),
+        };
+        let body = resp.bytes().await?.to_vec();
+        if let Err(e) = write_cache(&meta, &body) {
+            warn!(url, error = %e, "failed to write marketplace cache");
+        }
+        return Ok(body);
+    }
+}
+
+/// How long the API still refuses requests, after an earlier answer said so.
+fn limited_for() -> Option<Duration> {
+    let until = *LIMITED_UNTIL.lock().expect("rate limit mutex poisoned");
+    let now = now_secs();
+    (until > now).then(|| Duration::from_secs(until - now))
+}
+
+/// How long to wait when `resp` says the rate limit was hit; `None` for any other answer.
+fn rate_limit_wait(resp: &reqwest::Response) -> Option<Duration> {
+    let status = resp.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let number =
+        |name: HeaderName| header_string(resp, name).and_then(|v| v.trim().parse::<u64>().ok());
+    if let Some(secs) = number(RETRY_AFTER) {
+        return Some(Duration::from_secs(secs));
+    }
+    if number(HeaderName::from_static("x-ratelimit-remaining")) == Some(0) {
+        let reset = number(HeaderName::from_static("x-ratelimit-reset")).unwrap_or(0);
+        return Some(Duration::from_secs(reset.saturating_sub(now_secs()).max(1)));
+    }
+    // A 403 without rate limit headers is a plain permission error.
+    (status == StatusCode::TOO_MANY_REQUESTS).then_some(SECONDARY_LIMIT_WAIT)
+}
+
+fn cached_or(cached: Option<CacheEntry>, url: &str, err: GithubError) -> anyhow::Result<Vec<u8>> {
+    match cached {
+        Some(c) => {
+            warn!(url, error = %err, "serving cached GitHub API data");
+            Ok(c.body)
+        }
+        None => Err(err.into()),
+    }
+}
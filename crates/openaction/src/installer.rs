@@ -1,8 +1,10 @@
 //! Marketplace installer: download plugin archives and install into the local registry.
 //!
 //! v1 goals:
-//! - Support `.zip` and `.tar.gz` / `.tgz` archives
+//! - Support `.zip` and `.tar.gz` / `.tgz` archives (`.streamdeckplugin` bundles are zips),
+//!   downloaded or picked from disk
 //! - Enforce basic safety (no path traversal, no symlinks)
+//! - Verify optional SHA-256 / minisign signature before extracting anything
 //! - Validate `manifest.json` and install into `data_dir/plugins/<plugin_id>`
 
 use std::ffi::OsStr;
@@ -10,6 +12,7 @@ use std::io::Cursor;
 use std::path::{Component, Path, PathBuf};
 
 use crate::manifest::PluginManifest;
+use crate::verify::Verification;
 
 #[derive(Debug, Clone, Copy)]
 enum ArchiveKind {
@@ -20,18 +23,50 @@ enum ArchiveKind {
 /// Download an archive from `url`, extract it safely, validate `manifest.json`, and install it.
 ///
 /// - If `expected_id` is provided, the extracted manifest must match it.
+/// - Checks in `verification` run on the raw download; a failure refuses the install unless
+///   `verification.allow_mismatch` is set.
 /// - Returns the installed plugin id.
-pub async fn install_from_url(url: &str, expected_id: Option<&str>) -> anyhow::Result<String> {
+pub async fn install_from_url(
+    url: &str,
+    expected_id: Option<&str>,
+    verification: &Verification,
+) -> anyhow::Result<String> {
     let url = url.trim();
     if url.is_empty() {
         anyhow::bail!("download url is empty");
     }
 
     let bytes = crate::marketplace::fetch_bytes(url).await?;
-    let kind = detect_archive_kind(url, &bytes)?;
+    install_archive(url, bytes, expected_id, verification).await
+}
+
+/// Install a plugin from a local archive file (`.zip`, `.tar.gz` / `.tgz`, `.streamdeckplugin`).
+///
+/// Goes through the same verification, extraction and validation as [`install_from_url`].
+pub async fn install_from_file(
+    path: &Path,
+    expected_id: Option<&str>,
+    verification: &Verification,
+) -> anyhow::Result<String> {
+    let path_owned = path.to_path_buf();
+    let bytes = tokio::task::spawn_blocking(move || std::fs::read(&path_owned))
+        .await?
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+    install_archive(&path.to_string_lossy(), bytes, expected_id, verification).await
+}
+
+/// Verify, extract and install the archive `bytes`, downloaded from or read at `source`.
+async fn install_archive(
+    source: &str,
+    bytes: Vec<u8>,
+    expected_id: Option<&str>,
+    verification: &Verification,
+) -> anyhow::Result<String> {
+    crate::verify::verify_archive(&bytes, verification).await?;
+    let kind = detect_archive_kind(source, &bytes)?;
 
     let expected = expected_id.map(|s| s.to_string());
-    let url_owned = url.to_string();
+    let source_owned = source.to_string();
 
     tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
         let staging_root = create_staging_root()?;
@@ -50,12 +85,12 @@ pub async fn install_from_url(url: &str, expected_id: Option<&str>) -> anyhow::R
         // Best-effort cleanup.
         let _ = std::fs::remove_dir_all(&staging_root);
 
-        res.map_err(|e| anyhow::anyhow!("install from url failed ({url_owned}): {e}"))
+        res.map_err(|e| anyhow::anyhow!("install failed ({source_owned}): {e}"))
     })
     .await?
 }
 
-fn detect_archive_kind(url: &str, bytes: &[u8]) -> anyhow::Result<ArchiveKind> {
+fn detect_archive_kind(source: &str, bytes: &[u8]) -> anyhow::Result<ArchiveKind> {
     // ZIP local file header magic: PK\x03\x04
     if bytes.len() >= 4 && &bytes[0..4] == b"PK\x03\x04" {
         return Ok(ArchiveKind::Zip);
@@ -67,15 +102,15 @@ fn detect_archive_kind(url: &str, bytes: &[u8]) -> anyhow::Result<ArchiveKind> {
     }
 
     // Fallback to extension.
-    let lower = url.to_ascii_lowercase();
-    if lower.ends_with(".zip") {
+    let lower = source.to_ascii_lowercase();
+    if lower.ends_with(".zip") || lower.ends_with(".streamdeckplugin") {
         return Ok(ArchiveKind::Zip);
     }
     if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
         return Ok(ArchiveKind::TarGz);
     }
 
-    anyhow::bail!("unsupported archive type (expected .zip or .tar.gz/.tgz)");
+    anyhow::bail!("unsupported archive type (expected .zip, .streamdeckplugin or .tar.gz/.tgz)");
 }
 
 fn create_staging_root() -> anyhow::Result<PathBuf> {
@@ -130,7 +165,9 @@ fn extract_zip(bytes: &[u8], staging_root: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn extract_tar_gz(bytes: &[u8], staging_root: &Path) -> anyhow::Result<()> {
+/// Extract a `.tar.gz` archive into `staging_root`. Entry paths go through the same checks as
+/// zip entry names (see [`sanitize_rel_path`]), and links are rejected.
+pub fn extract_tar_gz(bytes: &[u8], staging_root: &Path) -> anyhow::Result<()> {
     let cursor = Cursor::new(bytes);
     let gz = flate2::read::GzDecoder::new(cursor);
     let mut ar = tar::Archive::new(gz);
@@ -165,7 +202,9 @@ fn extract_tar_gz(bytes: &[u8], staging_root: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn sanitize_rel_path(name: &str) -> anyhow::Result<PathBuf> {
+/// The path a zip entry named `name` is extracted to, relative to the staging directory.
+/// Absolute names and names with `..` are rejected.
+pub fn sanitize_rel_path(name: &str) -> anyhow::Result<PathBuf> {
     // Zip uses forward slashes regardless of platform.
     let cleaned = name.trim_start_matches('/');
     if cleaned.is_empty() {
@@ -221,7 +260,7 @@ fn find_plugin_root(staging_root: &Path) -> anyhow::Result<PathBuf> {
 
 fn load_manifest(dir: &Path) -> anyhow::Result<PluginManifest> {
     let raw = std::fs::read_to_string(dir.join("manifest.json"))?;
-    Ok(serde_json::from_str(&raw)?)
+    PluginManifest::parse(&raw, dir)
 }
 
 fn validate_manifest(manifest: &PluginManifest, expected_id: Option<&str>) -> anyhow::Result<()> {
@@ -272,5 +311,3 @@ fn ensure_executable_usable(plugin_dir: &Path, manifest: &PluginManifest) -> any
 
     Ok(())
 }
-
-
@@ -1,6 +1,10 @@
 //! OpenAction integration crate (manifest parsing, local installs, registry).
 
+pub mod assets;
+pub mod github;
 pub mod manifest;
 pub mod marketplace;
+pub mod profile_gallery;
 pub mod registry;
 pub mod installer;
+pub mod verify;
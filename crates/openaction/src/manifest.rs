@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use serde::{Deserialize, Serialize};
 
 /// Minimal OpenAction-style manifest model for MVP bring-up.
@@ -23,6 +25,29 @@ pub struct PluginManifest {
     pub executable_linux: Option<String>,
     #[serde(default)]
     pub executable_windows: Option<String>,
+    /// Capabilities the plugin asks for when it runs sandboxed.
+    #[serde(default)]
+    pub permissions: PluginPermissions,
+}
+
+impl PluginManifest {
+    /// Parse the `manifest.json` of the plugin in `plugin_dir`, in our schema or in Elgato's
+    /// Stream Deck schema (`.sdPlugin` packages), which is translated.
+    pub fn parse(raw: &str, plugin_dir: &Path) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(raw)?;
+        if !is_elgato_manifest(&value) {
+            return Ok(serde_json::from_value(value)?);
+        }
+        let elgato: ElgatoManifest = serde_json::from_value(value)?;
+        Ok(elgato.into_manifest(plugin_dir))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginPermissions {
+    /// Needs network access (the sandbox unshares the network otherwise).
+    #[serde(default)]
+    pub network: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +56,26 @@ pub struct ActionDefinition {
     pub name: String,
     #[serde(default)]
     pub settings: Vec<SettingField>,
+    /// Relative path to the action's icon; the extension may be left out, as Stream Deck
+    /// manifests do (see `InstalledPlugin::image_file`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Relative path to the action's property inspector page (Stream Deck plugins).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property_inspector: Option<String>,
+    /// Looks the key can be switched between with the plugin's `setState` message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<ActionState>,
+}
+
+/// One look of an action (Stream Deck manifests' `States`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionState {
+    /// Relative path to the state's image, resolved like [`ActionDefinition::icon`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,3 +95,100 @@ pub enum SettingType {
     Boolean,
     Number,
 }
+
+/// Elgato manifests use PascalCase keys; ours are snake_case.
+fn is_elgato_manifest(value: &serde_json::Value) -> bool {
+    ["Actions", "CodePath", "UUID"]
+        .iter()
+        .any(|key| value.get(key).is_some())
+}
+
+/// The parts of a Stream Deck `manifest.json` we can use.
+///
+/// Stream Deck packages carry no Linux executable: `CodePath` is used on every platform but
+/// Windows, which is only useful for plugins whose code path runs on its own (e.g. a script).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ElgatoManifest {
+    name: String,
+    #[serde(default)]
+    version: String,
+    /// Missing in older manifests; the `.sdPlugin` folder is named after it.
+    #[serde(default, rename = "UUID")]
+    uuid: Option<String>,
+    #[serde(default)]
+    code_path: Option<String>,
+    #[serde(default)]
+    code_path_win: Option<String>,
+    /// Plugin-wide inspector, used by actions that don't name their own.
+    #[serde(default)]
+    property_inspector_path: Option<String>,
+    #[serde(default)]
+    actions: Vec<ElgatoAction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ElgatoAction {
+    #[serde(rename = "UUID")]
+    uuid: String,
+    name: String,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    property_inspector_path: Option<String>,
+    #[serde(default)]
+    states: Vec<ElgatoState>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ElgatoState {
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+impl ElgatoManifest {
+    fn into_manifest(self, plugin_dir: &Path) -> PluginManifest {
+        let id = self.uuid.unwrap_or_else(|| {
+            let dir = plugin_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            dir.strip_suffix(".sdPlugin").unwrap_or(&dir).to_string()
+        });
+        let actions = self
+            .actions
+            .into_iter()
+            .map(|a| ActionDefinition {
+                id: a.uuid,
+                name: a.name,
+                settings: Vec::new(),
+                icon: a.icon,
+                property_inspector: a
+                    .property_inspector_path
+                    .or_else(|| self.property_inspector_path.clone()),
+                states: a
+                    .states
+                    .into_iter()
+                    .map(|s| ActionState {
+                        icon: s.image,
+                        title: s.title,
+                    })
+                    .collect(),
+            })
+            .collect();
+        PluginManifest {
+            id,
+            name: self.name,
+            version: self.version,
+            actions,
+            executable: None,
+            executable_linux: self.code_path.clone(),
+            executable_windows: self.code_path_win.or(self.code_path),
+            permissions: PluginPermissions::default(),
+        }
+    }
+}
@@ -2,9 +2,24 @@
 //!
 //! The repo currently supports local plugin installs only. This module enables
 //! reading an HTTP-hosted marketplace "index" so UIs can display available plugins.
+//!
+//! Listings (indexes, plugin details, images) are cached on disk under
+//! `<cache_dir>/marketplace/` and revalidated with `ETag`/`Last-Modified`. When the network is
+//! unreachable, the last cached copy is served (stale-while-offline) so the marketplace view
+//! keeps working without connectivity. Plugin archives and signatures are always downloaded.
+//!
+//! Plugin icons and screenshots go through an [`IconDownloader`], which bounds how many run at
+//! once, retries failures and serves recently cached images without asking the server again.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+use crate::verify::sha256_hex;
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
@@ -73,6 +88,36 @@ pub struct MarketplacePlugin {
     /// Optional screenshot/image URLs for marketplace details.
     #[serde(default, alias = "screenshots", alias = "images")]
     pub images: Vec<String>,
+    /// Optional hex SHA-256 of the download artifact.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Optional minisign signature of the download artifact (inline `.minisig` text or a URL).
+    #[serde(default, alias = "minisig")]
+    pub signature: Option<String>,
+    /// Optional minisign public key used to check `signature`.
+    #[serde(default, alias = "publicKey", alias = "minisign_public_key")]
+    pub public_key: Option<String>,
+    /// Optional categories (a single `category` string is also accepted).
+    #[serde(default, alias = "category", deserialize_with = "one_or_many")]
+    pub categories: Vec<String>,
+    /// Supported operating systems (`linux`, `windows`, `macos`); empty means unspecified.
+    #[serde(
+        default,
+        alias = "platforms",
+        alias = "supportedOs",
+        alias = "supported_os",
+        deserialize_with = "one_or_many"
+    )]
+    pub os: Vec<String>,
+    /// Supported OpenAction API versions (e.g. `"1"`, `"1.2"`); empty means unspecified.
+    #[serde(
+        default,
+        alias = "apiVersions",
+        alias = "api_version",
+        alias = "apiVersion",
+        deserialize_with = "one_or_many"
+    )]
+    pub api_versions: Vec<String>,
 }
 
 /// The Rivul/OpenAction catalogue shape is a map keyed by plugin ID:
@@ -100,6 +145,30 @@ struct CatalogueEntry {
     pub downloads: Option<DownloadSpec>,
     #[serde(default, alias = "screenshots", alias = "images")]
     pub images: Vec<String>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default, alias = "minisig")]
+    pub signature: Option<String>,
+    #[serde(default, alias = "publicKey", alias = "minisign_public_key")]
+    pub public_key: Option<String>,
+    #[serde(default, alias = "category", deserialize_with = "one_or_many")]
+    pub categories: Vec<String>,
+    #[serde(
+        default,
+        alias = "platforms",
+        alias = "supportedOs",
+        alias = "supported_os",
+        deserialize_with = "one_or_many"
+    )]
+    pub os: Vec<String>,
+    #[serde(
+        default,
+        alias = "apiVersions",
+        alias = "api_version",
+        alias = "apiVersion",
+        deserialize_with = "one_or_many"
+    )]
+    pub api_versions: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -110,6 +179,127 @@ enum MarketplaceResponse {
     Catalogue(BTreeMap<String, CatalogueEntry>),
 }
 
+/// Major OpenAction API versions this host can run.
+pub const SUPPORTED_API_MAJOR_VERSIONS: &[u32] = &[1];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Str(String),
+    Num(serde_json::Number),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(Scalar),
+    Many(Vec<Scalar>),
+}
+
+/// Accepts `"x"`, `1`, `["x", 2]` (or `null`) and yields trimmed, non-empty strings.
+pub(crate) fn one_or_many<'de, D>(d: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw: Option<OneOrMany> = Option::deserialize(d)?;
+    let items = match raw {
+        None => vec![],
+        Some(OneOrMany::One(s)) => vec![s],
+        Some(OneOrMany::Many(v)) => v,
+    };
+    Ok(items
+        .into_iter()
+        .map(|s| match s {
+            Scalar::Str(s) => s.trim().to_string(),
+            Scalar::Num(n) => n.to_string(),
+        })
+        .filter(|s| !s.is_empty())
+        .collect())
+}
+
+/// Whether a marketplace plugin can run on this host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    Compatible,
+    /// The entry does not declare OS or API support.
+    Unknown,
+    UnsupportedOs,
+    UnsupportedApi,
+}
+
+impl Compatibility {
+    pub fn is_incompatible(self) -> bool {
+        matches!(self, Self::UnsupportedOs | Self::UnsupportedApi)
+    }
+}
+
+fn normalize_os(raw: &str) -> Option<&'static str> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "linux" => Some("linux"),
+        "windows" | "win" | "win32" | "win64" => Some("windows"),
+        "macos" | "mac" | "osx" | "darwin" => Some("macos"),
+        _ => None,
+    }
+}
+
+pub(crate) fn current_os() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "macos"
+    } else {
+        "linux"
+    }
+}
+
+fn api_major(raw: &str) -> Option<u32> {
+    raw.trim()
+        .trim_start_matches(['v', 'V'])
+        .split('.')
+        .next()?
+        .parse()
+        .ok()
+}
+
+impl MarketplacePlugin {
+    /// Normalized OS names (`linux`/`windows`/`macos`) the entry declares; unknown names are dropped.
+    pub fn supported_os(&self) -> Vec<&'static str> {
+        let mut out: Vec<&'static str> = self.os.iter().filter_map(|s| normalize_os(s)).collect();
+        out.dedup();
+        out
+    }
+
+    pub fn compatibility(&self) -> Compatibility {
+        let os = self.supported_os();
+        if !os.is_empty() && !os.contains(&current_os()) {
+            return Compatibility::UnsupportedOs;
+        }
+        let majors: Vec<u32> = self.api_versions.iter().filter_map(|v| api_major(v)).collect();
+        if !majors.is_empty() && !majors.iter().any(|m| SUPPORTED_API_MAJOR_VERSIONS.contains(m)) {
+            return Compatibility::UnsupportedApi;
+        }
+        if os.is_empty() && majors.is_empty() {
+            Compatibility::Unknown
+        } else {
+            Compatibility::Compatible
+        }
+    }
+
+    pub fn has_category(&self, category: &str) -> bool {
+        self.categories.iter().any(|c| c.eq_ignore_ascii_case(category))
+    }
+}
+
+/// A parsed marketplace index plus whether it came from the offline cache.
+#[derive(Debug, Clone)]
+pub struct MarketplaceIndex {
+    pub plugins: Vec<MarketplacePlugin>,
+    /// `true` when the network was unreachable and a cached copy was used.
+    pub stale: bool,
+    /// Unix timestamp (seconds) of when the served copy was last fetched from the network.
+    pub fetched_at: u64,
+}
+
 /// Fetches a marketplace index from the provided URL.
 ///
 /// Supported JSON shapes:
@@ -117,7 +307,13 @@ enum MarketplaceResponse {
 /// - `{ "plugins": [{...}, {...}] }`
 /// - `{ "<plugin_id>": { "name": "...", ... }, ... }` (OpenAction catalogue.json)
 pub async fn fetch_plugins(index_url: &str) -> anyhow::Result<Vec<MarketplacePlugin>> {
-    let bytes = fetch_bytes(index_url).await?;
+    Ok(fetch_index(index_url).await?.plugins)
+}
+
+/// Like [`fetch_plugins`], but also reports whether the result is a stale offline copy.
+pub async fn fetch_index(index_url: &str) -> anyhow::Result<MarketplaceIndex> {
+    let fetched = fetch_cached(index_url).await?;
+    let bytes = fetched.bytes;
     let parsed: MarketplaceResponse = serde_json::from_slice(&bytes).map_err(|e| {
         // Include a small, safe preview to help diagnose wrong endpoints (HTML, etc.).
         let preview = String::from_utf8_lossy(&bytes[..bytes.len().min(240)]);
@@ -143,6 +339,12 @@ pub async fn fetch_plugins(index_url: &str) -> anyhow::Result<Vec<MarketplacePlu
                     .or_else(|| e.downloads.as_ref().and_then(pick_platform_download)),
                 downloads: e.downloads,
                 images: e.images,
+                sha256: e.sha256,
+                signature: e.signature,
+                public_key: e.public_key,
+                categories: e.categories,
+                os: e.os,
+                api_versions: e.api_versions,
             })
             .collect(),
     };
@@ -155,26 +357,268 @@ pub async fn fetch_plugins(index_url: &str) -> anyhow::Result<Vec<MarketplacePlu
     }
 
     plugins.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    Ok(plugins)
+    Ok(MarketplaceIndex {
+        plugins,
+        stale: fetched.stale,
+        fetched_at: fetched.fetched_at,
+    })
 }
 
-/// Fetch raw bytes from a marketplace URL.
+/// Fetch raw bytes from a marketplace URL, bypassing the disk cache.
 ///
-/// We set `Accept-Encoding: identity` to keep things predictable (plain bodies),
-/// especially when `reqwest` is built with a reduced feature set.
+/// Used for plugin archives and signatures: they are not worth keeping around, and a failed
+/// download must not be replaced by an old copy.
 pub async fn fetch_bytes(url: &str) -> anyhow::Result<Vec<u8>> {
-    let client = reqwest::Client::builder()
+    let resp = client()?.get(url).send().await?.error_for_status()?;
+
+    Ok(resp.bytes().await?.to_vec())
+}
+
+/// Most image downloads an [`IconDownloader`] runs at once by default.
+pub const ICON_CONCURRENCY: usize = 4;
+
+/// Tries per image before giving up.
+const ICON_ATTEMPTS: u32 = 3;
+
+/// Wait before the second try; doubled for every further one.
+const ICON_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Cached images fetched less than this long ago (in seconds) are used without revalidating.
+const ICON_MAX_AGE: u64 = 7 * 24 * 60 * 60;
+
+/// Queue for marketplace image downloads (icons, screenshots).
+///
+/// Clones share the queue: however many images are requested, at most `concurrency` downloads
+/// run at a time and the rest wait for a free slot. Images go through the same disk cache as
+/// [`fetch_cached`], but a copy younger than a week is served without a request, and transport
+/// errors and 5xx responses are retried with a growing delay.
+#[derive(Debug, Clone)]
+pub struct IconDownloader {
+    slots: Arc<Semaphore>,
+}
+
+impl IconDownloader {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    /// The image at `url`, waiting for a free download slot if needed.
+    pub async fn fetch(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        if let Some(c) = read_cache(url) {
+            if now_secs().saturating_sub(c.meta.fetched_at) < ICON_MAX_AGE {
+                return Ok(c.body);
+            }
+        }
+
+        let mut delay = ICON_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let res = {
+                let _slot = self.slots.acquire().await?;
+                fetch_cached(url).await
+            };
+            match res {
+                Ok(fetched) => return Ok(fetched.bytes),
+                Err(e) if attempt < ICON_ATTEMPTS && is_retryable(&e) => {
+                    debug!(url, attempt, error = %e, "image download failed; retrying");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Default for IconDownloader {
+    fn default() -> Self {
+        Self::new(ICON_CONCURRENCY)
+    }
+}
+
+/// Whether a failed fetch may succeed when tried again: not for 4xx responses.
+fn is_retryable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<reqwest::Error>().and_then(|e| e.status()) {
+        Some(status) => !status.is_client_error(),
+        None => true,
+    }
+}
+
+/// Result of a cached fetch.
+#[derive(Debug, Clone)]
+pub struct Fetched {
+    pub bytes: Vec<u8>,
+    /// `true` when the network request failed and the cached body was served instead.
+    pub stale: bool,
+    pub fetched_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct CacheMeta {
+    pub(crate) url: String,
+    #[serde(default)]
+    pub(crate) etag: Option<String>,
+    #[serde(default)]
+    pub(crate) last_modified: Option<String>,
+    #[serde(default)]
+    pub(crate) fetched_at: u64,
+}
+
+pub(crate) struct CacheEntry {
+    pub(crate) meta: CacheMeta,
+    pub(crate) body: Vec<u8>,
+}
+
+fn cache_dir() -> anyhow::Result<PathBuf> {
+    Ok(storage::paths::cache_dir()?.join("marketplace"))
+}
+
+fn cache_paths(url: &str) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let key = sha256_hex(url.as_bytes());
+    let dir = cache_dir()?;
+    Ok((dir.join(format!("{key}.json")), dir.join(format!("{key}.body"))))
+}
+
+pub(crate) fn read_cache(url: &str) -> Option<CacheEntry> {
+    let (meta_path, body_path) = cache_paths(url).ok()?;
+    let meta: CacheMeta = serde_json::from_slice(&std::fs::read(meta_path).ok()?).ok()?;
+    if meta.url != url {
+        return None;
+    }
+    let body = std::fs::read(body_path).ok()?;
+    Some(CacheEntry { meta, body })
+}
+
+pub(crate) fn write_cache(meta: &CacheMeta, body: &[u8]) -> anyhow::Result<()> {
+    let (meta_path, body_path) = cache_paths(&meta.url)?;
+    if let Some(dir) = meta_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Body first, then metadata: a crash in between leaves an entry that fails the lookup.
+    let tmp = body_path.with_extension("body.tmp");
+    std::fs::write(&tmp, body)?;
+    std::fs::rename(&tmp, &body_path)?;
+    let tmp = meta_path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(meta)?)?;
+    std::fs::rename(&tmp, &meta_path)?;
+    Ok(())
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub(crate) fn header_string(resp: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Client for marketplace requests. Every request asks for `Accept-Encoding: identity` to keep
+/// things predictable (plain bodies), especially when `reqwest` is built with a reduced feature
+/// set.
+fn client() -> anyhow::Result<reqwest::Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::ACCEPT_ENCODING,
+        reqwest::header::HeaderValue::from_static("identity"),
+    );
+    Ok(reqwest::Client::builder()
         .user_agent("RiverDeck-Redux/0.1 (OpenAction Marketplace)")
-        .build()?;
+        .default_headers(headers)
+        .build()?)
+}
 
-    let resp = client
-        .get(url)
-        .header(reqwest::header::ACCEPT_ENCODING, "identity")
-        .send()
-        .await?
-        .error_for_status()?;
+/// Fetch `url`, revalidating any cached copy with `If-None-Match`/`If-Modified-Since`.
+///
+/// Only for listings: indexes, plugin details and images. Downloads go through
+/// [`fetch_bytes`].
+///
+/// Transport errors and 5xx responses fall back to the cached body (marked `stale`);
+/// other HTTP errors are returned as-is so wrong URLs are not masked by old data.
+pub async fn fetch_cached(url: &str) -> anyhow::Result<Fetched> {
+    let cached = read_cache(url);
 
-    Ok(resp.bytes().await?.to_vec())
+    let mut req = client()?.get(url);
+    if let Some(c) = &cached {
+        if let Some(etag) = &c.meta.etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(lm) = &c.meta.last_modified {
+            req = req.header(reqwest::header::IF_MODIFIED_SINCE, lm);
+        }
+    }
+
+    let resp = match req.send().await {
+        Ok(resp) => resp,
+        Err(e) => return stale_or(cached, url, e.into()),
+    };
+
+    let status = resp.status();
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(mut c) = cached {
+            debug!(url, "marketplace cache revalidated");
+            c.meta.fetched_at = now_secs();
+            if let Err(e) = write_cache(&c.meta, &c.body) {
+                warn!(url, error = %e, "failed to update marketplace cache");
+            }
+            return Ok(Fetched {
+                bytes: c.body,
+                stale: false,
+                fetched_at: c.meta.fetched_at,
+            });
+        }
+        anyhow::bail!("server returned 304 Not Modified without a cached copy: {url}");
+    }
+    if status.is_server_error() {
+        let err = anyhow::anyhow!("HTTP {status} for {url}");
+        return stale_or(cached, url, err);
+    }
+    let resp = resp.error_for_status()?;
+
+    let etag = header_string(&resp, reqwest::header::ETAG);
+    let last_modified = header_string(&resp, reqwest::header::LAST_MODIFIED);
+    let bytes = match resp.bytes().await {
+        Ok(b) => b.to_vec(),
+        Err(e) => return stale_or(cached, url, e.into()),
+    };
+
+    let meta = CacheMeta {
+        url: url.to_string(),
+        etag,
+        last_modified,
+        fetched_at: now_secs(),
+    };
+    if let Err(e) = write_cache(&meta, &bytes) {
+        warn!(url, error = %e, "failed to write marketplace cache");
+    }
+
+    Ok(Fetched {
+        bytes,
+        stale: false,
+        fetched_at: meta.fetched_at,
+    })
+}
+
+fn stale_or(cached: Option<CacheEntry>, url: &str, err: anyhow::Error) -> anyhow::Result<Fetched> {
+    match cached {
+        Some(c) => {
+            warn!(url, error = %err, "network unavailable; serving cached marketplace data");
+            Ok(Fetched {
+                bytes: c.body,
+                stale: true,
+                fetched_at: c.meta.fetched_at,
+            })
+        }
+        None => Err(err),
+    }
 }
 
 
@@ -0,0 +1,153 @@
+//! Community profile gallery client.
+//!
+//! A gallery index lists shared profiles in the same shapes as a marketplace index (a list, a
+//! `{ "profiles": [...] }` object or a catalogue map keyed by id). Each entry points at a profile
+//! file and names the plugins its bindings need, so they can be installed along with it. Indexes
+//! and profiles go through the marketplace disk cache.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::Deserialize;
+use storage::profiles::Profile;
+
+use crate::marketplace::{fetch_cached, one_or_many};
+use crate::verify::sha256_hex;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GalleryProfile {
+    /// Filled in from the key of catalogue-shaped indexes.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    #[serde(alias = "iconUrl")]
+    pub icon_url: Option<String>,
+    /// URL of the profile file; relative URLs are resolved against the index URL. Entries
+    /// without one are left out.
+    #[serde(default, alias = "downloadUrl", alias = "url")]
+    pub download_url: String,
+    #[serde(default, alias = "screenshots")]
+    pub images: Vec<String>,
+    /// Optional hex SHA-256 of the profile file.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default, alias = "category", deserialize_with = "one_or_many")]
+    pub categories: Vec<String>,
+    /// Number of keys of the device the profile was made for, when the entry says.
+    #[serde(default, alias = "keyCount")]
+    pub key_count: Option<u8>,
+    /// Ids of the plugins the profile's bindings use.
+    #[serde(default, alias = "dependencies", deserialize_with = "one_or_many")]
+    pub plugins: Vec<String>,
+}
+
+impl GalleryProfile {
+    pub fn has_category(&self, category: &str) -> bool {
+        self.categories
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(category))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum GalleryResponse {
+    List(Vec<GalleryProfile>),
+    Wrapped { profiles: Vec<GalleryProfile> },
+    Catalogue(BTreeMap<String, GalleryProfile>),
+}
+
+/// A parsed gallery index plus whether it came from the offline cache.
+#[derive(Debug, Clone)]
+pub struct ProfileGallery {
+    pub profiles: Vec<GalleryProfile>,
+    /// `true` when the network was unreachable and a cached copy was used.
+    pub stale: bool,
+    /// Unix timestamp (seconds) of when the served copy was last fetched from the network.
+    pub fetched_at: u64,
+}
+
+/// Fetches the gallery index at `index_url`, sorted by name.
+pub async fn fetch_gallery(index_url: &str) -> anyhow::Result<ProfileGallery> {
+    let fetched = fetch_cached(index_url).await?;
+    let bytes = fetched.bytes;
+    let parsed: GalleryResponse = serde_json::from_slice(&bytes).map_err(|e| {
+        let preview = String::from_utf8_lossy(&bytes[..bytes.len().min(240)]);
+        anyhow::anyhow!("failed to parse profile gallery JSON: {e}. body preview: {preview}")
+    })?;
+
+    let mut profiles = match parsed {
+        GalleryResponse::List(list) => list,
+        GalleryResponse::Wrapped { profiles } => profiles,
+        GalleryResponse::Catalogue(map) => map
+            .into_iter()
+            .map(|(id, p)| GalleryProfile { id, ..p })
+            .collect(),
+    };
+    for p in &mut profiles {
+        if p.id.is_empty() {
+            p.id = p.name.clone();
+        }
+        p.download_url = resolve_url(index_url, &p.download_url).unwrap_or_default();
+        if let Some(icon) = &p.icon_url {
+            p.icon_url = resolve_url(index_url, icon);
+        }
+        for image in &mut p.images {
+            *image = resolve_url(index_url, image).unwrap_or_default();
+        }
+        p.images.retain(|i| !i.is_empty());
+    }
+    profiles.retain(|p| !p.download_url.is_empty());
+
+    profiles.sort_by_key(|p| p.name.to_lowercase());
+    Ok(ProfileGallery {
+        profiles,
+        stale: fetched.stale,
+        fetched_at: fetched.fetched_at,
+    })
+}
+
+/// Downloads the profile of `entry`, checks it against the entry's checksum and reads it as a
+/// new profile (see [`storage::profiles::parse_shared_profile`]).
+pub async fn fetch_profile(entry: &GalleryProfile) -> anyhow::Result<Profile> {
+    let bytes = fetch_cached(&entry.download_url).await?.bytes;
+    let expected = entry.sha256.as_deref().map(str::trim);
+    if let Some(expected) = expected.filter(|s| !s.is_empty()) {
+        let actual = sha256_hex(&bytes);
+        if !actual.eq_ignore_ascii_case(expected) {
+            anyhow::bail!("checksum mismatch (expected sha256 {expected}, got {actual})");
+        }
+    }
+    storage::profiles::parse_shared_profile(&bytes)
+        .with_context(|| format!("{} is not a usable profile", entry.download_url))
+}
+
+/// Plugins the profile of `entry` needs: the ones the entry names plus the ones its bindings
+/// invoke, sorted and without repeats.
+pub fn required_plugins(entry: &GalleryProfile, profile: &Profile) -> Vec<String> {
+    let mut ids = storage::profiles::plugin_ids(profile);
+    ids.extend(entry.plugins.iter().cloned());
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// `raw` as an absolute URL, resolving relative ones against `base`.
+fn resolve_url(base: &str, raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    if reqwest::Url::parse(raw).is_ok() {
+        return Some(raw.to_string());
+    }
+    let base = reqwest::Url::parse(base.trim()).ok()?;
+    base.join(raw).ok().map(|u| u.to_string())
+}
@@ -1,12 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
-use crate::manifest::PluginManifest;
+use serde::{Deserialize, Serialize};
+use storage::profiles::strip_plugin_triggers;
+use storage::{midi_triggers, osc_triggers, process_watchers, schedules, typed_triggers, webhooks};
+use tracing::warn;
+
+use crate::manifest::{ActionDefinition, PluginManifest};
+
+/// Tried in order after an action icon path without an extension.
+const ICON_SUFFIXES: [&str; 4] = [".png", "@2x.png", ".jpg", ".jpeg"];
 
 #[derive(Debug, Clone)]
 pub struct InstalledPlugin {
     pub dir: PathBuf,
     pub manifest: PluginManifest,
+    /// Disabled plugins stay installed but are hidden from action lists and never invoked.
+    pub enabled: bool,
+    /// Run the plugin executable inside the sandbox (Linux only; see `plugin-runtime`).
+    pub sandboxed: bool,
+    /// User decision for the manifest's `permissions.network` request (`None` = not asked yet).
+    pub network_granted: Option<bool>,
+    /// How the user wants the executable started.
+    pub launch: PluginLaunch,
+}
+
+impl InstalledPlugin {
+    /// The plugin needs a permission decision from the user before it can run sandboxed.
+    pub fn needs_permission_prompt(&self) -> bool {
+        self.sandboxed && self.manifest.permissions.network && self.network_granted.is_none()
+    }
+
+    /// The directory to start the executable in, if one is configured.
+    pub fn working_dir(&self) -> Option<PathBuf> {
+        let cwd = self.launch.cwd.as_deref().map(str::trim).filter(|c| !c.is_empty())?;
+        Some(self.dir.join(cwd))
+    }
+
+    /// The image file of `action`'s icon, if it declares one that exists.
+    pub fn action_icon(&self, action: &ActionDefinition) -> Option<PathBuf> {
+        self.image_file(action.icon.as_deref()?)
+    }
+
+    /// The image file of state `state` of `action`, if it declares one that exists.
+    pub fn state_icon(&self, action: &ActionDefinition, state: usize) -> Option<PathBuf> {
+        self.image_file(action.states.get(state)?.icon.as_deref()?)
+    }
+
+    /// The image at `rel` inside the plugin directory; the extension may be left out.
+    pub fn image_file(&self, rel: &str) -> Option<PathBuf> {
+        let rel = Path::new(rel);
+        // Manifests and plugins are untrusted: stay inside the plugin directory.
+        if !rel
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return None;
+        }
+        let base = self.dir.join(rel);
+        if base.is_file() {
+            return Some(base);
+        }
+        ICON_SUFFIXES.iter().find_map(|suffix| {
+            let mut name = base.clone().into_os_string();
+            name.push(suffix);
+            let path = PathBuf::from(name);
+            path.is_file().then_some(path)
+        })
+    }
+}
+
+/// User configuration for starting a plugin's executable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginLaunch {
+    /// Arguments passed after the executable path.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Environment variables set for the process, e.g. API keys.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Working directory; relative paths are inside the plugin directory (`.` is the plugin
+    /// directory itself). Unset runs in the app's working directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// At most this many processes of the plugin at once (`1` runs invocations one after
+    /// another); further invocations queue. Unset runs every invocation right away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+}
+
+/// Per-plugin user state that must survive reinstalls (kept outside `plugins/<id>`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginState {
+    #[serde(default)]
+    pub disabled: BTreeSet<String>,
+    /// Opt-in: run plugin executables sandboxed (Linux, via `bwrap`).
+    #[serde(default)]
+    pub sandbox: bool,
+    /// Per-plugin network permission decisions (`true` = allowed).
+    #[serde(default)]
+    pub network: BTreeMap<String, bool>,
+    /// Settings plugins saved for themselves (`setGlobalSettings`), shared by all their actions.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub global_settings: BTreeMap<String, serde_json::Value>,
+    /// Per-plugin launch configuration; plugins without one start with the defaults.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub launch: BTreeMap<String, PluginLaunch>,
+}
+
+/// Outcome of `uninstall`, so callers can surface what else was touched.
+#[derive(Debug, Clone, Default)]
+pub struct UninstallReport {
+    pub plugin_id: String,
+    /// Number of profile bindings (including macro steps) that referenced the plugin.
+    pub cleared_bindings: usize,
+    /// Profiles that were rewritten on disk.
+    pub profiles_updated: usize,
+    /// Number of references in global triggers (schedules, webhooks, typed, process, MIDI and
+    /// OSC triggers); triggers whose action was the plugin's are removed.
+    pub cleared_triggers: usize,
 }
 
 pub fn plugins_dir() -> anyhow::Result<PathBuf> {
@@ -25,12 +138,133 @@ pub fn manifest_path(plugin_dir: &Path) -> PathBuf {
 
 pub fn load_manifest(plugin_dir: &Path) -> anyhow::Result<PluginManifest> {
     let raw = fs::read_to_string(manifest_path(plugin_dir))?;
-    let m: PluginManifest = serde_json::from_str(&raw)?;
-    Ok(m)
+    PluginManifest::parse(&raw, plugin_dir)
+}
+
+pub fn state_path() -> anyhow::Result<PathBuf> {
+    Ok(storage::paths::data_dir()?.join("plugin-state.json"))
+}
+
+pub fn load_state() -> anyhow::Result<PluginState> {
+    let path = state_path()?;
+    if !path.exists() {
+        return Ok(PluginState::default());
+    }
+    let raw = fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+pub fn save_state(state: &PluginState) -> anyhow::Result<()> {
+    let path = state_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_vec_pretty(state)?)?;
+    if cfg!(windows) && path.exists() {
+        let _ = fs::remove_file(&path);
+    }
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+pub fn is_enabled(plugin_id: &str) -> bool {
+    load_state()
+        .map(|s| !s.disabled.contains(plugin_id))
+        .unwrap_or(true)
+}
+
+/// Enable or disable an installed plugin without removing it.
+pub fn set_enabled(plugin_id: &str, enabled: bool) -> anyhow::Result<()> {
+    let id = plugin_id.trim();
+    if id.is_empty() {
+        anyhow::bail!("plugin id is empty");
+    }
+    let mut state = load_state()?;
+    let changed = if enabled {
+        state.disabled.remove(id)
+    } else {
+        state.disabled.insert(id.to_string())
+    };
+    if changed {
+        save_state(&state)?;
+    }
+    Ok(())
+}
+
+pub fn sandbox_enabled() -> bool {
+    load_state().map(|s| s.sandbox).unwrap_or(false)
+}
+
+/// Turn the plugin sandbox on or off for all plugins.
+pub fn set_sandbox_enabled(enabled: bool) -> anyhow::Result<()> {
+    let mut state = load_state()?;
+    if state.sandbox != enabled {
+        state.sandbox = enabled;
+        save_state(&state)?;
+    }
+    Ok(())
+}
+
+/// Record the user's answer to a plugin's network permission request.
+pub fn set_network_permission(plugin_id: &str, allowed: bool) -> anyhow::Result<()> {
+    let id = plugin_id.trim();
+    if id.is_empty() {
+        anyhow::bail!("plugin id is empty");
+    }
+    let mut state = load_state()?;
+    if state.network.insert(id.to_string(), allowed) != Some(allowed) {
+        save_state(&state)?;
+    }
+    Ok(())
+}
+
+/// Global settings of `plugin_id`; `null` until the plugin saves some.
+pub fn global_settings(plugin_id: &str) -> serde_json::Value {
+    load_state()
+        .ok()
+        .and_then(|mut s| s.global_settings.remove(plugin_id))
+        .unwrap_or_default()
+}
+
+/// Save the global settings a plugin sent, replacing its previous ones.
+pub fn set_global_settings(plugin_id: &str, settings: serde_json::Value) -> anyhow::Result<()> {
+    let id = plugin_id.trim();
+    if id.is_empty() {
+        anyhow::bail!("plugin id is empty");
+    }
+    let mut state = load_state()?;
+    if state.global_settings.get(id) != Some(&settings) {
+        state.global_settings.insert(id.to_string(), settings);
+        save_state(&state)?;
+    }
+    Ok(())
+}
+
+/// Save how `plugin_id`'s executable is started; the default configuration is not stored.
+pub fn set_launch(plugin_id: &str, launch: PluginLaunch) -> anyhow::Result<()> {
+    let id = plugin_id.trim();
+    if id.is_empty() {
+        anyhow::bail!("plugin id is empty");
+    }
+    if let Some(key) = launch.env.keys().find(|k| k.is_empty() || k.contains(['=', '\0'])) {
+        anyhow::bail!("invalid environment variable name: {key:?}");
+    }
+    let mut state = load_state()?;
+    let changed = if launch == PluginLaunch::default() {
+        state.launch.remove(id).is_some()
+    } else {
+        state.launch.insert(id.to_string(), launch.clone()) != Some(launch)
+    };
+    if changed {
+        save_state(&state)?;
+    }
+    Ok(())
 }
 
 pub fn list_installed() -> anyhow::Result<Vec<InstalledPlugin>> {
     let dir = ensure_plugins_dir()?;
+    let state = load_state().unwrap_or_default();
     let mut out = vec![];
 
     for entry in fs::read_dir(&dir)? {
@@ -42,6 +276,10 @@ pub fn list_installed() -> anyhow::Result<Vec<InstalledPlugin>> {
         match load_manifest(&path) {
             Ok(m) => out.push(InstalledPlugin {
                 dir: path,
+                enabled: !state.disabled.contains(&m.id),
+                sandboxed: state.sandbox,
+                network_granted: state.network.get(&m.id).copied(),
+                launch: state.launch.get(&m.id).cloned().unwrap_or_default(),
                 manifest: m,
             }),
             Err(_) => continue,
@@ -131,8 +369,12 @@ pub fn install_dir_atomic(src: &Path, expected_id: Option<&str>) -> anyhow::Resu
     res
 }
 
-/// Uninstall a plugin by id (removes `data_dir/plugins/<id>`).
-pub fn uninstall(plugin_id: &str) -> anyhow::Result<()> {
+/// Uninstall a plugin by id.
+///
+/// - Removes `data_dir/plugins/<id>`
+/// - Clears every profile binding that references the plugin (logged as a warning)
+/// - Forgets the plugin's enable/disable state and permission decisions
+pub fn uninstall(plugin_id: &str) -> anyhow::Result<UninstallReport> {
     let id = plugin_id.trim();
     if id.is_empty() {
         anyhow::bail!("plugin id is empty");
@@ -141,7 +383,107 @@ pub fn uninstall(plugin_id: &str) -> anyhow::Result<()> {
     if dir.exists() {
         fs::remove_dir_all(&dir)?;
     }
-    Ok(())
+
+    let mut report = UninstallReport {
+        plugin_id: id.to_string(),
+        ..UninstallReport::default()
+    };
+
+    for meta in storage::profiles::list_profiles()? {
+        let mut profile = match storage::profiles::load_profile(&meta.path) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!(path = %meta.path.display(), error = %e, "skipping unreadable profile during uninstall");
+                continue;
+            }
+        };
+        let removed = storage::profiles::strip_plugin_bindings(&mut profile, id);
+        if removed == 0 {
+            continue;
+        }
+        warn!(
+            plugin_id = id,
+            profile = %meta.name,
+            removed,
+            "cleared bindings referencing uninstalled plugin"
+        );
+        storage::profiles::save_profile_to_path(&profile, &meta.path)?;
+        report.cleared_bindings += removed;
+        report.profiles_updated += 1;
+    }
+
+    report.cleared_triggers += strip_trigger_store(
+        "schedules",
+        schedules::load_schedules,
+        schedules::save_schedules,
+        |t| strip_plugin_triggers(t, id, |t| &mut t.action),
+    )?;
+    report.cleared_triggers += strip_trigger_store(
+        "webhooks",
+        webhooks::load_webhooks,
+        webhooks::save_webhooks,
+        |t| strip_plugin_triggers(t, id, |t| &mut t.action),
+    )?;
+    report.cleared_triggers += strip_trigger_store(
+        "typed triggers",
+        typed_triggers::load_typed_triggers,
+        typed_triggers::save_typed_triggers,
+        |t| strip_plugin_triggers(t, id, |t| &mut t.action),
+    )?;
+    report.cleared_triggers += strip_trigger_store(
+        "process watchers",
+        process_watchers::load_process_watchers,
+        process_watchers::save_process_watchers,
+        |t| strip_plugin_triggers(t, id, |t| &mut t.action),
+    )?;
+    report.cleared_triggers += strip_trigger_store(
+        "MIDI triggers",
+        midi_triggers::load_midi_triggers,
+        midi_triggers::save_midi_triggers,
+        |t| strip_plugin_triggers(t, id, |t| &mut t.action),
+    )?;
+    report.cleared_triggers += strip_trigger_store(
+        "OSC triggers",
+        osc_triggers::load_osc_triggers,
+        osc_triggers::save_osc_triggers,
+        |t| strip_plugin_triggers(t, id, |t| &mut t.action),
+    )?;
+
+    let mut state = load_state().unwrap_or_default();
+    let forgot_disabled = state.disabled.remove(id);
+    let forgot_network = state.network.remove(id).is_some();
+    if forgot_disabled || forgot_network {
+        save_state(&state)?;
+    }
+
+    Ok(report)
+}
+
+/// Run `strip` over one global trigger store, saving it if anything was removed.
+///
+/// Returns the number of removed references; an unreadable store is skipped.
+fn strip_trigger_store<T>(
+    store: &str,
+    load: impl FnOnce() -> anyhow::Result<Vec<T>>,
+    save: impl FnOnce(&[T]) -> anyhow::Result<()>,
+    strip: impl FnOnce(&mut Vec<T>) -> usize,
+) -> anyhow::Result<usize> {
+    let mut triggers = match load() {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(store, error = %e, "skipping unreadable trigger store during uninstall");
+            return Ok(0);
+        }
+    };
+    let removed = strip(&mut triggers);
+    if removed > 0 {
+        warn!(
+            store,
+            removed, "cleared triggers referencing uninstalled plugin"
+        );
+        save(&triggers)?;
+    }
+    Ok(removed)
 }
 
 pub fn plugin_executable_path(plugin: &InstalledPlugin) -> Option<PathBuf> {
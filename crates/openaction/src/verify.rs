@@ -0,0 +1,132 @@
+//! Integrity checks for downloaded plugin archives (SHA-256 + minisign/ed25519).
+//!
+//! Checks are opt-in: a catalogue entry that provides no checksum/signature installs as before.
+//! When a check is configured and fails, the install is refused unless the caller explicitly
+//! sets `allow_mismatch`.
+
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::marketplace::MarketplacePlugin;
+
+#[derive(Debug, Clone, Default)]
+pub struct Verification {
+    /// Expected hex SHA-256 of the archive.
+    pub sha256: Option<String>,
+    /// Minisign public key: either the bare base64 key line or full `minisign.pub` contents.
+    pub public_key: Option<String>,
+    /// Minisign signature: full `.minisig` contents, or an http(s) URL to fetch them from.
+    pub signature: Option<String>,
+    /// Install even if a configured check fails (failures are logged instead).
+    pub allow_mismatch: bool,
+}
+
+impl Verification {
+    /// Build checks from the optional fields of a marketplace catalogue entry.
+    pub fn from_marketplace(plugin: &MarketplacePlugin) -> Self {
+        Self {
+            sha256: non_empty(plugin.sha256.as_deref()),
+            public_key: non_empty(plugin.public_key.as_deref()),
+            signature: non_empty(plugin.signature.as_deref()),
+            allow_mismatch: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sha256.is_none() && self.signature.is_none()
+    }
+}
+
+fn non_empty(s: Option<&str>) -> Option<String> {
+    s.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerificationError {
+    #[error("checksum mismatch (expected sha256 {expected}, got {actual})")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("signature provided without a public key")]
+    MissingPublicKey,
+    #[error("invalid minisign public key: {0}")]
+    BadPublicKey(String),
+    #[error("invalid minisign signature: {0}")]
+    BadSignature(String),
+    #[error("signature verification failed: {0}")]
+    SignatureMismatch(String),
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let mut out = String::with_capacity(digest.len() * 2);
+    for b in digest {
+        out.push_str(&format!("{b:02x}"));
+    }
+    out
+}
+
+/// Verify `bytes` against the configured checks.
+///
+/// Remote signatures (`signature` is a URL) are fetched first. With `allow_mismatch` set,
+/// failures are logged and `Ok(())` is returned.
+pub async fn verify_archive(bytes: &[u8], v: &Verification) -> anyhow::Result<()> {
+    if v.is_empty() {
+        return Ok(());
+    }
+
+    let signature = match v.signature.as_deref() {
+        Some(s) if s.starts_with("http://") || s.starts_with("https://") => {
+            let raw = crate::marketplace::fetch_bytes(s).await?;
+            Some(String::from_utf8(raw).map_err(|_| {
+                VerificationError::BadSignature("signature file is not utf-8".to_string())
+            })?)
+        }
+        Some(s) => Some(s.to_string()),
+        None => None,
+    };
+
+    match check(bytes, v.sha256.as_deref(), v.public_key.as_deref(), signature.as_deref()) {
+        Ok(()) => Ok(()),
+        Err(e) if v.allow_mismatch => {
+            warn!(error = %e, "archive verification failed; installing anyway (override)");
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn check(
+    bytes: &[u8],
+    sha256: Option<&str>,
+    public_key: Option<&str>,
+    signature: Option<&str>,
+) -> Result<(), VerificationError> {
+    if let Some(expected) = sha256 {
+        let expected = expected.trim().to_ascii_lowercase();
+        let actual = sha256_hex(bytes);
+        if expected != actual {
+            return Err(VerificationError::ChecksumMismatch { expected, actual });
+        }
+    }
+
+    if let Some(sig) = signature {
+        let key = public_key.ok_or(VerificationError::MissingPublicKey)?;
+        let key = parse_public_key(key)?;
+        let sig = minisign_verify::Signature::decode(sig.trim())
+            .map_err(|e| VerificationError::BadSignature(e.to_string()))?;
+        key.verify(bytes, &sig, false)
+            .map_err(|e| VerificationError::SignatureMismatch(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn parse_public_key(raw: &str) -> Result<minisign_verify::PublicKey, VerificationError> {
+    let raw = raw.trim();
+    // Accept both the bare key and the two-line `minisign.pub` file format.
+    let res = if raw.lines().count() > 1 {
+        minisign_verify::PublicKey::decode(raw)
+    } else {
+        minisign_verify::PublicKey::from_base64(raw)
+    };
+    res.map_err(|e| VerificationError::BadPublicKey(e.to_string()))
+}
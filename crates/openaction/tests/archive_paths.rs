@@ -0,0 +1,81 @@
+//! Properties of archive entry paths: whatever an archive names its entries, nothing is
+//! extracted outside the staging directory (see also `fuzz/`).
+
+use std::path::{Component, Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use openaction::installer::{extract_tar_gz, sanitize_rel_path};
+use proptest::prelude::*;
+
+/// Entry names made of `/`, `.`, `..` and short names, the parts path tricks are built from.
+const TRICKY_NAME: &str = "(/|\\.\\.?|[a-z]{1,4}){1,8}";
+
+/// A `.tar.gz` archive of one-byte files named `names`. The names are written into the headers
+/// as they are, since the `tar` builder refuses names with `..`.
+fn tar_gz(names: &[String]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for name in names {
+        let mut header = tar::Header::new_old();
+        let field = &mut header.as_old_mut().name;
+        let len = name.len().min(field.len());
+        field[..len].copy_from_slice(&name.as_bytes()[..len]);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(1);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"x"[..]).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+proptest! {
+    #[test]
+    fn accepted_names_stay_in_the_staging_dir(name in prop_oneof![TRICKY_NAME, ".*"]) {
+        if let Ok(rel) = sanitize_rel_path(&name) {
+            prop_assert!(rel.components().all(|c| matches!(c, Component::Normal(_))));
+            let staging = Path::new("/tmp/riverdeck-staging");
+            prop_assert!(staging.join(&rel).starts_with(staging));
+        }
+    }
+
+    #[test]
+    fn names_climbing_out_are_rejected(
+        segments in prop::collection::vec(prop_oneof![Just(".."), Just("."), Just("a")], 1..6),
+    ) {
+        let name = segments.join("/");
+        if segments.contains(&"..") {
+            prop_assert!(sanitize_rel_path(&name).is_err());
+        }
+    }
+
+    #[test]
+    fn plain_names_are_kept(
+        segments in prop::collection::vec("[a-zA-Z0-9_-]{1,8}", 1..6),
+        absolute in any::<bool>(),
+    ) {
+        let name = format!("{}{}", if absolute { "/" } else { "" }, segments.join("/"));
+        prop_assert_eq!(sanitize_rel_path(&name).unwrap(), segments.iter().collect::<PathBuf>());
+    }
+
+    #[test]
+    fn tar_gz_entries_stay_in_the_staging_dir(
+        names in prop::collection::vec(TRICKY_NAME, 1..8),
+    ) {
+        let root = tempfile::tempdir().unwrap();
+        let staging = root.path().join("staging");
+        std::fs::create_dir(&staging).unwrap();
+        let extracted = extract_tar_gz(&tar_gz(&names), &staging);
+
+        let beside: Vec<_> = std::fs::read_dir(root.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        prop_assert_eq!(beside, ["staging"]);
+        if extracted.is_ok() {
+            for name in &names {
+                prop_assert!(staging.join(sanitize_rel_path(name).unwrap()).exists());
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Properties of `manifest.json` parsing, in both schemas (see also `fuzz/`).
+
+use std::path::Path;
+
+use openaction::manifest::PluginManifest;
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+fn plugin_dir() -> &'static Path {
+    Path::new("/tmp/riverdeck-plugins/com.example.prop.sdPlugin")
+}
+
+/// A Stream Deck manifest, with or without the optional keys.
+fn elgato_manifest() -> impl Strategy<Value = Value> {
+    let action = ("[a-z.]{1,16}", ".{0,12}", prop::option::of("[a-z/]{1,12}"));
+    (
+        ".{0,12}",
+        prop::option::of("[a-z.]{1,16}"),
+        prop::option::of("[a-z/]{1,12}"),
+        prop::option::of("[a-z/]{1,12}\\.exe"),
+        prop::collection::vec(action, 0..4),
+    )
+        .prop_map(|(name, uuid, code_path, code_path_win, actions)| {
+            let actions: Vec<Value> = actions
+                .into_iter()
+                .map(|(uuid, name, icon)| json!({ "UUID": uuid, "Name": name, "Icon": icon }))
+                .collect();
+            let mut manifest = json!({ "Name": name, "Version": "1.0", "Actions": actions });
+            for (key, value) in [
+                ("UUID", uuid),
+                ("CodePath", code_path),
+                ("CodePathWin", code_path_win),
+            ] {
+                if let Some(value) = value {
+                    manifest[key] = Value::String(value);
+                }
+            }
+            manifest
+        })
+}
+
+proptest! {
+    #[test]
+    fn any_text_parses_or_fails_without_panicking(raw in ".*") {
+        let _ = PluginManifest::parse(&raw, plugin_dir());
+    }
+
+    #[test]
+    fn elgato_manifests_are_translated(manifest in elgato_manifest()) {
+        let parsed = PluginManifest::parse(&manifest.to_string(), plugin_dir()).unwrap();
+        let uuid = manifest["UUID"].as_str().unwrap_or("com.example.prop");
+        prop_assert_eq!(&parsed.id, uuid);
+        prop_assert_eq!(&parsed.name, manifest["Name"].as_str().unwrap());
+        let code_path = manifest["CodePath"].as_str();
+        prop_assert_eq!(parsed.executable_linux.as_deref(), code_path);
+        let code_path_win = manifest["CodePathWin"].as_str().or(code_path);
+        prop_assert_eq!(parsed.executable_windows.as_deref(), code_path_win);
+        let ids: Vec<&str> = parsed.actions.iter().map(|a| a.id.as_str()).collect();
+        let uuids: Vec<&str> = manifest["Actions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["UUID"].as_str().unwrap())
+            .collect();
+        prop_assert_eq!(ids, uuids);
+    }
+
+    #[test]
+    fn parsed_manifests_read_back_the_same(manifest in elgato_manifest()) {
+        let parsed = PluginManifest::parse(&manifest.to_string(), plugin_dir()).unwrap();
+        let ours = serde_json::to_string(&parsed).unwrap();
+        let again = PluginManifest::parse(&ours, plugin_dir()).unwrap();
+        prop_assert_eq!(serde_json::to_string(&again).unwrap(), ours);
+    }
+}
@@ -0,0 +1,63 @@
+//! Messages plugins send back while an action runs.
+//!
+//! A plugin writes one JSON object per line to stdout, in the shape of the Stream Deck SDK's
+//! messages, e.g. `{"event":"setTitle","payload":{"title":"42"}}`. They apply to the control
+//! that fired the action, or (settings) to the binding or the plugin as a whole. Other lines
+//! (logging, unknown events) are ignored.
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tracing::debug;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "event", content = "payload", rename_all = "camelCase")]
+pub enum PluginFeedback {
+    /// Show `image` on the key: a `data:` URL (base64 PNG/JPEG) or a path inside the plugin
+    /// directory. Without one the key shows its own icon again.
+    SetImage {
+        #[serde(default)]
+        image: Option<String>,
+    },
+    /// Show `title` on the key; without one the key shows its own text again.
+    SetTitle {
+        #[serde(default)]
+        title: Option<String>,
+    },
+    /// Switch the key to one of the states the action declares in its manifest.
+    SetState { state: usize },
+    /// Replace the settings of the binding that invoked the action, saved with the profile.
+    SetSettings(serde_json::Value),
+    /// Replace the plugin's global settings, passed to all its actions from then on.
+    SetGlobalSettings(serde_json::Value),
+}
+
+impl PluginFeedback {
+    /// Parse one stdout line; `None` for anything that is not a known message.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if !line.starts_with('{') {
+            return None;
+        }
+        match serde_json::from_str(line) {
+            Ok(feedback) => Some(feedback),
+            Err(e) => {
+                debug!(line, error = %e, "ignoring plugin output line");
+                None
+            }
+        }
+    }
+}
+
+/// Pass each message `output` carries to `on_feedback` as it arrives, until `output` closes.
+pub(crate) async fn read_feedback(
+    output: impl AsyncRead + Unpin,
+    on_feedback: &(dyn Fn(PluginFeedback) + Send + Sync),
+) -> std::io::Result<()> {
+    let mut lines = BufReader::new(output).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(feedback) = PluginFeedback::parse(&line) {
+            on_feedback(feedback);
+        }
+    }
+    Ok(())
+}
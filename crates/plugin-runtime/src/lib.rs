@@ -1,9 +1,17 @@
 //! Runtime for executing actions provided by OpenAction plugins.
-
-use std::path::PathBuf;
-
-use openaction::manifest::PluginManifest;
-use openaction::registry::{plugin_executable_path, InstalledPlugin};
+//!
+//! Each invocation spawns the plugin executable and writes one [`ActionInvocation`] as a JSON
+//! line to its stdin. The payload carries a `schema_version`; version 2 added the flat `key`,
+//! `dial`, `delta`, `x` and `delta_x` fields next to the tagged `control` and `event`, version 3
+//! the plugin's `global_settings`. While it runs, the plugin can redraw the control and save
+//! settings through [`feedback`] messages on stdout.
+
+pub mod feedback;
+pub mod queue;
+pub mod sandbox;
+
+use feedback::PluginFeedback;
+use openaction::registry::{self, plugin_executable_path, InstalledPlugin};
 use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 use tracing::warn;
@@ -14,6 +22,20 @@ pub enum InvocationControl {
     Key { index: u8 },
     Dial { index: u8 },
     TouchStrip,
+    /// Fired by a schedule rather than a device control.
+    Schedule { id: u64 },
+    /// Fired by a request to a configured webhook.
+    Webhook { id: u64 },
+    /// Fired by typing the abbreviation of a typed trigger.
+    TypedTrigger { id: u64 },
+    /// Fired by a change to a file a file watcher of the profile watches.
+    FileWatcher { id: u64 },
+    /// Fired by a program starting or exiting.
+    ProcessWatcher { id: u64 },
+    /// Fired by a note or controller of a MIDI controller.
+    MidiTrigger { id: u64 },
+    /// Fired by an OSC message sent to the address of an OSC trigger.
+    OscTrigger { id: u64 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,26 +47,121 @@ pub enum InvocationEvent {
     DialUp,
     DialRotate { delta: i32 },
     TouchTap { x: u16 },
+    TouchLongPress { x: u16 },
     TouchDrag { delta_x: i16 },
+    TouchSwipeLeft,
+    TouchSwipeRight,
+    ScheduleFired,
+    WebhookReceived,
+    AbbreviationTyped,
+    /// `path` is the file that changed.
+    FileCreated { path: String },
+    FileModified { path: String },
+    FileDeleted { path: String },
+    /// `process` is the name the watcher looks for.
+    ProcessStarted { process: String },
+    ProcessExited { process: String },
+    /// Channels are 1 to 16.
+    MidiNote { channel: u8, note: u8, velocity: u8 },
+    MidiControlChange { channel: u8, controller: u8, value: u8 },
+    /// `address` is the one the message was sent to; `args` are its arguments (numbers, strings,
+    /// booleans, `null`, or blobs as arrays of bytes).
+    OscReceived { address: String, args: Vec<serde_json::Value> },
 }
 
+/// Version of the [`ActionInvocation`] payload written to plugins.
+pub const INVOCATION_SCHEMA_VERSION: u32 = 3;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionInvocation {
+    /// Payloads without one predate versioning (version 1).
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
     pub plugin_id: String,
     pub action_id: String,
     pub control: InvocationControl,
     pub event: InvocationEvent,
+    /// Index of the key that fired, for key events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<u8>,
+    /// Index of the dial that fired, for dial events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dial: Option<u8>,
+    /// Rotation in ticks (positive is clockwise), for dial rotations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<i32>,
+    /// Touch position on the strip in pixels, for taps and long presses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<u16>,
+    /// Horizontal movement in pixels, for drags on the strip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_x: Option<i16>,
+    /// Settings of this binding; a plugin replaces them with `setSettings`.
     #[serde(default)]
     pub settings: serde_json::Value,
+    /// Settings the plugin saved for all its actions with `setGlobalSettings`.
+    #[serde(default)]
+    pub global_settings: serde_json::Value,
 }
 
-pub struct ActionRuntime;
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+impl ActionInvocation {
+    /// The current-version payload for `event` on `control`, with the flat fields filled in.
+    pub fn new(
+        plugin_id: impl Into<String>,
+        action_id: impl Into<String>,
+        control: InvocationControl,
+        event: InvocationEvent,
+        settings: serde_json::Value,
+        global_settings: serde_json::Value,
+    ) -> Self {
+        let (key, dial) = match control {
+            InvocationControl::Key { index } => (Some(index), None),
+            InvocationControl::Dial { index } => (None, Some(index)),
+            _ => (None, None),
+        };
+        let (delta, x, delta_x) = match event {
+            InvocationEvent::DialRotate { delta } => (Some(delta), None, None),
+            InvocationEvent::TouchTap { x } | InvocationEvent::TouchLongPress { x } => {
+                (None, Some(x), None)
+            }
+            InvocationEvent::TouchDrag { delta_x } => (None, None, Some(delta_x)),
+            _ => (None, None, None),
+        };
+        Self {
+            schema_version: INVOCATION_SCHEMA_VERSION,
+            plugin_id: plugin_id.into(),
+            action_id: action_id.into(),
+            control,
+            event,
+            key,
+            dial,
+            delta,
+            x,
+            delta_x,
+            settings,
+            global_settings,
+        }
+    }
+}
+
+pub struct ActionRuntime {
+    queues: queue::InvocationQueues,
+}
 
 impl ActionRuntime {
     pub fn new() -> Self {
-        Self
+        Self {
+            queues: queue::InvocationQueues::default(),
+        }
     }
 
+    /// Run `action` of `plugin`; messages it sends back are passed to `on_feedback` as they
+    /// arrive. Waits for a free slot first when the plugin limits its concurrent runs (see
+    /// [`queue`]).
     pub async fn invoke(
         &self,
         plugin: &InstalledPlugin,
@@ -52,11 +169,26 @@ impl ActionRuntime {
         control: InvocationControl,
         event: InvocationEvent,
         settings: serde_json::Value,
+        on_feedback: &(dyn Fn(PluginFeedback) + Send + Sync),
     ) -> anyhow::Result<()> {
+        if !plugin.enabled {
+            anyhow::bail!("plugin is disabled: {}", plugin.manifest.id);
+        }
+
         let exe = plugin_executable_path(plugin)
             .ok_or_else(|| anyhow::anyhow!("plugin has no executable for this platform"))?;
 
-        invoke_process(exe, &plugin.manifest, action, control, event, settings).await
+        let cmd = sandbox::command_for(plugin, &exe)?;
+        let _slot = self.queues.enter(plugin).await?;
+        let payload = ActionInvocation::new(
+            plugin.manifest.id.as_str(),
+            action,
+            control,
+            event,
+            settings,
+            registry::global_settings(&plugin.manifest.id),
+        );
+        invoke_process(cmd, &payload, on_feedback).await
     }
 }
 
@@ -67,28 +199,23 @@ impl Default for ActionRuntime {
 }
 
 async fn invoke_process(
-    exe: PathBuf,
-    manifest: &PluginManifest,
-    action: &str,
-    control: InvocationControl,
-    event: InvocationEvent,
-    settings: serde_json::Value,
+    mut cmd: Command,
+    payload: &ActionInvocation,
+    on_feedback: &(dyn Fn(PluginFeedback) + Send + Sync),
 ) -> anyhow::Result<()> {
-    let payload = ActionInvocation {
-        plugin_id: manifest.id.clone(),
-        action_id: action.to_string(),
-        control,
-        event,
-        settings,
-    };
+    let stdin = serde_json::to_vec(payload)?;
 
-    let stdin = serde_json::to_vec(&payload)?;
-
-    let mut child = Command::new(exe)
+    let metrics = app_core::metrics::metrics();
+    metrics.plugin_starts.inc();
+    let mut child = cmd
         .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
-        .spawn()?;
+        .spawn()
+        .map_err(|e| {
+            metrics.plugin_failures.inc();
+            e
+        })?;
 
     if let Some(mut w) = child.stdin.take() {
         use tokio::io::AsyncWriteExt;
@@ -96,8 +223,18 @@ async fn invoke_process(
         w.write_all(b"\n").await?;
     }
 
-    let out = child.wait_with_output().await?;
+    let stdout = child.stdout.take();
+    let read_stdout = async {
+        if let Some(stdout) = stdout {
+            if let Err(e) = feedback::read_feedback(stdout, on_feedback).await {
+                warn!(error = %e, "failed to read plugin output");
+            }
+        }
+    };
+    let ((), out) = tokio::join!(read_stdout, child.wait_with_output());
+    let out = out?;
     if !out.status.success() {
+        metrics.plugin_failures.inc();
         let stderr = String::from_utf8_lossy(&out.stderr);
         warn!(code=?out.status.code(), %stderr, "plugin action invocation failed");
         anyhow::bail!("plugin invocation failed: {}", out.status);
@@ -0,0 +1,102 @@
+//! Per-plugin limits on concurrent invocations.
+//!
+//! A plugin whose launch configuration sets `max_concurrent` runs at most that many processes
+//! at once (`1` runs its invocations one after another). Further invocations wait their turn
+//! in a queue of at most [`MAX_QUEUED`]; once it is full they are refused with
+//! [`QueueError::Full`], so rapid presses cannot pile up work the plugin can't keep up with.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use openaction::registry::InstalledPlugin;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+/// Invocations of one plugin that may wait for a free slot.
+pub const MAX_QUEUED: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum QueueError {
+    #[error(
+        "plugin {plugin_id} is busy ({running} running, {queued} waiting); invocation dropped"
+    )]
+    Full {
+        plugin_id: String,
+        running: usize,
+        queued: usize,
+    },
+}
+
+#[derive(Default)]
+pub(crate) struct InvocationQueues {
+    plugins: Mutex<HashMap<String, Arc<Queue>>>,
+}
+
+struct Queue {
+    limit: usize,
+    slots: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+/// Counts an invocation as waiting until it is dropped, including when its future is.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl InvocationQueues {
+    /// Wait for a turn to run `plugin`, holding the returned permit while it runs; `None` for
+    /// plugins without a limit.
+    pub(crate) async fn enter(
+        &self,
+        plugin: &InstalledPlugin,
+    ) -> Result<Option<OwnedSemaphorePermit>, QueueError> {
+        let Some(limit) = plugin.launch.max_concurrent.filter(|n| *n > 0) else {
+            return Ok(None);
+        };
+        let plugin_id = &plugin.manifest.id;
+        let queue = {
+            let mut plugins = self.plugins.lock().expect("plugin queue mutex poisoned");
+            let queue = plugins
+                .entry(plugin_id.clone())
+                .or_insert_with(|| Queue::new(limit));
+            // Invocations still running under the old limit keep their permits.
+            if queue.limit != limit {
+                *queue = Queue::new(limit);
+            }
+            queue.clone()
+        };
+
+        if let Ok(permit) = queue.slots.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+        let queued = queue.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&queue.waiting);
+        if queued >= MAX_QUEUED {
+            app_core::metrics::metrics().plugin_queue_rejections.inc();
+            return Err(QueueError::Full {
+                plugin_id: plugin_id.clone(),
+                running: limit - queue.slots.available_permits().min(limit),
+                queued,
+            });
+        }
+        debug!(%plugin_id, queued = queued + 1, "plugin invocation queued");
+        let permit = queue.slots.clone().acquire_owned().await;
+        let permit = permit.expect("plugin queue semaphore is never closed");
+        Ok(Some(permit))
+    }
+}
+
+impl Queue {
+    fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            slots: Arc::new(Semaphore::new(limit)),
+            waiting: AtomicUsize::new(0),
+        })
+    }
+}
@@ -0,0 +1,111 @@
+//! Optional sandbox for plugin executables (Linux, via `bwrap`).
+//!
+//! The sandboxed process sees a read-only system (`/usr`, `/lib*`, `/bin`, a few `/etc` files),
+//! its own plugin directory (read-write), and a private `/tmp`. Every namespace is unshared,
+//! including the network unless the manifest declares `permissions.network` and the user
+//! allowed it. It starts in the plugin directory, or in the configured working directory when
+//! that is inside it.
+
+use std::path::{Component, Path, PathBuf};
+
+use openaction::registry::InstalledPlugin;
+use tokio::process::Command;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SandboxError {
+    #[error("plugin {plugin_id} requests network access; allow or deny it first")]
+    PermissionPending { plugin_id: String },
+    #[error("plugin sandbox is enabled but `bwrap` (bubblewrap) was not found in PATH")]
+    BwrapMissing,
+}
+
+/// System paths bound read-only so dynamically linked executables and interpreters still run.
+const RO_SYSTEM_PATHS: &[&str] = &[
+    "/usr",
+    "/lib",
+    "/lib64",
+    "/lib32",
+    "/bin",
+    "/sbin",
+    "/etc/alternatives",
+    "/etc/ld.so.cache",
+    "/etc/ld.so.conf",
+    "/etc/ld.so.conf.d",
+    "/etc/localtime",
+];
+
+/// Extra `/etc` entries needed for name resolution and TLS when network is allowed.
+const RO_NETWORK_PATHS: &[&str] = &[
+    "/etc/resolv.conf",
+    "/etc/hosts",
+    "/etc/nsswitch.conf",
+    "/etc/ssl",
+    "/etc/ca-certificates",
+    "/etc/pki",
+];
+
+pub fn bwrap_path() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join("bwrap"))
+        .find(|p| p.is_file())
+}
+
+/// Build the command used to launch `exe` for `plugin`, with its launch configuration
+/// (arguments, environment, working directory) applied.
+///
+/// Returns a plain command when the plugin is not sandboxed or the platform has no sandbox.
+pub fn command_for(plugin: &InstalledPlugin, exe: &Path) -> anyhow::Result<Command> {
+    let launch = &plugin.launch;
+    if !plugin.sandboxed || !cfg!(target_os = "linux") {
+        let mut cmd = Command::new(exe);
+        cmd.args(&launch.args).envs(&launch.env);
+        if let Some(dir) = plugin.working_dir() {
+            cmd.current_dir(dir);
+        }
+        return Ok(cmd);
+    }
+
+    let wants_network = plugin.manifest.permissions.network;
+    let allow_network = match (wants_network, plugin.network_granted) {
+        (false, _) => false,
+        (true, Some(granted)) => granted,
+        (true, None) => {
+            return Err(SandboxError::PermissionPending {
+                plugin_id: plugin.manifest.id.clone(),
+            }
+            .into())
+        }
+    };
+
+    let bwrap = bwrap_path().ok_or(SandboxError::BwrapMissing)?;
+    let mut cmd = Command::new(bwrap);
+    cmd.args(["--unshare-all", "--die-with-parent", "--new-session"]);
+    if allow_network {
+        cmd.arg("--share-net");
+    }
+    for p in RO_SYSTEM_PATHS {
+        cmd.args(["--ro-bind-try", p, p]);
+    }
+    if allow_network {
+        for p in RO_NETWORK_PATHS {
+            cmd.args(["--ro-bind-try", p, p]);
+        }
+    }
+    cmd.args(["--proc", "/proc", "--dev", "/dev", "--tmpfs", "/tmp"]);
+    cmd.arg("--bind").arg(&plugin.dir).arg(&plugin.dir);
+    // Nothing outside the plugin directory is there to start in.
+    let dir = plugin
+        .working_dir()
+        .filter(|d| {
+            d.starts_with(&plugin.dir) && !d.components().any(|c| c == Component::ParentDir)
+        })
+        .unwrap_or_else(|| plugin.dir.clone());
+    cmd.arg("--chdir").arg(dir);
+    cmd.arg("--setenv").arg("TMPDIR").arg("/tmp");
+    for (key, value) in &launch.env {
+        cmd.arg("--setenv").arg(key).arg(value);
+    }
+    cmd.arg("--").arg(exe).args(&launch.args);
+    Ok(cmd)
+}
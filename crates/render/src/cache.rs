@@ -0,0 +1,199 @@
+//! Content-hash cache for LCD images.
+//!
+//! Every image is identified by a hash of what it is rendered from (size, background, text and
+//! the icon file's path/size/mtime). Rendered JPEGs are reused for identical content, and the
+//! cache double-buffers what is on the device: `prepare` stages a slot's new content in the back
+//! buffer and returns `None` when the front buffer (last content confirmed on the device)
+//! already matches, so unchanged keys are neither re-rendered nor re-sent.
+//!
+//! The editor's live preview renders through the same cache with [`ImageCache::render`], so it
+//! shows exactly the JPEGs the device gets without rendering them twice.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+/// Rendered images kept beyond those referenced by the front/back buffers.
+const MAX_ENTRIES: usize = 256;
+
+/// A display surface on the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Key(u8),
+    Dial(u8),
+    TouchStrip,
+}
+
+/// Inputs of [`crate::lcd::render_lcd_jpeg`] and the progress ring, or of
+/// [`crate::dashboard::render_dashboard_jpeg`] when there is a dashboard.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LcdImage {
+    pub width: u32,
+    pub height: u32,
+    pub background_rgb: Option<[u8; 3]>,
+    pub icon_path: Option<PathBuf>,
+    pub text: Option<String>,
+    /// Pixel scale of the 8x8 text font; picked from the height when `None`.
+    pub text_scale: Option<u32>,
+    pub flash: Option<crate::lcd::Flash>,
+    /// Percent of a hold-to-confirm press, drawn as a ring along the edge.
+    pub progress: Option<u8>,
+    /// Drawn instead of the icon and text.
+    pub dashboard: Option<crate::dashboard::Dashboard>,
+}
+
+impl LcdImage {
+    /// Hash of everything that affects the rendered pixels.
+    pub fn content_hash(&self) -> u64 {
+        let mut h = DefaultHasher::new();
+        self.hash(&mut h);
+        // Pick up edits to the icon file itself, not just a different path.
+        if let Some(meta) = self
+            .icon_path
+            .as_ref()
+            .and_then(|p| std::fs::metadata(p).ok())
+        {
+            meta.len().hash(&mut h);
+            meta.modified()
+                .ok()
+                .map(|t| t.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default())
+                .hash(&mut h);
+        }
+        h.finish()
+    }
+
+    pub fn render(&self) -> anyhow::Result<Vec<u8>> {
+        if let Some(dashboard) = &self.dashboard {
+            return crate::dashboard::render_dashboard_jpeg(
+                self.width,
+                self.height,
+                self.background_rgb,
+                dashboard,
+                self.flash,
+            );
+        }
+        let mut frame = crate::lcd::render_lcd_frame(
+            self.width,
+            self.height,
+            self.background_rgb,
+            self.icon_path.as_deref(),
+            self.text.as_deref(),
+            self.text_scale,
+        );
+        if let Some(percent) = self.progress {
+            crate::lcd::draw_progress_ring(&mut frame, percent);
+        }
+        crate::lcd::apply_flash(&mut frame, self.flash);
+        crate::lcd::encode_jpeg(&frame)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Images served from the cache instead of being rendered.
+    pub render_hits: u64,
+    /// Images rendered.
+    pub render_misses: u64,
+    /// Slots skipped because the device already shows the same content.
+    pub sends_skipped: u64,
+    /// Slots whose new content was handed out for sending.
+    pub sends: u64,
+    /// Rendered images currently cached.
+    pub entries: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ImageCache {
+    rendered: HashMap<u64, Arc<Vec<u8>>>,
+    /// Content confirmed on the device, per slot.
+    front: HashMap<Slot, u64>,
+    /// Content handed out by `prepare` and not yet committed.
+    back: HashMap<Slot, u64>,
+    stats: CacheStats,
+}
+
+impl ImageCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage `image` for `slot`; returns the JPEG to send, or `None` if the slot is up to date.
+    ///
+    /// Call [`commit`](Self::commit) after a successful send and [`discard`](Self::discard)
+    /// after a failed one.
+    pub fn prepare(
+        &mut self,
+        slot: Slot,
+        image: &LcdImage,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let hash = image.content_hash();
+        if self.front.get(&slot) == Some(&hash) {
+            self.stats.sends_skipped += 1;
+            return Ok(None);
+        }
+
+        let jpeg = self.rendered(hash, image)?;
+        self.back.insert(slot, hash);
+        self.stats.sends += 1;
+        Ok(Some(jpeg.as_ref().clone()))
+    }
+
+    /// The JPEG for `image`, rendered or from the cache, without staging it for any slot.
+    pub fn render(&mut self, image: &LcdImage) -> anyhow::Result<Arc<Vec<u8>>> {
+        self.rendered(image.content_hash(), image)
+    }
+
+    fn rendered(&mut self, hash: u64, image: &LcdImage) -> anyhow::Result<Arc<Vec<u8>>> {
+        if let Some(jpeg) = self.rendered.get(&hash) {
+            self.stats.render_hits += 1;
+            return Ok(jpeg.clone());
+        }
+        self.stats.render_misses += 1;
+        let started = Instant::now();
+        let jpeg = Arc::new(image.render()?);
+        app_core::metrics::metrics().render_seconds.observe(started.elapsed());
+        self.evict_if_full();
+        self.rendered.insert(hash, jpeg.clone());
+        Ok(jpeg)
+    }
+
+    /// The staged content of `slot` reached the device.
+    pub fn commit(&mut self, slot: Slot) {
+        if let Some(hash) = self.back.remove(&slot) {
+            self.front.insert(slot, hash);
+        }
+    }
+
+    /// Sending the staged content failed; the slot's device state is unknown now.
+    pub fn discard(&mut self, slot: Slot) {
+        self.back.remove(&slot);
+        self.front.remove(&slot);
+    }
+
+    /// Something else drew on the device; resend every slot on the next `prepare`.
+    pub fn invalidate(&mut self) {
+        self.back.clear();
+        self.front.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.rendered.len(),
+            ..self.stats
+        }
+    }
+
+    fn evict_if_full(&mut self) {
+        if self.rendered.len() < MAX_ENTRIES {
+            return;
+        }
+        let front = &self.front;
+        let back = &self.back;
+        self.rendered.retain(|hash, _| {
+            front.values().any(|h| h == hash) || back.values().any(|h| h == hash)
+        });
+    }
+}
@@ -0,0 +1,134 @@
+//! Multi-metric dashboard drawn across a wide display such as the Stream Deck+ touch strip.
+//!
+//! The frame is split into equal panels side by side; each shows a caption, the metric's
+//! current value and a filled line graph of its recent history. On the 800×100 strip four
+//! panels line up with the dials below them.
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+
+use crate::lcd::{apply_flash, draw_text_at, encode_jpeg, fill_rect, Flash};
+
+/// Space around a panel's contents.
+const PADDING: u32 = 6;
+/// Gap between the value and the graph.
+const GRAPH_GAP: u32 = 4;
+
+const LABEL_RGBA: Rgba<u8> = Rgba([150, 150, 160, 255]);
+const VALUE_RGBA: Rgba<u8> = Rgba([235, 235, 240, 255]);
+const SEPARATOR_RGBA: Rgba<u8> = Rgba([60, 60, 66, 255]);
+
+/// Panels shown side by side, left to right.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Dashboard {
+    pub panels: Vec<Panel>,
+}
+
+/// One metric of a [`Dashboard`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Panel {
+    /// Caption in the top left corner, e.g. `CPU`.
+    pub label: String,
+    /// Current value, e.g. `42%`.
+    pub value: String,
+    /// Recent values, oldest first, in percent of the graph's height (clamped to 100).
+    pub history: Vec<u8>,
+    /// Colour of the graph.
+    pub rgb: [u8; 3],
+}
+
+/// Render `dashboard` on a `width`×`height` frame to JPEG bytes.
+pub fn render_dashboard_jpeg(
+    width: u32,
+    height: u32,
+    background_rgb: Option<[u8; 3]>,
+    dashboard: &Dashboard,
+    flash: Option<Flash>,
+) -> anyhow::Result<Vec<u8>> {
+    let bg = background_rgb.unwrap_or([16, 16, 18]);
+    let mut frame: RgbaImage =
+        ImageBuffer::from_pixel(width, height, Rgba([bg[0], bg[1], bg[2], 255]));
+    draw_dashboard(&mut frame, dashboard);
+    apply_flash(&mut frame, flash);
+    encode_jpeg(&frame)
+}
+
+/// Draw the panels of `dashboard` over `frame`.
+pub fn draw_dashboard(frame: &mut RgbaImage, dashboard: &Dashboard) {
+    let count = dashboard.panels.len() as u32;
+    if count == 0 {
+        return;
+    }
+    let panel_w = frame.width() / count;
+    for (i, panel) in dashboard.panels.iter().enumerate() {
+        let x0 = i as u32 * panel_w;
+        if i > 0 {
+            fill_rect(
+                frame,
+                x0,
+                PADDING,
+                1,
+                frame.height().saturating_sub(2 * PADDING),
+                SEPARATOR_RGBA,
+            );
+        }
+        draw_panel(frame, x0, panel_w, panel);
+    }
+}
+
+fn draw_panel(frame: &mut RgbaImage, x0: u32, width: u32, panel: &Panel) {
+    let inner_w = width.saturating_sub(2 * PADDING);
+    let (x, mut y) = (x0 + PADDING, PADDING);
+
+    // Tall frames get a larger value; the 100 px strip fits an 8 px caption and a 16 px value.
+    let value_scale = if frame.height() >= 72 { 2 } else { 1 };
+    let fit = |scale: u32| (inner_w / (8 * scale)) as usize;
+
+    let label: Vec<char> = panel.label.chars().take(fit(1)).collect();
+    draw_text_at(frame, x, y, &label, 1, LABEL_RGBA);
+    y += 8 + 2;
+
+    let value: Vec<char> = panel.value.chars().take(fit(value_scale)).collect();
+    draw_text_at(frame, x, y, &value, value_scale, VALUE_RGBA);
+    y += 8 * value_scale + GRAPH_GAP;
+
+    let graph_h = frame.height().saturating_sub(y + PADDING);
+    draw_graph(frame, x, y, inner_w, graph_h, panel);
+}
+
+/// A filled line graph of `panel.history`, stretched to fill the `width`×`height` box.
+fn draw_graph(frame: &mut RgbaImage, x0: u32, y0: u32, width: u32, height: u32, panel: &Panel) {
+    let len = panel.history.len();
+    if len == 0 || width == 0 || height == 0 {
+        return;
+    }
+    let [r, g, b] = panel.rgb;
+    let line = Rgba([r, g, b, 255]);
+    let area = Rgba([r, g, b, 80]);
+
+    // Baseline, so an idle metric still shows where its graph is.
+    fill_rect(frame, x0, y0 + height - 1, width, 1, SEPARATOR_RGBA);
+
+    for col in 0..width {
+        let sample = panel.history[col as usize * len / width as usize].min(100) as u32;
+        let bar_h = (sample * height).div_ceil(100);
+        if bar_h == 0 {
+            continue;
+        }
+        let top = y0 + height - bar_h;
+        for y in top + 1..y0 + height {
+            blend(frame, x0 + col, y, area);
+        }
+        fill_rect(frame, x0 + col, top, 1, 2.min(bar_h), line);
+    }
+}
+
+fn blend(frame: &mut RgbaImage, x: u32, y: u32, src: Rgba<u8>) {
+    if x >= frame.width() || y >= frame.height() {
+        return;
+    }
+    let dst = frame.get_pixel_mut(x, y);
+    let a = src[3] as u16;
+    for c in 0..3 {
+        dst[c] = ((src[c] as u16 * a + dst[c] as u16 * (255 - a)) / 255) as u8;
+    }
+}
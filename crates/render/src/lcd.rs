@@ -5,19 +5,46 @@ use image::codecs::jpeg::JpegEncoder;
 use image::imageops::FilterType;
 use image::{DynamicImage, ImageBuffer, Pixel, Rgb, Rgba, RgbaImage};
 
+/// Effect drawn over a rendered frame, e.g. to confirm that a key fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flash {
+    /// Invert every pixel.
+    Invert,
+    /// A bright border along the edges.
+    Border,
+}
+
 /// Render a simple LCD frame (background + optional icon + optional text) to JPEG bytes.
 ///
 /// This is intentionally “dumb but reliable” for MVP:
 /// - background: either a solid RGB or a default dark gray
 /// - icon: optional image from disk; resized to fit and centered
-/// - text: optional single-line text rendered with an 8x8 bitmap font
+/// - text: optional text rendered with an 8x8 bitmap font, scaled by `text_scale` (picked
+///   from the frame height when `None`); lines are stacked up from the bottom edge
+/// - flash: optional effect applied on top of everything else
 pub fn render_lcd_jpeg(
     width: u32,
     height: u32,
     background_rgb: Option<[u8; 3]>,
     icon_path: Option<&Path>,
     text: Option<&str>,
+    text_scale: Option<u32>,
+    flash: Option<Flash>,
 ) -> anyhow::Result<Vec<u8>> {
+    let mut frame = render_lcd_frame(width, height, background_rgb, icon_path, text, text_scale);
+    apply_flash(&mut frame, flash);
+    encode_jpeg(&frame)
+}
+
+/// The frame of [`render_lcd_jpeg`] before the flash and encoding.
+pub(crate) fn render_lcd_frame(
+    width: u32,
+    height: u32,
+    background_rgb: Option<[u8; 3]>,
+    icon_path: Option<&Path>,
+    text: Option<&str>,
+    text_scale: Option<u32>,
+) -> RgbaImage {
     let bg = background_rgb.unwrap_or([16, 16, 18]);
 
     let mut frame: RgbaImage = ImageBuffer::from_pixel(width, height, Rgba([bg[0], bg[1], bg[2], 255]));
@@ -29,11 +56,44 @@ pub fn render_lcd_jpeg(
     }
 
     if let Some(t) = text {
-        draw_text_bottom_center(&mut frame, t, Rgba([235, 235, 240, 255]));
+        draw_text_bottom_center(&mut frame, t, text_scale, Rgba([235, 235, 240, 255]));
+    }
+    frame
+}
+
+/// Draw a ring along the frame's edge, filled clockwise from 12 o'clock up to `percent`.
+pub(crate) fn draw_progress_ring(frame: &mut RgbaImage, percent: u8) {
+    let (w, h) = frame.dimensions();
+    let thickness = (w.min(h) / 10).max(3) as f32;
+    let radius = w.min(h) as f32 / 2.0 - thickness / 2.0 - 1.0;
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+    let filled = f32::from(percent.min(100)) / 100.0;
+    for (x, y, px) in frame.enumerate_pixels_mut() {
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        if ((dx * dx + dy * dy).sqrt() - radius).abs() > thickness / 2.0 {
+            continue;
+        }
+        // Fraction of a turn clockwise from the top.
+        let turn = dx.atan2(-dy).rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU;
+        *px = if turn <= filled {
+            Rgba([250, 190, 40, 255])
+        } else {
+            Rgba([70, 70, 76, 255])
+        };
     }
+}
 
+pub(crate) fn apply_flash(frame: &mut RgbaImage, flash: Option<Flash>) {
+    match flash {
+        Some(Flash::Invert) => image::imageops::invert(frame),
+        Some(Flash::Border) => draw_border(frame, Rgba([250, 250, 255, 255])),
+        None => {}
+    }
+}
+
+pub(crate) fn encode_jpeg(frame: &RgbaImage) -> anyhow::Result<Vec<u8>> {
     // JPEG has no alpha, so flatten to RGB.
-    let mut rgb = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(width, height);
+    let mut rgb = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(frame.width(), frame.height());
     for (x, y, px) in frame.enumerate_pixels() {
         let c = px.to_rgb();
         rgb.put_pixel(x, y, c);
@@ -50,6 +110,42 @@ pub fn render_lcd_jpeg(
     Ok(out)
 }
 
+/// Load the icon at `path`, scaled to fit a `size`×`size` box (aspect preserved), for UI lists.
+pub fn load_icon(path: &Path, size: u32) -> anyhow::Result<RgbaImage> {
+    let img = image::open(path)?;
+    Ok(img.resize(size, size, FilterType::Triangle).to_rgba8())
+}
+
+/// Encode a raw RGB bitmap (3 bytes per pixel) as a `width`×`height` JPEG, scaling if needed.
+///
+/// Used for images rendered elsewhere, e.g. key bitmaps sent by Bitfocus Companion.
+pub fn rgb_bitmap_jpeg(
+    bitmap_width: u32,
+    bitmap_height: u32,
+    rgb: Vec<u8>,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let Some(bitmap) = ImageBuffer::<Rgb<u8>, _>::from_raw(bitmap_width, bitmap_height, rgb) else {
+        anyhow::bail!("bitmap is not {bitmap_width}x{bitmap_height} RGB");
+    };
+    let frame = if (bitmap_width, bitmap_height) == (width, height) {
+        bitmap
+    } else {
+        image::imageops::resize(&bitmap, width, height, FilterType::Triangle)
+    };
+
+    let mut out = Vec::new();
+    let mut enc = JpegEncoder::new_with_quality(&mut out, 90);
+    enc.encode(
+        frame.as_raw(),
+        frame.width(),
+        frame.height(),
+        image::ColorType::Rgb8.into(),
+    )?;
+    Ok(out)
+}
+
 fn overlay_icon(dst: &mut RgbaImage, icon: &DynamicImage) {
     let (w, h) = dst.dimensions();
     if w == 0 || h == 0 {
@@ -78,29 +174,40 @@ fn overlay_icon(dst: &mut RgbaImage, icon: &DynamicImage) {
     alpha_blit(dst, &resized, ox, oy);
 }
 
-fn draw_text_bottom_center(img: &mut RgbaImage, text: &str, color: Rgba<u8>) {
+fn draw_text_bottom_center(img: &mut RgbaImage, text: &str, scale: Option<u32>, color: Rgba<u8>) {
     // Use 8x8 font, scale up for readability.
-    let scale: u32 = ((img.height() as f32 / 72.0).clamp(1.0, 3.0)).round() as u32;
+    let scale = scale
+        .unwrap_or_else(|| ((img.height() as f32 / 72.0).clamp(1.0, 3.0)).round() as u32)
+        .max(1);
     let char_w = 8 * scale;
     let char_h = 8 * scale;
     let padding = 4 * scale;
 
-    let printable: Vec<char> = text.chars().take(20).collect();
-    if printable.is_empty() {
-        return;
-    }
+    // Lines are stacked upwards from the bottom edge.
+    let line_h = char_h + 2 * scale;
+    for (i, line) in text.lines().rev().enumerate() {
+        let printable: Vec<char> = line.chars().take(20).collect();
+        if printable.is_empty() {
+            continue;
+        }
 
-    let text_w = (printable.len() as u32) * char_w;
-    let x0 = (img.width().saturating_sub(text_w)) / 2;
-    let y0 = img.height().saturating_sub(char_h + padding);
+        let text_w = (printable.len() as u32) * char_w;
+        let x0 = (img.width().saturating_sub(text_w)) / 2;
+        let Some(y0) = img
+            .height()
+            .checked_sub(char_h + padding + i as u32 * line_h)
+        else {
+            break;
+        };
 
-    // Slight shadow for contrast.
-    let shadow = Rgba([0, 0, 0, 180]);
-    draw_text_at(img, x0 + scale, y0 + scale, &printable, scale, shadow);
-    draw_text_at(img, x0, y0, &printable, scale, color);
+        // Slight shadow for contrast.
+        let shadow = Rgba([0, 0, 0, 180]);
+        draw_text_at(img, x0 + scale, y0 + scale, &printable, scale, shadow);
+        draw_text_at(img, x0, y0, &printable, scale, color);
+    }
 }
 
-fn draw_text_at(
+pub(crate) fn draw_text_at(
     img: &mut RgbaImage,
     x0: u32,
     y0: u32,
@@ -133,7 +240,16 @@ fn draw_char(img: &mut RgbaImage, x0: u32, y0: u32, ch: char, scale: u32, color:
     }
 }
 
-fn fill_rect(img: &mut RgbaImage, x0: u32, y0: u32, w: u32, h: u32, c: Rgba<u8>) {
+fn draw_border(img: &mut RgbaImage, color: Rgba<u8>) {
+    let (w, h) = img.dimensions();
+    let t = (w.min(h) / 12).max(3);
+    fill_rect(img, 0, 0, w, t, color);
+    fill_rect(img, 0, h.saturating_sub(t), w, t, color);
+    fill_rect(img, 0, 0, t, h, color);
+    fill_rect(img, w.saturating_sub(t), 0, t, h, color);
+}
+
+pub(crate) fn fill_rect(img: &mut RgbaImage, x0: u32, y0: u32, w: u32, h: u32, c: Rgba<u8>) {
     for y in y0..y0.saturating_add(h) {
         if y >= img.height() {
             break;
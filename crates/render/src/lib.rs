@@ -1,5 +1,8 @@
 //! Key image rendering pipeline.
 
+pub mod cache;
+pub mod dashboard;
 pub mod lcd;
 pub mod plus_strip;
 pub mod test_patterns;
+pub mod weather;
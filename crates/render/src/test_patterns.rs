@@ -4,7 +4,98 @@ use image::{ImageBuffer, Rgb};
 /// Generate a solid-color JPEG image (RGB) for quick device bring-up.
 pub fn solid_color_jpeg(width: u32, height: u32, rgb: [u8; 3]) -> anyhow::Result<Vec<u8>> {
     let img: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::from_pixel(width, height, Rgb(rgb));
+    encode_jpeg(&img)
+}
+
+/// Patterns for checking a device's keys: dead pixels, color channels, scaling and which key
+/// shows which image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+    Red,
+    Green,
+    Blue,
+    White,
+    /// Black to white from left to right, with the color channels top to bottom.
+    Gradient,
+    /// One-pixel checkerboard; blurry or moiré keys are being scaled.
+    Checkerboard,
+    /// The key's index, to spot keys drawn at the wrong position.
+    KeyIndex,
+}
+
+impl TestPattern {
+    pub const ALL: [Self; 7] = [
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::White,
+        Self::Gradient,
+        Self::Checkerboard,
+        Self::KeyIndex,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Red => "Red",
+            Self::Green => "Green",
+            Self::Blue => "Blue",
+            Self::White => "White",
+            Self::Gradient => "Gradient",
+            Self::Checkerboard => "Checkerboard",
+            Self::KeyIndex => "Key index",
+        }
+    }
+}
+
+/// Render `pattern` for `key` as a `width`×`height` JPEG.
+pub fn pattern_jpeg(
+    pattern: TestPattern,
+    key: u8,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let solid = |rgb| solid_color_jpeg(width, height, rgb);
+    match pattern {
+        TestPattern::Red => solid([255, 0, 0]),
+        TestPattern::Green => solid([0, 255, 0]),
+        TestPattern::Blue => solid([0, 0, 255]),
+        TestPattern::White => solid([255, 255, 255]),
+        TestPattern::Gradient => {
+            let img = ImageBuffer::from_fn(width, height, |x, y| {
+                let level = (x * 255 / width.max(2).saturating_sub(1)).min(255) as u8;
+                // Grey on top, then red, green and blue bands.
+                match y * 4 / height.max(1) {
+                    0 => Rgb([level; 3]),
+                    1 => Rgb([level, 0, 0]),
+                    2 => Rgb([0, level, 0]),
+                    _ => Rgb([0, 0, level]),
+                }
+            });
+            encode_jpeg(&img)
+        }
+        TestPattern::Checkerboard => {
+            let img = ImageBuffer::from_fn(width, height, |x, y| {
+                if (x + y) % 2 == 0 {
+                    Rgb([255; 3])
+                } else {
+                    Rgb([0; 3])
+                }
+            });
+            encode_jpeg(&img)
+        }
+        TestPattern::KeyIndex => crate::lcd::render_lcd_jpeg(
+            width,
+            height,
+            Some([0, 0, 0]),
+            None,
+            Some(&key.to_string()),
+            Some((height / 24).max(1)),
+            Some(crate::lcd::Flash::Border),
+        ),
+    }
+}
 
+fn encode_jpeg(img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> anyhow::Result<Vec<u8>> {
     let mut out = Vec::new();
     let mut enc = JpegEncoder::new_with_quality(&mut out, 90);
     enc.encode(
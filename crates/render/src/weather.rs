@@ -0,0 +1,293 @@
+//! Weather condition icons, drawn from simple shapes so no image assets have to ship.
+//!
+//! Icons are drawn on a transparent square and encoded as PNG, for use as a key's icon.
+
+use std::io::Cursor;
+
+use image::{ImageBuffer, ImageFormat, Rgba, RgbaImage};
+
+/// Samples per pixel along each axis, for smooth edges.
+const SUPERSAMPLE: u32 = 4;
+
+const SUN_RGB: [u8; 3] = [255, 196, 40];
+const MOON_RGB: [u8; 3] = [222, 222, 236];
+const CLOUD_RGB: [u8; 3] = [206, 210, 220];
+/// Clouds that bring rain, snow or storms.
+const DARK_CLOUD_RGB: [u8; 3] = [150, 155, 168];
+const RAIN_RGB: [u8; 3] = [80, 160, 255];
+const SNOW_RGB: [u8; 3] = [245, 248, 255];
+const BOLT_RGB: [u8; 3] = [255, 214, 60];
+
+/// What a weather icon shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WeatherIcon {
+    Clear,
+    ClearNight,
+    PartlyCloudy,
+    PartlyCloudyNight,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunderstorm,
+}
+
+impl WeatherIcon {
+    /// Lowercase name, e.g. `partly-cloudy-night`; used for file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            WeatherIcon::Clear => "clear",
+            WeatherIcon::ClearNight => "clear-night",
+            WeatherIcon::PartlyCloudy => "partly-cloudy",
+            WeatherIcon::PartlyCloudyNight => "partly-cloudy-night",
+            WeatherIcon::Cloudy => "cloudy",
+            WeatherIcon::Fog => "fog",
+            WeatherIcon::Drizzle => "drizzle",
+            WeatherIcon::Rain => "rain",
+            WeatherIcon::Snow => "snow",
+            WeatherIcon::Thunderstorm => "thunderstorm",
+        }
+    }
+}
+
+/// A filled shape in coordinates relative to the icon, `0.0..1.0` on both axes.
+enum Shape {
+    Disc {
+        x: f32,
+        y: f32,
+        r: f32,
+    },
+    /// A line with round caps.
+    Segment {
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+    },
+    Polygon(Vec<(f32, f32)>),
+}
+
+impl Shape {
+    fn contains(&self, px: f32, py: f32) -> bool {
+        match self {
+            Shape::Disc { x, y, r } => (px - x).powi(2) + (py - y).powi(2) <= r * r,
+            Shape::Segment { from, to, width } => {
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                let len2 = (dx * dx + dy * dy).max(f32::EPSILON);
+                let t = (((px - from.0) * dx + (py - from.1) * dy) / len2).clamp(0.0, 1.0);
+                let (cx, cy) = (from.0 + t * dx, from.1 + t * dy);
+                (px - cx).powi(2) + (py - cy).powi(2) <= (width / 2.0).powi(2)
+            }
+            Shape::Polygon(points) => {
+                // Even-odd rule.
+                let mut inside = false;
+                let mut j = points.len().wrapping_sub(1);
+                for (i, &(xi, yi)) in points.iter().enumerate() {
+                    let (xj, yj) = points[j];
+                    if (yi > py) != (yj > py) && px < (xj - xi) * (py - yi) / (yj - yi) + xi {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+}
+
+/// Shapes in one colour, minus the `cut` shapes (e.g. the moon's crescent).
+struct Layer {
+    rgb: [u8; 3],
+    shapes: Vec<Shape>,
+    cut: Vec<Shape>,
+}
+
+impl Layer {
+    fn new(rgb: [u8; 3], shapes: Vec<Shape>) -> Self {
+        Self {
+            rgb,
+            shapes,
+            cut: vec![],
+        }
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.shapes.iter().any(|s| s.contains(x, y)) && !self.cut.iter().any(|s| s.contains(x, y))
+    }
+}
+
+/// Draw `icon` on a transparent `size`×`size` image.
+pub fn draw_weather_icon(icon: WeatherIcon, size: u32) -> RgbaImage {
+    let mut img: RgbaImage = ImageBuffer::from_pixel(size, size, Rgba([0, 0, 0, 0]));
+    for layer in layers(icon) {
+        paint(&mut img, &layer);
+    }
+    img
+}
+
+/// [`draw_weather_icon`] encoded as PNG.
+pub fn weather_icon_png(icon: WeatherIcon, size: u32) -> anyhow::Result<Vec<u8>> {
+    let mut out = Cursor::new(Vec::new());
+    draw_weather_icon(icon, size).write_to(&mut out, ImageFormat::Png)?;
+    Ok(out.into_inner())
+}
+
+fn layers(icon: WeatherIcon) -> Vec<Layer> {
+    match icon {
+        WeatherIcon::Clear => vec![sun(0.5, 0.5, 0.2)],
+        WeatherIcon::ClearNight => vec![moon(0.5, 0.5, 0.3)],
+        WeatherIcon::PartlyCloudy => vec![sun(0.36, 0.36, 0.14), cloud(0.08, CLOUD_RGB)],
+        WeatherIcon::PartlyCloudyNight => vec![moon(0.36, 0.34, 0.2), cloud(0.08, CLOUD_RGB)],
+        WeatherIcon::Cloudy => vec![cloud(0.0, CLOUD_RGB)],
+        WeatherIcon::Fog => {
+            let bars = [0.3, 0.46, 0.62, 0.78]
+                .iter()
+                .enumerate()
+                .map(|(i, &y)| {
+                    // Staggered, like drifting fog.
+                    let shift = if i % 2 == 0 { 0.0 } else { 0.08 };
+                    Shape::Segment {
+                        from: (0.16 + shift, y),
+                        to: (0.76 + shift, y),
+                        width: 0.08,
+                    }
+                })
+                .collect();
+            vec![Layer::new(CLOUD_RGB, bars)]
+        }
+        WeatherIcon::Drizzle => vec![cloud(-0.1, DARK_CLOUD_RGB), drops(0.06)],
+        WeatherIcon::Rain => vec![cloud(-0.1, DARK_CLOUD_RGB), drops(0.14)],
+        WeatherIcon::Snow => {
+            let flakes = [
+                (0.32, 0.74),
+                (0.5, 0.84),
+                (0.68, 0.74),
+                (0.41, 0.92),
+                (0.59, 0.92),
+            ]
+            .iter()
+            .map(|&(x, y)| Shape::Disc { x, y, r: 0.045 })
+            .collect();
+            vec![cloud(-0.1, DARK_CLOUD_RGB), Layer::new(SNOW_RGB, flakes)]
+        }
+        WeatherIcon::Thunderstorm => {
+            let bolt = Shape::Polygon(vec![
+                (0.54, 0.5),
+                (0.36, 0.76),
+                (0.5, 0.76),
+                (0.42, 0.98),
+                (0.68, 0.66),
+                (0.54, 0.66),
+                (0.62, 0.5),
+            ]);
+            vec![
+                cloud(-0.1, DARK_CLOUD_RGB),
+                Layer::new(BOLT_RGB, vec![bolt]),
+            ]
+        }
+    }
+}
+
+/// A sun with eight rays.
+fn sun(x: f32, y: f32, r: f32) -> Layer {
+    let mut shapes = vec![Shape::Disc { x, y, r }];
+    shapes.extend((0..8).map(|i| {
+        let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+        let (dx, dy) = (angle.cos(), angle.sin());
+        Shape::Segment {
+            from: (x + dx * r * 1.35, y + dy * r * 1.35),
+            to: (x + dx * r * 1.75, y + dy * r * 1.75),
+            width: r * 0.3,
+        }
+    }));
+    Layer::new(SUN_RGB, shapes)
+}
+
+/// A crescent moon, open to the top right.
+fn moon(x: f32, y: f32, r: f32) -> Layer {
+    Layer {
+        rgb: MOON_RGB,
+        shapes: vec![Shape::Disc { x, y, r }],
+        cut: vec![Shape::Disc {
+            x: x + r * 0.55,
+            y: y - r * 0.35,
+            r: r * 0.85,
+        }],
+    }
+}
+
+/// A cloud across the middle of the icon, moved down by `dy`.
+fn cloud(dy: f32, rgb: [u8; 3]) -> Layer {
+    Layer::new(
+        rgb,
+        vec![
+            Shape::Disc {
+                x: 0.34,
+                y: 0.58 + dy,
+                r: 0.14,
+            },
+            Shape::Disc {
+                x: 0.52,
+                y: 0.5 + dy,
+                r: 0.2,
+            },
+            Shape::Disc {
+                x: 0.7,
+                y: 0.6 + dy,
+                r: 0.12,
+            },
+            Shape::Segment {
+                from: (0.3, 0.64 + dy),
+                to: (0.72, 0.64 + dy),
+                width: 0.16,
+            },
+        ],
+    )
+}
+
+/// Slanted drops under a cloud, each `length` long.
+fn drops(length: f32) -> Layer {
+    let drops = [(0.36, 0.7), (0.52, 0.74), (0.68, 0.7)]
+        .iter()
+        .map(|&(x, y)| Shape::Segment {
+            from: (x, y),
+            to: (x - length * 0.35, y + length),
+            width: 0.05,
+        })
+        .collect();
+    Layer::new(RAIN_RGB, drops)
+}
+
+/// Composite `layer` over `img`, with coverage from [`SUPERSAMPLE`]² samples per pixel.
+fn paint(img: &mut RgbaImage, layer: &Layer) {
+    let size = img.width().max(1) as f32;
+    let samples = SUPERSAMPLE * SUPERSAMPLE;
+    let [r, g, b] = layer.rgb;
+    for (px, py, dst) in img.enumerate_pixels_mut() {
+        let mut covered = 0;
+        for sy in 0..SUPERSAMPLE {
+            for sx in 0..SUPERSAMPLE {
+                let x = (px as f32 + (sx as f32 + 0.5) / SUPERSAMPLE as f32) / size;
+                let y = (py as f32 + (sy as f32 + 0.5) / SUPERSAMPLE as f32) / size;
+                if layer.contains(x, y) {
+                    covered += 1;
+                }
+            }
+        }
+        if covered == 0 {
+            continue;
+        }
+        // Source-over with straight alpha.
+        let sa = covered as f32 / samples as f32;
+        let da = dst[3] as f32 / 255.0;
+        let out_a = sa + da * (1.0 - sa);
+        let mix =
+            |s: u8, d: u8| ((s as f32 * sa + d as f32 * da * (1.0 - sa)) / out_a).round() as u8;
+        *dst = Rgba([
+            mix(r, dst[0]),
+            mix(g, dst[1]),
+            mix(b, dst[2]),
+            (out_a * 255.0).round() as u8,
+        ]);
+    }
+}
@@ -0,0 +1,168 @@
+//! The OS keyring, through its command-line tools: `secret-tool` (libsecret) for the Secret
+//! Service on Linux and `security` for the login keychain on macOS. Other platforms have none
+//! here, so their secrets always go to the encrypted file.
+
+/// Service the secrets are filed under, with their name as the account.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const SERVICE: &str = "riverdeck-redux";
+
+#[cfg(target_os = "linux")]
+pub(crate) fn store(name: &str, value: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    use anyhow::Context;
+
+    let label = format!("RiverDeck-Redux: {name}");
+    let mut child = std::process::Command::new("secret-tool")
+        .args([
+            "store", "--label", &label, "service", SERVICE, "secret", name,
+        ])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("[Secrets] Failed to run secret-tool")?;
+    // The value is read from stdin until it closes.
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(value.as_bytes())?;
+    }
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        anyhow::bail!(
+            "[Secrets] secret-tool failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn lookup(name: &str) -> anyhow::Result<Option<String>> {
+    use anyhow::Context;
+
+    let out = std::process::Command::new("secret-tool")
+        .args(["lookup", "service", SERVICE, "secret", name])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("[Secrets] Failed to run secret-tool")?;
+    if !out.status.success() {
+        // Nothing found fails without a message.
+        if out.stderr.is_empty() {
+            return Ok(None);
+        }
+        anyhow::bail!(
+            "[Secrets] secret-tool failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(Some(String::from_utf8(out.stdout)?))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn delete(name: &str) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let status = std::process::Command::new("secret-tool")
+        .args(["clear", "service", SERVICE, "secret", name])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .context("[Secrets] Failed to run secret-tool")?;
+    // Also fails when there was nothing to clear.
+    tracing::debug!(%status, name, "cleared secret from the keyring");
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn store(name: &str, value: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    use anyhow::Context;
+
+    // Given through the interactive mode's stdin so the value is not on a command line.
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let command = format!(
+        "add-generic-password -U -s {SERVICE} -a {} -w {}\n",
+        quote(name),
+        quote(value)
+    );
+    let mut child = std::process::Command::new("security")
+        .arg("-i")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("[Secrets] Failed to run security")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(command.as_bytes())?;
+    }
+    let out = child.wait_with_output()?;
+    if !out.status.success() || !out.stderr.is_empty() {
+        anyhow::bail!(
+            "[Secrets] security failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// `security` exits with this when no item matches.
+#[cfg(target_os = "macos")]
+const ITEM_NOT_FOUND: i32 = 44;
+
+#[cfg(target_os = "macos")]
+pub(crate) fn lookup(name: &str) -> anyhow::Result<Option<String>> {
+    use anyhow::Context;
+
+    let out = std::process::Command::new("security")
+        .args(["find-generic-password", "-s", SERVICE, "-a", name, "-w"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("[Secrets] Failed to run security")?;
+    match out.status.code() {
+        Some(0) => {}
+        Some(ITEM_NOT_FOUND) => return Ok(None),
+        _ => anyhow::bail!(
+            "[Secrets] security failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ),
+    }
+    let mut value = String::from_utf8(out.stdout)?;
+    if value.ends_with('\n') {
+        value.pop();
+    }
+    Ok(Some(value))
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn delete(name: &str) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let out = std::process::Command::new("security")
+        .args(["delete-generic-password", "-s", SERVICE, "-a", name])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("[Secrets] Failed to run security")?;
+    match out.status.code() {
+        Some(0) | Some(ITEM_NOT_FOUND) => Ok(()),
+        _ => anyhow::bail!(
+            "[Secrets] security failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn store(_name: &str, _value: &str) -> anyhow::Result<()> {
+    anyhow::bail!("[Secrets] No keyring on this platform")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn lookup(_name: &str) -> anyhow::Result<Option<String>> {
+    Ok(None)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn delete(_name: &str) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,363 @@
+//! Secrets for action settings: tokens and passwords that should not sit in profiles.
+//!
+//! Action fields reference a secret by name as `{{secret:name}}`, which is only resolved when
+//! the action runs, so profiles (and copies of them in a sync folder) never hold the value.
+//! Values go to the OS keyring where there is one (see [`keyring`]) and otherwise to
+//! `secrets.json` in the data directory, encrypted with ChaCha20-Poly1305 under the key in
+//! `secrets.key` beside it. That key keeps the values out of copies of the file, not away from
+//! someone who can read the whole data directory.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde_json::Value;
+use storage::secrets::SecretsFile;
+use tracing::{debug, warn};
+
+mod keyring;
+
+/// What a placeholder's name starts with when it references a secret.
+pub const REFERENCE_PREFIX: &str = "secret:";
+
+const NONCE_LEN: usize = 12;
+
+/// What the names of the app settings' credentials start with.
+const SETTINGS_PREFIX: &str = "settings.";
+
+/// Where a secret is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Backend {
+    Keyring,
+    File,
+}
+
+#[derive(Default)]
+struct Inner {
+    file: SecretsFile,
+    /// Values read from the keyring so far, so each is asked for once.
+    cache: BTreeMap<String, String>,
+}
+
+/// Handle to the secrets. Cheap to clone; all clones share the same list.
+#[derive(Clone, Default)]
+pub struct SecretStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl SecretStore {
+    /// Load the list of secrets (starting empty if it can't be read).
+    pub fn load() -> Self {
+        let file = storage::secrets::load_secrets().unwrap_or_else(|e| {
+            warn!(error = %e, "failed to load secrets; starting empty");
+            SecretsFile::default()
+        });
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                file,
+                cache: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Names of the secrets and where each is kept, sorted by name. The app settings'
+    /// credentials (`settings.…`, see [`storage::settings::CredentialStore`]) are left
+    /// out.
+    pub fn names(&self) -> Vec<(String, Backend)> {
+        let inner = self.lock();
+        let keyring = inner
+            .file
+            .keyring
+            .iter()
+            .map(|n| (n.clone(), Backend::Keyring));
+        let file = inner
+            .file
+            .encrypted
+            .keys()
+            .map(|n| (n.clone(), Backend::File));
+        let mut names: Vec<_> = keyring
+            .chain(file)
+            .filter(|(n, _)| !n.starts_with(SETTINGS_PREFIX))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Store `value` as `name`, replacing what was there: in the keyring if there is one, in the
+    /// encrypted file otherwise. Blocks while the keyring is asked.
+    pub fn set(&self, name: &str, value: &str) -> anyhow::Result<Backend> {
+        check_name(name)?;
+        let backend = match keyring::store(name, value) {
+            Ok(()) => Backend::Keyring,
+            Err(e) => {
+                debug!(error = %format!("{e:#}"), "keeping the secret in the encrypted file");
+                Backend::File
+            }
+        };
+        let encrypted = match backend {
+            Backend::Keyring => None,
+            Backend::File => Some(encrypt(name, value)?),
+        };
+
+        let mut inner = self.lock();
+        let mut file = inner.file.clone();
+        match encrypted {
+            Some(encrypted) => {
+                file.encrypted.insert(name.to_string(), encrypted);
+                file.keyring.remove(name);
+            }
+            None => {
+                file.keyring.insert(name.to_string());
+                file.encrypted.remove(name);
+            }
+        }
+        storage::secrets::save_secrets(&file)?;
+        inner.file = file;
+        inner.cache.insert(name.to_string(), value.to_string());
+        Ok(backend)
+    }
+
+    /// Forget `name`, also in the keyring. Blocks while the keyring is asked.
+    pub fn remove(&self, name: &str) -> anyhow::Result<()> {
+        if self.lock().file.keyring.contains(name) {
+            keyring::delete(name)?;
+        }
+        let mut inner = self.lock();
+        let mut file = inner.file.clone();
+        file.keyring.remove(name);
+        file.encrypted.remove(name);
+        storage::secrets::save_secrets(&file)?;
+        inner.file = file;
+        inner.cache.remove(name);
+        Ok(())
+    }
+
+    /// The value of `name`. Blocks while the keyring is asked.
+    pub fn get(&self, name: &str) -> anyhow::Result<String> {
+        let (in_keyring, encrypted) = {
+            let inner = self.lock();
+            if let Some(value) = inner.cache.get(name) {
+                return Ok(value.clone());
+            }
+            let in_keyring = inner.file.keyring.contains(name);
+            (in_keyring, inner.file.encrypted.get(name).cloned())
+        };
+        let value = match (encrypted, in_keyring) {
+            (Some(encrypted), _) => decrypt(name, &encrypted)?,
+            (None, true) => keyring::lookup(name)?
+                .with_context(|| format!("[Secrets] {name} is missing from the keyring"))?,
+            (None, false) => anyhow::bail!("[Secrets] No secret named {name}"),
+        };
+        self.lock().cache.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    /// Values of the secrets `template` references, by name. Keyring lookups run off the async
+    /// executor, as the keyring may ask the user to unlock it.
+    pub async fn resolve(&self, template: &str) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut values = BTreeMap::new();
+        for name in references(template) {
+            let store = self.clone();
+            let owned = name.to_string();
+            let value = tokio::task::spawn_blocking(move || store.get(&owned)).await??;
+            values.insert(name.to_string(), value);
+        }
+        Ok(values)
+    }
+
+    /// `template` with its secret references replaced by their values; other placeholders are
+    /// kept as they are.
+    pub async fn render(&self, template: &str) -> anyhow::Result<String> {
+        let values = self.resolve(template).await?;
+        Ok(replace(template, |placeholder| {
+            reference(placeholder).and_then(|name| values.get(name).cloned())
+        }))
+    }
+
+    /// `value` with the secret references in all its strings resolved (see [`Self::render`]).
+    pub async fn render_json(&self, value: &Value) -> anyhow::Result<Value> {
+        let mut out = value.clone();
+        let mut pending = vec![&mut out];
+        while let Some(value) = pending.pop() {
+            match value {
+                Value::String(s) if !references(s).is_empty() => *s = self.render(s).await?,
+                Value::Array(items) => pending.extend(items.iter_mut()),
+                Value::Object(map) => pending.extend(map.values_mut()),
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("secret store mutex poisoned")
+    }
+}
+
+/// Keeps the app settings' credentials; set with [`storage::settings::set_credential_store`].
+impl storage::settings::CredentialStore for SecretStore {
+    fn get(&self, name: &str) -> anyhow::Result<String> {
+        SecretStore::get(self, name)
+    }
+
+    /// Leaves the keyring alone when the value is already stored.
+    fn set(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        if SecretStore::get(self, name).is_ok_and(|v| v == value) {
+            return Ok(());
+        }
+        SecretStore::set(self, name, value).map(|_| ())
+    }
+
+    fn remove(&self, name: &str) -> anyhow::Result<()> {
+        let stored = {
+            let inner = self.lock();
+            inner.file.keyring.contains(name) || inner.file.encrypted.contains_key(name)
+        };
+        if stored {
+            SecretStore::remove(self, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The secret the content of a `{{…}}` placeholder references, if it references one.
+pub fn reference(placeholder: &str) -> Option<&str> {
+    let name = placeholder.trim().strip_prefix(REFERENCE_PREFIX)?.trim();
+    Some(name).filter(|n| !n.is_empty())
+}
+
+/// Names of the secrets `template` references, without repeats.
+pub fn references(template: &str) -> Vec<&str> {
+    let mut names = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        if let Some(name) = reference(&after[..end]) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    names
+}
+
+/// Puts the secret references of `original` back into settings a plugin hands back, wherever it
+/// returned a string exactly as it got it `resolved`, so saving them keeps the references.
+pub fn conceal(original: &Value, resolved: &Value, returned: Value) -> Value {
+    match (original, resolved, returned) {
+        (Value::String(o), Value::String(r), Value::String(s)) if *r == s && o != r => {
+            Value::String(o.clone())
+        }
+        (Value::Array(o), Value::Array(r), Value::Array(s)) => Value::Array(
+            s.into_iter()
+                .enumerate()
+                .map(|(i, s)| match (o.get(i), r.get(i)) {
+                    (Some(o), Some(r)) => conceal(o, r, s),
+                    _ => s,
+                })
+                .collect(),
+        ),
+        (Value::Object(o), Value::Object(r), Value::Object(s)) => Value::Object(
+            s.into_iter()
+                .map(|(k, s)| {
+                    let s = match (o.get(&k), r.get(&k)) {
+                        (Some(o), Some(r)) => conceal(o, r, s),
+                        _ => s,
+                    };
+                    (k, s)
+                })
+                .collect(),
+        ),
+        (_, _, returned) => returned,
+    }
+}
+
+/// Replace the `{{…}}` placeholders `lookup` has a value for, keeping the others.
+fn replace(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        match lookup(&after[..end]) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn check_name(name: &str) -> anyhow::Result<()> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
+    if name.is_empty() || !name.chars().all(valid) {
+        anyhow::bail!("[Secrets] Names may only use letters, digits, `_`, `-` and `.`");
+    }
+    Ok(())
+}
+
+/// The key of the encrypted file, made on first use.
+fn file_key() -> anyhow::Result<Key> {
+    if let Some(key) = storage::secrets::load_secrets_key()? {
+        if key.len() != 32 {
+            anyhow::bail!("[Secrets] secrets.key is damaged");
+        }
+        return Ok(*Key::from_slice(&key));
+    }
+    let mut key = [0u8; 32];
+    getrandom::getrandom(&mut key)
+        .map_err(|e| anyhow::anyhow!("[Secrets] No randomness for a key: {e}"))?;
+    storage::secrets::save_secrets_key(&key)?;
+    Ok(key.into())
+}
+
+/// Encrypt `value`, bound to `name` so entries can't be swapped around in the file.
+fn encrypt(name: &str, value: &str) -> anyhow::Result<String> {
+    let cipher = ChaCha20Poly1305::new(&file_key()?);
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut nonce)
+        .map_err(|e| anyhow::anyhow!("[Secrets] No randomness for a nonce: {e}"))?;
+    let payload = Payload {
+        msg: value.as_bytes(),
+        aad: name.as_bytes(),
+    };
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| anyhow::anyhow!("[Secrets] Failed to encrypt {name}"))?;
+    let mut out = nonce.to_vec();
+    out.extend(sealed);
+    Ok(BASE64.encode(out))
+}
+
+fn decrypt(name: &str, encrypted: &str) -> anyhow::Result<String> {
+    let bytes = BASE64
+        .decode(encrypted)
+        .with_context(|| format!("[Secrets] {name} is damaged"))?;
+    if bytes.len() < NONCE_LEN {
+        anyhow::bail!("[Secrets] {name} is damaged");
+    }
+    let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&file_key()?);
+    let payload = Payload {
+        msg: sealed,
+        aad: name.as_bytes(),
+    };
+    let value = cipher
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| {
+            anyhow::anyhow!("[Secrets] Cannot decrypt {name}; was secrets.key replaced?")
+        })?;
+    String::from_utf8(value).with_context(|| format!("[Secrets] {name} is damaged"))
+}
@@ -0,0 +1,575 @@
+//! Spotify client over the Web API: sign-in, playback polling and player commands.
+//!
+//! Spotify's accounts service offers no device-authorization grant, so sign-in uses the
+//! Authorization Code flow with PKCE: the browser shows Spotify's consent page, which redirects
+//! to a short-lived listener on [`REDIRECT_URI`] (add it to the app on the developer dashboard).
+//! The refresh token is kept in the settings; playback is polled while signed in.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Redirect URI of the sign-in flow; must be registered for the Spotify app.
+pub const REDIRECT_URI: &str = "http://127.0.0.1:8898/callback";
+const LISTEN_ADDR: &str = "127.0.0.1:8898";
+const AUTHORIZE_URL: &str = "https://accounts.spotify.com/authorize";
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const API_URL: &str = "https://api.spotify.com/v1";
+const SCOPES: &str =
+    "user-read-playback-state user-modify-playback-state user-library-read user-library-modify";
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Access tokens are refreshed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// The track playing (or paused) on the account's active device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playback {
+    pub track_id: String,
+    pub title: String,
+    /// Artist names, comma separated.
+    pub artists: String,
+    /// Album cover, downloaded to the cache directory.
+    pub album_art: Option<PathBuf>,
+    pub progress_ms: u64,
+    pub duration_ms: u64,
+    pub is_playing: bool,
+}
+
+impl Playback {
+    /// Position and length, e.g. `1:23/3:45`.
+    pub fn progress(&self) -> String {
+        format!(
+            "{}/{}",
+            minutes(self.progress_ms),
+            minutes(self.duration_ms)
+        )
+    }
+}
+
+fn minutes(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Sign-in changes and playback updates, for UIs.
+#[derive(Debug, Clone)]
+pub enum Event {
+    SignedIn,
+    /// Signed out; with the reason when the sign-in was revoked rather than ended by the user.
+    SignedOut(Option<String>),
+    /// `None` when nothing is playing on any device.
+    Playback(Option<Playback>),
+}
+
+/// The refresh token was rejected; the user has to sign in again.
+#[derive(Debug)]
+struct Revoked(String);
+
+impl fmt::Display for Revoked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Spotify sign-in expired or was revoked: {}", self.0)
+    }
+}
+
+impl std::error::Error for Revoked {}
+
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    /// Set while signed in.
+    poll: Option<tokio::task::JoinHandle<()>>,
+    playback: Option<Playback>,
+    /// Cover URL of the current album and its downloaded file.
+    art: Option<(String, PathBuf)>,
+    subscribers: Vec<mpsc::UnboundedSender<Event>>,
+}
+
+impl State {
+    fn publish(&mut self, event: Event) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    fn set_playback(&mut self, playback: Option<Playback>) {
+        if self.playback != playback {
+            self.playback = playback.clone();
+            self.publish(Event::Playback(playback));
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Also serializes refreshes, so a rotated refresh token is never used twice.
+    token: tokio::sync::Mutex<Option<AccessToken>>,
+    http: reqwest::Client,
+}
+
+/// Handle to the client. Cheap to clone; all clones share one sign-in.
+#[derive(Clone)]
+pub struct Spotify {
+    shared: Arc<Shared>,
+}
+
+impl Default for Spotify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spotify {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent("RiverDeck-Redux/0.1 (Spotify)")
+            .build()
+            .unwrap_or_default();
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::default()),
+                token: tokio::sync::Mutex::new(None),
+                http,
+            }),
+        }
+    }
+
+    /// Receive sign-in changes and playback updates from now on.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<Event> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        lock(&self.shared.state).subscribers.push(tx);
+        rx
+    }
+
+    pub fn is_signed_in(&self) -> bool {
+        lock(&self.shared.state).poll.is_some()
+    }
+
+    pub fn playback(&self) -> Option<Playback> {
+        lock(&self.shared.state).playback.clone()
+    }
+
+    /// Start polling if an earlier sign-in is stored in the settings.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn resume(&self) -> anyhow::Result<()> {
+        let settings = storage::settings::load_settings()?.spotify;
+        if !settings.client_id.is_empty() && !settings.refresh_token.is_empty() {
+            start(&self.shared);
+        }
+        Ok(())
+    }
+
+    /// Sign in through the browser (see the crate docs), store the sign-in and start polling.
+    pub async fn sign_in(&self, client_id: &str) -> anyhow::Result<()> {
+        let client_id = client_id.trim();
+        if client_id.is_empty() {
+            anyhow::bail!("a client ID is required");
+        }
+        let verifier = random_hex(32)?;
+        let request_state = random_hex(16)?;
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let listener = TcpListener::bind(LISTEN_ADDR)
+            .await
+            .with_context(|| format!("failed to listen on {LISTEN_ADDR}"))?;
+
+        let url = reqwest::Url::parse_with_params(
+            AUTHORIZE_URL,
+            [
+                ("client_id", client_id),
+                ("response_type", "code"),
+                ("redirect_uri", REDIRECT_URI),
+                ("code_challenge_method", "S256"),
+                ("code_challenge", &challenge),
+                ("state", &request_state),
+                ("scope", SCOPES),
+            ],
+        )?;
+        opener::open_browser(url.as_str()).context("failed to open the browser")?;
+        let code = tokio::time::timeout(SIGN_IN_TIMEOUT, receive_code(&listener, &request_state))
+            .await
+            .context("sign-in timed out")??;
+
+        let res = self
+            .shared
+            .http
+            .post(TOKEN_URL)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", REDIRECT_URI),
+                ("client_id", client_id),
+                ("code_verifier", &verifier),
+            ])
+            .send()
+            .await?;
+        let tokens = token_response(res).await?;
+        let refresh_token = tokens["refresh_token"]
+            .as_str()
+            .context("no refresh token in the response")?;
+        storage::settings::update_settings(|s| {
+            s.spotify.client_id = client_id.to_string();
+            s.spotify.refresh_token = refresh_token.to_string();
+        })?;
+        *self.shared.token.lock().await = Some(access_token_from(&tokens)?);
+        info!("signed in to Spotify");
+        start(&self.shared);
+        Ok(())
+    }
+
+    /// Forget the stored sign-in and stop polling.
+    pub async fn sign_out(&self) -> anyhow::Result<()> {
+        storage::settings::update_settings(|s| s.spotify.refresh_token.clear())?;
+        *self.shared.token.lock().await = None;
+        stop(&self.shared, None);
+        Ok(())
+    }
+
+    /// Pause when playing, resume otherwise.
+    pub async fn play_pause(&self) -> anyhow::Result<()> {
+        let playing = self.playback().is_some_and(|p| p.is_playing);
+        let path = if playing {
+            "/me/player/pause"
+        } else {
+            "/me/player/play"
+        };
+        self.command(Method::PUT, path).await?;
+        // Show the new state right away instead of at the next poll.
+        let mut state = lock(&self.shared.state);
+        if let Some(mut playback) = state.playback.clone() {
+            playback.is_playing = !playing;
+            state.set_playback(Some(playback));
+        }
+        Ok(())
+    }
+
+    pub async fn next(&self) -> anyhow::Result<()> {
+        self.command(Method::POST, "/me/player/next").await
+    }
+
+    pub async fn previous(&self) -> anyhow::Result<()> {
+        self.command(Method::POST, "/me/player/previous").await
+    }
+
+    /// Save the current track to Liked Songs, or remove it when already saved.
+    pub async fn toggle_like(&self) -> anyhow::Result<()> {
+        let Some(track) = self.playback().map(|p| p.track_id) else {
+            anyhow::bail!("[Spotify] Nothing is playing");
+        };
+        let saved = api(
+            &self.shared,
+            Method::GET,
+            &format!("/me/tracks/contains?ids={track}"),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("[Spotify] {e:#}"))?;
+        let saved = saved.is_some_and(|v| v[0].as_bool() == Some(true));
+        let method = if saved { Method::DELETE } else { Method::PUT };
+        self.command(method, &format!("/me/tracks?ids={track}"))
+            .await
+    }
+
+    async fn command(&self, method: Method, path: &str) -> anyhow::Result<()> {
+        if !self.is_signed_in() {
+            anyhow::bail!("[Spotify] Not signed in");
+        }
+        api(&self.shared, method, path)
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("[Spotify] {e:#}"))
+    }
+}
+
+fn lock(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    state.lock().expect("spotify mutex poisoned")
+}
+
+fn start(shared: &Arc<Shared>) {
+    let mut state = lock(&shared.state);
+    if let Some(task) = state.poll.take() {
+        task.abort();
+    }
+    state.poll = Some(tokio::spawn(poll(Arc::downgrade(shared))));
+    state.publish(Event::SignedIn);
+}
+
+fn stop(shared: &Shared, reason: Option<String>) {
+    let mut state = lock(&shared.state);
+    if let Some(task) = state.poll.take() {
+        task.abort();
+    }
+    state.set_playback(None);
+    state.publish(Event::SignedOut(reason));
+}
+
+/// Poll playback until signed out or the client is dropped.
+async fn poll(shared: Weak<Shared>) {
+    loop {
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        match fetch_playback(&shared).await {
+            Ok(playback) => lock(&shared.state).set_playback(playback),
+            Err(e) if e.downcast_ref::<Revoked>().is_some() => {
+                warn!(error = %e, "Spotify sign-in is no longer valid");
+                if let Err(e) =
+                    storage::settings::update_settings(|s| s.spotify.refresh_token.clear())
+                {
+                    warn!(error = %e, "failed to clear the Spotify sign-in");
+                }
+                stop(&shared, Some(e.to_string()));
+                return;
+            }
+            Err(e) => debug!(error = %format!("{e:#}"), "failed to poll Spotify playback"),
+        }
+        drop(shared);
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn fetch_playback(shared: &Shared) -> anyhow::Result<Option<Playback>> {
+    let Some(player) = api(shared, Method::GET, "/me/player").await? else {
+        return Ok(None);
+    };
+    let item = &player["item"];
+    // Ads and local files have no track id.
+    let Some(track_id) = item["id"].as_str() else {
+        return Ok(None);
+    };
+    let artists: Vec<&str> = item["artists"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|a| a["name"].as_str())
+        .collect();
+    // Covers come largest first (640, 300, 64 px); the middle one suits a key best.
+    let images = item["album"]["images"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let cover = images
+        .iter()
+        .rev()
+        .find(|i| i["width"].as_u64().unwrap_or(0) >= 100)
+        .or(images.first())
+        .and_then(|i| i["url"].as_str());
+    let album_art = match cover {
+        Some(url) => album_art(shared, url).await,
+        None => None,
+    };
+    Ok(Some(Playback {
+        track_id: track_id.to_string(),
+        title: item["name"].as_str().unwrap_or_default().to_string(),
+        artists: artists.join(", "),
+        album_art,
+        progress_ms: player["progress_ms"].as_u64().unwrap_or(0),
+        duration_ms: item["duration_ms"].as_u64().unwrap_or(0),
+        is_playing: player["is_playing"].as_bool().unwrap_or(false),
+    }))
+}
+
+/// The cover at `url` as a local file, downloaded once per album.
+async fn album_art(shared: &Shared, url: &str) -> Option<PathBuf> {
+    if let Some((cached, path)) = &lock(&shared.state).art {
+        if cached == url {
+            return Some(path.clone());
+        }
+    }
+    match download_art(shared, url).await {
+        Ok(path) => {
+            lock(&shared.state).art = Some((url.to_string(), path.clone()));
+            Some(path)
+        }
+        Err(e) => {
+            warn!(url, error = %format!("{e:#}"), "failed to download album art");
+            None
+        }
+    }
+}
+
+async fn download_art(shared: &Shared, url: &str) -> anyhow::Result<PathBuf> {
+    let name = url
+        .rsplit('/')
+        .next()
+        .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_alphanumeric()))
+        .context("unexpected cover URL")?;
+    let dir = storage::paths::cache_dir()?.join("spotify");
+    let path = dir.join(format!("{name}.jpg"));
+    if !path.exists() {
+        let bytes = shared
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&path, &bytes)?;
+    }
+    Ok(path)
+}
+
+/// Call the Web API; `None` for empty responses (e.g. nothing playing).
+async fn api(shared: &Shared, method: Method, path: &str) -> anyhow::Result<Option<Value>> {
+    let token = access_token(shared).await?;
+    let mut req = shared
+        .http
+        .request(method.clone(), format!("{API_URL}{path}"))
+        .bearer_auth(token);
+    if method != Method::GET {
+        // Spotify rejects bodiless PUT/POST requests without a Content-Length.
+        req = req.body("");
+    }
+    let res = req.send().await?;
+    let status = res.status();
+    let body = res.bytes().await?;
+    if !status.is_success() {
+        let message = serde_json::from_slice::<Value>(&body)
+            .ok()
+            .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        anyhow::bail!("{message}");
+    }
+    if status == StatusCode::NO_CONTENT || body.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// A valid access token, refreshed with the stored refresh token when needed.
+async fn access_token(shared: &Shared) -> anyhow::Result<String> {
+    let mut token = shared.token.lock().await;
+    if let Some(t) = token
+        .as_ref()
+        .filter(|t| t.expires_at > Instant::now() + EXPIRY_MARGIN)
+    {
+        return Ok(t.token.clone());
+    }
+
+    let settings = storage::settings::load_settings()?.spotify;
+    if settings.refresh_token.is_empty() {
+        anyhow::bail!("not signed in");
+    }
+    let res = shared
+        .http
+        .post(TOKEN_URL)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &settings.refresh_token),
+            ("client_id", &settings.client_id),
+        ])
+        .send()
+        .await?;
+    let tokens = token_response(res).await?;
+    // Spotify may rotate the refresh token; the old one stops working.
+    if let Some(rotated) = tokens["refresh_token"]
+        .as_str()
+        .filter(|t| *t != settings.refresh_token)
+    {
+        storage::settings::update_settings(|s| s.spotify.refresh_token = rotated.to_string())?;
+    }
+    let fresh = access_token_from(&tokens)?;
+    let value = fresh.token.clone();
+    *token = Some(fresh);
+    Ok(value)
+}
+
+async fn token_response(res: reqwest::Response) -> anyhow::Result<Value> {
+    let status = res.status();
+    let body: Value = res.json().await.context("invalid token response")?;
+    if status.is_success() {
+        return Ok(body);
+    }
+    let error = body["error"].as_str().unwrap_or("unknown error");
+    let description = body["error_description"].as_str().unwrap_or(error);
+    if error == "invalid_grant" {
+        return Err(Revoked(description.to_string()).into());
+    }
+    anyhow::bail!("token request failed: {description}")
+}
+
+fn access_token_from(tokens: &Value) -> anyhow::Result<AccessToken> {
+    let token = tokens["access_token"]
+        .as_str()
+        .context("no access token in the response")?;
+    let expires_in = tokens["expires_in"].as_u64().unwrap_or(3600);
+    Ok(AccessToken {
+        token: token.to_string(),
+        expires_at: Instant::now() + Duration::from_secs(expires_in),
+    })
+}
+
+/// Wait for the browser's redirect and return the authorization code.
+async fn receive_code(listener: &TcpListener, request_state: &str) -> anyhow::Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buf = vec![0u8; 4096];
+        let n = stream.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let target = request
+            .lines()
+            .next()
+            .and_then(|line| line.split(' ').nth(1))
+            .unwrap_or_default();
+        // Browsers also ask for a favicon and the like.
+        let Some(query) = target.strip_prefix("/callback?") else {
+            respond(&mut stream, "404 Not Found", "Not found").await;
+            continue;
+        };
+        let param = |name: &str| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        };
+        let result = if param("state") != Some(request_state) {
+            Err(anyhow::anyhow!(
+                "sign-in response does not match the request"
+            ))
+        } else if let Some(error) = param("error") {
+            Err(anyhow::anyhow!("sign-in was refused: {error}"))
+        } else {
+            param("code")
+                .map(str::to_string)
+                .context("sign-in response has no code")
+        };
+        let message = match result {
+            Ok(_) => "Signed in to Spotify. You can close this window.",
+            Err(_) => "Spotify sign-in failed; see RiverDeck-Redux for details.",
+        };
+        respond(&mut stream, "200 OK", message).await;
+        return result;
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!(error = %e, "failed to answer the sign-in redirect");
+    }
+}
+
+/// `bytes` random bytes as hex (PKCE verifiers allow `[0-9a-f]`, 43 to 128 characters).
+fn random_hex(bytes: usize) -> anyhow::Result<String> {
+    let mut buf = vec![0u8; bytes];
+    getrandom::getrandom(&mut buf).map_err(|e| anyhow::anyhow!("no randomness: {e}"))?;
+    Ok(buf.iter().map(|b| format!("{b:02x}")).collect())
+}
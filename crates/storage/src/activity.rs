@@ -0,0 +1,57 @@
+//! History of dispatched actions, stored as `activity.json` in the data directory.
+//!
+//! The log is a ring buffer: only the latest [`MAX_ENTRIES`] are kept, oldest first.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+/// Entries kept; older ones are dropped as new ones come in.
+pub const MAX_ENTRIES: usize = 500;
+
+/// One run of a binding, from whichever control, schedule or webhook fired it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    /// When the binding fired, in milliseconds since the Unix epoch.
+    pub at_ms: u64,
+    /// What fired it, e.g. `Key 3` or `Schedule 2`.
+    pub control: String,
+    /// How it was used, e.g. `press` or `rotate +1`.
+    pub event: String,
+    /// Short description of the binding.
+    pub binding: String,
+    /// Message of the first failed step; `None` when every step succeeded.
+    #[serde(default)]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Run from the action editor's "Test" button rather than by the control itself.
+    #[serde(default)]
+    pub test: bool,
+    /// The actions were only logged (dry run).
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+pub fn activity_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("activity.json"))
+}
+
+/// Load the log, oldest entry first; a missing file yields none.
+pub fn load_activity() -> anyhow::Result<VecDeque<ActivityEntry>> {
+    json::read_or_default(&activity_path()?)
+}
+
+pub fn save_activity(entries: &VecDeque<ActivityEntry>) -> anyhow::Result<()> {
+    json::write_atomic(&activity_path()?, entries)
+}
+
+/// Append `entry`, dropping the oldest entries beyond [`MAX_ENTRIES`].
+pub fn push_entry(entries: &mut VecDeque<ActivityEntry>, entry: ActivityEntry) {
+    entries.push_back(entry);
+    while entries.len() > MAX_ENTRIES {
+        entries.pop_front();
+    }
+}
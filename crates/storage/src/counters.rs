@@ -0,0 +1,62 @@
+//! Persisted values of counter keys, stored as `counters.json` in the data directory.
+//!
+//! Values are keyed by profile and key index so every counter key keeps its own tally across
+//! restarts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use app_core::ids::ProfileId;
+
+use crate::{json, paths};
+
+/// Profile id -> key index -> value.
+type CounterFile = HashMap<u64, BTreeMap<u8, i64>>;
+
+/// Serializes read-modify-write cycles within the process (keys may fire concurrently).
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub fn counters_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("counters.json"))
+}
+
+/// Counter values of one profile; keys that never counted are absent (value 0).
+pub fn load_counters(profile: ProfileId) -> anyhow::Result<BTreeMap<u8, i64>> {
+    let _guard = LOCK.lock().expect("counters mutex poisoned");
+    Ok(read_file()?.remove(&profile.0).unwrap_or_default())
+}
+
+/// Add `delta` to a key's counter and return the new value.
+pub fn add_to_counter(profile: ProfileId, key: u8, delta: i64) -> anyhow::Result<i64> {
+    let _guard = LOCK.lock().expect("counters mutex poisoned");
+    let mut file = read_file()?;
+    let value = file.entry(profile.0).or_default().entry(key).or_default();
+    *value = value.saturating_add(delta);
+    let value = *value;
+    write_file(&file)?;
+    Ok(value)
+}
+
+pub fn reset_counter(profile: ProfileId, key: u8) -> anyhow::Result<()> {
+    let _guard = LOCK.lock().expect("counters mutex poisoned");
+    let mut file = read_file()?;
+    let Some(keys) = file.get_mut(&profile.0) else {
+        return Ok(());
+    };
+    if keys.remove(&key).is_none() {
+        return Ok(());
+    }
+    if keys.is_empty() {
+        file.remove(&profile.0);
+    }
+    write_file(&file)
+}
+
+fn read_file() -> anyhow::Result<CounterFile> {
+    json::read_or_default(&counters_path()?)
+}
+
+fn write_file(file: &CounterFile) -> anyhow::Result<()> {
+    json::write_atomic(&counters_path()?, file)
+}
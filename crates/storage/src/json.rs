@@ -0,0 +1,59 @@
+//! Shared helpers for small JSON files in the data directory.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Read `path`; a missing file yields `T::default()`.
+pub(crate) fn read_or_default<T: DeserializeOwned + Default>(path: &Path) -> anyhow::Result<T> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write `value` as pretty JSON with [`write_bytes_atomic`].
+pub(crate) fn write_atomic<T: Serialize + ?Sized>(path: &Path, value: &T) -> anyhow::Result<()> {
+    let mut json = serde_json::to_vec_pretty(value)?;
+    json.push(b'\n');
+    write_bytes_atomic(path, &json)
+}
+
+/// Replace `path` with `bytes` so that readers, and the file after a crash or power loss, are
+/// always either the old or the new content: write a temp file, fsync it, rename it over `path`
+/// and fsync the directory.
+pub(crate) fn write_bytes_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    {
+        let mut f = fs::File::create(&tmp_path)?;
+        f.write_all(bytes)?;
+        f.sync_all()?;
+    }
+
+    // Replaces an existing file on every platform (`MoveFileEx` on Windows).
+    fs::rename(&tmp_path, path)?;
+    sync_parent(path);
+    Ok(())
+}
+
+/// Persist the directory entry of a just-renamed file (Unix only; Windows has no equivalent).
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::File::open(parent).and_then(|d| d.sync_all()) {
+            tracing::debug!(dir = %parent.display(), error = %e, "failed to sync directory");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
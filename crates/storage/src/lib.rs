@@ -1,4 +1,22 @@
 //! Storage helpers (paths, atomic writes, config formats, migrations).
 
+pub mod activity;
+pub mod counters;
+mod json;
+pub mod midi_triggers;
+pub mod osc_triggers;
 pub mod paths;
+pub mod pomodoro;
+pub mod process_watchers;
 pub mod profiles;
+pub mod schedules;
+pub mod secrets;
+pub mod settings;
+pub mod surfaces;
+pub mod sync;
+pub mod themes;
+pub mod typed_triggers;
+pub mod usage;
+pub mod validate;
+pub mod variables;
+pub mod webhooks;
@@ -0,0 +1,64 @@
+//! MIDI triggers, stored as `midi_triggers.json` in the data directory.
+//!
+//! Each trigger maps a note or controller of a MIDI controller to an action, so its pads and
+//! buttons can run what keys run. Listening to the input port is opt-in (see
+//! [`crate::settings::MidiSettings`]).
+
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidiTrigger {
+    /// Unique within the file; identifies the trigger in the UI and in invocations.
+    pub id: u64,
+    pub input: MidiInput,
+    /// Channel 1 to 16 the message must come on; `None` takes any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+    /// Note or controller number, 0 to 127.
+    pub number: u8,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub action: ActionBinding,
+}
+
+/// The kind of message a [`MidiTrigger`] fires on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiInput {
+    /// A note played (note on with a velocity above 0), e.g. a pad hit.
+    Note,
+    /// A controller set above 0, e.g. a button pressed; buttons that send 127 and 0 fire once
+    /// per press.
+    ControlChange,
+}
+
+impl std::fmt::Display for MidiInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Note => "Note",
+            Self::ControlChange => "Control change",
+        })
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn midi_triggers_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("midi_triggers.json"))
+}
+
+/// Load all MIDI triggers; a missing file yields none.
+pub fn load_midi_triggers() -> anyhow::Result<Vec<MidiTrigger>> {
+    json::read_or_default(&midi_triggers_path()?)
+}
+
+pub fn save_midi_triggers(triggers: &[MidiTrigger]) -> anyhow::Result<()> {
+    json::write_atomic(&midi_triggers_path()?, triggers)
+}
@@ -0,0 +1,70 @@
+//! OSC triggers, stored as `osc_triggers.json` in the data directory.
+//!
+//! Each trigger maps an OSC address to an action, so a lighting desk, mixer or show-control
+//! app can run what keys run. Listening for messages is opt-in (see
+//! [`crate::settings::OscSettings`]).
+
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OscTrigger {
+    /// Unique within the file; identifies the trigger in the UI and in invocations.
+    pub id: u64,
+    /// Address the message must be sent to, e.g. `/deck/scene`. `*` matches any part of a
+    /// segment and `?` one character, as in OSC address patterns.
+    pub address: String,
+    /// Variable set to the message's first argument before the action runs; empty sets none.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub variable: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub action: ActionBinding,
+}
+
+impl OscTrigger {
+    /// Whether a message sent to `address` fires this trigger (enabled or not).
+    pub fn matches(&self, address: &str) -> bool {
+        let pattern: Vec<&str> = self.address.trim().split('/').collect();
+        let segments: Vec<&str> = address.split('/').collect();
+        pattern.len() == segments.len()
+            && pattern
+                .iter()
+                .zip(&segments)
+                .all(|(p, s)| glob(p.as_bytes(), s.as_bytes()))
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters and `?` one.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.split_first(), text.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            glob(rest, text) || text.split_first().is_some_and(|(_, t)| glob(pattern, t))
+        }
+        (Some((b'?', rest)), Some((_, t))) => glob(rest, t),
+        (Some((p, rest)), Some((c, t))) => p == c && glob(rest, t),
+        _ => false,
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn osc_triggers_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("osc_triggers.json"))
+}
+
+/// Load all OSC triggers; a missing file yields none.
+pub fn load_osc_triggers() -> anyhow::Result<Vec<OscTrigger>> {
+    json::read_or_default(&osc_triggers_path()?)
+}
+
+pub fn save_osc_triggers(triggers: &[OscTrigger]) -> anyhow::Result<()> {
+    json::write_atomic(&osc_triggers_path()?, triggers)
+}
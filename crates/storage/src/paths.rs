@@ -1,13 +1,120 @@
-use std::path::PathBuf;
+//! Where the app keeps its files.
+//!
+//! Everything (profiles, plugins, settings, caches) lives under one root, chosen once per
+//! process, in order of precedence:
+//!
+//! 1. a directory given on the command line (`--config-dir <dir>`, see [`init`]);
+//! 2. the `RIVERDECK_CONFIG_DIR` environment variable;
+//! 3. portable mode: a `data` folder next to the executable, when it exists or `--portable`
+//!    was given;
+//! 4. the platform's per-user directories.
+//!
+//! With 1-3 the cache lives in a `cache` folder inside the root.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use directories::ProjectDirs;
 
+/// Environment variable naming the data directory.
+pub const CONFIG_DIR_ENV: &str = "RIVERDECK_CONFIG_DIR";
+
+/// Folder next to the executable that turns on portable mode.
+const PORTABLE_DIR: &str = "data";
+
+/// Where the app's files are kept (see the module docs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataLocation {
+    /// Given with `--config-dir` or `RIVERDECK_CONFIG_DIR`.
+    Custom(PathBuf),
+    /// The `data` folder next to the executable.
+    Portable(PathBuf),
+    /// The platform's per-user data and cache directories.
+    Platform,
+}
+
+static LOCATION: OnceLock<DataLocation> = OnceLock::new();
+
+/// Choose the data location from the command line, falling back to the environment and
+/// portable mode. Binaries call this at startup, before any file is read; later calls, and
+/// calls after a path was already looked up, fail.
+pub fn init(config_dir: Option<PathBuf>, portable: bool) -> anyhow::Result<&'static DataLocation> {
+    let location = resolve(config_dir, portable)?;
+    LOCATION
+        .set(location)
+        .map_err(|_| anyhow::anyhow!("the data location was already chosen"))?;
+    let location = data_location();
+    tracing::info!(?location, "data location");
+    Ok(location)
+}
+
+/// The data location in effect; resolved from the environment on first use if [`init`] was
+/// not called.
+pub fn data_location() -> &'static DataLocation {
+    LOCATION.get_or_init(|| {
+        resolve(None, false).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to resolve the data location; using the default");
+            DataLocation::Platform
+        })
+    })
+}
+
+fn resolve(config_dir: Option<PathBuf>, portable: bool) -> anyhow::Result<DataLocation> {
+    let custom = config_dir.or_else(|| {
+        std::env::var_os(CONFIG_DIR_ENV)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    });
+    if let Some(dir) = custom {
+        return Ok(DataLocation::Custom(absolute(dir)?));
+    }
+
+    let exe = std::env::current_exe()?;
+    let Some(exe_dir) = exe.parent() else {
+        return Ok(DataLocation::Platform);
+    };
+    let portable_dir = exe_dir.join(PORTABLE_DIR);
+    if portable {
+        std::fs::create_dir_all(&portable_dir)?;
+    }
+    if portable_dir.is_dir() {
+        return Ok(DataLocation::Portable(portable_dir));
+    }
+    Ok(DataLocation::Platform)
+}
+
+fn absolute(dir: PathBuf) -> anyhow::Result<PathBuf> {
+    if dir.is_absolute() {
+        Ok(dir)
+    } else {
+        Ok(std::env::current_dir()?.join(dir))
+    }
+}
+
 pub fn project_dirs() -> anyhow::Result<ProjectDirs> {
     ProjectDirs::from("io", "github", "riverdeck-redux")
         .ok_or_else(|| anyhow::anyhow!("unable to determine platform data directories"))
 }
 
+fn custom_root() -> Option<&'static Path> {
+    match data_location() {
+        DataLocation::Custom(dir) | DataLocation::Portable(dir) => Some(dir),
+        DataLocation::Platform => None,
+    }
+}
+
 /// Directory for user-writable application data (profiles, logs, caches).
 pub fn data_dir() -> anyhow::Result<PathBuf> {
-    Ok(project_dirs()?.data_dir().to_path_buf())
+    match custom_root() {
+        Some(root) => Ok(root.to_path_buf()),
+        None => Ok(project_dirs()?.data_dir().to_path_buf()),
+    }
+}
+
+/// Directory for disposable cached data (marketplace responses, downloaded images).
+pub fn cache_dir() -> anyhow::Result<PathBuf> {
+    match custom_root() {
+        Some(root) => Ok(root.join("cache")),
+        None => Ok(project_dirs()?.cache_dir().to_path_buf()),
+    }
 }
@@ -0,0 +1,195 @@
+//! State of Pomodoro timer keys, stored as `pomodoro.json` in the data directory.
+//!
+//! Timers are keyed by profile and key index, like counters. A running phase is stored by the
+//! time it ends, so the timer keeps counting down while the app is closed.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use actions::BuiltinAction;
+use app_core::ids::ProfileId;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+/// Profile id -> key index -> timer.
+type PomodoroFile = HashMap<u64, BTreeMap<u8, PomodoroTimer>>;
+
+/// Serializes read-modify-write cycles within the process.
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PomodoroPhase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn label(self) -> &'static str {
+        match self {
+            PomodoroPhase::Work => "Work",
+            PomodoroPhase::ShortBreak => "Break",
+            PomodoroPhase::LongBreak => "Long break",
+        }
+    }
+}
+
+/// Phase lengths of a timer, as set by its binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PomodoroLengths {
+    pub work: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    /// Every this many work phases are followed by a long break; never when 0.
+    pub long_break_every: u32,
+}
+
+impl PomodoroLengths {
+    /// The lengths of a `pomodoro` binding; `None` for other actions.
+    pub fn of(action: &BuiltinAction) -> Option<Self> {
+        let BuiltinAction::Pomodoro {
+            work_min,
+            break_min,
+            long_break_every,
+            long_break_min,
+            ..
+        } = action
+        else {
+            return None;
+        };
+        // A phase of 0 minutes would end as soon as it starts.
+        let minutes = |m: &u32| Duration::from_secs(u64::from((*m).max(1)) * 60);
+        Some(Self {
+            work: minutes(work_min),
+            short_break: minutes(break_min),
+            long_break: minutes(long_break_min),
+            long_break_every: *long_break_every,
+        })
+    }
+
+    pub fn of_phase(&self, phase: PomodoroPhase) -> Duration {
+        match phase {
+            PomodoroPhase::Work => self.work,
+            PomodoroPhase::ShortBreak => self.short_break,
+            PomodoroPhase::LongBreak => self.long_break,
+        }
+    }
+}
+
+/// Where a timer is in its cycle. The default is a work phase that has not started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PomodoroTimer {
+    pub phase: PomodoroPhase,
+    /// Work phases finished since the last long break.
+    #[serde(default)]
+    pub completed: u32,
+    /// End of the running phase, in milliseconds since the Unix epoch; unset while stopped.
+    #[serde(default)]
+    pub ends_at_ms: Option<u64>,
+    /// Time left of a paused phase, in milliseconds; unset when the phase has not started.
+    #[serde(default)]
+    pub remaining_ms: Option<u64>,
+}
+
+impl PomodoroTimer {
+    pub fn is_running(&self) -> bool {
+        self.ends_at_ms.is_some()
+    }
+
+    /// Time left of the current phase at `now`.
+    pub fn remaining(&self, lengths: &PomodoroLengths, now: SystemTime) -> Duration {
+        match (self.ends_at_ms, self.remaining_ms) {
+            (Some(ends_at), _) => Duration::from_millis(ends_at.saturating_sub(millis(now))),
+            (None, Some(remaining)) => Duration::from_millis(remaining),
+            (None, None) => lengths.of_phase(self.phase),
+        }
+    }
+
+    /// Start or resume the current phase when stopped, pause it when running.
+    pub fn toggle(&mut self, lengths: &PomodoroLengths, now: SystemTime) {
+        if self.is_running() {
+            self.remaining_ms = Some(millis_of(self.remaining(lengths, now)));
+            self.ends_at_ms = None;
+        } else {
+            let remaining = millis_of(self.remaining(lengths, now));
+            self.ends_at_ms = Some(millis(now).saturating_add(remaining));
+            self.remaining_ms = None;
+        }
+    }
+
+    /// Move on to the next phase when the running one has ended by `now`, and return the phase
+    /// that ended. The next phase waits for a press to start.
+    pub fn advance(&mut self, lengths: &PomodoroLengths, now: SystemTime) -> Option<PomodoroPhase> {
+        self.ends_at_ms.filter(|&ends_at| ends_at <= millis(now))?;
+        let ended = self.phase;
+        self.phase = match ended {
+            PomodoroPhase::Work => {
+                self.completed += 1;
+                if lengths.long_break_every > 0 && self.completed >= lengths.long_break_every {
+                    self.completed = 0;
+                    PomodoroPhase::LongBreak
+                } else {
+                    PomodoroPhase::ShortBreak
+                }
+            }
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => PomodoroPhase::Work,
+        };
+        self.ends_at_ms = None;
+        self.remaining_ms = None;
+        Some(ended)
+    }
+}
+
+fn millis(t: SystemTime) -> u64 {
+    millis_of(t.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default())
+}
+
+fn millis_of(d: Duration) -> u64 {
+    u64::try_from(d.as_millis()).unwrap_or(u64::MAX)
+}
+
+pub fn pomodoro_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("pomodoro.json"))
+}
+
+/// Timers of one profile; keys whose timer never ran are absent (a work phase not started).
+pub fn load_timers(profile: ProfileId) -> anyhow::Result<BTreeMap<u8, PomodoroTimer>> {
+    let _guard = LOCK.lock().expect("pomodoro mutex poisoned");
+    Ok(read_file()?.remove(&profile.0).unwrap_or_default())
+}
+
+pub fn save_timer(profile: ProfileId, key: u8, timer: PomodoroTimer) -> anyhow::Result<()> {
+    let _guard = LOCK.lock().expect("pomodoro mutex poisoned");
+    let mut file = read_file()?;
+    file.entry(profile.0).or_default().insert(key, timer);
+    write_file(&file)
+}
+
+/// Put a key's timer back to a work phase that has not started.
+pub fn reset_timer(profile: ProfileId, key: u8) -> anyhow::Result<()> {
+    let _guard = LOCK.lock().expect("pomodoro mutex poisoned");
+    let mut file = read_file()?;
+    let Some(keys) = file.get_mut(&profile.0) else {
+        return Ok(());
+    };
+    if keys.remove(&key).is_none() {
+        return Ok(());
+    }
+    if keys.is_empty() {
+        file.remove(&profile.0);
+    }
+    write_file(&file)
+}
+
+fn read_file() -> anyhow::Result<PomodoroFile> {
+    json::read_or_default(&pomodoro_path()?)
+}
+
+fn write_file(file: &PomodoroFile) -> anyhow::Result<()> {
+    json::write_atomic(&pomodoro_path()?, file)
+}
@@ -0,0 +1,59 @@
+//! Process watchers, stored as `process_watchers.json` in the data directory.
+//!
+//! Each watcher runs an action when a program starts or exits, e.g. switching to an OBS profile
+//! while `obs` runs. Like schedules they are global, so they can switch profiles themselves.
+
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessWatcher {
+    /// Unique within the file; identifies the watcher in the UI and in invocations.
+    pub id: u64,
+    /// Process name as in a `process_running` condition, e.g. `obs` (`.exe` is optional).
+    pub process: String,
+    /// Fire when the process starts, or when it exits.
+    pub on: ProcessTransition,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub action: ActionBinding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessTransition {
+    /// No process of the name ran, and now one does.
+    Started,
+    /// The last process of the name exited.
+    Exited,
+}
+
+impl std::fmt::Display for ProcessTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Started => "starts",
+            Self::Exited => "exits",
+        })
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn process_watchers_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("process_watchers.json"))
+}
+
+/// Load all process watchers; a missing file yields none.
+pub fn load_process_watchers() -> anyhow::Result<Vec<ProcessWatcher>> {
+    json::read_or_default(&process_watchers_path()?)
+}
+
+pub fn save_process_watchers(watchers: &[ProcessWatcher]) -> anyhow::Result<()> {
+    json::write_atomic(&process_watchers_path()?, watchers)
+}
@@ -1,16 +1,17 @@
+use std::ffi::OsString;
 use std::fs;
-use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use anyhow::Context;
 use app_core::ids::ProfileId;
 use serde::{Deserialize, Serialize};
 
-use crate::paths;
+use crate::{json, paths};
 
-const PROFILE_SCHEMA_VERSION: u32 = 4;
+pub(crate) const PROFILE_SCHEMA_VERSION: u32 = 4;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Profile {
     pub version: u32,
     pub id: ProfileId,
@@ -23,9 +24,73 @@ pub struct Profile {
     /// Stream Deck+ touch strip config; defaults to an empty config for non-plus devices.
     #[serde(default)]
     pub touch_strip: TouchStripConfig,
+    /// Brightness (percent) applied while the profile is active, instead of the device's own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+    /// Profile whose keys fill in the keys this one leaves empty (see [`apply_base_profile`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_profile: Option<ProfileId>,
+    /// Surface whose grid the keys fill, for a wall of decks (see [`crate::surfaces`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surface: Option<u64>,
+    /// Actions run when files change, while the profile is active.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_watchers: Vec<FileWatcher>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Runs `action` when a file or a directory's contents change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileWatcher {
+    /// Unique within the profile; identifies the watcher in the UI and in invocations.
+    pub id: u64,
+    /// File or directory to watch.
+    pub path: String,
+    /// Also watch the directories below `path`.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Changes that fire the action.
+    #[serde(default = "FileChange::all")]
+    pub changes: Vec<FileChange>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub action: actions::ActionBinding,
+}
+
+impl FileWatcher {
+    /// Whether a change of `path` is one this watcher looks at: `path` itself, what is directly
+    /// in it or, when recursive, anywhere below it.
+    pub fn covers(&self, path: &Path) -> bool {
+        let root = Path::new(&self.path);
+        if self.recursive {
+            path.starts_with(root)
+        } else {
+            path == root || path.parent() == Some(root)
+        }
+    }
+}
+
+/// What happened to a file, as far as [`FileWatcher`]s tell apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    /// Created, or moved in.
+    Create,
+    Modify,
+    /// Deleted, or moved away.
+    Delete,
+}
+
+impl FileChange {
+    pub fn all() -> Vec<FileChange> {
+        vec![FileChange::Create, FileChange::Modify, FileChange::Delete]
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Background {
     None,
@@ -38,7 +103,7 @@ impl Default for Background {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct Appearance {
     #[serde(default)]
     pub background: Background,
@@ -48,9 +113,48 @@ pub struct Appearance {
     /// Optional single-line text rendered on the LCD surface.
     #[serde(default)]
     pub text: Option<String>,
+    /// Home Assistant entity whose state the surface shows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+    /// Size of `text`; picked to fit the surface when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_size: Option<TextSize>,
+    /// Briefly shown on the surface whenever one of the control's bindings fires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flash: Option<FlashEffect>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Text size of [`Appearance::text`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl TextSize {
+    /// Pixel scale of the 8x8 LCD font.
+    pub fn scale(self) -> u32 {
+        match self {
+            TextSize::Small => 1,
+            TextSize::Medium => 2,
+            TextSize::Large => 3,
+        }
+    }
+}
+
+/// Feedback effect of [`Appearance::flash`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashEffect {
+    /// Invert the surface's colors.
+    Invert,
+    /// Draw a bright border around the surface.
+    Border,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct KeyConfig {
     /// Temporary, MVP-level metadata for UI bring-up.
     /// OpenAction bindings will later live alongside this.
@@ -59,9 +163,35 @@ pub struct KeyConfig {
     pub action: Option<actions::ActionBinding>,
     #[serde(default)]
     pub appearance: Appearance,
+    /// Global keyboard shortcut that also presses this key (e.g. `control+alt+KeyA`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hotkey: Option<String>,
+    /// The base profile's key this one was filled in from; never saved.
+    #[serde(skip)]
+    pub base: Option<Box<KeyConfig>>,
+}
+
+impl KeyConfig {
+    /// Whether the key comes from the base profile unchanged, i.e. does not override it.
+    pub fn is_inherited(&self) -> bool {
+        self.base.as_deref().is_some_and(|base| {
+            base.label == self.label
+                && base.action == self.action
+                && base.appearance == self.appearance
+                && base.hotkey == self.hotkey
+        })
+    }
+
+    /// Whether nothing is configured on the key.
+    pub fn is_empty(&self) -> bool {
+        self.label.is_empty()
+            && self.action.is_none()
+            && self.appearance == Appearance::default()
+            && self.hotkey.is_none()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct DialConfig {
     #[serde(default)]
     pub label: String,
@@ -75,24 +205,67 @@ pub struct DialConfig {
     pub appearance: Appearance,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct TouchStripConfig {
     /// Invoked on touch tap.
     #[serde(default)]
     pub tap: Option<actions::ActionBinding>,
-    /// Invoked on touch drag/swipe.
+    /// Invoked on a long press; falls back to `tap` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_press: Option<actions::ActionBinding>,
+    /// Invoked on touch drag (every movement of the finger).
     #[serde(default)]
     pub drag: Option<actions::ActionBinding>,
+    /// Invoked on drags that start on the left half; falls back to `drag` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drag_left: Option<actions::ActionBinding>,
+    /// Invoked on drags that start on the right half; falls back to `drag` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drag_right: Option<actions::ActionBinding>,
+    /// Invoked once per quick swipe towards the left.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swipe_left: Option<actions::ActionBinding>,
+    /// Invoked once per quick swipe towards the right.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swipe_right: Option<actions::ActionBinding>,
     #[serde(default)]
     pub appearance: Appearance,
 }
 
+impl TouchStripConfig {
+    /// Every binding slot of the strip.
+    pub fn bindings(&self) -> [&Option<actions::ActionBinding>; 7] {
+        [
+            &self.tap,
+            &self.long_press,
+            &self.drag,
+            &self.drag_left,
+            &self.drag_right,
+            &self.swipe_left,
+            &self.swipe_right,
+        ]
+    }
+
+    pub fn bindings_mut(&mut self) -> [&mut Option<actions::ActionBinding>; 7] {
+        [
+            &mut self.tap,
+            &mut self.long_press,
+            &mut self.drag,
+            &mut self.drag_left,
+            &mut self.drag_right,
+            &mut self.swipe_left,
+            &mut self.swipe_right,
+        ]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProfileMeta {
     pub id: ProfileId,
     pub name: String,
     pub path: PathBuf,
     pub key_count: u8,
+    pub surface: Option<u64>,
 }
 
 pub fn profiles_dir() -> anyhow::Result<PathBuf> {
@@ -120,6 +293,7 @@ pub fn list_profiles() -> anyhow::Result<Vec<ProfileMeta>> {
                 id: profile.id,
                 name: profile.name,
                 key_count: profile.key_count,
+                surface: profile.surface,
                 path,
             });
         }
@@ -143,6 +317,10 @@ pub fn create_profile(name: &str, key_count: u8) -> anyhow::Result<Profile> {
             vec![]
         },
         touch_strip: TouchStripConfig::default(),
+        brightness: None,
+        base_profile: None,
+        surface: None,
+        file_watchers: vec![],
     };
 
     // Give the first profile a minimal default label so UI looks alive.
@@ -158,10 +336,67 @@ pub fn profile_path(id: ProfileId) -> anyhow::Result<PathBuf> {
     Ok(ensure_profiles_dir()?.join(format!("{}.json", id.0)))
 }
 
+/// Load the profile at `path` with the keys it inherits from its base profiles filled in.
 pub fn load_profile(path: &Path) -> anyhow::Result<Profile> {
-    let raw = fs::read_to_string(path)?;
-    let mut p: Profile = serde_json::from_str(&raw)?;
+    let mut p = read_profile(path)?;
+    apply_base_profile(&mut p)?;
+    Ok(p)
+}
+
+/// Re-resolve the keys `profile` inherits, e.g. after its `base_profile` changed.
+///
+/// Empty keys take the key at the same index from the base profile, then from that profile's
+/// base and so on. A missing base profile ends the chain; a chain that loops is an error.
+pub fn apply_base_profile(profile: &mut Profile) -> anyhow::Result<()> {
+    for k in &mut profile.keys {
+        if k.is_inherited() {
+            *k = KeyConfig::default();
+        }
+        k.base = None;
+    }
+
+    let mut seen = vec![profile.id];
+    let mut next = profile.base_profile;
+    while let Some(id) = next {
+        if seen.contains(&id) {
+            anyhow::bail!("the base profiles of {} form a loop", profile.name);
+        }
+        seen.push(id);
+        let base = match read_profile(&profile_path(id)?) {
+            Ok(base) => base,
+            Err(e) => {
+                tracing::warn!(base = id.0, error = %e, "failed to load base profile");
+                break;
+            }
+        };
+        for (k, inherited) in profile.keys.iter_mut().zip(&base.keys) {
+            if k.base.is_none() && k.is_empty() && !inherited.is_empty() {
+                *k = KeyConfig {
+                    base: Some(Box::new(inherited.clone())),
+                    ..inherited.clone()
+                };
+            }
+        }
+        next = base.base_profile;
+    }
+    Ok(())
+}
+
+/// Load the profile at `path` as stored, without inherited keys.
+///
+/// A file that is not a valid profile (e.g. cut short by a crash) is replaced by its backup, the
+/// version saved before it; the damaged file is kept next to it with a `.corrupt` suffix.
+fn read_profile(path: &Path) -> anyhow::Result<Profile> {
+    let raw = fs::read(path)?;
+    let p: Profile = match serde_json::from_slice(&raw) {
+        Ok(p) => p,
+        Err(e) => restore_backup(path, &e)?,
+    };
+    migrate(p)
+}
 
+/// Bring a profile of an older schema version up to date and fix its key and dial counts.
+fn migrate(mut p: Profile) -> anyhow::Result<Profile> {
     if p.version == 0 {
         // Future-proofing: treat missing/zero as v1.
         p.version = 1;
@@ -202,6 +437,79 @@ pub fn load_profile(path: &Path) -> anyhow::Result<Profile> {
     Ok(p)
 }
 
+/// Read a profile someone shared (e.g. from a profile gallery) as a new profile of this user.
+///
+/// It gets a fresh id and no base profile or surface, since ids only mean something on the
+/// machine that made them. Icon paths point into the sharer's file system, so they are dropped
+/// too, as are file watchers, which would run actions without anyone pressing a key.
+pub fn parse_shared_profile(bytes: &[u8]) -> anyhow::Result<Profile> {
+    let p: Profile = serde_json::from_slice(bytes).context("not a valid profile")?;
+    let mut p = migrate(p)?;
+    p.id = new_profile_id();
+    p.base_profile = None;
+    p.surface = None;
+    p.file_watchers.clear();
+    let appearances = p
+        .keys
+        .iter_mut()
+        .map(|k| &mut k.appearance)
+        .chain(p.dials.iter_mut().map(|d| &mut d.appearance))
+        .chain([&mut p.touch_strip.appearance]);
+    for appearance in appearances {
+        appearance.icon_path = None;
+    }
+    Ok(p)
+}
+
+/// Ids of the plugins the bindings of `profile` invoke, sorted and without repeats.
+pub fn plugin_ids(profile: &Profile) -> Vec<String> {
+    let keys = profile.keys.iter().map(|k| &k.action);
+    let dials = profile.dials.iter().flat_map(|d| [&d.press, &d.rotate]);
+    let watchers = profile.file_watchers.iter().map(|w| &w.action);
+    let mut ids: Vec<String> = keys
+        .chain(dials)
+        .chain(profile.touch_strip.bindings())
+        .flatten()
+        .chain(watchers)
+        .flat_map(|b| b.plugin_ids())
+        .map(str::to_string)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// `<path>.<suffix>`, e.g. `123.json.bak`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Put the backup of the damaged profile at `path` in its place and return it.
+fn restore_backup(path: &Path, error: &serde_json::Error) -> anyhow::Result<Profile> {
+    let backup_path = sibling(path, "bak");
+    let backup = fs::read(&backup_path).ok();
+    let Some((bytes, profile)) = backup.and_then(|b| {
+        let profile = serde_json::from_slice::<Profile>(&b).ok()?;
+        Some((b, profile))
+    }) else {
+        anyhow::bail!(
+            "profile {} is damaged ({error}) and has no usable backup",
+            path.display()
+        );
+    };
+    tracing::warn!(
+        path = %path.display(),
+        %error,
+        "profile is damaged; restoring the last good backup"
+    );
+    fs::rename(path, sibling(path, "corrupt"))?;
+    json::write_bytes_atomic(path, &bytes)?;
+    Ok(profile)
+}
+
 pub fn save_profile(profile: &Profile) -> anyhow::Result<()> {
     let path = profile_path(profile.id)?;
     save_profile_to_path(profile, &path)
@@ -215,26 +523,73 @@ pub fn save_profile_to_path(profile: &Profile, path: &Path) -> anyhow::Result<()
         );
     }
 
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    let tmp_path = path.with_extension("json.tmp");
-    let json = serde_json::to_vec_pretty(profile)?;
+    let mut bytes = if profile.keys.iter().any(KeyConfig::is_inherited) {
+        // Inherited keys are stored empty so they keep following the base profile.
+        let mut own = profile.clone();
+        for k in own.keys.iter_mut().filter(|k| k.is_inherited()) {
+            *k = KeyConfig::default();
+        }
+        serde_json::to_vec_pretty(&own)?
+    } else {
+        serde_json::to_vec_pretty(profile)?
+    };
+    bytes.push(b'\n');
 
-    {
-        let mut f = fs::File::create(&tmp_path)?;
-        f.write_all(&json)?;
-        f.write_all(b"\n")?;
-        f.sync_all()?;
+    // Keep the version being replaced as the backup, unless it is damaged itself.
+    if let Ok(current) = fs::read(path) {
+        if current != bytes && serde_json::from_slice::<Profile>(&current).is_ok() {
+            json::write_bytes_atomic(&sibling(path, "bak"), &current)?;
+        }
     }
+    json::write_bytes_atomic(path, &bytes)
+}
 
-    // Best-effort atomic replace. On Windows, rename over existing may fail; remove first.
-    if cfg!(windows) && path.exists() {
-        let _ = fs::remove_file(path);
+/// Clears all bindings in `profile` that reference `plugin_id` (see `actions::strip_plugin`).
+///
+/// Returns the number of removed references.
+pub fn strip_plugin_bindings(profile: &mut Profile, plugin_id: &str) -> usize {
+    let mut removed = 0;
+    for k in &mut profile.keys {
+        removed += actions::strip_plugin(&mut k.action, plugin_id);
+        // The base profile loses the binding too; keep inherited keys matching it.
+        if let Some(base) = &mut k.base {
+            actions::strip_plugin(&mut base.action, plugin_id);
+        }
     }
-    fs::rename(tmp_path, path)?;
-    Ok(())
+    for d in &mut profile.dials {
+        removed += actions::strip_plugin(&mut d.press, plugin_id);
+        removed += actions::strip_plugin(&mut d.rotate, plugin_id);
+    }
+    for binding in profile.touch_strip.bindings_mut() {
+        removed += actions::strip_plugin(binding, plugin_id);
+    }
+    removed += strip_plugin_triggers(&mut profile.file_watchers, plugin_id, |w| &mut w.action);
+    removed
+}
+
+/// Clears references to `plugin_id` in the actions of `triggers` (file watchers, schedules,
+/// webhooks and the other trigger stores); a trigger whose action was the plugin's goes with it.
+///
+/// Returns the number of removed references.
+pub fn strip_plugin_triggers<T>(
+    triggers: &mut Vec<T>,
+    plugin_id: &str,
+    action: impl Fn(&mut T) -> &mut actions::ActionBinding,
+) -> usize {
+    let mut removed = 0;
+    triggers.retain_mut(|t| {
+        let binding = action(t);
+        let mut slot = Some(binding.clone());
+        removed += actions::strip_plugin(&mut slot, plugin_id);
+        match slot {
+            Some(stripped) => {
+                *binding = stripped;
+                true
+            }
+            None => false,
+        }
+    });
+    removed
 }
 
 fn new_profile_id() -> ProfileId {
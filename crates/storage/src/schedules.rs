@@ -0,0 +1,39 @@
+//! Time-based action triggers, stored as `schedules.json` in the data directory.
+//!
+//! Schedules are global (not per profile): a schedule can switch profiles itself.
+
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Unique within the file; used to identify the schedule in logs and invocations.
+    pub id: u64,
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Five-field cron expression (minute hour day-of-month month day-of-week), in local time.
+    pub cron: String,
+    pub action: ActionBinding,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+pub fn schedules_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("schedules.json"))
+}
+
+/// Load all schedules; a missing file yields none.
+pub fn load_schedules() -> anyhow::Result<Vec<Schedule>> {
+    json::read_or_default(&schedules_path()?)
+}
+
+pub fn save_schedules(schedules: &[Schedule]) -> anyhow::Result<()> {
+    json::write_atomic(&schedules_path()?, schedules)
+}
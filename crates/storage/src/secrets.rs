@@ -0,0 +1,70 @@
+//! Where secrets for action settings are kept, stored as `secrets.json` in the data directory.
+//!
+//! The file holds the names of the secrets in the OS keyring, and the secrets kept without one,
+//! encrypted with the key in `secrets.key` next to it. Both files are readable by the user only.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretsFile {
+    /// Names of the secrets kept in the OS keyring.
+    #[serde(default)]
+    pub keyring: BTreeSet<String>,
+    /// Secrets kept in this file instead, by name: base64 of the nonce followed by the
+    /// ciphertext.
+    #[serde(default)]
+    pub encrypted: BTreeMap<String, String>,
+}
+
+pub fn secrets_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("secrets.json"))
+}
+
+pub fn secrets_key_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("secrets.key"))
+}
+
+/// Load the secrets file; a missing file yields no secrets.
+pub fn load_secrets() -> anyhow::Result<SecretsFile> {
+    json::read_or_default(&secrets_path()?)
+}
+
+pub fn save_secrets(secrets: &SecretsFile) -> anyhow::Result<()> {
+    let path = secrets_path()?;
+    json::write_atomic(&path, secrets)?;
+    restrict(&path)
+}
+
+/// The key of the encrypted secrets; `None` until one was saved.
+pub fn load_secrets_key() -> anyhow::Result<Option<Vec<u8>>> {
+    match fs::read(secrets_key_path()?) {
+        Ok(key) => Ok(Some(key)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_secrets_key(key: &[u8]) -> anyhow::Result<()> {
+    let path = secrets_key_path()?;
+    json::write_bytes_atomic(&path, key)?;
+    restrict(&path)
+}
+
+/// Make `path` readable by its owner only (Unix only; the data directory of other platforms is
+/// private to the user already).
+fn restrict(path: &Path) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
@@ -0,0 +1,596 @@
+//! App-wide user settings, stored as `settings.json` in the data directory.
+//!
+//! Credentials (tokens and keys) are not written to the file once a [`CredentialStore`] is
+//! set: the file keeps a `{{secret:settings.…}}` reference and loading puts the value back.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use app_core::ids::ProfileId;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Audio output device name (`None` = system default).
+    #[serde(default)]
+    pub audio_output_device: Option<String>,
+    #[serde(default)]
+    pub webhook: WebhookSettings,
+    #[serde(default)]
+    pub companion: CompanionSettings,
+    #[serde(default)]
+    pub home_assistant: HomeAssistantSettings,
+    #[serde(default)]
+    pub spotify: SpotifySettings,
+    /// Last brightness (percent) per device, keyed by device id; restored on connect.
+    #[serde(default)]
+    pub device_brightness: BTreeMap<u64, u8>,
+    #[serde(default)]
+    pub touch_gestures: TouchGestureSettings,
+    #[serde(default)]
+    pub interface: InterfaceSettings,
+    #[serde(default)]
+    pub marketplace: MarketplaceSettings,
+    #[serde(default)]
+    pub macros: MacroSettings,
+    #[serde(default)]
+    pub text_expander: TextExpanderSettings,
+    #[serde(default)]
+    pub midi: MidiSettings,
+    #[serde(default)]
+    pub osc: OscSettings,
+    #[serde(default)]
+    pub notification_mirror: NotificationMirrorSettings,
+    #[serde(default)]
+    pub lighting: LightingSettings,
+    #[serde(default)]
+    pub meetings: MeetingSettings,
+    #[serde(default)]
+    pub sync: SyncSettings,
+    #[serde(default)]
+    pub kiosk: KioskSettings,
+}
+
+/// The embedded HTTP listener that maps webhook requests to actions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_webhook_port")]
+    pub port: u16,
+    /// Listen on all interfaces instead of localhost only.
+    #[serde(default)]
+    pub listen_on_network: bool,
+    /// Shared secret every request must present; the listener refuses to start without one.
+    #[serde(default)]
+    pub token: String,
+    /// Also serve the remote-control API (`/api/...`) for companion apps.
+    #[serde(default)]
+    pub api: bool,
+    /// Also serve counters and timings for monitoring on `GET /metrics`.
+    #[serde(default)]
+    pub metrics: bool,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_webhook_port(),
+            listen_on_network: false,
+            token: String::new(),
+            api: false,
+            metrics: false,
+        }
+    }
+}
+
+fn default_webhook_port() -> u16 {
+    8787
+}
+
+/// Watching the keyboard for the abbreviations of typed triggers (see
+/// [`crate::typed_triggers`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextExpanderSettings {
+    /// Off unless the user turns it on: it reads every key typed on the system.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Listening to a MIDI input port for the notes and controllers of MIDI triggers (see
+/// [`crate::midi_triggers`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Port to listen to, matched against the port names (case-insensitively, in part); empty
+    /// picks the first port.
+    #[serde(default)]
+    pub input_port: String,
+}
+
+/// Listening for the OSC messages of OSC triggers (see [`crate::osc_triggers`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OscSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// UDP port to listen on.
+    #[serde(default = "default_osc_port")]
+    pub port: u16,
+    /// Listen on all interfaces instead of localhost only.
+    #[serde(default)]
+    pub listen_on_network: bool,
+}
+
+impl Default for OscSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_osc_port(),
+            listen_on_network: false,
+        }
+    }
+}
+
+fn default_osc_port() -> u16 {
+    9000
+}
+
+/// Showing the latest desktop notification on a key for a few seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationMirrorSettings {
+    /// Off unless the user turns it on: it reads every notification shown on the desktop.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Index of the key the notifications are shown on.
+    #[serde(default)]
+    pub key: u8,
+    /// How long a notification stays on the key.
+    #[serde(default = "default_mirror_seconds")]
+    pub seconds: u32,
+}
+
+impl Default for NotificationMirrorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key: 0,
+            seconds: default_mirror_seconds(),
+        }
+    }
+}
+
+fn default_mirror_seconds() -> u32 {
+    5
+}
+
+/// Connection to a Bitfocus Companion installation (Satellite protocol).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompanionSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_companion_host")]
+    pub host: String,
+    #[serde(default = "default_companion_port")]
+    pub port: u16,
+}
+
+impl Default for CompanionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_companion_host(),
+            port: default_companion_port(),
+        }
+    }
+}
+
+fn default_companion_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_companion_port() -> u16 {
+    16622
+}
+
+/// Connection to a Home Assistant instance (WebSocket API).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeAssistantSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Base URL of the instance, e.g. `http://homeassistant.local:8123`.
+    #[serde(default = "default_home_assistant_url")]
+    pub url: String,
+    /// Long-lived access token (created on the Home Assistant user profile page).
+    #[serde(default)]
+    pub token: String,
+}
+
+impl Default for HomeAssistantSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: default_home_assistant_url(),
+            token: String::new(),
+        }
+    }
+}
+
+fn default_home_assistant_url() -> String {
+    "http://homeassistant.local:8123".to_string()
+}
+
+/// When a touch strip gesture counts as a swipe (instead of only a drag).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TouchGestureSettings {
+    /// Minimum horizontal travel, in pixels of the 800 px wide strip.
+    #[serde(default = "default_swipe_distance")]
+    pub swipe_distance: u16,
+    /// Longest a swipe may take, in milliseconds.
+    #[serde(default = "default_swipe_max_ms")]
+    pub swipe_max_ms: u64,
+}
+
+impl Default for TouchGestureSettings {
+    fn default() -> Self {
+        Self {
+            swipe_distance: default_swipe_distance(),
+            swipe_max_ms: default_swipe_max_ms(),
+        }
+    }
+}
+
+fn default_swipe_distance() -> u16 {
+    200
+}
+
+fn default_swipe_max_ms() -> u64 {
+    500
+}
+
+/// How the app's own window is drawn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceSettings {
+    /// Scale of the whole interface, in percent.
+    #[serde(default = "default_scale_percent")]
+    pub scale_percent: u16,
+    #[serde(default)]
+    pub theme: ThemeSetting,
+}
+
+impl Default for InterfaceSettings {
+    fn default() -> Self {
+        Self {
+            scale_percent: default_scale_percent(),
+            theme: ThemeSetting::default(),
+        }
+    }
+}
+
+/// Theme of the app window.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeSetting {
+    #[default]
+    Dark,
+    Light,
+    /// A theme file, by id (see [`crate::themes`]).
+    Custom(String),
+}
+
+fn default_scale_percent() -> u16 {
+    100
+}
+
+/// Link to a Spotify account (Web API, signed in with Authorization Code + PKCE).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpotifySettings {
+    /// Client ID of the user's app on the Spotify developer dashboard.
+    #[serde(default)]
+    pub client_id: String,
+    /// Refresh token of the signed-in account; empty when signed out.
+    #[serde(default)]
+    pub refresh_token: String,
+}
+
+/// The Philips Hue bridge `light` actions go through; WLED controllers need no settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightingSettings {
+    /// Host name or address of the bridge; empty when none is set up.
+    #[serde(default)]
+    pub hue_bridge: String,
+    /// Application key the bridge handed out when its link button was pressed.
+    #[serde(default)]
+    pub hue_app_key: String,
+}
+
+/// Access to Microsoft Teams' API for third-party devices, for `meeting` actions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeetingSettings {
+    /// Token Teams handed out once the user allowed the app; empty until then.
+    #[serde(default)]
+    pub teams_token: String,
+}
+
+/// Sync of profiles and settings through a shared folder (see [`crate::sync`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSettings {
+    /// The folder, e.g. a git checkout or a Syncthing folder; empty when sync is off.
+    #[serde(default)]
+    pub folder: String,
+}
+
+impl SyncSettings {
+    /// The folder, when one is set.
+    pub fn folder(&self) -> Option<&str> {
+        Some(self.folder.trim()).filter(|f| !f.is_empty())
+    }
+}
+
+/// Kiosk lock for shared machines: locked profiles can't be edited or switched away from
+/// without the PIN. The PIN keeps casual hands off, nothing more; whoever can edit the settings
+/// file can lift it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KioskSettings {
+    /// Raw ids of the locked profiles.
+    #[serde(default)]
+    pub locked_profiles: Vec<u64>,
+    /// Hex PBKDF2-HMAC-SHA256 of the PIN under [`Self::pin_salt`]; empty when none is set,
+    /// which leaves the lock off.
+    #[serde(default)]
+    pub pin_hash: String,
+    /// Hex random salt of [`Self::pin_hash`], made when the PIN is set.
+    #[serde(default)]
+    pub pin_salt: String,
+    /// Hex unsalted SHA-256 of a PIN set by older versions; replaced by [`Self::pin_hash`] on
+    /// the first unlock (see [`Self::pin_outdated`]).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pin_sha256: String,
+}
+
+/// PBKDF2 rounds for the PIN, OWASP's recommendation for HMAC-SHA256.
+const PIN_ROUNDS: u32 = 600_000;
+
+impl KioskSettings {
+    /// Whether the lock is on: a PIN is set and some profile is locked.
+    pub fn enabled(&self) -> bool {
+        self.has_pin() && !self.locked_profiles.is_empty()
+    }
+
+    pub fn has_pin(&self) -> bool {
+        !self.pin_hash.is_empty() || !self.pin_sha256.is_empty()
+    }
+
+    pub fn is_locked(&self, profile: ProfileId) -> bool {
+        self.locked_profiles.contains(&profile.0)
+    }
+
+    /// Sets the PIN under a new random salt; an empty one removes it.
+    pub fn set_pin(&mut self, pin: &str) -> anyhow::Result<()> {
+        let pin = pin.trim();
+        self.pin_sha256.clear();
+        if pin.is_empty() {
+            self.pin_hash.clear();
+            self.pin_salt.clear();
+            return Ok(());
+        }
+        let mut salt = [0u8; 16];
+        getrandom::getrandom(&mut salt)
+            .map_err(|e| anyhow::anyhow!("[Settings] No randomness for the PIN's salt: {e}"))?;
+        self.pin_salt = to_hex(&salt);
+        self.pin_hash = to_hex(&pin_hash(pin, &salt));
+        Ok(())
+    }
+
+    pub fn pin_matches(&self, pin: &str) -> bool {
+        use sha2::{Digest, Sha256};
+        use subtle::ConstantTimeEq;
+
+        let pin = pin.trim();
+        let (stored, hash) = if !self.pin_hash.is_empty() {
+            let Some(salt) = from_hex(&self.pin_salt) else {
+                return false;
+            };
+            (&self.pin_hash, to_hex(&pin_hash(pin, &salt)))
+        } else if !self.pin_sha256.is_empty() {
+            (&self.pin_sha256, to_hex(&Sha256::digest(pin.as_bytes())))
+        } else {
+            return false;
+        };
+        hash.as_bytes().ct_eq(stored.as_bytes()).into()
+    }
+
+    /// Whether the PIN is still an older version's unsalted hash, to be set again once it is
+    /// known.
+    pub fn pin_outdated(&self) -> bool {
+        self.pin_hash.is_empty() && !self.pin_sha256.is_empty()
+    }
+}
+
+fn pin_hash(pin: &str, salt: &[u8]) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<sha2::Sha256, 32>(pin.as_bytes(), salt, PIN_ROUNDS)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Plugin marketplace access.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketplaceSettings {
+    /// GitHub personal access token for plugin details (release downloads) and GitHub Actions
+    /// keys, which need it for private repositories; empty means unauthenticated requests,
+    /// which GitHub limits to 60 an hour.
+    #[serde(default)]
+    pub github_token: String,
+    /// Index of the community profile gallery shown in the marketplace's Profiles tab.
+    #[serde(default)]
+    pub profile_gallery_url: String,
+}
+
+impl MarketplaceSettings {
+    /// The token, when one is set.
+    pub fn github_token(&self) -> Option<&str> {
+        Some(self.github_token.trim()).filter(|t| !t.is_empty())
+    }
+}
+
+/// How far a binding may expand before it is refused (see [`actions::ExpandLimits`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroSettings {
+    /// Most steps, delays included, one binding may run.
+    #[serde(default = "default_macro_max_steps")]
+    pub max_steps: usize,
+    /// Deepest nesting of macros inside macros.
+    #[serde(default = "default_macro_max_depth")]
+    pub max_depth: usize,
+}
+
+impl Default for MacroSettings {
+    fn default() -> Self {
+        Self {
+            max_steps: default_macro_max_steps(),
+            max_depth: default_macro_max_depth(),
+        }
+    }
+}
+
+impl MacroSettings {
+    pub fn limits(&self) -> actions::ExpandLimits {
+        actions::ExpandLimits {
+            max_steps: self.max_steps.max(1),
+            max_depth: self.max_depth.max(1),
+        }
+    }
+}
+
+fn default_macro_max_steps() -> usize {
+    actions::ExpandLimits::DEFAULT_MAX_STEPS
+}
+
+fn default_macro_max_depth() -> usize {
+    actions::ExpandLimits::DEFAULT_MAX_DEPTH
+}
+
+pub fn settings_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("settings.json"))
+}
+
+/// The settings that hold credentials, by the name they are kept under in the credential store.
+fn credentials(settings: &mut Settings) -> [(&'static str, &mut String); 6] {
+    [
+        ("settings.webhook.token", &mut settings.webhook.token),
+        (
+            "settings.home_assistant.token",
+            &mut settings.home_assistant.token,
+        ),
+        (
+            "settings.spotify.refresh_token",
+            &mut settings.spotify.refresh_token,
+        ),
+        (
+            "settings.meetings.teams_token",
+            &mut settings.meetings.teams_token,
+        ),
+        (
+            "settings.lighting.hue_app_key",
+            &mut settings.lighting.hue_app_key,
+        ),
+        (
+            "settings.marketplace.github_token",
+            &mut settings.marketplace.github_token,
+        ),
+    ]
+}
+
+/// Where the credentials in the settings are kept instead of `settings.json` (the `secrets`
+/// crate's store).
+pub trait CredentialStore: Send + Sync {
+    fn get(&self, name: &str) -> anyhow::Result<String>;
+    /// Store `value` as `name`, replacing what was there.
+    fn set(&self, name: &str, value: &str) -> anyhow::Result<()>;
+    /// Forget `name`; forgetting one that isn't there succeeds.
+    fn remove(&self, name: &str) -> anyhow::Result<()>;
+}
+
+static CREDENTIAL_STORE: OnceLock<Box<dyn CredentialStore>> = OnceLock::new();
+
+/// Keep the credentials in `store` from now on. Binaries call this at startup, before the
+/// settings are first loaded; later calls fail. Without a store they stay in `settings.json`.
+pub fn set_credential_store(store: Box<dyn CredentialStore>) -> anyhow::Result<()> {
+    CREDENTIAL_STORE
+        .set(store)
+        .map_err(|_| anyhow::anyhow!("the credential store was already set"))
+}
+
+/// What `settings.json` holds in place of the credential kept as `name`.
+fn credential_reference(name: &str) -> String {
+    format!("{{{{secret:{name}}}}}")
+}
+
+/// Load settings; a missing file yields the defaults. Credentials still in the file are moved
+/// to the credential store. One that can't be read is left as its reference, so saving keeps
+/// it.
+pub fn load_settings() -> anyhow::Result<Settings> {
+    let mut settings: Settings = json::read_or_default(&settings_path()?)?;
+    let Some(store) = CREDENTIAL_STORE.get() else {
+        return Ok(settings);
+    };
+    let mut plaintext = false;
+    for (name, value) in credentials(&mut settings) {
+        if *value == credential_reference(name) {
+            match store.get(name) {
+                Ok(stored) => *value = stored,
+                Err(e) => {
+                    tracing::warn!(error = %format!("{e:#}"), name, "failed to read a credential")
+                }
+            }
+        } else if !value.is_empty() {
+            plaintext = true;
+        }
+    }
+    if plaintext {
+        save_settings(&settings)?;
+    }
+    Ok(settings)
+}
+
+/// Save settings, with the credentials in the credential store when one is set.
+pub fn save_settings(settings: &Settings) -> anyhow::Result<()> {
+    let Some(store) = CREDENTIAL_STORE.get() else {
+        return json::write_atomic(&settings_path()?, settings);
+    };
+    let mut stored = settings.clone();
+    for (name, value) in credentials(&mut stored) {
+        let reference = credential_reference(name);
+        if value.is_empty() {
+            store.remove(name)?;
+        } else if *value != reference {
+            store.set(name, value)?;
+            *value = reference;
+        }
+    }
+    json::write_atomic(&settings_path()?, &stored)
+}
+
+/// Load, modify and save the settings in one step.
+pub fn update_settings(f: impl FnOnce(&mut Settings)) -> anyhow::Result<Settings> {
+    let mut settings = load_settings()?;
+    f(&mut settings);
+    save_settings(&settings)?;
+    Ok(settings)
+}
@@ -0,0 +1,179 @@
+//! Surfaces: decks placed side by side and composed into one grid of keys, stored as
+//! `surfaces.json` in the data directory.
+//!
+//! A profile that targets a surface (see [`crate::profiles::Profile::surface`]) has one key per
+//! cell of the surface grid, numbered row by row like the keys of a single deck. Each member deck
+//! covers a rectangle of the grid; cells no deck covers are keys that never show. Only keys are
+//! composed: dials and touch strips stay with the deck the app is connected to.
+
+use std::path::PathBuf;
+
+use app_core::ids::DeviceId;
+use serde::{Deserialize, Serialize};
+
+use crate::profiles::{self, KeyConfig, Profile};
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Surface {
+    /// Unique within the file; profiles refer to the surface by it.
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub members: Vec<SurfaceMember>,
+}
+
+/// A deck of a surface and where its keys are in the grid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SurfaceMember {
+    /// Raw id of the deck.
+    pub device: u64,
+    /// Name of the deck when it was added, shown while it is not plugged in.
+    #[serde(default)]
+    pub name: String,
+    /// Grid cell of the deck's top-left key.
+    pub column: u8,
+    pub row: u8,
+    /// Key layout of the deck.
+    pub columns: u8,
+    pub rows: u8,
+}
+
+impl SurfaceMember {
+    fn covers(&self, column: u16, row: u16) -> bool {
+        let (left, top) = (u16::from(self.column), u16::from(self.row));
+        (left..left + u16::from(self.columns)).contains(&column)
+            && (top..top + u16::from(self.rows)).contains(&row)
+    }
+
+    fn overlaps(&self, other: &SurfaceMember) -> bool {
+        let span = |start: u8, len: u8| u16::from(start)..u16::from(start) + u16::from(len);
+        let (columns, other_columns) = (
+            span(self.column, self.columns),
+            span(other.column, other.columns),
+        );
+        let (rows, other_rows) = (span(self.row, self.rows), span(other.row, other.rows));
+        columns.start < other_columns.end
+            && other_columns.start < columns.end
+            && rows.start < other_rows.end
+            && other_rows.start < rows.end
+    }
+}
+
+impl Surface {
+    /// Columns and rows of the grid: the smallest one holding every member.
+    pub fn grid(&self) -> (u8, u8) {
+        let extent = |f: fn(&SurfaceMember) -> u8| self.members.iter().map(f).max().unwrap_or(0);
+        (
+            extent(|m| m.column.saturating_add(m.columns)),
+            extent(|m| m.row.saturating_add(m.rows)),
+        )
+    }
+
+    /// Keys of a profile targeting the surface.
+    pub fn key_count(&self) -> u8 {
+        let (columns, rows) = self.grid();
+        u8::try_from(u16::from(columns) * u16::from(rows)).unwrap_or(u8::MAX)
+    }
+
+    pub fn member(&self, device: DeviceId) -> Option<&SurfaceMember> {
+        self.members.iter().find(|m| m.device == device.0)
+    }
+
+    /// The surface key that key `key` of `device` is.
+    pub fn surface_key(&self, device: DeviceId, key: u8) -> Option<u8> {
+        let m = self.member(device)?;
+        if m.columns == 0 || u16::from(key) >= u16::from(m.columns) * u16::from(m.rows) {
+            return None;
+        }
+        let (columns, _) = self.grid();
+        let column = u16::from(m.column) + u16::from(key % m.columns);
+        let row = u16::from(m.row) + u16::from(key / m.columns);
+        u8::try_from(row * u16::from(columns) + column).ok()
+    }
+
+    /// The deck and key that show surface key `key`, if a deck covers it.
+    pub fn device_key(&self, key: u8) -> Option<(DeviceId, u8)> {
+        let (columns, _) = self.grid();
+        if columns == 0 {
+            return None;
+        }
+        let (column, row) = (u16::from(key % columns), u16::from(key / columns));
+        let m = self.members.iter().find(|m| m.covers(column, row))?;
+        let index = (row - u16::from(m.row)) * u16::from(m.columns) + column - u16::from(m.column);
+        Some((DeviceId(m.device), u8::try_from(index).ok()?))
+    }
+
+    /// Fails when the surface can't be used as laid out: a deck is in it twice, decks overlap
+    /// or the grid has more than 255 keys.
+    pub fn check(&self) -> anyhow::Result<()> {
+        for (i, m) in self.members.iter().enumerate() {
+            for other in &self.members[i + 1..] {
+                if other.device == m.device {
+                    anyhow::bail!("[Surfaces] {} is in {} twice", m.name, self.name);
+                }
+                if m.overlaps(other) {
+                    anyhow::bail!("[Surfaces] {} and {} overlap", m.name, other.name);
+                }
+            }
+        }
+        let (columns, rows) = self.grid();
+        if u16::from(columns) * u16::from(rows) > u16::from(u8::MAX) {
+            anyhow::bail!(
+                "[Surfaces] {} has {columns}×{rows} keys; a surface can have at most 255",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Moves the keys of `profile`, laid out for `old`, to where the same deck keys are in `new`.
+/// Keys of decks that left the surface, and of cells no deck covered, are dropped.
+pub fn relayout(profile: &mut Profile, old: &Surface, new: &Surface) {
+    let mut keys = vec![KeyConfig::default(); usize::from(new.key_count())];
+    for (i, key) in profile.keys.drain(..).enumerate() {
+        let on_deck = u8::try_from(i).ok().and_then(|i| old.device_key(i));
+        let moved = on_deck.and_then(|(device, k)| new.surface_key(device, k));
+        if let Some(slot) = moved.and_then(|k| keys.get_mut(usize::from(k))) {
+            *slot = key;
+        }
+    }
+    profile.keys = keys;
+    profile.key_count = new.key_count();
+}
+
+pub fn surfaces_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("surfaces.json"))
+}
+
+/// Load all surfaces; a missing file yields none.
+pub fn load_surfaces() -> anyhow::Result<Vec<Surface>> {
+    json::read_or_default(&surfaces_path()?)
+}
+
+/// Save `surfaces`. The keys of profiles on a surface whose decks moved are moved along (see
+/// [`relayout`]).
+pub fn save_surfaces(surfaces: &[Surface]) -> anyhow::Result<()> {
+    for surface in surfaces {
+        surface.check()?;
+    }
+    let old = load_surfaces()?;
+    json::write_atomic(&surfaces_path()?, surfaces)?;
+
+    let find = |list: &[Surface], id| list.iter().find(|s| s.id == id).cloned();
+    for meta in profiles::list_profiles()? {
+        let Some(id) = meta.surface else {
+            continue;
+        };
+        let (Some(before), Some(after)) = (find(&old, id), find(surfaces, id)) else {
+            continue;
+        };
+        if before.members != after.members {
+            let mut profile = profiles::load_profile(&meta.path)?;
+            relayout(&mut profile, &before, &after);
+            profiles::save_profile(&profile)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,338 @@
+//! Sync of profiles and settings through a folder the user picks, e.g. a git checkout or a
+//! Syncthing folder that other machines share.
+//!
+//! The folder mirrors the data directory: `profiles/<id>.json` and `settings.json`, the latter
+//! without the settings that belong to one machine (see [`LOCAL_SETTINGS`]). What was in sync
+//! last time is kept in `sync-base` in the data directory, so each file can be compared three
+//! ways: a file changed on one side only is copied to the other, and one changed on both sides is
+//! merged part by part. Parts changed on both sides in different ways make a conflict, which
+//! stays until the user picks a side with [`resolve`]. Nothing here commits or pulls; the git
+//! checkout or sync tool takes care of moving the folder between machines.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Context;
+use app_core::ids::ProfileId;
+use serde_json::Value;
+
+use crate::profiles::Profile;
+use crate::settings::{self, Settings};
+use crate::{json, paths};
+
+/// The synced settings file, relative to the data directory and the sync folder.
+pub const SETTINGS_FILE: &str = "settings.json";
+const PROFILES_DIR: &str = "profiles";
+
+/// Settings that stay on this machine, as dotted paths into `settings.json`. Credentials are
+/// among them: the sync folder is often a git repository, and must not receive them.
+pub const LOCAL_SETTINGS: &[&str] = &[
+    "sync",
+    "audio_output_device",
+    "device_brightness",
+    "kiosk",
+    "webhook.token",
+    "home_assistant.token",
+    "spotify.refresh_token",
+    "meetings.teams_token",
+    "lighting.hue_app_key",
+    "marketplace.github_token",
+];
+
+/// What one sync did, as paths relative to the data directory.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Files taken over from the folder.
+    pub pulled: Vec<String>,
+    /// Files copied to the folder.
+    pub pushed: Vec<String>,
+    /// Files changed on both sides and merged.
+    pub merged: Vec<String>,
+    /// Files changed on both sides in ways that do not merge.
+    pub conflicts: Vec<String>,
+}
+
+impl SyncReport {
+    /// Files whose copy in the data directory changed.
+    pub fn changed_here(&self) -> impl Iterator<Item = &str> {
+        self.pulled.iter().chain(&self.merged).map(String::as_str)
+    }
+}
+
+/// Which side wins a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    KeepLocal,
+    UseSynced,
+}
+
+/// The profile a synced file holds, if it is a profile.
+pub fn profile_id(file: &str) -> Option<ProfileId> {
+    let name = file.strip_prefix(PROFILES_DIR)?.strip_prefix('/')?;
+    name.strip_suffix(".json")?.parse().ok().map(ProfileId)
+}
+
+/// Latest modification time of the synced files on both sides, to notice changes by polling.
+pub fn modified(folder: &Path) -> Option<SystemTime> {
+    let data_dir = paths::data_dir().ok()?;
+    let mut latest = None;
+    for root in [folder, data_dir.as_path()] {
+        for path in [root.to_path_buf(), root.join(SETTINGS_FILE)] {
+            bump(&mut latest, &path);
+        }
+        let dir = root.join(PROFILES_DIR);
+        bump(&mut latest, &dir);
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            bump(&mut latest, &entry.path());
+        }
+    }
+    latest
+}
+
+fn bump(latest: &mut Option<SystemTime>, path: &Path) {
+    if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+        *latest = Some(latest.map_or(modified, |l| l.max(modified)));
+    }
+}
+
+/// Brings the data directory and `folder` in sync.
+pub fn sync(folder: &Path) -> anyhow::Result<SyncReport> {
+    if !folder.is_dir() {
+        anyhow::bail!("[Sync] {} is not a folder", folder.display());
+    }
+    let base = base_dir(folder)?;
+    let mut report = SyncReport::default();
+
+    for file in synced_files(folder, &base)? {
+        let local = read_local(&file)?;
+        let synced = read_file(&folder.join(&file))?;
+        let last = read_file(&base.join(&file))?;
+        if local == synced {
+            if last != local {
+                write_file(&base.join(&file), local.as_deref())?;
+            }
+        } else if local == last && (synced.is_some() || file != SETTINGS_FILE) {
+            write_local(&file, synced.as_deref())?;
+            write_file(&base.join(&file), synced.as_deref())?;
+            report.pulled.push(file);
+        } else if synced == last || (synced.is_none() && file == SETTINGS_FILE) {
+            // Settings removed from the folder are put back rather than reset here.
+            write_file(&folder.join(&file), local.as_deref())?;
+            write_file(&base.join(&file), local.as_deref())?;
+            report.pushed.push(file);
+        } else if let Some(merged) = merge_files(&file, &last, &local, &synced)? {
+            write_local(&file, Some(&merged))?;
+            write_file(&folder.join(&file), Some(&merged))?;
+            write_file(&base.join(&file), Some(&merged))?;
+            report.merged.push(file);
+        } else {
+            report.conflicts.push(file);
+        }
+    }
+    Ok(report)
+}
+
+/// Settles a conflict over `file` by copying the winning side over the other.
+pub fn resolve(folder: &Path, file: &str, resolution: Resolution) -> anyhow::Result<()> {
+    let base = base_dir(folder)?;
+    let winner = match resolution {
+        Resolution::KeepLocal => {
+            let local = read_local(file)?;
+            write_file(&folder.join(file), local.as_deref())?;
+            local
+        }
+        Resolution::UseSynced => {
+            let synced = read_file(&folder.join(file))?;
+            if synced.is_none() && file == SETTINGS_FILE {
+                anyhow::bail!("[Sync] The sync folder has no settings");
+            }
+            write_local(file, synced.as_deref())?;
+            synced
+        }
+    };
+    write_file(&base.join(file), winner.as_deref())
+}
+
+/// The copies from the last sync with `folder`. Switching folders starts over, as the copies
+/// of the old one say nothing about the new one.
+fn base_dir(folder: &Path) -> anyhow::Result<PathBuf> {
+    let base = paths::data_dir()?.join("sync-base");
+    let marker = base.join(".folder");
+    let folder = folder.to_string_lossy();
+    if fs::read_to_string(&marker).ok().as_deref() != Some(&*folder) {
+        if base.exists() {
+            fs::remove_dir_all(&base).context("[Sync] Failed to reset the last sync")?;
+        }
+        json::write_bytes_atomic(&marker, folder.as_bytes())?;
+    }
+    Ok(base)
+}
+
+/// The settings file plus every profile on any side.
+fn synced_files(folder: &Path, base: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let mut files = BTreeSet::from([SETTINGS_FILE.to_string()]);
+    for root in [paths::data_dir()?.as_path(), folder, base] {
+        for entry in fs::read_dir(root.join(PROFILES_DIR)).into_iter().flatten() {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                files.insert(format!("{PROFILES_DIR}/{name}"));
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn read_file(path: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("[Sync] Failed to read {}", path.display())),
+    }
+}
+
+/// Writes `bytes` to `path`, or removes `path` for `None`.
+fn write_file(path: &Path, bytes: Option<&[u8]>) -> anyhow::Result<()> {
+    let res = match bytes {
+        Some(bytes) => json::write_bytes_atomic(path, bytes),
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        },
+    };
+    res.with_context(|| format!("[Sync] Failed to write {}", path.display()))
+}
+
+/// This machine's copy of `file`; for the settings, the synced part of them.
+fn read_local(file: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    if file != SETTINGS_FILE {
+        return read_file(&paths::data_dir()?.join(file));
+    }
+    if !settings::settings_path()?.exists() {
+        return Ok(None);
+    }
+    let mut value = serde_json::to_value(settings::load_settings()?)?;
+    for path in LOCAL_SETTINGS {
+        let (parent, key) = split_setting(&mut value, path);
+        if let Some(map) = parent.and_then(Value::as_object_mut) {
+            map.remove(key);
+        }
+    }
+    to_bytes(&value).map(Some)
+}
+
+/// Replaces this machine's copy of `file`, keeping the settings that stay on this machine.
+fn write_local(file: &str, bytes: Option<&[u8]>) -> anyhow::Result<()> {
+    if file != SETTINGS_FILE {
+        if let Some(bytes) = bytes {
+            serde_json::from_slice::<Profile>(bytes)
+                .with_context(|| format!("[Sync] {file} in the sync folder is not a profile"))?;
+        }
+        return write_file(&paths::data_dir()?.join(file), bytes);
+    }
+    let Some(bytes) = bytes else {
+        return Ok(());
+    };
+    let mut value: Value = serde_json::from_slice(bytes)
+        .context("[Sync] The settings in the sync folder are not valid JSON")?;
+    let mut current = serde_json::to_value(settings::load_settings()?)?;
+    for path in LOCAL_SETTINGS {
+        let (current, _) = split_setting(&mut current, path);
+        let (parent, key) = split_setting(&mut value, path);
+        let local = current.and_then(|c| c.get(key)).cloned();
+        if let (Some(map), Some(local)) = (parent.and_then(Value::as_object_mut), local) {
+            map.insert(key.to_string(), local);
+        }
+    }
+    let settings: Settings = serde_json::from_value(value)
+        .context("[Sync] The settings in the sync folder are not usable")?;
+    settings::save_settings(&settings)
+}
+
+/// The object holding the setting at the dotted `path` (if there is one), and its key there.
+fn split_setting<'a, 'p>(value: &'a mut Value, path: &'p str) -> (Option<&'a mut Value>, &'p str) {
+    match path.rsplit_once('.') {
+        Some((parent, key)) => (parent.split('.').try_fold(value, |v, k| v.get_mut(k)), key),
+        None => (Some(value), path),
+    }
+}
+
+/// Three-way merge of a file changed on both sides; `None` when the changes conflict.
+fn merge_files(
+    file: &str,
+    last: &Option<Vec<u8>>,
+    local: &Option<Vec<u8>>,
+    synced: &Option<Vec<u8>>,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    // A file removed on one side and changed on the other is for the user to settle.
+    let (Some(last), Some(local), Some(synced)) = (last, local, synced) else {
+        return Ok(None);
+    };
+    let parse = |bytes: &[u8]| serde_json::from_slice::<Value>(bytes).ok();
+    let (Some(last), Some(local), Some(synced)) = (parse(last), parse(local), parse(synced)) else {
+        return Ok(None);
+    };
+    let Some(Some(merged)) = merge(Some(&last), Some(&local), Some(&synced)) else {
+        return Ok(None);
+    };
+    if file == SETTINGS_FILE {
+        return to_bytes(&merged).map(Some);
+    }
+    // Written the way profiles are saved, so the merged file keeps its field order.
+    match serde_json::from_value::<Profile>(merged) {
+        Ok(profile) => to_bytes(&profile).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Three-way merge of JSON values, `None` standing for a missing value: parts only one side
+/// changed take that side's version, objects merge key by key and arrays of unchanged length
+/// item by item. The outer `None` means both sides changed a part in different ways.
+fn merge(
+    last: Option<&Value>,
+    local: Option<&Value>,
+    synced: Option<&Value>,
+) -> Option<Option<Value>> {
+    if local == synced || synced == last {
+        return Some(local.cloned());
+    }
+    if local == last {
+        return Some(synced.cloned());
+    }
+    match (last?, local?, synced?) {
+        (Value::Object(last), Value::Object(local), Value::Object(synced)) => {
+            let keys: BTreeSet<&String> = last
+                .keys()
+                .chain(local.keys())
+                .chain(synced.keys())
+                .collect();
+            let mut out = serde_json::Map::new();
+            for key in keys {
+                if let Some(value) = merge(last.get(key), local.get(key), synced.get(key))? {
+                    out.insert(key.clone(), value);
+                }
+            }
+            Some(Some(Value::Object(out)))
+        }
+        (Value::Array(last), Value::Array(local), Value::Array(synced))
+            if last.len() == local.len() && local.len() == synced.len() =>
+        {
+            let items = last.iter().zip(local).zip(synced);
+            let merged = items
+                .map(|((l, o), s)| merge(Some(l), Some(o), Some(s)).flatten())
+                .collect::<Option<Vec<_>>>()?;
+            Some(Some(Value::Array(merged)))
+        }
+        _ => None,
+    }
+}
+
+fn to_bytes<T: serde::Serialize>(value: &T) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = serde_json::to_vec_pretty(value)?;
+    bytes.push(b'\n');
+    Ok(bytes)
+}
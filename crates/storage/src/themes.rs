@@ -0,0 +1,146 @@
+//! User themes: one JSON file per theme in the `themes` folder of the data directory.
+//!
+//! A theme file names the five palette colors the UI derives all its shades from, as `[r, g, b]`
+//! arrays, and optionally the corner radii of the app's own widgets:
+//!
+//! ```json
+//! {
+//!   "name": "Solarized",
+//!   "colors": {
+//!     "background": [0, 43, 54],
+//!     "text": [238, 232, 213],
+//!     "primary": [38, 139, 210],
+//!     "success": [133, 153, 0],
+//!     "danger": [220, 50, 47]
+//!   },
+//!   "radii": { "key": 4, "card": 6 }
+//! }
+//! ```
+//!
+//! Themes are identified by their file name without the extension.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeFile {
+    /// Shown in the theme picker; the file name when missing.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub colors: ThemeColors,
+    #[serde(default)]
+    pub radii: ThemeRadii,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThemeColors {
+    pub background: [u8; 3],
+    pub text: [u8; 3],
+    pub primary: [u8; 3],
+    pub success: [u8; 3],
+    pub danger: [u8; 3],
+}
+
+/// Corner radii, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThemeRadii {
+    /// Keys of the deck preview.
+    #[serde(default = "default_key_radius")]
+    pub key: f32,
+    /// Cards and callouts.
+    #[serde(default = "default_card_radius")]
+    pub card: f32,
+    /// Body of the deck preview; by default it follows the device model.
+    #[serde(default)]
+    pub deck: Option<f32>,
+}
+
+impl Default for ThemeRadii {
+    fn default() -> Self {
+        Self {
+            key: default_key_radius(),
+            card: default_card_radius(),
+            deck: None,
+        }
+    }
+}
+
+fn default_key_radius() -> f32 {
+    8.0
+}
+
+fn default_card_radius() -> f32 {
+    10.0
+}
+
+/// A theme file found in [`themes_dir`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserTheme {
+    /// File name without the extension.
+    pub id: String,
+    pub theme: ThemeFile,
+}
+
+impl UserTheme {
+    pub fn name(&self) -> &str {
+        self.theme.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+pub fn themes_dir() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("themes"))
+}
+
+/// All readable theme files, sorted by name; a missing folder yields none.
+pub fn list_themes() -> anyhow::Result<Vec<UserTheme>> {
+    let dir = themes_dir()?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut out = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        match read_theme(&path) {
+            Ok(theme) => out.push(UserTheme {
+                id: id.to_string(),
+                theme,
+            }),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "skipping unreadable theme")
+            }
+        }
+    }
+
+    out.sort_by_key(|t| t.name().to_lowercase());
+    Ok(out)
+}
+
+fn read_theme(path: &Path) -> anyhow::Result<ThemeFile> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Latest modification time of the themes folder and its files, to notice edits cheaply.
+pub fn themes_modified() -> Option<SystemTime> {
+    let dir = themes_dir().ok()?;
+    let mut latest = fs::metadata(&dir).and_then(|m| m.modified()).ok()?;
+    for entry in fs::read_dir(&dir).ok()?.flatten() {
+        if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+            latest = latest.max(modified);
+        }
+    }
+    Some(latest)
+}
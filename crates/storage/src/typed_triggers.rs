@@ -0,0 +1,42 @@
+//! Typed triggers (text expander), stored as `typed_triggers.json` in the data directory.
+//!
+//! Each trigger maps an abbreviation typed anywhere on the system to an action. Watching the
+//! keyboard is opt-in (see [`crate::settings::TextExpanderSettings`]).
+
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypedTrigger {
+    /// Unique within the file; identifies the trigger in the UI and in invocations.
+    pub id: u64,
+    /// Text that fires the action once typed, e.g. `;sig`.
+    pub abbreviation: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Delete the typed abbreviation (with backspaces) before the action runs.
+    #[serde(default = "default_true")]
+    pub erase: bool,
+    pub action: ActionBinding,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn typed_triggers_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("typed_triggers.json"))
+}
+
+/// Load all typed triggers; a missing file yields none.
+pub fn load_typed_triggers() -> anyhow::Result<Vec<TypedTrigger>> {
+    json::read_or_default(&typed_triggers_path()?)
+}
+
+pub fn save_typed_triggers(triggers: &[TypedTrigger]) -> anyhow::Result<()> {
+    json::write_atomic(&typed_triggers_path()?, triggers)
+}
@@ -0,0 +1,123 @@
+//! How often and for how long the bindings of each profile ran, stored as `usage.json` in the data
+//! directory.
+//!
+//! Only the device's controls are counted: runs from schedules, webhooks, typed triggers, file and
+//! process watchers, MIDI and OSC triggers, test runs and dry runs are in the activity log (see
+//! [`crate::activity`]) but not here.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+/// Profile id -> usage of its bindings.
+pub type UsageFile = BTreeMap<u64, Vec<BindingUsage>>;
+
+/// The control a binding belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UsageControl {
+    Key { index: u8 },
+    Dial { index: u8 },
+    TouchStrip,
+}
+
+impl std::fmt::Display for UsageControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key { index } => write!(f, "Key {index}"),
+            Self::Dial { index } => write!(f, "Dial {index}"),
+            Self::TouchStrip => f.write_str("Touch strip"),
+        }
+    }
+}
+
+/// Totals of one binding: a control and how it was used, e.g. key 3 pressed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingUsage {
+    pub control: UsageControl,
+    /// How the control was used, as in the activity log (e.g. `press` or `rotate +1`).
+    pub event: String,
+    pub count: u64,
+    /// Time all runs took together, and the longest run.
+    pub total_ms: u64,
+    pub max_ms: u64,
+    /// When it last ran, in milliseconds since the Unix epoch.
+    pub last_at_ms: u64,
+}
+
+impl BindingUsage {
+    pub fn average_ms(&self) -> u64 {
+        self.total_ms.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+/// One run of a binding of a profile, as the engine reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingUse {
+    pub profile: u64,
+    pub control: UsageControl,
+    pub event: String,
+    pub at_ms: u64,
+    pub duration_ms: u64,
+}
+
+pub fn usage_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("usage.json"))
+}
+
+/// Load the totals of all profiles; a missing file yields none.
+pub fn load_usage() -> anyhow::Result<UsageFile> {
+    json::read_or_default(&usage_path()?)
+}
+
+pub fn save_usage(file: &UsageFile) -> anyhow::Result<()> {
+    json::write_atomic(&usage_path()?, file)
+}
+
+/// Add the run `used` to the totals of its binding.
+pub fn record(file: &mut UsageFile, used: BindingUse) {
+    let usage = BindingUsage {
+        control: used.control,
+        event: used.event,
+        count: 1,
+        total_ms: used.duration_ms,
+        max_ms: used.duration_ms,
+        last_at_ms: used.at_ms,
+    };
+    merge(file, UsageFile::from([(used.profile, vec![usage])]));
+}
+
+/// Add the totals of `other` to `file`, e.g. runs counted while the file was being read.
+pub fn merge(file: &mut UsageFile, other: UsageFile) {
+    for (profile, bindings) in other {
+        let into = file.entry(profile).or_default();
+        for b in bindings {
+            match into
+                .iter_mut()
+                .find(|i| i.control == b.control && i.event == b.event)
+            {
+                Some(i) => {
+                    i.count = i.count.saturating_add(b.count);
+                    i.total_ms = i.total_ms.saturating_add(b.total_ms);
+                    i.max_ms = i.max_ms.max(b.max_ms);
+                    i.last_at_ms = i.last_at_ms.max(b.last_at_ms);
+                }
+                None => into.push(b),
+            }
+        }
+    }
+}
+
+/// Runs per key, all of its bindings together; keys that never ran are absent.
+pub fn key_counts(bindings: &[BindingUsage]) -> BTreeMap<u8, u64> {
+    let mut counts = BTreeMap::new();
+    for b in bindings {
+        if let UsageControl::Key { index } = b.control {
+            *counts.entry(index).or_default() += b.count;
+        }
+    }
+    counts
+}
@@ -0,0 +1,626 @@
+//! Checks of hand-edited profile files, with diagnostics pointing at the offending line and field.
+//!
+//! Loading a profile only reports the first serde error, which rarely says which key or binding is
+//! wrong. [`validate_profile`] walks the file instead and reports every problem it recognizes:
+//! missing or misspelled fields, key counts that don't add up, bindings of the wrong shape and
+//! plugins that are not installed. Anything else that keeps the file from loading is reported as
+//! the serde error, still with its line and column.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::profiles::{self, Profile, PROFILE_SCHEMA_VERSION};
+use crate::surfaces;
+
+/// Key counts of the supported devices (Pedal, Mini, Plus/Neo, original/MK.2, XL).
+const DEVICE_KEY_COUNTS: &[u8] = &[3, 6, 8, 15, 32];
+
+const PROFILE_FIELDS: &[&str] = &[
+    "version",
+    "id",
+    "name",
+    "key_count",
+    "keys",
+    "dials",
+    "touch_strip",
+    "brightness",
+    "base_profile",
+    "surface",
+    "file_watchers",
+];
+const KEY_FIELDS: &[&str] = &["label", "action", "appearance", "hotkey"];
+const DIAL_FIELDS: &[&str] = &["label", "press", "rotate", "appearance"];
+const FILE_WATCHER_FIELDS: &[&str] = &["id", "path", "recursive", "changes", "enabled", "action"];
+const TOUCH_STRIP_BINDINGS: &[&str] = &[
+    "tap",
+    "long_press",
+    "drag",
+    "drag_left",
+    "drag_right",
+    "swipe_left",
+    "swipe_right",
+];
+const APPEARANCE_FIELDS: &[&str] = &[
+    "background",
+    "icon_path",
+    "text",
+    "entity_id",
+    "text_size",
+    "flash",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The profile does not load.
+    Error,
+    /// The profile loads, but probably not as intended.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Field the problem is in, e.g. `keys[3].action`; empty when unknown.
+    pub path: String,
+    /// Where the field's value starts (1-based), when known.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " at line {line}, column {column}")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in `{}`", self.path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Check the profile file at `path`; see [`validate_profile`].
+pub fn validate_profile_file(
+    path: &Path,
+    plugin_installed: &dyn Fn(&str) -> bool,
+) -> anyhow::Result<Vec<Diagnostic>> {
+    Ok(validate_profile(&std::fs::read(path)?, plugin_installed))
+}
+
+/// Check the raw contents of a profile file, errors and warnings in file order.
+///
+/// `plugin_installed` tells whether a plugin id referenced by a binding is installed.
+pub fn validate_profile(raw: &[u8], plugin_installed: &dyn Fn(&str) -> bool) -> Vec<Diagnostic> {
+    let text = match std::str::from_utf8(raw) {
+        Ok(text) => text,
+        Err(e) => {
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                path: String::new(),
+                line: None,
+                column: None,
+                message: format!(
+                    "not UTF-8 text (invalid byte at offset {})",
+                    e.valid_up_to()
+                ),
+            }]
+        }
+    };
+    let value: Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => return vec![serde_error(&e)],
+    };
+
+    let mut checker = Checker {
+        positions: value_positions(text),
+        plugin_installed,
+        diagnostics: vec![],
+    };
+    checker.profile(&value);
+
+    // Whatever the checks above don't cover still shows up when deserializing.
+    if !checker.has_errors() {
+        if let Err(e) = serde_json::from_str::<Profile>(text) {
+            checker.diagnostics.push(serde_error(&e));
+        }
+    }
+
+    let mut out = checker.diagnostics;
+    out.sort_by_key(|d| (d.line, d.column));
+    out
+}
+
+fn serde_error(e: &serde_json::Error) -> Diagnostic {
+    // The message ends with the position, which the diagnostic carries on its own.
+    let message = e.to_string();
+    let message = match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    };
+    Diagnostic {
+        severity: Severity::Error,
+        path: String::new(),
+        line: (e.line() > 0).then_some(e.line()),
+        column: (e.line() > 0).then_some(e.column()),
+        message,
+    }
+}
+
+struct Checker<'a> {
+    positions: HashMap<String, (usize, usize)>,
+    plugin_installed: &'a dyn Fn(&str) -> bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, severity: Severity, path: &str, message: String) {
+        let position = self.positions.get(path).copied();
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: path.to_string(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            message,
+        });
+    }
+
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.push(Severity::Error, path, message.into());
+    }
+
+    fn warning(&mut self, path: &str, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message.into());
+    }
+
+    fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    fn profile(&mut self, value: &Value) {
+        let Some(obj) = value.as_object() else {
+            self.error("", "a profile must be a JSON object");
+            return;
+        };
+        self.unknown_fields(obj, "", PROFILE_FIELDS);
+
+        match obj.get("version").map(Value::as_u64) {
+            None => self.error("", "missing field `version`"),
+            Some(None) => self.error("version", "expected a number"),
+            Some(Some(v)) if v > u64::from(PROFILE_SCHEMA_VERSION) => self.error(
+                "version",
+                format!("version {v} is newer than this app supports ({PROFILE_SCHEMA_VERSION})"),
+            ),
+            Some(Some(_)) => {}
+        }
+        match obj.get("id").map(Value::as_u64) {
+            None => self.error("", "missing field `id`"),
+            Some(None) => self.error("id", "expected a positive whole number"),
+            Some(Some(_)) => {}
+        }
+        match obj.get("name") {
+            None => self.error("", "missing field `name`"),
+            Some(Value::String(_)) => {}
+            Some(_) => self.error("name", "expected a string"),
+        }
+
+        let key_count = match obj.get("key_count").map(Value::as_u64) {
+            None => {
+                self.error("", "missing field `key_count`");
+                None
+            }
+            Some(Some(n)) if n <= u64::from(u8::MAX) => Some(n as usize),
+            Some(_) => {
+                self.error("key_count", "expected a number of keys from 0 to 255");
+                None
+            }
+        };
+        // Surface profiles have a key for every cell of the surface grid.
+        let surface = obj.get("surface").and_then(Value::as_u64);
+        if let Some(n) = key_count.filter(|_| surface.is_none()) {
+            if !DEVICE_KEY_COUNTS.contains(&(n as u8)) {
+                self.warning(
+                    "key_count",
+                    format!("no supported device has {n} keys; the profile won't match a device"),
+                );
+            }
+        }
+
+        match obj.get("keys") {
+            None => self.error("", "missing field `keys`"),
+            Some(Value::Array(keys)) => {
+                for (i, key) in keys.iter().enumerate() {
+                    self.key(key, &format!("keys[{i}]"));
+                }
+                match key_count {
+                    Some(n) if keys.len() > n => self.warning(
+                        &format!("keys[{n}]"),
+                        format!(
+                            "key_count is {n} but the list has {}; keys past the first {n} are \
+                             dropped",
+                            keys.len()
+                        ),
+                    ),
+                    Some(n) if keys.len() < n => self.warning(
+                        "keys",
+                        format!(
+                            "key_count is {n} but the list has {}; the missing keys stay empty",
+                            keys.len()
+                        ),
+                    ),
+                    _ => {}
+                }
+            }
+            Some(_) => self.error("keys", "expected a list of keys"),
+        }
+
+        match obj.get("dials") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(dials)) => {
+                for (i, dial) in dials.iter().enumerate() {
+                    self.dial(dial, &format!("dials[{i}]"));
+                }
+            }
+            Some(_) => self.error("dials", "expected a list of dials"),
+        }
+
+        match obj.get("touch_strip") {
+            None | Some(Value::Null) => {}
+            Some(Value::Object(strip)) => {
+                let known: Vec<&str> = TOUCH_STRIP_BINDINGS
+                    .iter()
+                    .copied()
+                    .chain(["appearance"])
+                    .collect();
+                self.unknown_fields(strip, "touch_strip", &known);
+                for slot in TOUCH_STRIP_BINDINGS {
+                    if let Some(binding) = strip.get(*slot) {
+                        self.binding(binding, &format!("touch_strip.{slot}"));
+                    }
+                }
+                self.appearance(strip, "touch_strip");
+            }
+            Some(_) => self.error("touch_strip", "expected an object"),
+        }
+
+        match obj.get("file_watchers") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(watchers)) => {
+                for (i, watcher) in watchers.iter().enumerate() {
+                    self.file_watcher(watcher, &format!("file_watchers[{i}]"));
+                }
+            }
+            Some(_) => self.error("file_watchers", "expected a list of file watchers"),
+        }
+
+        if let Some(id) = obj.get("base_profile").and_then(Value::as_u64) {
+            let exists = profiles::profiles_dir()
+                .map(|dir| dir.join(format!("{id}.json")).is_file())
+                .unwrap_or(true);
+            if !exists {
+                self.warning(
+                    "base_profile",
+                    format!("there is no profile with id {id}; no keys are inherited"),
+                );
+            }
+        }
+
+        if let (Some(id), Ok(surfaces)) = (surface, surfaces::load_surfaces()) {
+            match surfaces.iter().find(|s| s.id == id) {
+                None => self.warning(
+                    "surface",
+                    format!("there is no surface with id {id}; the profile won't match a device"),
+                ),
+                Some(s) if key_count.is_some_and(|n| n != usize::from(s.key_count())) => self
+                    .warning(
+                        "key_count",
+                        format!("{} has {} keys", s.name, s.key_count()),
+                    ),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn key(&mut self, value: &Value, path: &str) {
+        let Some(obj) = value.as_object() else {
+            self.error(path, "expected a key: an object with at least a `label`");
+            return;
+        };
+        self.unknown_fields(obj, path, KEY_FIELDS);
+        if !obj.contains_key("label") {
+            self.error(path, "missing field `label` (use \"\" for no label)");
+        }
+        if let Some(binding) = obj.get("action") {
+            self.binding(binding, &format!("{path}.action"));
+        }
+        self.appearance(obj, path);
+    }
+
+    fn dial(&mut self, value: &Value, path: &str) {
+        let Some(obj) = value.as_object() else {
+            self.error(path, "expected a dial: an object");
+            return;
+        };
+        self.unknown_fields(obj, path, DIAL_FIELDS);
+        for slot in ["press", "rotate"] {
+            if let Some(binding) = obj.get(slot) {
+                self.binding(binding, &format!("{path}.{slot}"));
+            }
+        }
+        self.appearance(obj, path);
+    }
+
+    fn file_watcher(&mut self, value: &Value, path: &str) {
+        let Some(obj) = value.as_object() else {
+            self.error(
+                path,
+                "expected a file watcher: an object with `path` and `action`",
+            );
+            return;
+        };
+        self.unknown_fields(obj, path, FILE_WATCHER_FIELDS);
+        match obj.get("path") {
+            None => self.error(path, "missing field `path`"),
+            Some(Value::String(p)) if !std::path::Path::new(p).is_absolute() => self.warning(
+                &format!("{path}.path"),
+                "not an absolute path; it is looked up from wherever the app was started",
+            ),
+            Some(Value::String(_)) => {}
+            Some(_) => self.error(&format!("{path}.path"), "expected a string"),
+        }
+        match obj.get("action") {
+            None => self.error(path, "missing field `action`"),
+            Some(binding) => self.binding(binding, &format!("{path}.action")),
+        }
+    }
+
+    fn appearance(&mut self, parent: &Map<String, Value>, path: &str) {
+        let path = format!("{path}.appearance");
+        match parent.get("appearance") {
+            None | Some(Value::Null) => {}
+            Some(Value::Object(obj)) => self.unknown_fields(obj, &path, APPEARANCE_FIELDS),
+            Some(_) => self.error(&path, "expected an object"),
+        }
+    }
+
+    /// A binding slot: empty, a plugin action or a builtin action (possibly nesting others).
+    fn binding(&mut self, value: &Value, path: &str) {
+        let obj = match value {
+            Value::Null => return,
+            Value::Object(obj) => obj,
+            _ => {
+                self.error(
+                    path,
+                    "expected an action: an object with `plugin_id` and `action_id`, or with \
+                     `builtin`",
+                );
+                return;
+            }
+        };
+
+        if let Some(plugin_id) = obj.get("plugin_id") {
+            if let Err(e) = serde_json::from_value::<actions::PluginActionBinding>(value.clone()) {
+                self.error(path, format!("invalid plugin action: {e}"));
+            } else if let Some(id) = plugin_id.as_str() {
+                if !(self.plugin_installed)(id) {
+                    self.warning(
+                        &format!("{path}.plugin_id"),
+                        format!("plugin `{id}` is not installed"),
+                    );
+                }
+            }
+            return;
+        }
+
+        let Some(builtin) = obj.get("builtin") else {
+            self.error(
+                path,
+                "an action needs either `plugin_id` (a plugin action) or `builtin` (a builtin \
+                 action)",
+            );
+            return;
+        };
+
+        // Check nested bindings first; their errors say more than the outer one.
+        let errors_before = self.diagnostics.len();
+        match builtin.as_str() {
+            Some("macro") => {
+                if let Some(steps) = obj.get("steps").and_then(Value::as_array) {
+                    for (i, step) in steps.iter().enumerate() {
+                        let step_path = format!("{path}.steps[{i}]");
+                        match step.get("action") {
+                            Some(action) if step.is_object() => {
+                                self.binding(action, &format!("{step_path}.action"))
+                            }
+                            _ => self.error(
+                                &step_path,
+                                "expected a macro step: an object with an `action`",
+                            ),
+                        }
+                    }
+                }
+            }
+            Some("conditional") => {
+                for branch in ["then", "otherwise"] {
+                    if let Some(binding) = obj.get(branch) {
+                        self.binding(binding, &format!("{path}.{branch}"));
+                    }
+                }
+            }
+            _ => {}
+        }
+        let nested_errors = self.diagnostics[errors_before..]
+            .iter()
+            .any(|d| d.severity == Severity::Error);
+        if !nested_errors {
+            if let Err(e) = serde_json::from_value::<actions::BuiltinAction>(value.clone()) {
+                self.error(path, format!("invalid builtin action: {e}"));
+            }
+        }
+    }
+
+    fn unknown_fields(&mut self, obj: &Map<String, Value>, path: &str, known: &[&str]) {
+        for name in obj.keys().filter(|k| !known.contains(&k.as_str())) {
+            self.warning(
+                &join(path, name),
+                format!(
+                    "unknown field `{name}` is ignored; expected one of: {}",
+                    known.join(", ")
+                ),
+            );
+        }
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{path}.{field}")
+    }
+}
+
+/// Line and column (1-based) at which each value in `text` starts, by path (`keys[2].action`).
+///
+/// `text` must be valid JSON.
+fn value_positions(text: &str) -> HashMap<String, (usize, usize)> {
+    let mut scanner = Scanner {
+        bytes: text.as_bytes(),
+        pos: 0,
+        line: 1,
+        column: 1,
+        out: HashMap::new(),
+    };
+    scanner.value(String::new());
+    scanner.out
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+    column: usize,
+    out: HashMap<String, (usize, usize)>,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if b & 0xC0 != 0x80 {
+            // Columns count characters, not UTF-8 continuation bytes.
+            self.column += 1;
+        }
+        Some(b)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self, path: String) {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.out.insert(path.clone(), (self.line, self.column));
+                self.bump();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => break,
+                        Some(b'}') => {
+                            self.bump();
+                            break;
+                        }
+                        Some(b',') => {
+                            self.bump();
+                        }
+                        Some(_) => {
+                            let name = self.string();
+                            self.skip_whitespace();
+                            self.bump(); // ':'
+                            self.value(join(&path, &name));
+                        }
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.out.insert(path.clone(), (self.line, self.column));
+                self.bump();
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => break,
+                        Some(b']') => {
+                            self.bump();
+                            break;
+                        }
+                        Some(b',') => {
+                            self.bump();
+                        }
+                        Some(_) => {
+                            self.value(format!("{path}[{index}]"));
+                            index += 1;
+                        }
+                    }
+                }
+            }
+            Some(b'"') => {
+                self.out.insert(path, (self.line, self.column));
+                self.string();
+            }
+            _ => {
+                self.out.insert(path, (self.line, self.column));
+                while let Some(b) = self.peek() {
+                    if matches!(b, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                        break;
+                    }
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    /// Skip a string literal, returning its raw contents (escapes are kept as written).
+    fn string(&mut self) -> String {
+        self.bump(); // '"'
+        let start = self.pos;
+        let mut end = start;
+        while let Some(b) = self.bump() {
+            match b {
+                b'\\' => {
+                    self.bump();
+                }
+                b'"' => break,
+                _ => {}
+            }
+            end = self.pos;
+        }
+        String::from_utf8_lossy(&self.bytes[start..end]).into_owned()
+    }
+}
@@ -0,0 +1,19 @@
+//! Persisted action variables, stored as `variables.json` in the data directory.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::{json, paths};
+
+pub fn variables_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("variables.json"))
+}
+
+/// Load all variables; a missing file yields none.
+pub fn load_variables() -> anyhow::Result<BTreeMap<String, String>> {
+    json::read_or_default(&variables_path()?)
+}
+
+pub fn save_variables(variables: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    json::write_atomic(&variables_path()?, variables)
+}
@@ -0,0 +1,33 @@
+//! Webhook routes, stored as `webhooks.json` in the data directory.
+//!
+//! Each webhook maps a request path (`/hooks/<path>` on the listener configured in
+//! [`crate::settings::WebhookSettings`]) to an action.
+
+use std::path::PathBuf;
+
+use actions::ActionBinding;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, paths};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    /// Unique within the file; identifies the webhook in the UI and in invocations.
+    pub id: u64,
+    /// Path below `/hooks/`, without leading or trailing slashes (e.g. `build-failed`).
+    pub path: String,
+    pub action: ActionBinding,
+}
+
+pub fn webhooks_path() -> anyhow::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("webhooks.json"))
+}
+
+/// Load all webhooks; a missing file yields none.
+pub fn load_webhooks() -> anyhow::Result<Vec<Webhook>> {
+    json::read_or_default(&webhooks_path()?)
+}
+
+pub fn save_webhooks(webhooks: &[Webhook]) -> anyhow::Result<()> {
+    json::write_atomic(&webhooks_path()?, webhooks)
+}
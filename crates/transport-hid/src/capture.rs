@@ -0,0 +1,232 @@
+//! Debug capture of HID traffic, and reading captures back for replay.
+//!
+//! Setting `RIVERDECK_HID_CAPTURE` to a file path makes every handle opened through
+//! [`crate::HidContext`] record what it reads and writes there, with a timestamp. The file is
+//! plain text, one record per line, so captures can be attached to bug reports and edited:
+//!
+//! ```text
+//! # open 1 0fd9:0084 Stream Deck +
+//! 1520344 1 read 01 00 08 00 00 01 00 00 00 00 00 00
+//! 1520871 1 write 02 07 ...
+//! ```
+//!
+//! Records are the microseconds since the capture started, the handle they belong to (numbered
+//! in the order handles were opened), `read`, `write` or `feature` (a feature report sent), and
+//! the bytes in hex. Lines starting with `#` describe the opened handles.
+
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+/// Environment variable naming the file to capture HID traffic to.
+pub const CAPTURE_ENV: &str = "RIVERDECK_HID_CAPTURE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// An input report read from the device.
+    Read,
+    /// An output report written to the device.
+    Write,
+    /// A feature report sent to the device.
+    Feature,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Read => "read",
+            Direction::Write => "write",
+            Direction::Feature => "feature",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(Direction::Read),
+            "write" => Some(Direction::Write),
+            "feature" => Some(Direction::Feature),
+            _ => None,
+        }
+    }
+}
+
+/// A handle opened while capturing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedDevice {
+    pub handle: u32,
+    /// USB vendor and product id, when the handle was opened from a discovered device.
+    pub ids: Option<(u16, u16)>,
+    /// Product name or device path.
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedReport {
+    /// Time since the capture started.
+    pub at: Duration,
+    pub handle: u32,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capture {
+    pub devices: Vec<CapturedDevice>,
+    /// Every record, in the order they were made.
+    pub reports: Vec<CapturedReport>,
+}
+
+impl Capture {
+    /// The input reports read through `handle`.
+    pub fn reads(&self, handle: u32) -> impl Iterator<Item = &CapturedReport> {
+        self.reports
+            .iter()
+            .filter(move |r| r.handle == handle && r.direction == Direction::Read)
+    }
+}
+
+/// Read a capture file written while `RIVERDECK_HID_CAPTURE` was set.
+pub fn read_capture(path: &Path) -> anyhow::Result<Capture> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut capture = Capture::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let parsed = match line.strip_prefix('#') {
+            Some(comment) => parse_open(comment).map(|d| capture.devices.extend(d)),
+            None => parse_record(line).map(|r| capture.reports.push(r)),
+        };
+        parsed.with_context(|| format!("{}:{}: invalid line", path.display(), i + 1))?;
+    }
+    Ok(capture)
+}
+
+/// `open <handle> [<vid>:<pid>] <description>`; other comments are `None`.
+fn parse_open(comment: &str) -> anyhow::Result<Option<CapturedDevice>> {
+    let Some(rest) = comment.trim().strip_prefix("open ") else {
+        return Ok(None);
+    };
+    let (handle, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let handle = handle.parse().context("invalid handle number")?;
+    let (ids, description) = match rest.split_once(' ') {
+        Some((ids, description)) => match parse_ids(ids) {
+            Some(ids) => (Some(ids), description),
+            None => (None, rest),
+        },
+        None => (parse_ids(rest), rest),
+    };
+    Ok(Some(CapturedDevice {
+        handle,
+        ids,
+        description: description.to_string(),
+    }))
+}
+
+fn parse_ids(s: &str) -> Option<(u16, u16)> {
+    let (vendor, product) = s.split_once(':')?;
+    Some((
+        u16::from_str_radix(vendor, 16).ok()?,
+        u16::from_str_radix(product, 16).ok()?,
+    ))
+}
+
+fn parse_record(line: &str) -> anyhow::Result<CapturedReport> {
+    let mut fields = line.split_whitespace();
+    let mut next = |what: &str| fields.next().with_context(|| format!("missing {what}"));
+    let at = next("timestamp")?.parse().context("invalid timestamp")?;
+    let handle = next("handle")?.parse().context("invalid handle number")?;
+    let direction = next("direction")?;
+    let direction =
+        Direction::parse(direction).with_context(|| format!("unknown direction {direction}"))?;
+    let data = fields
+        .map(|byte| u8::from_str_radix(byte, 16))
+        .collect::<Result<_, _>>()
+        .context("invalid hex byte")?;
+    Ok(CapturedReport {
+        at: Duration::from_micros(at),
+        handle,
+        direction,
+        data,
+    })
+}
+
+/// Where the traffic of this process goes, if capturing.
+struct Recorder {
+    started: Instant,
+    out: Mutex<LineWriter<File>>,
+    next_handle: AtomicU32,
+}
+
+fn recorder() -> Option<&'static Recorder> {
+    static RECORDER: OnceLock<Option<Recorder>> = OnceLock::new();
+    RECORDER
+        .get_or_init(|| {
+            let path = std::env::var_os(CAPTURE_ENV).filter(|p| !p.is_empty())?;
+            let file = match File::create(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!(path = ?path, error = %e, "cannot create the HID capture file");
+                    return None;
+                }
+            };
+            info!(path = ?path, "capturing HID traffic");
+            Some(Recorder {
+                started: Instant::now(),
+                out: Mutex::new(LineWriter::new(file)),
+                next_handle: AtomicU32::new(1),
+            })
+        })
+        .as_ref()
+}
+
+/// Records the traffic of one handle.
+pub(crate) struct Tap {
+    recorder: &'static Recorder,
+    handle: u32,
+}
+
+impl Tap {
+    /// Start recording a newly opened handle, if capturing is on.
+    pub(crate) fn open(ids: Option<(u16, u16)>, description: &str) -> Option<Self> {
+        let recorder = recorder()?;
+        let handle = recorder.next_handle.fetch_add(1, Ordering::Relaxed);
+        let ids = ids
+            .map(|(vendor, product)| format!("{vendor:04x}:{product:04x} "))
+            .unwrap_or_default();
+        recorder.write_line(&format!("# open {handle} {ids}{description}"));
+        Some(Self { recorder, handle })
+    }
+
+    pub(crate) fn record(&self, direction: Direction, data: &[u8]) {
+        let mut line = format!(
+            "{} {} {}",
+            self.recorder.started.elapsed().as_micros(),
+            self.handle,
+            direction.as_str()
+        );
+        for byte in data {
+            line.push_str(&format!(" {byte:02x}"));
+        }
+        self.recorder.write_line(&line);
+    }
+}
+
+impl Recorder {
+    fn write_line(&self, line: &str) {
+        let Ok(mut out) = self.out.lock() else {
+            return;
+        };
+        if let Err(e) = writeln!(out, "{line}") {
+            warn!(error = %e, "failed to write to the HID capture file");
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Permission diagnostics for `/dev/hidraw*` access on Linux and IOKit access on macOS.
+//!
+//! The most common bring-up failure is a missing udev rule: the device enumerates but opening
+//! its hidraw node fails with `EACCES`. `diagnose()` probes the nodes directly and returns a
+//! structured remediation that UIs can present as a guided fix (`install_udev_rule`).
+//!
+//! On macOS, opening an HID device fails with `kIOReturnNotPermitted` until the user allows the
+//! app under Input Monitoring; there `diagnose()` opens every Elgato interface through `hidapi`
+//! and points to that setting.
+
+use std::path::{Path, PathBuf};
+
+/// Elgato's USB vendor id (all Stream Deck models).
+pub const ELGATO_VENDOR_ID: u16 = 0x0fd9;
+
+/// Where `install_udev_rule` writes the rule.
+pub const UDEV_RULE_PATH: &str = "/etc/udev/rules.d/70-riverdeck-redux.rules";
+
+/// Grants the active seat user access to Stream Deck devices.
+pub const UDEV_RULE: &str = "\
+# RiverDeck-Redux: allow the logged-in user to access Elgato Stream Deck devices
+SUBSYSTEM==\"usb\", ATTRS{idVendor}==\"0fd9\", TAG+=\"uaccess\"
+KERNEL==\"hidraw*\", ATTRS{idVendor}==\"0fd9\", TAG+=\"uaccess\"
+";
+
+/// Opens the Input Monitoring pane of the macOS privacy settings.
+pub const INPUT_MONITORING_SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_ListenEvent";
+
+const UDEV_RULE_DIRS: &[&str] = &[
+    "/etc/udev/rules.d",
+    "/run/udev/rules.d",
+    "/lib/udev/rules.d",
+    "/usr/lib/udev/rules.d",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HidrawIssue {
+    /// Opening the hidraw node failed with `EACCES` (on macOS: IOKit refused access).
+    PermissionDenied { node: PathBuf },
+    /// Opening failed for another reason (device busy, unplugged mid-probe, ...).
+    OpenFailed { node: PathBuf, error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remediation {
+    /// No udev rule for the vendor exists; install one (see `install_udev_rule`).
+    InstallUdevRule { rule_path: PathBuf, rule: String },
+    /// A rule exists but the device was plugged in before it took effect.
+    ReplugDevice,
+    /// macOS refused access: the app needs to be allowed under Input Monitoring (the URL
+    /// opens those settings).
+    GrantInputMonitoring { settings_url: String },
+    /// Nothing we can automate; show the message.
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidrawDiagnosis {
+    pub issue: HidrawIssue,
+    pub remediation: Remediation,
+    /// Human-readable summary suitable for a UI banner.
+    pub message: String,
+}
+
+/// Probe every Elgato hidraw node and report the first access problem found.
+///
+/// Returns `None` when all nodes open fine, none are present, or on Windows.
+pub fn diagnose() -> Option<HidrawDiagnosis> {
+    if cfg!(target_os = "macos") {
+        return diagnose_iokit();
+    }
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let issue = elgato_hidraw_nodes()
+        .into_iter()
+        .find_map(|node| probe_node(&node))?;
+    Some(diagnosis_for(issue))
+}
+
+/// Whether any udev rules file mentions the Elgato vendor id.
+pub fn udev_rule_installed() -> bool {
+    let needle = format!("{ELGATO_VENDOR_ID:04x}");
+    UDEV_RULE_DIRS.iter().any(|dir| {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return false;
+        };
+        entries.flatten().any(|e| {
+            let path = e.path();
+            path.extension().is_some_and(|ext| ext == "rules")
+                && std::fs::read_to_string(&path)
+                    .map(|s| s.to_ascii_lowercase().contains(&needle))
+                    .unwrap_or(false)
+        })
+    })
+}
+
+/// Write `UDEV_RULE` to `UDEV_RULE_PATH` via `pkexec` and reload udev.
+///
+/// Blocks until the polkit prompt is answered. The device usually needs replugging afterwards.
+pub fn install_udev_rule() -> anyhow::Result<()> {
+    if !cfg!(target_os = "linux") {
+        anyhow::bail!("udev rules are only used on Linux");
+    }
+    // The rule text is passed as a positional argument so no shell quoting is involved.
+    let script = "printf '%s' \"$1\" > \"$2\" && chmod 0644 \"$2\" \
+                  && udevadm control --reload-rules && udevadm trigger";
+    let status = std::process::Command::new("pkexec")
+        .args(["sh", "-c", script, "sh", UDEV_RULE, UDEV_RULE_PATH])
+        .status()
+        .map_err(|e| anyhow::anyhow!("failed to run pkexec: {e}"))?;
+    match status.code() {
+        Some(0) => Ok(()),
+        // pkexec: 126 = authorization dismissed/denied, 127 = not authorized.
+        Some(126) | Some(127) => anyhow::bail!("authorization was denied"),
+        _ => anyhow::bail!("installing udev rule failed: {status}"),
+    }
+}
+
+fn diagnosis_for(issue: HidrawIssue) -> HidrawDiagnosis {
+    match &issue {
+        HidrawIssue::PermissionDenied { node } => {
+            if udev_rule_installed() {
+                HidrawDiagnosis {
+                    message: format!(
+                        "Permission denied opening {}. A udev rule is installed; unplug and \
+                         replug the Stream Deck so it takes effect.",
+                        node.display()
+                    ),
+                    issue,
+                    remediation: Remediation::ReplugDevice,
+                }
+            } else {
+                HidrawDiagnosis {
+                    message: format!(
+                        "Permission denied opening {}. No udev rule grants access to Stream Deck \
+                         devices; install one to fix this.",
+                        node.display()
+                    ),
+                    issue,
+                    remediation: Remediation::InstallUdevRule {
+                        rule_path: PathBuf::from(UDEV_RULE_PATH),
+                        rule: UDEV_RULE.to_string(),
+                    },
+                }
+            }
+        }
+        HidrawIssue::OpenFailed { node, error } => HidrawDiagnosis {
+            message: format!("Failed to open {}: {error}", node.display()),
+            issue,
+            remediation: Remediation::Manual,
+        },
+    }
+}
+
+/// Open every Elgato interface through `hidapi` and report the first that fails.
+fn diagnose_iokit() -> Option<HidrawDiagnosis> {
+    let hid = match crate::HidContext::new() {
+        Ok(hid) => hid,
+        Err(e) => {
+            return Some(HidrawDiagnosis {
+                message: format!("Failed to initialize HID access: {e}"),
+                issue: HidrawIssue::OpenFailed {
+                    node: PathBuf::new(),
+                    error: e.to_string(),
+                },
+                remediation: Remediation::Manual,
+            })
+        }
+    };
+    let (node, error) = hid
+        .list_all()
+        .into_iter()
+        .filter(|d| d.vendor_id == ELGATO_VENDOR_ID)
+        .find_map(|d| {
+            let error = hid.open_path(&d.path).err()?;
+            let node = PathBuf::from(String::from_utf8_lossy(&d.path).trim_end_matches('\0'));
+            Some((node, error.to_string()))
+        })?;
+    // hidapi reports the IOReturn code, e.g. "(0xE00002E2) (iokit/common) not permitted".
+    let lower = error.to_ascii_lowercase();
+    if !(lower.contains("0xe00002e2") || lower.contains("not permitted")) {
+        return Some(diagnosis_for(HidrawIssue::OpenFailed { node, error }));
+    }
+    Some(HidrawDiagnosis {
+        message: "macOS denied access to the Stream Deck. Allow RiverDeck under System Settings \
+                  > Privacy & Security > Input Monitoring, then restart it."
+            .to_string(),
+        issue: HidrawIssue::PermissionDenied { node },
+        remediation: Remediation::GrantInputMonitoring {
+            settings_url: INPUT_MONITORING_SETTINGS_URL.to_string(),
+        },
+    })
+}
+
+fn probe_node(node: &Path) -> Option<HidrawIssue> {
+    match std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(node)
+    {
+        Ok(_) => None,
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Some(HidrawIssue::PermissionDenied {
+                node: node.to_path_buf(),
+            })
+        }
+        Err(e) => Some(HidrawIssue::OpenFailed {
+            node: node.to_path_buf(),
+            error: e.to_string(),
+        }),
+    }
+}
+
+/// `/dev/hidrawN` nodes whose sysfs `uevent` reports the Elgato vendor id.
+fn elgato_hidraw_nodes() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/hidraw") else {
+        return vec![];
+    };
+    let mut out = vec![];
+    for e in entries.flatten() {
+        let uevent = e.path().join("device").join("uevent");
+        let Ok(raw) = std::fs::read_to_string(uevent) else {
+            continue;
+        };
+        if uevent_vendor(&raw) == Some(ELGATO_VENDOR_ID) {
+            out.push(Path::new("/dev").join(e.file_name()));
+        }
+    }
+    out.sort();
+    out
+}
+
+/// Parse the vendor id from a `HID_ID=<bus>:<vendor>:<product>` uevent line.
+fn uevent_vendor(raw: &str) -> Option<u16> {
+    let id = raw.lines().find_map(|l| l.strip_prefix("HID_ID="))?;
+    let vendor = id.split(':').nth(1)?;
+    u32::from_str_radix(vendor, 16).ok().map(|v| v as u16)
+}
@@ -1,9 +1,15 @@
-//! HID transport layer (Linux + Windows) built on `hidapi`.
+//! HID transport layer (Linux, Windows and macOS) built on `hidapi`.
+
+pub mod capture;
+pub mod diagnostics;
+pub mod monitor;
 
 use std::ffi::CStr;
 
 use hidapi::{DeviceInfo, HidApi, HidDevice};
 
+use capture::{Direction, Tap};
+
 #[derive(Debug, Clone)]
 pub struct HidDiscoveredDevice {
     pub vendor_id: u16,
@@ -36,9 +42,12 @@ pub struct HidContext {
 
 impl HidContext {
     pub fn new() -> anyhow::Result<Self> {
-        Ok(Self {
-            api: HidApi::new()?,
-        })
+        let api = HidApi::new()?;
+        // macOS opens devices exclusively by default, which would keep the input, BMP image
+        // and report monitor handles from opening next to the `elgato-streamdeck` one.
+        #[cfg(target_os = "macos")]
+        api.set_open_exclusive(false);
+        Ok(Self { api })
     }
 
     pub fn list_all(&self) -> Vec<HidDiscoveredDevice> {
@@ -52,13 +61,30 @@ impl HidContext {
         let cstr = CStr::from_bytes_with_nul(path)?;
         Ok(HidDeviceHandle {
             inner: self.api.open_path(cstr)?,
+            tap: Tap::open(None, &cstr.to_string_lossy()),
+        })
+    }
+
+    /// Open `device`; unlike [`Self::open_path`], a capture names the device it came from.
+    pub fn open_device(&self, device: &HidDiscoveredDevice) -> anyhow::Result<HidDeviceHandle> {
+        let cstr = CStr::from_bytes_with_nul(&device.path)?;
+        let description = device
+            .product_string
+            .clone()
+            .unwrap_or_else(|| cstr.to_string_lossy().into_owned());
+        Ok(HidDeviceHandle {
+            inner: self.api.open_path(cstr)?,
+            tap: Tap::open(Some((device.vendor_id, device.product_id)), &description),
         })
     }
 }
 
 /// Thin wrapper so downstream crates don't need to depend on `hidapi` directly.
+///
+/// Records its traffic while capturing (see [`capture`]).
 pub struct HidDeviceHandle {
     inner: HidDevice,
+    tap: Option<Tap>,
 }
 
 impl HidDeviceHandle {
@@ -68,14 +94,24 @@ impl HidDeviceHandle {
     }
 
     pub fn read_timeout(&mut self, buf: &mut [u8], timeout_ms: i32) -> anyhow::Result<usize> {
-        Ok(self.inner.read_timeout(buf, timeout_ms)?)
+        let len = self.inner.read_timeout(buf, timeout_ms)?;
+        if let Some(tap) = self.tap.as_ref().filter(|_| len > 0) {
+            tap.record(Direction::Read, &buf[..len]);
+        }
+        Ok(len)
     }
 
     pub fn write(&mut self, buf: &[u8]) -> anyhow::Result<usize> {
+        if let Some(tap) = &self.tap {
+            tap.record(Direction::Write, buf);
+        }
         Ok(self.inner.write(buf)?)
     }
 
     pub fn send_feature_report(&mut self, report: &[u8]) -> anyhow::Result<()> {
+        if let Some(tap) = &self.tap {
+            tap.record(Direction::Feature, report);
+        }
         Ok(self.inner.send_feature_report(report)?)
     }
 }
@@ -0,0 +1,96 @@
+//! Raw input reports of a HID device, for diagnostics.
+//!
+//! A [`ReportMonitor`] opens the device a second time and reads on its own thread. On Linux
+//! every open hidraw node gets its own copy of each input report, so the app keeps seeing key
+//! presses meanwhile; on other platforms the two readers may split the reports between them.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use crate::HidContext;
+
+/// Largest input report read; longer ones are cut off.
+const MAX_REPORT_LEN: usize = 1024;
+/// How often the reading thread checks whether it should stop.
+const POLL_MS: i32 = 250;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputReport {
+    /// Time since the monitor started.
+    pub elapsed: Duration,
+    /// The report as read, starting with the report id.
+    pub data: Vec<u8>,
+}
+
+/// Reads input reports until dropped.
+pub struct ReportMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ReportMonitor {
+    /// Open the device at `path` (as in [`crate::HidDiscoveredDevice::path`]) and pass each
+    /// input report to `on_report`. A read error is passed on too and ends the monitor.
+    pub fn start(
+        path: &[u8],
+        mut on_report: impl FnMut(anyhow::Result<InputReport>) + Send + 'static,
+    ) -> anyhow::Result<Self> {
+        let path = path.to_vec();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let stopped = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("hid-report-monitor".to_string())
+            .spawn(move || {
+                // Opened here: the handle stays on the thread that reads from it.
+                let device = HidContext::new().and_then(|ctx| ctx.open_path(&path));
+                let mut device = match device {
+                    Ok(device) => {
+                        let _ = opened_tx.send(Ok(()));
+                        device
+                    }
+                    Err(e) => {
+                        let _ = opened_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let started = Instant::now();
+                let mut buf = [0u8; MAX_REPORT_LEN];
+                while !stopped.load(Ordering::Relaxed) {
+                    match device.read_timeout(&mut buf, POLL_MS) {
+                        Ok(0) => {}
+                        Ok(len) => on_report(Ok(InputReport {
+                            elapsed: started.elapsed(),
+                            data: buf[..len].to_vec(),
+                        })),
+                        Err(e) => {
+                            on_report(Err(e.context("reading the device failed")));
+                            return;
+                        }
+                    }
+                }
+            })
+            .context("failed to start the report monitor thread")?;
+        opened_rx
+            .recv()
+            .context("report monitor thread exited")?
+            .context("failed to open the device")?;
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for ReportMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Text of `clock` keys: the time in a key's time zone, with the date and week number below.
+//!
+//! Keys are redrawn when the minute changes; every zone's minutes start together, since all
+//! UTC offsets are whole minutes.
+
+use actions::{BuiltinAction, ClockFormat};
+use chrono::{DateTime, Datelike, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// What the `clock` binding `action` shows at `now`, one line per part: time, then date, then
+/// week number. `None` for other actions; an error when the time zone is unknown.
+pub fn key_text(action: &BuiltinAction, now: DateTime<Utc>) -> Option<Result<String, String>> {
+    let BuiltinAction::Clock {
+        timezone,
+        format,
+        show_date,
+        show_week,
+    } = action
+    else {
+        return None;
+    };
+    let options = (*format, *show_date, *show_week);
+    let zone = timezone.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    Some(match zone {
+        Some(name) => parse_timezone(name).map(|tz| lines(&now.with_timezone(&tz), options)),
+        None => Ok(lines(&now.with_timezone(&Local), options)),
+    })
+}
+
+/// The IANA time zone `name`, e.g. `Europe/Berlin`.
+fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse()
+        .map_err(|_| format!("Unknown time zone \"{name}\"; expected e.g. Europe/Berlin"))
+}
+
+/// Time, date and week number of `t`, as picked by the format, date and week options.
+fn lines<Z: TimeZone>(t: &DateTime<Z>, (format, date, week): (ClockFormat, bool, bool)) -> String
+where
+    Z::Offset: std::fmt::Display,
+{
+    let mut lines = vec![match format {
+        ClockFormat::TwentyFourHour => t.format("%H:%M").to_string(),
+        ClockFormat::TwelveHour => t.format("%-I:%M %p").to_string(),
+    }];
+    if date {
+        lines.push(t.format("%-d %b").to_string());
+    }
+    if week {
+        lines.push(format!("W{}", t.iso_week().week()));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,78 @@
+//! App command registry, listed by the command palette (Ctrl+K).
+//!
+//! Commands are plain data: the app rebuilds the registry from its current state (profiles,
+//! devices, connection) whenever it is needed and runs a picked [`AppCommand`] like any other
+//! input. Every entry has a stable id (e.g. `view.marketplace`) so shortcuts or scripts can
+//! refer to commands without going through their titles.
+
+use app_core::ids::{DeviceId, ProfileId};
+use storage::settings::ThemeSetting;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppCommand {
+    SwitchProfile(ProfileId),
+    ConnectDevice(DeviceId),
+    OpenMarketplace,
+    OpenSchedules,
+    OpenWebhooks,
+    OpenTypedTriggers,
+    OpenFileWatchers,
+    OpenProcessWatchers,
+    OpenMidi,
+    OpenOsc,
+    OpenHistory,
+    OpenStats,
+    OpenDeviceTest,
+    /// Turn the device's screens off, or back on at the remembered brightness.
+    ToggleBrightness,
+    SetPaused(bool),
+    /// Only log bound actions instead of running them.
+    SetDryRun(bool),
+    SaveProfile,
+    RefreshDevices,
+    /// Scale the interface, in percent.
+    SetUiScale(u16),
+    SetTheme(ThemeSetting),
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandEntry {
+    pub id: String,
+    pub title: String,
+    pub command: AppCommand,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandRegistry {
+    entries: Vec<CommandEntry>,
+}
+
+impl CommandRegistry {
+    pub fn register(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        command: AppCommand,
+    ) {
+        self.entries.push(CommandEntry {
+            id: id.into(),
+            title: title.into(),
+            command,
+        });
+    }
+
+    /// Entries whose title or id contains every word of `query` (ignoring case), in
+    /// registration order.
+    pub fn search(&self, query: &str) -> Vec<&CommandEntry> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.entries
+            .iter()
+            .filter(|e| {
+                let title = e.title.to_lowercase();
+                words
+                    .iter()
+                    .all(|w| title.contains(w.as_str()) || e.id.contains(w.as_str()))
+            })
+            .collect()
+    }
+}
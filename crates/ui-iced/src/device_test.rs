@@ -0,0 +1,169 @@
+//! Device test view: test patterns cycled over every key, the raw input reports the device
+//! sends, and how long key image writes take.
+//!
+//! Write times are measured around `DeviceController::set_key_image_jpeg`, which returns once
+//! the image is on the wire, so they include queueing in the device task and the USB transfer.
+//! Reports are read by a second handle on the device's HID interface (see
+//! [`transport_hid::monitor`]).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use render::test_patterns::TestPattern;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use transport_hid::monitor::{InputReport, ReportMonitor};
+
+/// How long each test pattern stays on the keys.
+pub const PATTERN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reports kept for the view, newest last.
+const MAX_REPORTS: usize = 200;
+
+/// Write times kept for the statistics.
+const MAX_SAMPLES: usize = 1_000;
+
+/// An Elgato HID interface the reports can be read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidInterface {
+    pub label: String,
+    pub path: Vec<u8>,
+}
+
+impl fmt::Display for HidInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// Statistics over the kept key image write times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteTimes {
+    pub last: Duration,
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    pub count: usize,
+}
+
+pub type ReportReceiver = UnboundedReceiver<Result<InputReport, String>>;
+
+#[derive(Default)]
+pub struct DeviceTest {
+    /// Test patterns are being cycled; the profile is not drawn on the device meanwhile.
+    pub cycling: bool,
+    /// A pattern is being written to the keys.
+    pub writing: bool,
+    /// Index into [`TestPattern::ALL`] of the pattern on the keys.
+    pattern: Option<usize>,
+    /// Time to write each key image, oldest first.
+    write_times: VecDeque<Duration>,
+    /// Time to write the last pattern to all keys.
+    pub last_frame: Option<Duration>,
+    pub interfaces: Vec<HidInterface>,
+    pub selected: Option<HidInterface>,
+    monitor: Option<ReportMonitor>,
+    reports: VecDeque<InputReport>,
+    /// Hands the receiver of the running monitor to the subscription.
+    pub report_slot: Arc<Mutex<Option<ReportReceiver>>>,
+    /// Bumped for every monitor, so the subscription restarts with its receiver.
+    pub monitor_session: u64,
+    pub error: Option<String>,
+}
+
+impl DeviceTest {
+    /// The pattern on the keys, if any.
+    pub fn pattern(&self) -> Option<TestPattern> {
+        self.pattern.map(|i| TestPattern::ALL[i])
+    }
+
+    /// Move on to the next pattern and return it.
+    pub fn advance(&mut self) -> TestPattern {
+        let next = self.pattern.map_or(0, |i| (i + 1) % TestPattern::ALL.len());
+        self.pattern = Some(next);
+        TestPattern::ALL[next]
+    }
+
+    /// Record the write times of one pattern, one per key.
+    pub fn record_writes(&mut self, times: &[Duration]) {
+        self.last_frame = Some(times.iter().sum());
+        self.write_times.extend(times);
+        while self.write_times.len() > MAX_SAMPLES {
+            self.write_times.pop_front();
+        }
+    }
+
+    pub fn write_times(&self) -> Option<WriteTimes> {
+        let last = *self.write_times.back()?;
+        let count = self.write_times.len();
+        let total: Duration = self.write_times.iter().sum();
+        Some(WriteTimes {
+            last,
+            min: self.write_times.iter().copied().min().unwrap_or_default(),
+            avg: total / count as u32,
+            max: self.write_times.iter().copied().max().unwrap_or_default(),
+            count,
+        })
+    }
+
+    /// Start reading the reports of `interface`, replacing the running monitor.
+    pub fn start_monitor(&mut self, interface: HidInterface) -> Result<(), String> {
+        self.stop_monitor();
+        self.selected = Some(interface.clone());
+        let (tx, rx) = mpsc::unbounded_channel();
+        let monitor = ReportMonitor::start(&interface.path, move |report| {
+            let _ = tx.send(report.map_err(|e| format!("{e:#}")));
+        })
+        .map_err(|e| format!("{e:#}"))?;
+        self.monitor = Some(monitor);
+        *self.report_slot.lock().expect("report slot mutex poisoned") = Some(rx);
+        self.monitor_session += 1;
+        Ok(())
+    }
+
+    pub fn stop_monitor(&mut self) {
+        self.monitor = None;
+    }
+
+    pub fn monitoring(&self) -> bool {
+        self.monitor.is_some()
+    }
+
+    pub fn push_report(&mut self, report: InputReport) {
+        self.reports.push_back(report);
+        while self.reports.len() > MAX_REPORTS {
+            self.reports.pop_front();
+        }
+    }
+
+    pub fn reports(&self) -> impl DoubleEndedIterator<Item = &InputReport> {
+        self.reports.iter()
+    }
+
+    pub fn clear_reports(&mut self) {
+        self.reports.clear();
+    }
+}
+
+/// `duration` in milliseconds, for display.
+pub fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// `report` as hex bytes, with trailing zero padding left out.
+pub fn format_report(report: &InputReport) -> String {
+    let len = report
+        .data
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(report.data.len().min(1), |i| i + 1);
+    let mut out: Vec<String> = report.data[..len]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    if len < report.data.len() {
+        out.push(format!("(+{} zero bytes)", report.data.len() - len));
+    }
+    out.join(" ")
+}
@@ -0,0 +1,140 @@
+//! Global keyboard shortcuts that press deck keys (via `global-hotkey`; X11 only on Linux).
+//!
+//! The app registers the hotkeys of the active profile's keys and forwards presses/releases to
+//! the engine as control events, so a shortcut behaves exactly like the key it is bound to.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use iced::keyboard::{key::Named, Key, Modifiers};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A registered hotkey went down (`pressed`) or up.
+#[derive(Debug, Clone, Copy)]
+pub struct HotkeyEvent {
+    id: u32,
+    pub pressed: bool,
+}
+
+pub struct Hotkeys {
+    manager: GlobalHotKeyManager,
+    /// Registered hotkeys by id, with the deck key each one presses.
+    registered: HashMap<u32, (HotKey, u8)>,
+    /// `(hotkey, key)` pairs last passed to [`Hotkeys::set_bindings`].
+    bindings: Vec<(String, u8)>,
+}
+
+impl Hotkeys {
+    /// `None` when global hotkeys are unavailable (e.g. a Wayland session without X11).
+    pub fn new(events: UnboundedSender<HotkeyEvent>) -> Option<Self> {
+        let manager = match GlobalHotKeyManager::new() {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::warn!(error = %e, "global hotkeys unavailable");
+                return None;
+            }
+        };
+        GlobalHotKeyEvent::set_event_handler(Some(move |e: GlobalHotKeyEvent| {
+            let _ = events.send(HotkeyEvent {
+                id: e.id,
+                pressed: e.state == HotKeyState::Pressed,
+            });
+        }));
+        Some(Self {
+            manager,
+            registered: HashMap::new(),
+            bindings: vec![],
+        })
+    }
+
+    /// Register exactly `bindings` (`(hotkey, deck key)` pairs); no-op when unchanged.
+    ///
+    /// Returns one message per hotkey that could not be registered (invalid or taken).
+    pub fn set_bindings(&mut self, bindings: Vec<(String, u8)>) -> Vec<String> {
+        if bindings == self.bindings {
+            return vec![];
+        }
+        for (hotkey, _) in self.registered.drain().map(|(_, v)| v) {
+            if let Err(e) = self.manager.unregister(hotkey) {
+                tracing::warn!(%hotkey, error = %e, "failed to unregister hotkey");
+            }
+        }
+
+        let mut errors = vec![];
+        for (text, key) in &bindings {
+            let hotkey = match HotKey::from_str(text) {
+                Ok(h) => h,
+                Err(e) => {
+                    errors.push(format!("Invalid hotkey {text}: {e}"));
+                    continue;
+                }
+            };
+            if self.registered.contains_key(&hotkey.id()) {
+                errors.push(format!("Hotkey {text} is assigned to more than one key"));
+                continue;
+            }
+            match self.manager.register(hotkey) {
+                Ok(()) => {
+                    self.registered.insert(hotkey.id(), (hotkey, *key));
+                }
+                Err(e) => errors.push(format!("Failed to register hotkey {text}: {e}")),
+            }
+        }
+        self.bindings = bindings;
+        errors
+    }
+
+    /// The deck key pressed by the hotkey of `event`, if it is still registered.
+    pub fn key_for(&self, event: HotkeyEvent) -> Option<u8> {
+        self.registered.get(&event.id).map(|(_, key)| *key)
+    }
+}
+
+/// The hotkey string (as stored in profiles) for a key press captured by the recorder.
+///
+/// `None` for presses that cannot be a hotkey on their own (modifiers alone, unsupported keys).
+pub fn from_key_press(key: &Key, modifiers: Modifiers) -> Option<String> {
+    let code = match key {
+        Key::Named(
+            Named::Shift
+            | Named::Control
+            | Named::Alt
+            | Named::AltGraph
+            | Named::Super
+            | Named::Meta
+            | Named::Hyper,
+        ) => return None,
+        Key::Named(named) => format!("{named:?}"),
+        Key::Character(c) => {
+            let c = c.chars().next()?;
+            match c {
+                'a'..='z' | 'A'..='Z' => format!("Key{}", c.to_ascii_uppercase()),
+                '0'..='9' => format!("Digit{c}"),
+                // Shifted digits on a US layout; the hotkey is bound to the physical key.
+                ')' | '!' | '@' | '#' | '$' | '%' | '^' | '&' | '*' | '(' => {
+                    let digit = ")!@#$%^&*(".find(c)?;
+                    format!("Digit{digit}")
+                }
+                c => c.to_string(),
+            }
+        }
+        Key::Unidentified => return None,
+    };
+
+    let mut text = String::new();
+    for (on, name) in [
+        (modifiers.control(), "control"),
+        (modifiers.alt(), "alt"),
+        (modifiers.shift(), "shift"),
+        (modifiers.logo(), "super"),
+    ] {
+        if on {
+            text.push_str(name);
+            text.push('+');
+        }
+    }
+    text.push_str(&code);
+    HotKey::from_str(&text).ok().map(|_| text)
+}
@@ -0,0 +1,182 @@
+//! Macro recording: key presses in the editor window and presses of deck keys become macro
+//! steps, each delayed by the time the user took before it.
+//!
+//! Keyboard steps are `keyboard_input` actions for the default tool (`wtype`): characters typed
+//! in a row become one `text` step, shortcuts and named keys become `-M`/`-k`/`-m` arguments.
+
+use std::time::Instant;
+
+use actions::{ActionBinding, BuiltinAction, KeyChord, KeyModifier, MacroStep};
+use iced::keyboard::{key::Named, Key, Modifiers};
+
+/// Pauses shorter than this are not kept as step delays.
+const MIN_DELAY_MS: u64 = 50;
+
+/// Characters typed closer together than this go into the same text step.
+const TYPING_GAP_MS: u64 = 1_000;
+
+pub struct MacroRecorder {
+    steps: Vec<MacroStep>,
+    /// When the last step was recorded (or recording started).
+    last: Instant,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Record `action`, performed just now.
+    pub fn record(&mut self, action: ActionBinding) {
+        let delay = self.take_elapsed_ms();
+        // The wait before the first step is just the user getting started.
+        let delay_ms = (!self.steps.is_empty() && delay >= MIN_DELAY_MS).then_some(delay);
+        self.steps.push(MacroStep {
+            action: Box::new(action),
+            delay_ms,
+        });
+    }
+
+    /// Record a key press in the editor window. Returns `false` for presses that make no step
+    /// (lone modifiers, keys without a keysym name here).
+    pub fn record_key(&mut self, key: &Key, modifiers: Modifiers) -> bool {
+        if let Some(c) = typed_char(key, modifiers) {
+            let typing = self.last.elapsed().as_millis() < u128::from(TYPING_GAP_MS);
+            if let Some(text) = self.last_text_mut().filter(|_| typing) {
+                text.push(c);
+                self.last = Instant::now();
+            } else {
+                self.record(keyboard_input(Some(c.to_string()), Vec::new()));
+            }
+            return true;
+        }
+        match key_args(key, modifiers) {
+            Some(keys) => {
+                self.record(keyboard_input(None, keys));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(self) -> Vec<MacroStep> {
+        self.steps
+    }
+
+    /// Milliseconds since the last step, rounded down to 10 ms; restarts the clock.
+    fn take_elapsed_ms(&mut self) -> u64 {
+        let now = Instant::now();
+        let ms = now.duration_since(self.last).as_millis() as u64;
+        self.last = now;
+        ms / 10 * 10
+    }
+
+    fn last_text_mut(&mut self) -> Option<&mut String> {
+        match self.steps.last_mut()?.action.as_mut() {
+            ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+                text: Some(text), ..
+            }) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+fn keyboard_input(text: Option<String>, keys: Vec<String>) -> ActionBinding {
+    ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+        text,
+        keys,
+        chord: None,
+    })
+}
+
+/// The character `key` types, if no modifier other than Shift is held.
+fn typed_char(key: &Key, modifiers: Modifiers) -> Option<char> {
+    if modifiers.control() || modifiers.alt() || modifiers.logo() {
+        return None;
+    }
+    match key {
+        Key::Character(c) => {
+            let mut chars = c.chars();
+            let ch = chars.next()?;
+            chars.next().is_none().then_some(ch)
+        }
+        Key::Named(Named::Space) => Some(' '),
+        _ => None,
+    }
+}
+
+/// `wtype` arguments pressing `key` while `modifiers` are held.
+fn key_args(key: &Key, modifiers: Modifiers) -> Option<Vec<String>> {
+    key_chord(key, modifiers).map(|chord| chord.wtype_args())
+}
+
+/// `key` pressed while `modifiers` are held as a chord, for the keys that have a keysym name
+/// here (letters, digits and the named keys worth recording).
+pub fn key_chord(key: &Key, modifiers: Modifiers) -> Option<KeyChord> {
+    let keysym = match key {
+        Key::Character(c) => {
+            let c = c.chars().next()?;
+            if !c.is_ascii_alphanumeric() {
+                return None;
+            }
+            c.to_ascii_lowercase().to_string()
+        }
+        Key::Named(named) => named_keysym(*named)?.to_string(),
+        Key::Unidentified => return None,
+    };
+
+    let modifiers = [
+        (modifiers.control(), KeyModifier::Ctrl),
+        (modifiers.alt(), KeyModifier::Alt),
+        (modifiers.shift(), KeyModifier::Shift),
+        (modifiers.logo(), KeyModifier::Logo),
+    ]
+    .into_iter()
+    .filter_map(|(on, m)| on.then_some(m))
+    .collect();
+    Some(KeyChord {
+        modifiers,
+        key: keysym,
+    })
+}
+
+/// X keysym name of `key`, for the named keys worth recording.
+fn named_keysym(key: Named) -> Option<&'static str> {
+    Some(match key {
+        Named::Enter => "Return",
+        Named::Tab => "Tab",
+        Named::Space => "space",
+        Named::Backspace => "BackSpace",
+        Named::Delete => "Delete",
+        Named::Escape => "Escape",
+        Named::Insert => "Insert",
+        Named::Home => "Home",
+        Named::End => "End",
+        Named::PageUp => "Prior",
+        Named::PageDown => "Next",
+        Named::ArrowLeft => "Left",
+        Named::ArrowRight => "Right",
+        Named::ArrowUp => "Up",
+        Named::ArrowDown => "Down",
+        Named::F1 => "F1",
+        Named::F2 => "F2",
+        Named::F3 => "F3",
+        Named::F4 => "F4",
+        Named::F5 => "F5",
+        Named::F6 => "F6",
+        Named::F7 => "F7",
+        Named::F8 => "F8",
+        Named::F9 => "F9",
+        Named::F10 => "F10",
+        Named::F11 => "F11",
+        Named::F12 => "F12",
+        _ => return None,
+    })
+}
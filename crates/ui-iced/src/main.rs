@@ -1,15 +1,32 @@
-use std::collections::HashMap;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::time::Duration;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 use std::{fmt, sync::Arc};
 
-use actions::{ActionBinding, ActionStep, BuiltinAction, PluginActionBinding};
+use actions::{ActionBinding, BuiltinAction, PluginActionBinding};
+use app_core::capabilities::DeviceCapabilities;
 use app_core::AppCore;
 use device::{
-    ControlEventKind, ControlId, DeviceController, DeviceEvent, DeviceService, DiscoveredDevice,
-    HidDeviceService,
+    ControlEvent, ControlEventKind, ControlId, DeviceController, DeviceEvent, DeviceService,
+    DiscoveredDevice, GestureThresholds, HidDeviceService, TouchZone,
 };
+use engine::docker::Container;
+use engine::git::GitStatus;
+use engine::launcher::AppEntry;
+use engine::monitoring::{self, MetricsSampler, Sample};
+use variables::{VariableChange, VariableStore};
+use weather::{Report as WeatherReport, Weather};
+use engine::plugin_feedback::KeyChange;
+use engine::vscode::TaskStatus;
+use engine::{DeviceInfo, EngineHandle, EngineNotification, Integrations, StepReport};
+use github_actions::{CiQuery, GithubActions, Run as CiRun, RunState};
+use home_assistant::{EntityState, HomeAssistant};
+use lighting::{DeviceKind, Discovered, HueLight, LightState, Lights};
+use meetings::{MeetingState, Meetings};
+use spotify::{Playback, Spotify};
+use render::cache::{ImageCache, LcdImage, Slot};
+use render::dashboard::{Dashboard, Panel};
+use secrets::SecretStore;
 use iced::widget::{
     button, checkbox, column, container, horizontal_rule, horizontal_space, image, mouse_area,
     pick_list, row, scrollable, slider, text, text_input,
@@ -19,28 +36,105 @@ use iced::{
     alignment::{Horizontal, Vertical}, Alignment, Application, Background, Border, Color, Command, Element,
     Length, Settings, Shadow, Subscription, Theme,
 };
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::{Receiver, UnboundedReceiver};
 
 use app_core::ids::ProfileId;
-use storage::profiles::{Profile, ProfileMeta};
+use storage::activity::ActivityEntry;
+use storage::midi_triggers::{MidiInput, MidiTrigger};
+use storage::osc_triggers::OscTrigger;
+use storage::pomodoro::PomodoroTimer;
+use storage::process_watchers::{ProcessTransition, ProcessWatcher};
+use storage::profiles::{
+    strip_plugin_triggers, FileChange, FileWatcher, FlashEffect, Profile, ProfileMeta, TextSize,
+};
+use storage::schedules::Schedule;
+use storage::settings::{
+    CompanionSettings, HomeAssistantSettings, InterfaceSettings, KioskSettings, MacroSettings,
+    MarketplaceSettings, MidiSettings, NotificationMirrorSettings, OscSettings, SyncSettings,
+    TextExpanderSettings, ThemeSetting, TouchGestureSettings, WebhookSettings,
+};
+use storage::surfaces::Surface;
+use storage::sync::{Resolution, SyncReport};
+use storage::themes::UserTheme;
+use storage::typed_triggers::TypedTrigger;
+use storage::usage::{UsageControl, UsageFile};
+use storage::validate::{Diagnostic, Severity};
+use storage::webhooks::Webhook;
 
 use openaction::manifest::{ActionDefinition, SettingField, SettingType};
-use openaction::marketplace::MarketplacePlugin;
-use openaction::registry::InstalledPlugin;
-use plugin_runtime::{ActionRuntime, InvocationControl, InvocationEvent};
+use openaction::marketplace::{
+    Compatibility, IconDownloader, MarketplaceIndex, MarketplacePlugin,
+};
+use openaction::profile_gallery::{GalleryProfile, ProfileGallery};
+use openaction::registry::{InstalledPlugin, PluginLaunch, UninstallReport};
+use openaction::verify::{Verification, VerificationError};
+use transport_hid::diagnostics::{HidrawDiagnosis, Remediation};
+use transport_hid::monitor::InputReport;
+
+mod clock;
+mod commands;
+mod device_test;
+mod hotkeys;
+mod macro_recorder;
+mod pomodoro;
+mod themes;
+mod tray;
+
+use commands::{AppCommand, CommandRegistry};
+use themes::{ActiveTheme, ThemeChoice};
 
 fn main() -> iced::Result {
     init_tracing();
 
+    let options = LaunchOptions::from_args(std::env::args().skip(1));
+    if let Err(e) = storage::paths::init(options.config_dir.clone(), options.portable) {
+        tracing::error!(error = %e, "failed to set up the data location");
+    }
+
     App::run(Settings {
+        flags: options,
         window: iced::window::Settings {
             size: iced::Size::new(1240.0, 760.0),
+            // Closing hides to the tray when one is available (see `WindowCloseRequested`).
+            exit_on_close_request: false,
             ..Default::default()
         },
         ..Default::default()
     })
 }
 
+/// Command-line options of the app.
+#[derive(Debug, Default)]
+struct LaunchOptions {
+    /// `--dry-run`: start with actions simulated instead of run.
+    dry_run: bool,
+    /// `--config-dir <dir>`: keep all data in `dir` (see `storage::paths`).
+    config_dir: Option<std::path::PathBuf>,
+    /// `--portable`: keep all data next to the executable.
+    portable: bool,
+}
+
+impl LaunchOptions {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dry-run" => options.dry_run = true,
+                "--portable" => options.portable = true,
+                "--config-dir" => match args.next() {
+                    Some(dir) => options.config_dir = Some(dir.into()),
+                    None => tracing::warn!("--config-dir needs a directory; ignoring it"),
+                },
+                other => match other.strip_prefix("--config-dir=") {
+                    Some(dir) => options.config_dir = Some(dir.into()),
+                    None => tracing::warn!(arg = other, "ignoring unknown command-line argument"),
+                },
+            }
+        }
+        options
+    }
+}
+
 fn init_tracing() {
     let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
     tracing_subscriber::fmt()
@@ -56,31 +150,321 @@ struct App {
     selected_device: Option<app_core::ids::DeviceId>,
     connecting: bool,
     connected: Option<ConnectedUi>,
+    /// Notifications from the engine of the current connection, taken by the subscription.
+    engine_events: Arc<std::sync::Mutex<Option<UnboundedReceiver<EngineNotification>>>>,
+    /// Bumped per connection so the engine subscription restarts with the new receiver.
+    engine_session: u64,
+    /// Bound actions are not run (toggled from the topbar or the tray).
+    paused: bool,
+    /// Bound actions are only logged, not run (`--dry-run` or the topbar toggle).
+    dry_run: bool,
+    /// Tray icon; when present, closing the window keeps the app running in the background.
+    tray: Option<tray::TrayHandle>,
+    tray_commands: Arc<std::sync::Mutex<Option<UnboundedReceiver<tray::TrayCommand>>>>,
+    /// Global hotkeys of the active profile's keys; `None` when unsupported on this system.
+    hotkeys: Option<hotkeys::Hotkeys>,
+    hotkey_events: Arc<std::sync::Mutex<Option<UnboundedReceiver<hotkeys::HotkeyEvent>>>>,
+    /// The next key press in the window becomes the selected key's hotkey.
+    recording_hotkey: bool,
+    /// The next key press in the window becomes the shortcut of the selected Keyboard Input.
+    recording_chord: bool,
+    /// Steps recorded for the selected macro while recording one.
+    macro_recorder: Option<macro_recorder::MacroRecorder>,
+    /// Linux hidraw access problem with a guided fix, if one was detected.
+    hid_diagnosis: Option<HidrawDiagnosis>,
+    installing_udev_rule: bool,
     profiles: Vec<ProfileMeta>,
     profile_choices: Vec<ProfileChoice>,
+    /// Decks composed into one grid of keys (persisted in surfaces.json).
+    surfaces: Vec<Surface>,
+    /// Name field of a new surface.
+    edit_surface_name: String,
     selected_profile: Option<ProfileId>,
     profile: Option<Profile>,
     selected_control: Option<SelectedControl>,
+    /// Keys selected together with shift-click (including the selected key) for bulk edits.
+    selected_keys: BTreeSet<usize>,
+    /// Keyboard modifiers currently held, for shift-click selection.
+    modifiers: iced::keyboard::Modifiers,
+    /// The key grid has keyboard focus: arrow keys move the selected key. Lost to text fields.
+    grid_focused: bool,
+    /// The keyboard was used since the last click; the focused key gets a focus ring.
+    focus_visible: bool,
     selected_binding_target: BindingTarget,
+    /// A test run of the selected binding is in progress.
+    testing_binding: bool,
+    /// Step-by-step outcome of the last test run of the selected binding.
+    binding_test: Option<Result<Vec<StepReport>, String>>,
     edit_label: String,
     edit_bg_rgb: String,
     edit_icon_path: String,
     edit_display_text: String,
+    edit_entity_id: String,
     plugins: Vec<InstalledPlugin>,
+    /// `plugins` holds the installed plugins, i.e. they were listed once.
+    plugins_listed: bool,
+    /// Run plugins under the Linux sandbox (persisted in plugin-state.json).
+    sandbox_enabled: bool,
+    /// Plugin id whose network permission request is being shown to the user.
+    permission_prompt: Option<String>,
+    /// Launch configuration being edited in a plugin's details panel.
+    plugin_details: Option<PluginDetails>,
+    /// Shared by every engine so sounds keep playing across reconnects.
+    audio: audio::AudioPlayer,
+    audio_devices: Vec<String>,
+    /// Selected output device (persisted in settings; `None` = system default).
+    audio_output_device: Option<String>,
+    /// Last brightness per device id (persisted in settings), restored on connect.
+    device_brightness: BTreeMap<u64, u8>,
+    /// Applications offered by the `open` action's app picker.
+    installed_apps: Vec<AppEntry>,
+    /// Folders VS Code has opened, offered by the `vs_code` action's folder picker.
+    vscode_workspaces: Vec<String>,
+    /// Task labels of the folder the selected `vs_code` binding opens, with the folder.
+    vscode_tasks: Option<(String, Vec<String>)>,
+    /// Persisted `counter` values of the loaded profile, by key index.
+    counters: BTreeMap<u8, i64>,
+    /// Persisted `pomodoro` timers of the loaded profile, by key index.
+    pomodoros: BTreeMap<u8, PomodoroTimer>,
+    /// What plugin actions drew on keys of the loaded profile, by key index.
+    plugin_keys: BTreeMap<u8, PluginKeyLook>,
+    /// Problems found in the loaded profile's file, e.g. after editing it by hand.
+    profile_diagnostics: Vec<Diagnostic>,
+    /// Plugins the loaded profile uses that are not installed.
+    missing_plugins: MissingPlugins,
+    /// Shared with every engine; `set_variable` actions write to it.
+    variables: VariableStore,
+    /// Current values, mirrored from the store's change notifications.
+    variable_values: BTreeMap<String, String>,
+    variable_changes: Arc<std::sync::Mutex<Option<UnboundedReceiver<VariableChange>>>>,
+    /// Time-based triggers (persisted in schedules.json; run by the engine while connected).
+    schedules: Vec<Schedule>,
+    /// Webhook routes (persisted in webhooks.json) and the listener settings.
+    webhooks: Vec<Webhook>,
+    /// Abbreviations that run actions when typed (persisted in typed_triggers.json).
+    typed_triggers: Vec<TypedTrigger>,
+    /// Programs whose start or exit runs actions (persisted in process_watchers.json).
+    process_watchers: Vec<ProcessWatcher>,
+    /// Whether the keyboard is watched for `typed_triggers`.
+    text_expander: TextExpanderSettings,
+    /// Notes and controllers that run actions (persisted in midi_triggers.json).
+    midi_triggers: Vec<MidiTrigger>,
+    /// Whether and which MIDI input port is listened to for `midi_triggers`.
+    midi_settings: MidiSettings,
+    /// OSC addresses that run actions (persisted in osc_triggers.json).
+    osc_triggers: Vec<OscTrigger>,
+    osc_settings: OscSettings,
+    /// Port field of the OSC view; applied on submit.
+    edit_osc_port: String,
+    /// Whether, where and how long desktop notifications are shown on a key.
+    notification_mirror: NotificationMirrorSettings,
+    /// Summary of the latest desktop notification while it is on the key, and when it came.
+    mirrored_notification: Option<(String, Instant)>,
+    /// Latest runs of bindings, oldest first (persisted in activity.json).
+    activity: VecDeque<ActivityEntry>,
+    /// How often and how long the bindings of each profile ran (persisted in usage.json).
+    usage: UsageFile,
+    /// Tint the preview's keys by how often they are used.
+    usage_heatmap: bool,
+    /// Test patterns, raw input reports and write times of the Device Test view.
+    device_test: device_test::DeviceTest,
+    /// Text the History view's entries must contain.
+    activity_filter: String,
+    activity_failures_only: bool,
+    webhook_settings: WebhookSettings,
+    /// Port field of the Webhooks view; applied on submit.
+    edit_webhook_port: String,
+    companion_settings: CompanionSettings,
+    /// `host:port` field of the Companion settings; applied on submit.
+    edit_companion_address: String,
+    /// When touch strip gestures count as swipes; pushed to the connected device.
+    touch_gestures: TouchGestureSettings,
+    /// Bounds on expanding macros; pushed to the engine.
+    macro_settings: MacroSettings,
+    interface: InterfaceSettings,
+    /// Value of the UI scale slider; applied when it is released.
+    edit_ui_scale: u16,
+    /// Theme files in the themes folder, reloaded when the folder changes.
+    user_themes: Vec<UserTheme>,
+    themes_modified: Option<SystemTime>,
+    /// `interface.theme` resolved against `user_themes`.
+    active_theme: ActiveTheme,
+    /// Shared with every engine; `home_assistant` actions call services through it.
+    home_assistant: HomeAssistant,
+    home_assistant_settings: HomeAssistantSettings,
+    /// URL and token fields of the Home Assistant settings; applied on submit.
+    edit_ha_url: String,
+    edit_ha_token: String,
+    marketplace_settings: MarketplaceSettings,
+    /// GitHub token field of the marketplace settings; applied on submit.
+    edit_github_token: String,
+    /// Gallery index field of the marketplace's Profiles tab; saved when loaded.
+    edit_profile_gallery_url: String,
+    sync_settings: SyncSettings,
+    /// Folder field of the sync settings; applied on submit.
+    edit_sync_folder: String,
+    sync: SyncState,
+    kiosk: KioskSettings,
+    /// The kiosk PIN was entered this session, so locked profiles can be edited and left.
+    kiosk_unlocked: bool,
+    /// PIN field of the lock screen and of the kiosk settings.
+    edit_kiosk_pin: String,
+    /// The last PIN entered to unlock was wrong.
+    kiosk_pin_wrong: bool,
+    /// Entity states, mirrored from the client's events while connected.
+    ha_states: BTreeMap<String, EntityState>,
+    ha_connected: bool,
+    /// Why the last connection attempt failed or dropped.
+    ha_error: Option<String>,
+    ha_events: Arc<std::sync::Mutex<Option<UnboundedReceiver<home_assistant::Event>>>>,
+    /// Shared with every engine; `spotify` actions control playback through it.
+    spotify: Spotify,
+    edit_spotify_client_id: String,
+    spotify_signed_in: bool,
+    spotify_signing_in: bool,
+    /// Why the last sign-in failed or was revoked.
+    spotify_error: Option<String>,
+    spotify_playback: Option<Playback>,
+    spotify_events: Arc<std::sync::Mutex<Option<UnboundedReceiver<spotify::Event>>>>,
+    /// Fetches and caches the reports `weather` keys show.
+    weather: Weather,
+    /// Latest report (or why there is none) by location and units of `weather` keys.
+    weather_reports: HashMap<WeatherQuery, Result<WeatherReport, String>>,
+    /// Fetches and caches the runs `ci_status` keys show, with the marketplace's GitHub token.
+    github_actions: GithubActions,
+    /// Latest run (`None` when there is none, or why it can't be read) of `ci_status` keys.
+    ci_runs: BTreeMap<CiQuery, Result<Option<CiRun>, String>>,
+    /// Whether the desktop's do-not-disturb is on, for `do_not_disturb` keys; `None` until
+    /// read, or when it cannot be.
+    do_not_disturb: Option<bool>,
+    /// Whether each radio `radio` keys switch is on; absent until read, or when it cannot be.
+    radios: BTreeMap<actions::RadioKind, bool>,
+    /// When each hold-to-confirm `power` key being held was pressed.
+    power_holds: BTreeMap<u8, Instant>,
+    /// Status of the repositories `git` keys show, by path; absent until read.
+    git_statuses: BTreeMap<String, Result<GitStatus, String>>,
+    /// Docker or Podman containers as last listed, for `container` keys and their picker; `None`
+    /// until listed.
+    containers: Option<Result<Vec<Container>, String>>,
+    /// MIDI output and input ports as last listed, for `midi` actions and MIDI triggers; `None`
+    /// until listed.
+    midi_output_ports: Option<Result<Vec<String>, String>>,
+    midi_input_ports: Option<Result<Vec<String>, String>>,
+    /// Serial ports as last listed, for `serial` actions; `None` until listed.
+    serial_ports: Option<Result<Vec<String>, String>>,
+    /// Last status of the `vs_code` tasks started from keys, by folder and label.
+    vscode_task_status: HashMap<(String, String), TaskStatus>,
+    /// Shared with every engine; `light` actions switch and dim through it.
+    lights: Lights,
+    lighting_settings: storage::settings::LightingSettings,
+    edit_hue_bridge: String,
+    hue_pairing: bool,
+    hue_pair_error: Option<String>,
+    /// Hue bridges and WLED controllers found on the network; `None` until searched for.
+    light_devices: Option<Result<Vec<Discovered>, String>>,
+    /// Lights of the paired Hue bridge, for the `light` picker; `None` until listed.
+    hue_lights: Option<Result<Vec<HueLight>, String>>,
+    /// State of the lights `light` keys and dials show; absent until read.
+    light_states: HashMap<actions::LightTarget, Result<LightState, String>>,
+    /// Shared with every engine, so they use one connection to Teams.
+    meetings: Meetings,
+    /// Where the user is in the meeting apps `meeting` keys control; absent until read.
+    meeting_states: BTreeMap<actions::MeetingApp, Result<MeetingState, String>>,
+    /// Shared with every engine; `{{secret:name}}` references in action fields read from it.
+    secrets: SecretStore,
+    /// Name and value fields of the secrets settings; stored on submit.
+    edit_secret_name: String,
+    edit_secret_value: String,
+    secret_saving: bool,
     actions: Vec<ActionChoice>,
+    /// Thumbnails of the icons plugin actions declare, keyed by file.
+    action_icons: HashMap<std::path::PathBuf, iced::widget::image::Handle>,
     action_search: String,
     install_plugin_path: String,
     active_view: ActiveView,
     marketplace: MarketplaceState,
     error: Option<String>,
-    next_action_seq_id: u64,
-    action_sequences: HashMap<u64, ActionSequence>,
-    sys: sysinfo::System,
-    sys_last_refresh: Instant,
-    sys_snapshot: SystemSnapshot,
+    monitoring: MetricsSampler,
+    monitoring_samples: Arc<std::sync::Mutex<Option<UnboundedReceiver<Sample>>>>,
+    /// Latest sample of the system metrics.
+    sys_snapshot: Sample,
+    /// Minute (since the Unix epoch) `clock` keys were last checked in.
+    clock_minute: i64,
     drag: DragState,
+    /// Key copied or cut in the editor, pasted with `KeyEdit::Paste`.
+    copied_key: Option<storage::profiles::KeyConfig>,
+    /// Open command palette (Ctrl+K).
+    palette: Option<PaletteState>,
+    /// Draw the keys in the preview as the device shows them instead of as labels.
+    live_preview: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PaletteState {
+    query: String,
+    /// Highlighted entry among the matches; Enter runs it.
+    selected: usize,
 }
 
+/// Widget id of the command palette's search field.
+const PALETTE_INPUT: &str = "command-palette";
+
+/// Matches listed by the command palette.
+const PALETTE_MAX_RESULTS: usize = 12;
+
+/// Widget id of the key inspector's label field, focused with Enter.
+const INSPECTOR_LABEL: &str = "inspector-label";
+
+/// How often the themes folder is checked for edited theme files.
+const THEMES_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the sync folder and the data directory are checked for changed files.
+const SYNC_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often `clock` keys check whether the minute changed.
+const CLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often running `pomodoro` keys redraw their countdown.
+const POMODORO_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often `weather` keys are checked; each report is fetched again once it is older than
+/// its binding's refresh interval.
+const WEATHER_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often `do_not_disturb` keys read the desktop's do-not-disturb, which other apps change
+/// too.
+const DO_NOT_DISTURB_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How often `radio` keys read whether their radio is on, e.g. after the airplane mode switch.
+const RADIO_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How often `git` keys read their repository's status, which edits and commits change.
+const GIT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often `ci_status` keys ask for their latest run; the client fetches less often without a
+/// GitHub token.
+const CI_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often `container` keys list the containers, which stop and start on their own too.
+const CONTAINER_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often `light` keys and dials read their light, which switches and other apps change too.
+const LIGHT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often `meeting` keys read whether a meeting is on and muted, which the app changes too.
+const MEETING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to wait for Hue bridges and WLED controllers to answer a search.
+const LIGHT_DISCOVERY_WAIT: Duration = Duration::from_secs(2);
+
+/// Size of plugin action icons in the actions panel, in pixels.
+const ACTION_ICON_SIZE: u32 = 20;
+
+/// Bounds and zoom step of the UI scale, in percent.
+const MIN_UI_SCALE: u16 = 50;
+const MAX_UI_SCALE: u16 = 200;
+const UI_SCALE_STEP: u16 = 10;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum SelectedControl {
     Key(usize),
@@ -94,7 +478,12 @@ enum BindingTarget {
     DialPress,
     DialRotate,
     TouchTap,
+    TouchLongPress,
     TouchDrag,
+    TouchDragLeft,
+    TouchDragRight,
+    TouchSwipeLeft,
+    TouchSwipeRight,
 }
 
 impl fmt::Display for BindingTarget {
@@ -104,7 +493,12 @@ impl fmt::Display for BindingTarget {
             BindingTarget::DialPress => write!(f, "Dial press"),
             BindingTarget::DialRotate => write!(f, "Dial rotate"),
             BindingTarget::TouchTap => write!(f, "Touch tap"),
+            BindingTarget::TouchLongPress => write!(f, "Touch long press"),
             BindingTarget::TouchDrag => write!(f, "Touch drag"),
+            BindingTarget::TouchDragLeft => write!(f, "Touch drag (left half)"),
+            BindingTarget::TouchDragRight => write!(f, "Touch drag (right half)"),
+            BindingTarget::TouchSwipeLeft => write!(f, "Swipe left"),
+            BindingTarget::TouchSwipeRight => write!(f, "Swipe right"),
         }
     }
 }
@@ -113,6 +507,16 @@ impl fmt::Display for BindingTarget {
 enum ActiveView {
     Main,
     Marketplace,
+    Schedules,
+    Webhooks,
+    TypedTriggers,
+    FileWatchers,
+    ProcessWatchers,
+    Midi,
+    Osc,
+    History,
+    Stats,
+    DeviceTest,
 }
 
 #[derive(Debug, Clone)]
@@ -121,9 +525,17 @@ struct MarketplaceState {
     selected_source_idx: Option<usize>,
     loading: bool,
     plugins: Vec<MarketplacePlugin>,
+    /// The index was served from the disk cache because the network was unreachable.
+    offline: bool,
     query: String,
+    category: Option<String>,
+    compatible_only: bool,
     error: Option<String>,
     icon_cache: HashMap<String, iced::widget::image::Handle>,
+    /// Icons queued or downloading, so paging back and forth does not request them again.
+    icons_pending: HashSet<String>,
+    /// Shared download queue for icons and screenshots.
+    downloader: IconDownloader,
     image_cache: HashMap<String, iced::widget::image::Handle>,
     svg_cache: HashMap<String, iced::widget::svg::Handle>,
     image_error_cache: HashMap<String, String>,
@@ -131,27 +543,134 @@ struct MarketplaceState {
     page: usize,
     installing: Option<String>,
     selected: Option<MarketplacePlugin>,
+    /// Plugin whose download failed checksum/signature verification; offers "Install anyway".
+    pending_override: Option<MarketplacePlugin>,
+    tab: MarketplaceTab,
+    gallery: ProfileGalleryState,
+}
+
+const ALL_CATEGORIES: &str = "All categories";
+
+impl MarketplaceState {
+    fn matches(&self, p: &MarketplacePlugin) -> bool {
+        if self.compatible_only && p.compatibility().is_incompatible() {
+            return false;
+        }
+        if let Some(cat) = &self.category {
+            if !p.has_category(cat) {
+                return false;
+            }
+        }
+        let q = self.query.trim().to_ascii_lowercase();
+        q.is_empty()
+            || p.name.to_ascii_lowercase().contains(&q)
+            || p.id.to_ascii_lowercase().contains(&q)
+            || p.description.to_ascii_lowercase().contains(&q)
+    }
+
+    /// Distinct categories in the loaded index, sorted case-insensitively.
+    fn categories(&self) -> Vec<String> {
+        let mut out: Vec<String> = vec![];
+        for c in self.plugins.iter().flat_map(|p| p.categories.iter()) {
+            if !out.iter().any(|o| o.eq_ignore_ascii_case(c)) {
+                out.push(c.clone());
+            }
+        }
+        out.sort_by_key(|c| c.to_ascii_lowercase());
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MarketplaceTab {
+    Plugins,
+    Profiles,
+}
+
+/// The marketplace's Profiles tab: profiles shared in a community gallery.
+#[derive(Debug, Clone, Default)]
+struct ProfileGalleryState {
+    loading: bool,
+    profiles: Vec<GalleryProfile>,
+    /// The index was served from the disk cache because the network was unreachable.
+    offline: bool,
+    query: String,
+    error: Option<String>,
+    selected: Option<GalleryProfile>,
+    /// The selected entry's profile, downloaded for the preview; `None` while downloading.
+    preview: Option<Result<Profile, String>>,
+    importing: bool,
+    /// The previewed profile was imported; importing it again would overwrite the copy.
+    imported: bool,
 }
 
+impl ProfileGalleryState {
+    fn matches(&self, p: &GalleryProfile) -> bool {
+        let q = self.query.trim().to_ascii_lowercase();
+        q.is_empty()
+            || p.name.to_ascii_lowercase().contains(&q)
+            || p.description.to_ascii_lowercase().contains(&q)
+            || p.categories
+                .iter()
+                .any(|c| c.to_ascii_lowercase().contains(&q))
+    }
+}
+
+/// A marketplace plugin to install, and where from.
 #[derive(Debug, Clone)]
-struct ActionSequence {
-    origin_control: InvocationControl,
-    origin_event: InvocationEvent,
-    steps: VecDeque<ActionStep>,
+struct PluginDownload {
+    plugin: MarketplacePlugin,
+    /// Direct download URL; without one, the latest release of `plugin.repository` is used.
+    url: Option<String>,
+}
+
+/// Plugins the loaded profile's bindings use that are not installed, and what the marketplace
+/// sources offer of them.
+#[derive(Debug, Clone, Default)]
+struct MissingPlugins {
+    ids: Vec<String>,
+    /// The missing plugins the marketplaces offer; `None` until they were looked up.
+    found: Option<Vec<PluginDownload>>,
+    installing: bool,
+    error: Option<String>,
+}
+
+impl MissingPlugins {
+    /// Whether `binding` (or a step or branch of it) uses a missing plugin.
+    fn affect(&self, binding: &ActionBinding) -> bool {
+        binding
+            .plugin_ids()
+            .iter()
+            .any(|id| self.ids.iter().any(|m| m == id))
+    }
 }
 
+/// Syncing with the folder in the sync settings.
 #[derive(Debug, Clone, Default)]
-struct SystemSnapshot {
-    cpu_percent: f32,
-    mem_used: u64,
-    mem_total: u64,
-    load: (f64, f64, f64),
+struct SyncState {
+    /// `storage::sync::modified` when the last sync started; a sync runs when it changes.
+    modified: Option<SystemTime>,
+    running: bool,
+    /// Files changed here and in the folder in ways that do not merge, until a side is picked.
+    conflicts: Vec<String>,
+    /// What the last sync did, or why it failed.
+    status: String,
+    /// Set once settings came from the folder; they take effect after a restart.
+    settings_pulled: bool,
+}
+
+#[derive(Debug, Clone)]
+struct MarketplaceInstallError {
+    message: String,
+    verification_failed: bool,
 }
 
 #[derive(Debug, Clone, Default)]
 struct DragState {
     dragging: Option<DraggedAction>,
     over_key: Option<usize>,
+    /// Key pressed in the preview; releasing the mouse over another key swaps the two.
+    moving_key: Option<usize>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -166,6 +685,8 @@ struct MarketplaceDetails {
     readme_md: Option<String>,
     /// Discovered image URLs from README and/or marketplace metadata.
     image_urls: Vec<String>,
+    /// Why the release details could not be fetched (e.g. the GitHub rate limit).
+    github_error: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -197,16 +718,46 @@ impl fmt::Display for MarketplaceSource {
 }
 
 const MARKETPLACE_PAGE_SIZE: usize = 50;
+/// Brightness (percent) of devices without a saved one.
+const DEFAULT_BRIGHTNESS: u8 = 30;
 
 impl Application for App {
     type Executor = iced::executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    type Flags = LaunchOptions;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
+    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
         let sources = default_marketplace_sources();
         let selected_source_idx = if sources.is_empty() { None } else { Some(0) };
+        let (tray_tx, tray_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (hotkey_tx, hotkey_rx) = tokio::sync::mpsc::unbounded_channel();
+        let secrets = SecretStore::load();
+        if let Err(e) = storage::settings::set_credential_store(Box::new(secrets.clone())) {
+            tracing::warn!(error = %e, "failed to keep the credentials in the secret store");
+        }
+        let settings = storage::settings::load_settings().unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to load settings; using defaults");
+            storage::settings::Settings::default()
+        });
+        let user_themes = storage::themes::list_themes().unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to load themes");
+            vec![]
+        });
+        let active_theme = ActiveTheme::resolve(&settings.interface.theme, &user_themes);
+
+        let variables = VariableStore::load();
+        let variable_changes = variables.subscribe();
+        let home_assistant = HomeAssistant::new();
+        let ha_events = home_assistant.subscribe();
+        let spotify = Spotify::new();
+        let spotify_events = spotify.subscribe();
+        let github_actions = GithubActions::new();
+        github_actions.set_token(settings.marketplace.github_token().map(str::to_string));
+        let lights = Lights::new();
+        lights.configure(settings.lighting.clone());
+        let monitoring = MetricsSampler::spawn();
+        let monitoring_samples = monitoring.subscribe();
 
         let app = Self {
             core: AppCore::new(),
@@ -215,18 +766,144 @@ impl Application for App {
             selected_device: None,
             connecting: false,
             connected: None,
+            engine_events: Arc::new(std::sync::Mutex::new(None)),
+            engine_session: 0,
+            paused: false,
+            dry_run: flags.dry_run,
+            tray: tray::TrayHandle::spawn(tray_tx),
+            tray_commands: Arc::new(std::sync::Mutex::new(Some(tray_rx))),
+            hotkeys: hotkeys::Hotkeys::new(hotkey_tx),
+            hotkey_events: Arc::new(std::sync::Mutex::new(Some(hotkey_rx))),
+            recording_hotkey: false,
+            recording_chord: false,
+            macro_recorder: None,
+            hid_diagnosis: None,
+            installing_udev_rule: false,
             profiles: vec![],
             profile_choices: vec![],
+            surfaces: vec![],
+            edit_surface_name: String::new(),
             selected_profile: None,
             profile: None,
             selected_control: None,
+            selected_keys: BTreeSet::new(),
+            modifiers: iced::keyboard::Modifiers::default(),
+            grid_focused: true,
+            focus_visible: false,
             selected_binding_target: BindingTarget::KeyPress,
+            testing_binding: false,
+            binding_test: None,
             edit_label: String::new(),
             edit_bg_rgb: String::new(),
             edit_icon_path: String::new(),
             edit_display_text: String::new(),
+            edit_entity_id: String::new(),
             plugins: vec![],
+            plugins_listed: false,
+            sandbox_enabled: openaction::registry::sandbox_enabled(),
+            permission_prompt: None,
+            plugin_details: None,
+            audio: audio::AudioPlayer::new(settings.audio_output_device.clone()),
+            audio_devices: vec![],
+            audio_output_device: settings.audio_output_device,
+            device_brightness: settings.device_brightness,
+            installed_apps: vec![],
+            vscode_workspaces: vec![],
+            vscode_tasks: None,
+            counters: BTreeMap::new(),
+            pomodoros: BTreeMap::new(),
+            plugin_keys: BTreeMap::new(),
+            profile_diagnostics: vec![],
+            missing_plugins: MissingPlugins::default(),
+            variable_values: variables.snapshot(),
+            variables,
+            variable_changes: Arc::new(std::sync::Mutex::new(Some(variable_changes))),
+            schedules: vec![],
+            webhooks: vec![],
+            typed_triggers: vec![],
+            process_watchers: vec![],
+            text_expander: settings.text_expander,
+            midi_triggers: vec![],
+            midi_settings: settings.midi,
+            osc_triggers: vec![],
+            edit_osc_port: settings.osc.port.to_string(),
+            osc_settings: settings.osc,
+            notification_mirror: settings.notification_mirror,
+            mirrored_notification: None,
+            activity: VecDeque::new(),
+            usage: UsageFile::new(),
+            usage_heatmap: false,
+            device_test: device_test::DeviceTest::default(),
+            activity_filter: String::new(),
+            activity_failures_only: false,
+            edit_webhook_port: settings.webhook.port.to_string(),
+            webhook_settings: settings.webhook,
+            edit_companion_address: format!(
+                "{}:{}",
+                settings.companion.host, settings.companion.port
+            ),
+            companion_settings: settings.companion,
+            touch_gestures: settings.touch_gestures,
+            macro_settings: settings.macros,
+            edit_ui_scale: settings.interface.scale_percent,
+            interface: settings.interface,
+            themes_modified: storage::themes::themes_modified(),
+            user_themes,
+            active_theme,
+            home_assistant,
+            edit_ha_url: settings.home_assistant.url.clone(),
+            edit_ha_token: settings.home_assistant.token.clone(),
+            home_assistant_settings: settings.home_assistant,
+            edit_github_token: settings.marketplace.github_token.clone(),
+            edit_profile_gallery_url: settings.marketplace.profile_gallery_url.clone(),
+            marketplace_settings: settings.marketplace,
+            edit_sync_folder: settings.sync.folder.clone(),
+            sync_settings: settings.sync,
+            sync: SyncState::default(),
+            kiosk: settings.kiosk,
+            kiosk_unlocked: false,
+            edit_kiosk_pin: String::new(),
+            kiosk_pin_wrong: false,
+            ha_states: BTreeMap::new(),
+            ha_connected: false,
+            ha_error: None,
+            ha_events: Arc::new(std::sync::Mutex::new(Some(ha_events))),
+            spotify,
+            edit_spotify_client_id: settings.spotify.client_id,
+            spotify_signed_in: false,
+            spotify_signing_in: false,
+            spotify_error: None,
+            spotify_playback: None,
+            spotify_events: Arc::new(std::sync::Mutex::new(Some(spotify_events))),
+            weather: Weather::new(),
+            weather_reports: HashMap::new(),
+            github_actions,
+            ci_runs: BTreeMap::new(),
+            do_not_disturb: None,
+            radios: BTreeMap::new(),
+            power_holds: BTreeMap::new(),
+            git_statuses: BTreeMap::new(),
+            containers: None,
+            midi_output_ports: None,
+            midi_input_ports: None,
+            serial_ports: None,
+            vscode_task_status: HashMap::new(),
+            lights,
+            edit_hue_bridge: settings.lighting.hue_bridge.clone(),
+            lighting_settings: settings.lighting,
+            hue_pairing: false,
+            hue_pair_error: None,
+            light_devices: None,
+            hue_lights: None,
+            light_states: HashMap::new(),
+            meetings: Meetings::new(),
+            meeting_states: BTreeMap::new(),
+            secrets,
+            edit_secret_name: String::new(),
+            edit_secret_value: String::new(),
+            secret_saving: false,
             actions: vec![],
+            action_icons: HashMap::new(),
             action_search: String::new(),
             install_plugin_path: String::new(),
             active_view: ActiveView::Main,
@@ -235,9 +912,14 @@ impl Application for App {
                 selected_source_idx,
                 loading: false,
                 plugins: vec![],
+                offline: false,
                 query: String::new(),
+                category: None,
+                compatible_only: true,
                 error: None,
                 icon_cache: HashMap::new(),
+                icons_pending: HashSet::new(),
+                downloader: IconDownloader::default(),
                 image_cache: HashMap::new(),
                 svg_cache: HashMap::new(),
                 image_error_cache: HashMap::new(),
@@ -245,14 +927,19 @@ impl Application for App {
                 page: 0,
                 installing: None,
                 selected: None,
+                pending_override: None,
+                tab: MarketplaceTab::Plugins,
+                gallery: ProfileGalleryState::default(),
             },
             error: None,
-            next_action_seq_id: 1,
-            action_sequences: HashMap::new(),
-            sys: sysinfo::System::new(),
-            sys_last_refresh: Instant::now(),
-            sys_snapshot: SystemSnapshot::default(),
+            monitoring,
+            monitoring_samples: Arc::new(std::sync::Mutex::new(Some(monitoring_samples))),
+            sys_snapshot: Sample::default(),
+            clock_minute: 0,
             drag: DragState::default(),
+            copied_key: None,
+            palette: None,
+            live_preview: true,
             show_color_picker: false,
         };
 
@@ -260,6 +947,22 @@ impl Application for App {
             Command::perform(list_devices_async(), Message::DevicesLoaded),
             Command::perform(list_profiles_async(), Message::ProfilesLoaded),
             Command::perform(list_plugins_async(), Message::PluginsLoaded),
+            Command::perform(list_audio_devices_async(), Message::AudioDevicesLoaded),
+            Command::perform(list_installed_apps_async(), Message::InstalledAppsLoaded),
+            Command::perform(list_vscode_folders_async(), Message::VsCodeFoldersLoaded),
+            Command::perform(load_schedules_async(), Message::SchedulesLoaded),
+            Command::perform(load_surfaces_async(), Message::SurfacesLoaded),
+            Command::perform(load_webhooks_async(), Message::WebhooksLoaded),
+            Command::perform(load_typed_triggers_async(), Message::TypedTriggersLoaded),
+            Command::perform(
+                load_process_watchers_async(),
+                Message::ProcessWatchersLoaded,
+            ),
+            Command::perform(load_midi_triggers_async(), Message::MidiTriggersLoaded),
+            Command::perform(load_osc_triggers_async(), Message::OscTriggersLoaded),
+            Command::perform(load_activity_async(), Message::ActivityLoaded),
+            Command::perform(load_usage_async(), Message::UsageLoaded),
+            Command::perform(resume_spotify_async(app.spotify.clone()), Message::SpotifySignedIn),
         ]);
         (app, cmd)
     }
@@ -268,21 +971,218 @@ impl Application for App {
         "RiverDeck-Redux".to_string()
     }
 
+    fn scale_factor(&self) -> f64 {
+        f64::from(self.interface.scale_percent) / 100.0
+    }
+
     fn theme(&self) -> Self::Theme {
-        // A more modern baseline look (affects all default widget styling).
-        Theme::TokyoNightStorm
+        self.active_theme.theme.clone()
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        // Drive device event polling + live system snapshot updates.
-        // Without this, `Message::Tick` is never emitted and the UI will not:
-        // - drain `ConnectedUi.events` (so presses/actions never run)
-        // - update `sys_snapshot` (so SystemMonitoring binding hints never change)
-        iced::time::every(Duration::from_millis(33)).map(|_| Message::Tick)
+        // The tick ends flashes. Action dispatch runs in the engine task and does not depend on
+        // it.
+        let mut subs = vec![
+            iced::time::every(Duration::from_millis(33)).map(|_| Message::Tick),
+            iced::time::every(THEMES_POLL_INTERVAL).map(|_| Message::CheckThemes),
+            iced::event::listen_with(|event, _status| match event {
+                iced::Event::Window(_, iced::window::Event::CloseRequested) => {
+                    Some(Message::WindowCloseRequested)
+                }
+                iced::Event::Keyboard(iced::keyboard::Event::ModifiersChanged(modifiers)) => {
+                    Some(Message::ModifiersChanged(modifiers))
+                }
+                // Also while a text field has focus.
+                iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                    key: iced::keyboard::Key::Character(c),
+                    modifiers,
+                    ..
+                }) if modifiers.command() && c.eq_ignore_ascii_case("k") => {
+                    Some(Message::TogglePalette)
+                }
+                // Text fields and overlays handle Escape themselves; the grid gets focus back.
+                iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                    key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
+                    ..
+                }) => Some(Message::FocusGrid),
+                iced::Event::Mouse(iced::mouse::Event::ButtonPressed(
+                    iced::mouse::Button::Left,
+                )) => Some(Message::PointerPressed),
+                _ => None,
+            }),
+        ];
+        subs.push(receiver_subscription(
+            "variable-changes",
+            0,
+            self.variable_changes.clone(),
+            Message::VariableChanged,
+        ));
+        subs.push(receiver_subscription(
+            "home-assistant",
+            0,
+            self.ha_events.clone(),
+            Message::HomeAssistantEvent,
+        ));
+        subs.push(receiver_subscription(
+            "spotify",
+            0,
+            self.spotify_events.clone(),
+            Message::SpotifyEvent,
+        ));
+        subs.push(receiver_subscription(
+            "system-monitoring",
+            0,
+            self.monitoring_samples.clone(),
+            Message::SystemSampled,
+        ));
+        if self.sync_settings.folder().is_some() {
+            subs.push(iced::time::every(SYNC_POLL_INTERVAL).map(|_| Message::CheckSync));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_clock) {
+            subs.push(iced::time::every(CLOCK_POLL_INTERVAL).map(|_| Message::ClockTick));
+        }
+        if self.pomodoros.values().any(PomodoroTimer::is_running) {
+            subs.push(iced::time::every(POMODORO_POLL_INTERVAL).map(|_| Message::PomodoroTick));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_weather) {
+            subs.push(iced::time::every(WEATHER_POLL_INTERVAL).map(|_| Message::RefreshWeather));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_dnd) {
+            let every = iced::time::every(DO_NOT_DISTURB_POLL_INTERVAL);
+            subs.push(every.map(|_| Message::CheckDoNotDisturb));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_radios) {
+            subs.push(iced::time::every(RADIO_POLL_INTERVAL).map(|_| Message::CheckRadios));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_ci) {
+            subs.push(iced::time::every(CI_POLL_INTERVAL).map(|_| Message::RefreshCi));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_git) {
+            subs.push(iced::time::every(GIT_POLL_INTERVAL).map(|_| Message::CheckGit));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_containers) {
+            let every = iced::time::every(CONTAINER_POLL_INTERVAL);
+            subs.push(every.map(|_| Message::ListContainers));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_lights) {
+            let every = iced::time::every(LIGHT_POLL_INTERVAL);
+            subs.push(every.map(|_| Message::CheckLights));
+        }
+        if self.profile.as_ref().is_some_and(profile_shows_meetings) {
+            let every = iced::time::every(MEETING_POLL_INTERVAL);
+            subs.push(every.map(|_| Message::CheckMeetings));
+        }
+        if self.hotkeys.is_some() {
+            subs.push(receiver_subscription(
+                "hotkeys",
+                0,
+                self.hotkey_events.clone(),
+                Message::Hotkey,
+            ));
+        }
+        if self.recording_hotkey {
+            subs.push(iced::keyboard::on_key_press(|key, modifiers| {
+                Some(Message::HotkeyRecorded(key, modifiers))
+            }));
+        } else if self.recording_chord {
+            subs.push(iced::keyboard::on_key_press(|key, modifiers| {
+                Some(Message::ChordRecorded(key, modifiers))
+            }));
+        } else if self.macro_recorder.is_some() {
+            subs.push(iced::keyboard::on_key_press(|key, modifiers| {
+                Some(Message::MacroKeyRecorded(key, modifiers))
+            }));
+        } else if !self.overlay_open() && !self.kiosk_locked() {
+            // Only presses no focused text field handled, so copying text still works.
+            subs.push(iced::keyboard::on_key_press(editor_key_press));
+        }
+        if self.palette.is_some() {
+            subs.push(iced::event::listen_with(|event, _status| {
+                use iced::keyboard::{key::Named, Event, Key};
+
+                let iced::Event::Keyboard(Event::KeyPressed {
+                    key: Key::Named(key),
+                    ..
+                }) = event
+                else {
+                    return None;
+                };
+                match key {
+                    Named::ArrowUp => Some(Message::PaletteMove(-1)),
+                    Named::ArrowDown => Some(Message::PaletteMove(1)),
+                    Named::Escape => Some(Message::ClosePalette),
+                    _ => None,
+                }
+            }));
+        }
+        if self.drag.moving_key.is_some() {
+            // Releasing the mouse anywhere but on a key ends the move.
+            subs.push(iced::event::listen_with(|event, _status| match event {
+                iced::Event::Mouse(iced::mouse::Event::ButtonReleased(
+                    iced::mouse::Button::Left,
+                )) => Some(Message::KeyMoveEnded),
+                _ => None,
+            }));
+        }
+        if self.tray.is_some() {
+            subs.push(receiver_subscription(
+                "tray-commands",
+                0,
+                self.tray_commands.clone(),
+                Message::Tray,
+            ));
+        }
+        if self.device_test.cycling {
+            subs.push(
+                iced::time::every(device_test::PATTERN_INTERVAL).map(|_| Message::KeyTestTick),
+            );
+        }
+        if self.device_test.monitoring() {
+            subs.push(receiver_subscription(
+                "hid-reports",
+                self.device_test.monitor_session,
+                self.device_test.report_slot.clone(),
+                Message::HidReport,
+            ));
+        }
+        if self.connected.is_some() {
+            subs.push(receiver_subscription(
+                "engine-notifications",
+                self.engine_session,
+                self.engine_events.clone(),
+                Message::Engine,
+            ));
+        }
+        Subscription::batch(subs)
+    }
+
+    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        // The kiosk lock leaves no way to another profile.
+        let switches = matches!(
+            message,
+            Message::TogglePalette
+                | Message::PaletteRun(_)
+                | Message::Tray(tray::TrayCommand::SwitchProfile(_))
+        );
+        if switches && self.kiosk_locked() {
+            return Command::none();
+        }
+        let cmd = self.handle_message(message);
+        self.sync_engine();
+        self.sync_tray();
+        self.sync_hotkeys();
+        self.home_assistant.configure(self.home_assistant_settings.clone());
+        cmd
+    }
+
+    fn view(&self) -> Element<'_, Self::Message> {
+        self.view_root()
     }
+}
 
-    fn update(&mut self, _message: Self::Message) -> Command<Self::Message> {
-        match _message {
+impl App {
+    fn handle_message(&mut self, message: Message) -> Command<Message> {
+        match message {
             Message::RefreshDevices => {
                 Command::perform(list_devices_async(), Message::DevicesLoaded)
             }
@@ -307,6 +1207,7 @@ impl Application for App {
                         self.error = Some(e);
                     }
                 }
+                let diagnose_cmd = Command::perform(diagnose_hid_async(), Message::HidDiagnosed);
                 // Auto-connect if possible (startup + after refresh).
                 if self.connected.is_none() && !self.connecting {
                     if let Some(id) = self.selected_device {
@@ -314,15 +1215,38 @@ impl Application for App {
                         self.error = None;
                         let events_slot: Arc<std::sync::Mutex<Option<Receiver<DeviceEvent>>>> =
                             Arc::new(std::sync::Mutex::new(None));
-                        return Command::perform(
-                            connect_device_async(id, events_slot),
-                            Message::Connected,
-                        );
+                        return Command::batch([
+                            diagnose_cmd,
+                            Command::perform(
+                                connect_device_async(id, events_slot),
+                                Message::Connected,
+                            ),
+                        ]);
                     }
                 }
 
+                diagnose_cmd
+            }
+            Message::HidDiagnosed(diagnosis) => {
+                self.hid_diagnosis = diagnosis;
                 Command::none()
             }
+            Message::InstallUdevRule => {
+                if self.installing_udev_rule {
+                    return Command::none();
+                }
+                self.installing_udev_rule = true;
+                Command::perform(install_udev_rule_async(), Message::UdevRuleInstalled)
+            }
+            Message::UdevRuleInstalled(res) => {
+                self.installing_udev_rule = false;
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to install udev rule: {e}"));
+                    return Command::none();
+                }
+                // Re-probe: the rule usually needs a replug before access is granted.
+                Command::perform(list_devices_async(), Message::DevicesLoaded)
+            }
             Message::DevicePicked(choice) => {
                 let id = choice.id;
                 self.selected_device = Some(id);
@@ -337,12 +1261,20 @@ impl Application for App {
                 self.profile_choices.clear();
                 self.error = None;
 
-                // Drop old connection and connect to the selected device.
-                self.connected = None;
+                // Drop old connection (blanking its keys) and connect to the selected device.
+                let clear = match self.connected.take() {
+                    Some(old) => {
+                        Command::perform(clear_device_async(old.controller), Message::DeviceCleared)
+                    }
+                    None => Command::none(),
+                };
                 self.connecting = true;
                 let events_slot: Arc<std::sync::Mutex<Option<Receiver<DeviceEvent>>>> =
                     Arc::new(std::sync::Mutex::new(None));
-                Command::perform(connect_device_async(id, events_slot), Message::Connected)
+                Command::batch([
+                    clear,
+                    Command::perform(connect_device_async(id, events_slot), Message::Connected),
+                ])
             }
             Message::Connected(res) => {
                 self.connecting = false;
@@ -358,21 +1290,70 @@ impl Application for App {
                         };
 
                         self.core.selected_device = Some(info.id);
-                        let brightness: u8 = 30;
+                        info.controller.set_gesture_thresholds(self.gesture_thresholds());
+                        let brightness = self.saved_brightness(info.id);
                         let pressed = vec![false; info.key_count as usize];
+                        let (engine, notifications) =
+                            EngineHandle::spawn(
+                                events,
+                                DeviceInfo {
+                                    id: info.id,
+                                    name: info.name.clone(),
+                                    capabilities: info.capabilities,
+                                    controller: info.controller.clone(),
+                                },
+                                brightness,
+                                self.audio.clone(),
+                                self.variables.clone(),
+                                Integrations {
+                                    home_assistant: self.home_assistant.clone(),
+                                    lights: self.lights.clone(),
+                                    meetings: self.meetings.clone(),
+                                    spotify: self.spotify.clone(),
+                                    secrets: self.secrets.clone(),
+                                },
+                            );
+                        engine.set_paused(self.paused);
+                        engine.set_dry_run(self.dry_run);
+                        engine.set_expand_limits(self.macro_settings.limits());
+                        *self.engine_events.lock().expect("engine events mutex poisoned") =
+                            Some(notifications);
+                        self.engine_session += 1;
                         self.connected = Some(ConnectedUi {
                             id: info.id,
                             name: info.name.clone(),
                             key_count: info.key_count,
+                            capabilities: info.capabilities,
                             pressed,
+                            playing_sounds: BTreeSet::new(),
                             brightness,
                             controller: info.controller.clone(),
-                            events,
+                            engine,
+                            display_cache: Arc::new(std::sync::Mutex::new(ImageCache::new())),
+                            companion_connected: false,
+                            reconnecting: false,
+                            screen_recording: false,
+                            flashing: HashMap::new(),
+                            previews: HashMap::new(),
+                            synced_profile: None,
+                            synced_plugins: None,
+                            synced_schedules: None,
+                            synced_webhooks: None,
+                            synced_typed_triggers: None,
+                            synced_process_watchers: None,
+                            synced_midi: None,
+                            synced_osc: None,
+                            synced_companion: None,
+                            synced_notification_mirror: None,
+                            synced_locked_profiles: None,
+                            synced_surface: None,
+                            surface_caches: HashMap::new(),
                         });
                         self.error = None;
 
-                        // Best-effort: apply a sane default brightness immediately after connect.
-                        // If the device was previously left at 0%, the screens can look "dead".
+                        // Best-effort: restore the device's last brightness (or a sane default)
+                        // right after connect. If the device was previously left at 0%, the
+                        // screens can look "dead".
                         let set_brightness_cmd = Command::perform(
                             set_brightness_async(info.controller, brightness),
                             Message::BrightnessApplied,
@@ -384,6 +1365,10 @@ impl Application for App {
                     Err(e) => {
                         self.connected = None;
                         self.error = Some(e);
+                        return Command::batch([
+                            Command::perform(diagnose_hid_async(), Message::HidDiagnosed),
+                            Command::perform(list_profiles_async(), Message::ProfilesLoaded),
+                        ]);
                     }
                 }
                 Command::perform(list_profiles_async(), Message::ProfilesLoaded)
@@ -395,7 +1380,14 @@ impl Application for App {
                 match res {
                     Ok(mut metas) => {
                         if let Some(c) = &self.connected {
-                            metas.retain(|m| m.key_count == c.key_count);
+                            // Profiles of the surfaces the deck is part of come along.
+                            let surfaces = &self.surfaces;
+                            metas.retain(|m| match m.surface {
+                                Some(id) => surfaces
+                                    .iter()
+                                    .any(|s| s.id == id && s.member(c.id).is_some()),
+                                None => m.key_count == c.key_count,
+                            });
                         }
                         self.profiles = metas;
                         self.profile_choices = self
@@ -426,7 +1418,12 @@ impl Application for App {
                         }
 
                         if self.selected_profile.is_none() {
-                            self.selected_profile = self.profiles.first().map(|p| p.id);
+                            // A kiosk starts on its locked profile.
+                            let locked = self
+                                .profiles
+                                .iter()
+                                .find(|p| self.kiosk.enabled() && self.kiosk.is_locked(p.id));
+                            self.selected_profile = locked.or(self.profiles.first()).map(|p| p.id);
                         }
 
                         if let Some(id) = self.selected_profile {
@@ -480,11 +1477,55 @@ impl Application for App {
                 match res {
                     Ok(p) => {
                         self.core.selected_profile = Some(p.id);
+                        self.counters.clear();
+                        self.pomodoros.clear();
+                        self.plugin_keys.clear();
+                        let id = p.id;
+                        let activated = self.profile.as_ref().map(|p| p.id) != Some(id);
+                        let brightness = p.brightness;
+                        let covered = p.keys.len();
                         self.profile = Some(p);
                         self.error = None;
+                        // Displays are pushed once the counter values are known.
+                        let loads = Command::batch(
+                            [
+                                Command::perform(load_counters_async(id), Message::CountersLoaded),
+                                Command::perform(
+                                    load_pomodoros_async(id),
+                                    Message::PomodorosLoaded,
+                                ),
+                                Command::perform(
+                                    validate_profile_async(id),
+                                    Message::ProfileValidated,
+                                ),
+                                self.refresh_weather(),
+                                self.check_do_not_disturb(),
+                                self.check_radios(),
+                                self.check_git(),
+                                self.list_containers(),
+                                self.refresh_ci(),
+                                self.check_lights(),
+                                self.check_meetings(),
+                                self.update_missing_plugins(),
+                            ]
+                            .into_iter()
+                            .chain(self.clear_uncovered_keys(covered)),
+                        );
+                        if !activated {
+                            return loads;
+                        }
+                        // The profile's brightness while it is active, else the device's own.
+                        let Some(device) = self.connected.as_ref().map(|c| c.id) else {
+                            return loads;
+                        };
+                        let brightness = brightness.unwrap_or(self.saved_brightness(device));
+                        return Command::batch([loads, self.apply_brightness(brightness)]);
                     }
                     Err(e) => {
+                        // The error lists the problems in the file.
                         self.profile = None;
+                        self.profile_diagnostics.clear();
+                        self.missing_plugins = MissingPlugins::default();
                         self.error = Some(e);
                     }
                 }
@@ -492,8 +1533,202 @@ impl Application for App {
                 // (Previously this only happened on edits/save.)
                 self.apply_displays_if_connected()
             }
+            Message::ProfileValidated((id, diagnostics)) => {
+                if self.profile.as_ref().map(|p| p.id) == Some(id) {
+                    self.profile_diagnostics = diagnostics;
+                }
+                Command::none()
+            }
+            Message::DismissProfileDiagnostics => {
+                self.profile_diagnostics.clear();
+                Command::none()
+            }
+            Message::CountersLoaded(res) => {
+                match res {
+                    Ok((id, counters)) if self.profile.as_ref().map(|p| p.id) == Some(id) => {
+                        self.counters = counters;
+                    }
+                    Ok(_) => return Command::none(),
+                    Err(e) => tracing::warn!(error = %e, "failed to load counter values"),
+                }
+                self.apply_displays_if_connected()
+            }
+            Message::PomodorosLoaded(res) => {
+                match res {
+                    Ok((id, timers)) if self.profile.as_ref().map(|p| p.id) == Some(id) => {
+                        self.pomodoros = timers;
+                    }
+                    Ok(_) => return Command::none(),
+                    Err(e) => tracing::warn!(error = %e, "failed to load Pomodoro timers"),
+                }
+                self.apply_displays_if_connected()
+            }
+            Message::VariableChanged(change) => {
+                match change.value {
+                    Some(v) => self.variable_values.insert(change.name, v),
+                    None => self.variable_values.remove(&change.name),
+                };
+                if self.profile.as_ref().is_some_and(profile_shows_variables) {
+                    self.apply_displays_if_connected()
+                } else {
+                    Command::none()
+                }
+            }
+            Message::HomeAssistantEvent(event) => {
+                let shown = match event {
+                    home_assistant::Event::Connected => {
+                        self.ha_connected = true;
+                        self.ha_error = None;
+                        return Command::none();
+                    }
+                    home_assistant::Event::Disconnected(reason) => {
+                        self.ha_connected = false;
+                        self.ha_error = Some(reason);
+                        self.ha_states.clear();
+                        self.profile.as_ref().is_some_and(|p| profile_shows_entity(p, None))
+                    }
+                    home_assistant::Event::State(state) => {
+                        let id = state.entity_id.clone();
+                        self.ha_states.insert(id.clone(), state);
+                        self.profile.as_ref().is_some_and(|p| profile_shows_entity(p, Some(&id)))
+                    }
+                };
+                if shown {
+                    self.apply_displays_if_connected()
+                } else {
+                    Command::none()
+                }
+            }
+            Message::ClearVariable(name) => {
+                if let Err(e) = self.variables.set(&name, None) {
+                    self.error = Some(format!("Failed to clear variable: {e}"));
+                }
+                Command::none()
+            }
+            Message::BuiltinSetVariableNameChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::SetVariable { name, .. } = b {
+                        *name = v.trim().to_string();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSetVariableValueChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::SetVariable { value, .. } = b {
+                        *value = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinScriptSourceChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Script { source, .. } = b {
+                        *source = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinHomeAssistantServiceChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::HomeAssistant { service, .. } = b {
+                        *service = v.trim().to_string();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinHomeAssistantEntityChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::HomeAssistant { entity_id, .. } = b {
+                        *entity_id = v.trim().to_string();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSpotifyCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Spotify { command } = b {
+                        *command = match c {
+                            SpotifyCommandChoice::PlayPause => actions::SpotifyCommand::PlayPause,
+                            SpotifyCommandChoice::Next => actions::SpotifyCommand::Next,
+                            SpotifyCommandChoice::Previous => actions::SpotifyCommand::Previous,
+                            SpotifyCommandChoice::Like => actions::SpotifyCommand::Like,
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinScreenshotRegionPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Screenshot { region, .. } = b {
+                        *region = match c {
+                            ScreenshotRegionChoice::FullScreen => {
+                                actions::ScreenshotRegion::FullScreen
+                            }
+                            ScreenshotRegionChoice::Selection => {
+                                actions::ScreenshotRegion::Selection
+                            }
+                            ScreenshotRegionChoice::ActiveWindow => {
+                                actions::ScreenshotRegion::ActiveWindow
+                            }
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinCaptureDirChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Screenshot { save_dir, .. }
+                    | BuiltinAction::ScreenRecord { save_dir } = b
+                    {
+                        *save_dir = Some(v.trim().to_string()).filter(|d| !d.is_empty());
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinScreenshotClipboardToggled(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Screenshot { clipboard, .. } = b {
+                        *clipboard = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinHomeAssistantDataChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::HomeAssistant { data, .. } = b {
+                        *data = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::CounterReset(key) => {
+                let Some(id) = self.profile.as_ref().map(|p| p.id) else {
+                    return Command::none();
+                };
+                Command::perform(reset_counter_async(id, key), Message::CountersLoaded)
+            }
+            Message::PomodoroReset(key) => {
+                // The engine keeps the running timers; it reports the reset like a press.
+                if let Some(c) = &self.connected {
+                    if let Err(e) = c.engine.reset_pomodoro(key) {
+                        self.error = Some(e.to_string());
+                    }
+                    return Command::none();
+                }
+                let Some(id) = self.profile.as_ref().map(|p| p.id) else {
+                    return Command::none();
+                };
+                Command::perform(reset_pomodoro_async(id, key), Message::PomodorosLoaded)
+            }
             Message::SelectControl(sel) => {
                 self.selected_control = Some(sel);
+                self.selected_keys.clear();
+                self.recording_hotkey = false;
+                self.recording_chord = false;
+                self.macro_recorder = None;
+                self.testing_binding = false;
+                self.binding_test = None;
                 self.selected_binding_target = match sel {
                     SelectedControl::Key(_) => BindingTarget::KeyPress,
                     SelectedControl::Dial(_) => BindingTarget::DialPress,
@@ -504,6 +1739,7 @@ impl Application for App {
                 self.edit_bg_rgb.clear();
                 self.edit_icon_path.clear();
                 self.edit_display_text.clear();
+                self.edit_entity_id.clear();
 
                 if let Some(p) = &self.profile {
                     match sel {
@@ -512,6 +1748,8 @@ impl Application for App {
                                 self.edit_label = k.label.clone();
                                 self.edit_icon_path = k.appearance.icon_path.clone().unwrap_or_default();
                                 self.edit_display_text = k.appearance.text.clone().unwrap_or_default();
+                                self.edit_entity_id =
+                                    k.appearance.entity_id.clone().unwrap_or_default();
                                 self.edit_bg_rgb = match k.appearance.background {
                                     storage::profiles::Background::Solid { rgb } => {
                                         format!("{},{},{}", rgb[0], rgb[1], rgb[2])
@@ -525,6 +1763,8 @@ impl Application for App {
                                 self.edit_label = d.label.clone();
                                 self.edit_icon_path = d.appearance.icon_path.clone().unwrap_or_default();
                                 self.edit_display_text = d.appearance.text.clone().unwrap_or_default();
+                                self.edit_entity_id =
+                                    d.appearance.entity_id.clone().unwrap_or_default();
                                 self.edit_bg_rgb = match d.appearance.background {
                                     storage::profiles::Background::Solid { rgb } => {
                                         format!("{},{},{}", rgb[0], rgb[1], rgb[2])
@@ -537,6 +1777,7 @@ impl Application for App {
                             let a = &p.touch_strip.appearance;
                             self.edit_icon_path = a.icon_path.clone().unwrap_or_default();
                             self.edit_display_text = a.text.clone().unwrap_or_default();
+                            self.edit_entity_id = a.entity_id.clone().unwrap_or_default();
                             self.edit_bg_rgb = match a.background {
                                 storage::profiles::Background::Solid { rgb } => {
                                     format!("{},{},{}", rgb[0], rgb[1], rgb[2])
@@ -575,31 +1816,38 @@ impl Application for App {
             }
             Message::BindingTargetPicked(t) => {
                 self.selected_binding_target = t;
+                self.testing_binding = false;
+                self.binding_test = None;
+                Command::none()
+            }
+            Message::TestBinding => {
+                let (Some(c), Some(ev)) = (&self.connected, self.test_event()) else {
+                    return Command::none();
+                };
+                self.testing_binding = true;
+                self.binding_test = None;
+                // Runs once this update is done, after `sync_engine` handed the engine the
+                // profile as edited.
+                Command::perform(c.engine.test_fire(ev), |res| {
+                    Message::BindingTested(res.map_err(|e| e.to_string()))
+                })
+            }
+            Message::BindingTested(res) => {
+                // Dropped when another binding was selected meanwhile.
+                if self.testing_binding {
+                    self.testing_binding = false;
+                    self.binding_test = Some(res);
+                }
                 Command::none()
             }
             Message::BgRgbChanged(v) => {
                 self.edit_bg_rgb = v;
-                if let (Some(sel), Some(p)) = (self.selected_control, &mut self.profile) {
-                    let bg = parse_bg_rgb(&self.edit_bg_rgb);
-                    let background = bg
-                        .map(|rgb| storage::profiles::Background::Solid { rgb })
-                        .unwrap_or(storage::profiles::Background::None);
-
-                    match sel {
-                        SelectedControl::Key(idx) => {
-                            if let Some(k) = p.keys.get_mut(idx) {
-                                k.appearance.background = background;
-                            }
-                        }
-                        SelectedControl::Dial(idx) => {
-                            if let Some(d) = p.dials.get_mut(idx) {
-                                d.appearance.background = background;
-                            }
-                        }
-                        SelectedControl::TouchStrip => {
-                            p.touch_strip.appearance.background = background;
-                        }
-                    }
+                let bg = parse_bg_rgb(&self.edit_bg_rgb);
+                let background = bg
+                    .map(|rgb| storage::profiles::Background::Solid { rgb })
+                    .unwrap_or(storage::profiles::Background::None);
+                for appearance in self.edited_appearances() {
+                    appearance.background = background.clone();
                 }
                 self.apply_displays_if_connected()
             }
@@ -649,6 +1897,51 @@ impl Application for App {
                 }
                 self.apply_displays_if_connected()
             }
+            Message::EntityIdChanged(v) => {
+                self.edit_entity_id = v;
+                if let (Some(sel), Some(p)) = (self.selected_control, &mut self.profile) {
+                    let val = self.edit_entity_id.trim();
+                    let new = if val.is_empty() { None } else { Some(val.to_string()) };
+                    match sel {
+                        SelectedControl::Key(idx) => {
+                            if let Some(k) = p.keys.get_mut(idx) {
+                                k.appearance.entity_id = new;
+                            }
+                        }
+                        SelectedControl::Dial(idx) => {
+                            if let Some(d) = p.dials.get_mut(idx) {
+                                d.appearance.entity_id = new;
+                            }
+                        }
+                        SelectedControl::TouchStrip => {
+                            p.touch_strip.appearance.entity_id = new;
+                        }
+                    }
+                }
+                self.apply_displays_if_connected()
+            }
+            Message::TextSizePicked(choice) => {
+                for appearance in self.edited_appearances() {
+                    appearance.text_size = choice.size();
+                }
+                self.apply_displays_if_connected()
+            }
+            Message::ClearSelectedActions => {
+                if let Some(p) = &mut self.profile {
+                    for &idx in &self.selected_keys {
+                        if let Some(k) = p.keys.get_mut(idx) {
+                            k.action = None;
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::FlashPicked(choice) => {
+                if let Some(appearance) = self.selected_appearance_mut() {
+                    appearance.flash = choice.effect();
+                }
+                Command::none()
+            }
             Message::SaveProfile => {
                 let Some(p) = self.profile.clone() else {
                     return Command::none();
@@ -658,15 +1951,21 @@ impl Application for App {
             Message::ProfileSaved(res) => {
                 match res {
                     Ok(()) => {
-                        // Best-effort: push LCD displays after saving.
-                        let Some(c) = self.connected.as_ref() else {
-                            return Command::none();
+                        // Saving rewrites the file; what was wrong with it may be gone.
+                        let validate = match &self.profile {
+                            Some(p) => Command::perform(
+                                validate_profile_async(p.id),
+                                Message::ProfileValidated,
+                            ),
+                            None => Command::none(),
                         };
-                        let Some(p) = self.profile.clone() else {
-                            return Command::none();
-                        };
-                        let controller = c.controller.clone();
-                        Command::perform(apply_displays_async(controller, p), Message::DisplaysApplied)
+                        // Best-effort: push LCD displays after saving.
+                        Command::batch([
+                            validate,
+                            self.refresh_weather(),
+                            self.update_missing_plugins(),
+                            self.apply_displays_if_connected(),
+                        ])
                     }
                     Err(e) => {
                         self.error = Some(e);
@@ -680,6 +1979,21 @@ impl Application for App {
                 }
                 Command::none()
             }
+            Message::PreviewsRendered(res) => {
+                match res {
+                    Ok(previews) => {
+                        if let Some(c) = &mut self.connected {
+                            c.previews.extend(previews);
+                        }
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+                Command::none()
+            }
+            Message::LivePreviewToggled(on) => {
+                self.live_preview = on;
+                Command::none()
+            }
             Message::RefreshPlugins => {
                 Command::perform(list_plugins_async(), Message::PluginsLoaded)
             }
@@ -687,13 +2001,35 @@ impl Application for App {
                 match res {
                     Ok(plugins) => {
                         self.plugins = plugins;
+                        self.plugins_listed = true;
                         self.actions = build_action_choices(&self.plugins);
                         self.error = None;
+                        let lookup = self.update_missing_plugins();
+                        let missing: BTreeSet<_> = self
+                            .actions
+                            .iter()
+                            .filter_map(|a| a.icon.clone())
+                            .filter(|icon| !self.action_icons.contains_key(icon))
+                            .collect();
+                        if !missing.is_empty() {
+                            return Command::batch([
+                                lookup,
+                                Command::perform(
+                                    load_action_icons_async(missing.into_iter().collect()),
+                                    Message::ActionIconsLoaded,
+                                ),
+                            ]);
+                        }
+                        return lookup;
                     }
                     Err(e) => self.error = Some(e),
                 }
                 Command::none()
             }
+            Message::ActionIconsLoaded(icons) => {
+                self.action_icons.extend(icons);
+                Command::none()
+            }
             Message::InstallPluginPathChanged(p) => {
                 self.install_plugin_path = p;
                 Command::none()
@@ -715,6 +2051,141 @@ impl Application for App {
                     Command::none()
                 }
             },
+            Message::PluginEnabledToggled { plugin_id, enabled } => {
+                if let Some(p) = self.plugins.iter_mut().find(|p| p.manifest.id == plugin_id) {
+                    p.enabled = enabled;
+                }
+                self.actions = build_action_choices(&self.plugins);
+                Command::perform(
+                    set_plugin_enabled_async(plugin_id, enabled),
+                    Message::PluginEnabledApplied,
+                )
+            }
+            Message::PluginEnabledApplied(res) => {
+                if let Err(e) = res {
+                    self.error = Some(e);
+                    return Command::perform(list_plugins_async(), Message::PluginsLoaded);
+                }
+                Command::none()
+            }
+            Message::SandboxToggled(enabled) => {
+                self.sandbox_enabled = enabled;
+                for p in &mut self.plugins {
+                    p.sandboxed = enabled;
+                }
+                Command::perform(set_sandbox_enabled_async(enabled), Message::SandboxApplied)
+            }
+            Message::TogglePluginDetails(plugin_id) => {
+                let open = self.plugin_details.as_ref().map(|d| &d.plugin_id) == Some(&plugin_id);
+                let plugin = self.plugins.iter().find(|p| p.manifest.id == plugin_id);
+                self.plugin_details = plugin.filter(|_| !open).map(PluginDetails::of);
+                Command::none()
+            }
+            Message::PluginDetailsEdited(edit) => {
+                let Some(details) = &mut self.plugin_details else {
+                    return Command::none();
+                };
+                match edit {
+                    PluginDetailsEdit::Args(args) => details.args = args,
+                    PluginDetailsEdit::Cwd(cwd) => details.cwd = cwd,
+                    PluginDetailsEdit::MaxConcurrent(n) => details.max_concurrent = n,
+                    PluginDetailsEdit::EnvKey(i, key) => {
+                        if let Some(var) = details.env.get_mut(i) {
+                            var.0 = key;
+                        }
+                    }
+                    PluginDetailsEdit::EnvValue(i, value) => {
+                        if let Some(var) = details.env.get_mut(i) {
+                            var.1 = value;
+                        }
+                    }
+                    PluginDetailsEdit::AddEnv => details.env.push(Default::default()),
+                    PluginDetailsEdit::RemoveEnv(i) => {
+                        if i < details.env.len() {
+                            details.env.remove(i);
+                        }
+                    }
+                    PluginDetailsEdit::Save => {
+                        let launch = match details.to_launch() {
+                            Ok(launch) => launch,
+                            Err(e) => {
+                                self.error = Some(e);
+                                return Command::none();
+                            }
+                        };
+                        let plugin_id = details.plugin_id.clone();
+                        let plugin = self.plugins.iter_mut().find(|p| p.manifest.id == plugin_id);
+                        if let Some(p) = plugin {
+                            p.launch = launch.clone();
+                        }
+                        self.plugin_details = None;
+                        return Command::perform(
+                            set_plugin_launch_async(plugin_id, launch),
+                            Message::PluginLaunchApplied,
+                        );
+                    }
+                }
+                Command::none()
+            }
+            Message::SandboxApplied(res)
+            | Message::PluginPermissionApplied(res)
+            | Message::PluginLaunchApplied(res) => {
+                if let Err(e) = res {
+                    self.error = Some(e);
+                    return Command::perform(list_plugins_async(), Message::PluginsLoaded);
+                }
+                Command::none()
+            }
+            Message::ShowPermissionPrompt(plugin_id) => {
+                self.permission_prompt = Some(plugin_id);
+                Command::none()
+            }
+            Message::PluginPermissionDecided { plugin_id, allow } => {
+                self.permission_prompt = None;
+                if let Some(p) = self.plugins.iter_mut().find(|p| p.manifest.id == plugin_id) {
+                    p.network_granted = Some(allow);
+                }
+                Command::perform(
+                    set_network_permission_async(plugin_id, allow),
+                    Message::PluginPermissionApplied,
+                )
+            }
+            Message::DismissPermissionPrompt => {
+                self.permission_prompt = None;
+                Command::none()
+            }
+            Message::UninstallPlugin(plugin_id) => {
+                Command::perform(uninstall_plugin_async(plugin_id), Message::PluginUninstalled)
+            }
+            Message::PluginUninstalled(res) => match res {
+                Ok(report) => {
+                    // The registry rewrote profiles on disk; mirror that in the open (possibly
+                    // unsaved) profile so a later save doesn't resurrect dangling bindings.
+                    let mut cleared = report.cleared_bindings + report.cleared_triggers;
+                    let id = report.plugin_id.as_str();
+                    if let Some(p) = &mut self.profile {
+                        cleared += storage::profiles::strip_plugin_bindings(p, id);
+                    }
+                    // Same for the trigger stores, which `sync_engine` pushes to the engine.
+                    strip_plugin_triggers(&mut self.schedules, id, |t| &mut t.action);
+                    strip_plugin_triggers(&mut self.webhooks, id, |t| &mut t.action);
+                    strip_plugin_triggers(&mut self.typed_triggers, id, |t| &mut t.action);
+                    strip_plugin_triggers(&mut self.process_watchers, id, |t| &mut t.action);
+                    strip_plugin_triggers(&mut self.midi_triggers, id, |t| &mut t.action);
+                    strip_plugin_triggers(&mut self.osc_triggers, id, |t| &mut t.action);
+                    if cleared > 0 {
+                        self.error = Some(format!(
+                            "Uninstalled {}; cleared {} binding(s) that referenced it.",
+                            report.plugin_id, cleared
+                        ));
+                    }
+                    Command::perform(list_plugins_async(), Message::PluginsLoaded)
+                }
+                Err(e) => {
+                    self.error = Some(e);
+                    Command::none()
+                }
+            },
             Message::OpenMarketplace => {
                 self.active_view = ActiveView::Marketplace;
                 self.marketplace.page = 0;
@@ -743,4542 +2214,15370 @@ impl Application for App {
                 self.active_view = ActiveView::Main;
                 Command::none()
             }
-            Message::MarketplaceRefresh => {
-                let Some(idx) = self.marketplace.selected_source_idx else {
-                    self.marketplace.loading = false;
-                    self.marketplace.error = Some("No marketplace selected.".to_string());
-                    return Command::none();
-                };
-                let url = self.marketplace.sources.get(idx).map(|s| s.index_url.clone());
-                let Some(url) = url else {
-                    self.marketplace.loading = false;
-                    self.marketplace.error = Some("Invalid marketplace selection.".to_string());
+            Message::Hotkey(ev) => {
+                let Some(key) = self.hotkeys.as_ref().and_then(|h| h.key_for(ev)) else {
                     return Command::none();
                 };
-                if url.trim().is_empty() {
-                    self.marketplace.loading = false;
-                    self.marketplace.error =
-                        Some("Marketplace URL is not configured.".to_string());
-                    return Command::none();
+                if let Some(c) = &self.connected {
+                    c.engine.press_control(ControlEvent {
+                        control: ControlId::Key(key),
+                        kind: if ev.pressed {
+                            ControlEventKind::Down
+                        } else {
+                            ControlEventKind::Up
+                        },
+                    });
                 }
-                self.marketplace.loading = true;
-                self.marketplace.error = None;
-                Command::perform(fetch_marketplace_async(url), Message::MarketplaceLoaded)
+                Command::none()
             }
-            Message::MarketplaceSourcePicked(src) => {
-                let idx = self.marketplace.sources.iter().position(|s| s == &src);
-                self.marketplace.selected_source_idx = idx;
-                Command::perform(async { () }, |_| Message::MarketplaceRefresh)
+            Message::RecordHotkey => {
+                self.recording_hotkey = true;
+                Command::none()
             }
-            Message::MarketplaceSearchChanged(q) => {
-                self.marketplace.query = q;
-                self.marketplace.page = 0;
+            Message::HotkeyRecorded(key, modifiers) => {
+                use iced::keyboard::{key::Named, Key};
+
+                if key == Key::Named(Named::Escape) && modifiers.is_empty() {
+                    self.recording_hotkey = false;
+                } else if let Some(hotkey) = hotkeys::from_key_press(&key, modifiers) {
+                    self.recording_hotkey = false;
+                    self.set_selected_hotkey(Some(hotkey));
+                }
                 Command::none()
             }
-            Message::MarketplaceLoaded(res) => {
-                self.marketplace.loading = false;
-                match res {
-                    Ok(list) => {
-                        self.marketplace.plugins = list;
-                        self.marketplace.error = None;
-                        // Kick off icon fetches for the current marketplace source.
-                        if let Some(src) = self.current_marketplace_source().cloned() {
-                            let mut cmds = vec![];
-                            let q = self.marketplace.query.trim().to_ascii_lowercase();
-                            for p in self
-                                .marketplace
-                                .plugins
-                                .iter()
-                                .filter(|p| {
-                                    if q.is_empty() {
-                                        true
-                                    } else {
-                                        p.name.to_ascii_lowercase().contains(&q)
-                                            || p.id.to_ascii_lowercase().contains(&q)
-                                            || p.description.to_ascii_lowercase().contains(&q)
-                                    }
-                                })
-                                .skip(self.marketplace.page.saturating_mul(MARKETPLACE_PAGE_SIZE))
-                                .take(MARKETPLACE_PAGE_SIZE)
-                            {
-                                let Some(icon_url) = marketplace_icon_url(&src, p) else {
-                                    continue;
-                                };
-                                let key = format!("{}|{}", src.index_url, p.id);
-                                if self.marketplace.icon_cache.contains_key(&key) {
-                                    continue;
-                                }
-                                cmds.push(Command::perform(
-                                    fetch_icon_async(icon_url),
-                                    {
-                                        let key = key.clone();
-                                        move |bytes| Message::MarketplaceIconLoaded { key, bytes }
-                                    },
-                                ));
-                            }
-                            return Command::batch(cmds);
+            Message::RecordChord => {
+                self.recording_chord = true;
+                Command::none()
+            }
+            Message::ChordRecorded(key, modifiers) => {
+                use iced::keyboard::{key::Named, Key};
+
+                if key == Key::Named(Named::Escape) && modifiers.is_empty() {
+                    self.recording_chord = false;
+                } else if let Some(recorded) = macro_recorder::key_chord(&key, modifiers) {
+                    // Lone modifiers make no chord, so the wait goes on until the key comes.
+                    self.recording_chord = false;
+                    self.update_selected_builtin(|b| {
+                        if let BuiltinAction::KeyboardInput { chord, .. } = b {
+                            *chord = Some(recorded);
                         }
+                    });
+                }
+                Command::none()
+            }
+            Message::ClearChord => {
+                self.recording_chord = false;
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::KeyboardInput { chord, .. } = b {
+                        *chord = None;
                     }
-                    Err(e) => {
-                        self.marketplace.plugins.clear();
-                        self.marketplace.error = Some(e);
-                    }
+                });
+                Command::none()
+            }
+            Message::ClearHotkey => {
+                self.recording_hotkey = false;
+                self.set_selected_hotkey(None);
+                Command::none()
+            }
+            Message::OpenSchedules => {
+                self.active_view = ActiveView::Schedules;
+                Command::none()
+            }
+            Message::CloseSchedules => {
+                self.active_view = ActiveView::Main;
+                Command::none()
+            }
+            Message::SchedulesLoaded(res) => {
+                match res {
+                    Ok(schedules) => self.schedules = schedules,
+                    Err(e) => self.error = Some(format!("Failed to load schedules: {e}")),
                 }
                 Command::none()
             }
-            Message::MarketplaceInstall(p) => {
-                if self.marketplace.installing.is_some() {
-                    return Command::none();
+            Message::SchedulesSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save schedules: {e}"));
                 }
-
-                let Some(src) = self.current_marketplace_source().cloned() else {
-                    self.marketplace.error = Some("No marketplace selected.".to_string());
+                Command::none()
+            }
+            Message::AddSchedule => {
+                let id = self.schedules.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+                self.schedules.push(Schedule {
+                    id,
+                    name: format!("Schedule {id}"),
+                    enabled: true,
+                    cron: "0 9 * * *".to_string(),
+                    action: TriggerActionChoice::IssueCommand.default_action(None),
+                });
+                self.save_schedules()
+            }
+            Message::RemoveSchedule(id) => {
+                self.schedules.retain(|s| s.id != id);
+                self.save_schedules()
+            }
+            Message::ScheduleEdited { id, edit } => {
+                let Some(schedule) = self.schedules.iter_mut().find(|s| s.id == id) else {
                     return Command::none();
                 };
-
-                // Prefer a direct download URL from the marketplace feed.
-                let url = resolve_marketplace_download_url(&src, &p);
-
-                if self.plugins.iter().any(|ip| ip.manifest.id == p.id) {
-                    return Command::none();
-                }
-
-                self.marketplace.installing = Some(p.id.clone());
-                self.marketplace.error = None;
-                if let Some(url) = url {
-                    Command::perform(
-                        install_marketplace_async(url, p.id),
-                        Message::MarketplaceInstalled,
-                    )
-                } else if let Some(repo) = p.repository.clone() {
-                    // Rivul marketplace derives downloads from the GitHub repository.
-                    // We attempt to resolve a release asset URL and install it.
-                    Command::perform(
-                        install_marketplace_from_repo_async(repo, p.id),
-                        Message::MarketplaceInstalled,
-                    )
-                        } else {
-                    self.marketplace.installing = None;
-                    self.marketplace.error =
-                        Some("No installable download found for this plugin.".to_string());
-                    Command::none()
+                match edit {
+                    ScheduleEdit::Name(name) => schedule.name = name,
+                    ScheduleEdit::Cron(cron) => schedule.cron = cron,
+                    ScheduleEdit::Enabled(enabled) => schedule.enabled = enabled,
+                    ScheduleEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut schedule.action, edit, plugins, choices)
+                    }
                 }
+                self.save_schedules()
             }
-            Message::MarketplaceInstalled(res) => {
-                self.marketplace.installing = None;
+            Message::OpenHistory => {
+                self.active_view = ActiveView::History;
+                Command::none()
+            }
+            Message::OpenDeviceTest => {
+                self.active_view = ActiveView::DeviceTest;
+                Command::perform(list_hid_interfaces_async(), Message::HidInterfacesListed)
+            }
+            Message::CloseDeviceTest => {
+                self.active_view = ActiveView::Main;
+                self.device_test.stop_monitor();
+                self.stop_key_test()
+            }
+            Message::RefreshHidInterfaces => {
+                Command::perform(list_hid_interfaces_async(), Message::HidInterfacesListed)
+            }
+            Message::HidInterfacesListed(res) => {
+                let t = &mut self.device_test;
                 match res {
-                    Ok(()) => {
-                        self.marketplace.error = None;
-                        Command::perform(list_plugins_async(), Message::PluginsLoaded)
+                    Ok(interfaces) => t.interfaces = interfaces,
+                    Err(e) => t.error = Some(format!("Failed to list HID devices: {e}")),
+                }
+                if !t.monitoring() {
+                    // Keep the interface picked before while it is still there.
+                    let pick = t
+                        .selected
+                        .clone()
+                        .filter(|s| t.interfaces.contains(s))
+                        .or_else(|| t.interfaces.first().cloned());
+                    if let Some(interface) = pick {
+                        t.error = t.start_monitor(interface).err();
                     }
+                }
+                Command::none()
+            }
+            Message::HidInterfacePicked(interface) => {
+                self.device_test.error = self.device_test.start_monitor(interface).err();
+                Command::none()
+            }
+            Message::HidReport(Ok(report)) => {
+                self.device_test.push_report(report);
+                Command::none()
+            }
+            Message::HidReport(Err(e)) => {
+                self.device_test.stop_monitor();
+                self.device_test.error = Some(e);
+                Command::none()
+            }
+            Message::ClearHidReports => {
+                self.device_test.clear_reports();
+                Command::none()
+            }
+            Message::SetKeyTestCycling(true) => {
+                self.device_test.cycling = true;
+                self.write_next_test_pattern()
+            }
+            Message::SetKeyTestCycling(false) => self.stop_key_test(),
+            Message::KeyTestTick => self.write_next_test_pattern(),
+            Message::TestPatternWritten(res) => {
+                self.device_test.writing = false;
+                match res {
+                    Ok(times) => self.device_test.record_writes(&times),
                     Err(e) => {
-                        self.marketplace.error = Some(e);
-                        Command::none()
+                        self.device_test.cycling = false;
+                        self.device_test.error = Some(format!("Failed to draw test pattern: {e}"));
                     }
                 }
-            }
-            Message::MarketplacePrevPage => {
-                if self.marketplace.page == 0 {
+                if self.device_test.cycling {
                     return Command::none();
                 }
-                self.marketplace.page = self.marketplace.page.saturating_sub(1);
-                self.marketplace_fetch_icons_for_current_page()
+                // Stopped while the pattern was being written.
+                self.redraw_displays()
             }
-            Message::MarketplaceNextPage => {
-                self.marketplace.page = self.marketplace.page.saturating_add(1);
-                self.marketplace_fetch_icons_for_current_page()
+            Message::CloseHistory => {
+                self.active_view = ActiveView::Main;
+                Command::none()
             }
-            Message::MarketplaceIconLoaded { key, bytes } => {
-                if let Ok(bytes) = bytes {
-                    self.marketplace
-                        .icon_cache
-                        .insert(key, iced::widget::image::Handle::from_memory(bytes));
+            Message::ActivityLoaded(res) => {
+                match res {
+                    Ok(mut loaded) => {
+                        // Keep whatever already ran while the file was being read.
+                        for entry in self.activity.drain(..) {
+                            storage::activity::push_entry(&mut loaded, entry);
+                        }
+                        self.activity = loaded;
+                    }
+                    Err(e) => self.error = Some(format!("Failed to load activity log: {e}")),
                 }
                 Command::none()
             }
-            Message::MarketplaceSelect(p) => {
-                self.marketplace.selected = Some(p.clone());
-                self.marketplace.error = None;
-
-                let Some(src) = self.current_marketplace_source().cloned() else {
-                    return Command::none();
-                };
-
-                let mut cmds = vec![];
-
-                // Ensure the selected icon is fetched (may not be in visible range).
-                if let Some(icon_url) = marketplace_icon_url(&src, &p) {
-                    let key = format!("{}|{}", src.index_url, p.id);
-                    if !self.marketplace.icon_cache.contains_key(&key) {
-                        cmds.push(Command::perform(
-                            fetch_icon_async(icon_url),
-                            {
-                                let key = key.clone();
-                                move |bytes| Message::MarketplaceIconLoaded { key, bytes }
-                            },
-                        ));
-                    }
-                }
-
-                // Fetch screenshots / images lazily.
-                for raw in p.images.iter().take(8) {
-                    let Some(url) = resolve_marketplace_asset_url(&src, raw) else {
-                        continue;
-                    };
-                    let key = format!("img|{}|{}|{}", src.index_url, p.id, url);
-                    if self.marketplace.image_cache.contains_key(&key)
-                        || self.marketplace.svg_cache.contains_key(&key)
-                    {
-                        continue;
-                    }
-                    cmds.push(Command::perform(
-                        fetch_icon_async(url),
-                        {
-                            let key = key.clone();
-                            move |bytes| Message::MarketplaceImageLoaded { key, bytes }
-                        },
-                    ));
-                }
-
-                // Fetch richer details (README, derived downloads, README images).
-                if !self.marketplace.details_cache.contains_key(&p.id) {
-                    cmds.push(Command::perform(
-                        fetch_marketplace_details_async(p.clone()),
-                        {
-                            let plugin_id = p.id.clone();
-                            move |details| Message::MarketplaceDetailsLoaded { plugin_id, details }
-                        },
-                    ));
+            Message::ActivitySaved(res) => {
+                if let Err(e) = res {
+                    tracing::warn!(error = %e, "failed to save activity log");
                 }
-
-                if cmds.is_empty() {
-                    Command::none()
-                } else {
-                Command::batch(cmds)
+                Command::none()
             }
+            Message::ActivityFilterChanged(v) => {
+                self.activity_filter = v;
+                Command::none()
             }
-            Message::MarketplaceImageLoaded { key, bytes } => {
-                match bytes {
-                    Ok(bytes) => {
-                        self.marketplace.image_error_cache.remove(&key);
-
-                        if is_svg_bytes(&bytes) {
-                            if let Err(e) = validate_svg_bytes(&bytes) {
-                                self.marketplace.image_error_cache.insert(key, format!("bad svg: {e}"));
-                                return Command::none();
-                            }
-                            self.marketplace
-                                .svg_cache
-                                .insert(key, iced::widget::svg::Handle::from_memory(bytes));
-                        } else if is_png_bytes(&bytes) || is_jpeg_bytes(&bytes) {
-                            self.marketplace
-                                .image_cache
-                                .insert(key, iced::widget::image::Handle::from_memory(bytes));
-                        } else if is_gif_bytes(&bytes) || is_webp_bytes(&bytes) {
-                            // Iced's image handle decoding may not support animated GIF/WEBP directly
-                            // (or may fail on some files). Decode via `image` and re-encode to PNG.
-                            match decode_raster_to_png_bytes(&bytes) {
-                                Ok(png) => {
-                                    self.marketplace.image_cache.insert(
-                                        key,
-                                        iced::widget::image::Handle::from_memory(png),
-                                    );
-                                }
-                                Err(e) => {
-                                    self.marketplace
-                                        .image_error_cache
-                                        .insert(key, format!("gif/webp decode failed: {e}"));
-                                }
-                            }
-                        } else {
-                            self.marketplace.image_error_cache.insert(
-                                key,
-                                "unsupported image format (expected svg/png/jpeg/gif/webp)".to_string(),
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        self.marketplace.image_error_cache.insert(key, e);
-                    }
-                }
+            Message::ActivityFailuresOnly(v) => {
+                self.activity_failures_only = v;
                 Command::none()
             }
-            Message::MarketplaceDetailsLoaded { plugin_id, details } => {
-                match details {
-                    Ok(d) => {
-                        // Merge cache.
-                        let mut merged = d.clone();
-                        if let Some(p) = self.marketplace.plugins.iter().find(|p| p.id == plugin_id) {
-                            // include any marketplace-provided images too
-                            for u in &p.images {
-                                if !merged.image_urls.contains(u) {
-                                    merged.image_urls.push(u.clone());
-                                }
-                            }
-                        }
-                        self.marketplace.details_cache.insert(plugin_id.clone(), merged.clone());
-
-                        // If we discovered a direct download URL, update the marketplace plugin in-place
-                        // so the list can show Install immediately.
-                        if let Some(url) = merged.resolved_download_url.clone() {
-                            if let Some(p) = self
-                                .marketplace
-                                .plugins
-                                .iter_mut()
-                                .find(|p| p.id == plugin_id)
-                            {
-                                if p.download_url.as_deref().map(|s| s.trim()).unwrap_or("").is_empty()
-                                {
-                                    p.download_url = Some(url.clone());
-                                }
-                            }
-                            if let Some(sel) = self.marketplace.selected.as_mut() {
-                                if sel.id == plugin_id
-                                    && sel.download_url.as_deref().map(|s| s.trim()).unwrap_or("").is_empty()
-                                {
-                                    sel.download_url = Some(url);
-                                }
-                            }
-                        }
-
-                        // Kick off image fetches for discovered URLs (best-effort).
-                        let Some(src) = self.current_marketplace_source().cloned() else {
-                            return Command::none();
-                        };
-                        let mut cmds = vec![];
-                        for raw in merged.image_urls.iter().take(12) {
-                            if !is_renderable_image_url(raw) {
-                                continue;
-                            }
-                            let Some(url) = resolve_marketplace_asset_url(&src, raw) else {
-                                continue;
-                            };
-                            let key = format!("img|{}|{}|{}", src.index_url, plugin_id, url);
-                            if self.marketplace.image_cache.contains_key(&key)
-                                || self.marketplace.svg_cache.contains_key(&key)
-                            {
-                                continue;
-                            }
-                            cmds.push(Command::perform(
-                                fetch_icon_async(url),
-                                {
-                                    let key = key.clone();
-                                    move |bytes| Message::MarketplaceImageLoaded { key, bytes }
-                                },
-                            ));
-                        }
-                        if cmds.is_empty() {
-                            Command::none()
-                        } else {
-                            Command::batch(cmds)
-                        }
-                    }
-                    Err(e) => {
-                        // Keep the UI usable even if GitHub is rate-limited.
-                        tracing::debug!(plugin_id, error=%e, "marketplace details fetch failed");
-                        Command::none()
+            Message::ClearActivity => {
+                self.activity.clear();
+                self.save_activity()
+            }
+            Message::OpenStats => {
+                self.active_view = ActiveView::Stats;
+                Command::none()
+            }
+            Message::CloseStats => {
+                self.active_view = ActiveView::Main;
+                Command::none()
+            }
+            Message::UsageLoaded(res) => {
+                match res {
+                    Ok(mut loaded) => {
+                        // Keep whatever ran while the file was being read.
+                        storage::usage::merge(&mut loaded, std::mem::take(&mut self.usage));
+                        self.usage = loaded;
                     }
+                    Err(e) => self.error = Some(format!("Failed to load usage statistics: {e}")),
                 }
+                Command::none()
             }
-            Message::OpenUrl(url) => {
-                Command::perform(open_url_async(url), |_| Message::Tick)
-            }
-            Message::ActionSeqContinue(seq_id) => self.run_next_action_step(seq_id),
-            Message::ActionSeqStepDone { seq_id, res } => {
+            Message::UsageSaved(res) => {
                 if let Err(e) = res {
-                    tracing::error!(seq_id, error = %e, "action step failed");
-                    self.error = Some(e);
-                } else {
-                    tracing::debug!(seq_id, "action step completed");
+                    tracing::warn!(error = %e, "failed to save usage statistics");
                 }
-                self.run_next_action_step(seq_id)
+                Command::none()
             }
-            Message::ActionModePicked(mode) => {
-                self.set_selected_action_mode(mode);
+            Message::SetUsageHeatmap(on) => {
+                self.usage_heatmap = on;
                 Command::none()
             }
-            Message::BuiltinKindPicked(kind) => {
-                self.set_selected_builtin_kind(kind);
+            Message::ResetUsage => {
+                let Some(p) = &self.profile else {
+                    return Command::none();
+                };
+                self.usage.remove(&p.id.0);
+                self.save_usage()
+            }
+            Message::OpenWebhooks => {
+                self.active_view = ActiveView::Webhooks;
                 Command::none()
             }
-            Message::BuiltinIssueCommandChanged(v) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::IssueCommand { command, .. } = b {
-                        *command = v;
-                    }
-                });
+            Message::CloseWebhooks => {
+                self.active_view = ActiveView::Main;
                 Command::none()
             }
-            Message::BuiltinIssueCwdChanged(v) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::IssueCommand { cwd, .. } = b {
-                        let s = v.trim().to_string();
-                        *cwd = if s.is_empty() { None } else { Some(s) };
-                    }
-                });
+            Message::WebhooksLoaded(res) => {
+                match res {
+                    Ok(webhooks) => self.webhooks = webhooks,
+                    Err(e) => self.error = Some(format!("Failed to load webhooks: {e}")),
+                }
                 Command::none()
             }
-            Message::BuiltinIssueTimeoutChanged(v) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::IssueCommand { timeout_ms, .. } = b {
-                        let s = v.trim();
-                        *timeout_ms = if s.is_empty() {
-                            None
-                        } else {
-                            s.parse::<u64>().ok()
-                        };
-                    }
-                });
+            Message::WebhooksSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save webhooks: {e}"));
+                }
                 Command::none()
             }
-            Message::BuiltinKeyboardTextChanged(v) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::KeyboardInput { text, .. } = b {
-                        let s = v;
-                        *text = if s.trim().is_empty() { None } else { Some(s) };
-                    }
+            Message::AddWebhook => {
+                let id = self.webhooks.iter().map(|w| w.id).max().unwrap_or(0) + 1;
+                self.webhooks.push(Webhook {
+                    id,
+                    path: format!("hook-{id}"),
+                    action: TriggerActionChoice::IssueCommand.default_action(None),
                 });
-                Command::none()
+                self.save_webhooks()
             }
-            Message::BuiltinKeyboardKeysChanged(v) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::KeyboardInput { keys, .. } = b {
-                        *keys = v
-                            .split_whitespace()
-                            .map(|s| s.to_string())
-                            .collect::<Vec<_>>();
+            Message::RemoveWebhook(id) => {
+                self.webhooks.retain(|w| w.id != id);
+                self.save_webhooks()
+            }
+            Message::WebhookEdited { id, edit } => {
+                let Some(webhook) = self.webhooks.iter_mut().find(|w| w.id == id) else {
+                    return Command::none();
+                };
+                match edit {
+                    WebhookEdit::Path(path) => webhook.path = path,
+                    WebhookEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut webhook.action, edit, plugins, choices)
                     }
-                });
-                Command::none()
+                }
+                self.save_webhooks()
             }
-            Message::BuiltinPlaySoundPathChanged(v) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::PlaySound { path } = b {
-                        *path = v;
+            Message::WebhookServerEdited(edit) => {
+                let mut settings = self.webhook_settings.clone();
+                match edit {
+                    WebhookServerEdit::Enabled(enabled) => settings.enabled = enabled,
+                    WebhookServerEdit::Port(port) => {
+                        self.edit_webhook_port = port;
+                        return Command::none();
                     }
-                });
+                    // Applied on submit so the listener is not rebound on every keystroke.
+                    WebhookServerEdit::ApplyPort => match self.edit_webhook_port.trim().parse() {
+                        Ok(port) => settings.port = port,
+                        Err(_) => {
+                            self.error = Some(format!(
+                                "Invalid webhook port: {}",
+                                self.edit_webhook_port
+                            ));
+                            return Command::none();
+                        }
+                    },
+                    WebhookServerEdit::ListenOnNetwork(v) => settings.listen_on_network = v,
+                    WebhookServerEdit::Api(v) => settings.api = v,
+                    WebhookServerEdit::Metrics(v) => settings.metrics = v,
+                    WebhookServerEdit::RegenerateToken => settings.token.clear(),
+                }
+                // The listener refuses to start without a token, so one is made on demand.
+                if settings.token.is_empty() {
+                    match engine::webhook::generate_token() {
+                        Ok(token) => settings.token = token,
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+                self.webhook_settings = settings.clone();
+                Command::perform(save_webhook_settings_async(settings), Message::SettingsSaved)
+            }
+            Message::OpenTypedTriggers => {
+                self.active_view = ActiveView::TypedTriggers;
                 Command::none()
             }
-            Message::BuiltinSwitchProfilePicked(choice) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::SwitchProfile { mode } = b {
-                        *mode = match choice {
-                            SwitchProfileChoice::Next => actions::SwitchProfileMode::Next,
-                            SwitchProfileChoice::Prev => actions::SwitchProfileMode::Prev,
-                            SwitchProfileChoice::To(id) => {
-                                actions::SwitchProfileMode::To { profile_id: id.0 }
-                            }
-                        };
-                    }
-                });
+            Message::CloseTypedTriggers => {
+                self.active_view = ActiveView::Main;
                 Command::none()
             }
-            Message::BuiltinBrightnessModePicked(m) => {
-                // Keep existing value if possible.
-                let current = self.selected_builtin_brightness_value().unwrap_or(30);
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::DeviceBrightness { mode } = b {
-                        *mode = match m {
-                            BrightnessModeChoice::Set => actions::BrightnessMode::Set {
-                                percent: current as u8,
-                            },
-                            BrightnessModeChoice::Increase => actions::BrightnessMode::Increase {
-                                delta: current as u8,
-                            },
-                            BrightnessModeChoice::Decrease => actions::BrightnessMode::Decrease {
-                                delta: current as u8,
-                            },
-                        };
-                    }
-                });
+            Message::TypedTriggersLoaded(res) => {
+                match res {
+                    Ok(triggers) => self.typed_triggers = triggers,
+                    Err(e) => self.error = Some(format!("Failed to load typed triggers: {e}")),
+                }
                 Command::none()
             }
-            Message::BuiltinBrightnessValueChanged(v) => {
-                let v = v.clamp(0, 100) as u8;
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::DeviceBrightness { mode } = b {
-                        match mode {
-                            actions::BrightnessMode::Set { percent } => *percent = v,
-                            actions::BrightnessMode::Increase { delta } => *delta = v,
-                            actions::BrightnessMode::Decrease { delta } => *delta = v,
-                        }
-                    }
-                });
+            Message::TypedTriggersSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save typed triggers: {e}"));
+                }
                 Command::none()
             }
-            Message::BuiltinMonitorKindPicked(k) => {
-                self.update_selected_builtin(|b| {
-                    if let BuiltinAction::SystemMonitoring { kind, .. } = b {
-                        *kind = match k {
-                            MonitorKindChoice::Cpu => actions::MonitorKind::Cpu,
-                            MonitorKindChoice::Memory => actions::MonitorKind::Memory,
-                            MonitorKindChoice::LoadAverage => actions::MonitorKind::LoadAverage,
-                        }
-                    }
+            Message::AddTypedTrigger => {
+                let id = self.typed_triggers.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                self.typed_triggers.push(TypedTrigger {
+                    id,
+                    abbreviation: format!(";t{id}"),
+                    enabled: true,
+                    erase: true,
+                    action: TriggerActionChoice::TypeText.default_action(None),
                 });
-                Command::none()
+                self.save_typed_triggers()
             }
-            Message::MacroAddStep => {
-                self.macro_add_step();
-                Command::none()
+            Message::RemoveTypedTrigger(id) => {
+                self.typed_triggers.retain(|t| t.id != id);
+                self.save_typed_triggers()
             }
-            Message::MacroRemoveStep(i) => {
-                self.macro_remove_step(i);
-                Command::none()
+            Message::TypedTriggerEdited { id, edit } => {
+                let Some(trigger) = self.typed_triggers.iter_mut().find(|t| t.id == id) else {
+                    return Command::none();
+                };
+                match edit {
+                    TypedTriggerEdit::Abbreviation(abbreviation) => {
+                        trigger.abbreviation = abbreviation
+                    }
+                    TypedTriggerEdit::Enabled(enabled) => trigger.enabled = enabled,
+                    TypedTriggerEdit::Erase(erase) => trigger.erase = erase,
+                    TypedTriggerEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut trigger.action, edit, plugins, choices)
+                    }
+                }
+                self.save_typed_triggers()
             }
-            Message::MacroMoveStepUp(i) => {
-                self.macro_move_step(i, true);
+            Message::OpenFileWatchers => {
+                self.active_view = ActiveView::FileWatchers;
                 Command::none()
             }
-            Message::MacroMoveStepDown(i) => {
-                self.macro_move_step(i, false);
+            Message::CloseFileWatchers => {
+                self.active_view = ActiveView::Main;
                 Command::none()
             }
-            Message::MacroStepKindPicked { idx, kind } => {
-                self.macro_set_step_kind(idx, kind);
-                Command::none()
+            Message::AddFileWatcher => {
+                let Some(p) = &mut self.profile else {
+                    return Command::none();
+                };
+                let id = p.file_watchers.iter().map(|w| w.id).max().unwrap_or(0) + 1;
+                p.file_watchers.push(FileWatcher {
+                    id,
+                    path: String::new(),
+                    recursive: false,
+                    changes: FileChange::all(),
+                    enabled: true,
+                    action: TriggerActionChoice::IssueCommand.default_action(None),
+                });
+                Command::perform(save_profile_async(p.clone()), Message::ProfileSaved)
             }
-            Message::MacroStepDelayChanged { idx, value } => {
-                self.macro_set_step_delay(idx, value);
-                Command::none()
+            Message::RemoveFileWatcher(id) => {
+                let Some(p) = &mut self.profile else {
+                    return Command::none();
+                };
+                p.file_watchers.retain(|w| w.id != id);
+                Command::perform(save_profile_async(p.clone()), Message::ProfileSaved)
             }
-            Message::MacroStepPluginPicked { idx, choice } => {
-                self.macro_set_step_plugin(idx, choice);
-                Command::none()
+            Message::FileWatcherEdited { id, edit } => {
+                let Some(p) = &mut self.profile else {
+                    return Command::none();
+                };
+                let Some(watcher) = p.file_watchers.iter_mut().find(|w| w.id == id) else {
+                    return Command::none();
+                };
+                match edit {
+                    FileWatcherEdit::Path(path) => watcher.path = path,
+                    FileWatcherEdit::Recursive(recursive) => watcher.recursive = recursive,
+                    FileWatcherEdit::Change(change, on) => {
+                        watcher.changes.retain(|c| *c != change);
+                        if on {
+                            watcher.changes.push(change);
+                        }
+                    }
+                    FileWatcherEdit::Enabled(enabled) => watcher.enabled = enabled,
+                    FileWatcherEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut watcher.action, edit, plugins, choices)
+                    }
+                }
+                Command::perform(save_profile_async(p.clone()), Message::ProfileSaved)
             }
-            Message::MacroStepCommandChanged { idx, value } => {
-                self.macro_set_step_command(idx, value);
+            Message::OpenProcessWatchers => {
+                self.active_view = ActiveView::ProcessWatchers;
                 Command::none()
             }
-            Message::StartDragAction(a) => {
-                self.drag.dragging = Some(a);
-                self.drag.over_key = None;
+            Message::CloseProcessWatchers => {
+                self.active_view = ActiveView::Main;
                 Command::none()
             }
-            Message::CancelDragAction => {
-                self.drag.dragging = None;
-                self.drag.over_key = None;
+            Message::ProcessWatchersLoaded(res) => {
+                match res {
+                    Ok(watchers) => self.process_watchers = watchers,
+                    Err(e) => self.error = Some(format!("Failed to load process watchers: {e}")),
+                }
                 Command::none()
             }
-            Message::DragOverKey(idx) => {
-                self.drag.over_key = idx;
+            Message::ProcessWatchersSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save process watchers: {e}"));
+                }
                 Command::none()
             }
-            Message::DropOnKey(idx) => {
-                let Some(dragged) = self.drag.dragging.clone() else {
+            Message::AddProcessWatcher => {
+                let watchers = &mut self.process_watchers;
+                let id = watchers.iter().map(|w| w.id).max().unwrap_or(0) + 1;
+                watchers.push(ProcessWatcher {
+                    id,
+                    process: String::new(),
+                    on: ProcessTransition::Started,
+                    enabled: true,
+                    action: TriggerActionChoice::SwitchProfile.default_action(None),
+                });
+                self.save_process_watchers()
+            }
+            Message::RemoveProcessWatcher(id) => {
+                self.process_watchers.retain(|w| w.id != id);
+                self.save_process_watchers()
+            }
+            Message::ProcessWatcherEdited { id, edit } => {
+                let Some(watcher) = self.process_watchers.iter_mut().find(|w| w.id == id) else {
                     return Command::none();
                 };
-                self.drag.dragging = None;
-                self.drag.over_key = None;
-
-                self.selected_control = Some(SelectedControl::Key(idx));
-                self.selected_binding_target = BindingTarget::KeyPress;
-                self.assign_dragged_action_to_key(idx, dragged);
+                match edit {
+                    ProcessWatcherEdit::Process(process) => watcher.process = process,
+                    ProcessWatcherEdit::On(on) => watcher.on = on,
+                    ProcessWatcherEdit::Enabled(enabled) => watcher.enabled = enabled,
+                    ProcessWatcherEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut watcher.action, edit, plugins, choices)
+                    }
+                }
+                self.save_process_watchers()
+            }
+            Message::OpenMidi => {
+                self.active_view = ActiveView::Midi;
+                self.list_midi_ports()
+            }
+            Message::CloseMidi => {
+                self.active_view = ActiveView::Main;
                 Command::none()
             }
-            Message::ActionSelected(choice) => {
-                if self.selected_control.is_none() {
-                    self.error = Some(
-                        "Select a key/dial/touch strip in the preview before assigning an action."
-                            .to_string(),
-                    );
-                    return Command::none();
-                };
-                let Some(_p) = &mut self.profile else {
-                    self.error = Some("No profile loaded.".to_string());
-                    return Command::none();
-                };
-
-                let settings = default_settings_for_action(&self.plugins, &choice);
-                let Some(slot) = self.selected_binding_mut() else {
-                    self.error = Some("Invalid binding target for selected control.".to_string());
-                    return Command::none();
-                };
-                *slot = Some(ActionBinding::Plugin(PluginActionBinding {
-                    plugin_id: choice.plugin_id.clone(),
-                    action_id: choice.action_id.clone(),
-                    settings,
-                }));
+            Message::ListMidiPorts => self.list_midi_ports(),
+            Message::ListSerialPorts => self.list_serial_ports(),
+            Message::SerialPortsListed(res) => {
+                self.serial_ports = Some(res);
                 Command::none()
             }
-            Message::ActionSearchChanged(s) => {
-                self.action_search = s;
+            Message::MidiOutputPortsListed(res) => {
+                self.midi_output_ports = Some(res);
                 Command::none()
             }
-            Message::SettingStringChanged { key, value } => {
-                self.set_selected_plugin_setting(key, serde_json::Value::String(value));
+            Message::MidiInputPortsListed(res) => {
+                self.midi_input_ports = Some(res);
                 Command::none()
             }
-            Message::SettingBoolChanged { key, value } => {
-                self.set_selected_plugin_setting(key, serde_json::Value::Bool(value));
+            Message::MidiSettingsEdited(edit) => {
+                match edit {
+                    MidiSettingsEdit::Enabled(enabled) => self.midi_settings.enabled = enabled,
+                    MidiSettingsEdit::InputPort(port) => self.midi_settings.input_port = port,
+                }
+                Command::perform(
+                    save_midi_settings_async(self.midi_settings.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::MidiTriggersLoaded(res) => {
+                match res {
+                    Ok(triggers) => self.midi_triggers = triggers,
+                    Err(e) => self.error = Some(format!("Failed to load MIDI triggers: {e}")),
+                }
                 Command::none()
             }
-            Message::SettingNumberChanged { key, value } => {
-                let num = value
-                    .parse::<f64>()
-                    .ok()
-                    .and_then(serde_json::Number::from_f64);
-                let v = num
-                    .map(serde_json::Value::Number)
-                    .unwrap_or(serde_json::Value::Null);
-                self.set_selected_plugin_setting(key, v);
+            Message::MidiTriggersSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save MIDI triggers: {e}"));
+                }
                 Command::none()
             }
-            Message::Tick => {
-                let mut cmds: Vec<Command<Message>> = vec![];
-                self.refresh_system_snapshot();
-                let mut pending_actions: Vec<(InvocationControl, InvocationEvent, ActionBinding)> =
-                    vec![];
-                if let Some(c) = &mut self.connected {
-                    while let Ok(ev) = c.events.try_recv() {
-                        match ev {
-                            DeviceEvent::Control(ev) => match (ev.control, ev.kind) {
-                                (ControlId::Key(key), ControlEventKind::Down) => {
-                                    if let Some(slot) = c.pressed.get_mut(key as usize) {
-                                        *slot = true;
-                                    }
-
-                                    // Dispatch bound action on key-down (plugin or builtin).
-                                    if let Some(p) = &self.profile {
-                                        if let Some(kcfg) = p.keys.get(key as usize) {
-                                            if let Some(binding) = &kcfg.action {
-                                                pending_actions.push((
-                                                    InvocationControl::Key { index: key },
-                                                    InvocationEvent::KeyDown,
-                                                    binding.clone(),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-                                (ControlId::Key(key), ControlEventKind::Up) => {
-                                    if let Some(slot) = c.pressed.get_mut(key as usize) {
-                                        *slot = false;
-                                    }
-                                }
-                                (ControlId::Dial(dial), ControlEventKind::Down) => {
-                                    if let Some(p) = &self.profile {
-                                        if let Some(d) = p.dials.get(dial as usize) {
-                                            if let Some(binding) = &d.press {
-                                                pending_actions.push((
-                                                    InvocationControl::Dial { index: dial },
-                                                    InvocationEvent::DialDown,
-                                                    binding.clone(),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-                                (ControlId::Dial(dial), ControlEventKind::Rotate { delta }) => {
-                                    if let Some(p) = &self.profile {
-                                        if let Some(d) = p.dials.get(dial as usize) {
-                                            if let Some(binding) = &d.rotate {
-                                                pending_actions.push((
-                                                    InvocationControl::Dial { index: dial },
-                                                    InvocationEvent::DialRotate { delta },
-                                                    binding.clone(),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-                                (ControlId::TouchStrip, ControlEventKind::Tap { x }) => {
-                                    if let Some(p) = &self.profile {
-                                        if let Some(binding) = &p.touch_strip.tap {
-                                            pending_actions.push((
-                                                InvocationControl::TouchStrip,
-                                                InvocationEvent::TouchTap { x },
-                                                binding.clone(),
-                                            ));
-                                        }
-                                    }
-                                }
-                                (ControlId::TouchStrip, ControlEventKind::Drag { delta_x }) => {
-                                    if let Some(p) = &self.profile {
-                                        if let Some(binding) = &p.touch_strip.drag {
-                                            pending_actions.push((
-                                                InvocationControl::TouchStrip,
-                                                InvocationEvent::TouchDrag { delta_x },
-                                                binding.clone(),
-                                            ));
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            },
-                            DeviceEvent::Disconnected => {
-                                self.error = Some("Device disconnected".to_string());
-                                self.connected = None;
-                                break;
-                            }
+            Message::AddMidiTrigger => {
+                let id = self.midi_triggers.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                self.midi_triggers.push(MidiTrigger {
+                    id,
+                    input: MidiInput::Note,
+                    channel: None,
+                    number: 36,
+                    enabled: true,
+                    action: TriggerActionChoice::IssueCommand.default_action(None),
+                });
+                self.save_midi_triggers()
+            }
+            Message::RemoveMidiTrigger(id) => {
+                self.midi_triggers.retain(|t| t.id != id);
+                self.save_midi_triggers()
+            }
+            Message::MidiTriggerEdited { id, edit } => {
+                let Some(trigger) = self.midi_triggers.iter_mut().find(|t| t.id == id) else {
+                    return Command::none();
+                };
+                match edit {
+                    MidiTriggerEdit::Input(input) => trigger.input = input,
+                    MidiTriggerEdit::Channel(v) => {
+                        trigger.channel = v.trim().parse().ok().filter(|c| (1..=16).contains(c))
+                    }
+                    MidiTriggerEdit::Number(v) => {
+                        if let Some(n) = v.trim().parse().ok().filter(|n| *n <= 127) {
+                            trigger.number = n;
                         }
                     }
+                    MidiTriggerEdit::Enabled(enabled) => trigger.enabled = enabled,
+                    MidiTriggerEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut trigger.action, edit, plugins, choices)
+                    }
                 }
-
-                for (control, event, binding) in pending_actions {
-                    cmds.push(self.start_action_sequence(control, event, &binding));
-                }
-                Command::batch(cmds)
+                self.save_midi_triggers()
             }
-            Message::BrightnessChanged(v) => {
-                let Some(c) = &mut self.connected else {
-                    return Command::none();
-                };
-                let v = v.clamp(0, 100) as u8;
-                c.brightness = v;
-                let controller = c.controller.clone();
+            Message::OpenOsc => {
+                self.active_view = ActiveView::Osc;
+                Command::none()
+            }
+            Message::CloseOsc => {
+                self.active_view = ActiveView::Main;
+                Command::none()
+            }
+            Message::OscSettingsEdited(edit) => {
+                let settings = &mut self.osc_settings;
+                match edit {
+                    OscSettingsEdit::Enabled(enabled) => settings.enabled = enabled,
+                    OscSettingsEdit::Port(port) => {
+                        self.edit_osc_port = port;
+                        return Command::none();
+                    }
+                    OscSettingsEdit::ApplyPort => match self.edit_osc_port.trim().parse() {
+                        Ok(port) => settings.port = port,
+                        Err(_) => {
+                            self.error = Some(format!("Invalid OSC port: {}", self.edit_osc_port));
+                            return Command::none();
+                        }
+                    },
+                    OscSettingsEdit::ListenOnNetwork(v) => settings.listen_on_network = v,
+                }
                 Command::perform(
-                    set_brightness_async(controller, v),
-                    Message::BrightnessApplied,
+                    save_osc_settings_async(self.osc_settings.clone()),
+                    Message::SettingsSaved,
                 )
             }
-            Message::BrightnessApplied(res) => {
-                if let Err(e) = res {
-                    self.error = Some(e);
+            Message::OscTriggersLoaded(res) => {
+                match res {
+                    Ok(triggers) => self.osc_triggers = triggers,
+                    Err(e) => self.error = Some(format!("Failed to load OSC triggers: {e}")),
                 }
                 Command::none()
             }
-            Message::OpenColorPicker => {
-                self.show_color_picker = !self.show_color_picker;
+            Message::OscTriggersSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save OSC triggers: {e}"));
+                }
                 Command::none()
             }
-            Message::ColorPicked(c) => {
-                self.show_color_picker = false;
-                let r = (c.r * 255.0) as u8;
-                let g = (c.g * 255.0) as u8;
-                let b = (c.b * 255.0) as u8;
-                let s = format!("{},{},{}", r, g, b);
-                Command::perform(async move { s }, Message::BgRgbChanged)
+            Message::AddOscTrigger => {
+                let id = self.osc_triggers.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                self.osc_triggers.push(OscTrigger {
+                    id,
+                    address: String::new(),
+                    variable: String::new(),
+                    enabled: true,
+                    action: TriggerActionChoice::IssueCommand.default_action(None),
+                });
+                self.save_osc_triggers()
             }
-            Message::OpenIconPicker => {
+            Message::RemoveOscTrigger(id) => {
+                self.osc_triggers.retain(|t| t.id != id);
+                self.save_osc_triggers()
+            }
+            Message::OscTriggerEdited { id, edit } => {
+                let Some(trigger) = self.osc_triggers.iter_mut().find(|t| t.id == id) else {
+                    return Command::none();
+                };
+                match edit {
+                    OscTriggerEdit::Address(address) => trigger.address = address,
+                    OscTriggerEdit::Variable(variable) => trigger.variable = variable,
+                    OscTriggerEdit::Enabled(enabled) => trigger.enabled = enabled,
+                    OscTriggerEdit::Action(edit) => {
+                        let (plugins, choices) = (&self.plugins, &self.actions);
+                        edit_trigger_action(&mut trigger.action, edit, plugins, choices)
+                    }
+                }
+                self.save_osc_triggers()
+            }
+            Message::NotificationMirrorEdited(edit) => {
+                let mirror = &mut self.notification_mirror;
+                match edit {
+                    NotificationMirrorEdit::Enabled(enabled) => {
+                        mirror.enabled = enabled;
+                        if !enabled && self.mirrored_notification.take().is_some() {
+                            return Command::batch([
+                                self.apply_displays_if_connected(),
+                                Command::perform(
+                                    save_notification_mirror_async(*mirror),
+                                    Message::SettingsSaved,
+                                ),
+                            ]);
+                        }
+                    }
+                    NotificationMirrorEdit::Key(key) => {
+                        mirror.key = (key - 1).clamp(0, i32::from(u8::MAX)) as u8;
+                        return Command::none();
+                    }
+                    NotificationMirrorEdit::Seconds(seconds) => {
+                        mirror.seconds = seconds.max(1) as u32;
+                        return Command::none();
+                    }
+                    NotificationMirrorEdit::Save => {}
+                }
                 Command::perform(
-                    async {
-                        rfd::AsyncFileDialog::new()
-                            .add_filter("Images", &["png", "jpg", "jpeg", "gif", "webp", "svg"])
-                            .pick_file()
-                            .await
-                            .map(|f| f.path().to_path_buf())
-                    },
-                    Message::IconPicked,
+                    save_notification_mirror_async(self.notification_mirror),
+                    Message::SettingsSaved,
                 )
             }
-            Message::IconPicked(path) => {
-                if let Some(p) = path {
-                    let s = p.to_string_lossy().to_string();
-                    Command::perform(async move { s }, Message::IconPathChanged)
-                } else {
+            Message::TextExpanderToggled(enabled) => {
+                self.text_expander.enabled = enabled;
+                Command::perform(
+                    save_text_expander_settings_async(self.text_expander.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::CompanionEdited(edit) => {
+                let mut settings = self.companion_settings.clone();
+                match edit {
+                    CompanionEdit::Enabled(enabled) => settings.enabled = enabled,
+                    CompanionEdit::Address(address) => {
+                        self.edit_companion_address = address;
+                        return Command::none();
+                    }
+                    CompanionEdit::ApplyAddress => {
+                        let address = self.edit_companion_address.trim();
+                        let parsed = match address.rsplit_once(':') {
+                            Some((host, port)) => port.parse().ok().map(|p| (host, p)),
+                            None => Some((address, settings.port)),
+                        };
+                        match parsed.filter(|(host, _)| !host.is_empty()) {
+                            Some((host, port)) => {
+                                settings.host = host.to_string();
+                                settings.port = port;
+                            }
+                            None => {
+                                self.error = Some(format!("Invalid Companion address: {address}"));
+                                return Command::none();
+                            }
+                        }
+                    }
+                }
+                self.companion_settings = settings.clone();
+                Command::perform(save_companion_settings_async(settings), Message::SettingsSaved)
+            }
+            Message::InterfaceEdited(edit) => match edit {
+                InterfaceEdit::Scale(percent) => {
+                    self.edit_ui_scale =
+                        percent.clamp(i32::from(MIN_UI_SCALE), i32::from(MAX_UI_SCALE)) as u16;
                     Command::none()
                 }
+                InterfaceEdit::Apply => {
+                    self.handle_message(Message::SetUiScale(self.edit_ui_scale))
+                }
+                InterfaceEdit::ZoomIn => {
+                    let percent = self.interface.scale_percent.saturating_add(UI_SCALE_STEP);
+                    self.handle_message(Message::SetUiScale(percent))
+                }
+                InterfaceEdit::ZoomOut => {
+                    let percent = self.interface.scale_percent.saturating_sub(UI_SCALE_STEP);
+                    self.handle_message(Message::SetUiScale(percent))
+                }
+                InterfaceEdit::ResetZoom => self.handle_message(Message::SetUiScale(100)),
+            },
+            Message::SetUiScale(percent) => {
+                let percent = percent.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+                self.interface.scale_percent = percent;
+                self.edit_ui_scale = percent;
+                Command::perform(
+                    save_interface_async(self.interface.clone()),
+                    Message::SettingsSaved,
+                )
             }
-        }
-    }
-
-    fn view(&self) -> Element<'_, Self::Message> {
+            Message::ThemePicked(choice) => self.set_theme(choice.setting),
+            Message::CheckThemes => {
+                let modified = storage::themes::themes_modified();
+                if modified == self.themes_modified {
+                    return Command::none();
+                }
+                self.themes_modified = modified;
+                Command::perform(load_themes_async(), Message::ThemesLoaded)
+            }
+            Message::ThemesLoaded(res) => {
+                match res {
+                    Ok(user_themes) => {
+                        self.user_themes = user_themes;
+                        self.active_theme =
+                            ActiveTheme::resolve(&self.interface.theme, &self.user_themes);
+                    }
+                    Err(e) => self.error = Some(format!("Failed to load themes: {e}")),
+                }
+                Command::none()
+            }
+            Message::MacroLimitsEdited(edit) => {
+                match edit {
+                    MacroLimitsEdit::MaxSteps(n) => {
+                        self.macro_settings.max_steps = n.max(1) as usize;
+                    }
+                    MacroLimitsEdit::MaxDepth(n) => {
+                        self.macro_settings.max_depth = n.max(1) as usize;
+                    }
+                    MacroLimitsEdit::Save => {
+                        return Command::perform(
+                            save_macro_settings_async(self.macro_settings),
+                            Message::SettingsSaved,
+                        );
+                    }
+                }
+                if let Some(c) = &self.connected {
+                    c.engine.set_expand_limits(self.macro_settings.limits());
+                }
+                Command::none()
+            }
+            Message::TouchGesturesEdited(edit) => {
+                match edit {
+                    TouchGestureEdit::SwipeDistance(px) => {
+                        self.touch_gestures.swipe_distance = px.clamp(0, 800) as u16;
+                    }
+                    TouchGestureEdit::SwipeTime(ms) => {
+                        self.touch_gestures.swipe_max_ms = ms.max(0) as u64;
+                    }
+                    TouchGestureEdit::Save => {
+                        return Command::perform(
+                            save_touch_gestures_async(self.touch_gestures),
+                            Message::SettingsSaved,
+                        );
+                    }
+                }
+                if let Some(c) = &self.connected {
+                    c.controller.set_gesture_thresholds(self.gesture_thresholds());
+                }
+                Command::none()
+            }
+            Message::HomeAssistantEdited(edit) => {
+                let mut settings = self.home_assistant_settings.clone();
+                match edit {
+                    HomeAssistantEdit::Enabled(enabled) => settings.enabled = enabled,
+                    HomeAssistantEdit::Url(url) => {
+                        self.edit_ha_url = url;
+                        return Command::none();
+                    }
+                    HomeAssistantEdit::Token(token) => {
+                        self.edit_ha_token = token;
+                        return Command::none();
+                    }
+                    HomeAssistantEdit::Apply => {
+                        settings.url = self.edit_ha_url.trim().to_string();
+                        settings.token = self.edit_ha_token.trim().to_string();
+                    }
+                }
+                self.home_assistant_settings = settings.clone();
+                Command::perform(
+                    save_home_assistant_settings_async(settings),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::SecretNameChanged(name) => {
+                self.edit_secret_name = name;
+                Command::none()
+            }
+            Message::SecretValueChanged(value) => {
+                self.edit_secret_value = value;
+                Command::none()
+            }
+            Message::SaveSecret => {
+                let name = self.edit_secret_name.trim().to_string();
+                if name.is_empty() || self.secret_saving {
+                    return Command::none();
+                }
+                self.secret_saving = true;
+                let value = std::mem::take(&mut self.edit_secret_value);
+                Command::perform(
+                    save_secret_async(self.secrets.clone(), name, value),
+                    Message::SecretSaved,
+                )
+            }
+            Message::SecretSaved(res) => {
+                self.secret_saving = false;
+                match res {
+                    Ok(()) => self.edit_secret_name.clear(),
+                    Err(e) => self.error = Some(e),
+                }
+                Command::none()
+            }
+            Message::RemoveSecret(name) => Command::perform(
+                remove_secret_async(self.secrets.clone(), name),
+                Message::SecretRemoved,
+            ),
+            Message::SecretRemoved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(e);
+                }
+                Command::none()
+            }
+            Message::SurfacesLoaded(res) => {
+                match res {
+                    Ok(surfaces) => self.surfaces = surfaces,
+                    Err(e) => self.error = Some(format!("Failed to load surfaces: {e}")),
+                }
+                // Surface profiles are only listed once their surfaces are known.
+                if self.connected.is_none() {
+                    return Command::none();
+                }
+                Command::perform(list_profiles_async(), Message::ProfilesLoaded)
+            }
+            Message::SurfacesSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save surfaces: {e}"));
+                }
+                // Keys of the profiles on a changed surface may have moved.
+                let reload = self
+                    .selected_profile
+                    .filter(|_| self.active_surface().is_some());
+                let list = Command::perform(list_profiles_async(), Message::ProfilesLoaded);
+                let mut cmds = vec![list];
+                if let Some(id) = reload {
+                    let load = Command::perform(load_profile_async(id), Message::ProfileLoaded);
+                    cmds.push(load);
+                }
+                Command::batch(cmds)
+            }
+            Message::SurfaceNameChanged(name) => {
+                self.edit_surface_name = name;
+                Command::none()
+            }
+            Message::AddSurface => {
+                let name = self.edit_surface_name.trim().to_string();
+                if name.is_empty() {
+                    return Command::none();
+                }
+                self.edit_surface_name.clear();
+                let id = self.surfaces.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+                let mut surface = Surface {
+                    id,
+                    name,
+                    members: vec![],
+                };
+                if let Some(c) = &self.connected {
+                    surface.members.push(surface_member(c, 0, 0));
+                }
+                self.update_surfaces(|surfaces| surfaces.push(surface))
+            }
+            Message::RemoveSurface(id) => {
+                self.update_surfaces(|surfaces| surfaces.retain(|s| s.id != id))
+            }
+            Message::AddDeckToSurface(id) => {
+                let Some(c) = &self.connected else {
+                    return Command::none();
+                };
+                // Next to the decks already there.
+                let column = self
+                    .surfaces
+                    .iter()
+                    .find(|s| s.id == id)
+                    .map_or(0, |s| s.grid().0);
+                let member = surface_member(c, column, 0);
+                self.update_surfaces(|surfaces| {
+                    if let Some(s) = surfaces.iter_mut().find(|s| s.id == id) {
+                        s.members.push(member);
+                    }
+                })
+            }
+            Message::RemoveDeckFromSurface { surface, device } => {
+                self.update_surfaces(|surfaces| {
+                    if let Some(s) = surfaces.iter_mut().find(|s| s.id == surface) {
+                        s.members.retain(|m| m.device != device);
+                    }
+                })
+            }
+            Message::MoveSurfaceDeck {
+                surface,
+                device,
+                dx,
+                dy,
+            } => self.update_surfaces(|surfaces| {
+                let members = surfaces
+                    .iter_mut()
+                    .filter(|s| s.id == surface)
+                    .flat_map(|s| s.members.iter_mut());
+                for m in members.filter(|m| m.device == device) {
+                    m.column = m.column.saturating_add_signed(dx);
+                    m.row = m.row.saturating_add_signed(dy);
+                }
+            }),
+            Message::CreateSurfaceProfile(id) => {
+                let Some(surface) = self.surfaces.iter().find(|s| s.id == id).cloned() else {
+                    return Command::none();
+                };
+                Command::perform(
+                    create_surface_profile_async(surface),
+                    Message::ProfileCreated,
+                )
+            }
+            Message::KioskPinChanged(pin) => {
+                self.edit_kiosk_pin = pin;
+                Command::none()
+            }
+            Message::UnlockKiosk => {
+                let pin = std::mem::take(&mut self.edit_kiosk_pin);
+                self.kiosk_pin_wrong = !self.kiosk.pin_matches(&pin);
+                self.kiosk_unlocked = !self.kiosk_pin_wrong;
+                if !self.kiosk_unlocked || !self.kiosk.pin_outdated() {
+                    return Command::none();
+                }
+                // A PIN from an older version is hashed again, salted, now that it is known.
+                if let Err(e) = self.kiosk.set_pin(&pin) {
+                    tracing::warn!(error = %format!("{e:#}"), "failed to rehash the kiosk PIN");
+                    return Command::none();
+                }
+                Command::perform(
+                    save_kiosk_settings_async(self.kiosk.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::LockKiosk => {
+                self.kiosk_unlocked = false;
+                self.palette = None;
+                self.edit_kiosk_pin.clear();
+                Command::none()
+            }
+            Message::KioskProfileToggled(id, locked) => {
+                if self.kiosk.enabled() && !self.kiosk_unlocked {
+                    return Command::none();
+                }
+                // Whoever sets the lock up keeps it open until "Lock now" or a restart.
+                self.kiosk_unlocked = true;
+                self.kiosk.locked_profiles.retain(|p| *p != id.0);
+                if locked {
+                    self.kiosk.locked_profiles.push(id.0);
+                }
+                Command::perform(
+                    save_kiosk_settings_async(self.kiosk.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::KioskPinSubmitted => {
+                if self.kiosk.enabled() && !self.kiosk_unlocked {
+                    return Command::none();
+                }
+                self.kiosk_unlocked = true;
+                let pin = std::mem::take(&mut self.edit_kiosk_pin);
+                if let Err(e) = self.kiosk.set_pin(&pin) {
+                    self.error = Some(format!("Failed to set the PIN: {e:#}"));
+                    return Command::none();
+                }
+                Command::perform(
+                    save_kiosk_settings_async(self.kiosk.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::SyncFolderChanged(folder) => {
+                self.edit_sync_folder = folder;
+                Command::none()
+            }
+            Message::SyncFolderSubmitted => {
+                self.sync_settings.folder = self.edit_sync_folder.trim().to_string();
+                // Syncs with the new folder on the next check.
+                self.sync = SyncState::default();
+                Command::perform(
+                    save_sync_settings_async(self.sync_settings.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::CheckSync => {
+                let Some(folder) = self.sync_settings.folder().map(std::path::PathBuf::from) else {
+                    return Command::none();
+                };
+                let modified = storage::sync::modified(&folder);
+                if self.sync.running || modified == self.sync.modified {
+                    return Command::none();
+                }
+                self.sync.modified = modified;
+                self.sync.running = true;
+                Command::perform(sync_async(folder), Message::Synced)
+            }
+            Message::Synced(res) => {
+                self.sync.running = false;
+                match res {
+                    Ok(report) => {
+                        self.sync.status = sync_summary(&report);
+                        self.sync.conflicts = report.conflicts.clone();
+                        return self.sync_pulled(report.changed_here());
+                    }
+                    Err(e) => {
+                        self.sync.status = e.clone();
+                        self.error = Some(e);
+                    }
+                }
+                Command::none()
+            }
+            Message::ResolveSyncConflict(file, resolution) => {
+                let Some(folder) = self.sync_settings.folder().map(std::path::PathBuf::from) else {
+                    return Command::none();
+                };
+                Command::perform(
+                    resolve_sync_conflict_async(folder, file, resolution),
+                    Message::SyncConflictResolved,
+                )
+            }
+            Message::SyncConflictResolved(res) => match res {
+                Ok((file, resolution)) => {
+                    self.sync.conflicts.retain(|f| *f != file);
+                    if resolution == Resolution::UseSynced {
+                        return self.sync_pulled([file.as_str()].into_iter());
+                    }
+                    Command::none()
+                }
+                Err(e) => {
+                    self.error = Some(e);
+                    Command::none()
+                }
+            },
+            Message::GithubTokenChanged(token) => {
+                self.edit_github_token = token;
+                Command::none()
+            }
+            Message::GithubTokenSubmitted => {
+                self.marketplace_settings.github_token = self.edit_github_token.trim().to_string();
+                let token = self.marketplace_settings.github_token().map(str::to_string);
+                self.github_actions.set_token(token);
+                // Details fetched without the token may lack what the limit held back.
+                self.marketplace.details_cache.clear();
+                Command::perform(
+                    save_marketplace_settings_async(self.marketplace_settings.clone()),
+                    Message::SettingsSaved,
+                )
+            }
+            Message::SpotifyClientIdChanged(v) => {
+                self.edit_spotify_client_id = v;
+                Command::none()
+            }
+            Message::SpotifySignIn => {
+                if self.spotify_signing_in {
+                    return Command::none();
+                }
+                self.spotify_signing_in = true;
+                self.spotify_error = None;
+                let client_id = self.edit_spotify_client_id.clone();
+                Command::perform(
+                    spotify_sign_in_async(self.spotify.clone(), client_id),
+                    Message::SpotifySignedIn,
+                )
+            }
+            Message::SpotifySignedIn(res) => {
+                self.spotify_signing_in = false;
+                if let Err(e) = res {
+                    self.spotify_error = Some(e);
+                }
+                Command::none()
+            }
+            Message::SpotifySignOut => Command::perform(
+                spotify_sign_out_async(self.spotify.clone()),
+                Message::SpotifySignedIn,
+            ),
+            Message::SpotifyEvent(event) => {
+                match event {
+                    spotify::Event::SignedIn => self.spotify_signed_in = true,
+                    spotify::Event::SignedOut(reason) => {
+                        self.spotify_signed_in = false;
+                        self.spotify_error = reason;
+                    }
+                    spotify::Event::Playback(playback) => {
+                        self.spotify_playback = playback;
+                        if self.profile.as_ref().is_some_and(profile_shows_playback) {
+                            return self.apply_displays_if_connected();
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::ClockTick => {
+                let minute = chrono::Utc::now().timestamp().div_euclid(60);
+                if std::mem::replace(&mut self.clock_minute, minute) == minute {
+                    return Command::none();
+                }
+                self.apply_displays_if_connected()
+            }
+            Message::PomodoroTick => self.apply_displays_if_connected(),
+            Message::RefreshWeather => self.refresh_weather(),
+            Message::CheckDoNotDisturb => self.check_do_not_disturb(),
+            Message::DoNotDisturbChecked(res) => {
+                let on = res
+                    .map_err(|e| tracing::debug!(error = %e, "cannot read do-not-disturb"))
+                    .ok();
+                if self.do_not_disturb == on {
+                    return Command::none();
+                }
+                self.do_not_disturb = on;
+                self.apply_displays_if_connected()
+            }
+            Message::CheckRadios => self.check_radios(),
+            Message::RadioChecked((radio, res)) => {
+                let on = res
+                    .map_err(|e| tracing::debug!(?radio, error = %e, "cannot read radio"))
+                    .ok();
+                if self.radios.get(&radio).copied() == on {
+                    return Command::none();
+                }
+                match on {
+                    Some(on) => self.radios.insert(radio, on),
+                    None => self.radios.remove(&radio),
+                };
+                self.apply_displays_if_connected()
+            }
+            Message::ListContainers => {
+                Command::perform(list_containers_async(), Message::ContainersListed)
+            }
+            Message::ContainersListed(res) => {
+                if let Err(e) = &res {
+                    tracing::debug!(error = %e, "cannot list containers");
+                }
+                let res = Some(res);
+                if self.containers == res {
+                    return Command::none();
+                }
+                self.containers = res;
+                self.apply_displays_if_connected()
+            }
+            Message::RefreshCi => self.refresh_ci(),
+            Message::CiFetched((query, run)) => {
+                if let Err(e) = &run {
+                    tracing::warn!(repo = %query.repo, error = %e, "no GitHub Actions run");
+                }
+                if self.ci_runs.get(&query) == Some(&run) {
+                    return Command::none();
+                }
+                self.ci_runs.insert(query, run);
+                self.apply_displays_if_connected()
+            }
+            Message::CheckLights => self.check_lights(),
+            Message::LightChecked((light, state)) => {
+                if self.light_states.get(&light) == Some(&state) {
+                    return Command::none();
+                }
+                self.light_states.insert(light, state);
+                self.apply_displays_if_connected()
+            }
+            Message::CheckMeetings => self.check_meetings(),
+            Message::MeetingChecked((app, state)) => {
+                if self.meeting_states.get(&app) == Some(&state) {
+                    return Command::none();
+                }
+                self.meeting_states.insert(app, state);
+                self.apply_displays_if_connected()
+            }
+            Message::DiscoverLights => {
+                Command::perform(discover_lights_async(), Message::LightsDiscovered)
+            }
+            Message::LightsDiscovered(res) => {
+                if let Ok(found) = &res {
+                    let bridge = found.iter().find(|d| d.kind == DeviceKind::HueBridge);
+                    if let Some(bridge) = bridge.filter(|_| self.edit_hue_bridge.is_empty()) {
+                        self.edit_hue_bridge = bridge.host.clone();
+                    }
+                }
+                self.light_devices = Some(res);
+                Command::none()
+            }
+            Message::ListHueLights => Command::perform(
+                list_hue_lights_async(self.lights.clone()),
+                Message::HueLightsListed,
+            ),
+            Message::HueLightsListed(res) => {
+                self.hue_lights = Some(res);
+                Command::none()
+            }
+            Message::HueBridgeChanged(v) => {
+                self.edit_hue_bridge = v;
+                Command::none()
+            }
+            Message::PairHue => {
+                if self.hue_pairing {
+                    return Command::none();
+                }
+                self.hue_pairing = true;
+                self.hue_pair_error = None;
+                let bridge = self.edit_hue_bridge.trim().to_string();
+                Command::perform(
+                    pair_hue_async(self.lights.clone(), bridge),
+                    Message::HuePaired,
+                )
+            }
+            Message::HuePaired(res) => {
+                self.hue_pairing = false;
+                let (bridge, key) = match res {
+                    Ok(paired) => paired,
+                    Err(e) => {
+                        self.hue_pair_error = Some(e);
+                        return Command::none();
+                    }
+                };
+                self.lighting_settings.hue_bridge = bridge;
+                self.lighting_settings.hue_app_key = key;
+                self.lights.configure(self.lighting_settings.clone());
+                Command::batch([
+                    Command::perform(
+                        save_lighting_settings_async(self.lighting_settings.clone()),
+                        Message::SettingsSaved,
+                    ),
+                    Command::perform(
+                        list_hue_lights_async(self.lights.clone()),
+                        Message::HueLightsListed,
+                    ),
+                    self.check_lights(),
+                ])
+            }
+            Message::CheckGit => self.check_git(),
+            Message::GitChecked((repo, status)) => {
+                if self.git_statuses.get(&repo) == Some(&status) {
+                    return Command::none();
+                }
+                self.git_statuses.insert(repo, status);
+                self.apply_displays_if_connected()
+            }
+            Message::WeatherFetched((query, report)) => {
+                if self.weather_reports.get(&query) == Some(&report) {
+                    return Command::none();
+                }
+                if let Err(e) = &report {
+                    tracing::warn!(location = %query.0, error = %e, "no weather report");
+                }
+                self.weather_reports.insert(query, report);
+                self.apply_displays_if_connected()
+            }
+            Message::MarketplaceRefresh => {
+                let Some(idx) = self.marketplace.selected_source_idx else {
+                    self.marketplace.loading = false;
+                    self.marketplace.error = Some("No marketplace selected.".to_string());
+                    return Command::none();
+                };
+                let url = self.marketplace.sources.get(idx).map(|s| s.index_url.clone());
+                let Some(url) = url else {
+                    self.marketplace.loading = false;
+                    self.marketplace.error = Some("Invalid marketplace selection.".to_string());
+                    return Command::none();
+                };
+                if url.trim().is_empty() {
+                    self.marketplace.loading = false;
+                    self.marketplace.error =
+                        Some("Marketplace URL is not configured.".to_string());
+                    return Command::none();
+                }
+                self.marketplace.loading = true;
+                self.marketplace.error = None;
+                Command::perform(fetch_marketplace_async(url), Message::MarketplaceLoaded)
+            }
+            Message::MarketplaceSourcePicked(src) => {
+                let idx = self.marketplace.sources.iter().position(|s| s == &src);
+                self.marketplace.selected_source_idx = idx;
+                Command::perform(async { () }, |_| Message::MarketplaceRefresh)
+            }
+            Message::MarketplaceSearchChanged(q) => {
+                self.marketplace.query = q;
+                self.marketplace.page = 0;
+                Command::none()
+            }
+            Message::MarketplaceCategoryPicked(c) => {
+                self.marketplace.category = (c != ALL_CATEGORIES).then_some(c);
+                self.marketplace.page = 0;
+                self.marketplace_fetch_icons_for_current_page()
+            }
+            Message::MarketplaceCompatibleOnlyToggled(v) => {
+                self.marketplace.compatible_only = v;
+                self.marketplace.page = 0;
+                self.marketplace_fetch_icons_for_current_page()
+            }
+            Message::MarketplaceLoaded(res) => {
+                self.marketplace.loading = false;
+                match res {
+                    Ok(index) => {
+                        self.marketplace.plugins = index.plugins;
+                        self.marketplace.offline = index.stale;
+                        self.marketplace.error = None;
+                        return self.marketplace_fetch_icons_for_current_page();
+                    }
+                    Err(e) => {
+                        self.marketplace.plugins.clear();
+                        self.marketplace.error = Some(e);
+                    }
+                }
+                Command::none()
+            }
+            Message::MarketplaceInstall(p) => self.start_marketplace_install(p, false),
+            Message::MarketplaceInstallAnyway(p) => self.start_marketplace_install(p, true),
+            Message::MarketplaceInstalled { plugin, res } => {
+                self.marketplace.installing = None;
+                match res {
+                    Ok(()) => {
+                        self.marketplace.error = None;
+                        self.marketplace.pending_override = None;
+                        Command::perform(list_plugins_async(), Message::PluginsLoaded)
+                    }
+                    Err(e) => {
+                        self.marketplace.pending_override = e.verification_failed.then_some(plugin);
+                        self.marketplace.error = Some(e.message);
+                        Command::none()
+                    }
+                }
+            }
+            Message::MarketplaceTabPicked(tab) => {
+                self.marketplace.tab = tab;
+                let gallery = &self.marketplace.gallery;
+                if tab == MarketplaceTab::Profiles
+                    && gallery.profiles.is_empty()
+                    && !gallery.loading
+                {
+                    return self.refresh_profile_gallery();
+                }
+                Command::none()
+            }
+            Message::ProfileGalleryUrlChanged(url) => {
+                self.edit_profile_gallery_url = url;
+                Command::none()
+            }
+            Message::ProfileGalleryRefresh => {
+                let url = self.edit_profile_gallery_url.trim().to_string();
+                if url == self.marketplace_settings.profile_gallery_url {
+                    return self.refresh_profile_gallery();
+                }
+                self.marketplace_settings.profile_gallery_url = url;
+                Command::batch([
+                    self.refresh_profile_gallery(),
+                    Command::perform(
+                        save_marketplace_settings_async(self.marketplace_settings.clone()),
+                        Message::SettingsSaved,
+                    ),
+                ])
+            }
+            Message::ProfileGalleryLoaded(res) => {
+                let gallery = &mut self.marketplace.gallery;
+                gallery.loading = false;
+                match res {
+                    Ok(index) => {
+                        gallery.profiles = index.profiles;
+                        gallery.offline = index.stale;
+                        gallery.error = None;
+                    }
+                    Err(e) => {
+                        gallery.profiles.clear();
+                        gallery.error = Some(e);
+                    }
+                }
+                Command::none()
+            }
+            Message::ProfileGallerySearchChanged(q) => {
+                self.marketplace.gallery.query = q;
+                Command::none()
+            }
+            Message::ProfileGallerySelect(entry) => {
+                let gallery = &mut self.marketplace.gallery;
+                gallery.selected = Some(entry.clone());
+                gallery.preview = None;
+                gallery.imported = false;
+                gallery.error = None;
+                let id = entry.id.clone();
+                Command::perform(fetch_gallery_profile_async(entry), move |res| {
+                    Message::ProfileGalleryPreviewLoaded { id, res }
+                })
+            }
+            Message::ProfileGalleryPreviewLoaded { id, res } => {
+                let gallery = &mut self.marketplace.gallery;
+                if gallery.selected.as_ref().is_some_and(|s| s.id == id) {
+                    gallery.preview = Some(res);
+                }
+                Command::none()
+            }
+            Message::ProfileGalleryImport => self.start_profile_import(),
+            Message::ProfileGalleryImported(res) => {
+                self.marketplace.gallery.importing = false;
+                // Plugins may have been installed even when the import failed.
+                let reload_plugins = Command::perform(list_plugins_async(), Message::PluginsLoaded);
+                match res {
+                    Ok(p) => {
+                        self.marketplace.gallery.imported = true;
+                        Command::batch([
+                            reload_plugins,
+                            self.handle_message(Message::ProfileCreated(Ok(p))),
+                        ])
+                    }
+                    Err(e) => {
+                        self.marketplace.gallery.error = Some(e);
+                        reload_plugins
+                    }
+                }
+            }
+            Message::MissingPluginsFound { ids, res } => {
+                if ids != self.missing_plugins.ids {
+                    return Command::none();
+                }
+                match res {
+                    Ok(found) => self.missing_plugins.found = Some(found),
+                    Err(e) => self.missing_plugins.error = Some(e),
+                }
+                Command::none()
+            }
+            Message::InstallMissingPlugins => {
+                let missing = &mut self.missing_plugins;
+                let Some(found) = missing.found.clone().filter(|f| !f.is_empty()) else {
+                    return Command::none();
+                };
+                if missing.installing {
+                    return Command::none();
+                }
+                missing.installing = true;
+                missing.error = None;
+                Command::perform(
+                    install_plugin_downloads_async(
+                        found,
+                        self.marketplace_settings.github_token().map(str::to_string),
+                    ),
+                    Message::MissingPluginsInstalled,
+                )
+            }
+            Message::MissingPluginsInstalled(res) => {
+                self.missing_plugins.installing = false;
+                if let Err(e) = res {
+                    self.missing_plugins.error = Some(e);
+                }
+                // The profile's diagnostics report the plugins that were missing.
+                let revalidate = self.profile.as_ref().map(|p| {
+                    Command::perform(validate_profile_async(p.id), Message::ProfileValidated)
+                });
+                let reload = Command::perform(list_plugins_async(), Message::PluginsLoaded);
+                Command::batch([reload].into_iter().chain(revalidate))
+            }
+            Message::MarketplacePrevPage => {
+                if self.marketplace.page == 0 {
+                    return Command::none();
+                }
+                self.marketplace.page = self.marketplace.page.saturating_sub(1);
+                self.marketplace_fetch_icons_for_current_page()
+            }
+            Message::MarketplaceNextPage => {
+                self.marketplace.page = self.marketplace.page.saturating_add(1);
+                self.marketplace_fetch_icons_for_current_page()
+            }
+            Message::MarketplaceIconLoaded { key, bytes } => {
+                self.marketplace.icons_pending.remove(&key);
+                if let Ok(bytes) = bytes {
+                    self.marketplace
+                        .icon_cache
+                        .insert(key, iced::widget::image::Handle::from_memory(bytes));
+                }
+                Command::none()
+            }
+            Message::MarketplaceSelect(p) => {
+                self.marketplace.selected = Some(p.clone());
+                self.marketplace.error = None;
+
+                let Some(src) = self.current_marketplace_source().cloned() else {
+                    return Command::none();
+                };
+
+                let mut cmds = vec![];
+
+                // Ensure the selected icon is fetched (may not be in visible range).
+                cmds.extend(self.marketplace_fetch_icon(&src, &p));
+
+                // Fetch screenshots / images lazily.
+                for raw in p.images.iter().take(8) {
+                    let Some(url) = resolve_marketplace_asset_url(&src, raw) else {
+                        continue;
+                    };
+                    let key = format!("img|{}|{}|{}", src.index_url, p.id, url);
+                    if self.marketplace.image_cache.contains_key(&key)
+                        || self.marketplace.svg_cache.contains_key(&key)
+                    {
+                        continue;
+                    }
+                    cmds.push(Command::perform(
+                        fetch_icon_async(self.marketplace.downloader.clone(), url),
+                        {
+                            let key = key.clone();
+                            move |bytes| Message::MarketplaceImageLoaded { key, bytes }
+                        },
+                    ));
+                }
+
+                // Fetch richer details (README, derived downloads, README images).
+                if !self.marketplace.details_cache.contains_key(&p.id) {
+                    cmds.push(Command::perform(
+                        fetch_marketplace_details_async(
+                            p.clone(),
+                            self.marketplace_settings.github_token().map(str::to_string),
+                        ),
+                        {
+                            let plugin_id = p.id.clone();
+                            move |details| Message::MarketplaceDetailsLoaded { plugin_id, details }
+                        },
+                    ));
+                }
+
+                if cmds.is_empty() {
+                    Command::none()
+                } else {
+                Command::batch(cmds)
+            }
+            }
+            Message::MarketplaceImageLoaded { key, bytes } => {
+                match bytes {
+                    Ok(bytes) => {
+                        self.marketplace.image_error_cache.remove(&key);
+
+                        if is_svg_bytes(&bytes) {
+                            if let Err(e) = validate_svg_bytes(&bytes) {
+                                self.marketplace.image_error_cache.insert(key, format!("bad svg: {e}"));
+                                return Command::none();
+                            }
+                            self.marketplace
+                                .svg_cache
+                                .insert(key, iced::widget::svg::Handle::from_memory(bytes));
+                        } else if is_png_bytes(&bytes) || is_jpeg_bytes(&bytes) {
+                            self.marketplace
+                                .image_cache
+                                .insert(key, iced::widget::image::Handle::from_memory(bytes));
+                        } else if is_gif_bytes(&bytes) || is_webp_bytes(&bytes) {
+                            // Iced's image handle decoding may not support animated GIF/WEBP directly
+                            // (or may fail on some files). Decode via `image` and re-encode to PNG.
+                            match decode_raster_to_png_bytes(&bytes) {
+                                Ok(png) => {
+                                    self.marketplace.image_cache.insert(
+                                        key,
+                                        iced::widget::image::Handle::from_memory(png),
+                                    );
+                                }
+                                Err(e) => {
+                                    self.marketplace
+                                        .image_error_cache
+                                        .insert(key, format!("gif/webp decode failed: {e}"));
+                                }
+                            }
+                        } else {
+                            self.marketplace.image_error_cache.insert(
+                                key,
+                                "unsupported image format (expected svg/png/jpeg/gif/webp)".to_string(),
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        self.marketplace.image_error_cache.insert(key, e);
+                    }
+                }
+                Command::none()
+            }
+            Message::MarketplaceDetailsLoaded { plugin_id, details } => {
+                match details {
+                    Ok(d) => {
+                        // Merge cache.
+                        let mut merged = d.clone();
+                        if let Some(p) = self.marketplace.plugins.iter().find(|p| p.id == plugin_id) {
+                            // include any marketplace-provided images too
+                            for u in &p.images {
+                                if !merged.image_urls.contains(u) {
+                                    merged.image_urls.push(u.clone());
+                                }
+                            }
+                        }
+                        self.marketplace.details_cache.insert(plugin_id.clone(), merged.clone());
+
+                        // If we discovered a direct download URL, update the marketplace plugin in-place
+                        // so the list can show Install immediately.
+                        if let Some(url) = merged.resolved_download_url.clone() {
+                            if let Some(p) = self
+                                .marketplace
+                                .plugins
+                                .iter_mut()
+                                .find(|p| p.id == plugin_id)
+                            {
+                                if p.download_url.as_deref().map(|s| s.trim()).unwrap_or("").is_empty()
+                                {
+                                    p.download_url = Some(url.clone());
+                                }
+                            }
+                            if let Some(sel) = self.marketplace.selected.as_mut() {
+                                if sel.id == plugin_id
+                                    && sel.download_url.as_deref().map(|s| s.trim()).unwrap_or("").is_empty()
+                                {
+                                    sel.download_url = Some(url);
+                                }
+                            }
+                        }
+
+                        // Kick off image fetches for discovered URLs (best-effort).
+                        let Some(src) = self.current_marketplace_source().cloned() else {
+                            return Command::none();
+                        };
+                        let mut cmds = vec![];
+                        for raw in merged.image_urls.iter().take(12) {
+                            if !is_renderable_image_url(raw) {
+                                continue;
+                            }
+                            let Some(url) = resolve_marketplace_asset_url(&src, raw) else {
+                                continue;
+                            };
+                            let key = format!("img|{}|{}|{}", src.index_url, plugin_id, url);
+                            if self.marketplace.image_cache.contains_key(&key)
+                                || self.marketplace.svg_cache.contains_key(&key)
+                            {
+                                continue;
+                            }
+                            cmds.push(Command::perform(
+                                fetch_icon_async(self.marketplace.downloader.clone(), url),
+                                {
+                                    let key = key.clone();
+                                    move |bytes| Message::MarketplaceImageLoaded { key, bytes }
+                                },
+                            ));
+                        }
+                        if cmds.is_empty() {
+                            Command::none()
+                        } else {
+                            Command::batch(cmds)
+                        }
+                    }
+                    Err(e) => {
+                        // Keep the UI usable even if GitHub is rate-limited.
+                        tracing::debug!(plugin_id, error=%e, "marketplace details fetch failed");
+                        Command::none()
+                    }
+                }
+            }
+            Message::OpenUrl(url) => {
+                Command::perform(open_url_async(url), |_| Message::Tick)
+            }
+            Message::ActionModePicked(mode) => {
+                self.set_selected_action_mode(mode);
+                Command::none()
+            }
+            Message::BuiltinKindPicked(kind) => {
+                self.set_selected_builtin_kind(kind);
+                match kind {
+                    BuiltinKindChoice::Midi => self.list_midi_ports(),
+                    BuiltinKindChoice::Serial => self.list_serial_ports(),
+                    _ => Command::none(),
+                }
+            }
+            Message::BuiltinIssueCommandChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::IssueCommand { command, .. } = b {
+                        *command = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinIssueCwdChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::IssueCommand { cwd, .. } = b {
+                        let s = v.trim().to_string();
+                        *cwd = if s.is_empty() { None } else { Some(s) };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinIssueTimeoutChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::IssueCommand { timeout_ms, .. } = b {
+                        let s = v.trim();
+                        *timeout_ms = if s.is_empty() {
+                            None
+                        } else {
+                            s.parse::<u64>().ok()
+                        };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinKeyboardTextChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::KeyboardInput { text, .. } = b {
+                        let s = v;
+                        *text = if s.trim().is_empty() { None } else { Some(s) };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinKeyboardKeysChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::KeyboardInput { keys, .. } = b {
+                        *keys = v
+                            .split_whitespace()
+                            .map(|s| s.to_string())
+                            .collect::<Vec<_>>();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSoundPathChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::PlaySound { path, .. }
+                    | BuiltinAction::Soundboard { path, .. } = b
+                    {
+                        *path = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSoundVolumeChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::PlaySound { volume, .. }
+                    | BuiltinAction::Soundboard { volume, .. } = b
+                    {
+                        let v = v.clamp(0, 200) as u8;
+                        *volume = if v == 100 { None } else { Some(v) };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSoundboardModePicked(m) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Soundboard { mode, .. } = b {
+                        *mode = match m {
+                            SoundboardModeChoice::PlayOverlap => {
+                                actions::SoundboardMode::PlayOverlap
+                            }
+                            SoundboardModeChoice::Restart => actions::SoundboardMode::Restart,
+                            SoundboardModeChoice::StopOthers => actions::SoundboardMode::StopOthers,
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinOpenTargetChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Open { target } = b {
+                        *target = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinOpenAppPicked(app) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Open { target } = b {
+                        *target = app.target;
+                    }
+                });
+                Command::none()
+            }
+            Message::InstalledAppsLoaded(res) => {
+                match res {
+                    Ok(apps) => self.installed_apps = apps,
+                    Err(e) => tracing::warn!(error = %e, "failed to list installed applications"),
+                }
+                Command::none()
+            }
+            Message::VsCodeFoldersLoaded(res) => {
+                match res {
+                    Ok(folders) => self.vscode_workspaces = folders,
+                    Err(e) => tracing::warn!(error = %e, "failed to list VS Code folders"),
+                }
+                Command::none()
+            }
+            Message::ListVsCodeTasks(folder) => {
+                Command::perform(list_vscode_tasks_async(folder), Message::VsCodeTasksLoaded)
+            }
+            Message::VsCodeTasksLoaded(res) => {
+                match res {
+                    Ok(tasks) => self.vscode_tasks = Some(tasks),
+                    Err(e) => {
+                        self.vscode_tasks = None;
+                        self.error = Some(e);
+                    }
+                }
+                Command::none()
+            }
+            Message::BuiltinClipboardModePicked(m) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Clipboard { mode } = b {
+                        let text = match mode {
+                            actions::ClipboardMode::CopyText(t)
+                            | actions::ClipboardMode::PasteText(t) => std::mem::take(t),
+                            actions::ClipboardMode::CyclePrevious => String::new(),
+                        };
+                        *mode = match m {
+                            ClipboardModeChoice::CopyText => {
+                                actions::ClipboardMode::CopyText(text)
+                            }
+                            ClipboardModeChoice::PasteText => {
+                                actions::ClipboardMode::PasteText(text)
+                            }
+                            ClipboardModeChoice::CyclePrevious => {
+                                actions::ClipboardMode::CyclePrevious
+                            }
+                        };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinClipboardTextChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Clipboard {
+                        mode:
+                            actions::ClipboardMode::CopyText(text)
+                            | actions::ClipboardMode::PasteText(text),
+                    } = b
+                    {
+                        *text = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinCounterStepChanged(v) => {
+                if let Ok(v) = v.trim().parse::<i64>() {
+                    self.update_selected_builtin(|b| {
+                        if let BuiltinAction::Counter { step, .. } = b {
+                            *step = v;
+                        }
+                    });
+                }
+                Command::none()
+            }
+            Message::BuiltinCounterResetOnLongPressToggled(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Counter {
+                        reset_on_long_press,
+                        ..
+                    } = b
+                    {
+                        *reset_on_long_press = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinStopSoundPathChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::StopSound { path } = b {
+                        let s = v.trim().to_string();
+                        *path = if s.is_empty() { None } else { Some(s) };
+                    }
+                });
+                Command::none()
+            }
+            Message::AudioDevicesLoaded(res) => {
+                match res {
+                    Ok(devices) => self.audio_devices = devices,
+                    Err(e) => tracing::warn!(error = %e, "failed to list audio output devices"),
+                }
+                Command::none()
+            }
+            Message::AudioDevicePicked(choice) => {
+                let device = match choice {
+                    AudioDeviceChoice::Default => None,
+                    AudioDeviceChoice::Named(name) => Some(name),
+                };
+                if let Err(e) = self.audio.set_output_device(device.clone()) {
+                    self.error = Some(e.to_string());
+                }
+                self.audio_output_device = device.clone();
+                Command::perform(save_audio_device_async(device), Message::SettingsSaved)
+            }
+            Message::SettingsSaved(res) => {
+                if let Err(e) = res {
+                    self.error = Some(format!("Failed to save settings: {e}"));
+                }
+                Command::none()
+            }
+            Message::BuiltinSwitchProfilePicked(choice) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::SwitchProfile { mode } = b {
+                        *mode = match choice {
+                            SwitchProfileChoice::Next => actions::SwitchProfileMode::Next,
+                            SwitchProfileChoice::Prev => actions::SwitchProfileMode::Prev,
+                            SwitchProfileChoice::To(id) => {
+                                actions::SwitchProfileMode::To { profile_id: id.0 }
+                            }
+                        };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinBrightnessModePicked(m) => {
+                // Keep existing value if possible.
+                let current = self.selected_builtin_brightness_value().unwrap_or(30);
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::DeviceBrightness { mode } = b {
+                        *mode = match m {
+                            BrightnessModeChoice::Set => actions::BrightnessMode::Set {
+                                percent: current as u8,
+                            },
+                            BrightnessModeChoice::Increase => actions::BrightnessMode::Increase {
+                                delta: current as u8,
+                            },
+                            BrightnessModeChoice::Decrease => actions::BrightnessMode::Decrease {
+                                delta: current as u8,
+                            },
+                        };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinBrightnessValueChanged(v) => {
+                let v = v.clamp(0, 100) as u8;
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::DeviceBrightness { mode } = b {
+                        match mode {
+                            actions::BrightnessMode::Set { percent } => *percent = v,
+                            actions::BrightnessMode::Increase { delta } => *delta = v,
+                            actions::BrightnessMode::Decrease { delta } => *delta = v,
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinMonitorKindPicked(k) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::SystemMonitoring { kind, .. } = b {
+                        if MonitorKindChoice::of(kind) != k {
+                            *kind = k.kind();
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinMonitorTargetChanged(v) => {
+                let target = (!v.is_empty()).then_some(v);
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::SystemMonitoring { kind, .. } = b {
+                        match kind {
+                            actions::MonitorKind::NetworkRx { interface }
+                            | actions::MonitorKind::NetworkTx { interface } => *interface = target,
+                            actions::MonitorKind::DiskUsage { mount } => *mount = target,
+                            actions::MonitorKind::Temperature { sensor } => *sensor = target,
+                            actions::MonitorKind::GpuUsage { gpu }
+                            | actions::MonitorKind::GpuMemory { gpu } => {
+                                *gpu = target.and_then(|t| t.trim().parse().ok());
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinClockTimezoneChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Clock { timezone, .. } = b {
+                        *timezone = Some(v.trim().to_string()).filter(|t| !t.is_empty());
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinClockFormatPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Clock { format, .. } = b {
+                        *format = c.format();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinClockDateToggled(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Clock { show_date, .. } = b {
+                        *show_date = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinClockWeekToggled(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Clock { show_week, .. } = b {
+                        *show_week = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinPomodoroMinutesChanged { field, value } => {
+                let Ok(v) = value.trim().parse::<u32>() else {
+                    return Command::none();
+                };
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Pomodoro {
+                        work_min,
+                        break_min,
+                        long_break_every,
+                        long_break_min,
+                        ..
+                    } = b
+                    {
+                        match field {
+                            PomodoroField::Work => *work_min = v.max(1),
+                            PomodoroField::Break => *break_min = v.max(1),
+                            PomodoroField::LongBreak => *long_break_min = v.max(1),
+                            PomodoroField::LongBreakEvery => *long_break_every = v,
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinPomodoroSoundChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Pomodoro { sound, .. } = b {
+                        *sound = Some(v.trim().to_string()).filter(|s| !s.is_empty());
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinPomodoroNotifyToggled(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Pomodoro { notify, .. } = b {
+                        *notify = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinNotifyTitleChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Notify { title, .. } = b {
+                        *title = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinNotifyBodyChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Notify { body, .. } = b {
+                        *body = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinDoNotDisturbModePicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::DoNotDisturb { mode } = b {
+                        *mode = c.mode();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinRadioPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Radio { radio } = b {
+                        *radio = c.radio();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinPowerCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Power { command, .. } = b {
+                        *command = c.command();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinPowerLongPressToggled(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Power {
+                        require_long_press, ..
+                    } = b
+                    {
+                        *require_long_press = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinCiRepoChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::CiStatus { repo, .. } = b {
+                        *repo = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinCiWorkflowChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::CiStatus { workflow, .. } = b {
+                        *workflow = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinCiBranchChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::CiStatus { branch, .. } = b {
+                        *branch = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinContainerChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Container { container, .. } = b {
+                        *container = v;
+                    }
+                });
+                self.apply_displays_if_connected()
+            }
+            Message::BuiltinContainerCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Container { command, .. } = b {
+                        *command = c.command();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinMeetingAppPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Meeting { app, .. } = b {
+                        *app = c.app();
+                    }
+                });
+                Command::batch([self.check_meetings(), self.apply_displays_if_connected()])
+            }
+            Message::BuiltinMeetingCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Meeting { command, .. } = b {
+                        *command = c.command();
+                    }
+                });
+                self.apply_displays_if_connected()
+            }
+            Message::BuiltinLightKindPicked(kind) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Light { light, .. } = b {
+                        if LightKindChoice::of(light) != kind {
+                            *light = kind.target();
+                        }
+                    }
+                });
+                let list = match kind {
+                    LightKindChoice::Hue if self.hue_lights.is_none() => Command::perform(
+                        list_hue_lights_async(self.lights.clone()),
+                        Message::HueLightsListed,
+                    ),
+                    LightKindChoice::Wled | LightKindChoice::KeyLight
+                        if self.light_devices.is_none() =>
+                    {
+                        Command::perform(discover_lights_async(), Message::LightsDiscovered)
+                    }
+                    _ => Command::none(),
+                };
+                Command::batch([list, self.apply_displays_if_connected()])
+            }
+            Message::BuiltinLightChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Light { light, .. } = b {
+                        match light {
+                            actions::LightTarget::Hue { light: id } => *id = v,
+                            actions::LightTarget::Wled { host }
+                            | actions::LightTarget::KeyLight { host } => *host = v,
+                        }
+                    }
+                });
+                Command::batch([self.check_lights(), self.apply_displays_if_connected()])
+            }
+            Message::BuiltinLightCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Light { command, .. } = b {
+                        *command = c.command();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinLightAmountChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Light { command, .. } = b {
+                        match command {
+                            actions::LightCommand::SetBrightness(percent) => {
+                                *percent = v.clamp(0, 100) as u8;
+                            }
+                            actions::LightCommand::StepBrightness(step) => {
+                                *step = v.clamp(-100, 100) as i8;
+                            }
+                            actions::LightCommand::ColorTemperature(kelvin) => {
+                                *kelvin = v.clamp(2000, 7000) as u16;
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinLightColorChanged(channel, v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Light {
+                        command: actions::LightCommand::Color(rgb),
+                        ..
+                    } = b
+                    {
+                        rgb[channel] = v.clamp(0, 255) as u8;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinMidiPortChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Midi { port, .. } = b {
+                        *port = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinMidiEdited(edit) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Midi { message, .. } = b {
+                        *message = MidiMessageChoice::edit(*message, edit);
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinOscTargetChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Osc { target, .. } = b {
+                        *target = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinOscAddressChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Osc { address, .. } = b {
+                        *address = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinOscArgsChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Osc { args, .. } = b {
+                        *args = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSerialPortChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Serial { port, .. } = b {
+                        *port = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSerialBaudPicked(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Serial { baud, .. } = b {
+                        *baud = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSerialFormatPicked(choice) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Serial { format, .. } = b {
+                        *format = choice.format();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinSerialPayloadChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Serial { payload, .. } = b {
+                        *payload = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinWakeOnLanMacChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::WakeOnLan { mac, .. } = b {
+                        *mac = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinWakeOnLanBroadcastChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::WakeOnLan { broadcast, .. } = b {
+                        *broadcast = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinGitRepoChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Git { repo, .. } = b {
+                        *repo = v;
+                    }
+                });
+                self.check_git()
+            }
+            Message::BuiltinGitCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Git { command, .. } = b {
+                        *command = c.command();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinVsCodeWorkspaceChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::VsCode { workspace, .. } = b {
+                        workspace.clone_from(&v);
+                    }
+                });
+                self.vscode_tasks = None;
+                Command::perform(list_vscode_tasks_async(v), Message::VsCodeTasksLoaded)
+            }
+            Message::BuiltinVsCodeCommandPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::VsCode { command, .. } = b {
+                        *command = match c {
+                            VsCodeCommandChoice::Open => actions::VsCodeCommand::Open,
+                            VsCodeCommandChoice::RunTask => {
+                                actions::VsCodeCommand::RunTask(String::new())
+                            }
+                        };
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinVsCodeTaskChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::VsCode {
+                        command: actions::VsCodeCommand::RunTask(label),
+                        ..
+                    } = b
+                    {
+                        *label = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinWeatherLocationChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Weather { location, .. } = b {
+                        *location = v;
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinWeatherUnitsPicked(c) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Weather { units, .. } = b {
+                        *units = c.units();
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinWeatherRefreshChanged(v) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Weather { refresh_min, .. } = b {
+                        *refresh_min = v.trim().parse().ok().filter(|m| *m > 0);
+                    }
+                });
+                Command::none()
+            }
+            Message::BuiltinMonitorMetricToggled(m, on) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::SystemMonitoring { kind, metrics, .. } = b {
+                        metrics.retain(|k| MonitorKindChoice::of(k) != m);
+                        // A panel of the metric picked above shows the same interface, mount
+                        // point or sensor.
+                        if on && MonitorKindChoice::of(kind) == m {
+                            metrics.push(kind.clone());
+                        } else if on {
+                            metrics.push(m.kind());
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::MacroAddStep => {
+                self.macro_add_step();
+                Command::none()
+            }
+            Message::MacroRemoveStep(i) => {
+                self.macro_remove_step(i);
+                Command::none()
+            }
+            Message::MacroMoveStepUp(i) => {
+                self.macro_move_step(i, true);
+                Command::none()
+            }
+            Message::MacroMoveStepDown(i) => {
+                self.macro_move_step(i, false);
+                Command::none()
+            }
+            Message::MacroStepKindPicked { idx, kind } => {
+                self.macro_set_step_kind(idx, kind);
+                Command::none()
+            }
+            Message::MacroStepDelayChanged { idx, value } => {
+                self.macro_set_step_delay(idx, value);
+                Command::none()
+            }
+            Message::MacroStepPluginPicked { idx, choice } => {
+                self.macro_set_step_plugin(idx, choice);
+                Command::none()
+            }
+            Message::MacroStepCommandChanged { idx, value } => {
+                self.macro_set_step_command(idx, value);
+                Command::none()
+            }
+            Message::MacroStepTextChanged { idx, value } => {
+                self.macro_set_step_text(idx, value);
+                Command::none()
+            }
+            Message::MacroRecordToggled => {
+                match self.macro_recorder.take() {
+                    Some(recorder) => {
+                        let recorded = recorder.finish();
+                        self.update_selected_builtin(|b| {
+                            if let BuiltinAction::Macro { steps } = b {
+                                steps.extend(recorded);
+                            }
+                        });
+                    }
+                    None => self.macro_recorder = Some(macro_recorder::MacroRecorder::new()),
+                }
+                Command::none()
+            }
+            Message::MacroKeyRecorded(key, modifiers) => {
+                use iced::keyboard::{key::Named, Key};
+
+                if key == Key::Named(Named::Escape) && modifiers.is_empty() {
+                    return self.handle_message(Message::MacroRecordToggled);
+                }
+                if let Some(recorder) = &mut self.macro_recorder {
+                    recorder.record_key(&key, modifiers);
+                }
+                Command::none()
+            }
+            Message::ConditionKindPicked(kind) => {
+                self.update_selected_builtin(|b| {
+                    if let BuiltinAction::Conditional { condition, .. } = b {
+                        if ConditionKindChoice::of(condition) != kind {
+                            *condition = kind.default_condition();
+                        }
+                    }
+                });
+                Command::none()
+            }
+            Message::ConditionFieldChanged { field, value } => {
+                self.set_condition_field(field, value);
+                Command::none()
+            }
+            Message::BranchKindPicked { branch, kind } => {
+                self.set_branch_kind(branch, kind);
+                Command::none()
+            }
+            Message::BranchPluginPicked { branch, choice } => {
+                let settings = default_settings_for_action(&self.plugins, &choice);
+                self.update_selected_branch(branch, |slot| {
+                    *slot = Some(Box::new(ActionBinding::Plugin(PluginActionBinding {
+                        plugin_id: choice.plugin_id,
+                        action_id: choice.action_id,
+                        settings,
+                    })));
+                });
+                Command::none()
+            }
+            Message::BranchCommandChanged { branch, value } => {
+                self.update_selected_branch(branch, |slot| {
+                    *slot = Some(Box::new(ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                        command: value,
+                        cwd: None,
+                        timeout_ms: None,
+                    })));
+                });
+                Command::none()
+            }
+            Message::StartDragAction(a) => {
+                self.drag.dragging = Some(a);
+                self.drag.over_key = None;
+                Command::none()
+            }
+            Message::CancelDragAction => {
+                self.drag.dragging = None;
+                self.drag.over_key = None;
+                Command::none()
+            }
+            Message::DragOverKey(idx) => {
+                self.drag.over_key = idx;
+                Command::none()
+            }
+            Message::KeyPressed(idx) => {
+                // Shift-click adds keys to (or removes them from) a selection for bulk edits.
+                if let (true, Some(SelectedControl::Key(first))) =
+                    (self.modifiers.shift(), self.selected_control)
+                {
+                    self.selected_keys.insert(first);
+                    if !self.selected_keys.insert(idx) && idx != first {
+                        self.selected_keys.remove(&idx);
+                    }
+                    return Command::none();
+                }
+                self.drag.moving_key = Some(idx);
+                self.handle_message(Message::SelectControl(SelectedControl::Key(idx)))
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                Command::none()
+            }
+            Message::MoveKeyFocus(dx, dy) => {
+                let Some(c) = &self.connected else {
+                    return Command::none();
+                };
+                let caps = self.layout_capabilities(c);
+                if !self.grid_focused {
+                    return Command::none();
+                }
+                self.focus_visible = true;
+                let idx = match self.selected_control {
+                    Some(SelectedControl::Key(idx)) => grid_neighbor(idx, &caps, dx, dy),
+                    _ => 0,
+                };
+                self.handle_message(Message::SelectControl(SelectedControl::Key(idx)))
+            }
+            Message::OpenInspector => {
+                let key_selected = matches!(self.selected_control, Some(SelectedControl::Key(_)));
+                if !self.grid_focused || !key_selected {
+                    return Command::none();
+                }
+                self.grid_focused = false;
+                iced::widget::text_input::focus(iced::widget::text_input::Id::new(INSPECTOR_LABEL))
+            }
+            Message::CycleFocus { backwards } => {
+                self.grid_focused = false;
+                if backwards {
+                    iced::widget::focus_previous()
+                } else {
+                    iced::widget::focus_next()
+                }
+            }
+            Message::FocusGrid => {
+                self.grid_focused = true;
+                self.focus_visible = true;
+                Command::none()
+            }
+            Message::PointerPressed => {
+                // Clicking a key focuses the grid; clicking anywhere else (e.g. a text field)
+                // takes the focus away, so arrow keys no longer move the selection.
+                self.grid_focused = self.drag.over_key.is_some();
+                self.focus_visible = false;
+                Command::none()
+            }
+            Message::TogglePalette => {
+                if self.palette.take().is_some() {
+                    return Command::none();
+                }
+                self.palette = Some(PaletteState::default());
+                iced::widget::text_input::focus(iced::widget::text_input::Id::new(PALETTE_INPUT))
+            }
+            Message::ClosePalette => {
+                self.palette = None;
+                Command::none()
+            }
+            Message::PaletteQueryChanged(query) => {
+                self.palette = Some(PaletteState { query, selected: 0 });
+                Command::none()
+            }
+            Message::PaletteMove(delta) => {
+                let Some(palette) = &self.palette else {
+                    return Command::none();
+                };
+                let matches = self.command_registry().search(&palette.query).len();
+                let last = matches.min(PALETTE_MAX_RESULTS).saturating_sub(1);
+                if let Some(palette) = &mut self.palette {
+                    let selected = palette.selected.saturating_add_signed(delta as isize);
+                    palette.selected = selected.min(last);
+                }
+                Command::none()
+            }
+            Message::PaletteSubmit => {
+                let Some(palette) = &self.palette else {
+                    return Command::none();
+                };
+                let registry = self.command_registry();
+                let matches = registry.search(&palette.query);
+                let picked = matches.get(palette.selected).map(|e| e.command.clone());
+                match picked {
+                    Some(command) => self.handle_message(Message::PaletteRun(command)),
+                    None => Command::none(),
+                }
+            }
+            Message::PaletteRun(command) => {
+                self.palette = None;
+                self.run_app_command(command)
+            }
+            Message::KeyMoveEnded => {
+                self.drag.moving_key = None;
+                Command::none()
+            }
+            Message::KeyEdited(edit) => self.edit_selected_key(edit),
+            Message::DropOnKey(idx) => {
+                let moved_from = self.drag.moving_key.take();
+                let Some(dragged) = self.drag.dragging.clone() else {
+                    return match moved_from {
+                        Some(from) if from != idx => self.swap_keys(from, idx),
+                        _ => Command::none(),
+                    };
+                };
+                self.drag.dragging = None;
+                self.drag.over_key = None;
+
+                self.selected_control = Some(SelectedControl::Key(idx));
+                self.selected_binding_target = BindingTarget::KeyPress;
+                self.assign_dragged_action_to_key(idx, dragged);
+                if let Some(k) = self.profile.as_ref().and_then(|p| p.keys.get(idx)) {
+                    self.edit_icon_path = k.appearance.icon_path.clone().unwrap_or_default();
+                }
+                Command::none()
+            }
+            Message::ActionSelected(choice) => {
+                if self.selected_control.is_none() {
+                    self.error = Some(
+                        "Select a key/dial/touch strip in the preview before assigning an action."
+                            .to_string(),
+                    );
+                    return Command::none();
+                };
+                let Some(_p) = &mut self.profile else {
+                    self.error = Some("No profile loaded.".to_string());
+                    return Command::none();
+                };
+
+                let settings = default_settings_for_action(&self.plugins, &choice);
+                if let (Some(SelectedControl::Key(idx)), BindingTarget::KeyPress) =
+                    (self.selected_control, self.selected_binding_target)
+                {
+                    if let Some(k) = self.profile.as_mut().and_then(|p| p.keys.get_mut(idx)) {
+                        apply_default_action_icon(&mut k.appearance, &choice);
+                        self.edit_icon_path = k.appearance.icon_path.clone().unwrap_or_default();
+                    }
+                }
+                let Some(slot) = self.selected_binding_mut() else {
+                    self.error = Some("Invalid binding target for selected control.".to_string());
+                    return Command::none();
+                };
+                *slot = Some(ActionBinding::Plugin(PluginActionBinding {
+                    plugin_id: choice.plugin_id.clone(),
+                    action_id: choice.action_id.clone(),
+                    settings,
+                }));
+                Command::none()
+            }
+            Message::ActionSearchChanged(s) => {
+                self.action_search = s;
+                Command::none()
+            }
+            Message::SettingStringChanged { key, value } => {
+                self.set_selected_plugin_setting(key, serde_json::Value::String(value));
+                Command::none()
+            }
+            Message::SettingBoolChanged { key, value } => {
+                self.set_selected_plugin_setting(key, serde_json::Value::Bool(value));
+                Command::none()
+            }
+            Message::SettingNumberChanged { key, value } => {
+                let num = value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64);
+                let v = num
+                    .map(serde_json::Value::Number)
+                    .unwrap_or(serde_json::Value::Null);
+                self.set_selected_plugin_setting(key, v);
+                Command::none()
+            }
+            Message::Tick => Command::batch([
+                self.end_flashes(),
+                self.end_notification(),
+                self.animate_power_holds(),
+            ]),
+            Message::SystemSampled(sample) => {
+                self.sys_snapshot = sample;
+                self.monitoring.set_interval(self.monitoring_interval());
+                if self.profile.as_ref().and_then(strip_monitoring).is_some() {
+                    return self.apply_displays_if_connected();
+                }
+                Command::none()
+            }
+            Message::Engine(n) => self.handle_engine_notification(n),
+            Message::Tray(cmd) => self.handle_tray_command(cmd),
+            Message::WindowCloseRequested => {
+                if self.tray.as_ref().is_some_and(|t| t.is_available()) {
+                    tracing::info!("window closed; running in the background (tray)");
+                    iced::window::change_mode(iced::window::Id::MAIN, iced::window::Mode::Hidden)
+                } else {
+                    iced::window::close(iced::window::Id::MAIN)
+                }
+            }
+            Message::SetPaused(paused) => {
+                self.paused = paused;
+                if let Some(c) = &self.connected {
+                    c.engine.set_paused(paused);
+                }
+                Command::none()
+            }
+            Message::SetDryRun(dry_run) => {
+                self.dry_run = dry_run;
+                if let Some(c) = &self.connected {
+                    c.engine.set_dry_run(dry_run);
+                }
+                Command::none()
+            }
+            Message::BrightnessChanged(v) => self.apply_brightness(v.clamp(0, 100) as u8),
+            Message::BrightnessReleased => {
+                let Some(c) = &self.connected else {
+                    return Command::none();
+                };
+                let brightness = c.brightness;
+                match &mut self.profile {
+                    // Adjusting a profile with its own brightness changes that instead.
+                    Some(p) if p.brightness.is_some() => {
+                        p.brightness = Some(brightness);
+                        Command::perform(save_profile_async(p.clone()), Message::ProfileSaved)
+                    }
+                    _ => self.save_device_brightness(brightness),
+                }
+            }
+            Message::ProfileBrightnessToggled(enabled) => {
+                let brightness = self.connected.as_ref().map(|c| c.brightness);
+                let Some(p) = &mut self.profile else {
+                    return Command::none();
+                };
+                p.brightness = brightness.filter(|_| enabled);
+                Command::perform(save_profile_async(p.clone()), Message::ProfileSaved)
+            }
+            Message::DeviceCleared(res) => {
+                // The device may already be gone (e.g. unplugged); nothing to show for that.
+                if let Err(e) = res {
+                    tracing::debug!(error = %e, "failed to clear device");
+                }
+                Command::none()
+            }
+            Message::BrightnessApplied(res) => {
+                if let Err(e) = res {
+                    self.error = Some(e);
+                }
+                Command::none()
+            }
+            Message::BaseProfilePicked(choice) => {
+                let Some(mut p) = self.profile.clone() else {
+                    return Command::none();
+                };
+                p.base_profile = match choice {
+                    BaseProfileChoice::None => None,
+                    BaseProfileChoice::Profile(base) => Some(base.id),
+                };
+                Command::perform(set_base_profile_async(p), Message::BaseProfileApplied)
+            }
+            Message::BaseProfileApplied(res) => match res {
+                Ok(p) => self.handle_message(Message::ProfileLoaded(Ok(p))),
+                Err(e) => {
+                    self.error = Some(e);
+                    Command::none()
+                }
+            },
+            Message::RevertKeyToBase(idx) => {
+                let Some(k) = self.profile.as_mut().and_then(|p| p.keys.get_mut(idx)) else {
+                    return Command::none();
+                };
+                let Some(base) = k.base.clone() else {
+                    return Command::none();
+                };
+                *k = storage::profiles::KeyConfig {
+                    base: Some(base.clone()),
+                    ..*base
+                };
+                Command::batch([
+                    self.handle_message(Message::SelectControl(SelectedControl::Key(idx))),
+                    self.apply_displays_if_connected(),
+                ])
+            }
+            Message::OpenColorPicker => {
+                self.show_color_picker = !self.show_color_picker;
+                Command::none()
+            }
+            Message::ColorPicked(c) => {
+                self.show_color_picker = false;
+                let r = (c.r * 255.0) as u8;
+                let g = (c.g * 255.0) as u8;
+                let b = (c.b * 255.0) as u8;
+                let s = format!("{},{},{}", r, g, b);
+                Command::perform(async move { s }, Message::BgRgbChanged)
+            }
+            Message::OpenIconPicker => {
+                Command::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .add_filter("Images", &["png", "jpg", "jpeg", "gif", "webp", "svg"])
+                            .pick_file()
+                            .await
+                            .map(|f| f.path().to_path_buf())
+                    },
+                    Message::IconPicked,
+                )
+            }
+            Message::IconPicked(path) => {
+                if let Some(p) = path {
+                    let s = p.to_string_lossy().to_string();
+                    Command::perform(async move { s }, Message::IconPathChanged)
+                } else {
+                    Command::none()
+                }
+            }
+        }
+    }
+
+    fn view_root(&self) -> Element<'_, Message> {
+        if self.kiosk_locked() {
+            return self.view_kiosk_lock();
+        }
         let content = self.view_main_content();
 
-        let overlay: Option<Element<Message>> = if self.show_color_picker {
-            let color = parse_bg_rgb(&self.edit_bg_rgb)
-                .map(|[r, g, b]| Color::from_rgb8(r, g, b))
-                .unwrap_or(Color::WHITE);
+        let overlay: Option<Element<Message>> = if let Some(palette) = &self.palette {
+            Some(self.view_command_palette(palette))
+        } else if self.show_color_picker {
+            let color = parse_bg_rgb(&self.edit_bg_rgb)
+                .map(|[r, g, b]| Color::from_rgb8(r, g, b))
+                .unwrap_or(Color::WHITE);
+
+            Some(
+                container(
+                    container(
+                        color_picker(
+                            true,
+                            color,
+                            button(text("Cancel")).on_press(Message::OpenColorPicker),
+                            Message::OpenColorPicker,
+                            Message::ColorPicked,
+                        ),
+                    )
+                    .padding(20)
+                    .style(iced::theme::Container::Box),
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(Horizontal::Left)
+                .align_y(Vertical::Top)
+                .padding(iced::Padding::from([100.0, 0.0, 0.0, 350.0]))
+                .into(),
+            )
+        } else if let Some(plugin_id) = &self.permission_prompt {
+            let name = self
+                .plugins
+                .iter()
+                .find(|p| &p.manifest.id == plugin_id)
+                .map(|p| p.manifest.name.clone())
+                .unwrap_or_else(|| plugin_id.clone());
+            Some(
+                container(
+                    container(
+                        column![
+                            text("Permission request").size(16),
+                            text(format!(
+                                "{name} ({plugin_id}) asks for network access while sandboxed."
+                            ))
+                            .size(13),
+                            text("Denying keeps the plugin offline; you can change this later.")
+                                .size(12)
+                                .style(self.color_text_muted()),
+                            row![
+                                horizontal_space(),
+                                button(text("Deny"))
+                                    .style(iced::theme::Button::Secondary)
+                                    .on_press(Message::PluginPermissionDecided {
+                                        plugin_id: plugin_id.clone(),
+                                        allow: false,
+                                    }),
+                                button(text("Allow"))
+                                    .style(iced::theme::Button::Primary)
+                                    .on_press(Message::PluginPermissionDecided {
+                                        plugin_id: plugin_id.clone(),
+                                        allow: true,
+                                    }),
+                            ]
+                            .spacing(8),
+                        ]
+                        .spacing(10)
+                        .width(Length::Fixed(420.0)),
+                    )
+                    .padding(20)
+                    .style(iced::theme::Container::Box),
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .into(),
+            )
+        } else {
+            None
+        };
+
+        let on_esc = if self.palette.is_some() {
+            Message::ClosePalette
+        } else if self.show_color_picker {
+            Message::OpenColorPicker
+        } else {
+            Message::DismissPermissionPrompt
+        };
+        Modal::new(content, overlay).on_esc(on_esc).into()
+    }
+
+    fn color_text_muted(&self) -> Color {
+        // Keep muted text aligned with the chosen theme, without hard-coding a random gray.
+        let p = self.active_theme.theme.extended_palette();
+        let base = p.background.base.text;
+        Color { a: 0.72, ..base }
+    }
+
+    /// A modal overlay (command palette, color picker, permission prompt) takes the keyboard.
+    fn overlay_open(&self) -> bool {
+        self.palette.is_some() || self.show_color_picker || self.permission_prompt.is_some()
+    }
+
+    /// The surface the active profile targets, when the connected deck is part of it.
+    fn active_surface(&self) -> Option<&Surface> {
+        let c = self.connected.as_ref()?;
+        surface_of(&self.surfaces, self.profile.as_ref()?, c.id)
+    }
+
+    /// Key layout of the preview and key navigation: the surface's grid for a surface profile,
+    /// the deck's own otherwise.
+    fn layout_capabilities(&self, c: &ConnectedUi) -> DeviceCapabilities {
+        let mut caps = c.capabilities;
+        if let Some(surface) = self.active_surface() {
+            caps.key_grid = surface.grid();
+            caps.key_count = surface.key_count();
+        }
+        caps
+    }
+
+    /// Change the surfaces and save them, unless a changed surface no longer works.
+    fn update_surfaces(&mut self, f: impl FnOnce(&mut Vec<Surface>)) -> Command<Message> {
+        let mut surfaces = self.surfaces.clone();
+        f(&mut surfaces);
+        if let Some(e) = surfaces.iter().find_map(|s| s.check().err()) {
+            self.error = Some(format!("{e:#}"));
+            return Command::none();
+        }
+        self.surfaces = surfaces.clone();
+        Command::perform(save_surfaces_async(surfaces), Message::SurfacesSaved)
+    }
+
+    /// The active profile is locked for kiosk use and the PIN was not entered: it can't be
+    /// edited or switched away from.
+    fn kiosk_locked(&self) -> bool {
+        self.kiosk.enabled()
+            && !self.kiosk_unlocked
+            && self
+                .selected_profile
+                .is_some_and(|id| self.kiosk.is_locked(id))
+    }
+
+    fn view_kiosk_lock(&self) -> Element<'_, Message> {
+        let name = self
+            .profile
+            .as_ref()
+            .map_or("This profile", |p| p.name.as_str());
+        let mut col = column![
+            text(format!("{name} is locked")).size(20),
+            text("Enter the PIN to edit or switch profiles.")
+                .size(13)
+                .style(self.color_text_muted()),
+            row![
+                text_input("PIN", &self.edit_kiosk_pin)
+                    .secure(true)
+                    .on_input(Message::KioskPinChanged)
+                    .on_submit(Message::UnlockKiosk)
+                    .width(Length::Fixed(200.0)),
+                button(text("Unlock")).on_press(Message::UnlockKiosk),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        if self.kiosk_pin_wrong {
+            col = col.push(text("Wrong PIN.").size(12).style(self.color_text_muted()));
+        }
+        container(col)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .into()
+    }
+
+}
+
+impl App {
+    fn view_command_palette(&self, palette: &PaletteState) -> Element<'_, Message> {
+        let registry = self.command_registry();
+        let matches = registry.search(&palette.query);
+
+        let mut list = column![].spacing(2);
+        for (i, entry) in matches.iter().take(PALETTE_MAX_RESULTS).enumerate() {
+            let style = if i == palette.selected {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Text
+            };
+            list = list.push(
+                button(text(&entry.title).size(13))
+                    .width(Length::Fill)
+                    .style(style)
+                    .on_press(Message::PaletteRun(entry.command.clone())),
+            );
+        }
+        if matches.is_empty() {
+            list = list.push(text("No matching commands.").size(13).style(self.color_text_muted()));
+        }
+
+        container(
+            container(
+                column![
+                    text_input("Type a command…", &palette.query)
+                        .id(iced::widget::text_input::Id::new(PALETTE_INPUT))
+                        .on_input(Message::PaletteQueryChanged)
+                        .on_submit(Message::PaletteSubmit),
+                    list,
+                    text("↑↓ to choose, Enter to run, Esc to close")
+                        .size(11)
+                        .style(self.color_text_muted()),
+                ]
+                .spacing(10)
+                .width(Length::Fixed(480.0)),
+            )
+            .padding(16)
+            .style(iced::theme::Container::Box),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_x(Horizontal::Center)
+        .align_y(Vertical::Top)
+        .padding(iced::Padding::from([80.0, 0.0, 0.0, 0.0]))
+        .into()
+    }
+
+    fn view_main_content(&self) -> Element<'_, Message> {
+        let topbar = self.view_topbar();
+        let content: Element<Message> = match self.active_view {
+            ActiveView::Main => {
+                let sidebar = self.view_sidebar();
+                let preview = self.view_preview_panel();
+                let inspector = self.view_inspector_panel();
+                let actions = self.view_actions_panel();
+
+                let main = column![preview, h_divider(), inspector]
+                    .spacing(0)
+                    .width(Length::Fill)
+                    .height(Length::Fill);
+
+                row![sidebar, v_divider(), main, v_divider(), actions]
+                    .spacing(0)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into()
+            }
+            ActiveView::Marketplace => self.view_marketplace(),
+            ActiveView::Schedules => self.view_schedules(),
+            ActiveView::Webhooks => self.view_webhooks(),
+            ActiveView::TypedTriggers => self.view_typed_triggers(),
+            ActiveView::FileWatchers => self.view_file_watchers(),
+            ActiveView::ProcessWatchers => self.view_process_watchers(),
+            ActiveView::Midi => self.view_midi(),
+            ActiveView::Osc => self.view_osc(),
+            ActiveView::History => self.view_history(),
+            ActiveView::Stats => self.view_stats(),
+            ActiveView::DeviceTest => self.view_device_test(),
+        };
+
+        let mut root = column![topbar]
+            .spacing(10)
+            .padding(12)
+            .width(Length::Fill)
+            .height(Length::Fill);
+
+        if let Some(diag) = &self.hid_diagnosis {
+            root = root.push(self.view_hid_diagnosis(diag));
+        }
+        if !self.profile_diagnostics.is_empty() {
+            root = root.push(self.view_profile_diagnostics());
+        }
+        if !self.missing_plugins.ids.is_empty() {
+            root = root.push(self.view_missing_plugins());
+        }
+        if !self.sync.conflicts.is_empty() {
+            root = root.push(self.view_sync_conflicts());
+        }
+        root = root.push(content);
+
+        if let Some(err) = &self.error {
+            root = root.push(self.view_error_banner(err));
+        }
+
+        container(root)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(app_background())
+            .into()
+    }
+
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    RefreshDevices,
+    HidDiagnosed(Option<HidrawDiagnosis>),
+    InstallUdevRule,
+    UdevRuleInstalled(Result<(), String>),
+    DevicesLoaded(Result<Vec<DiscoveredDevice>, String>),
+    OpenColorPicker,
+    ColorPicked(Color),
+    OpenIconPicker,
+    IconPicked(Option<std::path::PathBuf>),
+    DevicePicked(DeviceChoice),
+    Connected(Result<ConnectedInfo, String>),
+    Engine(EngineNotification),
+    Tray(tray::TrayCommand),
+    WindowCloseRequested,
+    SetPaused(bool),
+    SetDryRun(bool),
+    RefreshProfiles,
+    ProfilesLoaded(Result<Vec<ProfileMeta>, String>),
+    CreateProfile,
+    ProfileCreated(Result<Profile, String>),
+    ProfilePicked(ProfileChoice),
+    ProfileLoaded(Result<Profile, String>),
+    ProfileValidated((ProfileId, Vec<Diagnostic>)),
+    DeviceCleared(Result<(), String>),
+    DismissProfileDiagnostics,
+    SelectControl(SelectedControl),
+    LabelChanged(String),
+    BindingTargetPicked(BindingTarget),
+    /// Fire the selected binding as if its control was used, reporting each step.
+    TestBinding,
+    BindingTested(Result<Vec<StepReport>, String>),
+    BgRgbChanged(String),
+    IconPathChanged(String),
+    DisplayTextChanged(String),
+    EntityIdChanged(String),
+    TextSizePicked(TextSizeChoice),
+    ClearSelectedActions,
+    FlashPicked(FlashChoice),
+    SaveProfile,
+    ProfileSaved(Result<(), String>),
+    DisplaysApplied(Result<(), String>),
+    PreviewsRendered(Result<Vec<(Slot, iced::widget::image::Handle)>, String>),
+    LivePreviewToggled(bool),
+    RefreshPlugins,
+    PluginsLoaded(Result<Vec<InstalledPlugin>, String>),
+    InstallPluginPathChanged(String),
+    InstallPluginFromPath,
+    PluginInstalled(Result<(), String>),
+    ActionIconsLoaded(Vec<(std::path::PathBuf, iced::widget::image::Handle)>),
+    PluginEnabledToggled { plugin_id: String, enabled: bool },
+    PluginEnabledApplied(Result<(), String>),
+    SandboxToggled(bool),
+    SandboxApplied(Result<(), String>),
+    ShowPermissionPrompt(String),
+    PluginPermissionDecided { plugin_id: String, allow: bool },
+    PluginPermissionApplied(Result<(), String>),
+    DismissPermissionPrompt,
+    TogglePluginDetails(String),
+    PluginDetailsEdited(PluginDetailsEdit),
+    PluginLaunchApplied(Result<(), String>),
+    UninstallPlugin(String),
+    PluginUninstalled(Result<UninstallReport, String>),
+    OpenMarketplace,
+    CloseMarketplace,
+    Hotkey(hotkeys::HotkeyEvent),
+    RecordHotkey,
+    HotkeyRecorded(iced::keyboard::Key, iced::keyboard::Modifiers),
+    RecordChord,
+    ChordRecorded(iced::keyboard::Key, iced::keyboard::Modifiers),
+    ClearChord,
+    ClearHotkey,
+    OpenSchedules,
+    CloseSchedules,
+    SchedulesLoaded(Result<Vec<Schedule>, String>),
+    SchedulesSaved(Result<(), String>),
+    AddSchedule,
+    RemoveSchedule(u64),
+    ScheduleEdited { id: u64, edit: ScheduleEdit },
+    OpenWebhooks,
+    CloseWebhooks,
+    WebhooksLoaded(Result<Vec<Webhook>, String>),
+    WebhooksSaved(Result<(), String>),
+    AddWebhook,
+    RemoveWebhook(u64),
+    WebhookEdited { id: u64, edit: WebhookEdit },
+    WebhookServerEdited(WebhookServerEdit),
+    OpenTypedTriggers,
+    CloseTypedTriggers,
+    TypedTriggersLoaded(Result<Vec<TypedTrigger>, String>),
+    TypedTriggersSaved(Result<(), String>),
+    AddTypedTrigger,
+    RemoveTypedTrigger(u64),
+    TypedTriggerEdited { id: u64, edit: TypedTriggerEdit },
+    TextExpanderToggled(bool),
+    OpenFileWatchers,
+    CloseFileWatchers,
+    AddFileWatcher,
+    RemoveFileWatcher(u64),
+    FileWatcherEdited {
+        id: u64,
+        edit: FileWatcherEdit,
+    },
+    OpenProcessWatchers,
+    CloseProcessWatchers,
+    ProcessWatchersLoaded(Result<Vec<ProcessWatcher>, String>),
+    ProcessWatchersSaved(Result<(), String>),
+    AddProcessWatcher,
+    RemoveProcessWatcher(u64),
+    ProcessWatcherEdited {
+        id: u64,
+        edit: ProcessWatcherEdit,
+    },
+    OpenMidi,
+    CloseMidi,
+    ListMidiPorts,
+    MidiOutputPortsListed(Result<Vec<String>, String>),
+    MidiInputPortsListed(Result<Vec<String>, String>),
+    ListSerialPorts,
+    SerialPortsListed(Result<Vec<String>, String>),
+    MidiSettingsEdited(MidiSettingsEdit),
+    MidiTriggersLoaded(Result<Vec<MidiTrigger>, String>),
+    MidiTriggersSaved(Result<(), String>),
+    AddMidiTrigger,
+    RemoveMidiTrigger(u64),
+    MidiTriggerEdited {
+        id: u64,
+        edit: MidiTriggerEdit,
+    },
+    OpenOsc,
+    CloseOsc,
+    OscSettingsEdited(OscSettingsEdit),
+    OscTriggersLoaded(Result<Vec<OscTrigger>, String>),
+    OscTriggersSaved(Result<(), String>),
+    AddOscTrigger,
+    RemoveOscTrigger(u64),
+    OscTriggerEdited {
+        id: u64,
+        edit: OscTriggerEdit,
+    },
+    NotificationMirrorEdited(NotificationMirrorEdit),
+    OpenHistory,
+    CloseHistory,
+    OpenStats,
+    CloseStats,
+    UsageLoaded(Result<UsageFile, String>),
+    UsageSaved(Result<(), String>),
+    SetUsageHeatmap(bool),
+    /// Forget the usage of the active profile.
+    ResetUsage,
+    ActivityLoaded(Result<VecDeque<ActivityEntry>, String>),
+    ActivitySaved(Result<(), String>),
+    ActivityFilterChanged(String),
+    ActivityFailuresOnly(bool),
+    ClearActivity,
+    OpenDeviceTest,
+    CloseDeviceTest,
+    RefreshHidInterfaces,
+    HidInterfacesListed(Result<Vec<device_test::HidInterface>, String>),
+    HidInterfacePicked(device_test::HidInterface),
+    HidReport(Result<InputReport, String>),
+    ClearHidReports,
+    SetKeyTestCycling(bool),
+    KeyTestTick,
+    TestPatternWritten(Result<Vec<Duration>, String>),
+    CompanionEdited(CompanionEdit),
+    TouchGesturesEdited(TouchGestureEdit),
+    MacroLimitsEdited(MacroLimitsEdit),
+    InterfaceEdited(InterfaceEdit),
+    /// Set the UI scale (percent) and save it.
+    SetUiScale(u16),
+    ThemePicked(ThemeChoice),
+    CheckThemes,
+    ThemesLoaded(Result<Vec<UserTheme>, String>),
+    HomeAssistantEdited(HomeAssistantEdit),
+    GithubTokenChanged(String),
+    GithubTokenSubmitted,
+    SecretNameChanged(String),
+    SecretValueChanged(String),
+    SaveSecret,
+    SecretSaved(Result<(), String>),
+    RemoveSecret(String),
+    SecretRemoved(Result<(), String>),
+    SurfacesLoaded(Result<Vec<Surface>, String>),
+    SurfacesSaved(Result<(), String>),
+    SurfaceNameChanged(String),
+    AddSurface,
+    RemoveSurface(u64),
+    /// Add the connected deck to a surface.
+    AddDeckToSurface(u64),
+    RemoveDeckFromSurface {
+        surface: u64,
+        device: u64,
+    },
+    MoveSurfaceDeck {
+        surface: u64,
+        device: u64,
+        dx: i8,
+        dy: i8,
+    },
+    CreateSurfaceProfile(u64),
+    KioskPinChanged(String),
+    UnlockKiosk,
+    LockKiosk,
+    KioskProfileToggled(ProfileId, bool),
+    KioskPinSubmitted,
+    SyncFolderChanged(String),
+    SyncFolderSubmitted,
+    CheckSync,
+    Synced(Result<SyncReport, String>),
+    ResolveSyncConflict(String, Resolution),
+    SyncConflictResolved(Result<(String, Resolution), String>),
+    HomeAssistantEvent(home_assistant::Event),
+    SpotifyClientIdChanged(String),
+    SpotifySignIn,
+    SpotifySignedIn(Result<(), String>),
+    SpotifySignOut,
+    SpotifyEvent(spotify::Event),
+    SystemSampled(Sample),
+    ClockTick,
+    PomodoroTick,
+    RefreshWeather,
+    WeatherFetched((WeatherQuery, Result<WeatherReport, String>)),
+    CheckDoNotDisturb,
+    DoNotDisturbChecked(Result<bool, String>),
+    CheckRadios,
+    RadioChecked((actions::RadioKind, Result<bool, String>)),
+    RefreshCi,
+    CiFetched((CiQuery, Result<Option<CiRun>, String>)),
+    ListContainers,
+    ContainersListed(Result<Vec<Container>, String>),
+    CheckGit,
+    GitChecked((String, Result<GitStatus, String>)),
+    CheckLights,
+    LightChecked((actions::LightTarget, Result<LightState, String>)),
+    CheckMeetings,
+    MeetingChecked((actions::MeetingApp, Result<MeetingState, String>)),
+    DiscoverLights,
+    LightsDiscovered(Result<Vec<Discovered>, String>),
+    ListHueLights,
+    HueLightsListed(Result<Vec<HueLight>, String>),
+    HueBridgeChanged(String),
+    PairHue,
+    /// The bridge and the application key it handed out.
+    HuePaired(Result<(String, String), String>),
+    MarketplaceRefresh,
+    MarketplaceSourcePicked(MarketplaceSource),
+    MarketplaceSearchChanged(String),
+    MarketplaceCategoryPicked(String),
+    MarketplaceCompatibleOnlyToggled(bool),
+    MarketplaceLoaded(Result<MarketplaceIndex, String>),
+    MarketplaceIconLoaded { key: String, bytes: Result<Vec<u8>, String> },
+    MarketplacePrevPage,
+    MarketplaceNextPage,
+    MarketplaceSelect(MarketplacePlugin),
+    MarketplaceImageLoaded { key: String, bytes: Result<Vec<u8>, String> },
+    MarketplaceDetailsLoaded {
+        plugin_id: String,
+        details: Result<MarketplaceDetails, String>,
+    },
+    OpenUrl(String),
+    MarketplaceInstall(MarketplacePlugin),
+    MarketplaceInstallAnyway(MarketplacePlugin),
+    MarketplaceInstalled {
+        plugin: MarketplacePlugin,
+        res: Result<(), MarketplaceInstallError>,
+    },
+    MarketplaceTabPicked(MarketplaceTab),
+    ProfileGalleryUrlChanged(String),
+    ProfileGalleryRefresh,
+    ProfileGalleryLoaded(Result<ProfileGallery, String>),
+    ProfileGallerySearchChanged(String),
+    ProfileGallerySelect(GalleryProfile),
+    ProfileGalleryPreviewLoaded {
+        id: String,
+        res: Result<Profile, String>,
+    },
+    ProfileGalleryImport,
+    /// The imported profile, saved.
+    ProfileGalleryImported(Result<Profile, String>),
+    MissingPluginsFound {
+        ids: Vec<String>,
+        res: Result<Vec<PluginDownload>, String>,
+    },
+    InstallMissingPlugins,
+    MissingPluginsInstalled(Result<(), String>),
+    ActionModePicked(ActionModeChoice),
+    BuiltinKindPicked(BuiltinKindChoice),
+    BuiltinIssueCommandChanged(String),
+    BuiltinIssueCwdChanged(String),
+    BuiltinIssueTimeoutChanged(String),
+    BuiltinKeyboardTextChanged(String),
+    BuiltinKeyboardKeysChanged(String),
+    BuiltinSoundPathChanged(String),
+    BuiltinSoundVolumeChanged(i32),
+    BuiltinStopSoundPathChanged(String),
+    BuiltinSoundboardModePicked(SoundboardModeChoice),
+    BuiltinCounterStepChanged(String),
+    BuiltinClipboardModePicked(ClipboardModeChoice),
+    BuiltinOpenTargetChanged(String),
+    BuiltinOpenAppPicked(AppEntry),
+    InstalledAppsLoaded(Result<Vec<AppEntry>, String>),
+    VsCodeFoldersLoaded(Result<Vec<String>, String>),
+    /// Task labels of a folder, with the folder.
+    VsCodeTasksLoaded(Result<(String, Vec<String>), String>),
+    ListVsCodeTasks(String),
+    BuiltinClipboardTextChanged(String),
+    BuiltinCounterResetOnLongPressToggled(bool),
+    CountersLoaded(Result<(ProfileId, BTreeMap<u8, i64>), String>),
+    PomodorosLoaded(Result<(ProfileId, BTreeMap<u8, PomodoroTimer>), String>),
+    VariableChanged(VariableChange),
+    ClearVariable(String),
+    BuiltinSetVariableNameChanged(String),
+    BuiltinSetVariableValueChanged(String),
+    BuiltinScriptSourceChanged(String),
+    BuiltinHomeAssistantServiceChanged(String),
+    BuiltinHomeAssistantEntityChanged(String),
+    BuiltinHomeAssistantDataChanged(String),
+    BuiltinSpotifyCommandPicked(SpotifyCommandChoice),
+    BuiltinScreenshotRegionPicked(ScreenshotRegionChoice),
+    BuiltinCaptureDirChanged(String),
+    BuiltinScreenshotClipboardToggled(bool),
+    CounterReset(u8),
+    PomodoroReset(u8),
+    AudioDevicesLoaded(Result<Vec<String>, String>),
+    AudioDevicePicked(AudioDeviceChoice),
+    SettingsSaved(Result<(), String>),
+    BuiltinSwitchProfilePicked(SwitchProfileChoice),
+    BuiltinBrightnessModePicked(BrightnessModeChoice),
+    BuiltinBrightnessValueChanged(i32),
+    BuiltinMonitorKindPicked(MonitorKindChoice),
+    BuiltinMonitorTargetChanged(String),
+    BuiltinMonitorMetricToggled(MonitorKindChoice, bool),
+    BuiltinClockTimezoneChanged(String),
+    BuiltinClockFormatPicked(ClockFormatChoice),
+    BuiltinClockDateToggled(bool),
+    BuiltinClockWeekToggled(bool),
+    BuiltinPomodoroMinutesChanged {
+        field: PomodoroField,
+        value: String,
+    },
+    BuiltinPomodoroSoundChanged(String),
+    BuiltinPomodoroNotifyToggled(bool),
+    BuiltinNotifyTitleChanged(String),
+    BuiltinNotifyBodyChanged(String),
+    BuiltinDoNotDisturbModePicked(DoNotDisturbModeChoice),
+    BuiltinRadioPicked(RadioChoice),
+    BuiltinPowerCommandPicked(PowerCommandChoice),
+    BuiltinPowerLongPressToggled(bool),
+    BuiltinCiRepoChanged(String),
+    BuiltinCiWorkflowChanged(String),
+    BuiltinCiBranchChanged(String),
+    BuiltinContainerChanged(String),
+    BuiltinContainerCommandPicked(ContainerCommandChoice),
+    BuiltinMidiPortChanged(String),
+    BuiltinMidiEdited(MidiMessageEdit),
+    BuiltinOscTargetChanged(String),
+    BuiltinOscAddressChanged(String),
+    BuiltinOscArgsChanged(String),
+    BuiltinSerialPortChanged(String),
+    BuiltinSerialBaudPicked(u32),
+    BuiltinSerialFormatPicked(SerialFormatChoice),
+    BuiltinSerialPayloadChanged(String),
+    BuiltinWakeOnLanMacChanged(String),
+    BuiltinWakeOnLanBroadcastChanged(String),
+    BuiltinMeetingAppPicked(MeetingAppChoice),
+    BuiltinMeetingCommandPicked(MeetingCommandChoice),
+    BuiltinLightKindPicked(LightKindChoice),
+    /// The Hue light's id or the WLED controller's host.
+    BuiltinLightChanged(String),
+    BuiltinLightCommandPicked(LightCommandChoice),
+    BuiltinLightAmountChanged(i32),
+    /// A channel (0 red, 1 green, 2 blue) of a `light` action's color.
+    BuiltinLightColorChanged(usize, i32),
+    BuiltinGitRepoChanged(String),
+    BuiltinGitCommandPicked(GitCommandChoice),
+    BuiltinVsCodeWorkspaceChanged(String),
+    BuiltinVsCodeCommandPicked(VsCodeCommandChoice),
+    BuiltinVsCodeTaskChanged(String),
+    BuiltinWeatherLocationChanged(String),
+    BuiltinWeatherUnitsPicked(WeatherUnitsChoice),
+    BuiltinWeatherRefreshChanged(String),
+    MacroAddStep,
+    MacroRemoveStep(usize),
+    MacroMoveStepUp(usize),
+    MacroMoveStepDown(usize),
+    MacroStepKindPicked { idx: usize, kind: MacroStepKindChoice },
+    MacroStepDelayChanged { idx: usize, value: String },
+    MacroStepPluginPicked { idx: usize, choice: ActionChoice },
+    ConditionKindPicked(ConditionKindChoice),
+    ConditionFieldChanged { field: ConditionField, value: String },
+    BranchKindPicked { branch: Branch, kind: BranchKindChoice },
+    BranchPluginPicked { branch: Branch, choice: ActionChoice },
+    BranchCommandChanged { branch: Branch, value: String },
+    MacroStepCommandChanged { idx: usize, value: String },
+    MacroStepTextChanged { idx: usize, value: String },
+    /// Start recording steps into the selected macro, or stop and append them.
+    MacroRecordToggled,
+    MacroKeyRecorded(iced::keyboard::Key, iced::keyboard::Modifiers),
+    StartDragAction(DraggedAction),
+    CancelDragAction,
+    DragOverKey(Option<usize>),
+    DropOnKey(usize),
+    KeyPressed(usize),
+    KeyMoveEnded,
+    TogglePalette,
+    ClosePalette,
+    PaletteQueryChanged(String),
+    PaletteMove(i32),
+    PaletteSubmit,
+    PaletteRun(AppCommand),
+    ModifiersChanged(iced::keyboard::Modifiers),
+    /// Arrow keys on the focused key grid: move the selection by columns and rows.
+    MoveKeyFocus(i32, i32),
+    /// Enter on the focused key grid: edit the selected key in the inspector.
+    OpenInspector,
+    /// Tab / Shift+Tab: move between text fields.
+    CycleFocus { backwards: bool },
+    FocusGrid,
+    PointerPressed,
+    KeyEdited(KeyEdit),
+    ActionSelected(ActionChoice),
+    ActionSearchChanged(String),
+    SettingStringChanged { key: String, value: String },
+    SettingBoolChanged { key: String, value: bool },
+    SettingNumberChanged { key: String, value: String },
+    Tick,
+    BrightnessChanged(i32),
+    BrightnessReleased,
+    ProfileBrightnessToggled(bool),
+    BrightnessApplied(Result<(), String>),
+    BaseProfilePicked(BaseProfileChoice),
+    BaseProfileApplied(Result<Profile, String>),
+    RevertKeyToBase(usize),
+}
+
+#[derive(Clone)]
+struct ConnectedInfo {
+    id: app_core::ids::DeviceId,
+    name: String,
+    key_count: u8,
+    capabilities: DeviceCapabilities,
+    controller: DeviceController,
+    events_slot: Arc<std::sync::Mutex<Option<Receiver<DeviceEvent>>>>,
+}
+
+impl fmt::Debug for ConnectedInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectedInfo")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("key_count", &self.key_count)
+            .finish_non_exhaustive()
+    }
+}
+
+struct ConnectedUi {
+    id: app_core::ids::DeviceId,
+    name: String,
+    key_count: u8,
+    /// Key grid and image sizes, strip and dials, as reported by the device.
+    capabilities: DeviceCapabilities,
+    pressed: Vec<bool>,
+    /// Soundboard keys whose sound is playing (highlighted in the preview and on the device).
+    playing_sounds: BTreeSet<u8>,
+    brightness: u8,
+    controller: DeviceController,
+    engine: EngineHandle,
+    /// Rendered LCD images and what the device currently shows; unchanged slots are not resent.
+    display_cache: Arc<std::sync::Mutex<ImageCache>>,
+    /// Registered with Bitfocus Companion, which draws the keys meanwhile.
+    companion_connected: bool,
+    /// The device went away; the engine reopens it once it is back.
+    reconnecting: bool,
+    /// A screen recording is running; `screen_record` keys are drawn red.
+    screen_recording: bool,
+    /// Surfaces flashing after their binding fired, with when the flash started.
+    flashing: HashMap<Slot, (render::lcd::Flash, Instant)>,
+    /// What each surface of the device shows, for the live preview.
+    previews: HashMap<Slot, iced::widget::image::Handle>,
+    /// Last profile/plugin snapshot pushed to the engine (see `App::sync_engine`).
+    synced_profile: Option<Profile>,
+    synced_plugins: Option<Vec<PluginSyncKey>>,
+    synced_schedules: Option<Vec<Schedule>>,
+    synced_webhooks: Option<(WebhookSettings, Vec<Webhook>)>,
+    synced_typed_triggers: Option<(TextExpanderSettings, Vec<TypedTrigger>)>,
+    synced_process_watchers: Option<Vec<ProcessWatcher>>,
+    synced_midi: Option<(MidiSettings, Vec<MidiTrigger>)>,
+    synced_osc: Option<(OscSettings, Vec<OscTrigger>)>,
+    synced_companion: Option<CompanionSettings>,
+    synced_notification_mirror: Option<NotificationMirrorSettings>,
+    synced_locked_profiles: Option<Vec<ProfileId>>,
+    synced_surface: Option<Surface>,
+    /// Display caches of the other decks of the surface, while the engine has them open.
+    surface_caches: HashMap<app_core::ids::DeviceId, Arc<std::sync::Mutex<ImageCache>>>,
+}
+
+/// The parts of an installed plugin that affect dispatch; compared to avoid re-pushing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PluginSyncKey {
+    dir: std::path::PathBuf,
+    enabled: bool,
+    sandboxed: bool,
+    network_granted: Option<bool>,
+    launch: PluginLaunch,
+}
+
+impl PluginSyncKey {
+    fn of(plugins: &[InstalledPlugin]) -> Vec<Self> {
+        plugins
+            .iter()
+            .map(|p| Self {
+                dir: p.dir.clone(),
+                enabled: p.enabled,
+                sandboxed: p.sandboxed,
+                network_granted: p.network_granted,
+                launch: p.launch.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Messages from a channel whose receiver is handed over through `slot`.
+///
+/// `session` should change whenever a new receiver is put in the slot, so iced restarts the
+/// stream with it (e.g. the engine receiver on every connect).
+fn receiver_subscription<T: Send + 'static>(
+    name: &'static str,
+    session: u64,
+    slot: Arc<std::sync::Mutex<Option<UnboundedReceiver<T>>>>,
+    to_message: fn(T) -> Message,
+) -> Subscription<Message> {
+    enum State<T> {
+        Pending(Arc<std::sync::Mutex<Option<UnboundedReceiver<T>>>>),
+        Running(UnboundedReceiver<T>),
+    }
+
+    fn take<T>(
+        slot: &std::sync::Mutex<Option<UnboundedReceiver<T>>>,
+    ) -> Option<UnboundedReceiver<T>> {
+        slot.lock().ok().and_then(|mut g| g.take())
+    }
+
+    iced::subscription::unfold((name, session), State::Pending(slot), move |state| async move {
+        let mut rx = match state {
+            State::Pending(slot) => match take(&slot) {
+                Some(rx) => rx,
+                None => iced::futures::future::pending().await,
+            },
+            State::Running(rx) => rx,
+        };
+        match rx.recv().await {
+            Some(v) => (to_message(v), State::Running(rx)),
+            None => iced::futures::future::pending().await,
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DeviceChoice {
+    id: app_core::ids::DeviceId,
+    label: String,
+}
+
+impl fmt::Display for DeviceChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ProfileChoice {
+    id: ProfileId,
+    label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BaseProfileChoice {
+    None,
+    Profile(ProfileChoice),
+}
+
+impl fmt::Display for BaseProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseProfileChoice::None => write!(f, "No base profile"),
+            BaseProfileChoice::Profile(p) => write!(f, "Based on {}", p.label),
+        }
+    }
+}
+
+impl fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ActionChoice {
+    plugin_id: String,
+    action_id: String,
+    label: String,
+    /// Icon file declared by the action's manifest.
+    icon: Option<std::path::PathBuf>,
+}
+
+impl fmt::Display for ActionChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ActionModeChoice {
+    None,
+    Plugin,
+    Builtin,
+}
+
+impl fmt::Display for ActionModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionModeChoice::None => write!(f, "None"),
+            ActionModeChoice::Plugin => write!(f, "Plugin"),
+            ActionModeChoice::Builtin => write!(f, "Builtin"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BuiltinKindChoice {
+    Macro,
+    IssueCommand,
+    KeyboardInput,
+    PlaySound,
+    StopSound,
+    Soundboard,
+    Counter,
+    Clipboard,
+    Open,
+    Conditional,
+    SetVariable,
+    HomeAssistant,
+    Spotify,
+    Screenshot,
+    ScreenRecord,
+    SwitchProfile,
+    DeviceBrightness,
+    SystemMonitoring,
+    Clock,
+    Weather,
+    Pomodoro,
+    Notify,
+    DoNotDisturb,
+    Radio,
+    Power,
+    VsCode,
+    Git,
+    Container,
+    CiStatus,
+    Light,
+    Meeting,
+    Midi,
+    Osc,
+    Serial,
+    WakeOnLan,
+    Script,
+}
+
+impl BuiltinKindChoice {
+    const ALL: [BuiltinKindChoice; 36] = [
+        BuiltinKindChoice::Macro,
+        BuiltinKindChoice::IssueCommand,
+        BuiltinKindChoice::KeyboardInput,
+        BuiltinKindChoice::PlaySound,
+        BuiltinKindChoice::StopSound,
+        BuiltinKindChoice::Soundboard,
+        BuiltinKindChoice::Counter,
+        BuiltinKindChoice::Clipboard,
+        BuiltinKindChoice::Open,
+        BuiltinKindChoice::Conditional,
+        BuiltinKindChoice::SetVariable,
+        BuiltinKindChoice::HomeAssistant,
+        BuiltinKindChoice::Spotify,
+        BuiltinKindChoice::Screenshot,
+        BuiltinKindChoice::ScreenRecord,
+        BuiltinKindChoice::SwitchProfile,
+        BuiltinKindChoice::DeviceBrightness,
+        BuiltinKindChoice::SystemMonitoring,
+        BuiltinKindChoice::Clock,
+        BuiltinKindChoice::Weather,
+        BuiltinKindChoice::Pomodoro,
+        BuiltinKindChoice::Notify,
+        BuiltinKindChoice::DoNotDisturb,
+        BuiltinKindChoice::Radio,
+        BuiltinKindChoice::Power,
+        BuiltinKindChoice::VsCode,
+        BuiltinKindChoice::Git,
+        BuiltinKindChoice::Container,
+        BuiltinKindChoice::CiStatus,
+        BuiltinKindChoice::Light,
+        BuiltinKindChoice::Meeting,
+        BuiltinKindChoice::Midi,
+        BuiltinKindChoice::Osc,
+        BuiltinKindChoice::Serial,
+        BuiltinKindChoice::WakeOnLan,
+        BuiltinKindChoice::Script,
+    ];
+
+    fn of(b: &BuiltinAction) -> Self {
+        match b {
+            BuiltinAction::Macro { .. } => BuiltinKindChoice::Macro,
+            BuiltinAction::IssueCommand { .. } => BuiltinKindChoice::IssueCommand,
+            BuiltinAction::KeyboardInput { .. } => BuiltinKindChoice::KeyboardInput,
+            BuiltinAction::PlaySound { .. } => BuiltinKindChoice::PlaySound,
+            BuiltinAction::StopSound { .. } => BuiltinKindChoice::StopSound,
+            BuiltinAction::Soundboard { .. } => BuiltinKindChoice::Soundboard,
+            BuiltinAction::Counter { .. } => BuiltinKindChoice::Counter,
+            BuiltinAction::Clipboard { .. } => BuiltinKindChoice::Clipboard,
+            BuiltinAction::Open { .. } => BuiltinKindChoice::Open,
+            BuiltinAction::Conditional { .. } => BuiltinKindChoice::Conditional,
+            BuiltinAction::SetVariable { .. } => BuiltinKindChoice::SetVariable,
+            BuiltinAction::HomeAssistant { .. } => BuiltinKindChoice::HomeAssistant,
+            BuiltinAction::Spotify { .. } => BuiltinKindChoice::Spotify,
+            BuiltinAction::Screenshot { .. } => BuiltinKindChoice::Screenshot,
+            BuiltinAction::ScreenRecord { .. } => BuiltinKindChoice::ScreenRecord,
+            BuiltinAction::SwitchProfile { .. } => BuiltinKindChoice::SwitchProfile,
+            BuiltinAction::DeviceBrightness { .. } => BuiltinKindChoice::DeviceBrightness,
+            BuiltinAction::SystemMonitoring { .. } => BuiltinKindChoice::SystemMonitoring,
+            BuiltinAction::Clock { .. } => BuiltinKindChoice::Clock,
+            BuiltinAction::Weather { .. } => BuiltinKindChoice::Weather,
+            BuiltinAction::Pomodoro { .. } => BuiltinKindChoice::Pomodoro,
+            BuiltinAction::Notify { .. } => BuiltinKindChoice::Notify,
+            BuiltinAction::DoNotDisturb { .. } => BuiltinKindChoice::DoNotDisturb,
+            BuiltinAction::Radio { .. } => BuiltinKindChoice::Radio,
+            BuiltinAction::Power { .. } => BuiltinKindChoice::Power,
+            BuiltinAction::VsCode { .. } => BuiltinKindChoice::VsCode,
+            BuiltinAction::Git { .. } => BuiltinKindChoice::Git,
+            BuiltinAction::Container { .. } => BuiltinKindChoice::Container,
+            BuiltinAction::CiStatus { .. } => BuiltinKindChoice::CiStatus,
+            BuiltinAction::Light { .. } => BuiltinKindChoice::Light,
+            BuiltinAction::Meeting { .. } => BuiltinKindChoice::Meeting,
+            BuiltinAction::Midi { .. } => BuiltinKindChoice::Midi,
+            BuiltinAction::Osc { .. } => BuiltinKindChoice::Osc,
+            BuiltinAction::Serial { .. } => BuiltinKindChoice::Serial,
+            BuiltinAction::WakeOnLan { .. } => BuiltinKindChoice::WakeOnLan,
+            BuiltinAction::Script { .. } => BuiltinKindChoice::Script,
+        }
+    }
+
+    /// A new action of this kind with empty/default settings.
+    fn default_action(self) -> BuiltinAction {
+        match self {
+            BuiltinKindChoice::Macro => BuiltinAction::Macro { steps: vec![] },
+            BuiltinKindChoice::IssueCommand => BuiltinAction::IssueCommand {
+                command: String::new(),
+                cwd: None,
+                timeout_ms: None,
+            },
+            BuiltinKindChoice::KeyboardInput => BuiltinAction::KeyboardInput {
+                text: None,
+                keys: vec![],
+                chord: None,
+            },
+            BuiltinKindChoice::PlaySound => BuiltinAction::PlaySound {
+                path: String::new(),
+                volume: None,
+            },
+            BuiltinKindChoice::StopSound => BuiltinAction::StopSound { path: None },
+            BuiltinKindChoice::Soundboard => BuiltinAction::Soundboard {
+                path: String::new(),
+                volume: None,
+                mode: actions::SoundboardMode::PlayOverlap,
+            },
+            BuiltinKindChoice::Counter => BuiltinAction::Counter {
+                step: 1,
+                reset_on_long_press: false,
+            },
+            BuiltinKindChoice::Clipboard => BuiltinAction::Clipboard {
+                mode: actions::ClipboardMode::PasteText(String::new()),
+            },
+            BuiltinKindChoice::Open => BuiltinAction::Open {
+                target: String::new(),
+            },
+            BuiltinKindChoice::Conditional => BuiltinAction::Conditional {
+                condition: ConditionKindChoice::ProcessRunning.default_condition(),
+                then: None,
+                otherwise: None,
+            },
+            BuiltinKindChoice::SetVariable => BuiltinAction::SetVariable {
+                name: String::new(),
+                value: String::new(),
+            },
+            BuiltinKindChoice::HomeAssistant => BuiltinAction::HomeAssistant {
+                service: "homeassistant.toggle".to_string(),
+                entity_id: String::new(),
+                data: String::new(),
+            },
+            BuiltinKindChoice::Spotify => BuiltinAction::Spotify {
+                command: actions::SpotifyCommand::PlayPause,
+            },
+            BuiltinKindChoice::Screenshot => BuiltinAction::Screenshot {
+                region: actions::ScreenshotRegion::FullScreen,
+                save_dir: None,
+                clipboard: false,
+            },
+            BuiltinKindChoice::ScreenRecord => BuiltinAction::ScreenRecord { save_dir: None },
+            BuiltinKindChoice::SwitchProfile => BuiltinAction::SwitchProfile {
+                mode: actions::SwitchProfileMode::Next,
+            },
+            BuiltinKindChoice::DeviceBrightness => BuiltinAction::DeviceBrightness {
+                mode: actions::BrightnessMode::Set { percent: 30 },
+            },
+            BuiltinKindChoice::SystemMonitoring => BuiltinAction::SystemMonitoring {
+                kind: actions::MonitorKind::Cpu,
+                refresh_ms: Some(500),
+                metrics: vec![],
+            },
+            BuiltinKindChoice::Clock => BuiltinAction::Clock {
+                timezone: None,
+                format: actions::ClockFormat::TwentyFourHour,
+                show_date: false,
+                show_week: false,
+            },
+            BuiltinKindChoice::Weather => BuiltinAction::Weather {
+                location: String::new(),
+                units: actions::WeatherUnits::Metric,
+                refresh_min: None,
+            },
+            BuiltinKindChoice::Pomodoro => BuiltinAction::Pomodoro {
+                work_min: 25,
+                break_min: 5,
+                long_break_every: 4,
+                long_break_min: 15,
+                sound: None,
+                notify: false,
+            },
+            BuiltinKindChoice::Notify => BuiltinAction::Notify {
+                title: String::new(),
+                body: String::new(),
+            },
+            BuiltinKindChoice::DoNotDisturb => BuiltinAction::DoNotDisturb {
+                mode: actions::DoNotDisturbMode::Toggle,
+            },
+            BuiltinKindChoice::Radio => BuiltinAction::Radio {
+                radio: actions::RadioKind::Wifi,
+            },
+            BuiltinKindChoice::Power => BuiltinAction::Power {
+                command: actions::PowerCommand::Lock,
+                require_long_press: true,
+            },
+            BuiltinKindChoice::VsCode => BuiltinAction::VsCode {
+                workspace: String::new(),
+                command: actions::VsCodeCommand::Open,
+            },
+            BuiltinKindChoice::Git => BuiltinAction::Git {
+                repo: String::new(),
+                command: actions::GitCommand::Fetch,
+            },
+            BuiltinKindChoice::Container => BuiltinAction::Container {
+                container: String::new(),
+                command: actions::ContainerCommand::Restart,
+            },
+            BuiltinKindChoice::CiStatus => BuiltinAction::CiStatus {
+                repo: String::new(),
+                workflow: String::new(),
+                branch: String::new(),
+            },
+            BuiltinKindChoice::Light => BuiltinAction::Light {
+                light: LightKindChoice::Hue.target(),
+                command: actions::LightCommand::Toggle,
+            },
+            BuiltinKindChoice::Meeting => BuiltinAction::Meeting {
+                app: actions::MeetingApp::Zoom,
+                command: actions::MeetingCommand::ToggleMute,
+            },
+            BuiltinKindChoice::Midi => BuiltinAction::Midi {
+                port: String::new(),
+                message: actions::MidiMessage::NoteOn {
+                    channel: 1,
+                    note: 60,
+                    velocity: 127,
+                },
+            },
+            BuiltinKindChoice::Osc => BuiltinAction::Osc {
+                target: "127.0.0.1:8000".to_string(),
+                address: String::new(),
+                args: String::new(),
+            },
+            BuiltinKindChoice::Serial => BuiltinAction::Serial {
+                port: String::new(),
+                baud: 9600,
+                payload: String::new(),
+                format: actions::SerialFormat::Text,
+            },
+            BuiltinKindChoice::WakeOnLan => BuiltinAction::WakeOnLan {
+                mac: String::new(),
+                broadcast: "255.255.255.255".to_string(),
+            },
+            BuiltinKindChoice::Script => BuiltinAction::Script {
+                language: actions::ScriptLanguage::Rhai,
+                source: String::new(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for BuiltinKindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuiltinKindChoice::Macro => write!(f, "Macro"),
+            BuiltinKindChoice::IssueCommand => write!(f, "Issue Command"),
+            BuiltinKindChoice::KeyboardInput => write!(f, "Keyboard Input"),
+            BuiltinKindChoice::PlaySound => write!(f, "Play Sound"),
+            BuiltinKindChoice::StopSound => write!(f, "Stop Sound"),
+            BuiltinKindChoice::Soundboard => write!(f, "Soundboard"),
+            BuiltinKindChoice::Counter => write!(f, "Counter"),
+            BuiltinKindChoice::Clipboard => write!(f, "Clipboard"),
+            BuiltinKindChoice::Open => write!(f, "Open"),
+            BuiltinKindChoice::Conditional => write!(f, "Conditional"),
+            BuiltinKindChoice::SetVariable => write!(f, "Set Variable"),
+            BuiltinKindChoice::HomeAssistant => write!(f, "Home Assistant"),
+            BuiltinKindChoice::Spotify => write!(f, "Spotify"),
+            BuiltinKindChoice::Screenshot => write!(f, "Screenshot"),
+            BuiltinKindChoice::ScreenRecord => write!(f, "Screen Record"),
+            BuiltinKindChoice::SwitchProfile => write!(f, "Switch Profile"),
+            BuiltinKindChoice::DeviceBrightness => write!(f, "Device Brightness"),
+            BuiltinKindChoice::SystemMonitoring => write!(f, "System Monitoring"),
+            BuiltinKindChoice::Clock => write!(f, "Clock"),
+            BuiltinKindChoice::Weather => write!(f, "Weather"),
+            BuiltinKindChoice::Pomodoro => write!(f, "Pomodoro"),
+            BuiltinKindChoice::Notify => write!(f, "Notify"),
+            BuiltinKindChoice::DoNotDisturb => write!(f, "Do Not Disturb"),
+            BuiltinKindChoice::Radio => write!(f, "Wi-Fi / Bluetooth"),
+            BuiltinKindChoice::Power => write!(f, "Power"),
+            BuiltinKindChoice::VsCode => write!(f, "VS Code"),
+            BuiltinKindChoice::Git => write!(f, "Git Status"),
+            BuiltinKindChoice::Container => write!(f, "Docker / Podman"),
+            BuiltinKindChoice::CiStatus => write!(f, "GitHub Actions"),
+            BuiltinKindChoice::Light => write!(f, "Hue / WLED / Key Light"),
+            BuiltinKindChoice::Meeting => write!(f, "Zoom / Teams Meeting"),
+            BuiltinKindChoice::Midi => write!(f, "MIDI"),
+            BuiltinKindChoice::Osc => write!(f, "OSC"),
+            BuiltinKindChoice::Serial => write!(f, "Serial Port"),
+            BuiltinKindChoice::WakeOnLan => write!(f, "Wake-on-LAN"),
+            BuiltinKindChoice::Script => write!(f, "Script"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AudioDeviceChoice {
+    Default,
+    Named(String),
+}
+
+impl fmt::Display for AudioDeviceChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioDeviceChoice::Default => write!(f, "System default"),
+            AudioDeviceChoice::Named(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SwitchProfileChoice {
+    Next,
+    Prev,
+    // Specific profile chosen from list.
+    To(ProfileId),
+}
+
+impl fmt::Display for SwitchProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwitchProfileChoice::Next => write!(f, "Next profile"),
+            SwitchProfileChoice::Prev => write!(f, "Previous profile"),
+            SwitchProfileChoice::To(id) => write!(f, "Profile {}", id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BrightnessModeChoice {
+    Set,
+    Increase,
+    Decrease,
+}
+
+impl fmt::Display for BrightnessModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrightnessModeChoice::Set => write!(f, "Set"),
+            BrightnessModeChoice::Increase => write!(f, "Increase"),
+            BrightnessModeChoice::Decrease => write!(f, "Decrease"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SoundboardModeChoice {
+    PlayOverlap,
+    Restart,
+    StopOthers,
+}
+
+impl fmt::Display for SoundboardModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoundboardModeChoice::PlayOverlap => write!(f, "Play (overlap)"),
+            SoundboardModeChoice::Restart => write!(f, "Restart"),
+            SoundboardModeChoice::StopOthers => write!(f, "Stop other sounds"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ClipboardModeChoice {
+    CopyText,
+    PasteText,
+    CyclePrevious,
+}
+
+impl fmt::Display for ClipboardModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardModeChoice::CopyText => write!(f, "Copy text"),
+            ClipboardModeChoice::PasteText => write!(f, "Paste text"),
+            ClipboardModeChoice::CyclePrevious => write!(f, "Previous clipboard entry"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MonitorKindChoice {
+    Cpu,
+    Memory,
+    LoadAverage,
+    NetworkRx,
+    NetworkTx,
+    DiskUsage,
+    Temperature,
+    GpuUsage,
+    GpuMemory,
+}
+
+impl MonitorKindChoice {
+    const ALL: [MonitorKindChoice; 9] = [
+        MonitorKindChoice::Cpu,
+        MonitorKindChoice::Memory,
+        MonitorKindChoice::LoadAverage,
+        MonitorKindChoice::NetworkRx,
+        MonitorKindChoice::NetworkTx,
+        MonitorKindChoice::DiskUsage,
+        MonitorKindChoice::Temperature,
+        MonitorKindChoice::GpuUsage,
+        MonitorKindChoice::GpuMemory,
+    ];
+
+    fn of(kind: &actions::MonitorKind) -> Self {
+        match kind {
+            actions::MonitorKind::Cpu => MonitorKindChoice::Cpu,
+            actions::MonitorKind::Memory => MonitorKindChoice::Memory,
+            actions::MonitorKind::LoadAverage => MonitorKindChoice::LoadAverage,
+            actions::MonitorKind::NetworkRx { .. } => MonitorKindChoice::NetworkRx,
+            actions::MonitorKind::NetworkTx { .. } => MonitorKindChoice::NetworkTx,
+            actions::MonitorKind::DiskUsage { .. } => MonitorKindChoice::DiskUsage,
+            actions::MonitorKind::Temperature { .. } => MonitorKindChoice::Temperature,
+            actions::MonitorKind::GpuUsage { .. } => MonitorKindChoice::GpuUsage,
+            actions::MonitorKind::GpuMemory { .. } => MonitorKindChoice::GpuMemory,
+        }
+    }
+
+    fn kind(self) -> actions::MonitorKind {
+        match self {
+            MonitorKindChoice::Cpu => actions::MonitorKind::Cpu,
+            MonitorKindChoice::Memory => actions::MonitorKind::Memory,
+            MonitorKindChoice::LoadAverage => actions::MonitorKind::LoadAverage,
+            MonitorKindChoice::NetworkRx => actions::MonitorKind::NetworkRx { interface: None },
+            MonitorKindChoice::NetworkTx => actions::MonitorKind::NetworkTx { interface: None },
+            MonitorKindChoice::DiskUsage => actions::MonitorKind::DiskUsage { mount: None },
+            MonitorKindChoice::Temperature => actions::MonitorKind::Temperature { sensor: None },
+            MonitorKindChoice::GpuUsage => actions::MonitorKind::GpuUsage { gpu: None },
+            MonitorKindChoice::GpuMemory => actions::MonitorKind::GpuMemory { gpu: None },
+        }
+    }
+}
+
+impl fmt::Display for MonitorKindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorKindChoice::Cpu => write!(f, "CPU"),
+            MonitorKindChoice::Memory => write!(f, "Memory"),
+            MonitorKindChoice::LoadAverage => write!(f, "Load average"),
+            MonitorKindChoice::NetworkRx => write!(f, "Network received"),
+            MonitorKindChoice::NetworkTx => write!(f, "Network sent"),
+            MonitorKindChoice::DiskUsage => write!(f, "Disk usage"),
+            MonitorKindChoice::Temperature => write!(f, "Temperature"),
+            MonitorKindChoice::GpuUsage => write!(f, "GPU usage"),
+            MonitorKindChoice::GpuMemory => write!(f, "GPU memory"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SpotifyCommandChoice {
+    PlayPause,
+    Next,
+    Previous,
+    Like,
+}
+
+impl fmt::Display for SpotifyCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpotifyCommandChoice::PlayPause => write!(f, "Play / Pause"),
+            SpotifyCommandChoice::Next => write!(f, "Next track"),
+            SpotifyCommandChoice::Previous => write!(f, "Previous track"),
+            SpotifyCommandChoice::Like => write!(f, "Like / Unlike"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ScreenshotRegionChoice {
+    FullScreen,
+    Selection,
+    ActiveWindow,
+}
+
+impl fmt::Display for ScreenshotRegionChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotRegionChoice::FullScreen => write!(f, "Full screen"),
+            ScreenshotRegionChoice::Selection => write!(f, "Selected area"),
+            ScreenshotRegionChoice::ActiveWindow => write!(f, "Active window"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ClockFormatChoice {
+    TwentyFourHour,
+    TwelveHour,
+}
+
+impl ClockFormatChoice {
+    const ALL: [ClockFormatChoice; 2] =
+        [ClockFormatChoice::TwentyFourHour, ClockFormatChoice::TwelveHour];
+
+    fn of(format: actions::ClockFormat) -> Self {
+        match format {
+            actions::ClockFormat::TwentyFourHour => ClockFormatChoice::TwentyFourHour,
+            actions::ClockFormat::TwelveHour => ClockFormatChoice::TwelveHour,
+        }
+    }
+
+    fn format(self) -> actions::ClockFormat {
+        match self {
+            ClockFormatChoice::TwentyFourHour => actions::ClockFormat::TwentyFourHour,
+            ClockFormatChoice::TwelveHour => actions::ClockFormat::TwelveHour,
+        }
+    }
+}
+
+impl fmt::Display for ClockFormatChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockFormatChoice::TwentyFourHour => write!(f, "24-hour"),
+            ClockFormatChoice::TwelveHour => write!(f, "12-hour"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum WeatherUnitsChoice {
+    Metric,
+    Imperial,
+}
+
+impl WeatherUnitsChoice {
+    const ALL: [WeatherUnitsChoice; 2] = [WeatherUnitsChoice::Metric, WeatherUnitsChoice::Imperial];
+
+    fn of(units: actions::WeatherUnits) -> Self {
+        match units {
+            actions::WeatherUnits::Metric => WeatherUnitsChoice::Metric,
+            actions::WeatherUnits::Imperial => WeatherUnitsChoice::Imperial,
+        }
+    }
+
+    fn units(self) -> actions::WeatherUnits {
+        match self {
+            WeatherUnitsChoice::Metric => actions::WeatherUnits::Metric,
+            WeatherUnitsChoice::Imperial => actions::WeatherUnits::Imperial,
+        }
+    }
+}
+
+impl fmt::Display for WeatherUnitsChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeatherUnitsChoice::Metric => write!(f, "Celsius"),
+            WeatherUnitsChoice::Imperial => write!(f, "Fahrenheit"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DoNotDisturbModeChoice {
+    Toggle,
+    On,
+    Off,
+}
+
+impl DoNotDisturbModeChoice {
+    const ALL: [DoNotDisturbModeChoice; 3] = [
+        DoNotDisturbModeChoice::Toggle,
+        DoNotDisturbModeChoice::On,
+        DoNotDisturbModeChoice::Off,
+    ];
+
+    fn of(mode: actions::DoNotDisturbMode) -> Self {
+        match mode {
+            actions::DoNotDisturbMode::Toggle => DoNotDisturbModeChoice::Toggle,
+            actions::DoNotDisturbMode::On => DoNotDisturbModeChoice::On,
+            actions::DoNotDisturbMode::Off => DoNotDisturbModeChoice::Off,
+        }
+    }
+
+    fn mode(self) -> actions::DoNotDisturbMode {
+        match self {
+            DoNotDisturbModeChoice::Toggle => actions::DoNotDisturbMode::Toggle,
+            DoNotDisturbModeChoice::On => actions::DoNotDisturbMode::On,
+            DoNotDisturbModeChoice::Off => actions::DoNotDisturbMode::Off,
+        }
+    }
+}
+
+impl fmt::Display for DoNotDisturbModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DoNotDisturbModeChoice::Toggle => write!(f, "Toggle"),
+            DoNotDisturbModeChoice::On => write!(f, "Turn on"),
+            DoNotDisturbModeChoice::Off => write!(f, "Turn off"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RadioChoice {
+    Wifi,
+    Bluetooth,
+}
+
+impl RadioChoice {
+    const ALL: [RadioChoice; 2] = [RadioChoice::Wifi, RadioChoice::Bluetooth];
+
+    fn of(radio: actions::RadioKind) -> Self {
+        match radio {
+            actions::RadioKind::Wifi => RadioChoice::Wifi,
+            actions::RadioKind::Bluetooth => RadioChoice::Bluetooth,
+        }
+    }
+
+    fn radio(self) -> actions::RadioKind {
+        match self {
+            RadioChoice::Wifi => actions::RadioKind::Wifi,
+            RadioChoice::Bluetooth => actions::RadioKind::Bluetooth,
+        }
+    }
+}
+
+impl fmt::Display for RadioChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RadioChoice::Wifi => write!(f, "Wi-Fi"),
+            RadioChoice::Bluetooth => write!(f, "Bluetooth"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PowerCommandChoice {
+    Lock,
+    Suspend,
+    Shutdown,
+}
+
+impl PowerCommandChoice {
+    const ALL: [PowerCommandChoice; 3] = [
+        PowerCommandChoice::Lock,
+        PowerCommandChoice::Suspend,
+        PowerCommandChoice::Shutdown,
+    ];
+
+    fn of(command: actions::PowerCommand) -> Self {
+        match command {
+            actions::PowerCommand::Lock => PowerCommandChoice::Lock,
+            actions::PowerCommand::Suspend => PowerCommandChoice::Suspend,
+            actions::PowerCommand::Shutdown => PowerCommandChoice::Shutdown,
+        }
+    }
+
+    fn command(self) -> actions::PowerCommand {
+        match self {
+            PowerCommandChoice::Lock => actions::PowerCommand::Lock,
+            PowerCommandChoice::Suspend => actions::PowerCommand::Suspend,
+            PowerCommandChoice::Shutdown => actions::PowerCommand::Shutdown,
+        }
+    }
+}
+
+impl fmt::Display for PowerCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerCommandChoice::Lock => write!(f, "Lock"),
+            PowerCommandChoice::Suspend => write!(f, "Suspend"),
+            PowerCommandChoice::Shutdown => write!(f, "Shut Down"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ContainerCommandChoice {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl ContainerCommandChoice {
+    const ALL: [ContainerCommandChoice; 3] = [
+        ContainerCommandChoice::Start,
+        ContainerCommandChoice::Stop,
+        ContainerCommandChoice::Restart,
+    ];
+
+    fn of(command: actions::ContainerCommand) -> Self {
+        match command {
+            actions::ContainerCommand::Start => ContainerCommandChoice::Start,
+            actions::ContainerCommand::Stop => ContainerCommandChoice::Stop,
+            actions::ContainerCommand::Restart => ContainerCommandChoice::Restart,
+        }
+    }
+
+    fn command(self) -> actions::ContainerCommand {
+        match self {
+            ContainerCommandChoice::Start => actions::ContainerCommand::Start,
+            ContainerCommandChoice::Stop => actions::ContainerCommand::Stop,
+            ContainerCommandChoice::Restart => actions::ContainerCommand::Restart,
+        }
+    }
+}
+
+impl fmt::Display for ContainerCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerCommandChoice::Start => write!(f, "Start"),
+            ContainerCommandChoice::Stop => write!(f, "Stop"),
+            ContainerCommandChoice::Restart => write!(f, "Restart"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MidiMessageChoice {
+    NoteOn,
+    NoteOff,
+    ControlChange,
+}
+
+impl MidiMessageChoice {
+    const ALL: [MidiMessageChoice; 3] = [
+        MidiMessageChoice::NoteOn,
+        MidiMessageChoice::NoteOff,
+        MidiMessageChoice::ControlChange,
+    ];
+
+    fn of(message: actions::MidiMessage) -> Self {
+        match message {
+            actions::MidiMessage::NoteOn { .. } => MidiMessageChoice::NoteOn,
+            actions::MidiMessage::NoteOff { .. } => MidiMessageChoice::NoteOff,
+            actions::MidiMessage::ControlChange { .. } => MidiMessageChoice::ControlChange,
+        }
+    }
+
+    /// Channel, note or controller, and velocity or value of `message` (0 for a note off).
+    fn parts(message: actions::MidiMessage) -> (u8, u8, u8) {
+        match message {
+            actions::MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => (channel, note, velocity),
+            actions::MidiMessage::NoteOff { channel, note } => (channel, note, 0),
+            actions::MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => (channel, controller, value),
+        }
+    }
+
+    fn message(self, channel: u8, number: u8, value: u8) -> actions::MidiMessage {
+        match self {
+            MidiMessageChoice::NoteOn => actions::MidiMessage::NoteOn {
+                channel,
+                note: number,
+                velocity: value,
+            },
+            MidiMessageChoice::NoteOff => actions::MidiMessage::NoteOff {
+                channel,
+                note: number,
+            },
+            MidiMessageChoice::ControlChange => actions::MidiMessage::ControlChange {
+                channel,
+                controller: number,
+                value,
+            },
+        }
+    }
+
+    /// `message` with `edit` applied; numbers out of range are ignored.
+    fn edit(message: actions::MidiMessage, edit: MidiMessageEdit) -> actions::MidiMessage {
+        let (mut channel, mut number, mut value) = Self::parts(message);
+        let mut kind = Self::of(message);
+        let parse = |v: String, range: std::ops::RangeInclusive<u8>| {
+            v.trim().parse().ok().filter(|n| range.contains(n))
+        };
+        match edit {
+            MidiMessageEdit::Kind(k) => {
+                // A note on without velocity would be a note off.
+                if k == MidiMessageChoice::NoteOn && value == 0 {
+                    value = 127;
+                }
+                kind = k;
+            }
+            MidiMessageEdit::Channel(v) => channel = parse(v, 1..=16).unwrap_or(channel),
+            MidiMessageEdit::Number(v) => number = parse(v, 0..=127).unwrap_or(number),
+            MidiMessageEdit::Value(v) => value = parse(v, 0..=127).unwrap_or(value),
+        }
+        kind.message(channel, number, value)
+    }
+}
+
+impl fmt::Display for MidiMessageChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiMessageChoice::NoteOn => write!(f, "Note on"),
+            MidiMessageChoice::NoteOff => write!(f, "Note off"),
+            MidiMessageChoice::ControlChange => write!(f, "Control change"),
+        }
+    }
+}
+
+/// Baud rates offered for `serial` actions.
+const SERIAL_BAUD_RATES: [u32; 12] = [
+    1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400, 250000, 500000, 1000000,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SerialFormatChoice {
+    Text,
+    Hex,
+}
+
+impl SerialFormatChoice {
+    const ALL: [SerialFormatChoice; 2] = [SerialFormatChoice::Text, SerialFormatChoice::Hex];
+
+    fn of(format: actions::SerialFormat) -> Self {
+        match format {
+            actions::SerialFormat::Text => SerialFormatChoice::Text,
+            actions::SerialFormat::Hex => SerialFormatChoice::Hex,
+        }
+    }
+
+    fn format(self) -> actions::SerialFormat {
+        match self {
+            SerialFormatChoice::Text => actions::SerialFormat::Text,
+            SerialFormatChoice::Hex => actions::SerialFormat::Hex,
+        }
+    }
+}
+
+impl fmt::Display for SerialFormatChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerialFormatChoice::Text => write!(f, "Text"),
+            SerialFormatChoice::Hex => write!(f, "Hex bytes"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MeetingAppChoice {
+    Zoom,
+    Teams,
+}
+
+impl MeetingAppChoice {
+    const ALL: [MeetingAppChoice; 2] = [MeetingAppChoice::Zoom, MeetingAppChoice::Teams];
+
+    fn of(app: actions::MeetingApp) -> Self {
+        match app {
+            actions::MeetingApp::Zoom => MeetingAppChoice::Zoom,
+            actions::MeetingApp::Teams => MeetingAppChoice::Teams,
+        }
+    }
+
+    fn app(self) -> actions::MeetingApp {
+        match self {
+            MeetingAppChoice::Zoom => actions::MeetingApp::Zoom,
+            MeetingAppChoice::Teams => actions::MeetingApp::Teams,
+        }
+    }
+}
+
+impl fmt::Display for MeetingAppChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeetingAppChoice::Zoom => write!(f, "Zoom"),
+            MeetingAppChoice::Teams => write!(f, "Microsoft Teams"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MeetingCommandChoice {
+    ToggleMute,
+    ToggleVideo,
+    Leave,
+}
+
+impl MeetingCommandChoice {
+    const ALL: [MeetingCommandChoice; 3] = [
+        MeetingCommandChoice::ToggleMute,
+        MeetingCommandChoice::ToggleVideo,
+        MeetingCommandChoice::Leave,
+    ];
+
+    fn of(command: actions::MeetingCommand) -> Self {
+        match command {
+            actions::MeetingCommand::ToggleMute => MeetingCommandChoice::ToggleMute,
+            actions::MeetingCommand::ToggleVideo => MeetingCommandChoice::ToggleVideo,
+            actions::MeetingCommand::Leave => MeetingCommandChoice::Leave,
+        }
+    }
+
+    fn command(self) -> actions::MeetingCommand {
+        match self {
+            MeetingCommandChoice::ToggleMute => actions::MeetingCommand::ToggleMute,
+            MeetingCommandChoice::ToggleVideo => actions::MeetingCommand::ToggleVideo,
+            MeetingCommandChoice::Leave => actions::MeetingCommand::Leave,
+        }
+    }
+}
+
+impl fmt::Display for MeetingCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeetingCommandChoice::ToggleMute => write!(f, "Mute / unmute"),
+            MeetingCommandChoice::ToggleVideo => write!(f, "Camera on / off"),
+            MeetingCommandChoice::Leave => write!(f, "Leave the meeting"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum LightKindChoice {
+    Hue,
+    Wled,
+    KeyLight,
+}
+
+impl LightKindChoice {
+    const ALL: [LightKindChoice; 3] = [
+        LightKindChoice::Hue,
+        LightKindChoice::Wled,
+        LightKindChoice::KeyLight,
+    ];
+
+    fn of(light: &actions::LightTarget) -> Self {
+        match light {
+            actions::LightTarget::Hue { .. } => LightKindChoice::Hue,
+            actions::LightTarget::Wled { .. } => LightKindChoice::Wled,
+            actions::LightTarget::KeyLight { .. } => LightKindChoice::KeyLight,
+        }
+    }
+
+    /// What discovery finds lights of this kind as; Hue lights are listed by their bridge.
+    fn device_kind(self) -> Option<DeviceKind> {
+        match self {
+            LightKindChoice::Hue => None,
+            LightKindChoice::Wled => Some(DeviceKind::Wled),
+            LightKindChoice::KeyLight => Some(DeviceKind::KeyLight),
+        }
+    }
+
+    fn target(self) -> actions::LightTarget {
+        match self {
+            LightKindChoice::Hue => actions::LightTarget::Hue {
+                light: String::new(),
+            },
+            LightKindChoice::Wled => actions::LightTarget::Wled {
+                host: String::new(),
+            },
+            LightKindChoice::KeyLight => actions::LightTarget::KeyLight {
+                host: String::new(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for LightKindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LightKindChoice::Hue => write!(f, "Philips Hue"),
+            LightKindChoice::Wled => write!(f, "WLED"),
+            LightKindChoice::KeyLight => write!(f, "Elgato Key Light"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum LightCommandChoice {
+    Toggle,
+    On,
+    Off,
+    SetBrightness,
+    StepBrightness,
+    Color,
+    ColorTemperature,
+}
+
+impl LightCommandChoice {
+    const ALL: [LightCommandChoice; 7] = [
+        LightCommandChoice::Toggle,
+        LightCommandChoice::On,
+        LightCommandChoice::Off,
+        LightCommandChoice::SetBrightness,
+        LightCommandChoice::StepBrightness,
+        LightCommandChoice::Color,
+        LightCommandChoice::ColorTemperature,
+    ];
+
+    fn of(command: actions::LightCommand) -> Self {
+        match command {
+            actions::LightCommand::Toggle => LightCommandChoice::Toggle,
+            actions::LightCommand::On => LightCommandChoice::On,
+            actions::LightCommand::Off => LightCommandChoice::Off,
+            actions::LightCommand::SetBrightness(_) => LightCommandChoice::SetBrightness,
+            actions::LightCommand::StepBrightness(_) => LightCommandChoice::StepBrightness,
+            actions::LightCommand::Color(_) => LightCommandChoice::Color,
+            actions::LightCommand::ColorTemperature(_) => LightCommandChoice::ColorTemperature,
+        }
+    }
+
+    fn command(self) -> actions::LightCommand {
+        match self {
+            LightCommandChoice::Toggle => actions::LightCommand::Toggle,
+            LightCommandChoice::On => actions::LightCommand::On,
+            LightCommandChoice::Off => actions::LightCommand::Off,
+            LightCommandChoice::SetBrightness => actions::LightCommand::SetBrightness(50),
+            LightCommandChoice::StepBrightness => actions::LightCommand::StepBrightness(5),
+            LightCommandChoice::Color => actions::LightCommand::Color([255, 180, 80]),
+            LightCommandChoice::ColorTemperature => actions::LightCommand::ColorTemperature(4500),
+        }
+    }
+}
+
+impl fmt::Display for LightCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LightCommandChoice::Toggle => write!(f, "Toggle"),
+            LightCommandChoice::On => write!(f, "Turn on"),
+            LightCommandChoice::Off => write!(f, "Turn off"),
+            LightCommandChoice::SetBrightness => write!(f, "Set brightness"),
+            LightCommandChoice::StepBrightness => write!(f, "Step brightness (dial)"),
+            LightCommandChoice::Color => write!(f, "Color"),
+            LightCommandChoice::ColorTemperature => write!(f, "Color temperature"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GitCommandChoice {
+    Fetch,
+    Pull,
+    Push,
+}
+
+impl GitCommandChoice {
+    const ALL: [GitCommandChoice; 3] = [
+        GitCommandChoice::Fetch,
+        GitCommandChoice::Pull,
+        GitCommandChoice::Push,
+    ];
+
+    fn of(command: actions::GitCommand) -> Self {
+        match command {
+            actions::GitCommand::Fetch => GitCommandChoice::Fetch,
+            actions::GitCommand::Pull => GitCommandChoice::Pull,
+            actions::GitCommand::Push => GitCommandChoice::Push,
+        }
+    }
+
+    fn command(self) -> actions::GitCommand {
+        match self {
+            GitCommandChoice::Fetch => actions::GitCommand::Fetch,
+            GitCommandChoice::Pull => actions::GitCommand::Pull,
+            GitCommandChoice::Push => actions::GitCommand::Push,
+        }
+    }
+}
+
+impl fmt::Display for GitCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitCommandChoice::Fetch => write!(f, "Fetch"),
+            GitCommandChoice::Pull => write!(f, "Pull"),
+            GitCommandChoice::Push => write!(f, "Push"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum VsCodeCommandChoice {
+    Open,
+    RunTask,
+}
+
+impl fmt::Display for VsCodeCommandChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VsCodeCommandChoice::Open => write!(f, "Open folder"),
+            VsCodeCommandChoice::RunTask => write!(f, "Run task"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TextSizeChoice {
+    Auto,
+    Small,
+    Medium,
+    Large,
+}
+
+impl TextSizeChoice {
+    const ALL: [TextSizeChoice; 4] = [
+        TextSizeChoice::Auto,
+        TextSizeChoice::Small,
+        TextSizeChoice::Medium,
+        TextSizeChoice::Large,
+    ];
+
+    fn of(size: Option<TextSize>) -> Self {
+        match size {
+            None => TextSizeChoice::Auto,
+            Some(TextSize::Small) => TextSizeChoice::Small,
+            Some(TextSize::Medium) => TextSizeChoice::Medium,
+            Some(TextSize::Large) => TextSizeChoice::Large,
+        }
+    }
+
+    fn size(self) -> Option<TextSize> {
+        match self {
+            TextSizeChoice::Auto => None,
+            TextSizeChoice::Small => Some(TextSize::Small),
+            TextSizeChoice::Medium => Some(TextSize::Medium),
+            TextSizeChoice::Large => Some(TextSize::Large),
+        }
+    }
+}
+
+impl fmt::Display for TextSizeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextSizeChoice::Auto => write!(f, "Auto"),
+            TextSizeChoice::Small => write!(f, "Small"),
+            TextSizeChoice::Medium => write!(f, "Medium"),
+            TextSizeChoice::Large => write!(f, "Large"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FlashChoice {
+    Off,
+    Invert,
+    Border,
+}
+
+impl FlashChoice {
+    const ALL: [FlashChoice; 3] = [FlashChoice::Off, FlashChoice::Invert, FlashChoice::Border];
+
+    fn of(effect: Option<FlashEffect>) -> Self {
+        match effect {
+            None => FlashChoice::Off,
+            Some(FlashEffect::Invert) => FlashChoice::Invert,
+            Some(FlashEffect::Border) => FlashChoice::Border,
+        }
+    }
+
+    fn effect(self) -> Option<FlashEffect> {
+        match self {
+            FlashChoice::Off => None,
+            FlashChoice::Invert => Some(FlashEffect::Invert),
+            FlashChoice::Border => Some(FlashEffect::Border),
+        }
+    }
+}
+
+impl fmt::Display for FlashChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlashChoice::Off => write!(f, "Off"),
+            FlashChoice::Invert => write!(f, "Invert colors"),
+            FlashChoice::Border => write!(f, "Border pulse"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ConditionKindChoice {
+    ProcessRunning,
+    FileExists,
+    TimeRange,
+    VariableEquals,
+    ShellExitCode,
+}
+
+impl ConditionKindChoice {
+    fn of(c: &actions::Condition) -> Self {
+        match c {
+            actions::Condition::ProcessRunning { .. } => ConditionKindChoice::ProcessRunning,
+            actions::Condition::FileExists { .. } => ConditionKindChoice::FileExists,
+            actions::Condition::TimeRange { .. } => ConditionKindChoice::TimeRange,
+            actions::Condition::VariableEquals { .. } => ConditionKindChoice::VariableEquals,
+            actions::Condition::ShellExitCode { .. } => ConditionKindChoice::ShellExitCode,
+        }
+    }
+
+    fn default_condition(self) -> actions::Condition {
+        match self {
+            ConditionKindChoice::ProcessRunning => actions::Condition::ProcessRunning {
+                name: String::new(),
+            },
+            ConditionKindChoice::FileExists => actions::Condition::FileExists {
+                path: String::new(),
+            },
+            ConditionKindChoice::TimeRange => actions::Condition::TimeRange {
+                start: "09:00".to_string(),
+                end: "17:00".to_string(),
+            },
+            ConditionKindChoice::VariableEquals => actions::Condition::VariableEquals {
+                name: String::new(),
+                value: String::new(),
+            },
+            ConditionKindChoice::ShellExitCode => actions::Condition::ShellExitCode {
+                command: String::new(),
+                code: 0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ConditionKindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConditionKindChoice::ProcessRunning => write!(f, "Process is running"),
+            ConditionKindChoice::FileExists => write!(f, "File exists"),
+            ConditionKindChoice::TimeRange => write!(f, "Time is between"),
+            ConditionKindChoice::VariableEquals => write!(f, "Variable equals"),
+            ConditionKindChoice::ShellExitCode => write!(f, "Command exits with code"),
+        }
+    }
+}
+
+/// Minute and count fields of the `pomodoro` action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PomodoroField {
+    Work,
+    Break,
+    LongBreak,
+    LongBreakEvery,
+}
+
+/// Editable fields of the `Condition` variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConditionField {
+    Name,
+    Value,
+    Path,
+    Start,
+    End,
+    Command,
+    Code,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Branch {
+    Then,
+    Otherwise,
+}
+
+/// Branch bindings are edited like macro steps (plugin action or command), or left empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BranchKindChoice {
+    Nothing,
+    PluginAction,
+    IssueCommand,
+}
+
+impl fmt::Display for BranchKindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BranchKindChoice::Nothing => write!(f, "Do nothing"),
+            BranchKindChoice::PluginAction => write!(f, "Plugin action"),
+            BranchKindChoice::IssueCommand => write!(f, "Issue command"),
+        }
+    }
+}
+
+fn branch_kind(binding: Option<&ActionBinding>) -> BranchKindChoice {
+    match binding {
+        None => BranchKindChoice::Nothing,
+        Some(ActionBinding::Builtin(BuiltinAction::IssueCommand { .. })) => {
+            BranchKindChoice::IssueCommand
+        }
+        Some(_) => BranchKindChoice::PluginAction,
+    }
+}
+
+/// What a schedule, webhook or typed trigger runs; their views edit these kinds of bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TriggerActionChoice {
+    SwitchProfile,
+    IssueCommand,
+    TypeText,
+    PluginAction,
+}
+
+impl TriggerActionChoice {
+    const ALL: [TriggerActionChoice; 4] = [
+        TriggerActionChoice::SwitchProfile,
+        TriggerActionChoice::IssueCommand,
+        TriggerActionChoice::TypeText,
+        TriggerActionChoice::PluginAction,
+    ];
+
+    fn of(binding: &ActionBinding) -> Self {
+        match binding {
+            ActionBinding::Builtin(BuiltinAction::SwitchProfile { .. }) => {
+                TriggerActionChoice::SwitchProfile
+            }
+            ActionBinding::Builtin(BuiltinAction::KeyboardInput { .. }) => {
+                TriggerActionChoice::TypeText
+            }
+            ActionBinding::Plugin(_) => TriggerActionChoice::PluginAction,
+            ActionBinding::Builtin(_) => TriggerActionChoice::IssueCommand,
+        }
+    }
+
+    /// A fresh binding of this kind; plugin actions start at `plugin` (choice + default
+    /// settings), falling back to an empty command when no plugin actions are installed.
+    fn default_action(self, plugin: Option<(ActionChoice, serde_json::Value)>) -> ActionBinding {
+        let command = ActionBinding::Builtin(BuiltinAction::IssueCommand {
+            command: String::new(),
+            cwd: None,
+            timeout_ms: None,
+        });
+        match self {
+            TriggerActionChoice::SwitchProfile => {
+                ActionBinding::Builtin(BuiltinAction::SwitchProfile {
+                    mode: actions::SwitchProfileMode::Next,
+                })
+            }
+            TriggerActionChoice::IssueCommand => command,
+            TriggerActionChoice::TypeText => ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+                text: Some(String::new()),
+                keys: vec![],
+                chord: None,
+            }),
+            TriggerActionChoice::PluginAction => match plugin {
+                Some((choice, settings)) => ActionBinding::Plugin(PluginActionBinding {
+                    plugin_id: choice.plugin_id,
+                    action_id: choice.action_id,
+                    settings,
+                }),
+                None => command,
+            },
+        }
+    }
+}
+
+impl fmt::Display for TriggerActionChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerActionChoice::SwitchProfile => write!(f, "Switch profile"),
+            TriggerActionChoice::IssueCommand => write!(f, "Issue command"),
+            TriggerActionChoice::TypeText => write!(f, "Type text"),
+            TriggerActionChoice::PluginAction => write!(f, "Plugin action"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TriggerActionEdit {
+    Kind(TriggerActionChoice),
+    Profile(ProfileChoice),
+    Command(String),
+    Text(String),
+    Plugin(ActionChoice),
+}
+
+/// Apply an edit from the trigger action editor (see `App::view_trigger_action`).
+fn edit_trigger_action(
+    action: &mut ActionBinding,
+    edit: TriggerActionEdit,
+    plugins: &[InstalledPlugin],
+    choices: &[ActionChoice],
+) {
+    *action = match edit {
+        TriggerActionEdit::Kind(kind) if TriggerActionChoice::of(action) == kind => return,
+        TriggerActionEdit::Kind(kind) => kind.default_action(choices.first().map(|c| {
+            let settings = default_settings_for_action(plugins, c);
+            (c.clone(), settings)
+        })),
+        TriggerActionEdit::Profile(choice) => ActionBinding::Builtin(BuiltinAction::SwitchProfile {
+            mode: actions::SwitchProfileMode::To {
+                profile_id: choice.id.0,
+            },
+        }),
+        TriggerActionEdit::Command(command) => {
+            ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                command,
+                cwd: None,
+                timeout_ms: None,
+            })
+        }
+        TriggerActionEdit::Text(text) => ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+            text: Some(text),
+            keys: vec![],
+            chord: None,
+        }),
+        TriggerActionEdit::Plugin(choice) => ActionBinding::Plugin(PluginActionBinding {
+            settings: default_settings_for_action(plugins, &choice),
+            plugin_id: choice.plugin_id,
+            action_id: choice.action_id,
+        }),
+    };
+}
+
+#[derive(Debug, Clone)]
+enum ScheduleEdit {
+    Name(String),
+    Cron(String),
+    Enabled(bool),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum MidiSettingsEdit {
+    Enabled(bool),
+    InputPort(String),
+}
+
+#[derive(Debug, Clone)]
+enum MidiTriggerEdit {
+    Input(MidiInput),
+    /// A channel number, or anything else for any channel.
+    Channel(String),
+    Number(String),
+    Enabled(bool),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum OscSettingsEdit {
+    Enabled(bool),
+    Port(String),
+    /// Applied on submit so the listener is not rebound on every keystroke.
+    ApplyPort,
+    ListenOnNetwork(bool),
+}
+
+#[derive(Debug, Clone)]
+enum OscTriggerEdit {
+    Address(String),
+    Variable(String),
+    Enabled(bool),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum MidiMessageEdit {
+    Kind(MidiMessageChoice),
+    Channel(String),
+    Number(String),
+    Value(String),
+}
+
+#[derive(Debug, Clone)]
+enum ProcessWatcherEdit {
+    Process(String),
+    On(ProcessTransition),
+    Enabled(bool),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum WebhookEdit {
+    Path(String),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum TypedTriggerEdit {
+    Abbreviation(String),
+    Enabled(bool),
+    Erase(bool),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum FileWatcherEdit {
+    Path(String),
+    Recursive(bool),
+    /// Whether the change fires the action.
+    Change(FileChange, bool),
+    Enabled(bool),
+    Action(TriggerActionEdit),
+}
+
+#[derive(Debug, Clone)]
+enum WebhookServerEdit {
+    Enabled(bool),
+    Port(String),
+    ApplyPort,
+    ListenOnNetwork(bool),
+    Api(bool),
+    Metrics(bool),
+    RegenerateToken,
+}
+
+#[derive(Debug, Clone)]
+enum CompanionEdit {
+    Enabled(bool),
+    Address(String),
+    ApplyAddress,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum KeyEdit {
+    Copy,
+    Cut,
+    Paste,
+    /// Copy the key into the next empty key.
+    Duplicate,
+}
+
+#[derive(Debug, Clone)]
+enum InterfaceEdit {
+    Scale(i32),
+    /// Apply the slider's scale once it is released.
+    Apply,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+}
+
+#[derive(Debug, Clone)]
+enum TouchGestureEdit {
+    SwipeDistance(i32),
+    SwipeTime(i32),
+    /// Persist the values once a slider is released.
+    Save,
+}
+
+#[derive(Debug, Clone)]
+enum PluginDetailsEdit {
+    Args(String),
+    Cwd(String),
+    MaxConcurrent(String),
+    EnvKey(usize, String),
+    EnvValue(usize, String),
+    AddEnv,
+    RemoveEnv(usize),
+    Save,
+}
+
+/// A plugin's launch configuration as edited in its details panel.
+#[derive(Debug, Clone)]
+struct PluginDetails {
+    plugin_id: String,
+    /// Arguments on one line, shell-quoted.
+    args: String,
+    cwd: String,
+    /// Empty for no limit.
+    max_concurrent: String,
+    env: Vec<(String, String)>,
+}
+
+impl PluginDetails {
+    fn of(p: &InstalledPlugin) -> Self {
+        let launch = &p.launch;
+        Self {
+            plugin_id: p.manifest.id.clone(),
+            args: shlex::try_join(launch.args.iter().map(String::as_str))
+                .unwrap_or_else(|_| launch.args.join(" ")),
+            cwd: launch.cwd.clone().unwrap_or_default(),
+            max_concurrent: launch
+                .max_concurrent
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            env: launch.env.clone().into_iter().collect(),
+        }
+    }
+
+    fn to_launch(&self) -> Result<PluginLaunch, String> {
+        let args = shlex::split(&self.args)
+            .ok_or_else(|| "Plugin arguments have an unclosed quote".to_string())?;
+        let mut env = BTreeMap::new();
+        for (key, value) in &self.env {
+            let key = key.trim();
+            if key.is_empty() && value.is_empty() {
+                continue;
+            }
+            if key.is_empty() || key.contains('=') {
+                return Err(format!("Invalid environment variable name: {key:?}"));
+            }
+            env.insert(key.to_string(), value.clone());
+        }
+        let max_concurrent = match self.max_concurrent.trim() {
+            "" => None,
+            n => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return Err("Concurrent runs must be a whole number of at least 1".to_string()),
+            },
+        };
+        let cwd = self.cwd.trim();
+        Ok(PluginLaunch {
+            args,
+            env,
+            cwd: (!cwd.is_empty()).then(|| cwd.to_string()),
+            max_concurrent,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum NotificationMirrorEdit {
+    Enabled(bool),
+    /// Key number, counted from 1.
+    Key(i32),
+    Seconds(i32),
+    /// Persist the values once a slider is released.
+    Save,
+}
+
+#[derive(Debug, Clone)]
+enum MacroLimitsEdit {
+    MaxSteps(i32),
+    MaxDepth(i32),
+    /// Persist the values once a slider is released.
+    Save,
+}
+
+#[derive(Debug, Clone)]
+enum HomeAssistantEdit {
+    Enabled(bool),
+    Url(String),
+    Token(String),
+    /// Apply the URL and token fields.
+    Apply,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MacroStepKindChoice {
+    PluginAction,
+    IssueCommand,
+    KeyboardInput,
+}
+
+impl fmt::Display for MacroStepKindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroStepKindChoice::PluginAction => write!(f, "Plugin action"),
+            MacroStepKindChoice::IssueCommand => write!(f, "Issue command"),
+            MacroStepKindChoice::KeyboardInput => write!(f, "Keyboard input"),
+        }
+    }
+}
+
+impl App {
+    /// Persist the schedules; the engine picks them up in `sync_engine`.
+    fn save_schedules(&self) -> Command<Message> {
+        Command::perform(
+            save_schedules_async(self.schedules.clone()),
+            Message::SchedulesSaved,
+        )
+    }
+
+    fn save_usage(&self) -> Command<Message> {
+        Command::perform(save_usage_async(self.usage.clone()), Message::UsageSaved)
+    }
+
+    /// Runs of key `idx` of the active profile, and how they compare to its most used key (0 to
+    /// 1), while the heatmap is on.
+    fn key_heat(&self, idx: usize) -> Option<(u64, f32)> {
+        if !self.usage_heatmap {
+            return None;
+        }
+        let p = self.profile.as_ref()?;
+        let bindings = self.usage.get(&p.id.0).map_or(&[][..], Vec::as_slice);
+        let counts = storage::usage::key_counts(bindings);
+        let max = counts.values().copied().max().unwrap_or(0);
+        let count = u8::try_from(idx)
+            .ok()
+            .and_then(|i| counts.get(&i))
+            .copied()
+            .unwrap_or(0);
+        let heat = if max == 0 {
+            0.0
+        } else {
+            count as f32 / max as f32
+        };
+        Some((count, heat))
+    }
+
+    fn save_activity(&self) -> Command<Message> {
+        Command::perform(
+            save_activity_async(self.activity.clone()),
+            Message::ActivitySaved,
+        )
+    }
+
+    /// Persist the webhook routes; the engine picks them up in `sync_engine`.
+    fn save_webhooks(&self) -> Command<Message> {
+        Command::perform(
+            save_webhooks_async(self.webhooks.clone()),
+            Message::WebhooksSaved,
+        )
+    }
+
+    fn save_process_watchers(&self) -> Command<Message> {
+        Command::perform(
+            save_process_watchers_async(self.process_watchers.clone()),
+            Message::ProcessWatchersSaved,
+        )
+    }
+
+    fn save_midi_triggers(&self) -> Command<Message> {
+        Command::perform(
+            save_midi_triggers_async(self.midi_triggers.clone()),
+            Message::MidiTriggersSaved,
+        )
+    }
+
+    fn save_osc_triggers(&self) -> Command<Message> {
+        Command::perform(
+            save_osc_triggers_async(self.osc_triggers.clone()),
+            Message::OscTriggersSaved,
+        )
+    }
+
+    /// Persist the typed triggers; the engine picks them up in `sync_engine`.
+    fn save_typed_triggers(&self) -> Command<Message> {
+        Command::perform(
+            save_typed_triggers_async(self.typed_triggers.clone()),
+            Message::TypedTriggersSaved,
+        )
+    }
+
+    fn gesture_thresholds(&self) -> GestureThresholds {
+        GestureThresholds {
+            swipe_distance: self.touch_gestures.swipe_distance,
+            swipe_max_duration: Duration::from_millis(self.touch_gestures.swipe_max_ms),
+        }
+    }
+
+    /// Last brightness saved for `device`, or the default for devices not seen before.
+    fn saved_brightness(&self, device: app_core::ids::DeviceId) -> u8 {
+        self.device_brightness.get(&device.0).copied().unwrap_or(DEFAULT_BRIGHTNESS)
+    }
+
+    /// Show `percent` on the connected device (and tell its engine) without persisting it.
+    fn apply_brightness(&mut self, percent: u8) -> Command<Message> {
+        let Some(c) = &mut self.connected else {
+            return Command::none();
+        };
+        c.brightness = percent;
+        c.engine.set_brightness(percent);
+        Command::perform(
+            set_brightness_async(c.controller.clone(), percent),
+            Message::BrightnessApplied,
+        )
+    }
+
+    /// Remember `percent` as the connected device's brightness.
+    fn save_device_brightness(&mut self, percent: u8) -> Command<Message> {
+        let Some(c) = &self.connected else {
+            return Command::none();
+        };
+        self.device_brightness.insert(c.id.0, percent);
+        Command::perform(
+            save_device_brightness_async(c.id.0, percent),
+            Message::SettingsSaved,
+        )
+    }
+
+    fn edit_selected_key(&mut self, edit: KeyEdit) -> Command<Message> {
+        use storage::profiles::KeyConfig;
+
+        let Some(SelectedControl::Key(idx)) = self.selected_control else {
+            return Command::none();
+        };
+        let Some(p) = &mut self.profile else {
+            return Command::none();
+        };
+        let Some(key) = p.keys.get_mut(idx) else {
+            return Command::none();
+        };
+        let target = match edit {
+            KeyEdit::Copy => {
+                // A copy must not take the original's global shortcut along.
+                self.copied_key = Some(KeyConfig {
+                    hotkey: None,
+                    base: None,
+                    ..key.clone()
+                });
+                return Command::none();
+            }
+            KeyEdit::Cut => {
+                let base = key.base.take();
+                self.copied_key = Some(std::mem::take(key));
+                key.base = base;
+                idx
+            }
+            KeyEdit::Paste => {
+                let Some(copied) = self.copied_key.as_mut() else {
+                    return Command::none();
+                };
+                *key = KeyConfig {
+                    base: key.base.take(),
+                    ..copied.clone()
+                };
+                // A cut key's shortcut moves once; further pastes are plain copies.
+                copied.hotkey = None;
+                idx
+            }
+            KeyEdit::Duplicate => {
+                let copy = KeyConfig {
+                    hotkey: None,
+                    base: None,
+                    ..key.clone()
+                };
+                let len = p.keys.len();
+                let free = (1..len)
+                    .map(|offset| (idx + offset) % len)
+                    .find(|&i| p.keys[i].is_empty());
+                let Some(free) = free else {
+                    self.error = Some("No empty key to duplicate into.".to_string());
+                    return Command::none();
+                };
+                let slot = &mut p.keys[free];
+                *slot = KeyConfig {
+                    base: slot.base.take(),
+                    ..copy
+                };
+                free
+            }
+        };
+        Command::batch([
+            self.handle_message(Message::SelectControl(SelectedControl::Key(target))),
+            self.apply_displays_if_connected(),
+        ])
+    }
+
+    /// Swap two keys of the profile; each position keeps what it inherits.
+    fn swap_keys(&mut self, from: usize, to: usize) -> Command<Message> {
+        let Some(p) = &mut self.profile else {
+            return Command::none();
+        };
+        if from >= p.keys.len() || to >= p.keys.len() {
+            return Command::none();
+        }
+        p.keys.swap(from, to);
+        let (a, b) = (from.min(to), from.max(to));
+        let (head, tail) = p.keys.split_at_mut(b);
+        std::mem::swap(&mut head[a].base, &mut tail[0].base);
+        Command::batch([
+            self.handle_message(Message::SelectControl(SelectedControl::Key(to))),
+            self.apply_displays_if_connected(),
+        ])
+    }
+
+    /// Redraw surfaces whose flash has run its course.
+    fn end_flashes(&mut self) -> Command<Message> {
+        let Some(c) = &mut self.connected else {
+            return Command::none();
+        };
+        let before = c.flashing.len();
+        c.flashing.retain(|_, (_, started)| started.elapsed() < FLASH_DURATION);
+        if c.flashing.len() == before {
+            return Command::none();
+        }
+        self.apply_displays_if_connected()
+    }
+
+    /// Redraw the `power` keys being held, so their rings fill up.
+    fn animate_power_holds(&self) -> Command<Message> {
+        if self.power_holds.is_empty() {
+            return Command::none();
+        }
+        self.apply_displays_if_connected()
+    }
+
+    /// Take the mirrored desktop notification off its key once its time is up.
+    fn end_notification(&mut self) -> Command<Message> {
+        let shown_for = Duration::from_secs(self.notification_mirror.seconds.into());
+        match &self.mirrored_notification {
+            Some((_, since)) if since.elapsed() >= shown_for => {}
+            _ => return Command::none(),
+        }
+        self.mirrored_notification = None;
+        self.apply_displays_if_connected()
+    }
+
+    /// Ask for the report of every `weather` key of the profile. The client answers from its
+    /// cache unless the report is older than the binding's refresh interval.
+    fn refresh_weather(&self) -> Command<Message> {
+        let Some(p) = &self.profile else {
+            return Command::none();
+        };
+        // Keys showing the same place share a fetch, at the shortest interval among them.
+        let mut queries: HashMap<WeatherQuery, Duration> = HashMap::new();
+        for (query, refresh) in p.keys.iter().filter_map(|k| weather_query(k.action.as_ref()?)) {
+            if query.0.is_empty() {
+                continue;
+            }
+            let shortest = queries.entry(query).or_insert(refresh);
+            *shortest = (*shortest).min(refresh);
+        }
+        Command::batch(queries.into_iter().map(|(query, refresh)| {
+            Command::perform(
+                fetch_weather_async(self.weather.clone(), query, refresh),
+                Message::WeatherFetched,
+            )
+        }))
+    }
+
+    /// Read the desktop's do-not-disturb when the profile has a key showing it.
+    fn check_do_not_disturb(&self) -> Command<Message> {
+        if !self.profile.as_ref().is_some_and(profile_shows_dnd) {
+            return Command::none();
+        }
+        Command::perform(do_not_disturb_async(), Message::DoNotDisturbChecked)
+    }
+
+    /// Read whether each radio a key of the profile switches is on.
+    fn check_radios(&self) -> Command<Message> {
+        let Some(p) = &self.profile else {
+            return Command::none();
+        };
+        let bindings = p.keys.iter().filter_map(|k| k.action.as_ref());
+        let radios: BTreeSet<actions::RadioKind> = bindings.filter_map(radio_of).collect();
+        Command::batch(
+            radios
+                .into_iter()
+                .map(|radio| Command::perform(radio_async(radio), Message::RadioChecked)),
+        )
+    }
+
+    /// Ask for the latest run of each workflow a key of the profile shows.
+    fn refresh_ci(&self) -> Command<Message> {
+        let Some(p) = &self.profile else {
+            return Command::none();
+        };
+        let bindings = p.keys.iter().filter_map(|k| k.action.as_ref());
+        let queries: BTreeSet<CiQuery> = bindings.filter_map(ci_query).collect();
+        Command::batch(queries.into_iter().map(|query| {
+            Command::perform(
+                latest_ci_run_async(self.github_actions.clone(), query),
+                Message::CiFetched,
+            )
+        }))
+    }
+
+    /// List the containers when a key of the profile shows one.
+    fn list_containers(&self) -> Command<Message> {
+        if !self.profile.as_ref().is_some_and(profile_shows_containers) {
+            return Command::none();
+        }
+        Command::perform(list_containers_async(), Message::ContainersListed)
+    }
+
+    /// List the MIDI ports for the MIDI view and the `midi` action editor.
+    fn list_midi_ports(&self) -> Command<Message> {
+        Command::batch([
+            Command::perform(
+                list_midi_output_ports_async(),
+                Message::MidiOutputPortsListed,
+            ),
+            Command::perform(list_midi_input_ports_async(), Message::MidiInputPortsListed),
+        ])
+    }
+
+    /// List the serial ports for the `serial` action editor.
+    fn list_serial_ports(&self) -> Command<Message> {
+        Command::perform(list_serial_ports_async(), Message::SerialPortsListed)
+    }
+
+    /// Read the state of each light a key or dial of the profile shows.
+    fn check_lights(&self) -> Command<Message> {
+        let Some(p) = &self.profile else {
+            return Command::none();
+        };
+        Command::batch(profile_lights(p).into_iter().map(|light| {
+            Command::perform(
+                light_state_async(self.lights.clone(), light),
+                Message::LightChecked,
+            )
+        }))
+    }
+
+    /// Read where the user is in each meeting app a key of the profile controls.
+    fn check_meetings(&self) -> Command<Message> {
+        let Some(p) = &self.profile else {
+            return Command::none();
+        };
+        let bindings = p.keys.iter().filter_map(|k| k.action.as_ref());
+        let apps: BTreeSet<actions::MeetingApp> =
+            bindings.filter_map(meeting_of).map(|m| m.0).collect();
+        Command::batch(apps.into_iter().map(|app| {
+            Command::perform(
+                meeting_state_async(self.meetings.clone(), app),
+                Message::MeetingChecked,
+            )
+        }))
+    }
+
+    /// Read the status of each repository a key of the profile shows.
+    fn check_git(&self) -> Command<Message> {
+        let Some(p) = &self.profile else {
+            return Command::none();
+        };
+        let bindings = p.keys.iter().filter_map(|k| k.action.as_ref());
+        let repos: BTreeSet<String> = bindings.filter_map(git_repo_of).collect();
+        Command::batch(
+            repos
+                .into_iter()
+                .map(|repo| Command::perform(git_status_async(repo), Message::GitChecked)),
+        )
+    }
+
+    fn apply_displays_if_connected(&self) -> Command<Message> {
+        // Test patterns are on the keys; they are redrawn once the test stops.
+        if self.device_test.cycling || self.device_test.writing {
+            return Command::none();
+        }
+        let Some(c) = self
+            .connected
+            .as_ref()
+            .filter(|c| !c.companion_connected && !c.reconnecting)
+        else {
+            return Command::none();
+        };
+        let Some(p) = self.profile.clone() else {
+            return Command::none();
+        };
+        let controller = c.controller.clone();
+        let live = LiveValues {
+            playing_sounds: c.playing_sounds.clone(),
+            counters: self.counters.clone(),
+            pomodoros: self.pomodoros.clone(),
+            plugin_keys: self.plugin_keys.clone(),
+            variables: self.variable_values.clone(),
+            entities: self.ha_states.clone(),
+            playback: self.spotify_playback.clone(),
+            screen_recording: c.screen_recording,
+            flashing: c.flashing.iter().map(|(slot, (flash, _))| (*slot, *flash)).collect(),
+            dashboard: self.strip_dashboard(&p),
+            weather: self.weather_reports.clone(),
+            now: chrono::Utc::now(),
+            notification: self
+                .mirrored_notification
+                .as_ref()
+                .map(|(summary, _)| (self.notification_mirror.key, summary.clone())),
+            do_not_disturb: self.do_not_disturb,
+            radios: self.radios.clone(),
+            power_holds: self.power_holds.clone(),
+            git: self.git_statuses.clone(),
+            containers: self.containers.clone(),
+            ci_runs: self.ci_runs.clone(),
+            vscode_tasks: self.vscode_task_status.clone(),
+            lights: self.light_states.clone(),
+            meetings: self.meeting_states.clone(),
+        };
+        let images = display_images(&p, &c.capabilities, &live);
+        let Some(surface) = surface_of(&self.surfaces, &p, c.id) else {
+            return Command::batch([
+                Command::perform(
+                    render_previews_async(c.display_cache.clone(), images.clone()),
+                    Message::PreviewsRendered,
+                ),
+                Command::perform(
+                    apply_displays_async(controller, c.display_cache.clone(), images),
+                    Message::DisplaysApplied,
+                ),
+            ]);
+        };
+
+        // The preview shows every key of the surface; each deck gets its own part.
+        let own = deck_images(surface, c.id, true, images.clone());
+        let mut cmds = vec![
+            Command::perform(
+                render_previews_async(c.display_cache.clone(), images),
+                Message::PreviewsRendered,
+            ),
+            Command::perform(
+                apply_displays_async(controller, c.display_cache.clone(), own),
+                Message::DisplaysApplied,
+            ),
+        ];
+        for deck in c.engine.surface_decks() {
+            let Some(cache) = c.surface_caches.get(&deck.id) else {
+                continue;
+            };
+            let images = display_images(&p, &deck.capabilities, &live);
+            let images = deck_images(surface, deck.id, false, images);
+            cmds.push(Command::perform(
+                apply_displays_async(deck.controller, cache.clone(), images),
+                Message::DisplaysApplied,
+            ));
+        }
+        Command::batch(cmds)
+    }
+
+    /// Forget what the device shows and draw the profile on it again.
+    fn redraw_displays(&self) -> Command<Message> {
+        if let Some(c) = &self.connected {
+            if let Ok(mut cache) = c.display_cache.lock() {
+                cache.invalidate();
+            }
+        }
+        self.apply_displays_if_connected()
+    }
+
+    /// Draw the next test pattern on every key, unless the last one is still being written.
+    fn write_next_test_pattern(&mut self) -> Command<Message> {
+        let Some(c) = self
+            .connected
+            .as_ref()
+            .filter(|c| !c.companion_connected && !c.reconnecting)
+        else {
+            return Command::none();
+        };
+        if !self.device_test.cycling || self.device_test.writing {
+            return Command::none();
+        }
+        self.device_test.writing = true;
+        let pattern = self.device_test.advance();
+        Command::perform(
+            write_test_pattern_async(
+                c.controller.clone(),
+                pattern,
+                c.key_count,
+                c.capabilities.key_size,
+            ),
+            Message::TestPatternWritten,
+        )
+    }
+
+    /// Stop cycling test patterns and draw the profile again.
+    fn stop_key_test(&mut self) -> Command<Message> {
+        let was_cycling = std::mem::take(&mut self.device_test.cycling);
+        // A pattern still being written is drawn over once it is done.
+        if !was_cycling || self.device_test.writing {
+            return Command::none();
+        }
+        self.redraw_displays()
+    }
+
+    /// Blank the device's keys from `covered` on: the loaded profile has no keys for them, so
+    /// they would keep showing the previous profile's images.
+    fn clear_uncovered_keys(&self, covered: usize) -> Vec<Command<Message>> {
+        let Some(c) = self.connected.as_ref().filter(|c| !c.companion_connected) else {
+            return vec![];
+        };
+        (covered..c.key_count as usize)
+            .map(|key| key as u8)
+            .map(|key| {
+                // Whatever the cache thinks is on the key is gone now.
+                if let Ok(mut cache) = c.display_cache.lock() {
+                    cache.discard(Slot::Key(key));
+                }
+                Command::perform(clear_key_async(c.controller.clone(), key), Message::DeviceCleared)
+            })
+            .collect()
+    }
+
+    fn start_marketplace_install(
+        &mut self,
+        p: MarketplacePlugin,
+        allow_mismatch: bool,
+    ) -> Command<Message> {
+        if self.marketplace.installing.is_some() {
+            return Command::none();
+        }
+
+        let Some(src) = self.current_marketplace_source().cloned() else {
+            self.marketplace.error = Some("No marketplace selected.".to_string());
+            return Command::none();
+        };
+
+        // Prefer a direct download URL from the marketplace feed.
+        let url = resolve_marketplace_download_url(&src, &p);
+
+        if self.plugins.iter().any(|ip| ip.manifest.id == p.id) {
+            return Command::none();
+        }
+
+        let mut verification = Verification::from_marketplace(&p);
+        verification.allow_mismatch = allow_mismatch;
+
+        self.marketplace.installing = Some(p.id.clone());
+        self.marketplace.error = None;
+        self.marketplace.pending_override = None;
+        let plugin = p.clone();
+        let done = move |res| Message::MarketplaceInstalled { plugin, res };
+        if let Some(url) = url {
+            Command::perform(install_marketplace_async(url, p.id, verification), done)
+        } else if let Some(repo) = p.repository.clone() {
+            // Rivul marketplace derives downloads from the GitHub repository.
+            // We attempt to resolve a release asset URL and install it.
+            Command::perform(
+                install_marketplace_from_repo_async(
+                    repo,
+                    p.id,
+                    verification,
+                    self.marketplace_settings.github_token().map(str::to_string),
+                ),
+                done,
+            )
+        } else {
+            self.marketplace.installing = None;
+            self.marketplace.error =
+                Some("No installable download found for this plugin.".to_string());
+            Command::none()
+        }
+    }
+
+    fn current_marketplace_source(&self) -> Option<&MarketplaceSource> {
+        self.marketplace
+            .selected_source_idx
+            .and_then(|i| self.marketplace.sources.get(i))
+    }
+
+    /// Picks up files a sync changed in the data directory: profiles are reloaded, settings wait
+    /// for a restart.
+    fn sync_pulled<'a>(&mut self, files: impl Iterator<Item = &'a str>) -> Command<Message> {
+        let mut profiles = false;
+        for file in files {
+            if file == storage::sync::SETTINGS_FILE {
+                self.sync.settings_pulled = true;
+            }
+            profiles |= storage::sync::profile_id(file).is_some();
+        }
+        if profiles {
+            return self.handle_message(Message::RefreshProfiles);
+        }
+        Command::none()
+    }
+
+    fn refresh_profile_gallery(&mut self) -> Command<Message> {
+        let url = self
+            .marketplace_settings
+            .profile_gallery_url
+            .trim()
+            .to_string();
+        let gallery = &mut self.marketplace.gallery;
+        if url.is_empty() {
+            gallery.error = Some("Enter the URL of a profile gallery index.".to_string());
+            return Command::none();
+        }
+        gallery.loading = true;
+        gallery.error = None;
+        Command::perform(
+            fetch_profile_gallery_async(url),
+            Message::ProfileGalleryLoaded,
+        )
+    }
+
+    /// Work out which plugins the loaded profile uses that are not installed, and look up the
+    /// ones that changed in the marketplace sources.
+    fn update_missing_plugins(&mut self) -> Command<Message> {
+        let ids: Vec<String> = match &self.profile {
+            Some(p) if self.plugins_listed => storage::profiles::plugin_ids(p)
+                .into_iter()
+                .filter(|id| !self.plugins.iter().any(|ip| &ip.manifest.id == id))
+                .collect(),
+            _ => vec![],
+        };
+        if ids == self.missing_plugins.ids {
+            return Command::none();
+        }
+        self.missing_plugins = MissingPlugins {
+            ids: ids.clone(),
+            ..MissingPlugins::default()
+        };
+        if ids.is_empty() {
+            return Command::none();
+        }
+        let sources = self.marketplace.sources.clone();
+        Command::perform(
+            find_plugin_downloads_async(sources, ids.clone()),
+            move |res| Message::MissingPluginsFound { ids, res },
+        )
+    }
+
+    /// How to get the plugin `id` a gallery profile uses: `Ok(None)` when it is installed,
+    /// `Ok(Some(..))` when the marketplace offers it and otherwise why it can't be installed.
+    fn gallery_plugin_download(&self, id: &str) -> Result<Option<PluginDownload>, &'static str> {
+        if self.plugins.iter().any(|p| p.manifest.id == id) {
+            return Ok(None);
+        }
+        let Some(p) = self.marketplace.plugins.iter().find(|p| p.id == id) else {
+            return Err("not in the marketplace");
+        };
+        if p.compatibility().is_incompatible() {
+            return Err("incompatible");
+        }
+        let url = self
+            .current_marketplace_source()
+            .and_then(|src| resolve_marketplace_download_url(src, p));
+        let from_repo = p.repository.as_deref().and_then(parse_github_owner_repo);
+        if url.is_none() && from_repo.is_none() {
+            return Err("no download");
+        }
+        Ok(Some(PluginDownload {
+            plugin: p.clone(),
+            url,
+        }))
+    }
+
+    /// Install the missing plugins the previewed gallery profile uses, then save it as a new
+    /// profile. Plugins the marketplace does not offer are left out; the profile's diagnostics
+    /// point them out.
+    fn start_profile_import(&mut self) -> Command<Message> {
+        let gallery = &self.marketplace.gallery;
+        let (Some(entry), Some(Ok(profile))) = (&gallery.selected, &gallery.preview) else {
+            return Command::none();
+        };
+        if gallery.importing || gallery.imported {
+            return Command::none();
+        }
+        let downloads = openaction::profile_gallery::required_plugins(entry, profile)
+            .iter()
+            .filter_map(|id| self.gallery_plugin_download(id).ok().flatten())
+            .collect();
+        let profile = profile.clone();
+        self.marketplace.gallery.importing = true;
+        self.marketplace.gallery.error = None;
+        Command::perform(
+            import_gallery_profile_async(
+                profile,
+                downloads,
+                self.marketplace_settings.github_token().map(str::to_string),
+            ),
+            Message::ProfileGalleryImported,
+        )
+    }
+
+    /// Queue the icons of the plugins on the current marketplace page; the downloader runs only
+    /// a few at a time.
+    fn marketplace_fetch_icons_for_current_page(&mut self) -> Command<Message> {
+        let Some(src) = self.current_marketplace_source().cloned() else {
+            return Command::none();
+        };
+
+        let start = self.marketplace.page.saturating_mul(MARKETPLACE_PAGE_SIZE);
+        let page: Vec<MarketplacePlugin> = self
+            .marketplace
+            .plugins
+            .iter()
+            .filter(|p| self.marketplace.matches(p))
+            .skip(start)
+            .take(MARKETPLACE_PAGE_SIZE)
+            .cloned()
+            .collect();
+
+        let mut cmds = vec![];
+        for p in &page {
+            cmds.extend(self.marketplace_fetch_icon(&src, p));
+        }
+
+        if cmds.is_empty() {
+            Command::none()
+        } else {
+            Command::batch(cmds)
+        }
+    }
+
+    /// Queue the icon of `p`, unless it is loaded or already queued.
+    fn marketplace_fetch_icon(
+        &mut self,
+        src: &MarketplaceSource,
+        p: &MarketplacePlugin,
+    ) -> Option<Command<Message>> {
+        let icon_url = marketplace_icon_url(src, p)?;
+        let key = format!("{}|{}", src.index_url, p.id);
+        if self.marketplace.icon_cache.contains_key(&key)
+            || !self.marketplace.icons_pending.insert(key.clone())
+        {
+            return None;
+        }
+        Some(Command::perform(
+            fetch_icon_async(self.marketplace.downloader.clone(), icon_url),
+            move |bytes| Message::MarketplaceIconLoaded { key, bytes },
+        ))
+    }
+
+    fn view_topbar(&self) -> Element<'_, Message> {
+        let device_selected = self
+            .selected_device
+            .and_then(|id| self.device_choices.iter().find(|d| d.id == id).cloned());
+        let profile_selected = self
+            .selected_profile
+            .and_then(|id| self.profile_choices.iter().find(|p| p.id == id).cloned());
+
+        let status = match (&self.connected, self.connecting) {
+            (None, true) => "Connecting…".to_string(),
+            (None, false) => "Not connected".to_string(),
+            (Some(c), _) if c.reconnecting => format!("{} disconnected; reconnecting…", c.name),
+            (Some(c), _) => format!(
+                "Connected to {} • {} keys • id {}",
+                c.name, c.key_count, c.id.0
+            ),
+        };
+        let status = if self.paused {
+            format!("{status} • actions paused")
+        } else if self.dry_run {
+            format!("{status} • dry run (actions are only logged)")
+        } else {
+            status
+        };
+
+        let bar = row![
+            column![
+                text("RiverDeck-Redux").size(22),
+                text(status).size(12).style(self.color_text_muted()),
+            ]
+            .spacing(2),
+            horizontal_space(),
+            column![
+                text("Device").size(12).style(self.color_text_muted()),
+                pick_list(
+                    self.device_choices.clone(),
+                    device_selected,
+                    Message::DevicePicked
+                )
+            ]
+            .spacing(4),
+            column![
+                text("Profile").size(12).style(self.color_text_muted()),
+                row![
+                    pick_list(
+                        self.profile_choices.clone(),
+                        profile_selected,
+                        Message::ProfilePicked
+                    ),
+                    button(text("R"))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::RefreshProfiles),
+                    button(text("+"))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::CreateProfile),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center)
+            ]
+            .spacing(4),
+            button(text("Schedules"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenSchedules),
+            button(text("Webhooks"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenWebhooks),
+            button(text("Text Expander"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenTypedTriggers),
+            button(text("File Watchers"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenFileWatchers),
+            button(text("Process Watchers"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenProcessWatchers),
+            button(text("MIDI"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenMidi),
+            button(text("OSC"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenOsc),
+            button(text("History"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenHistory),
+            button(text("Stats"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenStats),
+            button(text("Device Test"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::OpenDeviceTest),
+            button(text(if self.paused { "Resume" } else { "Pause" }))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::SetPaused(!self.paused)),
+            checkbox("Dry run", self.dry_run).on_toggle(Message::SetDryRun),
+            button(text("Refresh"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::RefreshDevices),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(14);
+
+        container(bar)
+            .padding(12)
+            .style(panel())
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn view_hid_diagnosis(&self, diag: &HidrawDiagnosis) -> Element<'_, Message> {
+        let action: Element<Message> = match &diag.remediation {
+            Remediation::InstallUdevRule { rule_path, .. } => {
+                let label = if self.installing_udev_rule {
+                    "Installing…".to_string()
+                } else {
+                    format!("Install udev rule ({})", rule_path.display())
+                };
+                let mut btn = button(text(label).size(12)).style(iced::theme::Button::Primary);
+                if !self.installing_udev_rule {
+                    btn = btn.on_press(Message::InstallUdevRule);
+                }
+                btn.into()
+            }
+            Remediation::ReplugDevice => text("Unplug and replug the device, then re-check.")
+                .size(12)
+                .style(self.color_text_muted())
+                .into(),
+            Remediation::GrantInputMonitoring { settings_url } => {
+                button(text("Open Input Monitoring settings").size(12))
+                    .style(iced::theme::Button::Primary)
+                    .on_press(Message::OpenUrl(settings_url.clone()))
+                    .into()
+            }
+            Remediation::Manual => text("").into(),
+        };
+
+        container(
+            column![
+                text("Device access problem").size(14),
+                text(&diag.message).size(12),
+                row![
+                    action,
+                    button(text("Re-check").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::RefreshDevices),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ]
+            .spacing(6),
+        )
+        .padding(10)
+        .style(callout_card(self.active_theme.radii.card))
+        .width(Length::Fill)
+        .into()
+    }
+
+    /// Problems in the loaded profile's file; the profile loaded, so these are warnings.
+    fn view_profile_diagnostics(&self) -> Element<'_, Message> {
+        let danger = self.active_theme.theme.palette().danger;
+        let mut list = column![].spacing(2);
+        for d in &self.profile_diagnostics {
+            let mut line = String::new();
+            if let Some(n) = d.line {
+                line.push_str(&format!("Line {n}: "));
+            }
+            if !d.path.is_empty() {
+                line.push_str(&format!("{}: ", d.path));
+            }
+            line.push_str(&d.message);
+            let color = match d.severity {
+                Severity::Error => danger,
+                Severity::Warning => self.color_text_muted(),
+            };
+            list = list.push(text(line).size(12).style(color));
+        }
+
+        container(
+            column![
+                row![
+                    text("Problems in this profile's file").size(14),
+                    horizontal_space(),
+                    button(text("Dismiss").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::DismissProfileDiagnostics),
+                ]
+                .align_items(Alignment::Center),
+                list,
+            ]
+            .spacing(6),
+        )
+        .padding(10)
+        .style(callout_card(self.active_theme.radii.card))
+        .width(Length::Fill)
+        .into()
+    }
+
+    /// The plugins the loaded profile uses that are not installed, with a way to install the
+    /// ones the marketplaces offer.
+    fn view_missing_plugins(&self) -> Element<'_, Message> {
+        let missing = &self.missing_plugins;
+        let mut list = column![].spacing(2);
+        for id in &missing.ids {
+            let download = missing
+                .found
+                .as_ref()
+                .and_then(|f| f.iter().find(|d| &d.plugin.id == id));
+            let line = match (&missing.found, download) {
+                (None, _) if missing.error.is_none() => format!("{id}: looking it up…"),
+                (_, Some(d)) => format!("{} ({id}): available", d.plugin.name),
+                _ => format!("{id}: not found in the marketplaces"),
+            };
+            list = list.push(text(line).size(12).style(self.color_text_muted()));
+        }
+        if let Some(e) = &missing.error {
+            let danger = self.active_theme.theme.palette().danger;
+            list = list.push(text(e).size(12).style(danger));
+        }
+
+        let available = missing.found.as_ref().map_or(0, Vec::len);
+        let install: Element<Message> = if missing.installing {
+            button(text("Installing…").size(12))
+                .style(iced::theme::Button::Primary)
+                .into()
+        } else if available > 0 {
+            let label = match available {
+                1 => "Install plugin".to_string(),
+                n => format!("Install {n} plugins"),
+            };
+            button(text(label).size(12))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::InstallMissingPlugins)
+                .into()
+        } else {
+            text("").into()
+        };
+
+        container(
+            column![
+                row![
+                    text("Keys marked in red use plugins that are not installed").size(14),
+                    horizontal_space(),
+                    install,
+                ]
+                .align_items(Alignment::Center),
+                list,
+            ]
+            .spacing(6),
+        )
+        .padding(10)
+        .style(callout_card(self.active_theme.radii.card))
+        .width(Length::Fill)
+        .into()
+    }
+
+    /// Files changed here and in the sync folder in ways that do not merge, each with a way to
+    /// pick the side that wins.
+    fn view_sync_conflicts(&self) -> Element<'_, Message> {
+        let mut list = column![].spacing(4);
+        for file in &self.sync.conflicts {
+            let name = match storage::sync::profile_id(file) {
+                Some(id) => match self.profiles.iter().find(|p| p.id == id) {
+                    Some(p) => format!("Profile \"{}\"", p.name),
+                    None => format!("Profile {}", id.0),
+                },
+                None if file == storage::sync::SETTINGS_FILE => "Settings".to_string(),
+                None => file.clone(),
+            };
+            let keep = Message::ResolveSyncConflict(file.clone(), Resolution::KeepLocal);
+            let take = Message::ResolveSyncConflict(file.clone(), Resolution::UseSynced);
+            list = list.push(
+                row![
+                    text(name).size(12),
+                    horizontal_space(),
+                    button(text("Keep mine").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(keep),
+                    button(text("Use synced").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(take),
+                ]
+                .spacing(6)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        container(
+            column![
+                text("Changed here and in the sync folder").size(14),
+                text("Pick the version to keep; the other one is replaced.")
+                    .size(12)
+                    .style(self.color_text_muted()),
+                list,
+            ]
+            .spacing(6),
+        )
+        .padding(10)
+        .style(callout_card(self.active_theme.radii.card))
+        .width(Length::Fill)
+        .into()
+    }
+
+    fn view_error_banner(&self, err: &str) -> Element<'_, Message> {
+        container(text(err).style(Color::from_rgb8(255, 160, 160)))
+            .padding(10)
+            .style(error_banner())
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn view_sidebar(&self) -> Element<'_, Message> {
+        let plugins_section = self.view_sidebar_plugins();
+        let settings_section = self.view_sidebar_settings();
+
+        let content = column![plugins_section, horizontal_rule(1), settings_section].spacing(12);
+
+        container(scrollable(content).height(Length::Fill))
+            .padding(12)
+            .width(Length::Fixed(300.0))
+            .height(Length::Fill)
+            .style(panel())
+            .into()
+    }
+
+    fn view_sidebar_settings(&self) -> Element<'_, Message> {
+        let mut choices = vec![AudioDeviceChoice::Default];
+        choices.extend(self.audio_devices.iter().cloned().map(AudioDeviceChoice::Named));
+        let selected = match &self.audio_output_device {
+            None => AudioDeviceChoice::Default,
+            Some(name) => AudioDeviceChoice::Named(name.clone()),
+        };
+
+        let mut variables = column![text("Variables").size(12).style(self.color_text_muted())].spacing(4);
+        if self.variable_values.is_empty() {
+            variables = variables.push(
+                text("None yet; set them with Set Variable actions.")
+                    .size(12)
+                    .style(self.color_text_muted()),
+            );
+        }
+        for (name, value) in &self.variable_values {
+            variables = variables.push(
+                row![
+                    text(format!("{name} = {value}")).size(12).width(Length::Fill),
+                    button(text("X").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::ClearVariable(name.clone())),
+                ]
+                .spacing(6)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        let companion = &self.companion_settings;
+        let companion_status = match &self.connected {
+            Some(c) if c.companion_connected => "Connected; Companion controls the keys.",
+            Some(_) if companion.enabled => "Connecting…",
+            _ if companion.enabled => "Connects once a device is connected.",
+            _ => "Hand the keys to Companion (Satellite protocol, port 16622).",
+        };
+        let companion = column![
+            text("Bitfocus Companion").size(12).style(self.color_text_muted()),
+            checkbox("Connect to Companion", companion.enabled)
+                .on_toggle(|v| Message::CompanionEdited(CompanionEdit::Enabled(v))),
+            text_input("127.0.0.1:16622", &self.edit_companion_address)
+                .on_input(|v| Message::CompanionEdited(CompanionEdit::Address(v)))
+                .on_submit(Message::CompanionEdited(CompanionEdit::ApplyAddress)),
+            text(companion_status).size(12).style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let gestures = &self.touch_gestures;
+        let touch_gestures = column![
+            text("Touch strip swipes").size(12).style(self.color_text_muted()),
+            text(format!("Distance: {} px", gestures.swipe_distance)).size(12),
+            slider(50..=600, i32::from(gestures.swipe_distance), |v| {
+                Message::TouchGesturesEdited(TouchGestureEdit::SwipeDistance(v))
+            })
+            .step(10)
+            .on_release(Message::TouchGesturesEdited(TouchGestureEdit::Save)),
+            text(format!("Within: {} ms", gestures.swipe_max_ms)).size(12),
+            slider(100..=1500, gestures.swipe_max_ms as i32, |v| {
+                Message::TouchGesturesEdited(TouchGestureEdit::SwipeTime(v))
+            })
+            .step(50)
+            .on_release(Message::TouchGesturesEdited(TouchGestureEdit::Save)),
+        ]
+        .spacing(4);
+
+        let limits = &self.macro_settings;
+        let macros = column![
+            text("Macros").size(12).style(self.color_text_muted()),
+            text(format!("At most {} steps per action", limits.max_steps)).size(12),
+            slider(64..=4096, limits.max_steps as i32, |v| {
+                Message::MacroLimitsEdited(MacroLimitsEdit::MaxSteps(v))
+            })
+            .step(64)
+            .on_release(Message::MacroLimitsEdited(MacroLimitsEdit::Save)),
+            text(format!("Macros nested at most {} deep", limits.max_depth)).size(12),
+            slider(1..=64, limits.max_depth as i32, |v| {
+                Message::MacroLimitsEdited(MacroLimitsEdit::MaxDepth(v))
+            })
+            .on_release(Message::MacroLimitsEdited(MacroLimitsEdit::Save)),
+        ]
+        .spacing(4);
+
+        let themes = ThemeChoice::all(&self.user_themes);
+        let theme = themes.iter().find(|c| c.setting == self.interface.theme).cloned();
+        let themes_dir = storage::themes::themes_dir()
+            .map(|d| d.display().to_string())
+            .unwrap_or_default();
+        let interface = column![
+            text("Interface").size(12).style(self.color_text_muted()),
+            pick_list(themes, theme, Message::ThemePicked).width(Length::Fill),
+            text(format!("Theme files (.json) in {themes_dir}"))
+                .size(12)
+                .style(self.color_text_muted()),
+            text(format!("Scale: {}% (Ctrl+= / Ctrl+-)", self.edit_ui_scale)).size(12),
+            slider(
+                i32::from(MIN_UI_SCALE)..=i32::from(MAX_UI_SCALE),
+                i32::from(self.edit_ui_scale),
+                |v| Message::InterfaceEdited(InterfaceEdit::Scale(v)),
+            )
+            .step(i32::from(UI_SCALE_STEP / 2))
+            .on_release(Message::InterfaceEdited(InterfaceEdit::Apply)),
+            text("Arrow keys move between keys, Enter edits the selected one.")
+                .size(12)
+                .style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let mirror = &self.notification_mirror;
+        let key_count = self.connected.as_ref().map_or(32, |c| c.key_count.max(1));
+        let save_mirror = Message::NotificationMirrorEdited(NotificationMirrorEdit::Save);
+        let notifications = column![
+            text("Desktop notifications")
+                .size(12)
+                .style(self.color_text_muted()),
+            checkbox("Show them on a key", mirror.enabled).on_toggle(|v| {
+                Message::NotificationMirrorEdited(NotificationMirrorEdit::Enabled(v))
+            }),
+            text(format!("Key {}", u16::from(mirror.key) + 1)).size(12),
+            slider(1..=i32::from(key_count), i32::from(mirror.key) + 1, |v| {
+                Message::NotificationMirrorEdited(NotificationMirrorEdit::Key(v))
+            })
+            .on_release(save_mirror.clone()),
+            text(format!("For {} s", mirror.seconds)).size(12),
+            slider(1..=30, mirror.seconds.min(30) as i32, |v| {
+                Message::NotificationMirrorEdited(NotificationMirrorEdit::Seconds(v))
+            })
+            .on_release(save_mirror.clone()),
+            text(
+                "Shows the summary of each new notification while a device is connected \
+                 (Linux: read from the session bus with dbus-monitor).",
+            )
+            .size(12)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let ha = &self.home_assistant_settings;
+        let ha_status = if self.ha_connected {
+            format!("Connected; {} entities.", self.ha_states.len())
+        } else if !ha.enabled {
+            "Call services and show entity states on keys.".to_string()
+        } else if ha.token.is_empty() {
+            "Needs a long-lived access token.".to_string()
+        } else {
+            self.ha_error.clone().unwrap_or_else(|| "Connecting…".to_string())
+        };
+        let home_assistant = column![
+            text("Home Assistant").size(12).style(self.color_text_muted()),
+            checkbox("Connect to Home Assistant", ha.enabled)
+                .on_toggle(|v| Message::HomeAssistantEdited(HomeAssistantEdit::Enabled(v))),
+            text_input("http://homeassistant.local:8123", &self.edit_ha_url)
+                .on_input(|v| Message::HomeAssistantEdited(HomeAssistantEdit::Url(v)))
+                .on_submit(Message::HomeAssistantEdited(HomeAssistantEdit::Apply)),
+            text_input("Long-lived access token", &self.edit_ha_token)
+                .secure(true)
+                .on_input(|v| Message::HomeAssistantEdited(HomeAssistantEdit::Token(v)))
+                .on_submit(Message::HomeAssistantEdited(HomeAssistantEdit::Apply)),
+            text(ha_status).size(12).style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let hue_status = if self.hue_pairing {
+            "Pairing…".to_string()
+        } else if let Some(e) = &self.hue_pair_error {
+            e.clone()
+        } else if self.lighting_settings.hue_app_key.is_empty() {
+            "Press the link button on the Hue bridge, then pair within 30 seconds.".to_string()
+        } else {
+            let bridge = &self.lighting_settings.hue_bridge;
+            format!("Paired with the bridge at {bridge}.")
+        };
+        let bridges: Vec<Discovered> = match &self.light_devices {
+            Some(Ok(found)) => {
+                let bridges = found.iter().filter(|d| d.kind == DeviceKind::HueBridge);
+                bridges.cloned().collect()
+            }
+            _ => Vec::new(),
+        };
+        let mut lighting = column![
+            text("Lighting").size(12).style(self.color_text_muted()),
+            row![
+                text_input("Hue bridge address", &self.edit_hue_bridge)
+                    .on_input(Message::HueBridgeChanged)
+                    .on_submit(Message::PairHue)
+                    .width(Length::Fill),
+                button(text("Find"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::DiscoverLights),
+                button(text("Pair"))
+                    .on_press_maybe((!self.hue_pairing).then_some(Message::PairHue)),
+            ]
+            .spacing(6)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(4);
+        if bridges.len() > 1 {
+            let host = &self.edit_hue_bridge;
+            let picked = bridges.iter().find(|b| b.host == *host).cloned();
+            let pick = pick_list(bridges, picked, |b: Discovered| {
+                Message::HueBridgeChanged(b.host)
+            });
+            lighting = lighting.push(pick.placeholder("Found bridges…").width(Length::Fill));
+        }
+        let lighting = lighting.push(text(hue_status).size(12).style(self.color_text_muted()));
+
+        let spotify_status = match (&self.spotify_playback, &self.spotify_error) {
+            _ if self.spotify_signing_in => "Waiting for the browser sign-in…".to_string(),
+            (Some(p), _) => format!("Now playing: {} – {}", p.title, p.artists),
+            _ if self.spotify_signed_in => "Signed in; nothing playing.".to_string(),
+            (None, Some(e)) => e.clone(),
+            (None, None) => format!(
+                "Create an app on developer.spotify.com with the redirect URI {}, then sign in \
+                 with its client ID.",
+                spotify::REDIRECT_URI
+            ),
+        };
+        let spotify_button = if self.spotify_signed_in {
+            button(text("Sign out"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::SpotifySignOut)
+        } else {
+            button(text("Sign in"))
+                .on_press_maybe((!self.spotify_signing_in).then_some(Message::SpotifySignIn))
+        };
+        let spotify = column![
+            text("Spotify").size(12).style(self.color_text_muted()),
+            row![
+                text_input("Client ID", &self.edit_spotify_client_id)
+                    .on_input(Message::SpotifyClientIdChanged)
+                    .on_submit(Message::SpotifySignIn)
+                    .width(Length::Fill),
+                spotify_button,
+            ]
+            .spacing(6)
+            .align_items(Alignment::Center),
+            text(spotify_status).size(12).style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let marketplace = column![
+            text("Marketplace").size(12).style(self.color_text_muted()),
+            text_input("GitHub token (optional)", &self.edit_github_token)
+                .secure(true)
+                .on_input(Message::GithubTokenChanged)
+                .on_submit(Message::GithubTokenSubmitted),
+            text(
+                "Used for plugin details from GitHub; without one GitHub allows 60 requests an \
+                 hour.",
+            )
+            .size(12)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let secrets_title = text("Secrets").size(12).style(self.color_text_muted());
+        let mut secrets = column![secrets_title].spacing(4);
+        for (name, backend) in self.secrets.names() {
+            let kept = match backend {
+                secrets::Backend::Keyring => "keyring",
+                secrets::Backend::File => "encrypted file",
+            };
+            secrets = secrets.push(
+                row![
+                    text(format!("{{{{secret:{name}}}}}")).size(12),
+                    text(kept).size(12).style(self.color_text_muted()),
+                    horizontal_space(),
+                    button(text("Remove").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::RemoveSecret(name)),
+                ]
+                .spacing(6)
+                .align_items(Alignment::Center),
+            );
+        }
+        let save_secret = (!self.secret_saving && !self.edit_secret_name.trim().is_empty())
+            .then_some(Message::SaveSecret);
+        let secrets = secrets
+            .push(
+                row![
+                    text_input("Name", &self.edit_secret_name)
+                        .on_input(Message::SecretNameChanged)
+                        .width(Length::FillPortion(1)),
+                    text_input("Value", &self.edit_secret_value)
+                        .secure(true)
+                        .on_input(Message::SecretValueChanged)
+                        .on_submit(Message::SaveSecret)
+                        .width(Length::FillPortion(2)),
+                    button(text("Save")).on_press_maybe(save_secret),
+                ]
+                .spacing(6)
+                .align_items(Alignment::Center),
+            )
+            .push(
+                text(
+                    "Action fields take a secret as {{secret:name}}, filled in only when the \
+                     action runs. Secrets go to the system keyring when there is one.",
+                )
+                .size(12)
+                .style(self.color_text_muted()),
+            );
+
+        let connected_id = self.connected.as_ref().map(|c| c.id);
+        let mut surfaces =
+            column![text("Surfaces").size(12).style(self.color_text_muted())].spacing(6);
+        for surface in &self.surfaces {
+            let (columns, rows) = surface.grid();
+            let id = surface.id;
+            let add_deck = connected_id
+                .filter(|d| surface.member(*d).is_none())
+                .map(|_| Message::AddDeckToSurface(id));
+            let header = row![
+                text(&surface.name),
+                text(format!("{columns}×{rows} keys"))
+                    .size(12)
+                    .style(self.color_text_muted()),
+                horizontal_space(),
+                button(text("Add this deck").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press_maybe(add_deck),
+                button(text("New profile").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press_maybe(
+                        (!surface.members.is_empty()).then_some(Message::CreateSurfaceProfile(id)),
+                    ),
+                button(text("Delete").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::RemoveSurface(id)),
+            ]
+            .spacing(6)
+            .align_items(Alignment::Center);
+            let mut card = column![header, view_surface_layout(surface)].spacing(6);
+            for m in &surface.members {
+                let device = m.device;
+                let nudge = |label: &'static str, dx: i8, dy: i8| {
+                    button(text(label).size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::MoveSurfaceDeck {
+                            surface: id,
+                            device,
+                            dx,
+                            dy,
+                        })
+                };
+                let here = if connected_id.is_some_and(|d| d.0 == device) {
+                    " (this deck)"
+                } else {
+                    ""
+                };
+                card = card.push(
+                    row![
+                        text(format!(
+                            "{}{here} at column {}, row {}",
+                            m.name,
+                            m.column + 1,
+                            m.row + 1
+                        ))
+                        .size(12),
+                        horizontal_space(),
+                        nudge("<", -1, 0),
+                        nudge(">", 1, 0),
+                        nudge("^", 0, -1),
+                        nudge("v", 0, 1),
+                        button(text("Remove").size(12))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::RemoveDeckFromSurface {
+                                surface: id,
+                                device,
+                            }),
+                    ]
+                    .spacing(4)
+                    .align_items(Alignment::Center),
+                );
+            }
+            surfaces = surfaces.push(
+                container(card)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(callout_card(self.active_theme.radii.card)),
+            );
+        }
+        let add_surface =
+            (!self.edit_surface_name.trim().is_empty()).then_some(Message::AddSurface);
+        let surfaces = surfaces
+            .push(
+                row![
+                    text_input("New surface", &self.edit_surface_name)
+                        .on_input(Message::SurfaceNameChanged)
+                        .on_submit(Message::AddSurface),
+                    button(text("Add")).on_press_maybe(add_surface),
+                ]
+                .spacing(6)
+                .align_items(Alignment::Center),
+            )
+            .push(
+                text(
+                    "A surface composes the keys of several decks into one grid for profiles made \
+                     for it. Connecting any of its decks opens the others; dials and touch strips \
+                     stay with the connected deck.",
+                )
+                .size(12)
+                .style(self.color_text_muted()),
+            );
+
+        let mut sync_status = match self.sync_settings.folder() {
+            None => "Profiles and settings are copied to and from this folder, e.g. a git \
+                     checkout or a Syncthing folder shared with other machines."
+                .to_string(),
+            Some(_) if self.sync.running => "Syncing…".to_string(),
+            Some(_) => self.sync.status.clone(),
+        };
+        if self.sync.settings_pulled {
+            sync_status.push_str(" Settings from the sync folder take effect after a restart.");
+        }
+        let sync = column![
+            text("Sync").size(12).style(self.color_text_muted()),
+            text_input("Sync folder (optional)", &self.edit_sync_folder)
+                .on_input(Message::SyncFolderChanged)
+                .on_submit(Message::SyncFolderSubmitted),
+            text(sync_status).size(12).style(self.color_text_muted()),
+        ]
+        .spacing(4);
+
+        let kiosk_open = !self.kiosk.enabled() || self.kiosk_unlocked;
+        let mut kiosk =
+            column![text("Kiosk lock").size(12).style(self.color_text_muted())].spacing(4);
+        for p in &self.profile_choices {
+            let toggle = checkbox(format!("Lock {}", p.label), self.kiosk.is_locked(p.id));
+            let id = p.id;
+            kiosk = kiosk.push(if kiosk_open {
+                toggle.on_toggle(move |v| Message::KioskProfileToggled(id, v))
+            } else {
+                toggle
+            });
+        }
+        let (pin_placeholder, pin_button, on_pin) = if kiosk_open {
+            let placeholder = "New PIN (empty turns the lock off)";
+            (placeholder, "Set PIN", Message::KioskPinSubmitted)
+        } else {
+            ("PIN", "Unlock", Message::UnlockKiosk)
+        };
+        let mut pin_row = row![
+            text_input(pin_placeholder, &self.edit_kiosk_pin)
+                .secure(true)
+                .on_input(Message::KioskPinChanged)
+                .on_submit(on_pin.clone()),
+            button(text(pin_button)).on_press(on_pin),
+        ]
+        .spacing(6)
+        .align_items(Alignment::Center);
+        if self.kiosk.enabled() && self.kiosk_unlocked {
+            pin_row = pin_row.push(
+                button(text("Lock now"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::LockKiosk),
+            );
+        }
+        let kiosk_status = match (self.kiosk.enabled(), kiosk_open) {
+            (false, _) if !self.kiosk.has_pin() => {
+                "Locked profiles can't be edited or switched away from without the PIN, here or \
+                 from keys and the API. Set a PIN to turn the lock on."
+            }
+            (false, _) => "Pick the profiles to lock.",
+            (true, true) => "Unlocked until \"Lock now\" or the next start.",
+            (true, false) => "Enter the PIN to change the lock.",
+        };
+        let kiosk = kiosk
+            .push(pin_row)
+            .push(text(kiosk_status).size(12).style(self.color_text_muted()));
+
+        let data_dir = storage::paths::data_dir()
+            .map(|d| d.display().to_string())
+            .unwrap_or_default();
+        let data_location = match storage::paths::data_location() {
+            storage::paths::DataLocation::Custom(_) => "custom folder",
+            storage::paths::DataLocation::Portable(_) => "portable",
+            storage::paths::DataLocation::Platform => "default",
+        };
+        let storage = column![
+            text("Data folder").size(12).style(self.color_text_muted()),
+            text(format!("{data_dir} ({data_location})")).size(12),
+        ]
+        .spacing(4);
+
+        column![
+            text("Settings").size(16),
+            text("Audio output").size(12).style(self.color_text_muted()),
+            pick_list(choices, Some(selected), Message::AudioDevicePicked).width(Length::Fill),
+            variables,
+            interface,
+            touch_gestures,
+            macros,
+            notifications,
+            companion,
+            home_assistant,
+            lighting,
+            spotify,
+            marketplace,
+            secrets,
+            surfaces,
+            kiosk,
+            sync,
+            storage,
+        ]
+        .spacing(8)
+        .into()
+    }
+
+    fn view_sidebar_plugins(&self) -> Element<'_, Message> {
+        let mut col = column![text("Plugins").size(16)].spacing(8);
+
+        col = col.push(
+            text_input(
+                "Local plugin dir or archive (.zip, .tar.gz, .streamdeckplugin)",
+                &self.install_plugin_path,
+            )
+            .on_input(Message::InstallPluginPathChanged),
+        );
+        col = col.push(
+            row![
+                button(text("Install"))
+                    .style(iced::theme::Button::Primary)
+                    .on_press(Message::InstallPluginFromPath),
+                button(text("Refresh"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::RefreshPlugins),
+                button(text("Marketplace"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::OpenMarketplace),
+            ]
+            .spacing(8),
+        );
+        if cfg!(target_os = "linux") {
+            col = col.push(
+                checkbox("Sandbox plugins (bwrap)", self.sandbox_enabled)
+                    .on_toggle(Message::SandboxToggled)
+                    .size(14)
+                    .text_size(12),
+            );
+        }
+
+        if self.plugins.is_empty() {
+            col = col.push(text("No plugins installed."));
+        } else {
+            for p in &self.plugins {
+                let plugin_id = p.manifest.id.clone();
+                let name = if p.enabled {
+                    text(&p.manifest.name).size(13)
+                } else {
+                    text(format!("{} (disabled)", p.manifest.name))
+                        .size(13)
+                        .style(self.color_text_muted())
+                };
+                col = col.push(
+                    row![
+                        checkbox("", p.enabled).on_toggle({
+                            let plugin_id = plugin_id.clone();
+                            move |enabled| Message::PluginEnabledToggled {
+                                plugin_id: plugin_id.clone(),
+                                enabled,
+                            }
+                        }),
+                        name,
+                        horizontal_space(),
+                        network_permission_button(p),
+                        button(text("Details").size(12))
+                            .style(iced::theme::Button::Text)
+                            .on_press(Message::TogglePluginDetails(plugin_id.clone())),
+                        button(text("Uninstall").size(12))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::UninstallPlugin(plugin_id.clone())),
+                    ]
+                    .spacing(6)
+                    .align_items(Alignment::Center),
+                );
+                if let Some(d) = self.plugin_details.as_ref().filter(|d| d.plugin_id == plugin_id) {
+                    col = col.push(self.view_plugin_details(p, d));
+                }
+            }
+        }
+
+        col.into()
+    }
+
+    fn view_plugin_details<'a>(
+        &self,
+        p: &InstalledPlugin,
+        d: &'a PluginDetails,
+    ) -> Element<'a, Message> {
+        let edit = |e: PluginDetailsEdit| Message::PluginDetailsEdited(e);
+        let mut env = column![].spacing(4);
+        for (i, (key, value)) in d.env.iter().enumerate() {
+            env = env.push(
+                row![
+                    text_input("NAME", key)
+                        .on_input(move |v| edit(PluginDetailsEdit::EnvKey(i, v)))
+                        .width(Length::FillPortion(2)),
+                    text_input("value", value)
+                        .on_input(move |v| edit(PluginDetailsEdit::EnvValue(i, v)))
+                        .width(Length::FillPortion(3)),
+                    button(text("Remove").size(12))
+                        .style(iced::theme::Button::Text)
+                        .on_press(edit(PluginDetailsEdit::RemoveEnv(i))),
+                ]
+                .spacing(4)
+                .align_items(Alignment::Center),
+            );
+        }
+        let sandbox_note = if p.sandboxed {
+            "In the sandbox only the plugin folder is there to start in."
+        } else {
+            "Relative to the plugin folder (. is the folder itself); empty keeps the app's."
+        };
+        container(
+            column![
+                text(format!("{} {}", p.manifest.id, p.manifest.version))
+                    .size(12)
+                    .style(self.color_text_muted()),
+                text("Arguments").size(12).style(self.color_text_muted()),
+                text_input("e.g. --port 8080 \"--name=My Deck\"", &d.args)
+                    .on_input(move |v| edit(PluginDetailsEdit::Args(v))),
+                text("Working directory").size(12).style(self.color_text_muted()),
+                text_input("Working directory", &d.cwd)
+                    .on_input(move |v| edit(PluginDetailsEdit::Cwd(v))),
+                text(sandbox_note).size(11).style(self.color_text_muted()),
+                text("Concurrent runs").size(12).style(self.color_text_muted()),
+                text_input("Unlimited (1 runs presses one after another)", &d.max_concurrent)
+                    .on_input(move |v| edit(PluginDetailsEdit::MaxConcurrent(v))),
+                text("Environment").size(12).style(self.color_text_muted()),
+                env,
+                row![
+                    button(text("Add variable").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(edit(PluginDetailsEdit::AddEnv)),
+                    horizontal_space(),
+                    button(text("Save").size(12))
+                        .style(iced::theme::Button::Primary)
+                        .on_press(edit(PluginDetailsEdit::Save)),
+                ]
+                .spacing(8),
+            ]
+            .spacing(6),
+        )
+        .padding(8)
+        .style(panel())
+        .into()
+    }
+
+    fn view_actions_panel(&self) -> Element<'_, Message> {
+        let header = text("Actions").size(16);
+
+        let search = text_input("Search actions…", &self.action_search)
+            .on_input(Message::ActionSearchChanged);
+
+        let q = self.action_search.trim().to_ascii_lowercase();
+        let builtin_actions = BuiltinKindChoice::ALL;
+
+        let mut list = column![].spacing(8);
+        let mut any = false;
+
+        // Builtin actions (always available).
+        for k in builtin_actions {
+            let label = k.to_string();
+            if !q.is_empty() && !label.to_ascii_lowercase().contains(&q) {
+                continue;
+            }
+            any = true;
+            list = list.push(
+                mouse_area(
+                    container(
+                        row![
+                            text(label).size(13),
+                            horizontal_space(),
+                            text("drag").size(12).style(self.color_text_muted()),
+                        ]
+                        .align_items(Alignment::Center),
+                    )
+                    .padding(8)
+                    .style(panel()),
+                )
+                .on_press(Message::StartDragAction(DraggedAction::Builtin(k)))
+                .interaction(iced::mouse::Interaction::Grab),
+            );
+        }
+
+        // Plugin actions.
+        for a in self.actions.iter() {
+            if !q.is_empty() && !a.label.to_ascii_lowercase().contains(&q) {
+                continue;
+            }
+            any = true;
+            let icon: Element<Message> =
+                match a.icon.as_ref().and_then(|p| self.action_icons.get(p)) {
+                    Some(handle) => image(handle.clone())
+                        .width(Length::Fixed(ACTION_ICON_SIZE as f32))
+                        .height(Length::Fixed(ACTION_ICON_SIZE as f32))
+                        .into(),
+                    None => iced::widget::Space::with_width(ACTION_ICON_SIZE as f32).into(),
+                };
+            list = list.push(
+                mouse_area(
+                    container(
+                        row![
+                            icon,
+                            text(&a.label).size(13),
+                            horizontal_space(),
+                            text("drag").size(12).style(self.color_text_muted()),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    )
+                    .padding(8)
+                    .style(panel()),
+                )
+                .on_press(Message::StartDragAction(DraggedAction::Plugin(a.clone())))
+                .interaction(iced::mouse::Interaction::Grab),
+            );
+        }
+
+        if !any {
+            list = list.push(
+                text(if self.actions.is_empty() {
+                    "No actions match your search."
+                } else {
+                    "No actions match your search."
+                })
+                .size(13)
+                .style(self.color_text_muted()),
+            );
+        }
+
+        let drag_hint: Element<Message> = if let Some(d) = &self.drag.dragging {
+            row![
+                text(format!("Dragging: {}", d.label())).size(12),
+                horizontal_space(),
+                button(text("Cancel"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::CancelDragAction),
+            ]
+            .align_items(Alignment::Center)
+            .into()
+        } else {
+            text("Drag an action onto a key to assign.").size(12).style(self.color_text_muted()).into()
+        };
+
+        let content = column![
+            header,
+            horizontal_rule(1),
+            drag_hint,
+            search,
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10);
+
+        container(content)
+            .padding(12)
+            .width(Length::Fixed(360.0))
+            .height(Length::Fill)
+            .style(panel())
+            .into()
+    }
+
+    fn view_schedules(&self) -> Element<'_, Message> {
+        let header = row![
+            text("Schedules").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::AddSchedule),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseSchedules),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let help = text(
+            "When: minute hour day-of-month month day-of-week in local time, e.g. \
+             \"0 9 * * 1-5\" (weekdays at 09:00) or \"0 * * * *\" (hourly). \
+             Schedules run while a device is connected and actions are not paused.",
+        )
+        .size(12)
+        .style(self.color_text_muted());
+
+        let mut list = column![].spacing(10);
+        if self.schedules.is_empty() {
+            list = list.push(text("No schedules yet.").size(13).style(self.color_text_muted()));
+        }
+        for s in &self.schedules {
+            list = list.push(self.view_schedule(s));
+        }
+
+        column![header, help, h_divider(), scrollable(list).height(Length::Fill)]
+            .spacing(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn view_schedule(&self, s: &Schedule) -> Element<'_, Message> {
+        let id = s.id;
+        let next_run = match engine::scheduler::next_run(&s.cron, chrono::Local::now()) {
+            Ok(t) if s.enabled => text(format!("Next run: {}", t.format("%a %Y-%m-%d %H:%M"))),
+            Ok(_) => text("Disabled"),
+            Err(e) => text(e.to_string()),
+        }
+        .size(12)
+        .style(self.color_text_muted());
+
+        let content = column![
+            row![
+                checkbox("", s.enabled).on_toggle(move |enabled| Message::ScheduleEdited {
+                    id,
+                    edit: ScheduleEdit::Enabled(enabled),
+                }),
+                text_input("Name", &s.name).on_input(move |v| Message::ScheduleEdited {
+                    id,
+                    edit: ScheduleEdit::Name(v),
+                }),
+                button(text("Remove").size(12))
+                    .style(iced::theme::Button::Destructive)
+                    .on_press(Message::RemoveSchedule(id)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                text("When").size(12).style(self.color_text_muted()),
+                text_input("0 9 * * *", &s.cron)
+                    .on_input(move |v| Message::ScheduleEdited {
+                        id,
+                        edit: ScheduleEdit::Cron(v),
+                    })
+                    .width(Length::Fixed(180.0)),
+                next_run,
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            self.view_trigger_action(&s.action, move |edit| Message::ScheduleEdited {
+                id,
+                edit: ScheduleEdit::Action(edit),
+            }),
+        ]
+        .spacing(8);
+
+        container(content).padding(10).style(panel()).into()
+    }
+
+    /// "Do" row of schedules, webhooks, typed triggers, file and process watchers and MIDI and
+    /// OSC triggers: the action kind plus its single setting.
+    fn view_trigger_action<'a>(
+        &'a self,
+        action: &'a ActionBinding,
+        on_edit: impl Fn(TriggerActionEdit) -> Message + Clone + 'a,
+    ) -> Element<'a, Message> {
+        let kind = TriggerActionChoice::of(action);
+        let editor: Element<Message> = match action {
+            ActionBinding::Builtin(BuiltinAction::SwitchProfile { mode }) => {
+                let selected = match mode {
+                    actions::SwitchProfileMode::To { profile_id } => self
+                        .profile_choices
+                        .iter()
+                        .find(|c| c.id.0 == *profile_id)
+                        .cloned(),
+                    _ => None,
+                };
+                let on_edit = on_edit.clone();
+                pick_list(self.profile_choices.clone(), selected, move |choice| {
+                    on_edit(TriggerActionEdit::Profile(choice))
+                })
+                .placeholder("Pick a profile")
+                .into()
+            }
+            ActionBinding::Builtin(BuiltinAction::IssueCommand { command, .. }) => {
+                let on_edit = on_edit.clone();
+                text_input("bash command…", command)
+                    .on_input(move |v| on_edit(TriggerActionEdit::Command(v)))
+                    .into()
+            }
+            ActionBinding::Builtin(BuiltinAction::KeyboardInput { text, .. }) => {
+                let on_edit = on_edit.clone();
+                text_input("Text to type…", text.as_deref().unwrap_or_default())
+                    .on_input(move |v| on_edit(TriggerActionEdit::Text(v)))
+                    .into()
+            }
+            ActionBinding::Plugin(p) => {
+                let current = self
+                    .actions
+                    .iter()
+                    .find(|a| a.plugin_id == p.plugin_id && a.action_id == p.action_id)
+                    .cloned();
+                let on_edit = on_edit.clone();
+                pick_list(self.actions.clone(), current, move |choice| {
+                    on_edit(TriggerActionEdit::Plugin(choice))
+                })
+                .into()
+            }
+            ActionBinding::Builtin(_) => text("Unsupported action (edit by changing kind).")
+                .style(self.color_text_muted())
+                .into(),
+        };
+
+        row![
+            text("Do").size(12).style(self.color_text_muted()),
+            pick_list(TriggerActionChoice::ALL.to_vec(), Some(kind), move |kind| {
+                on_edit(TriggerActionEdit::Kind(kind))
+            }),
+            editor,
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    fn view_typed_triggers(&self) -> Element<'_, Message> {
+        let header = row![
+            text("Text Expander").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::AddTypedTrigger),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseTypedTriggers),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let watch = column![
+            checkbox("Watch the keyboard", self.text_expander.enabled)
+                .on_toggle(Message::TextExpanderToggled),
+            text(
+                "Typing an abbreviation anywhere runs its action, e.g. \";sig\" typing a \
+                 signature. This reads every key you type (Linux: from /dev/input, so your user \
+                 needs to be in the \"input\" group) and assumes a US keyboard layout. \
+                 \"Erase\" deletes the abbreviation with the keyboard tool of Keyboard Input \
+                 actions first. Triggers fire while a device is connected and actions are not \
+                 paused.",
+            )
+            .size(12)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(8);
+
+        let mut list = column![].spacing(10);
+        if self.typed_triggers.is_empty() {
+            list = list.push(
+                text("No typed triggers yet.")
+                    .size(13)
+                    .style(self.color_text_muted()),
+            );
+        }
+        for t in &self.typed_triggers {
+            let id = t.id;
+            list = list.push(
+                container(
+                    column![
+                        row![
+                            checkbox("", t.enabled).on_toggle(move |enabled| {
+                                Message::TypedTriggerEdited {
+                                    id,
+                                    edit: TypedTriggerEdit::Enabled(enabled),
+                                }
+                            }),
+                            text("Typed").size(12).style(self.color_text_muted()),
+                            text_input(";sig", &t.abbreviation).on_input(move |v| {
+                                Message::TypedTriggerEdited {
+                                    id,
+                                    edit: TypedTriggerEdit::Abbreviation(v),
+                                }
+                            }),
+                            checkbox("Erase", t.erase).on_toggle(move |erase| {
+                                Message::TypedTriggerEdited {
+                                    id,
+                                    edit: TypedTriggerEdit::Erase(erase),
+                                }
+                            }),
+                            button(text("Remove").size(12))
+                                .style(iced::theme::Button::Destructive)
+                                .on_press(Message::RemoveTypedTrigger(id)),
+                        ]
+                        .spacing(10)
+                        .align_items(Alignment::Center),
+                        self.view_trigger_action(&t.action, move |edit| {
+                            Message::TypedTriggerEdited {
+                                id,
+                                edit: TypedTriggerEdit::Action(edit),
+                            }
+                        }),
+                    ]
+                    .spacing(8),
+                )
+                .padding(10)
+                .style(panel()),
+            );
+        }
+
+        column![
+            header,
+            container(watch).padding(10).style(panel()),
+            h_divider(),
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_file_watchers(&self) -> Element<'_, Message> {
+        let add = self.profile.is_some().then_some(Message::AddFileWatcher);
+        let header = row![
+            text("File Watchers").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press_maybe(add),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseFileWatchers),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let help = text(
+            "Run an action when a file is created, modified or deleted, e.g. when a build \
+             finishes or a download lands. A directory covers the files in it (and below it, \
+             with \"Subdirectories\"). Watchers belong to the profile and run while it is active, \
+             a device is connected and actions are not paused; a file fires once it has not \
+             changed for half a second.",
+        )
+        .size(12)
+        .style(self.color_text_muted());
+
+        let mut list = column![].spacing(10);
+        match &self.profile {
+            None => {
+                list = list.push(
+                    text("No profile loaded.")
+                        .size(13)
+                        .style(self.color_text_muted()),
+                )
+            }
+            Some(p) if p.file_watchers.is_empty() => {
+                let empty = format!("{} has no file watchers yet.", p.name);
+                list = list.push(text(empty).size(13).style(self.color_text_muted()));
+            }
+            Some(p) => {
+                for w in &p.file_watchers {
+                    list = list.push(self.view_file_watcher(w));
+                }
+            }
+        }
+
+        column![
+            header,
+            help,
+            h_divider(),
+            scrollable(list).height(Length::Fill)
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_file_watcher<'a>(&'a self, w: &'a FileWatcher) -> Element<'a, Message> {
+        let id = w.id;
+        let edit = move |edit| Message::FileWatcherEdited { id, edit };
+        let mut changes = row![text("On").size(12).style(self.color_text_muted())]
+            .spacing(10)
+            .align_items(Alignment::Center);
+        for (change, label) in [
+            (FileChange::Create, "Created"),
+            (FileChange::Modify, "Modified"),
+            (FileChange::Delete, "Deleted"),
+        ] {
+            changes = changes.push(
+                checkbox(label, w.changes.contains(&change))
+                    .on_toggle(move |on| edit(FileWatcherEdit::Change(change, on))),
+            );
+        }
+        changes = changes.push(
+            checkbox("Subdirectories", w.recursive)
+                .on_toggle(move |on| edit(FileWatcherEdit::Recursive(on))),
+        );
+
+        let content = column![
+            row![
+                checkbox("", w.enabled).on_toggle(move |on| edit(FileWatcherEdit::Enabled(on))),
+                text_input("/path/to/file or directory", &w.path)
+                    .on_input(move |v| edit(FileWatcherEdit::Path(v))),
+                button(text("Remove").size(12))
+                    .style(iced::theme::Button::Destructive)
+                    .on_press(Message::RemoveFileWatcher(id)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            changes,
+            self.view_trigger_action(&w.action, move |e| edit(FileWatcherEdit::Action(e))),
+        ]
+        .spacing(8);
+
+        container(content).padding(10).style(panel()).into()
+    }
+
+    fn view_process_watchers(&self) -> Element<'_, Message> {
+        let header = row![
+            text("Process Watchers").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::AddProcessWatcher),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseProcessWatchers),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let help = text(
+            "Run an action when a program starts or exits, e.g. switch to an OBS profile when \
+             \"obs\" starts and back when it exits. Names match like the \"process running\" \
+             condition (\".exe\" is optional). Processes are checked every two seconds while a \
+             device is connected and actions are not paused; a program already running when the \
+             watcher is added has not started.",
+        )
+        .size(12)
+        .style(self.color_text_muted());
+
+        let mut list = column![].spacing(10);
+        if self.process_watchers.is_empty() {
+            list = list.push(
+                text("No process watchers yet.")
+                    .size(13)
+                    .style(self.color_text_muted()),
+            );
+        }
+        for w in &self.process_watchers {
+            let id = w.id;
+            let edit = move |edit| Message::ProcessWatcherEdited { id, edit };
+            let content = column![
+                row![
+                    checkbox("", w.enabled)
+                        .on_toggle(move |on| edit(ProcessWatcherEdit::Enabled(on))),
+                    text("When").size(12).style(self.color_text_muted()),
+                    text_input("obs", &w.process)
+                        .on_input(move |v| edit(ProcessWatcherEdit::Process(v))),
+                    pick_list(
+                        vec![ProcessTransition::Started, ProcessTransition::Exited],
+                        Some(w.on),
+                        move |on| edit(ProcessWatcherEdit::On(on)),
+                    )
+                    .text_size(12),
+                    button(text("Remove").size(12))
+                        .style(iced::theme::Button::Destructive)
+                        .on_press(Message::RemoveProcessWatcher(id)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                self.view_trigger_action(&w.action, move |e| edit(ProcessWatcherEdit::Action(e))),
+            ]
+            .spacing(8);
+            list = list.push(container(content).padding(10).style(panel()));
+        }
+
+        column![
+            header,
+            help,
+            h_divider(),
+            scrollable(list).height(Length::Fill)
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_midi(&self) -> Element<'_, Message> {
+        let header = row![
+            text("MIDI").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::AddMidiTrigger),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseMidi),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let settings = &self.midi_settings;
+        let ports = match &self.midi_input_ports {
+            Some(Ok(ports)) => ports.clone(),
+            _ => Vec::new(),
+        };
+        let picked = ports.iter().find(|p| **p == settings.input_port).cloned();
+        let mut input = column![
+            row![
+                checkbox("Listen to MIDI input", settings.enabled)
+                    .on_toggle(|v| Message::MidiSettingsEdited(MidiSettingsEdit::Enabled(v))),
+                pick_list(ports, picked, |p| {
+                    Message::MidiSettingsEdited(MidiSettingsEdit::InputPort(p))
+                })
+                .placeholder("Pick a port…")
+                .text_size(12),
+                text_input("Part of the port name", &settings.input_port)
+                    .on_input(|v| Message::MidiSettingsEdited(MidiSettingsEdit::InputPort(v)))
+                    .width(Length::Fixed(200.0)),
+                button(text("Refresh").size(12)).on_press(Message::ListMidiPorts),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            text(
+                "Run an action when a controller plays a note or sets a controller, e.g. a pad \
+                 or button of a MIDI controller. The first input port whose name contains the \
+                 text above is listened to (empty listens to the first port) while a device is \
+                 connected and actions are not paused. Leave the channel empty to take any.",
+            )
+            .size(12)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(8);
+        if let Some(Err(e)) = &self.midi_input_ports {
+            let danger = self.active_theme.theme.palette().danger;
+            input = input.push(text(e).size(12).style(danger));
+        }
+
+        let mut list = column![].spacing(10);
+        if self.midi_triggers.is_empty() {
+            list = list.push(
+                text("No MIDI triggers yet.")
+                    .size(13)
+                    .style(self.color_text_muted()),
+            );
+        }
+        for t in &self.midi_triggers {
+            let id = t.id;
+            let edit = move |edit| Message::MidiTriggerEdited { id, edit };
+            let channel = t.channel.map(|c| c.to_string()).unwrap_or_default();
+            let content = column![
+                row![
+                    checkbox("", t.enabled).on_toggle(move |on| edit(MidiTriggerEdit::Enabled(on))),
+                    pick_list(
+                        vec![MidiInput::Note, MidiInput::ControlChange],
+                        Some(t.input),
+                        move |input| edit(MidiTriggerEdit::Input(input)),
+                    )
+                    .text_size(12),
+                    text_input("36", &t.number.to_string())
+                        .on_input(move |v| edit(MidiTriggerEdit::Number(v)))
+                        .width(Length::Fixed(60.0)),
+                    text("Channel").size(12).style(self.color_text_muted()),
+                    text_input("Any", &channel)
+                        .on_input(move |v| edit(MidiTriggerEdit::Channel(v)))
+                        .width(Length::Fixed(60.0)),
+                    horizontal_space(),
+                    button(text("Remove").size(12))
+                        .style(iced::theme::Button::Destructive)
+                        .on_press(Message::RemoveMidiTrigger(id)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                self.view_trigger_action(&t.action, move |e| edit(MidiTriggerEdit::Action(e))),
+            ]
+            .spacing(8);
+            list = list.push(container(content).padding(10).style(panel()));
+        }
+
+        column![
+            header,
+            container(input).padding(10).style(panel()),
+            h_divider(),
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_osc(&self) -> Element<'_, Message> {
+        let header = row![
+            text("OSC").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::AddOscTrigger),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseOsc),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let settings = &self.osc_settings;
+        let listener = column![
+            row![
+                checkbox("Listen for OSC", settings.enabled)
+                    .on_toggle(|v| Message::OscSettingsEdited(OscSettingsEdit::Enabled(v))),
+                text("UDP port").size(12).style(self.color_text_muted()),
+                text_input("9000", &self.edit_osc_port)
+                    .on_input(|v| Message::OscSettingsEdited(OscSettingsEdit::Port(v)))
+                    .on_submit(Message::OscSettingsEdited(OscSettingsEdit::ApplyPort))
+                    .width(Length::Fixed(80.0)),
+                checkbox("Reachable from other machines", settings.listen_on_network)
+                    .on_toggle(|v| Message::OscSettingsEdited(OscSettingsEdit::ListenOnNetwork(v))),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            text(
+                "Run an action when a message is sent to an address, e.g. from a lighting desk, \
+                 mixer or show-control app. In addresses, * matches any part of a segment and ? \
+                 one character. A trigger can store the message's first argument in a variable \
+                 for the action to use as {{name}}. Messages are handled while a device is \
+                 connected and actions are not paused (press Enter to apply a port change).",
+            )
+            .size(12)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(8);
+
+        let mut list = column![].spacing(10);
+        if self.osc_triggers.is_empty() {
+            list = list.push(
+                text("No OSC triggers yet.")
+                    .size(13)
+                    .style(self.color_text_muted()),
+            );
+        }
+        for t in &self.osc_triggers {
+            let id = t.id;
+            let edit = move |edit| Message::OscTriggerEdited { id, edit };
+            let content = column![
+                row![
+                    checkbox("", t.enabled).on_toggle(move |on| edit(OscTriggerEdit::Enabled(on))),
+                    text_input("/deck/scene/*", &t.address)
+                        .on_input(move |v| edit(OscTriggerEdit::Address(v))),
+                    text("Store first argument in")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    text_input("(none)", &t.variable)
+                        .on_input(move |v| edit(OscTriggerEdit::Variable(v)))
+                        .width(Length::Fixed(140.0)),
+                    button(text("Remove").size(12))
+                        .style(iced::theme::Button::Destructive)
+                        .on_press(Message::RemoveOscTrigger(id)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                self.view_trigger_action(&t.action, move |e| edit(OscTriggerEdit::Action(e))),
+            ]
+            .spacing(8);
+            list = list.push(container(content).padding(10).style(panel()));
+        }
+
+        column![
+            header,
+            container(listener).padding(10).style(panel()),
+            h_divider(),
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_webhooks(&self) -> Element<'_, Message> {
+        let header = row![
+            text("Webhooks").size(18),
+            horizontal_space(),
+            button(text("Add"))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::AddWebhook),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseWebhooks),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let settings = &self.webhook_settings;
+        let server = column![
+            row![
+                checkbox("Listen for webhooks", settings.enabled)
+                    .on_toggle(|v| Message::WebhookServerEdited(WebhookServerEdit::Enabled(v))),
+                text("Port").size(12).style(self.color_text_muted()),
+                text_input("8787", &self.edit_webhook_port)
+                    .on_input(|v| Message::WebhookServerEdited(WebhookServerEdit::Port(v)))
+                    .on_submit(Message::WebhookServerEdited(WebhookServerEdit::ApplyPort))
+                    .width(Length::Fixed(80.0)),
+                checkbox("Reachable from other machines", settings.listen_on_network)
+                    .on_toggle(|v| {
+                        Message::WebhookServerEdited(WebhookServerEdit::ListenOnNetwork(v))
+                    }),
+                checkbox("Remote-control API", settings.api)
+                    .on_toggle(|v| Message::WebhookServerEdited(WebhookServerEdit::Api(v))),
+                checkbox("Metrics", settings.metrics)
+                    .on_toggle(|v| Message::WebhookServerEdited(WebhookServerEdit::Metrics(v))),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                text("Token").size(12).style(self.color_text_muted()),
+                text(if settings.token.is_empty() {
+                    "(generated when the listener is enabled)"
+                } else {
+                    &settings.token
+                })
+                .size(13),
+                button(text("Regenerate").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::WebhookServerEdited(WebhookServerEdit::RegenerateToken)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            text(format!(
+                "Send the token as \"Authorization: Bearer <token>\". \
+                 Besides POST /hooks/<path> below: POST /keys/<index> presses a key of the \
+                 active profile, PUT /variables/<name> sets a variable to the request body \
+                 (DELETE clears it). With the remote-control API, companion apps can also \
+                 read the device, profiles and key images under /api and follow events on the \
+                 /api/events WebSocket. With metrics, GET /metrics reports action, render, \
+                 device write and plugin statistics for Prometheus. Webhooks run while a \
+                 device is connected; base URL: \
+                 http://127.0.0.1:{} (press Enter to apply a port change).",
+                settings.port,
+            ))
+            .size(12)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(8);
+
+        let mut list = column![].spacing(10);
+        if self.webhooks.is_empty() {
+            list = list.push(text("No webhooks yet.").size(13).style(self.color_text_muted()));
+        }
+        for w in &self.webhooks {
+            let id = w.id;
+            list = list.push(
+                container(
+                    column![
+                        row![
+                            text("POST /hooks/").size(13),
+                            text_input("build-failed", &w.path).on_input(move |v| {
+                                Message::WebhookEdited {
+                                    id,
+                                    edit: WebhookEdit::Path(v),
+                                }
+                            }),
+                            button(text("Remove").size(12))
+                                .style(iced::theme::Button::Destructive)
+                                .on_press(Message::RemoveWebhook(id)),
+                        ]
+                        .spacing(10)
+                        .align_items(Alignment::Center),
+                        self.view_trigger_action(&w.action, move |edit| Message::WebhookEdited {
+                            id,
+                            edit: WebhookEdit::Action(edit),
+                        }),
+                    ]
+                    .spacing(8),
+                )
+                .padding(10)
+                .style(panel()),
+            );
+        }
+
+        column![
+            header,
+            container(server).padding(10).style(panel()),
+            h_divider(),
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_history(&self) -> Element<'_, Message> {
+        let header = row![
+            text("History").size(18),
+            horizontal_space(),
+            text_input("Filter by control, action or error…", &self.activity_filter)
+                .on_input(Message::ActivityFilterChanged)
+                .width(Length::Fixed(280.0)),
+            checkbox("Failures only", self.activity_failures_only)
+                .on_toggle(Message::ActivityFailuresOnly),
+            button(text("Clear"))
+                .style(iced::theme::Button::Destructive)
+                .on_press_maybe((!self.activity.is_empty()).then_some(Message::ClearActivity)),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseHistory),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let filter = self.activity_filter.to_lowercase();
+        let matches = |e: &&ActivityEntry| {
+            (!self.activity_failures_only || e.error.is_some())
+                && (filter.is_empty()
+                    || [&e.control, &e.event, &e.binding]
+                        .into_iter()
+                        .chain(e.error.as_ref())
+                        .any(|s| s.to_lowercase().contains(&filter)))
+        };
+
+        let palette = self.active_theme.theme.palette();
+        let mut list = column![].spacing(6);
+        let mut shown = 0;
+        for e in self.activity.iter().rev().filter(matches) {
+            shown += 1;
+            let at = chrono::DateTime::from_timestamp_millis(e.at_ms as i64)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            let mut tags = vec![];
+            if e.test {
+                tags.push("test");
+            }
+            if e.dry_run {
+                tags.push("dry run");
+            }
+            let (mark, color) = match &e.error {
+                None => ("✓", palette.success),
+                Some(_) => ("✗", palette.danger),
+            };
+            let mut entry = column![row![
+                text(mark).size(13).style(color),
+                text(at).size(12).style(self.color_text_muted()),
+                text(format!("{} · {}", e.control, e.event)).size(13),
+                text(&e.binding).size(13),
+                horizontal_space(),
+                text(tags.join(", ")).size(12).style(self.color_text_muted()),
+                text(format!("{} ms", e.duration_ms)).size(12).style(self.color_text_muted()),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center)]
+            .spacing(2);
+            if let Some(err) = &e.error {
+                entry = entry.push(text(err).size(12).style(palette.danger));
+            }
+            list = list.push(container(entry).padding([6, 10]).style(panel()));
+        }
+        if shown == 0 {
+            let empty = if self.activity.is_empty() {
+                "Nothing has run yet. Every press, schedule and webhook that runs a binding \
+                 shows up here."
+            } else {
+                "No entries match the filter."
+            };
+            list = list.push(text(empty).size(13).style(self.color_text_muted()));
+        }
+
+        column![
+            header,
+            text(format!(
+                "The latest {} runs are kept, newest first.",
+                storage::activity::MAX_ENTRIES
+            ))
+            .size(12)
+            .style(self.color_text_muted()),
+            h_divider(),
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_stats(&self) -> Element<'_, Message> {
+        let header = row![
+            text("Usage").size(18),
+            horizontal_space(),
+            checkbox("Heatmap on the preview", self.usage_heatmap)
+                .on_toggle(Message::SetUsageHeatmap),
+            button(text("Reset"))
+                .style(iced::theme::Button::Destructive)
+                .on_press_maybe(
+                    self.profile
+                        .as_ref()
+                        .filter(|p| self.usage.contains_key(&p.id.0))
+                        .map(|_| Message::ResetUsage),
+                ),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseStats),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let Some(p) = &self.profile else {
+            let hint = text("Open a profile to see how its keys are used.")
+                .size(13)
+                .style(self.color_text_muted());
+            return column![header, h_divider(), hint].spacing(10).into();
+        };
+        let key_label = |index: u8| {
+            p.keys
+                .get(usize::from(index))
+                .map(|k| k.label.as_str())
+                .filter(|l| !l.is_empty())
+        };
+        let describe = |control: UsageControl| match control {
+            UsageControl::Key { index } => match key_label(index) {
+                Some(label) => format!("{control} ({label})"),
+                None => control.to_string(),
+            },
+            _ => control.to_string(),
+        };
+
+        let mut bindings = self.usage.get(&p.id.0).cloned().unwrap_or_default();
+        bindings.sort_by(|a, b| b.count.cmp(&a.count).then(a.control.cmp(&b.control)));
+        let most = bindings.first().map_or(0, |b| b.count);
+        let mut list = column![].spacing(6);
+        for b in &bindings {
+            let last = chrono::DateTime::from_timestamp_millis(b.last_at_ms as i64)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let share = if most == 0 {
+                0.0
+            } else {
+                b.count as f32 / most as f32
+            };
+            let line = row![
+                text(format!("{} · {}", describe(b.control), b.event))
+                    .size(13)
+                    .width(Length::FillPortion(3)),
+                iced::widget::progress_bar(0.0..=1.0, share)
+                    .height(Length::Fixed(6.0))
+                    .width(Length::FillPortion(2)),
+                text(format!("{}×", b.count)).size(13),
+                text(format!("avg {} ms · max {} ms", b.average_ms(), b.max_ms))
+                    .size(12)
+                    .style(self.color_text_muted()),
+                text(format!("last {last}"))
+                    .size(12)
+                    .style(self.color_text_muted()),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+            list = list.push(container(line).padding([6, 10]).style(panel()));
+        }
+        if bindings.is_empty() {
+            list = list.push(
+                text("None of this profile's keys, dials or touch strip gestures has run yet.")
+                    .size(13)
+                    .style(self.color_text_muted()),
+            );
+        }
+
+        let used = storage::usage::key_counts(&bindings);
+        let unused: Vec<String> = p
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| k.action.is_some())
+            .filter_map(|(i, _)| u8::try_from(i).ok())
+            .filter(|i| !used.contains_key(i))
+            .map(|i| describe(UsageControl::Key { index: i }))
+            .collect();
+        if !unused.is_empty() && !bindings.is_empty() {
+            list = list.push(
+                text(format!("Bound but never used: {}", unused.join(", ")))
+                    .size(12)
+                    .style(self.color_text_muted()),
+            );
+        }
+
+        column![
+            header,
+            text(format!(
+                "Runs of {}'s bindings from the deck, most used first. Schedules, webhooks, \
+                 file and process watchers, MIDI and OSC triggers, tests and dry runs are not \
+                 counted.",
+                p.name
+            ))
+            .size(12)
+            .style(self.color_text_muted()),
+            h_divider(),
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_device_test(&self) -> Element<'_, Message> {
+        let t = &self.device_test;
+        let header = row![
+            text("Device Test").size(18),
+            horizontal_space(),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseDeviceTest),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let palette = self.active_theme.theme.palette();
+        let muted = self.color_text_muted();
+        let mut body = column![].spacing(10);
+        if let Some(err) = &t.error {
+            body = body.push(text(err).size(13).style(palette.danger));
+        }
+
+        let showing = match t.pattern().filter(|_| t.cycling) {
+            Some(pattern) => format!("Showing: {}", pattern.label()),
+            None if self.connected.is_none() => "Connect a device to test its keys.".to_string(),
+            None => "The keys show the profile.".to_string(),
+        };
+        let toggle = if t.cycling {
+            "Stop test patterns"
+        } else {
+            "Cycle test patterns"
+        };
+        let writes = match t.write_times() {
+            Some(w) => format!(
+                "Key image writes: last {}, min {}, avg {}, max {} ({} writes). All keys: {}.",
+                device_test::millis(w.last),
+                device_test::millis(w.min),
+                device_test::millis(w.avg),
+                device_test::millis(w.max),
+                w.count,
+                t.last_frame.map(device_test::millis).unwrap_or_default(),
+            ),
+            None => "No key images written yet.".to_string(),
+        };
+        body = body.push(
+            container(
+                column![
+                    row![
+                        text("Keys").size(15),
+                        horizontal_space(),
+                        text(showing).size(13).style(muted),
+                        button(text(toggle))
+                            .style(iced::theme::Button::Primary)
+                            .on_press_maybe(
+                                self.connected
+                                    .as_ref()
+                                    .map(|_| Message::SetKeyTestCycling(!t.cycling)),
+                            ),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                    text(writes).size(13),
+                    text(
+                        "Every key shows each test pattern in turn instead of the profile, \
+                         until the test stops. Write times run from handing a key image to \
+                         the device until it is sent.",
+                    )
+                    .size(12)
+                    .style(muted),
+                ]
+                .spacing(8),
+            )
+            .padding(10)
+            .style(panel()),
+        );
+
+        let mut reports = column![].spacing(2);
+        for report in t.reports().rev() {
+            reports = reports.push(
+                row![
+                    text(format!("+{:.3}s", report.elapsed.as_secs_f64()))
+                        .size(12)
+                        .style(muted),
+                    text(device_test::format_report(report))
+                        .size(12)
+                        .font(iced::Font::MONOSPACE),
+                ]
+                .spacing(10),
+            );
+        }
+        if t.reports().next().is_none() {
+            let empty = if t.monitoring() {
+                "No reports yet. Press a key, turn a dial or touch the strip."
+            } else {
+                "Pick a device to read its input reports."
+            };
+            reports = reports.push(text(empty).size(13).style(muted));
+        }
+        body = body.push(
+            container(
+                column![
+                    row![
+                        text("Input reports").size(15),
+                        horizontal_space(),
+                        pick_list(
+                            t.interfaces.clone(),
+                            t.selected.clone(),
+                            Message::HidInterfacePicked,
+                        )
+                        .placeholder("No Stream Deck found"),
+                        button(text("Refresh"))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::RefreshHidInterfaces),
+                        button(text("Clear"))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::ClearHidReports),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                    text(
+                        "Raw reports as the device sends them, newest first. They are read \
+                         through a second handle on the device; on Linux the app keeps \
+                         getting every press meanwhile.",
+                    )
+                    .size(12)
+                    .style(muted),
+                    scrollable(reports).height(Length::Fill),
+                ]
+                .spacing(8),
+            )
+            .padding(10)
+            .style(panel())
+            .height(Length::Fill),
+        );
+
+        column![header, h_divider(), body.height(Length::Fill)]
+            .spacing(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn view_marketplace(&self) -> Element<'_, Message> {
+        if self.marketplace.tab == MarketplaceTab::Profiles {
+            return self.view_profile_gallery();
+        }
+        let header = self.view_marketplace_header();
+
+        let selected = self.current_marketplace_source().cloned();
+
+        let source_picker = pick_list(
+            self.marketplace.sources.clone(),
+            selected.clone(),
+            Message::MarketplaceSourcePicked,
+        );
+
+        let url_row = row![
+            text("Marketplace").size(12).style(self.color_text_muted()),
+            source_picker,
+            horizontal_space(),
+            button(text("Refresh"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::MarketplaceRefresh),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let search = text_input("Search plugins…", &self.marketplace.query)
+            .on_input(Message::MarketplaceSearchChanged);
+
+        let mut categories = vec![ALL_CATEGORIES.to_string()];
+        categories.extend(self.marketplace.categories());
+        let filters = row![
+            pick_list(
+                categories,
+                Some(
+                    self.marketplace
+                        .category
+                        .clone()
+                        .unwrap_or_else(|| ALL_CATEGORIES.to_string()),
+                ),
+                Message::MarketplaceCategoryPicked,
+            ),
+            checkbox("Compatible only", self.marketplace.compatible_only)
+                .on_toggle(Message::MarketplaceCompatibleOnlyToggled),
+        ]
+        .spacing(12)
+        .align_items(Alignment::Center);
+
+        let list_iter = self
+            .marketplace
+            .plugins
+            .iter()
+            .filter(|p| self.marketplace.matches(p));
+        let matches = list_iter.collect::<Vec<_>>();
+        let total_matches = matches.len();
+        let page_count = (total_matches + MARKETPLACE_PAGE_SIZE - 1) / MARKETPLACE_PAGE_SIZE;
+        let page = self.marketplace.page.min(page_count.saturating_sub(1));
+        let start = page.saturating_mul(MARKETPLACE_PAGE_SIZE);
+        let end = (start + MARKETPLACE_PAGE_SIZE).min(total_matches);
+        let shown = if total_matches == 0 { 0 } else { end.saturating_sub(start) };
+
+        let mut list = column![].spacing(10);
+        for p in matches.into_iter().skip(start).take(MARKETPLACE_PAGE_SIZE) {
+            let icon: Element<Message> = if let Some(src) = &selected {
+                let key = format!("{}|{}", src.index_url, p.id);
+                if let Some(handle) = self.marketplace.icon_cache.get(&key) {
+                    image(handle.clone())
+                        .width(Length::Fixed(32.0))
+                        .height(Length::Fixed(32.0))
+                        .into()
+                } else {
+                    container(text(""))
+                        .width(Length::Fixed(32.0))
+                        .height(Length::Fixed(32.0))
+                        .style(panel())
+                        .into()
+                }
+            } else {
+                container(text(""))
+                    .width(Length::Fixed(32.0))
+                    .height(Length::Fixed(32.0))
+                    .style(panel())
+                    .into()
+            };
+
+            let mut body = column![text(&p.name).size(14)]
+                .spacing(4)
+                .push(text(p.id.clone()).size(12).style(self.color_text_muted()));
+
+            if !p.version.is_empty() {
+                body = body.push(text(format!("v{}", p.version)).size(12).style(self.color_text_muted()));
+            }
+            if !p.description.is_empty() {
+                body = body.push(text(p.description.clone()).size(12).style(self.color_text_muted()));
+            }
+            let compat = p.compatibility();
+            if let Some(badge) = compatibility_badge(p, compat) {
+                body = body.push(badge);
+            }
+
+            let is_installed = self.plugins.iter().any(|ip| ip.manifest.id == p.id);
+            let is_installing = self
+                .marketplace
+                .installing
+                .as_deref()
+                .is_some_and(|id| id == p.id);
+            let is_selected = self
+                .marketplace
+                .selected
+                .as_ref()
+                .is_some_and(|sel| sel.id == p.id);
+            let can_install_direct = selected
+                .as_ref()
+                .and_then(|src| resolve_marketplace_download_url(src, p))
+                .is_some();
+            let can_install_repo = p
+                .repository
+                .as_deref()
+                .and_then(parse_github_owner_repo)
+                .is_some();
+            let can_install = can_install_direct || can_install_repo;
+
+            let install_btn = if is_installed {
+                button(text("Installed")).style(iced::theme::Button::Secondary)
+            } else if is_installing {
+                button(text("Installing…")).style(iced::theme::Button::Secondary)
+            } else if compat.is_incompatible() {
+                button(text("Incompatible")).style(iced::theme::Button::Secondary)
+            } else if can_install {
+                button(text("Install"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::MarketplaceInstall(p.clone()))
+            } else {
+                button(text("No download")).style(iced::theme::Button::Secondary)
+            };
+
+            let select_area: Element<Message> = mouse_area(
+                container(
+                    row![icon, container(body).width(Length::Fill)]
+                        .spacing(12)
+                        .align_items(Alignment::Center),
+                )
+                .width(Length::Fill),
+            )
+            .on_press(Message::MarketplaceSelect(p.clone()))
+            .into();
+
+            list = list.push(
+                container(
+                    row![
+                        container(select_area).width(Length::Fill),
+                        install_btn
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                )
+                .padding(10)
+                .style(if is_selected {
+                    callout_card(self.active_theme.radii.card)
+                } else {
+                    panel()
+                }),
+            );
+        }
+
+        let status: Element<Message> = if self.marketplace.loading {
+            text("Loading…").style(self.color_text_muted()).into()
+        } else if let Some(err) = &self.marketplace.error {
+            if let Some(p) = &self.marketplace.pending_override {
+                row![
+                    text(format!("Error: {err}")),
+                    horizontal_space(),
+                    button(text("Install anyway"))
+                        .style(iced::theme::Button::Destructive)
+                        .on_press(Message::MarketplaceInstallAnyway(p.clone())),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center)
+                .into()
+            } else {
+                text(format!("Error: {err}")).into()
+            }
+        } else if self.marketplace.plugins.is_empty() {
+            text("No plugins found.").style(self.color_text_muted()).into()
+        } else if self.marketplace.offline {
+            text("Offline: showing cached marketplace data.")
+                .style(self.color_text_muted())
+                .into()
+        } else {
+            text("").into()
+        };
+
+        let footer: Element<Message> = if total_matches > 0 {
+            row![
+                text(format!(
+                    "Page {} / {}  •  Showing {}–{} of {}",
+                    page + 1,
+                    page_count.max(1),
+                    start + 1,
+                    start + shown,
+                    total_matches
+                ))
+                .style(self.color_text_muted()),
+                horizontal_space(),
+                button(text("Prev"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::MarketplacePrevPage),
+                button(text("Next"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::MarketplaceNextPage),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into()
+        } else {
+            text("").into()
+        };
+
+        let content_left = column![
+            header,
+            h_divider(),
+            url_row,
+            search,
+            filters,
+            status,
+            scrollable(list).height(Length::Fill),
+            footer
+        ]
+        .spacing(10)
+        .height(Length::Fill);
+
+        let details = self.view_marketplace_details_panel(selected.as_ref());
+
+        row![
+            container(content_left)
+            .padding(12)
+            .width(Length::Fill)
+            .height(Length::Fill)
+                .style(panel()),
+            v_divider(),
+            container(details)
+                .padding(12)
+                .width(Length::Fixed(420.0))
+                .height(Length::Fill)
+                .style(panel()),
+        ]
+        .spacing(12)
+        .into()
+    }
+
+    fn view_marketplace_header(&self) -> Element<'_, Message> {
+        let tab = |label: &'static str, tab: MarketplaceTab| {
+            let style = if self.marketplace.tab == tab {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Secondary
+            };
+            button(text(label))
+                .style(style)
+                .on_press(Message::MarketplaceTabPicked(tab))
+        };
+        row![
+            text("Marketplace").size(18),
+            tab("Plugins", MarketplaceTab::Plugins),
+            tab("Profiles", MarketplaceTab::Profiles),
+            horizontal_space(),
+            button(text("Back"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseMarketplace),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into()
+    }
+
+    /// The Profiles tab: the gallery's profiles, and the selected one next to them.
+    fn view_profile_gallery(&self) -> Element<'_, Message> {
+        let gallery = &self.marketplace.gallery;
+        let url_row = row![
+            text_input("Profile gallery index URL", &self.edit_profile_gallery_url)
+                .on_input(Message::ProfileGalleryUrlChanged)
+                .on_submit(Message::ProfileGalleryRefresh),
+            button(text("Load"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::ProfileGalleryRefresh),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let search = text_input("Search profiles…", &gallery.query)
+            .on_input(Message::ProfileGallerySearchChanged);
+
+        let mut list = column![].spacing(10);
+        for p in gallery.profiles.iter().filter(|p| gallery.matches(p)) {
+            let mut body = column![text(&p.name).size(14)].spacing(4);
+            let mut byline: Vec<String> = p.author.iter().map(|a| format!("by {a}")).collect();
+            if !p.version.is_empty() {
+                byline.push(format!("v{}", p.version));
+            }
+            if let Some(keys) = p.key_count {
+                byline.push(format!("{keys} keys"));
+            }
+            if !byline.is_empty() {
+                let byline = text(byline.join("  •  ")).size(12);
+                body = body.push(byline.style(self.color_text_muted()));
+            }
+            if !p.description.is_empty() {
+                let description = text(&p.description).size(12);
+                body = body.push(description.style(self.color_text_muted()));
+            }
+            let style = if gallery.selected.as_ref().is_some_and(|s| s.id == p.id) {
+                callout_card(self.active_theme.radii.card)
+            } else {
+                panel()
+            };
+            let card = container(body).width(Length::Fill).padding(10).style(style);
+            let select = Message::ProfileGallerySelect(p.clone());
+            list = list.push(mouse_area(card).on_press(select));
+        }
+
+        let status: Element<Message> = if gallery.loading {
+            text("Loading…").style(self.color_text_muted()).into()
+        } else if let Some(err) = &gallery.error {
+            text(format!("Error: {err}")).into()
+        } else if gallery.profiles.is_empty() {
+            text("No profiles found.")
+                .style(self.color_text_muted())
+                .into()
+        } else if gallery.offline {
+            text("Offline: showing cached gallery data.")
+                .style(self.color_text_muted())
+                .into()
+        } else {
+            text("").into()
+        };
+
+        let content_left = column![
+            self.view_marketplace_header(),
+            h_divider(),
+            url_row,
+            search,
+            status,
+            scrollable(list).height(Length::Fill),
+        ]
+        .spacing(10)
+        .height(Length::Fill);
+
+        row![
+            container(content_left)
+                .padding(12)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .style(panel()),
+            v_divider(),
+            container(scrollable(self.view_profile_gallery_details()))
+                .padding(12)
+                .width(Length::Fixed(420.0))
+                .height(Length::Fill)
+                .style(panel()),
+        ]
+        .spacing(12)
+        .into()
+    }
+
+    fn view_profile_gallery_details(&self) -> Element<'_, Message> {
+        let gallery = &self.marketplace.gallery;
+        let Some(entry) = &gallery.selected else {
+            return text("Select a profile to preview it.")
+                .style(self.color_text_muted())
+                .into();
+        };
+        let mut col = column![text(&entry.name).size(18)].spacing(10);
+        if !entry.description.is_empty() {
+            col = col.push(text(&entry.description).size(13));
+        }
+        let profile = match &gallery.preview {
+            None => {
+                let downloading = text("Downloading…").style(self.color_text_muted());
+                return col.push(downloading).into();
+            }
+            Some(Err(e)) => return col.push(text(format!("Error: {e}"))).into(),
+            Some(Ok(p)) => p,
+        };
+        col = col.push(view_profile_preview(profile));
+
+        let device_keys = self.connected.as_ref().map(|c| c.key_count);
+        if device_keys.is_some_and(|n| n != profile.key_count) {
+            let note = format!(
+                "Made for devices with {} keys; it is listed while one is connected.",
+                profile.key_count
+            );
+            col = col.push(text(note).size(12).style(self.color_text_muted()));
+        }
+
+        let required = openaction::profile_gallery::required_plugins(entry, profile);
+        if !required.is_empty() {
+            let mut plugins =
+                column![text("Plugins").size(12).style(self.color_text_muted())].spacing(4);
+            for id in &required {
+                let status = match self.gallery_plugin_download(id) {
+                    Ok(None) => "installed",
+                    Ok(Some(_)) => "installed on import",
+                    Err(why) => why,
+                };
+                let name = self
+                    .marketplace
+                    .plugins
+                    .iter()
+                    .find(|p| &p.id == id)
+                    .map_or(id.as_str(), |p| p.name.as_str());
+                plugins = plugins.push(text(format!("{name} — {status}")).size(12));
+            }
+            col = col.push(plugins);
+        }
+
+        let keys = profile.keys.iter().map(|k| &k.action);
+        let dials = profile.dials.iter().flat_map(|d| [&d.press, &d.rotate]);
+        let runs_commands = keys
+            .chain(dials)
+            .chain(profile.touch_strip.bindings())
+            .flatten()
+            .any(ActionBinding::runs_commands);
+        if runs_commands {
+            let danger = self.active_theme.theme.palette().danger;
+            let warning = "Some controls of this profile run shell commands or scripts. Check \
+                           them before pressing them.";
+            col = col.push(text(warning).size(12).style(danger));
+        }
+
+        let import = if gallery.importing {
+            button(text("Importing…"))
+        } else if gallery.imported {
+            button(text("Imported"))
+        } else {
+            button(text("Import")).on_press(Message::ProfileGalleryImport)
+        };
+        col.push(import.style(iced::theme::Button::Primary)).into()
+    }
+
+    fn view_marketplace_details_panel(
+        &self,
+        source: Option<&MarketplaceSource>,
+    ) -> Element<'_, Message> {
+        let header = row![
+            text("Details").size(16),
+            horizontal_space(),
+        ]
+        .align_items(Alignment::Center);
+
+        let Some(p) = self.marketplace.selected.as_ref() else {
+            return container(column![
+                header,
+                h_divider(),
+                text("Click a plugin to view details.")
+                    .size(13)
+                    .style(self.color_text_muted()),
+            ])
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into();
+        };
+
+        let icon: Element<Message> = if let Some(src) = source {
+            let key = format!("{}|{}", src.index_url, p.id);
+            if let Some(handle) = self.marketplace.icon_cache.get(&key) {
+                image(handle.clone())
+                    .width(Length::Fixed(96.0))
+                    .height(Length::Fixed(96.0))
+                    .into()
+            } else {
+                container(text(""))
+                    .width(Length::Fixed(96.0))
+                    .height(Length::Fixed(96.0))
+            .style(panel())
+                    .into()
+            }
+        } else {
+            container(text(""))
+                .width(Length::Fixed(96.0))
+                .height(Length::Fixed(96.0))
+                .style(panel())
+                .into()
+        };
+
+        let mut meta = column![
+            row![icon, column![
+                text(&p.name).size(18),
+                text(p.id.clone()).size(12).style(self.color_text_muted()),
+                if p.version.is_empty() {
+                    text("").size(1)
+                } else {
+                    text(format!("v{}", p.version)).size(12).style(self.color_text_muted())
+                },
+            ]
+            .spacing(4)]
+            .spacing(12)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(10);
+
+        if let Some(author) = &p.author {
+            if !author.trim().is_empty() {
+                meta = meta.push(text(format!("Author: {author}")).size(12).style(self.color_text_muted()));
+            }
+        }
+        if let Some(home) = &p.homepage {
+            if !home.trim().is_empty() {
+                meta = meta.push(text(format!("Homepage: {home}")).size(12).style(self.color_text_muted()));
+            }
+        }
+        if !p.description.trim().is_empty() {
+            meta = meta.push(text(p.description.clone()).size(13));
+        }
+        if !p.categories.is_empty() {
+            meta = meta.push(
+                text(format!("Categories: {}", p.categories.join(", ")))
+                    .size(12)
+                    .style(self.color_text_muted()),
+            );
+        }
+        if let Some(badge) = compatibility_badge(p, p.compatibility()) {
+            meta = meta.push(badge);
+        }
+
+        // Extra details derived from repository (matches Rivul marketplace behavior).
+        if let Some(d) = self.marketplace.details_cache.get(&p.id) {
+            if let Some(repo) = d.repository.as_deref() {
+                if !repo.trim().is_empty() {
+                    meta = meta.push(text(format!("Repository: {repo}")).size(12).style(self.color_text_muted()));
+                }
+            }
+            if let Some(e) = d.github_error.as_deref() {
+                meta = meta.push(text(e).size(12).style(self.color_text_muted()));
+            }
+            if let Some(dl) = d.total_downloads {
+                meta = meta.push(
+                    text(format!("Total downloads (GitHub releases): {dl}"))
+                        .size(12)
+                        .style(self.color_text_muted()),
+                );
+            }
+            if let Some(url) = d.resolved_download_url.as_deref() {
+                meta = meta.push(
+                    text(format!("Resolved download: {url}"))
+                        .size(12)
+                        .style(self.color_text_muted()),
+                );
+            }
+        }
+
+        let readme_title = text("README").size(13).style(self.color_text_muted());
+        let readme_body: Element<Message> = if let Some(d) = self.marketplace.details_cache.get(&p.id) {
+            if let Some(md) = d.readme_md.as_deref() {
+                container(self.render_markdown(md, source))
+                    .padding(10)
+                    .width(Length::Fill)
+                    .style(panel())
+                    .into()
+            } else {
+                text("Loading README…").size(12).style(self.color_text_muted()).into()
+            }
+        } else if p.repository.is_some() {
+            text("Loading README…").size(12).style(self.color_text_muted()).into()
+        } else {
+            text("No repository/README available.").size(12).style(self.color_text_muted()).into()
+        };
+
+        let screenshots_title = text("Images").size(13).style(self.color_text_muted());
+        let mut shots = column![].spacing(10);
+        if let Some(src) = source {
+            // Prefer images discovered from README; fall back to marketplace-provided screenshots.
+            let mut urls: Vec<String> = vec![];
+            if let Some(d) = self.marketplace.details_cache.get(&p.id) {
+                urls.extend(d.image_urls.iter().cloned());
+            }
+            urls.extend(p.images.iter().cloned());
+
+            let mut shown_any = false;
+            for raw in urls.iter().take(12) {
+                let Some(url) = resolve_marketplace_asset_url(src, raw) else {
+                    continue;
+                };
+                shown_any = true;
+                let key = format!("img|{}|{}|{}", src.index_url, p.id, url);
+                if let Some(handle) = self.marketplace.image_cache.get(&key) {
+                    shots = shots.push(image(handle.clone()).width(Length::Fill));
+                } else if let Some(handle) = self.marketplace.svg_cache.get(&key) {
+                    shots = shots.push(
+                        iced::widget::svg(handle.clone())
+                            .width(Length::Fill)
+                            .height(Length::Shrink)
+                        ,
+                    );
+                } else if let Some(err) = self.marketplace.image_error_cache.get(&key) {
+                    shots = shots.push(
+                        row![
+                            text("Failed to load image.")
+                                .style(iced::theme::Text::Color(self.color_text_muted())),
+                            horizontal_space(),
+                            button(text("Open"))
+                                .style(iced::theme::Button::Secondary)
+                                .on_press(Message::OpenUrl(url.clone())),
+                        ]
+                        .align_items(Alignment::Center)
+                        .spacing(10),
+                    );
+                    shots = shots.push(
+                        text(err.clone())
+                            .size(12)
+                            .style(iced::theme::Text::Color(self.color_text_muted())),
+                    );
+                } else {
+                    shots = shots.push(
+                        container(text("Loading…").style(self.color_text_muted()))
+                            .padding(10)
+                            .width(Length::Fill)
+                            .style(panel()),
+                    );
+                }
+            }
+            if !shown_any {
+                shots = shots.push(text("No images found.").size(12).style(self.color_text_muted()));
+            }
+        }
+
+        container(
+            column![
+                header,
+                h_divider(),
+                scrollable(
+                    column![meta, h_divider(), readme_title, readme_body, h_divider(), screenshots_title, shots]
+                        .spacing(12),
+                )
+                    .height(Length::Fill),
+            ]
+            .spacing(10),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+            .into()
+    }
+
+    fn view_preview_panel(&self) -> Element<'_, Message> {
+        let selected = self
+            .selected_control
+            .map(|s| match s {
+                SelectedControl::Key(k) => format!("Selected: Key {k}"),
+                SelectedControl::Dial(d) => format!("Selected: Dial {d}"),
+                SelectedControl::TouchStrip => "Selected: Touch strip".to_string(),
+            })
+            .unwrap_or_else(|| "Selected: —".to_string());
+
+        let title = row![
+            column![
+                text("Preview").size(16),
+                text(selected)
+                    .size(12)
+                    .style(self.color_text_muted()),
+            ]
+            .spacing(2),
+            horizontal_space(),
+            checkbox("Device images", self.live_preview)
+                .text_size(12)
+                .on_toggle(Message::LivePreviewToggled),
+            self.view_base_profile_picker(),
+            self.view_brightness_control_compact(),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(12);
+
+        let body: Element<Message> = match &self.connected {
+            None => container(
+                column![
+                    text("Connect a device to see a preview.").size(16),
+                    text("Tip: use the left sidebar to select a device and click Connect.")
+                        .size(13)
+                        .style(self.color_text_muted()),
+                ]
+                .spacing(6),
+            )
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+            Some(c) => container(self.view_deck_preview(&self.layout_capabilities(c), &c.pressed))
+                .center_x()
+                .center_y()
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+        };
+
+        container(column![title, horizontal_rule(1), body].spacing(10))
+            .padding(12)
+            .width(Length::Fill)
+            .height(Length::FillPortion(2))
+            .style(panel())
+            .into()
+    }
+
+    fn view_base_profile_picker(&self) -> Element<'_, Message> {
+        let Some(p) = &self.profile else {
+            return text("").into();
+        };
+        let mut choices = vec![BaseProfileChoice::None];
+        choices.extend(
+            self.profile_choices
+                .iter()
+                .filter(|c| c.id != p.id)
+                .cloned()
+                .map(BaseProfileChoice::Profile),
+        );
+        let selected = match p.base_profile {
+            None => Some(BaseProfileChoice::None),
+            Some(id) => choices
+                .iter()
+                .find(|c| matches!(c, BaseProfileChoice::Profile(b) if b.id == id))
+                .cloned(),
+        };
+        pick_list(choices, selected, Message::BaseProfilePicked)
+            .text_size(12)
+            .into()
+    }
+
+    fn view_brightness_control_compact(&self) -> Element<'_, Message> {
+        let Some(c) = &self.connected else {
+            return text("").into();
+        };
+
+        let profile_brightness = self.profile.as_ref().is_some_and(|p| p.brightness.is_some());
+        let toggle_profile_brightness =
+            self.profile.is_some().then_some(Message::ProfileBrightnessToggled);
+        row![
+            text(format!("Brightness {}%", c.brightness)).size(12),
+            slider(0..=100, c.brightness as i32, Message::BrightnessChanged)
+                .on_release(Message::BrightnessReleased)
+                .width(Length::Fixed(160.0)),
+            checkbox("For this profile", profile_brightness)
+                .on_toggle_maybe(toggle_profile_brightness)
+                .size(14)
+                .text_size(12),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    fn view_inspector_panel(&self) -> Element<'_, Message> {
+        let header = text("Inspector").size(16);
+
+        let body = match (self.connected.as_ref(), self.selected_control) {
+            (None, _) => text("Connect a device to inspect controls.").into(),
+            (Some(_), None) => text("Click a key/dial/touch strip in the preview to edit it.").into(),
+            (Some(_), Some(_)) if self.selected_keys.len() > 1 => self.view_bulk_inspector(),
+            (Some(c), Some(sel)) => match sel {
+                SelectedControl::Key(idx) => self.view_key_inspector(c, idx),
+                SelectedControl::Dial(idx) => self.view_dial_inspector(idx),
+                SelectedControl::TouchStrip => self.view_touch_strip_inspector(),
+            },
+        };
+
+        container(
+            column![
+                header,
+                horizontal_rule(1),
+                scrollable(body).height(Length::Fill)
+            ]
+            .spacing(10),
+        )
+        .padding(12)
+        .width(Length::Fill)
+        .height(Length::FillPortion(1))
+        .style(panel())
+        .into()
+    }
+
+    fn view_bulk_inspector(&self) -> Element<'_, Message> {
+        let appearances: Vec<_> = self
+            .profile
+            .iter()
+            .flat_map(|p| p.keys.iter().enumerate())
+            .filter(|(idx, _)| self.selected_keys.contains(idx))
+            .map(|(_, k)| &k.appearance)
+            .collect();
+        // Shown only when every selected key agrees.
+        let text_size = appearances
+            .first()
+            .map(|a| a.text_size)
+            .filter(|size| appearances.iter().all(|a| a.text_size == *size))
+            .map(TextSizeChoice::of);
+
+        column![
+            text(format!("{} keys selected", self.selected_keys.len())).size(20),
+            text("Shift-click keys to add or remove them; a plain click selects one key again.")
+                .size(13)
+                .style(self.color_text_muted()),
+            horizontal_rule(1),
+            text("Action").size(14),
+            button(text("Clear actions"))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::ClearSelectedActions),
+            horizontal_rule(1),
+            text("Background Color").size(14),
+            row![
+                button(text("Pick Color"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::OpenColorPicker),
+                button(text("Clear"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::BgRgbChanged(String::new())),
+            ]
+            .spacing(8),
+            text("Text size").size(14),
+            pick_list(TextSizeChoice::ALL.to_vec(), text_size, Message::TextSizePicked),
+            horizontal_rule(1),
+            button(text("Save"))
+                .on_press(Message::SaveProfile)
+                .style(iced::theme::Button::Primary),
+        ]
+        .spacing(6)
+        .into()
+    }
+
+    fn view_key_inspector(&self, c: &ConnectedUi, idx: usize) -> Element<'_, Message> {
+        let is_down = c.pressed.get(idx).copied().unwrap_or(false);
+
+        let mut col = column![
+            text(format!("Key {idx}")).size(20),
+            text(if is_down {
+                "State: Pressed"
+            } else {
+                "State: Released"
+            })
+            .size(13)
+            .style(self.color_text_muted()),
+        ]
+        .spacing(6);
+
+        col = col.push(
+            row![
+                button(text("Copy").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::KeyEdited(KeyEdit::Copy)),
+                button(text("Cut").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::KeyEdited(KeyEdit::Cut)),
+                button(text("Paste").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press_maybe(
+                        self.copied_key.is_some().then_some(Message::KeyEdited(KeyEdit::Paste)),
+                    ),
+                button(text("Duplicate").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::KeyEdited(KeyEdit::Duplicate)),
+            ]
+            .spacing(6),
+        );
+
+        let key = self.profile.as_ref().and_then(|p| p.keys.get(idx));
+        if key.is_some_and(|k| k.is_inherited()) {
+            col = col.push(
+                text("Inherited from the base profile; editing it overrides the base key.")
+                    .size(12)
+                    .style(self.color_text_muted()),
+            );
+        } else if key.is_some_and(|k| k.base.is_some()) {
+            col = col.push(
+                row![
+                    text("Overrides the base profile's key.")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    button(text("Revert").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::RevertKeyToBase(idx)),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        col = col.push(horizontal_rule(1));
+
+        col = col.push(text("Label").size(14));
+        col = col.push(
+            text_input("Label", &self.edit_label)
+                .id(iced::widget::text_input::Id::new(INSPECTOR_LABEL))
+                .on_input(Message::LabelChanged),
+        );
+
+        col = col.push(horizontal_rule(1));
+
+        col = col.push(text("Hotkey").size(14));
+        col = col.push(self.view_hotkey_editor(idx));
+
+        col = col.push(horizontal_rule(1));
+
+        col = col.push(text("Action").size(14));
+        col = col.push(self.view_action_editor());
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Display (LCD)").size(14));
+        col = col.push(self.view_display_editor());
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(
+            row![button(text("Save"))
+                .on_press(Message::SaveProfile)
+                .style(iced::theme::Button::Primary),]
+            .spacing(8),
+        );
+
+        col.into()
+    }
+
+    fn view_hotkey_editor(&self, idx: usize) -> Element<'_, Message> {
+        if self.hotkeys.is_none() {
+            return text("Global hotkeys are not available on this system (Linux needs X11).")
+                .size(12)
+                .style(self.color_text_muted())
+                .into();
+        }
+        let current = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(idx))
+            .and_then(|k| k.hotkey.as_deref());
+        let record = if self.recording_hotkey {
+            button(text("Press a shortcut… (Esc cancels)").size(12))
+                .style(iced::theme::Button::Primary)
+        } else {
+            button(text("Record").size(12))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::RecordHotkey)
+        };
+        let mut clear = button(text("Clear").size(12)).style(iced::theme::Button::Secondary);
+        if current.is_some() {
+            clear = clear.on_press(Message::ClearHotkey);
+        }
+        row![
+            text(current.unwrap_or("None")).size(13).width(Length::Fill),
+            record,
+            clear,
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    fn view_dial_inspector(&self, idx: usize) -> Element<'_, Message> {
+        let mut col = column![text(format!("Dial {idx}")).size(20)]
+            .spacing(6);
 
-            Some(
-                container(
-                    container(
-                        color_picker(
-                            true,
-                            color,
-                            button(text("Cancel")).on_press(Message::OpenColorPicker),
-                            Message::OpenColorPicker,
-                            Message::ColorPicked,
-                        ),
-                    )
-                    .padding(20)
-                    .style(iced::theme::Container::Box),
-                )
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .align_x(Horizontal::Left)
-                .align_y(Vertical::Top)
-                .padding(iced::Padding::from([100.0, 0.0, 0.0, 350.0]))
-                .into(),
-            )
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Label").size(14));
+        col = col.push(text_input("Label", &self.edit_label).on_input(Message::LabelChanged));
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Binding target").size(14));
+        col = col.push(pick_list(
+            vec![BindingTarget::DialPress, BindingTarget::DialRotate],
+            Some(self.selected_binding_target),
+            Message::BindingTargetPicked,
+        ));
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Action").size(14));
+        col = col.push(self.view_action_editor());
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Display (LCD)").size(14));
+        col = col.push(self.view_display_editor());
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(
+            row![button(text("Save"))
+                .on_press(Message::SaveProfile)
+                .style(iced::theme::Button::Primary),]
+            .spacing(8),
+        );
+
+        col.into()
+    }
+
+    fn view_touch_strip_inspector(&self) -> Element<'_, Message> {
+        let mut col = column![text("Touch strip").size(20)].spacing(6);
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Binding target").size(14));
+        col = col.push(pick_list(
+            vec![
+                BindingTarget::TouchTap,
+                BindingTarget::TouchLongPress,
+                BindingTarget::TouchDrag,
+                BindingTarget::TouchDragLeft,
+                BindingTarget::TouchDragRight,
+                BindingTarget::TouchSwipeLeft,
+                BindingTarget::TouchSwipeRight,
+            ],
+            Some(self.selected_binding_target),
+            Message::BindingTargetPicked,
+        ));
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Action").size(14));
+        col = col.push(self.view_action_editor());
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(text("Display (LCD)").size(14));
+        col = col.push(self.view_display_editor());
+
+        col = col.push(horizontal_rule(1));
+        col = col.push(
+            row![button(text("Save"))
+                .on_press(Message::SaveProfile)
+                .style(iced::theme::Button::Primary),]
+            .spacing(8),
+        );
+
+        col.into()
+    }
+
+    fn selected_appearance(&self) -> Option<&storage::profiles::Appearance> {
+        let p = self.profile.as_ref()?;
+        match self.selected_control? {
+            SelectedControl::Key(idx) => Some(&p.keys.get(idx)?.appearance),
+            SelectedControl::Dial(idx) => Some(&p.dials.get(idx)?.appearance),
+            SelectedControl::TouchStrip => Some(&p.touch_strip.appearance),
+        }
+    }
+
+    fn selected_appearance_mut(&mut self) -> Option<&mut storage::profiles::Appearance> {
+        let p = self.profile.as_mut()?;
+        match self.selected_control? {
+            SelectedControl::Key(idx) => Some(&mut p.keys.get_mut(idx)?.appearance),
+            SelectedControl::Dial(idx) => Some(&mut p.dials.get_mut(idx)?.appearance),
+            SelectedControl::TouchStrip => Some(&mut p.touch_strip.appearance),
+        }
+    }
+
+    /// Appearances that appearance edits apply to: every key of a multi-selection, otherwise
+    /// the selected control's.
+    fn edited_appearances(&mut self) -> Vec<&mut storage::profiles::Appearance> {
+        if self.selected_keys.len() > 1 {
+            let selected = &self.selected_keys;
+            match &mut self.profile {
+                Some(p) => p
+                    .keys
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(idx, _)| selected.contains(idx))
+                    .map(|(_, k)| &mut k.appearance)
+                    .collect(),
+                None => vec![],
+            }
         } else {
-            None
+            self.selected_appearance_mut().into_iter().collect()
+        }
+    }
+
+    fn selected_binding(&self) -> Option<&Option<ActionBinding>> {
+        let p = self.profile.as_ref()?;
+        let sel = self.selected_control?;
+        match (sel, self.selected_binding_target) {
+            (SelectedControl::Key(idx), BindingTarget::KeyPress) => Some(&p.keys.get(idx)?.action),
+            (SelectedControl::Dial(idx), BindingTarget::DialPress) => Some(&p.dials.get(idx)?.press),
+            (SelectedControl::Dial(idx), BindingTarget::DialRotate) => Some(&p.dials.get(idx)?.rotate),
+            (SelectedControl::TouchStrip, BindingTarget::TouchTap) => Some(&p.touch_strip.tap),
+            (SelectedControl::TouchStrip, BindingTarget::TouchLongPress) => {
+                Some(&p.touch_strip.long_press)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDrag) => Some(&p.touch_strip.drag),
+            (SelectedControl::TouchStrip, BindingTarget::TouchDragLeft) => {
+                Some(&p.touch_strip.drag_left)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDragRight) => {
+                Some(&p.touch_strip.drag_right)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchSwipeLeft) => {
+                Some(&p.touch_strip.swipe_left)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchSwipeRight) => {
+                Some(&p.touch_strip.swipe_right)
+            }
+            _ => None,
+        }
+    }
+
+    /// The control event that fires the selected binding on the device.
+    fn test_event(&self) -> Option<ControlEvent> {
+        let touch = |kind| ControlEvent {
+            control: ControlId::TouchStrip,
+            kind,
         };
+        let middle = device::gestures::TOUCH_STRIP_WIDTH / 2;
+        Some(match (self.selected_control?, self.selected_binding_target) {
+            (SelectedControl::Key(idx), BindingTarget::KeyPress) => ControlEvent {
+                control: ControlId::Key(u8::try_from(idx).ok()?),
+                kind: ControlEventKind::Down,
+            },
+            (SelectedControl::Dial(idx), BindingTarget::DialPress) => ControlEvent {
+                control: ControlId::Dial(u8::try_from(idx).ok()?),
+                kind: ControlEventKind::Down,
+            },
+            (SelectedControl::Dial(idx), BindingTarget::DialRotate) => ControlEvent {
+                control: ControlId::Dial(u8::try_from(idx).ok()?),
+                kind: ControlEventKind::Rotate { delta: 1 },
+            },
+            (SelectedControl::TouchStrip, BindingTarget::TouchTap) => {
+                touch(ControlEventKind::Tap { x: middle })
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchLongPress) => {
+                touch(ControlEventKind::LongPress { x: middle })
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDrag) => {
+                // Drag in a half without its own binding, so the whole-strip one is used.
+                let strip = &self.profile.as_ref()?.touch_strip;
+                let zone = if strip.drag_left.is_none() {
+                    TouchZone::Left
+                } else {
+                    TouchZone::Right
+                };
+                touch(ControlEventKind::Drag { delta_x: 10, zone })
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDragLeft) => {
+                touch(ControlEventKind::Drag {
+                    delta_x: 10,
+                    zone: TouchZone::Left,
+                })
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDragRight) => {
+                touch(ControlEventKind::Drag {
+                    delta_x: 10,
+                    zone: TouchZone::Right,
+                })
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchSwipeLeft) => {
+                touch(ControlEventKind::SwipeLeft)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchSwipeRight) => {
+                touch(ControlEventKind::SwipeRight)
+            }
+            _ => return None,
+        })
+    }
 
-        Modal::new(content, overlay)
-            .on_esc(Message::OpenColorPicker)
-            .into()
+    fn selected_binding_mut(&mut self) -> Option<&mut Option<ActionBinding>> {
+        let p = self.profile.as_mut()?;
+        let sel = self.selected_control?;
+        match (sel, self.selected_binding_target) {
+            (SelectedControl::Key(idx), BindingTarget::KeyPress) => Some(&mut p.keys.get_mut(idx)?.action),
+            (SelectedControl::Dial(idx), BindingTarget::DialPress) => Some(&mut p.dials.get_mut(idx)?.press),
+            (SelectedControl::Dial(idx), BindingTarget::DialRotate) => Some(&mut p.dials.get_mut(idx)?.rotate),
+            (SelectedControl::TouchStrip, BindingTarget::TouchTap) => Some(&mut p.touch_strip.tap),
+            (SelectedControl::TouchStrip, BindingTarget::TouchLongPress) => {
+                Some(&mut p.touch_strip.long_press)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDrag) => Some(&mut p.touch_strip.drag),
+            (SelectedControl::TouchStrip, BindingTarget::TouchDragLeft) => {
+                Some(&mut p.touch_strip.drag_left)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchDragRight) => {
+                Some(&mut p.touch_strip.drag_right)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchSwipeLeft) => {
+                Some(&mut p.touch_strip.swipe_left)
+            }
+            (SelectedControl::TouchStrip, BindingTarget::TouchSwipeRight) => {
+                Some(&mut p.touch_strip.swipe_right)
+            }
+            _ => None,
+        }
     }
 
-}
+    fn set_selected_plugin_setting(&mut self, key: String, value: serde_json::Value) {
+        let Some(slot) = self.selected_binding_mut() else {
+            return;
+        };
+        let Some(ActionBinding::Plugin(binding)) = slot.as_mut() else {
+            return;
+        };
 
-impl App {
-    fn view_main_content(&self) -> Element<'_, Message> {
-        let topbar = self.view_topbar();
-        let content: Element<Message> = match self.active_view {
-            ActiveView::Main => {
-                let sidebar = self.view_sidebar();
-                let preview = self.view_preview_panel();
-                let inspector = self.view_inspector_panel();
-                let actions = self.view_actions_panel();
+        if let Some(obj) = binding.settings.as_object_mut() {
+            obj.insert(key, value);
+            return;
+        }
+
+        let mut map = serde_json::Map::new();
+        map.insert(key, value);
+        binding.settings = serde_json::Value::Object(map);
+    }
+
+    fn view_display_editor(&self) -> Element<'_, Message> {
+        let color = parse_bg_rgb(&self.edit_bg_rgb)
+            .map(|[r, g, b]| Color::from_rgb8(r, g, b))
+            .unwrap_or(Color::TRANSPARENT);
+
+        column![
+            text("Background Color").size(12).style(self.color_text_muted()),
+            row![
+                container(text(""))
+                    .width(Length::Fixed(30.0))
+                    .height(Length::Fixed(30.0))
+                    .style(iced::theme::Container::Custom(Box::new(move |_theme: &Theme| {
+                        iced::widget::container::Appearance {
+                            background: Some(Background::Color(color)),
+                            border: Border {
+                                radius: 4.0.into(),
+                                width: 1.0,
+                                color: Color::from_rgba8(255, 255, 255, 0.2),
+                            },
+                            ..Default::default()
+                        }
+                    }))),
+                button(text("Pick Color"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::OpenColorPicker),
+                button(text("Clear"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::BgRgbChanged(String::new())),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+            text("Icon").size(12).style(self.color_text_muted()),
+            row![
+                text_input("No icon selected", &self.edit_icon_path)
+                    .on_input(Message::IconPathChanged)
+                    .width(Length::Fill),
+                button(text("Browse…"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::OpenIconPicker),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+            text("Text (optional, {{variable}} placeholders allowed)")
+                .size(12)
+                .style(self.color_text_muted()),
+            text_input("", &self.edit_display_text).on_input(Message::DisplayTextChanged),
+            text("Text size").size(12).style(self.color_text_muted()),
+            pick_list(
+                TextSizeChoice::ALL.to_vec(),
+                Some(TextSizeChoice::of(self.selected_appearance().and_then(|a| a.text_size))),
+                Message::TextSizePicked,
+            ),
+            text("Home Assistant entity (shows its state)")
+                .size(12)
+                .style(self.color_text_muted()),
+            text_input("sensor.living_room_temperature", &self.edit_entity_id)
+                .on_input(Message::EntityIdChanged),
+            text("Flash when triggered").size(12).style(self.color_text_muted()),
+            pick_list(
+                FlashChoice::ALL.to_vec(),
+                Some(FlashChoice::of(self.selected_appearance().and_then(|a| a.flash))),
+                Message::FlashPicked,
+            ),
+        ]
+        .spacing(6)
+        .into()
+    }
+
+    fn view_action_editor(&self) -> Element<'_, Message> {
+        let Some(binding) = self.selected_binding() else {
+            return text("Select a control to edit its action.").into();
+        };
+
+        let mode = match binding {
+            None => ActionModeChoice::None,
+            Some(ActionBinding::Plugin(_)) => ActionModeChoice::Plugin,
+            Some(ActionBinding::Builtin(_)) => ActionModeChoice::Builtin,
+        };
+
+        let mode_picker = pick_list(
+            vec![
+                ActionModeChoice::None,
+                ActionModeChoice::Plugin,
+                ActionModeChoice::Builtin,
+            ],
+            Some(mode),
+            Message::ActionModePicked,
+        );
 
-                let main = column![preview, h_divider(), inspector]
-                    .spacing(0)
-                    .width(Length::Fill)
-                    .height(Length::Fill);
+        let can_test = binding.is_some() && self.connected.is_some() && !self.testing_binding;
+        let test_button = button(text(if self.testing_binding { "Testing…" } else { "Test" }))
+            .on_press_maybe(can_test.then_some(Message::TestBinding));
 
-                row![sidebar, v_divider(), main, v_divider(), actions]
-                    .spacing(0)
-                    .width(Length::Fill)
-                    .height(Length::Fill)
-                    .into()
+        let mut col = column![row![
+            text("Mode").size(12).style(self.color_text_muted()),
+            mode_picker,
+            horizontal_space(),
+            test_button
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)]
+        .spacing(8);
+        if let Some(report) = &self.binding_test {
+            col = col.push(self.view_binding_test(report));
+        }
+
+        match binding {
+            None => {
+                col = col.push(text("No action bound.").style(self.color_text_muted()));
             }
-            ActiveView::Marketplace => self.view_marketplace(),
-        };
+            Some(ActionBinding::Plugin(_)) => {
+                let current = self.current_action_choice();
+                col = col.push(pick_list(
+                    self.actions.clone(),
+                    current,
+                    Message::ActionSelected,
+                ));
+                col = col.push(self.view_action_settings());
+            }
+            Some(ActionBinding::Builtin(b)) => {
+                let current_kind = BuiltinKindChoice::of(b);
 
-        let mut root = column![topbar, content]
-            .spacing(10)
-            .padding(12)
-            .width(Length::Fill)
-            .height(Length::Fill);
+                col = col.push(pick_list(
+                    BuiltinKindChoice::ALL.to_vec(),
+                    Some(current_kind),
+                    Message::BuiltinKindPicked,
+                ));
 
-        if let Some(err) = &self.error {
-            root = root.push(self.view_error_banner(err));
+                col = col.push(self.view_builtin_settings(b));
+            }
         }
 
-        container(root)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .style(app_background())
-            .into()
+        col.into()
     }
 
-}
+    /// Outcome of a test run, one line per step; steps of a conditional are indented.
+    fn view_binding_test(&self, report: &Result<Vec<StepReport>, String>) -> Element<'_, Message> {
+        let palette = self.active_theme.theme.palette();
+        let steps = match report {
+            Ok(steps) => steps,
+            Err(e) => return text(e).size(12).style(palette.danger).into(),
+        };
+        if steps.is_empty() {
+            return text("The binding has no steps.")
+                .size(12)
+                .style(self.color_text_muted())
+                .into();
+        }
 
-#[derive(Debug, Clone)]
-enum Message {
-    RefreshDevices,
-    DevicesLoaded(Result<Vec<DiscoveredDevice>, String>),
-    OpenColorPicker,
-    ColorPicked(Color),
-    OpenIconPicker,
-    IconPicked(Option<std::path::PathBuf>),
-    DevicePicked(DeviceChoice),
-    Connected(Result<ConnectedInfo, String>),
-    RefreshProfiles,
-    ProfilesLoaded(Result<Vec<ProfileMeta>, String>),
-    CreateProfile,
-    ProfileCreated(Result<Profile, String>),
-    ProfilePicked(ProfileChoice),
-    ProfileLoaded(Result<Profile, String>),
-    SelectControl(SelectedControl),
-    LabelChanged(String),
-    BindingTargetPicked(BindingTarget),
-    BgRgbChanged(String),
-    IconPathChanged(String),
-    DisplayTextChanged(String),
-    SaveProfile,
-    ProfileSaved(Result<(), String>),
-    DisplaysApplied(Result<(), String>),
-    RefreshPlugins,
-    PluginsLoaded(Result<Vec<InstalledPlugin>, String>),
-    InstallPluginPathChanged(String),
-    InstallPluginFromPath,
-    PluginInstalled(Result<(), String>),
-    OpenMarketplace,
-    CloseMarketplace,
-    MarketplaceRefresh,
-    MarketplaceSourcePicked(MarketplaceSource),
-    MarketplaceSearchChanged(String),
-    MarketplaceLoaded(Result<Vec<MarketplacePlugin>, String>),
-    MarketplaceIconLoaded { key: String, bytes: Result<Vec<u8>, String> },
-    MarketplacePrevPage,
-    MarketplaceNextPage,
-    MarketplaceSelect(MarketplacePlugin),
-    MarketplaceImageLoaded { key: String, bytes: Result<Vec<u8>, String> },
-    MarketplaceDetailsLoaded {
-        plugin_id: String,
-        details: Result<MarketplaceDetails, String>,
-    },
-    OpenUrl(String),
-    MarketplaceInstall(MarketplacePlugin),
-    MarketplaceInstalled(Result<(), String>),
-    ActionSeqContinue(u64),
-    ActionSeqStepDone { seq_id: u64, res: Result<(), String> },
-    ActionModePicked(ActionModeChoice),
-    BuiltinKindPicked(BuiltinKindChoice),
-    BuiltinIssueCommandChanged(String),
-    BuiltinIssueCwdChanged(String),
-    BuiltinIssueTimeoutChanged(String),
-    BuiltinKeyboardTextChanged(String),
-    BuiltinKeyboardKeysChanged(String),
-    BuiltinPlaySoundPathChanged(String),
-    BuiltinSwitchProfilePicked(SwitchProfileChoice),
-    BuiltinBrightnessModePicked(BrightnessModeChoice),
-    BuiltinBrightnessValueChanged(i32),
-    BuiltinMonitorKindPicked(MonitorKindChoice),
-    MacroAddStep,
-    MacroRemoveStep(usize),
-    MacroMoveStepUp(usize),
-    MacroMoveStepDown(usize),
-    MacroStepKindPicked { idx: usize, kind: MacroStepKindChoice },
-    MacroStepDelayChanged { idx: usize, value: String },
-    MacroStepPluginPicked { idx: usize, choice: ActionChoice },
-    MacroStepCommandChanged { idx: usize, value: String },
-    StartDragAction(DraggedAction),
-    CancelDragAction,
-    DragOverKey(Option<usize>),
-    DropOnKey(usize),
-    ActionSelected(ActionChoice),
-    ActionSearchChanged(String),
-    SettingStringChanged { key: String, value: String },
-    SettingBoolChanged { key: String, value: bool },
-    SettingNumberChanged { key: String, value: String },
-    Tick,
-    BrightnessChanged(i32),
-    BrightnessApplied(Result<(), String>),
-}
+        let mut col = column![].spacing(4);
+        for step in steps {
+            let (mark, color) = match &step.result {
+                Ok(()) => ("✓", palette.success),
+                Err(_) => ("✗", palette.danger),
+            };
+            let line = row![
+                text(mark).size(12).style(color),
+                text(&step.step).size(12),
+                horizontal_space(),
+                text(format!("{} ms", step.duration.as_millis()))
+                    .size(12)
+                    .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .align_items(Alignment::Center);
+            let indent = 16 * step.depth as u16;
+            col = col.push(container(line).padding([0, 0, 0, indent]));
+            if let Err(e) = &step.result {
+                col = col.push(
+                    container(text(e).size(12).style(palette.danger))
+                        .padding([0, 0, 0, indent + 18]),
+                );
+            }
+        }
+        container(col).padding(8).style(callout_card(self.active_theme.radii.card)).into()
+    }
 
-#[derive(Clone)]
-struct ConnectedInfo {
-    id: app_core::ids::DeviceId,
-    name: String,
-    key_count: u8,
-    controller: DeviceController,
-    events_slot: Arc<std::sync::Mutex<Option<Receiver<DeviceEvent>>>>,
-}
+    fn view_builtin_settings(&self, b: &BuiltinAction) -> Element<'_, Message> {
+        match b {
+            BuiltinAction::Macro { steps } => self.view_macro_editor(steps),
+            BuiltinAction::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => self.view_conditional_editor(condition, then.as_deref(), otherwise.as_deref()),
+            BuiltinAction::IssueCommand {
+                command,
+                cwd,
+                timeout_ms,
+            } => {
+                let timeout = timeout_ms.map(|v| v.to_string()).unwrap_or_default();
+                column![
+                    text("Command").size(12).style(self.color_text_muted()),
+                    text_input("bash command…", command).on_input(Message::BuiltinIssueCommandChanged),
+                    text("Working dir (optional)").size(12).style(self.color_text_muted()),
+                    text_input("", cwd.as_deref().unwrap_or("")).on_input(Message::BuiltinIssueCwdChanged),
+                    text("Timeout ms (optional)").size(12).style(self.color_text_muted()),
+                    text_input("", &timeout).on_input(Message::BuiltinIssueTimeoutChanged),
+                    text("Runs via `bash -lc` (Linux MVP).").size(12).style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::KeyboardInput {
+                text: input_text,
+                keys,
+                chord,
+            } => {
+                let keys_s = keys.join(" ");
+                let record = if self.recording_chord {
+                    button(text("Press a shortcut… (Esc cancels)").size(12))
+                        .style(iced::theme::Button::Primary)
+                } else {
+                    button(text("Record").size(12))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::RecordChord)
+                };
+                let clear = button(text("Clear").size(12))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press_maybe(chord.is_some().then_some(Message::ClearChord));
+                let current = chord.as_ref().map_or("None".to_string(), |c| c.to_string());
+                column![
+                    text("Text (optional)").size(12).style(self.color_text_muted()),
+                    text_input("", input_text.as_deref().unwrap_or(""))
+                        .on_input(Message::BuiltinKeyboardTextChanged),
+                    text("Shortcut (optional, pressed when there is no text)")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    row![text(current).size(13).width(Length::Fill), record, clear]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    text("Keys (space-separated, optional)").size(12).style(self.color_text_muted()),
+                    text_input("e.g. -k Return", &keys_s).on_input(Message::BuiltinKeyboardKeysChanged),
+                    text("Linux MVP uses external tool: env `RIVERDECK_KEYBOARD_TOOL` (default: wtype).")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::PlaySound { path, volume } => {
+                let volume = volume.unwrap_or(100);
+                column![
+                    text("Audio file path").size(12).style(self.color_text_muted()),
+                    text_input("/path/to/file.wav", path)
+                        .on_input(Message::BuiltinSoundPathChanged),
+                    text(format!("Volume {volume}%")).size(12).style(self.color_text_muted()),
+                    slider(0..=200, volume as i32, Message::BuiltinSoundVolumeChanged),
+                    text("Plays in the background; overlapping sounds are mixed.")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Soundboard { path, volume, mode } => {
+                let volume = volume.unwrap_or(100);
+                let m = match mode {
+                    actions::SoundboardMode::PlayOverlap => SoundboardModeChoice::PlayOverlap,
+                    actions::SoundboardMode::Restart => SoundboardModeChoice::Restart,
+                    actions::SoundboardMode::StopOthers => SoundboardModeChoice::StopOthers,
+                };
+
+                column![
+                    text("Audio file path").size(12).style(self.color_text_muted()),
+                    text_input("/path/to/file.wav", path)
+                        .on_input(Message::BuiltinSoundPathChanged),
+                    text(format!("Volume {volume}%")).size(12).style(self.color_text_muted()),
+                    slider(0..=200, volume as i32, Message::BuiltinSoundVolumeChanged),
+                    text("On press").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        vec![
+                            SoundboardModeChoice::PlayOverlap,
+                            SoundboardModeChoice::Restart,
+                            SoundboardModeChoice::StopOthers,
+                        ],
+                        Some(m),
+                        Message::BuiltinSoundboardModePicked,
+                    ),
+                    text("The key is highlighted while its sound plays.")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Counter {
+                step,
+                reset_on_long_press,
+            } => {
+                let mut col = column![
+                    text("Step (added per press; negative counts down)")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    text_input("1", &step.to_string()).on_input(Message::BuiltinCounterStepChanged),
+                    checkbox("Reset on long press", *reset_on_long_press)
+                        .on_toggle(Message::BuiltinCounterResetOnLongPressToggled),
+                ]
+                .spacing(6);
+                if let Some(SelectedControl::Key(idx)) = self.selected_control {
+                    let value = self.counters.get(&(idx as u8)).copied().unwrap_or(0);
+                    col = col.push(
+                        row![
+                            text(format!("Current count: {value}")).size(12),
+                            horizontal_space(),
+                            button(text("Reset"))
+                                .style(iced::theme::Button::Secondary)
+                                .on_press(Message::CounterReset(idx as u8)),
+                        ]
+                        .align_items(Alignment::Center),
+                    );
+                }
+                col.into()
+            }
+            BuiltinAction::SetVariable { name, value } => column![
+                text("Variable name").size(12).style(self.color_text_muted()),
+                text_input("scene", name).on_input(Message::BuiltinSetVariableNameChanged),
+                text("Value ({{other}} placeholders allowed)").size(12).style(self.color_text_muted()),
+                text_input("Gaming", value).on_input(Message::BuiltinSetVariableValueChanged),
+                text(format!(
+                    "Current value: {}",
+                    self.variable_values.get(name).map(String::as_str).unwrap_or("(unset)")
+                ))
+                .size(12)
+                .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::HomeAssistant {
+                service,
+                entity_id,
+                data,
+            } => {
+                let status = if !self.ha_connected {
+                    "Not connected; set up Home Assistant in the settings.".to_string()
+                } else {
+                    match self.ha_states.get(entity_id) {
+                        Some(state) => format!("Current state: {}", state.display()),
+                        None if entity_id.is_empty() => String::new(),
+                        None => "Unknown entity".to_string(),
+                    }
+                };
+                column![
+                    text("Service (domain.service)").size(12).style(self.color_text_muted()),
+                    text_input("light.toggle", service)
+                        .on_input(Message::BuiltinHomeAssistantServiceChanged),
+                    text("Entity").size(12).style(self.color_text_muted()),
+                    text_input("light.desk", entity_id)
+                        .on_input(Message::BuiltinHomeAssistantEntityChanged),
+                    text("Service data (JSON, optional)").size(12).style(self.color_text_muted()),
+                    text_input("{\"brightness_pct\": 40}", data)
+                        .on_input(Message::BuiltinHomeAssistantDataChanged),
+                    text(status).size(12).style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Spotify { command } => {
+                let c = match command {
+                    actions::SpotifyCommand::PlayPause => SpotifyCommandChoice::PlayPause,
+                    actions::SpotifyCommand::Next => SpotifyCommandChoice::Next,
+                    actions::SpotifyCommand::Previous => SpotifyCommandChoice::Previous,
+                    actions::SpotifyCommand::Like => SpotifyCommandChoice::Like,
+                };
+                let status = match &self.spotify_playback {
+                    _ if !self.spotify_signed_in => {
+                        "Not signed in; sign in to Spotify in the settings.".to_string()
+                    }
+                    Some(p) => format!("Now playing: {} – {}", p.title, p.artists),
+                    None => "Nothing playing.".to_string(),
+                };
+                column![
+                    text("Command").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        vec![
+                            SpotifyCommandChoice::PlayPause,
+                            SpotifyCommandChoice::Next,
+                            SpotifyCommandChoice::Previous,
+                            SpotifyCommandChoice::Like,
+                        ],
+                        Some(c),
+                        Message::BuiltinSpotifyCommandPicked,
+                    ),
+                    text("Play / Pause keys and the touch strip show the album art and progress.")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    text(status).size(12).style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Screenshot {
+                region,
+                save_dir,
+                clipboard,
+            } => {
+                let r = match region {
+                    actions::ScreenshotRegion::FullScreen => ScreenshotRegionChoice::FullScreen,
+                    actions::ScreenshotRegion::Selection => ScreenshotRegionChoice::Selection,
+                    actions::ScreenshotRegion::ActiveWindow => ScreenshotRegionChoice::ActiveWindow,
+                };
+                column![
+                    text("Capture").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        vec![
+                            ScreenshotRegionChoice::FullScreen,
+                            ScreenshotRegionChoice::Selection,
+                            ScreenshotRegionChoice::ActiveWindow,
+                        ],
+                        Some(r),
+                        Message::BuiltinScreenshotRegionPicked,
+                    ),
+                    text("Save to").size(12).style(self.color_text_muted()),
+                    text_input("Pictures folder", save_dir.as_deref().unwrap_or_default())
+                        .on_input(Message::BuiltinCaptureDirChanged),
+                    checkbox("Copy to clipboard", *clipboard)
+                        .on_toggle(Message::BuiltinScreenshotClipboardToggled),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::ScreenRecord { save_dir } => column![
+                text("Save to").size(12).style(self.color_text_muted()),
+                text_input("Videos folder", save_dir.as_deref().unwrap_or_default())
+                    .on_input(Message::BuiltinCaptureDirChanged),
+                text("Press again to stop; the key turns red while recording.")
+                    .size(12)
+                    .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::Open { target } => {
+                let selected = self.installed_apps.iter().find(|a| a.target == *target).cloned();
+                column![
+                    text("Application").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        self.installed_apps.clone(),
+                        selected,
+                        Message::BuiltinOpenAppPicked,
+                    )
+                    .placeholder("Pick an installed app…")
+                    .width(Length::Fill),
+                    text("…or a file, folder or URL").size(12).style(self.color_text_muted()),
+                    text_input("https://example.com or /path/to/file", target)
+                        .on_input(Message::BuiltinOpenTargetChanged),
+                    text("Opened with the system's default handler; no shell is involved.")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Clipboard { mode } => {
+                let (m, text_value) = match mode {
+                    actions::ClipboardMode::CopyText(t) => {
+                        (ClipboardModeChoice::CopyText, Some(t))
+                    }
+                    actions::ClipboardMode::PasteText(t) => {
+                        (ClipboardModeChoice::PasteText, Some(t))
+                    }
+                    actions::ClipboardMode::CyclePrevious => {
+                        (ClipboardModeChoice::CyclePrevious, None)
+                    }
+                };
 
-impl fmt::Debug for ConnectedInfo {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("ConnectedInfo")
-            .field("id", &self.id)
-            .field("name", &self.name)
-            .field("key_count", &self.key_count)
-            .finish_non_exhaustive()
-    }
-}
+                let mut col = column![
+                    text("Mode").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        vec![
+                            ClipboardModeChoice::CopyText,
+                            ClipboardModeChoice::PasteText,
+                            ClipboardModeChoice::CyclePrevious,
+                        ],
+                        Some(m),
+                        Message::BuiltinClipboardModePicked,
+                    ),
+                ]
+                .spacing(6);
+                match text_value {
+                    Some(t) => {
+                        col = col
+                            .push(text("Text").size(12).style(self.color_text_muted()))
+                            .push(
+                                text_input("Snippet", t)
+                                    .on_input(Message::BuiltinClipboardTextChanged),
+                            );
+                        if m == ClipboardModeChoice::PasteText {
+                            col = col.push(
+                                text("Pastes with the keyboard input tool (RIVERDECK_PASTE_KEYS).")
+                                    .size(12)
+                                    .style(self.color_text_muted()),
+                            );
+                        }
+                    }
+                    None => {
+                        col = col.push(
+                            text("Each press goes one entry further back in the clipboard history.")
+                                .size(12)
+                                .style(self.color_text_muted()),
+                        );
+                    }
+                }
+                col.into()
+            }
+            BuiltinAction::StopSound { path } => column![
+                text("Audio file path (empty = stop all sounds)").size(12).style(self.color_text_muted()),
+                text_input("/path/to/file.wav", path.as_deref().unwrap_or(""))
+                    .on_input(Message::BuiltinStopSoundPathChanged),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::SwitchProfile { mode } => {
+                let mut choices = vec![SwitchProfileChoice::Next, SwitchProfileChoice::Prev];
+                for p in &self.profiles {
+                    choices.push(SwitchProfileChoice::To(p.id));
+                }
 
-struct ConnectedUi {
-    id: app_core::ids::DeviceId,
-    name: String,
-    key_count: u8,
-    pressed: Vec<bool>,
-    brightness: u8,
-    controller: DeviceController,
-    events: tokio::sync::mpsc::Receiver<DeviceEvent>,
-}
+                let selected = match mode {
+                    actions::SwitchProfileMode::Next => SwitchProfileChoice::Next,
+                    actions::SwitchProfileMode::Prev => SwitchProfileChoice::Prev,
+                    actions::SwitchProfileMode::To { profile_id } => {
+                        SwitchProfileChoice::To(ProfileId(*profile_id))
+                    }
+                };
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct DeviceChoice {
-    id: app_core::ids::DeviceId,
-    label: String,
-}
+                column![
+                    text("Mode").size(12).style(self.color_text_muted()),
+                    pick_list(choices, Some(selected), Message::BuiltinSwitchProfilePicked),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::DeviceBrightness { mode } => {
+                let (m, v) = match mode {
+                    actions::BrightnessMode::Set { percent } => (BrightnessModeChoice::Set, *percent as i32),
+                    actions::BrightnessMode::Increase { delta } => {
+                        (BrightnessModeChoice::Increase, *delta as i32)
+                    }
+                    actions::BrightnessMode::Decrease { delta } => {
+                        (BrightnessModeChoice::Decrease, *delta as i32)
+                    }
+                };
 
-impl fmt::Display for DeviceChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.label)
-    }
-}
+                column![
+                    text("Mode").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        vec![
+                            BrightnessModeChoice::Set,
+                            BrightnessModeChoice::Increase,
+                            BrightnessModeChoice::Decrease,
+                        ],
+                        Some(m),
+                        Message::BuiltinBrightnessModePicked,
+                    ),
+                    slider(0..=100, v, Message::BuiltinBrightnessValueChanged),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::SystemMonitoring { kind, metrics, .. } => {
+                let panels = MonitorKindChoice::ALL.into_iter().fold(row![].spacing(12), |r, m| {
+                    let checked = metrics.iter().any(|k| MonitorKindChoice::of(k) == m);
+                    r.push(
+                        checkbox(m.to_string(), checked)
+                            .on_toggle(move |on| Message::BuiltinMonitorMetricToggled(m, on)),
+                    )
+                });
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct ProfileChoice {
-    id: ProfileId,
-    label: String,
-}
+                let mut col = column![
+                    text("Metric").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        MonitorKindChoice::ALL.to_vec(),
+                        Some(MonitorKindChoice::of(kind)),
+                        Message::BuiltinMonitorKindPicked,
+                    ),
+                ]
+                .spacing(6);
+
+                // Which interface, mount point, sensor or GPU, with those the last sample had.
+                let s = &self.sys_snapshot;
+                let target = match kind {
+                    actions::MonitorKind::NetworkRx { interface }
+                    | actions::MonitorKind::NetworkTx { interface } => Some((
+                        "Interface",
+                        "All but loopback",
+                        interface.clone(),
+                        s.networks.keys().cloned().collect::<Vec<_>>(),
+                    )),
+                    actions::MonitorKind::DiskUsage { mount } => Some((
+                        "Mount point",
+                        "Root filesystem",
+                        mount.clone(),
+                        s.disks.keys().cloned().collect(),
+                    )),
+                    actions::MonitorKind::Temperature { sensor } => Some((
+                        "Sensor",
+                        "Hottest",
+                        sensor.clone(),
+                        s.temperatures.keys().cloned().collect(),
+                    )),
+                    actions::MonitorKind::GpuUsage { gpu }
+                    | actions::MonitorKind::GpuMemory { gpu } => Some((
+                        "GPU number",
+                        "0",
+                        gpu.map(|i| i.to_string()),
+                        s.gpus
+                            .iter()
+                            .enumerate()
+                            .map(|(i, g)| format!("{i}: {}", g.name))
+                            .collect(),
+                    )),
+                    _ => None,
+                };
+                if let Some((label, placeholder, value, available)) = target {
+                    col = col.push(text(label).size(12).style(self.color_text_muted())).push(
+                        text_input(placeholder, value.as_deref().unwrap_or(""))
+                            .on_input(Message::BuiltinMonitorTargetChanged),
+                    );
+                    if !available.is_empty() {
+                        col = col.push(
+                            text(format!("Available: {}", available.join(", ")))
+                                .size(12)
+                                .style(self.color_text_muted()),
+                        );
+                    }
+                }
 
-impl fmt::Display for ProfileChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.label)
+                col.push(column![
+                    text("Touch strip dashboard").size(12).style(self.color_text_muted()),
+                    panels,
+                    text(
+                        "On the touch strip each checked metric gets a panel with a graph of \
+                         its recent values, in the order checked; just the metric above when \
+                         none are."
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6))
+                .into()
+            }
+            BuiltinAction::Clock {
+                timezone,
+                format,
+                show_date,
+                show_week,
+            } => {
+                let preview = match clock::key_text(b, chrono::Utc::now()) {
+                    Some(Ok(t)) => format!("Now: {}", t.replace('\n', "  ")),
+                    Some(Err(e)) => e,
+                    None => String::new(),
+                };
+                column![
+                    text("Time zone").size(12).style(self.color_text_muted()),
+                    text_input("Local time", timezone.as_deref().unwrap_or(""))
+                        .on_input(Message::BuiltinClockTimezoneChanged),
+                    text("Format").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        ClockFormatChoice::ALL.to_vec(),
+                        Some(ClockFormatChoice::of(*format)),
+                        Message::BuiltinClockFormatPicked,
+                    ),
+                    checkbox("Show date", *show_date).on_toggle(Message::BuiltinClockDateToggled),
+                    checkbox("Show week number", *show_week)
+                        .on_toggle(Message::BuiltinClockWeekToggled),
+                    text(preview).size(12).style(self.color_text_muted()),
+                    text(
+                        "Time zones are IANA names such as America/New_York or Asia/Kolkata. \
+                         The key's text, if any, is shown above the time."
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Weather {
+                location,
+                units,
+                refresh_min,
+            } => {
+                let query = (location.trim().to_string(), *units);
+                let status = match self.weather_reports.get(&query) {
+                    Some(Ok(r)) if r.stale => format!(
+                        "{}: {}, {} (offline; from {} min ago)",
+                        r.place,
+                        r.temperature_text(),
+                        r.conditions(),
+                        r.age().as_secs() / 60
+                    ),
+                    Some(Ok(r)) => {
+                        format!("{}: {}, {}", r.place, r.temperature_text(), r.conditions())
+                    }
+                    Some(Err(e)) => e.clone(),
+                    None => "Fetched from Open-Meteo once the profile is saved.".to_string(),
+                };
+                let refresh = refresh_min.map(|m| m.to_string()).unwrap_or_default();
+                column![
+                    text("Location").size(12).style(self.color_text_muted()),
+                    text_input("City name, or latitude,longitude", location)
+                        .on_input(Message::BuiltinWeatherLocationChanged),
+                    text("Units").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        WeatherUnitsChoice::ALL.to_vec(),
+                        Some(WeatherUnitsChoice::of(*units)),
+                        Message::BuiltinWeatherUnitsPicked,
+                    ),
+                    text("Refresh every (minutes)").size(12).style(self.color_text_muted()),
+                    text_input("15", &refresh).on_input(Message::BuiltinWeatherRefreshChanged),
+                    text(status).size(12).style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Pomodoro {
+                work_min,
+                break_min,
+                long_break_every,
+                long_break_min,
+                sound,
+                notify,
+            } => {
+                let minutes = |field: PomodoroField, label, value: &u32, placeholder| {
+                    column![
+                        text(label).size(12).style(self.color_text_muted()),
+                        text_input(placeholder, &value.to_string()).on_input(move |value| {
+                            Message::BuiltinPomodoroMinutesChanged { field, value }
+                        }),
+                    ]
+                    .spacing(6)
+                };
+                let mut col = column![
+                    row![
+                        minutes(PomodoroField::Work, "Work (minutes)", work_min, "25"),
+                        minutes(PomodoroField::Break, "Break (minutes)", break_min, "5"),
+                    ]
+                    .spacing(8),
+                    row![
+                        minutes(
+                            PomodoroField::LongBreak,
+                            "Long break (minutes)",
+                            long_break_min,
+                            "15"
+                        ),
+                        minutes(
+                            PomodoroField::LongBreakEvery,
+                            "Long break every (0 = never)",
+                            long_break_every,
+                            "4"
+                        ),
+                    ]
+                    .spacing(8),
+                    text("Sound at the end of a phase")
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    text_input("/path/to/file.wav", sound.as_deref().unwrap_or(""))
+                        .on_input(Message::BuiltinPomodoroSoundChanged),
+                    checkbox("Desktop notification at the end of a phase", *notify)
+                        .on_toggle(Message::BuiltinPomodoroNotifyToggled),
+                    text(
+                        "Pressing the key starts, pauses or resumes the current phase; the next \
+                         phase waits for a press. The key is red while working, green during \
+                         breaks and blue during long breaks."
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6);
+                if let Some(SelectedControl::Key(idx)) = self.selected_control {
+                    let timer = self.pomodoros.get(&(idx as u8));
+                    let status = pomodoro::key_look(b, timer, SystemTime::now())
+                        .map(|look| look.text.replace('\n', " "))
+                        .unwrap_or_default();
+                    let completed = timer.map_or(0, |t| t.completed);
+                    let status = if *long_break_every > 0 {
+                        format!("Now: {status} ({completed}/{long_break_every} to a long break)")
+                    } else {
+                        format!("Now: {status}")
+                    };
+                    col = col.push(
+                        row![
+                            text(status).size(12),
+                            horizontal_space(),
+                            button(text("Reset"))
+                                .style(iced::theme::Button::Secondary)
+                                .on_press(Message::PomodoroReset(idx as u8)),
+                        ]
+                        .align_items(Alignment::Center),
+                    );
+                }
+                col.into()
+            }
+            BuiltinAction::Notify { title, body } => column![
+                text("Title").size(12).style(self.color_text_muted()),
+                text_input("Build finished", title).on_input(Message::BuiltinNotifyTitleChanged),
+                text("Body").size(12).style(self.color_text_muted()),
+                text_input("{{status}}", body).on_input(Message::BuiltinNotifyBodyChanged),
+                text(
+                    "Shown with notify-send on Linux, osascript on macOS and a tray balloon on \
+                     Windows. Use {{name}} to insert a variable."
+                )
+                .size(12)
+                .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::DoNotDisturb { mode } => {
+                let status = match self.do_not_disturb {
+                    Some(true) => "Do not disturb is on.",
+                    Some(false) => "Do not disturb is off.",
+                    None => "Read once the profile is saved.",
+                };
+                column![
+                    pick_list(
+                        DoNotDisturbModeChoice::ALL.to_vec(),
+                        Some(DoNotDisturbModeChoice::of(*mode)),
+                        Message::BuiltinDoNotDisturbModePicked,
+                    ),
+                    text(status).size(12),
+                    text(
+                        "Uses GNOME's or Plasma's do-not-disturb; on Windows, turns toast \
+                         notifications off. The key turns purple while it is on."
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Radio { radio } => {
+                let name = RadioChoice::of(*radio);
+                let status = match self.radios.get(radio) {
+                    Some(true) => format!("{name} is on."),
+                    Some(false) => format!("{name} is off."),
+                    None => "Read once the profile is saved.".to_string(),
+                };
+                column![
+                    pick_list(
+                        RadioChoice::ALL.to_vec(),
+                        Some(name),
+                        Message::BuiltinRadioPicked,
+                    ),
+                    text(status).size(12),
+                    text(
+                        "Pressing the key turns the radio on or off (Linux: nmcli for Wi-Fi, \
+                         rfkill for Bluetooth). The key turns blue while it is on."
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Power {
+                command,
+                require_long_press,
+            } => column![
+                pick_list(
+                    PowerCommandChoice::ALL.to_vec(),
+                    Some(PowerCommandChoice::of(*command)),
+                    Message::BuiltinPowerCommandPicked,
+                ),
+                checkbox("Hold to confirm", *require_long_press)
+                    .on_toggle(Message::BuiltinPowerLongPressToggled),
+                text(format!(
+                    "When holding is required, the key has to be held for {:.1} s, shown as a \
+                     ring filling up; letting go earlier does nothing.",
+                    engine::POWER_HOLD.as_secs_f32()
+                ))
+                .size(12)
+                .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::CiStatus {
+                repo,
+                workflow,
+                branch,
+            } => column![
+                text("Repository").size(12).style(self.color_text_muted()),
+                text_input("owner/name", repo).on_input(Message::BuiltinCiRepoChanged),
+                text("Workflow (optional)")
+                    .size(12)
+                    .style(self.color_text_muted()),
+                text_input("ci.yml", workflow).on_input(Message::BuiltinCiWorkflowChanged),
+                text("Branch (optional)")
+                    .size(12)
+                    .style(self.color_text_muted()),
+                text_input("main", branch).on_input(Message::BuiltinCiBranchChanged),
+                text(
+                    "The key shows the latest run: green passing, red failing, amber queued or \
+                     running. A press opens the run. Private repositories need the GitHub \
+                     token under Settings → Marketplace.",
+                )
+                .size(12)
+                .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::Light { light, command } => {
+                let (target, error) = match light {
+                    actions::LightTarget::Hue { light: id } => {
+                        let (lights, error) = match &self.hue_lights {
+                            Some(Ok(lights)) => (lights.clone(), None),
+                            Some(Err(e)) => (Vec::new(), Some(e)),
+                            None => (Vec::new(), None),
+                        };
+                        let picked = lights.iter().find(|l| l.id == *id).cloned();
+                        let target = column![
+                            row![
+                                pick_list(lights, picked, |l: HueLight| {
+                                    Message::BuiltinLightChanged(l.id)
+                                })
+                                .placeholder("Pick a light…")
+                                .width(Length::Fill),
+                                button(text("Refresh").size(12)).on_press(Message::ListHueLights),
+                            ]
+                            .spacing(6)
+                            .align_items(Alignment::Center),
+                            text_input("Light id", id).on_input(Message::BuiltinLightChanged),
+                        ]
+                        .spacing(6);
+                        (target, error)
+                    }
+                    actions::LightTarget::Wled { host }
+                    | actions::LightTarget::KeyLight { host } => {
+                        let (found, error) = match &self.light_devices {
+                            Some(Ok(found)) => (found.clone(), None),
+                            Some(Err(e)) => (Vec::new(), Some(e)),
+                            None => (Vec::new(), None),
+                        };
+                        let kind = LightKindChoice::of(light).device_kind();
+                        let devices: Vec<Discovered> =
+                            found.into_iter().filter(|d| Some(d.kind) == kind).collect();
+                        let picked = devices.iter().find(|d| d.host == host.trim()).cloned();
+                        let target = column![
+                            row![
+                                pick_list(devices, picked, |d: Discovered| {
+                                    Message::BuiltinLightChanged(d.host)
+                                })
+                                .placeholder("Pick a device…")
+                                .width(Length::Fill),
+                                button(text("Find").size(12)).on_press(Message::DiscoverLights),
+                            ]
+                            .spacing(6)
+                            .align_items(Alignment::Center),
+                            text_input("Host or address", host)
+                                .on_input(Message::BuiltinLightChanged),
+                        ]
+                        .spacing(6);
+                        (target, error)
+                    }
+                };
+                let mut col = column![
+                    text("Light").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        LightKindChoice::ALL.to_vec(),
+                        Some(LightKindChoice::of(light)),
+                        Message::BuiltinLightKindPicked,
+                    ),
+                    target,
+                ]
+                .spacing(6);
+                if let Some(e) = error {
+                    let danger = self.active_theme.theme.palette().danger;
+                    col = col.push(text(e).size(12).style(danger));
+                }
+                col = col.push(pick_list(
+                    LightCommandChoice::ALL.to_vec(),
+                    Some(LightCommandChoice::of(*command)),
+                    Message::BuiltinLightCommandPicked,
+                ));
+                match command {
+                    actions::LightCommand::SetBrightness(percent) => {
+                        let changed = Message::BuiltinLightAmountChanged;
+                        col = col.push(text(format!("Brightness {percent}%")).size(12));
+                        col = col.push(slider(0..=100, i32::from(*percent), changed));
+                    }
+                    actions::LightCommand::StepBrightness(step) => {
+                        let changed = Message::BuiltinLightAmountChanged;
+                        col = col.push(text(format!("{step:+}% per step")).size(12));
+                        col = col.push(slider(-25..=25, i32::from(*step), changed));
+                    }
+                    actions::LightCommand::ColorTemperature(kelvin) => {
+                        let changed = Message::BuiltinLightAmountChanged;
+                        col = col.push(text(format!("{kelvin} K")).size(12));
+                        col = col.push(slider(2000..=7000, i32::from(*kelvin), changed).step(100));
+                    }
+                    actions::LightCommand::Color(rgb) => {
+                        for (channel, name) in ["Red", "Green", "Blue"].into_iter().enumerate() {
+                            let value = rgb[channel];
+                            col = col.push(text(format!("{name} {value}")).size(12));
+                            col = col.push(slider(0..=255, i32::from(value), move |v| {
+                                Message::BuiltinLightColorChanged(channel, v)
+                            }));
+                        }
+                    }
+                    _ => {}
+                }
+                col.push(
+                    text(
+                        "Bind Step brightness to a dial's rotation to dim as it turns; the key or \
+                         dial shows the brightness. Hue lights need the bridge paired under \
+                         Settings → Lighting; color temperature is 2900–7000 K on a Key Light.",
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                )
+                .into()
+            }
+            BuiltinAction::Meeting { app, command } => {
+                let status = match self.meeting_states.get(app) {
+                    Some(Ok(s)) if s.in_meeting => "In a meeting.".to_string(),
+                    Some(Ok(_)) => "Not in a meeting.".to_string(),
+                    Some(Err(e)) => e.clone(),
+                    None => "Read once the profile is saved.".to_string(),
+                };
+                let help = match app {
+                    actions::MeetingApp::Zoom => {
+                        "Presses Zoom's shortcut (Alt+A, Alt+V, Alt+Q; Cmd on macOS), which Zoom \
+                         only sees while its window is focused unless the shortcut is made global \
+                         in Zoom's settings. The key shows whether a meeting is on."
+                    }
+                    actions::MeetingApp::Teams => {
+                        "Turn on Settings → Privacy → Manage API in Teams. The first press in a \
+                         meeting asks in Teams to allow RiverDeck-Redux. The key turns green \
+                         while the microphone or camera is on and red while it is off."
+                    }
+                };
+                column![
+                    pick_list(
+                        MeetingAppChoice::ALL.to_vec(),
+                        Some(MeetingAppChoice::of(*app)),
+                        Message::BuiltinMeetingAppPicked,
+                    ),
+                    pick_list(
+                        MeetingCommandChoice::ALL.to_vec(),
+                        Some(MeetingCommandChoice::of(*command)),
+                        Message::BuiltinMeetingCommandPicked,
+                    ),
+                    text(status).size(12),
+                    text(help).size(12).style(self.color_text_muted()),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Container { container, command } => {
+                let names: Vec<String> = match &self.containers {
+                    Some(Ok(containers)) => containers.iter().map(|c| c.name.clone()).collect(),
+                    _ => Vec::new(),
+                };
+                let picked = names.iter().find(|n| *n == container.trim()).cloned();
+                let mut col = column![
+                    text("Container").size(12).style(self.color_text_muted()),
+                    row![
+                        pick_list(names, picked, Message::BuiltinContainerChanged)
+                            .placeholder("Pick a container…")
+                            .width(Length::Fill),
+                        button(text("Refresh").size(12)).on_press(Message::ListContainers),
+                    ]
+                    .spacing(6)
+                    .align_items(Alignment::Center),
+                    text_input("Name or id", container).on_input(Message::BuiltinContainerChanged),
+                    pick_list(
+                        ContainerCommandChoice::ALL.to_vec(),
+                        Some(ContainerCommandChoice::of(*command)),
+                        Message::BuiltinContainerCommandPicked,
+                    ),
+                    text(
+                        "Talks to the Docker socket (DOCKER_HOST, or Podman's when Docker's is \
+                         missing). The key turns green while the container runs and red while \
+                         it is stopped.",
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6);
+                if let Some(Err(e)) = &self.containers {
+                    let danger = self.active_theme.theme.palette().danger;
+                    col = col.push(text(e).size(12).style(danger));
+                }
+                col.into()
+            }
+            BuiltinAction::Midi { port, message } => {
+                let names = match &self.midi_output_ports {
+                    Some(Ok(ports)) => ports.clone(),
+                    _ => Vec::new(),
+                };
+                let picked = names.iter().find(|n| *n == port.trim()).cloned();
+                let kind = MidiMessageChoice::of(*message);
+                let (channel, number, value) = MidiMessageChoice::parts(*message);
+                let edit = Message::BuiltinMidiEdited;
+                let mut numbers = row![
+                    text("Channel").size(12).style(self.color_text_muted()),
+                    text_input("1", &channel.to_string())
+                        .on_input(move |v| edit(MidiMessageEdit::Channel(v)))
+                        .width(Length::Fixed(60.0)),
+                    text(match kind {
+                        MidiMessageChoice::ControlChange => "Controller",
+                        _ => "Note",
+                    })
+                    .size(12)
+                    .style(self.color_text_muted()),
+                    text_input("60", &number.to_string())
+                        .on_input(move |v| edit(MidiMessageEdit::Number(v)))
+                        .width(Length::Fixed(60.0)),
+                ]
+                .spacing(6)
+                .align_items(Alignment::Center);
+                if kind != MidiMessageChoice::NoteOff {
+                    numbers = numbers.push(
+                        text(match kind {
+                            MidiMessageChoice::ControlChange => "Value",
+                            _ => "Velocity",
+                        })
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    );
+                    numbers = numbers.push(
+                        text_input("127", &value.to_string())
+                            .on_input(move |v| edit(MidiMessageEdit::Value(v)))
+                            .width(Length::Fixed(60.0)),
+                    );
+                }
+                let mut col = column![
+                    text("Output port").size(12).style(self.color_text_muted()),
+                    row![
+                        pick_list(names, picked, Message::BuiltinMidiPortChanged)
+                            .placeholder("Pick a port…")
+                            .width(Length::Fill),
+                        button(text("Refresh").size(12)).on_press(Message::ListMidiPorts),
+                    ]
+                    .spacing(6)
+                    .align_items(Alignment::Center),
+                    text_input("Part of the port name", port)
+                        .on_input(Message::BuiltinMidiPortChanged),
+                    pick_list(MidiMessageChoice::ALL.to_vec(), Some(kind), move |k| {
+                        edit(MidiMessageEdit::Kind(k))
+                    }),
+                    numbers,
+                    text(
+                        "Sends the message to the first output port whose name contains this \
+                         (empty sends to the first port), e.g. to a DAW or a lighting desk \
+                         through a virtual port. Channels are 1 to 16, the rest 0 to 127.",
+                    )
+                    .size(12)
+                    .style(self.color_text_muted()),
+                ]
+                .spacing(6);
+                if let Some(Err(e)) = &self.midi_output_ports {
+                    let danger = self.active_theme.theme.palette().danger;
+                    col = col.push(text(e).size(12).style(danger));
+                }
+                col.into()
+            }
+            BuiltinAction::Osc {
+                target,
+                address,
+                args,
+            } => {
+                let muted = self.color_text_muted();
+                column![
+                    text("Target (host:port)").size(12).style(muted),
+                    text_input("127.0.0.1:8000", target).on_input(Message::BuiltinOscTargetChanged),
+                    text("Address").size(12).style(muted),
+                    text_input("/scene/recall", address)
+                        .on_input(Message::BuiltinOscAddressChanged),
+                    text("Arguments").size(12).style(muted),
+                    text_input("1 0.5 \"Main stage\" {{level}}", args)
+                        .on_input(Message::BuiltinOscArgsChanged),
+                    text(
+                        "Sends one UDP message. Arguments are separated by spaces: whole \
+                         numbers are sent as integers, numbers with a dot as floats, true and \
+                         false as booleans and the rest as strings (double quotes keep spaces). \
+                         Use {{name}} in the address or arguments to insert a variable.",
+                    )
+                    .size(12)
+                    .style(muted),
+                ]
+                .spacing(6)
+                .into()
+            }
+            BuiltinAction::Serial {
+                port,
+                baud,
+                payload,
+                format,
+            } => {
+                let names = match &self.serial_ports {
+                    Some(Ok(ports)) => ports.clone(),
+                    _ => Vec::new(),
+                };
+                let picked = names.iter().find(|n| *n == port.trim()).cloned();
+                let muted = self.color_text_muted();
+                let danger = self.active_theme.theme.palette().danger;
+                let mut col = column![
+                    text("Port").size(12).style(muted),
+                    row![
+                        pick_list(names, picked, Message::BuiltinSerialPortChanged)
+                            .placeholder("Pick a port…")
+                            .width(Length::Fill),
+                        button(text("Refresh").size(12)).on_press(Message::ListSerialPorts),
+                    ]
+                    .spacing(6)
+                    .align_items(Alignment::Center),
+                    text_input("/dev/ttyACM0", port).on_input(Message::BuiltinSerialPortChanged),
+                    row![
+                        text("Baud").size(12).style(muted),
+                        pick_list(
+                            SERIAL_BAUD_RATES.to_vec(),
+                            Some(*baud),
+                            Message::BuiltinSerialBaudPicked,
+                        ),
+                        text("Payload").size(12).style(muted),
+                        pick_list(
+                            SerialFormatChoice::ALL.to_vec(),
+                            Some(SerialFormatChoice::of(*format)),
+                            Message::BuiltinSerialFormatPicked,
+                        ),
+                    ]
+                    .spacing(6)
+                    .align_items(Alignment::Center),
+                    text_input(
+                        match format {
+                            actions::SerialFormat::Text => "led on\\n",
+                            actions::SerialFormat::Hex => "FF 01 0A",
+                        },
+                        payload,
+                    )
+                    .on_input(Message::BuiltinSerialPayloadChanged),
+                    text(
+                        "Text is sent as UTF-8 with \\n, \\r, \\t and \\xHH escapes and {{name}} \
+                         for variables. The port is opened at 8 data bits, no parity and one \
+                         stop bit, and stays open so boards that reset when it opens do so \
+                         only once.",
+                    )
+                    .size(12)
+                    .style(muted),
+                ]
+                .spacing(6);
+                if let Err(e) = format.bytes(payload) {
+                    col = col.push(text(e.to_string()).size(12).style(danger));
+                }
+                if let Some(Err(e)) = &self.serial_ports {
+                    col = col.push(text(e).size(12).style(danger));
+                }
+                col.into()
+            }
+            BuiltinAction::WakeOnLan { mac, broadcast } => {
+                let muted = self.color_text_muted();
+                let mut col = column![
+                    text("MAC address").size(12).style(muted),
+                    text_input("00:1A:2B:3C:4D:5E", mac)
+                        .on_input(Message::BuiltinWakeOnLanMacChanged),
+                    text("Broadcast address").size(12).style(muted),
+                    text_input("255.255.255.255", broadcast)
+                        .on_input(Message::BuiltinWakeOnLanBroadcastChanged),
+                    text(
+                        "Sends a magic packet to UDP port 9 (add :port for another). Use the \
+                         broadcast address of the computer's network, e.g. 192.168.1.255, when \
+                         the default does not reach it; waking it must be enabled in its \
+                         firmware and network card settings.",
+                    )
+                    .size(12)
+                    .style(muted),
+                ]
+                .spacing(6);
+                // Variables are only known when the action runs.
+                if !mac.trim().is_empty() && !mac.contains("{{") {
+                    if let Err(e) = actions::parse_mac_address(mac) {
+                        let danger = self.active_theme.theme.palette().danger;
+                        col = col.push(text(e.to_string()).size(12).style(danger));
+                    }
+                }
+                col.into()
+            }
+            BuiltinAction::Git { repo, command } => column![
+                text("Repository").size(12).style(self.color_text_muted()),
+                text_input("/path/to/repo", repo).on_input(Message::BuiltinGitRepoChanged),
+                text("On press").size(12).style(self.color_text_muted()),
+                pick_list(
+                    GitCommandChoice::ALL.to_vec(),
+                    Some(GitCommandChoice::of(*command)),
+                    Message::BuiltinGitCommandPicked,
+                ),
+                text(
+                    "The key shows the branch, * for uncommitted changes and the commits ahead \
+                     (+) and behind (-) its upstream, and turns orange while there are changes.",
+                )
+                .size(12)
+                .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+            BuiltinAction::VsCode { workspace, command } => {
+                let known = self.vscode_workspaces.contains(workspace);
+                let (choice, label) = match command {
+                    actions::VsCodeCommand::Open => (VsCodeCommandChoice::Open, None),
+                    actions::VsCodeCommand::RunTask(label) => {
+                        (VsCodeCommandChoice::RunTask, Some(label))
+                    }
+                };
+                let mut col = column![
+                    text("Folder").size(12).style(self.color_text_muted()),
+                    pick_list(
+                        self.vscode_workspaces.clone(),
+                        known.then(|| workspace.clone()),
+                        Message::BuiltinVsCodeWorkspaceChanged,
+                    )
+                    .placeholder("Pick a recent folder…")
+                    .width(Length::Fill),
+                    text_input("/path/to/project", workspace)
+                        .on_input(Message::BuiltinVsCodeWorkspaceChanged),
+                    pick_list(
+                        [VsCodeCommandChoice::Open, VsCodeCommandChoice::RunTask].to_vec(),
+                        Some(choice),
+                        Message::BuiltinVsCodeCommandPicked,
+                    ),
+                ]
+                .spacing(6);
+                if let Some(label) = label {
+                    let tasks = match &self.vscode_tasks {
+                        Some((folder, tasks)) if folder == workspace => Some(tasks.clone()),
+                        _ => None,
+                    };
+                    col = col.push(text("Task").size(12).style(self.color_text_muted()));
+                    col = match tasks {
+                        Some(tasks) => col.push(
+                            pick_list(
+                                tasks,
+                                Some(label.clone()).filter(|l| !l.is_empty()),
+                                Message::BuiltinVsCodeTaskChanged,
+                            )
+                            .placeholder("Pick a task…")
+                            .width(Length::Fill),
+                        ),
+                        None => col.push(
+                            button(text("List tasks").size(12))
+                                .on_press(Message::ListVsCodeTasks(workspace.clone())),
+                        ),
+                    };
+                    col = col.push(
+                        text_input("Task label", label).on_input(Message::BuiltinVsCodeTaskChanged),
+                    );
+                    col = col.push(
+                        text(
+                            "Shell and process tasks from .vscode/tasks.json run in the folder; \
+                             the key shows whether the task is running, succeeded or failed.",
+                        )
+                        .size(12)
+                        .style(self.color_text_muted()),
+                    );
+                }
+                col.into()
+            }
+            BuiltinAction::Script { source, .. } => column![
+                text("Rhai script").size(12).style(self.color_text_muted()),
+                text_input("if run(\"make\") { set_key_text(\"OK\") }", source)
+                    .on_input(Message::BuiltinScriptSourceChanged),
+                text(
+                    "Available: run(command) -> bool, get_var(name), set_var(name, value), \
+                     set_key_text(text), sleep(ms), print(text). Stopped after 60 s."
+                )
+                .size(12)
+                .style(self.color_text_muted()),
+            ]
+            .spacing(6)
+            .into(),
+        }
     }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct ActionChoice {
-    plugin_id: String,
-    action_id: String,
-    label: String,
-}
 
-impl fmt::Display for ActionChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.label)
-    }
-}
+    fn view_macro_editor(&self, steps: &[actions::MacroStep]) -> Element<'_, Message> {
+        let record = match &self.macro_recorder {
+            Some(recorder) => button(text(format!("Stop ({} recorded)", recorder.len())).size(12))
+                .style(iced::theme::Button::Primary)
+                .on_press(Message::MacroRecordToggled),
+            None => button(text("Record").size(12))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::MacroRecordToggled),
+        };
+        let mut col = column![
+            row![
+                text("Macro steps").size(12).style(self.color_text_muted()),
+                horizontal_space(),
+                record,
+                button(text("+")).style(iced::theme::Button::Secondary).on_press(Message::MacroAddStep),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(8),
+        ]
+        .spacing(8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum ActionModeChoice {
-    None,
-    Plugin,
-    Builtin,
-}
+        if self.macro_recorder.is_some() {
+            col = col.push(
+                text("Recording: type in this window or press deck keys. Esc stops.")
+                    .size(12)
+                    .style(self.color_text_muted()),
+            );
+        }
 
-impl fmt::Display for ActionModeChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ActionModeChoice::None => write!(f, "None"),
-            ActionModeChoice::Plugin => write!(f, "Plugin"),
-            ActionModeChoice::Builtin => write!(f, "Builtin"),
+        if steps.is_empty() {
+            col = col.push(text("No steps yet.").style(self.color_text_muted()));
+            return col.into();
         }
-    }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum BuiltinKindChoice {
-    Macro,
-    IssueCommand,
-    KeyboardInput,
-    PlaySound,
-    SwitchProfile,
-    DeviceBrightness,
-    SystemMonitoring,
-}
+        for (i, s) in steps.iter().enumerate() {
+            let kind = match s.action.as_ref() {
+                ActionBinding::Plugin(_) => MacroStepKindChoice::PluginAction,
+                ActionBinding::Builtin(BuiltinAction::IssueCommand { .. }) => {
+                    MacroStepKindChoice::IssueCommand
+                }
+                ActionBinding::Builtin(BuiltinAction::KeyboardInput { .. }) => {
+                    MacroStepKindChoice::KeyboardInput
+                }
+                _ => MacroStepKindChoice::PluginAction,
+            };
 
-impl fmt::Display for BuiltinKindChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            BuiltinKindChoice::Macro => write!(f, "Macro"),
-            BuiltinKindChoice::IssueCommand => write!(f, "Issue Command"),
-            BuiltinKindChoice::KeyboardInput => write!(f, "Keyboard Input"),
-            BuiltinKindChoice::PlaySound => write!(f, "Play Sound"),
-            BuiltinKindChoice::SwitchProfile => write!(f, "Switch Profile"),
-            BuiltinKindChoice::DeviceBrightness => write!(f, "Device Brightness"),
-            BuiltinKindChoice::SystemMonitoring => write!(f, "System Monitoring"),
-        }
-    }
-}
+            let delay = s.delay_ms.map(|d| d.to_string()).unwrap_or_default();
+            let kind_picker = pick_list(
+                vec![
+                    MacroStepKindChoice::PluginAction,
+                    MacroStepKindChoice::IssueCommand,
+                    MacroStepKindChoice::KeyboardInput,
+                ],
+                Some(kind),
+                move |k| Message::MacroStepKindPicked { idx: i, kind: k },
+            );
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum SwitchProfileChoice {
-    Next,
-    Prev,
-    // Specific profile chosen from list.
-    To(ProfileId),
-}
+            let controls = row![
+                button(text("^"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::MacroMoveStepUp(i)),
+                button(text("v"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::MacroMoveStepDown(i)),
+                button(text("X"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::MacroRemoveStep(i)),
+            ]
+            .spacing(6);
 
-impl fmt::Display for SwitchProfileChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SwitchProfileChoice::Next => write!(f, "Next profile"),
-            SwitchProfileChoice::Prev => write!(f, "Previous profile"),
-            SwitchProfileChoice::To(id) => write!(f, "Profile {}", id.0),
-        }
-    }
-}
+            let delay_input = text_input("Delay ms", &delay).on_input(move |v| {
+                Message::MacroStepDelayChanged {
+                    idx: i,
+                    value: v,
+                }
+            });
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum BrightnessModeChoice {
-    Set,
-    Increase,
-    Decrease,
-}
+            let editor: Element<Message> = match s.action.as_ref() {
+                ActionBinding::Plugin(p) => {
+                    let current = self
+                        .actions
+                        .iter()
+                        .find(|a| a.plugin_id == p.plugin_id && a.action_id == p.action_id)
+                        .cloned();
+                    pick_list(self.actions.clone(), current, move |c| {
+                        Message::MacroStepPluginPicked { idx: i, choice: c }
+                    })
+                    .into()
+                }
+                ActionBinding::Builtin(BuiltinAction::IssueCommand { command, .. }) => {
+                    text_input("bash command…", command).on_input(move |v| {
+                        Message::MacroStepCommandChanged { idx: i, value: v }
+                    })
+                    .into()
+                }
+                ActionBinding::Builtin(BuiltinAction::KeyboardInput { text: Some(t), .. }) => {
+                    text_input("text to type…", t)
+                        .on_input(move |v| Message::MacroStepTextChanged { idx: i, value: v })
+                        .into()
+                }
+                ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+                    chord: Some(chord), ..
+                }) => text(format!("Shortcut: {chord}")).size(13).into(),
+                ActionBinding::Builtin(BuiltinAction::KeyboardInput { keys, .. }) => {
+                    text(format!("Keys: {}", keys.join(" "))).size(13).into()
+                }
+                // Recorded deck key presses can be any action.
+                other => match self.binding_hint(other) {
+                    Some(hint) => text(hint).size(13).into(),
+                    None => text("Unsupported step type (edit by changing kind).")
+                        .style(self.color_text_muted())
+                        .into(),
+                },
+            };
 
-impl fmt::Display for BrightnessModeChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            BrightnessModeChoice::Set => write!(f, "Set"),
-            BrightnessModeChoice::Increase => write!(f, "Increase"),
-            BrightnessModeChoice::Decrease => write!(f, "Decrease"),
+            col = col.push(
+                container(
+                    column![
+                        row![
+                            controls,
+                            horizontal_space(),
+                            text(format!("Step {}", i + 1)).style(self.color_text_muted()),
+                        ]
+                        .align_items(Alignment::Center),
+                        row![text("Kind").size(12).style(self.color_text_muted()), kind_picker]
+                            .spacing(10)
+                            .align_items(Alignment::Center),
+                        row![text("Delay").size(12).style(self.color_text_muted()), delay_input]
+                            .spacing(10)
+                            .align_items(Alignment::Center),
+                        editor,
+                    ]
+                    .spacing(6),
+                )
+                .padding(10)
+                .style(panel()),
+            );
         }
-    }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum MonitorKindChoice {
-    Cpu,
-    Memory,
-    LoadAverage,
-}
+        col.into()
+    }
 
-impl fmt::Display for MonitorKindChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            MonitorKindChoice::Cpu => write!(f, "CPU"),
-            MonitorKindChoice::Memory => write!(f, "Memory"),
-            MonitorKindChoice::LoadAverage => write!(f, "Load average"),
+    fn set_selected_action_mode(&mut self, mode: ActionModeChoice) {
+        match mode {
+            ActionModeChoice::None => {
+                if let Some(slot) = self.selected_binding_mut() {
+                    *slot = None;
+                }
+            }
+            ActionModeChoice::Plugin => {
+                // Keep existing plugin binding if present; otherwise choose first available action.
+                if matches!(
+                    self.selected_binding().and_then(|b| b.as_ref()),
+                    Some(ActionBinding::Plugin(_))
+                ) {
+                    return;
+                }
+                let Some(first) = self.actions.first().cloned() else {
+                    self.error = Some("No plugin actions available. Install a plugin first.".to_string());
+                    if let Some(slot) = self.selected_binding_mut() {
+                        *slot = None;
+                    }
+                    return;
+                };
+                let settings = default_settings_for_action(&self.plugins, &first);
+                if let Some(slot) = self.selected_binding_mut() {
+                    *slot = Some(ActionBinding::Plugin(PluginActionBinding {
+                        plugin_id: first.plugin_id,
+                        action_id: first.action_id,
+                        settings,
+                    }));
+                }
+            }
+            ActionModeChoice::Builtin => {
+                if matches!(
+                    self.selected_binding().and_then(|b| b.as_ref()),
+                    Some(ActionBinding::Builtin(_))
+                ) {
+                    return;
+                }
+                if let Some(slot) = self.selected_binding_mut() {
+                    *slot = Some(ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                        command: String::new(),
+                        cwd: None,
+                        timeout_ms: None,
+                    }));
+                }
+            }
         }
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum MacroStepKindChoice {
-    PluginAction,
-    IssueCommand,
-}
+    fn assign_dragged_action_to_key(&mut self, idx: usize, dragged: DraggedAction) {
+        let Some(p) = &mut self.profile else {
+            self.error = Some("[Action] No profile loaded.".to_string());
+            return;
+        };
+        let Some(k) = p.keys.get_mut(idx) else {
+            return;
+        };
 
-impl fmt::Display for MacroStepKindChoice {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            MacroStepKindChoice::PluginAction => write!(f, "Plugin action"),
-            MacroStepKindChoice::IssueCommand => write!(f, "Issue command"),
+        match dragged {
+            DraggedAction::Plugin(choice) => {
+                let settings = default_settings_for_action(&self.plugins, &choice);
+                apply_default_action_icon(&mut k.appearance, &choice);
+                k.action = Some(ActionBinding::Plugin(PluginActionBinding {
+                    plugin_id: choice.plugin_id,
+                    action_id: choice.action_id,
+                    settings,
+                }));
+            }
+            DraggedAction::Builtin(kind) => {
+                k.action = Some(ActionBinding::Builtin(kind.default_action()));
+            }
         }
     }
-}
 
-impl App {
-    fn apply_displays_if_connected(&self) -> Command<Message> {
-        let Some(c) = self.connected.as_ref() else {
-            return Command::none();
-        };
-        let Some(p) = self.profile.clone() else {
-            return Command::none();
+    fn set_selected_builtin_kind(&mut self, kind: BuiltinKindChoice) {
+        let Some(slot) = self.selected_binding_mut() else {
+            return;
         };
-        let controller = c.controller.clone();
-        Command::perform(apply_displays_async(controller, p), Message::DisplaysApplied)
+        *slot = Some(ActionBinding::Builtin(kind.default_action()));
     }
 
-    fn current_marketplace_source(&self) -> Option<&MarketplaceSource> {
-        self.marketplace
-            .selected_source_idx
-            .and_then(|i| self.marketplace.sources.get(i))
+    fn update_selected_builtin(&mut self, f: impl FnOnce(&mut BuiltinAction)) {
+        let Some(slot) = self.selected_binding_mut() else {
+            return;
+        };
+        let Some(ActionBinding::Builtin(b)) = slot.as_mut() else {
+            return;
+        };
+        f(b)
     }
 
-    fn marketplace_fetch_icons_for_current_page(&self) -> Command<Message> {
-        let Some(src) = self.current_marketplace_source().cloned() else {
-            return Command::none();
+    fn selected_builtin_brightness_value(&self) -> Option<i32> {
+        let b = self.selected_binding()?.as_ref()?;
+        let ActionBinding::Builtin(BuiltinAction::DeviceBrightness { mode }) = b else {
+            return None;
         };
+        Some(match mode {
+            actions::BrightnessMode::Set { percent } => *percent as i32,
+            actions::BrightnessMode::Increase { delta } => *delta as i32,
+            actions::BrightnessMode::Decrease { delta } => *delta as i32,
+        })
+    }
 
-        let q = self.marketplace.query.trim().to_ascii_lowercase();
-        let start = self.marketplace.page.saturating_mul(MARKETPLACE_PAGE_SIZE);
+    fn macro_add_step(&mut self) {
+        let default_plugin = self.actions.first().cloned();
+        let default_settings = default_plugin
+            .as_ref()
+            .map(|c| default_settings_for_action(&self.plugins, c));
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
 
-        let mut cmds = vec![];
-        for p in self
-            .marketplace
-            .plugins
-            .iter()
-            .filter(|p| {
-                if q.is_empty() {
-                    true
-                } else {
-                    p.name.to_ascii_lowercase().contains(&q)
-                        || p.id.to_ascii_lowercase().contains(&q)
-                        || p.description.to_ascii_lowercase().contains(&q)
-                }
-            })
-            .skip(start)
-            .take(MARKETPLACE_PAGE_SIZE)
-        {
-            let Some(icon_url) = marketplace_icon_url(&src, p) else {
-                continue;
+            let action = if let Some(first) = default_plugin {
+                ActionBinding::Plugin(PluginActionBinding {
+                    plugin_id: first.plugin_id,
+                    action_id: first.action_id,
+                    settings: default_settings.unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new())),
+                })
+            } else {
+                ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                    command: String::new(),
+                    cwd: None,
+                    timeout_ms: None,
+                })
             };
-            let key = format!("{}|{}", src.index_url, p.id);
-            if self.marketplace.icon_cache.contains_key(&key) {
-                continue;
-            }
-            cmds.push(Command::perform(
-                fetch_icon_async(icon_url),
-                {
-                    let key = key.clone();
-                    move |bytes| Message::MarketplaceIconLoaded { key, bytes }
-                },
-            ));
-        }
 
-        if cmds.is_empty() {
-            Command::none()
-        } else {
-            Command::batch(cmds)
-        }
+            steps.push(actions::MacroStep {
+                action: Box::new(action),
+                delay_ms: None,
+            });
+        });
     }
 
-    fn start_action_sequence(
-        &mut self,
-        control: InvocationControl,
-        event: InvocationEvent,
-        binding: &ActionBinding,
-    ) -> Command<Message> {
-        let steps = match actions::expand(binding) {
-            Ok(steps) => steps,
-            Err(e) => {
-                tracing::error!(?control, ?event, error = %e, "failed to expand action binding");
-                return Command::perform(async { () }, move |_| {
-                    Message::ActionSeqStepDone {
-                        seq_id: 0,
-                        res: Err(e.to_string()),
-                    }
-                })
+    fn macro_remove_step(&mut self, idx: usize) {
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
+            if idx < steps.len() {
+                steps.remove(idx);
             }
-        };
+        });
+    }
+
+    fn macro_move_step(&mut self, idx: usize, up: bool) {
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
+            if steps.is_empty() || idx >= steps.len() {
+                return;
+            }
+            if up {
+                if idx == 0 {
+                    return;
+                }
+                steps.swap(idx, idx - 1);
+            } else {
+                if idx + 1 >= steps.len() {
+                    return;
+                }
+                steps.swap(idx, idx + 1);
+            }
+        });
+    }
 
-        let seq_id = self.next_action_seq_id;
-        self.next_action_seq_id = self.next_action_seq_id.saturating_add(1);
+    fn macro_set_step_kind(&mut self, idx: usize, kind: MacroStepKindChoice) {
+        let default_plugin = self.actions.first().cloned();
+        let default_settings = default_plugin
+            .as_ref()
+            .map(|c| default_settings_for_action(&self.plugins, c));
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
+            let Some(step) = steps.get_mut(idx) else {
+                return;
+            };
+            step.action = Box::new(match kind {
+                MacroStepKindChoice::PluginAction => {
+                    if let Some(first) = default_plugin {
+                        ActionBinding::Plugin(PluginActionBinding {
+                            plugin_id: first.plugin_id,
+                            action_id: first.action_id,
+                            settings: default_settings.unwrap_or_else(|| {
+                                serde_json::Value::Object(serde_json::Map::new())
+                            }),
+                        })
+                    } else {
+                        ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                            command: String::new(),
+                            cwd: None,
+                            timeout_ms: None,
+                        })
+                    }
+                }
+                MacroStepKindChoice::IssueCommand => ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                    command: String::new(),
+                    cwd: None,
+                    timeout_ms: None,
+                }),
+                MacroStepKindChoice::KeyboardInput => {
+                    ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+                        text: Some(String::new()),
+                        keys: vec![],
+                        chord: None,
+                    })
+                }
+            });
+        });
+    }
 
-        self.action_sequences.insert(
-            seq_id,
-            ActionSequence {
-                origin_control: control.clone(),
-                origin_event: event.clone(),
-                steps: VecDeque::from(steps),
-            },
-        );
+    fn macro_set_step_delay(&mut self, idx: usize, value: String) {
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
+            let Some(step) = steps.get_mut(idx) else {
+                return;
+            };
+            let s = value.trim();
+            step.delay_ms = if s.is_empty() { None } else { s.parse::<u64>().ok() };
+        });
+    }
 
-        tracing::info!(?control, ?event, seq_id, "starting action sequence");
-        Command::perform(async { () }, move |_| Message::ActionSeqContinue(seq_id))
+    fn macro_set_step_plugin(&mut self, idx: usize, choice: ActionChoice) {
+        let settings = default_settings_for_action(&self.plugins, &choice);
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
+            let Some(step) = steps.get_mut(idx) else {
+                return;
+            };
+            step.action = Box::new(ActionBinding::Plugin(PluginActionBinding {
+                plugin_id: choice.plugin_id,
+                action_id: choice.action_id,
+                settings,
+            }));
+        });
     }
 
-    fn run_next_action_step(&mut self, seq_id: u64) -> Command<Message> {
-        let Some((origin_control, origin_event)) = self
-            .action_sequences
-            .get(&seq_id)
-            .map(|s| (s.origin_control.clone(), s.origin_event.clone()))
-        else {
-            return Command::none();
-        };
+    fn macro_set_step_command(&mut self, idx: usize, value: String) {
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
+            };
+            let Some(step) = steps.get_mut(idx) else {
+                return;
+            };
+            step.action = Box::new(ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                command: value,
+                cwd: None,
+                timeout_ms: None,
+            }));
+        });
+    }
 
-        let step = {
-            let Some(seq) = self.action_sequences.get_mut(&seq_id) else {
-                return Command::none();
+    fn macro_set_step_text(&mut self, idx: usize, value: String) {
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Macro { steps } = b else {
+                return;
             };
-            seq.steps.pop_front()
-        };
+            let Some(step) = steps.get_mut(idx) else {
+                return;
+            };
+            step.action = Box::new(ActionBinding::Builtin(BuiltinAction::KeyboardInput {
+                text: Some(value),
+                keys: vec![],
+                chord: None,
+            }));
+        });
+    }
 
-        let Some(step) = step else {
-            self.action_sequences.remove(&seq_id);
-            tracing::debug!(seq_id, "action sequence finished");
-            return Command::none();
+    /// While recording a macro, a press of deck key `idx` records the key's action.
+    fn record_deck_key(&mut self, idx: usize) {
+        let Some(recorder) = &mut self.macro_recorder else {
+            return;
         };
-
-        match step {
-            ActionStep::DelayMs(ms) => Command::perform(sleep_ms_async(ms), move |_| {
-                tracing::debug!(seq_id, delay_ms = ms, "action sequence delay");
-                Message::ActionSeqContinue(seq_id)
-            }),
-            ActionStep::Plugin(p) => {
-                tracing::info!(
-                    seq_id,
-                    ?origin_control,
-                    ?origin_event,
-                    plugin_id = %p.plugin_id,
-                    action_id = %p.action_id,
-                    "executing plugin action"
-                );
-                let Some(plugin) = self.plugins.iter().find(|pl| pl.manifest.id == p.plugin_id).cloned() else {
-                    return Command::perform(async { () }, move |_| Message::ActionSeqStepDone {
-                        seq_id,
-                        res: Err(format!("[Action] Plugin not installed: {}", p.plugin_id)),
-                    });
-                };
-                let action_id = p.action_id.clone();
-                let settings = p.settings.clone();
-                Command::perform(
-                    invoke_action_async(
-                        plugin,
-                        action_id,
-                        origin_control.clone(),
-                        origin_event.clone(),
-                        settings,
-                    ),
-                    move |res| Message::ActionSeqStepDone { seq_id, res },
-                )
-            }
-            ActionStep::Builtin(b) => {
-                tracing::info!(seq_id, ?origin_control, builtin = ?b, "executing builtin action");
-                self.execute_builtin_step(seq_id, origin_control.clone(), b)
-            }
+        // The key whose macro is being recorded would make the macro run itself.
+        if self.selected_control == Some(SelectedControl::Key(idx)) {
+            return;
+        }
+        let action = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(idx))
+            .and_then(|k| k.action.clone());
+        if let Some(action) = action {
+            recorder.record(action);
         }
     }
 
-    fn execute_builtin_step(
-        &mut self,
-        seq_id: u64,
-        origin_control: InvocationControl,
-        b: actions::BuiltinAction,
-    ) -> Command<Message> {
-        match b {
-            BuiltinAction::Macro { .. } => {
-                // Macro should have been expanded away by `actions::expand`.
-                Command::perform(async { () }, move |_| Message::ActionSeqStepDone {
-                    seq_id,
-                    res: Err("Internal: macro was not expanded".to_string()),
-                })
-            }
-            BuiltinAction::IssueCommand { command, cwd, timeout_ms } => {
-                tracing::info!(seq_id, ?origin_control, %command, "builtin: issue_command");
-                Command::perform(issue_command_async(command, cwd, timeout_ms), move |res| {
-                    Message::ActionSeqStepDone { seq_id, res }
-                })
-            }
-            BuiltinAction::KeyboardInput { text, keys } => {
-                tracing::info!(
-                    seq_id,
-                    ?origin_control,
-                    has_text = text.is_some(),
-                    keys_len = keys.len(),
-                    "builtin: keyboard_input"
-                );
-                Command::perform(keyboard_input_async(text, keys), move |res| {
-                    Message::ActionSeqStepDone { seq_id, res }
-                })
-            }
-            BuiltinAction::PlaySound { path } => {
-                tracing::info!(seq_id, ?origin_control, %path, "builtin: play_sound");
-                Command::perform(play_sound_async(path), move |res| {
-                Message::ActionSeqStepDone { seq_id, res }
-                })
-            }
-            BuiltinAction::SwitchProfile { mode } => {
-                tracing::info!(seq_id, ?origin_control, mode = ?mode, "builtin: switch_profile");
-                let target = match mode {
-                    actions::SwitchProfileMode::To { profile_id } => Some(ProfileId(profile_id)),
-                    actions::SwitchProfileMode::Next => {
-                        let current = self.selected_profile;
-                        self.profiles
-                            .iter()
-                            .position(|p| Some(p.id) == current)
-                            .map(|i| self.profiles[(i + 1) % self.profiles.len()].id)
-                    }
-                    actions::SwitchProfileMode::Prev => {
-                        let current = self.selected_profile;
-                        self.profiles.iter().position(|p| Some(p.id) == current).map(|i| {
-                            let n = self.profiles.len();
-                            self.profiles[(i + n - 1) % n].id
-                        })
-                    }
-                };
-
-                let Some(id) = target else {
-                    return Command::perform(async { () }, move |_| Message::ActionSeqStepDone {
-                        seq_id,
-                        res: Err("[Action] No profiles available to switch.".to_string()),
-                    });
-                };
-
-                self.selected_profile = Some(id);
-                // Load profile and continue sequence.
-                Command::batch([
-                    Command::perform(load_profile_async(id), Message::ProfileLoaded),
-                    Command::perform(async { () }, move |_| Message::ActionSeqContinue(seq_id)),
-                ])
-            }
-            BuiltinAction::DeviceBrightness { mode } => {
-                tracing::info!(seq_id, ?origin_control, mode = ?mode, "builtin: device_brightness");
-                let Some(c) = &mut self.connected else {
-                    return Command::perform(async { () }, move |_| Message::ActionSeqStepDone {
-                        seq_id,
-                        res: Err("[Action] Not connected to a device.".to_string()),
-                    });
-                };
+    fn set_condition_field(&mut self, field: ConditionField, value: String) {
+        use actions::Condition;
 
-                let new_val = match mode {
-                    actions::BrightnessMode::Set { percent } => percent,
-                    actions::BrightnessMode::Increase { delta } => c.brightness.saturating_add(delta),
-                    actions::BrightnessMode::Decrease { delta } => c.brightness.saturating_sub(delta),
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Conditional { condition, .. } = b else {
+                return;
+            };
+            match (condition, field) {
+                (Condition::ProcessRunning { name }, ConditionField::Name) => *name = value,
+                (Condition::FileExists { path }, ConditionField::Path) => *path = value,
+                (Condition::TimeRange { start, .. }, ConditionField::Start) => *start = value,
+                (Condition::TimeRange { end, .. }, ConditionField::End) => *end = value,
+                (Condition::VariableEquals { name, .. }, ConditionField::Name) => {
+                    *name = value.trim().to_string()
                 }
-                .clamp(0, 100);
-
-                c.brightness = new_val;
-                let controller = c.controller.clone();
-                Command::perform(set_brightness_async(controller, new_val), move |res| {
-                    Message::ActionSeqStepDone { seq_id, res }
-                })
-            }
-            BuiltinAction::SystemMonitoring { .. } => {
-                tracing::debug!(seq_id, ?origin_control, "builtin: system_monitoring (noop execute)");
-                // Live display is handled via `binding_hint` + periodic sys refresh.
-                // Executing it is a no-op for now.
-                Command::perform(async { () }, move |_| Message::ActionSeqStepDone {
-                    seq_id,
-                    res: Ok(()),
-                })
+                (Condition::VariableEquals { value: v, .. }, ConditionField::Value) => *v = value,
+                (Condition::ShellExitCode { command, .. }, ConditionField::Command) => {
+                    *command = value
+                }
+                (Condition::ShellExitCode { code, .. }, ConditionField::Code) => {
+                    if let Ok(v) = value.trim().parse() {
+                        *code = v;
+                    }
+                }
+                _ => {}
             }
-        }
-    }
-
-    fn view_topbar(&self) -> Element<'_, Message> {
-        let device_selected = self
-            .selected_device
-            .and_then(|id| self.device_choices.iter().find(|d| d.id == id).cloned());
-        let profile_selected = self
-            .selected_profile
-            .and_then(|id| self.profile_choices.iter().find(|p| p.id == id).cloned());
-
-        let status = match (&self.connected, self.connecting) {
-            (None, true) => "Connecting…".to_string(),
-            (None, false) => "Not connected".to_string(),
-            (Some(c), _) => format!(
-                "Connected to {} • {} keys • id {}",
-                c.name, c.key_count, c.id.0
-            ),
-        };
-
-        let bar = row![
-            column![
-                text("RiverDeck-Redux").size(22),
-                text(status).size(12).style(color_text_muted()),
-            ]
-            .spacing(2),
-            horizontal_space(),
-            column![
-                text("Device").size(12).style(color_text_muted()),
-                pick_list(
-                    self.device_choices.clone(),
-                    device_selected,
-                    Message::DevicePicked
-                )
-            ]
-            .spacing(4),
-            column![
-                text("Profile").size(12).style(color_text_muted()),
-                row![
-                    pick_list(
-                        self.profile_choices.clone(),
-                        profile_selected,
-                        Message::ProfilePicked
-                    ),
-                    button(text("R"))
-                        .style(iced::theme::Button::Secondary)
-                        .on_press(Message::RefreshProfiles),
-                    button(text("+"))
-                        .style(iced::theme::Button::Secondary)
-                        .on_press(Message::CreateProfile),
-                ]
-                .spacing(8)
-                .align_items(Alignment::Center)
-            ]
-            .spacing(4),
-            button(text("Refresh"))
-                .style(iced::theme::Button::Secondary)
-                .on_press(Message::RefreshDevices),
-        ]
-        .align_items(Alignment::Center)
-        .spacing(14);
-
-        container(bar)
-            .padding(12)
-            .style(panel())
-            .width(Length::Fill)
-            .into()
-    }
-
-    fn view_error_banner(&self, err: &str) -> Element<'_, Message> {
-        container(text(err).style(Color::from_rgb8(255, 160, 160)))
-            .padding(10)
-            .style(error_banner())
-            .width(Length::Fill)
-            .into()
+        });
     }
 
-    fn view_sidebar(&self) -> Element<'_, Message> {
-        let plugins_section = self.view_sidebar_plugins();
-
-        let content = column![plugins_section].spacing(12);
-
-        container(scrollable(content).height(Length::Fill))
-            .padding(12)
-            .width(Length::Fixed(300.0))
-            .height(Length::Fill)
-            .style(panel())
-            .into()
+    fn update_selected_branch(
+        &mut self,
+        branch: Branch,
+        f: impl FnOnce(&mut Option<Box<ActionBinding>>),
+    ) {
+        self.update_selected_builtin(|b| {
+            let BuiltinAction::Conditional {
+                then, otherwise, ..
+            } = b
+            else {
+                return;
+            };
+            f(match branch {
+                Branch::Then => then,
+                Branch::Otherwise => otherwise,
+            });
+        });
     }
 
-    fn view_sidebar_plugins(&self) -> Element<'_, Message> {
-        let mut col = column![text("Plugins").size(16)].spacing(8);
-
-        col = col.push(
-            text_input(
-                "Local plugin dir (contains manifest.json)",
-                &self.install_plugin_path,
-            )
-            .on_input(Message::InstallPluginPathChanged),
-        );
-        col = col.push(
-            row![
-                button(text("Install"))
-                    .style(iced::theme::Button::Primary)
-                    .on_press(Message::InstallPluginFromPath),
-                button(text("Refresh"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::RefreshPlugins),
-                button(text("Marketplace"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::OpenMarketplace),
-            ]
-            .spacing(8),
-        );
-
-        if self.plugins.is_empty() {
-            col = col.push(text("No plugins installed."));
-        } else {
-            for p in &self.plugins {
-                col = col.push(text(format!("• {}", p.manifest.name)).size(13));
+    fn set_branch_kind(&mut self, branch: Branch, kind: BranchKindChoice) {
+        let default_plugin = self.actions.first().cloned();
+        let default_settings = default_plugin
+            .as_ref()
+            .map(|c| default_settings_for_action(&self.plugins, c));
+        self.update_selected_branch(branch, |slot| {
+            if branch_kind(slot.as_deref()) == kind {
+                return;
             }
-        }
-
-        col.into()
+            let command = || {
+                ActionBinding::Builtin(BuiltinAction::IssueCommand {
+                    command: String::new(),
+                    cwd: None,
+                    timeout_ms: None,
+                })
+            };
+            *slot = match kind {
+                BranchKindChoice::Nothing => None,
+                BranchKindChoice::PluginAction => Some(Box::new(match default_plugin {
+                    Some(first) => ActionBinding::Plugin(PluginActionBinding {
+                        plugin_id: first.plugin_id,
+                        action_id: first.action_id,
+                        settings: default_settings.unwrap_or_else(|| {
+                            serde_json::Value::Object(serde_json::Map::new())
+                        }),
+                    }),
+                    None => command(),
+                })),
+                BranchKindChoice::IssueCommand => Some(Box::new(command())),
+            };
+        });
     }
 
-    fn view_actions_panel(&self) -> Element<'_, Message> {
-        let header = text("Actions").size(16);
-
-        let search = text_input("Search actions…", &self.action_search)
-            .on_input(Message::ActionSearchChanged);
-
-        let q = self.action_search.trim().to_ascii_lowercase();
-        let builtin_actions = [
-            BuiltinKindChoice::Macro,
-            BuiltinKindChoice::IssueCommand,
-            BuiltinKindChoice::KeyboardInput,
-            BuiltinKindChoice::PlaySound,
-            BuiltinKindChoice::SwitchProfile,
-            BuiltinKindChoice::DeviceBrightness,
-            BuiltinKindChoice::SystemMonitoring,
-        ];
+    fn view_conditional_editor(
+        &self,
+        condition: &actions::Condition,
+        then: Option<&ActionBinding>,
+        otherwise: Option<&ActionBinding>,
+    ) -> Element<'_, Message> {
+        use actions::Condition;
 
-        let mut list = column![].spacing(8);
-        let mut any = false;
+        let field = |label: &str, placeholder: &str, value: &str, field: ConditionField| {
+            column![
+                text(label.to_string()).size(12).style(self.color_text_muted()),
+                text_input(placeholder, value)
+                    .on_input(move |v| Message::ConditionFieldChanged { field, value: v }),
+            ]
+            .spacing(6)
+        };
 
-        // Builtin actions (always available).
-        for k in builtin_actions {
-            let label = k.to_string();
-            if !q.is_empty() && !label.to_ascii_lowercase().contains(&q) {
-                continue;
+        let fields: Element<Message> = match condition {
+            Condition::ProcessRunning { name } => {
+                field("Process name", "obs", name, ConditionField::Name).into()
             }
-            any = true;
-            list = list.push(
-                mouse_area(
-                    container(
-                        row![
-                            text(label).size(13),
-                            horizontal_space(),
-                            text("drag").size(12).style(color_text_muted()),
-                        ]
-                        .align_items(Alignment::Center),
-                    )
-                    .padding(8)
-                    .style(panel()),
-                )
-                .on_press(Message::StartDragAction(DraggedAction::Builtin(k)))
-                .interaction(iced::mouse::Interaction::Grab),
-            );
-        }
-
-        // Plugin actions.
-        for a in self.actions.iter() {
-            if !q.is_empty() && !a.label.to_ascii_lowercase().contains(&q) {
-                continue;
+            Condition::FileExists { path } => {
+                field("Path", "/path/to/file", path, ConditionField::Path).into()
             }
-            any = true;
-            list = list.push(
-                mouse_area(
-                    container(
-                        row![
-                            text(&a.label).size(13),
-                            horizontal_space(),
-                            text("drag").size(12).style(color_text_muted()),
-                        ]
-                        .align_items(Alignment::Center),
-                    )
-                    .padding(8)
-                    .style(panel()),
-                )
-                .on_press(Message::StartDragAction(DraggedAction::Plugin(a.clone())))
-                .interaction(iced::mouse::Interaction::Grab),
-            );
-        }
-
-        if !any {
-            list = list.push(
-                text(if self.actions.is_empty() {
-                    "No actions match your search."
-                } else {
-                    "No actions match your search."
-                })
-                .size(13)
-                .style(color_text_muted()),
-            );
-        }
-
-        let drag_hint: Element<Message> = if let Some(d) = &self.drag.dragging {
-            row![
-                text(format!("Dragging: {}", d.label())).size(12),
-                horizontal_space(),
-                button(text("Cancel"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::CancelDragAction),
+            Condition::TimeRange { start, end } => row![
+                field("From (HH:MM)", "09:00", start, ConditionField::Start),
+                field("Until (HH:MM)", "17:00", end, ConditionField::End),
             ]
-            .align_items(Alignment::Center)
-            .into()
-        } else {
-            text("Drag an action onto a key to assign.").size(12).style(color_text_muted()).into()
+            .spacing(10)
+            .into(),
+            Condition::VariableEquals { name, value } => row![
+                field("Variable", "scene", name, ConditionField::Name),
+                field("Value", "Gaming", value, ConditionField::Value),
+            ]
+            .spacing(10)
+            .into(),
+            Condition::ShellExitCode { command, code } => column![
+                field("Command", "bash command…", command, ConditionField::Command),
+                field("Exit code", "0", &code.to_string(), ConditionField::Code),
+            ]
+            .spacing(6)
+            .into(),
         };
 
-        let content = column![
-            header,
-            horizontal_rule(1),
-            drag_hint,
-            search,
-            scrollable(list).height(Length::Fill),
+        column![
+            text("If").size(12).style(self.color_text_muted()),
+            pick_list(
+                vec![
+                    ConditionKindChoice::ProcessRunning,
+                    ConditionKindChoice::FileExists,
+                    ConditionKindChoice::TimeRange,
+                    ConditionKindChoice::VariableEquals,
+                    ConditionKindChoice::ShellExitCode,
+                ],
+                Some(ConditionKindChoice::of(condition)),
+                Message::ConditionKindPicked,
+            ),
+            fields,
+            self.view_branch_editor("Then", Branch::Then, then),
+            self.view_branch_editor("Otherwise", Branch::Otherwise, otherwise),
+            text("The condition is checked each time the binding fires.")
+                .size(12)
+                .style(self.color_text_muted()),
         ]
-        .spacing(10);
-
-        container(content)
-            .padding(12)
-            .width(Length::Fixed(360.0))
-            .height(Length::Fill)
-            .style(panel())
-            .into()
+        .spacing(8)
+        .into()
     }
 
-    fn view_marketplace(&self) -> Element<'_, Message> {
-        let header = row![
-            text("Plugin Marketplace").size(18),
-            horizontal_space(),
-            button(text("Back"))
-                .style(iced::theme::Button::Secondary)
-                .on_press(Message::CloseMarketplace),
-        ]
-        .align_items(Alignment::Center)
-        .spacing(10);
-
-        let selected = self.current_marketplace_source().cloned();
-
-        let source_picker = pick_list(
-            self.marketplace.sources.clone(),
-            selected.clone(),
-            Message::MarketplaceSourcePicked,
+    fn view_branch_editor(
+        &self,
+        label: &'static str,
+        branch: Branch,
+        binding: Option<&ActionBinding>,
+    ) -> Element<'_, Message> {
+        let kind_picker = pick_list(
+            vec![
+                BranchKindChoice::Nothing,
+                BranchKindChoice::PluginAction,
+                BranchKindChoice::IssueCommand,
+            ],
+            Some(branch_kind(binding)),
+            move |kind| Message::BranchKindPicked { branch, kind },
         );
 
-        let url_row = row![
-            text("Marketplace").size(12).style(color_text_muted()),
-            source_picker,
-            horizontal_space(),
-            button(text("Refresh"))
-                .style(iced::theme::Button::Secondary)
-                .on_press(Message::MarketplaceRefresh),
-        ]
-        .spacing(10)
-        .align_items(Alignment::Center);
+        let editor: Option<Element<Message>> = match binding {
+            None => None,
+            Some(ActionBinding::Plugin(p)) => {
+                let current = self
+                    .actions
+                    .iter()
+                    .find(|a| a.plugin_id == p.plugin_id && a.action_id == p.action_id)
+                    .cloned();
+                Some(
+                    pick_list(self.actions.clone(), current, move |choice| {
+                        Message::BranchPluginPicked { branch, choice }
+                    })
+                    .into(),
+                )
+            }
+            Some(ActionBinding::Builtin(BuiltinAction::IssueCommand { command, .. })) => Some(
+                text_input("bash command…", command)
+                    .on_input(move |value| Message::BranchCommandChanged { branch, value })
+                    .into(),
+            ),
+            Some(_) => Some(
+                text("Unsupported branch action (edit by changing kind).")
+                    .style(self.color_text_muted())
+                    .into(),
+            ),
+        };
 
-        let search = text_input("Search plugins…", &self.marketplace.query)
-            .on_input(Message::MarketplaceSearchChanged);
+        let mut col = column![row![text(label).size(12).style(self.color_text_muted()), kind_picker]
+            .spacing(10)
+            .align_items(Alignment::Center)]
+        .spacing(6);
+        if let Some(editor) = editor {
+            col = col.push(editor);
+        }
+        container(col).padding(10).style(panel()).into()
+    }
 
-        let q = self.marketplace.query.trim().to_ascii_lowercase();
-        let list_iter = self.marketplace.plugins.iter().filter(|p| {
-            if q.is_empty() {
-                true
-            } else {
-                p.name.to_ascii_lowercase().contains(&q)
-                    || p.id.to_ascii_lowercase().contains(&q)
-                    || p.description.to_ascii_lowercase().contains(&q)
+    fn view_deck_preview(
+        &self,
+        caps: &DeviceCapabilities,
+        pressed: &[bool],
+    ) -> Element<'_, Message> {
+        let (cols, rows) = (usize::from(caps.key_grid.0), usize::from(caps.key_grid.1));
+        let key_count = caps.key_count;
+        let (key, gap, pad, radius) = deck_metrics(key_count);
+
+        let mut grid = column![].spacing(gap as u16);
+        for r in 0..rows {
+            let mut line = row![].spacing(gap as u16);
+            for c in 0..cols {
+                let idx = r * cols + c;
+                if idx >= key_count as usize {
+                    break;
+                }
+                line = line.push(self.view_deck_key(idx, pressed));
             }
-        });
-        let matches = list_iter.collect::<Vec<_>>();
-        let total_matches = matches.len();
-        let page_count = (total_matches + MARKETPLACE_PAGE_SIZE - 1) / MARKETPLACE_PAGE_SIZE;
-        let page = self.marketplace.page.min(page_count.saturating_sub(1));
-        let start = page.saturating_mul(MARKETPLACE_PAGE_SIZE);
-        let end = (start + MARKETPLACE_PAGE_SIZE).min(total_matches);
-        let shown = if total_matches == 0 { 0 } else { end.saturating_sub(start) };
+            grid = grid.push(line);
+        }
 
-        let mut list = column![].spacing(10);
-        for p in matches.into_iter().skip(start).take(MARKETPLACE_PAGE_SIZE) {
-            let icon: Element<Message> = if let Some(src) = &selected {
-                let key = format!("{}|{}", src.index_url, p.id);
-                if let Some(handle) = self.marketplace.icon_cache.get(&key) {
-                    image(handle.clone())
-                        .width(Length::Fixed(32.0))
-                        .height(Length::Fixed(32.0))
-                        .into()
-                } else {
-                    container(text(""))
-                        .width(Length::Fixed(32.0))
-                        .height(Length::Fixed(32.0))
-                        .style(panel())
-                        .into()
+        // Stream Deck+ preview: keys + touch strip + dials.
+        let has_strip = caps.lcd_strip_size.is_some() && caps.supports_dials();
+        let content = if has_strip {
+            let strip_h = 52.0;
+            let dial_size = 56.0;
+            let strip_selected = self.selected_control == Some(SelectedControl::TouchStrip);
+
+            let strip_content: Element<Message> = match self.preview_image(Slot::TouchStrip) {
+                Some(handle) => image(handle).width(Length::Fill).height(Length::Fill).into(),
+                None => text("Touch strip").size(12).style(self.color_text_muted()).into(),
+            };
+            let strip = container(strip_content)
+            .width(Length::Fill)
+            .height(Length::Fixed(strip_h))
+            .center_x()
+            .center_y()
+            .style(iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
+                let p = theme.extended_palette();
+                iced::widget::container::Appearance {
+                    background: Some(Background::Color(p.background.base.color)),
+                    text_color: Some(p.background.base.text),
+                    border: Border {
+                        // Square strip (sleek / hardware-like)
+                        radius: 0.0.into(),
+                        width: 1.0,
+                        color: if strip_selected {
+                            Color::from_rgba8(255, 255, 255, 0.22)
+                        } else {
+                            Color::from_rgba8(255, 255, 255, 0.08)
+                        },
+                    },
+                    shadow: Shadow::default(),
                 }
-            } else {
-                container(text(""))
-                    .width(Length::Fixed(32.0))
-                    .height(Length::Fixed(32.0))
-                    .style(panel())
-                    .into()
+            })));
+
+            let dial = |idx: usize| {
+                let dial_selected = self.selected_control == Some(SelectedControl::Dial(idx));
+                let label = self
+                    .profile
+                    .as_ref()
+                    .and_then(|p| p.dials.get(idx))
+                    .map(|d| d.label.clone())
+                    .filter(|s| !s.trim().is_empty())
+                    .unwrap_or_else(|| format!("Dial {}", idx + 1));
+                container(text(label).size(11).style(self.color_text_muted()))
+                    .width(Length::Fixed(dial_size))
+                    .height(Length::Fixed(dial_size))
+                    .center_x()
+                    .center_y()
+                    .style(iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
+                        let p = theme.extended_palette();
+                        iced::widget::container::Appearance {
+                            background: Some(Background::Color(p.background.base.color)),
+                            text_color: Some(p.background.base.text),
+                            border: Border {
+                                // Fully round knobs.
+                                radius: 999.0.into(),
+                                width: 1.0,
+                                color: if dial_selected {
+                                    Color::from_rgba8(255, 255, 255, 0.22)
+                                } else {
+                                    Color::from_rgba8(255, 255, 255, 0.08)
+                                },
+                            },
+                            shadow: Shadow::default(),
+                        }
+                    })))
             };
 
-            let mut body = column![text(&p.name).size(14)]
-                .spacing(4)
-                .push(text(p.id.clone()).size(12).style(color_text_muted()));
-
-            if !p.version.is_empty() {
-                body = body.push(text(format!("v{}", p.version)).size(12).style(color_text_muted()));
-            }
-            if !p.description.is_empty() {
-                body = body.push(text(p.description.clone()).size(12).style(color_text_muted()));
+            let strip = mouse_area(strip).on_press(Message::SelectControl(SelectedControl::TouchStrip));
+            let mut dial_row = row![].spacing(12).align_items(Alignment::Center);
+            for idx in 0..usize::from(caps.dials) {
+                let select = Message::SelectControl(SelectedControl::Dial(idx));
+                dial_row = dial_row.push(mouse_area(dial(idx)).on_press(select));
             }
+            let dials = container(dial_row)
+            .width(Length::Fill)
+            .center_x();
 
-            let is_installed = self.plugins.iter().any(|ip| ip.manifest.id == p.id);
-            let is_installing = self
-                .marketplace
-                .installing
-                .as_deref()
-                .is_some_and(|id| id == p.id);
-            let is_selected = self
-                .marketplace
-                .selected
-                .as_ref()
-                .is_some_and(|sel| sel.id == p.id);
-            let can_install_direct = selected
+            column![grid, strip, dials].spacing(gap as u16)
+        } else {
+            grid
+        };
+
+        let width = (cols as f32 * key) + ((cols - 1) as f32 * gap) + 2.0 * pad;
+        let base_height = (rows as f32 * key) + ((rows - 1) as f32 * gap) + 2.0 * pad;
+        let extra = if has_strip {
+            // Touch strip + dials + gaps between sections
+            52.0 + 56.0 + (gap * 2.0)
+        } else {
+            0.0
+        };
+
+        let deck = container(content)
+            .padding(pad as u16)
+            .style(deck_body_style(self.active_theme.radii.deck.unwrap_or(radius)))
+            .width(Length::Fixed(width))
+            .height(Length::Fixed(base_height + extra));
+
+        deck.into()
+    }
+
+    fn view_deck_key(&self, idx: usize, pressed: &[bool]) -> Element<'_, Message> {
+        let is_pressed = pressed.get(idx).copied().unwrap_or(false);
+        let is_playing = self
+            .connected
+            .as_ref()
+            .is_some_and(|c| c.playing_sounds.contains(&(idx as u8)));
+        let is_recording = self.connected.as_ref().is_some_and(|c| c.screen_recording)
+            && self
+                .profile
                 .as_ref()
-                .and_then(|src| resolve_marketplace_download_url(src, p))
-                .is_some();
-            let can_install_repo = p
-                .repository
-                .as_deref()
-                .and_then(parse_github_owner_repo)
-                .is_some();
-            let can_install = can_install_direct || can_install_repo;
+                .and_then(|p| p.keys.get(idx)?.action.as_ref())
+                .is_some_and(is_screen_record);
+        let is_selected = self.selected_control == Some(SelectedControl::Key(idx))
+            || self.selected_keys.contains(&idx);
+        let is_focused = self.grid_focused
+            && self.focus_visible
+            && self.selected_control == Some(SelectedControl::Key(idx));
+        let is_inherited = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(idx))
+            .is_some_and(|k| k.is_inherited());
+        let is_missing_plugin = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(idx)?.action.as_ref())
+            .is_some_and(|a| self.missing_plugins.affect(a));
+        let is_mouse_over = self.drag.over_key == Some(idx);
+        let is_drop_target = self.drag.dragging.is_some()
+            || self.drag.moving_key.is_some_and(|from| from != idx);
+        let is_drop_hover = is_drop_target && is_mouse_over;
+        let (key, _gap, _pad, _radius) =
+            deck_metrics(self.connected.as_ref().map(|c| c.key_count).unwrap_or(15));
 
-            let install_btn = if is_installed {
-                button(text("Installed")).style(iced::theme::Button::Secondary)
-            } else if is_installing {
-                button(text("Installing…")).style(iced::theme::Button::Secondary)
-            } else if can_install {
-                button(text("Install"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::MarketplaceInstall(p.clone()))
-            } else {
-                button(text("No download")).style(iced::theme::Button::Secondary)
-            };
+        let label = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(idx))
+            .map(|k| k.label.as_str())
+            .unwrap_or("");
 
-            let select_area: Element<Message> = mouse_area(
-                container(
-                    row![icon, container(body).width(Length::Fill)]
-                        .spacing(12)
-                        .align_items(Alignment::Center),
-                )
-                .width(Length::Fill),
-            )
-            .on_press(Message::MarketplaceSelect(p.clone()))
-            .into();
+        let action_hint = self
+            .profile
+            .as_ref()
+            .and_then(|p| p.keys.get(idx))
+            .and_then(|k| k.action.as_ref())
+            .and_then(|a| match a {
+                ActionBinding::Builtin(BuiltinAction::Counter { .. }) => Some(format!(
+                    "Count {}",
+                    self.counters.get(&(idx as u8)).copied().unwrap_or(0)
+                )),
+                ActionBinding::Builtin(b @ BuiltinAction::Pomodoro { .. }) => {
+                    let timer = self.pomodoros.get(&(idx as u8));
+                    pomodoro::key_look(b, timer, SystemTime::now())
+                        .map(|look| look.text.replace('\n', " "))
+                }
+                _ if is_missing_plugin => Some("Plugin missing".to_string()),
+                _ => self.binding_hint(a),
+            });
 
-            list = list.push(
-                container(
-                    row![
-                        container(select_area).width(Length::Fill),
-                        install_btn
-                    ]
-                    .spacing(12)
-                    .align_items(Alignment::Center),
-                )
-                .padding(10)
-                .style(if is_selected { callout_card() } else { panel() }),
-            );
-        }
+        let max_title = if key <= 64.0 { 10 } else { 14 };
+        let max_sub = if key <= 64.0 { 12 } else { 18 };
 
-        let status: Element<Message> = if self.marketplace.loading {
-            text("Loading…").style(color_text_muted()).into()
-        } else if let Some(err) = &self.marketplace.error {
-            text(format!("Error: {err}")).into()
-        } else if self.marketplace.plugins.is_empty() {
-            text("No plugins found.").style(color_text_muted()).into()
+        let title = if label.is_empty() {
+            action_hint.clone().unwrap_or_else(|| format!("Key {idx}"))
         } else {
-            text("").into()
+            label.to_string()
         };
 
-        let footer: Element<Message> = if total_matches > 0 {
-            row![
-                text(format!(
-                    "Page {} / {}  •  Showing {}–{} of {}",
-                    page + 1,
-                    page_count.max(1),
-                    start + 1,
-                    start + shown,
-                    total_matches
-                ))
-                .style(color_text_muted()),
-                horizontal_space(),
-                button(text("Prev"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::MarketplacePrevPage),
-                button(text("Next"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::MarketplaceNextPage),
+        let heat = self.key_heat(idx);
+        let subtitle = match heat {
+            Some((count, _)) => Some(format!("{count}×")),
+            None if label.is_empty() => None,
+            None => action_hint,
+        };
+        let title = truncate(&title, max_title);
+        let subtitle = subtitle.map(|s| truncate(&s, max_sub));
+
+        // The heatmap is drawn on the key itself, so the device image makes way for it.
+        let preview = self
+            .preview_image(Slot::Key(idx as u8))
+            .filter(|_| heat.is_none());
+        // The key's border stays visible around a device image.
+        let padding = if preview.is_some() { 3 } else { 0 };
+        let content: Element<Message> = match preview {
+            Some(handle) => image(handle).width(Length::Fill).height(Length::Fill).into(),
+            None => column![
+                text(title)
+                    .size(12)
+                    .width(Length::Fill)
+                    .horizontal_alignment(Horizontal::Center),
+                subtitle
+                    .map(|s| {
+                        text(s)
+                            .size(10)
+                            .style(self.color_text_muted())
+                            .width(Length::Fill)
+                            .horizontal_alignment(Horizontal::Center)
+                    })
+                    .unwrap_or_else(|| text("").size(10))
             ]
+            .spacing(2)
             .align_items(Alignment::Center)
-            .spacing(10)
-            .into()
-        } else {
-            text("").into()
+            .into(),
         };
 
-        let content_left = column![
-            header,
-            h_divider(),
-            url_row,
-            search,
-            status,
-            scrollable(list).height(Length::Fill),
-            footer
-        ]
-        .spacing(10)
-        .height(Length::Fill);
+        // Presses are handled by the surrounding mouse area so a key can be dragged elsewhere.
+        let key_btn = button(container(content).center_x().center_y())
+            .width(Length::Fixed(key))
+            .height(Length::Fixed(key))
+            .padding(padding)
+            .style(iced::theme::Button::custom(DeckKeyStyle {
+                pressed: is_pressed,
+                playing: is_playing,
+                recording: is_recording,
+                selected: is_selected,
+                focused: is_focused,
+                drop_hover: is_drop_hover,
+                inherited: is_inherited,
+                missing_plugin: is_missing_plugin,
+                mouse_over: is_mouse_over,
+                heat: heat.map(|(_, heat)| heat),
+                radius: self.active_theme.radii.key,
+            }))
+            ;
 
-        let details = self.view_marketplace_details_panel(selected.as_ref());
+        // Drop target surface: release mouse over a key to assign the currently dragged action
+        // or to swap it with the key the drag started on.
+        mouse_area(key_btn)
+            .on_press(Message::KeyPressed(idx))
+            .on_enter(Message::DragOverKey(Some(idx)))
+            .on_exit(Message::DragOverKey(None))
+            .on_release(Message::DropOnKey(idx))
+            .into()
+    }
 
-        row![
-            container(content_left)
-            .padding(12)
-            .width(Length::Fill)
-            .height(Length::Fill)
-                .style(panel()),
-            v_divider(),
-            container(details)
-                .padding(12)
-                .width(Length::Fixed(420.0))
-                .height(Length::Fill)
-                .style(panel()),
-        ]
-        .spacing(12)
-        .into()
+    /// The device image of `slot` for the live preview, if it is on and the app draws the device.
+    fn preview_image(&self, slot: Slot) -> Option<iced::widget::image::Handle> {
+        let c = self.connected.as_ref().filter(|c| !c.companion_connected)?;
+        if !self.live_preview {
+            return None;
+        }
+        c.previews.get(&slot).cloned()
+    }
+
+    fn action_label(&self, plugin_id: &str, action_id: &str) -> Option<String> {
+        let (_plugin, action) = find_action_def_by_ids(&self.plugins, plugin_id, action_id)?;
+        Some(action.name.clone())
+    }
+
+    fn binding_hint(&self, binding: &ActionBinding) -> Option<String> {
+        match binding {
+            ActionBinding::Plugin(p) => self.action_label(&p.plugin_id, &p.action_id),
+            ActionBinding::Builtin(b) => Some(match b {
+                actions::BuiltinAction::Macro { .. } => "Macro".to_string(),
+                actions::BuiltinAction::IssueCommand { .. } => "Issue Command".to_string(),
+                actions::BuiltinAction::KeyboardInput { .. } => "Keyboard Input".to_string(),
+                actions::BuiltinAction::PlaySound { .. } => "Play Sound".to_string(),
+                actions::BuiltinAction::StopSound { .. } => "Stop Sound".to_string(),
+                actions::BuiltinAction::Soundboard { .. } => "Soundboard".to_string(),
+                actions::BuiltinAction::Counter { .. } => "Counter".to_string(),
+                actions::BuiltinAction::Conditional { .. } => "If / Else".to_string(),
+                actions::BuiltinAction::SetVariable { name, value } => format!("{name} = {value}"),
+                actions::BuiltinAction::HomeAssistant { service, entity_id, .. } => {
+                    match self.ha_states.get(entity_id) {
+                        Some(state) => state.display(),
+                        None => service.clone(),
+                    }
+                }
+                actions::BuiltinAction::Open { target } => {
+                    let name = self
+                        .installed_apps
+                        .iter()
+                        .find(|a| a.target == *target)
+                        .map(|a| a.name.clone());
+                    name.unwrap_or_else(|| "Open".to_string())
+                }
+                actions::BuiltinAction::Clipboard { mode } => match mode {
+                    actions::ClipboardMode::CopyText(_) => "Copy Text".to_string(),
+                    actions::ClipboardMode::PasteText(_) => "Paste Text".to_string(),
+                    actions::ClipboardMode::CyclePrevious => "Prev. Clipboard".to_string(),
+                },
+                actions::BuiltinAction::Spotify { command } => match command {
+                    actions::SpotifyCommand::PlayPause => match &self.spotify_playback {
+                        Some(p) => p.title.clone(),
+                        None => "Play / Pause".to_string(),
+                    },
+                    actions::SpotifyCommand::Next => "Next Track".to_string(),
+                    actions::SpotifyCommand::Previous => "Prev. Track".to_string(),
+                    actions::SpotifyCommand::Like => "Like".to_string(),
+                },
+                actions::BuiltinAction::Screenshot { .. } => "Screenshot".to_string(),
+                actions::BuiltinAction::ScreenRecord { .. } => {
+                    if self.connected.as_ref().is_some_and(|c| c.screen_recording) {
+                        "Stop Rec.".to_string()
+                    } else {
+                        "Record".to_string()
+                    }
+                }
+                actions::BuiltinAction::SwitchProfile { .. } => "Switch Profile".to_string(),
+                actions::BuiltinAction::DeviceBrightness { .. } => "Device Brightness".to_string(),
+                actions::BuiltinAction::Script { .. } => "Script".to_string(),
+                actions::BuiltinAction::Clock { .. } => {
+                    match clock::key_text(b, chrono::Utc::now()) {
+                        Some(Ok(t)) => t.replace('\n', " "),
+                        _ => "Clock".to_string(),
+                    }
+                }
+                actions::BuiltinAction::Weather {
+                    location, units, ..
+                } => match self.weather_reports.get(&(location.trim().to_string(), *units)) {
+                    Some(Ok(r)) => format!("{} {}", r.temperature_text(), r.conditions()),
+                    _ => "Weather".to_string(),
+                },
+                actions::BuiltinAction::Pomodoro { .. } => "Pomodoro".to_string(),
+                actions::BuiltinAction::Notify { .. } => "Notify".to_string(),
+                actions::BuiltinAction::DoNotDisturb { .. } => match self.do_not_disturb {
+                    Some(true) => "DND On".to_string(),
+                    Some(false) => "DND Off".to_string(),
+                    None => "Do Not Disturb".to_string(),
+                },
+                actions::BuiltinAction::Power { command, .. } => {
+                    PowerCommandChoice::of(*command).to_string()
+                }
+                actions::BuiltinAction::CiStatus { .. } => {
+                    let run = CiQuery::of(b).and_then(|q| self.ci_runs.get(&q).cloned());
+                    match run {
+                        Some(Ok(Some(run))) => format!("{} #{}", run.name, run.number),
+                        _ => "GitHub Actions".to_string(),
+                    }
+                }
+                actions::BuiltinAction::Light { light, command } => {
+                    match self.light_states.get(light) {
+                        Some(Ok(s)) if s.on => format!("Light {}%", s.brightness),
+                        Some(Ok(_)) => "Light Off".to_string(),
+                        _ => LightCommandChoice::of(*command).to_string(),
+                    }
+                }
+                actions::BuiltinAction::Container { container, command } => {
+                    let verb = ContainerCommandChoice::of(*command);
+                    match container.trim() {
+                        "" => "Container".to_string(),
+                        name => format!("{verb} {name}"),
+                    }
+                }
+                actions::BuiltinAction::Midi { message, .. } => match *message {
+                    actions::MidiMessage::NoteOn { note, .. } => format!("Note {note}"),
+                    actions::MidiMessage::NoteOff { note, .. } => format!("Note {note} off"),
+                    actions::MidiMessage::ControlChange { controller, .. } => {
+                        format!("CC {controller}")
+                    }
+                },
+                actions::BuiltinAction::Osc { address, .. } => match address.trim() {
+                    "" => "OSC".to_string(),
+                    address => address.to_string(),
+                },
+                actions::BuiltinAction::Serial { port, .. } => {
+                    match port.trim().rsplit(['/', '\\']).next() {
+                        Some("") | None => "Serial".to_string(),
+                        Some(name) => name.to_string(),
+                    }
+                }
+                actions::BuiltinAction::WakeOnLan { .. } => "Wake".to_string(),
+                actions::BuiltinAction::Git { repo, .. } => {
+                    match self.git_statuses.get(repo.trim()) {
+                        Some(Ok(s)) => s.text().replace('\n', " "),
+                        _ => "Git".to_string(),
+                    }
+                }
+                actions::BuiltinAction::VsCode { workspace, command } => match command {
+                    actions::VsCodeCommand::RunTask(label) if !label.is_empty() => label.clone(),
+                    _ => std::path::Path::new(workspace)
+                        .file_name()
+                        .map_or_else(|| "VS Code".to_string(), |n| n.to_string_lossy().into()),
+                },
+                actions::BuiltinAction::Radio { radio } => {
+                    let name = RadioChoice::of(*radio);
+                    match self.radios.get(radio) {
+                        Some(true) => format!("{name} On"),
+                        Some(false) => format!("{name} Off"),
+                        None => name.to_string(),
+                    }
+                }
+                actions::BuiltinAction::SystemMonitoring { kind, .. } => match kind {
+                    actions::MonitorKind::Cpu => format!("CPU {:.0}%", self.sys_snapshot.cpu_percent),
+                    actions::MonitorKind::Memory => {
+                        let used = self.sys_snapshot.mem_used as f32 / (1024.0 * 1024.0);
+                        let total = self.sys_snapshot.mem_total as f32 / (1024.0 * 1024.0);
+                        format!("Mem {:.0}/{:.0}MB", used, total)
+                    }
+                    actions::MonitorKind::LoadAverage => {
+                        let (a, b, c) = self.sys_snapshot.load;
+                        format!("Load {:.2} {:.2} {:.2}", a, b, c)
+                    }
+                    actions::MonitorKind::NetworkRx { .. } => {
+                        format!("Rx {}", monitor_value(&self.sys_snapshot, kind))
+                    }
+                    actions::MonitorKind::NetworkTx { .. } => {
+                        format!("Tx {}", monitor_value(&self.sys_snapshot, kind))
+                    }
+                    actions::MonitorKind::DiskUsage { .. } => {
+                        format!("Disk {}", monitor_value(&self.sys_snapshot, kind))
+                    }
+                    actions::MonitorKind::Temperature { .. } => {
+                        monitor_value(&self.sys_snapshot, kind)
+                    }
+                    actions::MonitorKind::GpuUsage { .. } => {
+                        format!("GPU {}", monitor_value(&self.sys_snapshot, kind))
+                    }
+                    actions::MonitorKind::GpuMemory { .. } => {
+                        format!("VRAM {}", monitor_value(&self.sys_snapshot, kind))
+                    }
+                },
+            }),
+        }
     }
 
-    fn view_marketplace_details_panel(
-        &self,
-        source: Option<&MarketplaceSource>,
-    ) -> Element<'_, Message> {
-        let header = row![
-            text("Details").size(16),
-            horizontal_space(),
-        ]
-        .align_items(Alignment::Center);
-
-        let Some(p) = self.marketplace.selected.as_ref() else {
-            return container(column![
-                header,
-                h_divider(),
-                text("Click a plugin to view details.")
-                    .size(13)
-                    .style(color_text_muted()),
-            ])
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .into();
-        };
-
-        let icon: Element<Message> = if let Some(src) = source {
-            let key = format!("{}|{}", src.index_url, p.id);
-            if let Some(handle) = self.marketplace.icon_cache.get(&key) {
-                image(handle.clone())
-                    .width(Length::Fixed(96.0))
-                    .height(Length::Fixed(96.0))
-                    .into()
-            } else {
-                container(text(""))
-                    .width(Length::Fixed(96.0))
-                    .height(Length::Fixed(96.0))
-            .style(panel())
-                    .into()
+    fn handle_engine_notification(&mut self, n: EngineNotification) -> Command<Message> {
+        match n {
+            EngineNotification::Control(ev) => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                if let ControlId::Key(key) = ev.control {
+                    if let Some(slot) = c.pressed.get_mut(key as usize) {
+                        match ev.kind {
+                            ControlEventKind::Down => *slot = true,
+                            ControlEventKind::Up => *slot = false,
+                            _ => {}
+                        }
+                    }
+                    if ev.kind == ControlEventKind::Down {
+                        self.record_deck_key(key as usize);
+                    }
+                }
+                Command::none()
             }
-        } else {
-            container(text(""))
-                .width(Length::Fixed(96.0))
-                .height(Length::Fixed(96.0))
-                .style(panel())
-                .into()
-        };
-
-        let mut meta = column![
-            row![icon, column![
-                text(&p.name).size(18),
-                text(p.id.clone()).size(12).style(color_text_muted()),
-                if p.version.is_empty() {
-                    text("").size(1)
+            EngineNotification::ActionFailed(e) => {
+                self.error = Some(e);
+                Command::none()
+            }
+            EngineNotification::PermissionRequired { plugin_id } => {
+                self.permission_prompt = Some(plugin_id);
+                Command::none()
+            }
+            EngineNotification::ProfileSwitched(p) => {
+                self.selected_profile = Some(p.id);
+                self.selected_control = None;
+                // The engine already dispatches from the new profile; mark it synced so the
+                // UI does not push it straight back.
+                if let Some(c) = &mut self.connected {
+                    c.synced_profile = Some((*p).clone());
+                }
+                Command::perform(async move { Ok(*p) }, Message::ProfileLoaded)
+            }
+            EngineNotification::BrightnessChanged(v) => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                c.brightness = v;
+                // Changes by actions stay temporary while the profile sets its own brightness.
+                if self.profile.as_ref().is_some_and(|p| p.brightness.is_some()) {
+                    return Command::none();
+                }
+                self.save_device_brightness(v)
+            }
+            EngineNotification::SoundPlaying { key, playing } => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                if playing {
+                    c.playing_sounds.insert(key);
                 } else {
-                    text(format!("v{}", p.version)).size(12).style(color_text_muted())
-                },
-            ]
-            .spacing(4)]
-            .spacing(12)
-            .align_items(Alignment::Center),
-        ]
-        .spacing(10);
-
-        if let Some(author) = &p.author {
-            if !author.trim().is_empty() {
-                meta = meta.push(text(format!("Author: {author}")).size(12).style(color_text_muted()));
+                    c.playing_sounds.remove(&key);
+                }
+                self.apply_displays_if_connected()
             }
-        }
-        if let Some(home) = &p.homepage {
-            if !home.trim().is_empty() {
-                meta = meta.push(text(format!("Homepage: {home}")).size(12).style(color_text_muted()));
+            EngineNotification::CompanionConnected(connected) => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                c.companion_connected = connected;
+                if connected {
+                    return Command::none();
+                }
+                // Companion's images are still on the keys; redraw the profile over them.
+                if let Ok(mut cache) = c.display_cache.lock() {
+                    cache.invalidate();
+                }
+                self.apply_displays_if_connected()
             }
-        }
-        if !p.description.trim().is_empty() {
-            meta = meta.push(text(p.description.clone()).size(13));
-        }
-
-        // Extra details derived from repository (matches Rivul marketplace behavior).
-        if let Some(d) = self.marketplace.details_cache.get(&p.id) {
-            if let Some(repo) = d.repository.as_deref() {
-                if !repo.trim().is_empty() {
-                    meta = meta.push(text(format!("Repository: {repo}")).size(12).style(color_text_muted()));
+            EngineNotification::DoNotDisturbChanged(on) => {
+                self.do_not_disturb = Some(on);
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::PowerHold { key, held } => {
+                if held {
+                    self.power_holds.insert(key, Instant::now());
+                } else {
+                    self.power_holds.remove(&key);
                 }
+                self.apply_displays_if_connected()
             }
-            if let Some(dl) = d.total_downloads {
-                meta = meta.push(
-                    text(format!("Total downloads (GitHub releases): {dl}"))
-                        .size(12)
-                        .style(color_text_muted()),
-                );
+            EngineNotification::RadioChanged { radio, on } => {
+                self.radios.insert(radio, on);
+                self.apply_displays_if_connected()
             }
-            if let Some(url) = d.resolved_download_url.as_deref() {
-                meta = meta.push(
-                    text(format!("Resolved download: {url}"))
-                        .size(12)
-                        .style(color_text_muted()),
-                );
+            EngineNotification::ContainerChanged { container, running } => {
+                if let Some(Ok(containers)) = &mut self.containers {
+                    for c in containers.iter_mut().filter(|c| c.is(&container)) {
+                        c.running = running;
+                    }
+                }
+                self.apply_displays_if_connected()
             }
-        }
-
-        let readme_title = text("README").size(13).style(color_text_muted());
-        let readme_body: Element<Message> = if let Some(d) = self.marketplace.details_cache.get(&p.id) {
-            if let Some(md) = d.readme_md.as_deref() {
-                container(self.render_markdown(md, source))
-                    .padding(10)
-                    .width(Length::Fill)
-                    .style(panel())
-                    .into()
-            } else {
-                text("Loading README…").size(12).style(color_text_muted()).into()
+            EngineNotification::LightChanged { light, state } => {
+                self.light_states.insert(light, Ok(state));
+                self.apply_displays_if_connected()
             }
-        } else if p.repository.is_some() {
-            text("Loading README…").size(12).style(color_text_muted()).into()
-        } else {
-            text("No repository/README available.").size(12).style(color_text_muted()).into()
-        };
-
-        let screenshots_title = text("Images").size(13).style(color_text_muted());
-        let mut shots = column![].spacing(10);
-        if let Some(src) = source {
-            // Prefer images discovered from README; fall back to marketplace-provided screenshots.
-            let mut urls: Vec<String> = vec![];
-            if let Some(d) = self.marketplace.details_cache.get(&p.id) {
-                urls.extend(d.image_urls.iter().cloned());
+            EngineNotification::GitStatus { repo, status } => {
+                self.git_statuses.insert(repo, status);
+                self.apply_displays_if_connected()
             }
-            urls.extend(p.images.iter().cloned());
-
-            let mut shown_any = false;
-            for raw in urls.iter().take(12) {
-                let Some(url) = resolve_marketplace_asset_url(src, raw) else {
-                    continue;
+            EngineNotification::VsCodeTask {
+                workspace,
+                label,
+                status,
+            } => {
+                self.vscode_task_status.insert((workspace, label), status);
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::ScreenRecording(recording) => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
                 };
-                shown_any = true;
-                let key = format!("img|{}|{}|{}", src.index_url, p.id, url);
-                if let Some(handle) = self.marketplace.image_cache.get(&key) {
-                    shots = shots.push(image(handle.clone()).width(Length::Fill));
-                } else if let Some(handle) = self.marketplace.svg_cache.get(&key) {
-                    shots = shots.push(
-                        iced::widget::svg(handle.clone())
-                            .width(Length::Fill)
-                            .height(Length::Shrink)
-                        ,
-                    );
-                } else if let Some(err) = self.marketplace.image_error_cache.get(&key) {
-                    shots = shots.push(
-                        row![
-                            text("Failed to load image.")
-                                .style(iced::theme::Text::Color(color_text_muted())),
-                            horizontal_space(),
-                            button(text("Open"))
-                                .style(iced::theme::Button::Secondary)
-                                .on_press(Message::OpenUrl(url.clone())),
-                        ]
-                        .align_items(Alignment::Center)
-                        .spacing(10),
-                    );
-                    shots = shots.push(
-                        text(err.clone())
-                            .size(12)
-                            .style(iced::theme::Text::Color(color_text_muted())),
-                    );
+                c.screen_recording = recording;
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::Flash { control, effect } => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                let slot = match control {
+                    ControlId::Key(key) => Slot::Key(key),
+                    ControlId::Dial(dial) => Slot::Dial(dial),
+                    ControlId::TouchStrip => Slot::TouchStrip,
+                };
+                let flash = match effect {
+                    FlashEffect::Invert => render::lcd::Flash::Invert,
+                    FlashEffect::Border => render::lcd::Flash::Border,
+                };
+                // Repeated triggers (e.g. a dial turning) keep the surface lit.
+                c.flashing.insert(slot, (flash, Instant::now()));
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::CounterChanged {
+                profile,
+                key,
+                value,
+            } => {
+                if self.profile.as_ref().map(|p| p.id) != Some(profile) {
+                    return Command::none();
+                }
+                self.counters.insert(key, value);
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::DesktopNotification(n) => {
+                if !self.notification_mirror.enabled {
+                    return Command::none();
+                }
+                let summary = if n.summary.trim().is_empty() {
+                    n.app
                 } else {
-                    shots = shots.push(
-                        container(text("Loading…").style(color_text_muted()))
-                            .padding(10)
-                            .width(Length::Fill)
-                            .style(panel()),
-                    );
+                    n.summary
+                };
+                self.mirrored_notification = Some((summary, Instant::now()));
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::PomodoroChanged {
+                profile,
+                key,
+                timer,
+            } => {
+                if self.profile.as_ref().map(|p| p.id) != Some(profile) {
+                    return Command::none();
+                }
+                self.pomodoros.insert(key, timer);
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::PluginKeyChanged {
+                profile,
+                key,
+                change,
+            } => {
+                if self.profile.as_ref().map(|p| p.id) != Some(profile) {
+                    return Command::none();
+                }
+                let look = self.plugin_keys.entry(key).or_default();
+                match change {
+                    KeyChange::Image(image) => look.image = image,
+                    KeyChange::Title(title) => look.title = title,
+                }
+                if look.image.is_none() && look.title.is_none() {
+                    self.plugin_keys.remove(&key);
+                }
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::PluginSettingsChanged {
+                profile,
+                control,
+                plugin_id,
+                action_id,
+                settings,
+            } => {
+                let Some(p) = self.profile.as_mut().filter(|p| p.id == profile) else {
+                    return Command::none();
+                };
+                let replaced = engine::plugin_feedback::set_binding_settings(
+                    p, &control, &plugin_id, &action_id, &settings,
+                );
+                if replaced == 0 {
+                    return Command::none();
+                }
+                Command::perform(save_profile_async(p.clone()), Message::ProfileSaved)
+            }
+            EngineNotification::Activity(entry) => {
+                storage::activity::push_entry(&mut self.activity, entry);
+                self.save_activity()
+            }
+            EngineNotification::BindingUsed(used) => {
+                storage::usage::record(&mut self.usage, used);
+                self.save_usage()
+            }
+            EngineNotification::Disconnected => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                // The engine keeps the connection state and reopens the device once it is back.
+                c.reconnecting = true;
+                c.pressed.fill(false);
+                self.error = Some("Device disconnected; reconnecting when it is back".to_string());
+                Command::none()
+            }
+            EngineNotification::SurfaceDeck { device, connected } => {
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                if !connected {
+                    c.surface_caches.remove(&device);
+                    return Command::none();
+                }
+                // Opening reset the deck, so everything is drawn afresh.
+                let cache = Arc::new(std::sync::Mutex::new(ImageCache::new()));
+                c.surface_caches.insert(device, cache);
+                self.apply_displays_if_connected()
+            }
+            EngineNotification::Reconnected => {
+                let thresholds = self.gesture_thresholds();
+                let Some(c) = &mut self.connected else {
+                    return Command::none();
+                };
+                c.reconnecting = false;
+                c.controller = c.engine.controller();
+                c.controller.set_gesture_thresholds(thresholds);
+                // The engine restored the profile's plain key images; redraw everything with
+                // live values on top.
+                if let Ok(mut cache) = c.display_cache.lock() {
+                    cache.invalidate();
                 }
+                self.error = None;
+                self.apply_displays_if_connected()
+            }
+        }
+    }
+
+    /// Commands offered by the command palette in the current state.
+    fn command_registry(&self) -> CommandRegistry {
+        let mut registry = CommandRegistry::default();
+        for p in &self.profile_choices {
+            if self.selected_profile != Some(p.id) {
+                registry.register(
+                    format!("profile.switch.{}", p.id.0),
+                    format!("Switch profile: {}", p.label),
+                    AppCommand::SwitchProfile(p.id),
+                );
+            }
+        }
+        if self.profile.is_some() {
+            registry.register("profile.save", "Save profile", AppCommand::SaveProfile);
+        }
+        for d in &self.device_choices {
+            if self.connected.as_ref().map(|c| c.id) != Some(d.id) {
+                registry.register(
+                    format!("device.connect.{}", d.id.0),
+                    format!("Connect device: {}", d.label),
+                    AppCommand::ConnectDevice(d.id),
+                );
             }
-            if !shown_any {
-                shots = shots.push(text("No images found.").size(12).style(color_text_muted()));
+        }
+        registry.register("device.refresh", "Refresh devices", AppCommand::RefreshDevices);
+        if let Some(c) = &self.connected {
+            let title = if c.brightness > 0 {
+                "Turn screens off"
+            } else {
+                "Turn screens on"
+            };
+            registry.register("device.brightness.toggle", title, AppCommand::ToggleBrightness);
+        }
+        let (title, paused) = if self.paused {
+            ("Resume actions", false)
+        } else {
+            ("Pause actions", true)
+        };
+        registry.register("actions.pause.toggle", title, AppCommand::SetPaused(paused));
+        let (title, dry_run) = if self.dry_run {
+            ("Run actions again (leave dry run)", false)
+        } else {
+            ("Dry run: only log actions", true)
+        };
+        registry.register("actions.dry_run.toggle", title, AppCommand::SetDryRun(dry_run));
+        registry.register("view.marketplace", "Open marketplace", AppCommand::OpenMarketplace);
+        registry.register("view.schedules", "Open schedules", AppCommand::OpenSchedules);
+        registry.register("view.webhooks", "Open webhooks", AppCommand::OpenWebhooks);
+        registry.register(
+            "view.typed_triggers",
+            "Open text expander",
+            AppCommand::OpenTypedTriggers,
+        );
+        registry.register(
+            "view.file_watchers",
+            "Open file watchers",
+            AppCommand::OpenFileWatchers,
+        );
+        registry.register(
+            "view.process_watchers",
+            "Open process watchers",
+            AppCommand::OpenProcessWatchers,
+        );
+        registry.register("view.midi", "Open MIDI", AppCommand::OpenMidi);
+        registry.register("view.osc", "Open OSC", AppCommand::OpenOsc);
+        registry.register("view.history", "Open activity history", AppCommand::OpenHistory);
+        registry.register("view.stats", "Open usage statistics", AppCommand::OpenStats);
+        registry.register("view.device_test", "Open device test", AppCommand::OpenDeviceTest);
+        let scale = self.interface.scale_percent;
+        if scale < MAX_UI_SCALE {
+            registry.register(
+                "view.zoom.in",
+                "Zoom in",
+                AppCommand::SetUiScale(scale + UI_SCALE_STEP),
+            );
+        }
+        if scale > MIN_UI_SCALE {
+            registry.register(
+                "view.zoom.out",
+                "Zoom out",
+                AppCommand::SetUiScale(scale - UI_SCALE_STEP),
+            );
+        }
+        if scale != 100 {
+            registry.register("view.zoom.reset", "Reset zoom", AppCommand::SetUiScale(100));
+        }
+        for choice in ThemeChoice::all(&self.user_themes) {
+            if choice.setting != self.interface.theme {
+                let id = match &choice.setting {
+                    ThemeSetting::Dark => "view.theme.dark".to_string(),
+                    ThemeSetting::Light => "view.theme.light".to_string(),
+                    ThemeSetting::Custom(id) => format!("view.theme.custom.{id}"),
+                };
+                registry.register(
+                    id,
+                    format!("Switch theme: {}", choice.label),
+                    AppCommand::SetTheme(choice.setting),
+                );
             }
         }
+        registry
+    }
 
-        container(
-            column![
-                header,
-                h_divider(),
-                scrollable(
-                    column![meta, h_divider(), readme_title, readme_body, h_divider(), screenshots_title, shots]
-                        .spacing(12),
-                )
-                    .height(Length::Fill),
-            ]
-            .spacing(10),
-        )
-        .width(Length::Fill)
-        .height(Length::Fill)
-            .into()
+    fn set_theme(&mut self, setting: ThemeSetting) -> Command<Message> {
+        self.active_theme = ActiveTheme::resolve(&setting, &self.user_themes);
+        self.interface.theme = setting;
+        Command::perform(save_interface_async(self.interface.clone()), Message::SettingsSaved)
     }
 
-    fn view_preview_panel(&self) -> Element<'_, Message> {
-        let selected = self
-            .selected_control
-            .map(|s| match s {
-                SelectedControl::Key(k) => format!("Selected: Key {k}"),
-                SelectedControl::Dial(d) => format!("Selected: Dial {d}"),
-                SelectedControl::TouchStrip => "Selected: Touch strip".to_string(),
-            })
-            .unwrap_or_else(|| "Selected: —".to_string());
+    fn run_app_command(&mut self, command: AppCommand) -> Command<Message> {
+        match command {
+            AppCommand::SwitchProfile(id) => {
+                let Some(choice) = self.profile_choices.iter().find(|p| p.id == id).cloned() else {
+                    return Command::none();
+                };
+                self.handle_message(Message::ProfilePicked(choice))
+            }
+            AppCommand::ConnectDevice(id) => {
+                let Some(choice) = self.device_choices.iter().find(|d| d.id == id).cloned() else {
+                    return Command::none();
+                };
+                self.handle_message(Message::DevicePicked(choice))
+            }
+            AppCommand::OpenMarketplace => self.handle_message(Message::OpenMarketplace),
+            AppCommand::OpenSchedules => self.handle_message(Message::OpenSchedules),
+            AppCommand::OpenWebhooks => self.handle_message(Message::OpenWebhooks),
+            AppCommand::OpenTypedTriggers => self.handle_message(Message::OpenTypedTriggers),
+            AppCommand::OpenFileWatchers => self.handle_message(Message::OpenFileWatchers),
+            AppCommand::OpenProcessWatchers => self.handle_message(Message::OpenProcessWatchers),
+            AppCommand::OpenMidi => self.handle_message(Message::OpenMidi),
+            AppCommand::OpenOsc => self.handle_message(Message::OpenOsc),
+            AppCommand::OpenHistory => self.handle_message(Message::OpenHistory),
+            AppCommand::OpenStats => self.handle_message(Message::OpenStats),
+            AppCommand::OpenDeviceTest => self.handle_message(Message::OpenDeviceTest),
+            AppCommand::ToggleBrightness => {
+                let Some(c) = &self.connected else {
+                    return Command::none();
+                };
+                // Not saved: turning the screens back on restores the remembered brightness.
+                let brightness = if c.brightness > 0 {
+                    0
+                } else {
+                    let device = c.id;
+                    self.profile
+                        .as_ref()
+                        .and_then(|p| p.brightness)
+                        .unwrap_or(self.saved_brightness(device))
+                };
+                self.apply_brightness(brightness)
+            }
+            AppCommand::SetPaused(paused) => self.handle_message(Message::SetPaused(paused)),
+            AppCommand::SetDryRun(dry_run) => self.handle_message(Message::SetDryRun(dry_run)),
+            AppCommand::SaveProfile => self.handle_message(Message::SaveProfile),
+            AppCommand::RefreshDevices => self.handle_message(Message::RefreshDevices),
+            AppCommand::SetUiScale(percent) => self.handle_message(Message::SetUiScale(percent)),
+            AppCommand::SetTheme(setting) => self.set_theme(setting),
+        }
+    }
 
-        let title = row![
-            column![
-                text("Preview").size(16),
-                text(selected)
-                    .size(12)
-                    .style(color_text_muted()),
-            ]
-            .spacing(2),
-            horizontal_space(),
-            self.view_brightness_control_compact(),
-        ]
-        .align_items(Alignment::Center)
-        .spacing(12);
+    fn handle_tray_command(&mut self, cmd: tray::TrayCommand) -> Command<Message> {
+        match cmd {
+            tray::TrayCommand::ShowWindow => Command::batch([
+                iced::window::change_mode(iced::window::Id::MAIN, iced::window::Mode::Windowed),
+                iced::window::gain_focus(iced::window::Id::MAIN),
+            ]),
+            tray::TrayCommand::SwitchProfile(id) => {
+                let Some(choice) = self.profile_choices.iter().find(|p| p.id == id).cloned() else {
+                    return Command::none();
+                };
+                self.handle_message(Message::ProfilePicked(choice))
+            }
+            tray::TrayCommand::SetPaused(paused) => self.handle_message(Message::SetPaused(paused)),
+            tray::TrayCommand::Quit => iced::window::close(iced::window::Id::MAIN),
+        }
+    }
 
-        let body: Element<Message> = match &self.connected {
-            None => container(
-                column![
-                    text("Connect a device to see a preview.").size(16),
-                    text("Tip: use the left sidebar to select a device and click Connect.")
-                        .size(13)
-                        .style(color_text_muted()),
-                ]
-                .spacing(6),
-            )
-            .center_x()
-            .center_y()
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .into(),
-            Some(c) => container(self.view_deck_preview(c.key_count, &c.pressed))
-                .center_x()
-                .center_y()
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into(),
+    /// Mirror the profile list and pause state into the tray menu.
+    /// Register the hotkeys of the active profile while connected (none while recording, so the
+    /// recorder sees every shortcut).
+    fn sync_hotkeys(&mut self) {
+        let Some(hotkeys) = &mut self.hotkeys else {
+            return;
         };
-
-        container(column![title, horizontal_rule(1), body].spacing(10))
-            .padding(12)
-            .width(Length::Fill)
-            .height(Length::FillPortion(2))
-            .style(panel())
-            .into()
+        let bindings = match (&self.profile, &self.connected) {
+            (Some(p), Some(_)) if !self.recording_hotkey => p
+                .keys
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, k)| Some((k.hotkey.clone()?, idx as u8)))
+                .collect(),
+            _ => vec![],
+        };
+        let errors = hotkeys.set_bindings(bindings);
+        if !errors.is_empty() {
+            self.error = Some(errors.join("\n"));
+        }
     }
 
-    fn view_brightness_control_compact(&self) -> Element<'_, Message> {
-        let Some(c) = &self.connected else {
-            return text("").into();
+    fn set_selected_hotkey(&mut self, hotkey: Option<String>) {
+        let (Some(SelectedControl::Key(idx)), Some(p)) = (self.selected_control, &mut self.profile)
+        else {
+            return;
         };
-
-        row![
-            text(format!("Brightness {}%", c.brightness)).size(12),
-            slider(0..=100, c.brightness as i32, Message::BrightnessChanged)
-                .width(Length::Fixed(160.0))
-        ]
-        .spacing(10)
-        .align_items(Alignment::Center)
-        .into()
+        if let Some(k) = p.keys.get_mut(idx) {
+            k.hotkey = hotkey;
+        }
     }
 
-    fn view_inspector_panel(&self) -> Element<'_, Message> {
-        let header = text("Inspector").size(16);
-
-        let body = match (self.connected.as_ref(), self.selected_control) {
-            (None, _) => text("Connect a device to inspect controls.").into(),
-            (Some(_), None) => text("Click a key/dial/touch strip in the preview to edit it.").into(),
-            (Some(c), Some(sel)) => match sel {
-                SelectedControl::Key(idx) => self.view_key_inspector(c, idx),
-                SelectedControl::Dial(idx) => self.view_dial_inspector(idx),
-                SelectedControl::TouchStrip => self.view_touch_strip_inspector(),
-            },
+    fn sync_tray(&mut self) {
+        let Some(tray) = &mut self.tray else {
+            return;
         };
-
-        container(
-            column![
-                header,
-                horizontal_rule(1),
-                scrollable(body).height(Length::Fill)
-            ]
-            .spacing(10),
-        )
-        .padding(12)
-        .width(Length::Fill)
-        .height(Length::FillPortion(1))
-        .style(panel())
-        .into()
+        tray.set_state(tray::TrayState {
+            profiles: self
+                .profile_choices
+                .iter()
+                .map(|p| (p.id, p.label.clone()))
+                .collect(),
+            active_profile: self.selected_profile,
+            paused: self.paused,
+        });
     }
 
-    fn view_key_inspector(&self, c: &ConnectedUi, idx: usize) -> Element<'_, Message> {
-        let is_down = c.pressed.get(idx).copied().unwrap_or(false);
+    /// Push the edited profile, installed plugins, schedules, webhooks, typed triggers, process
+    /// watchers and MIDI and OSC triggers to the engine when they changed.
+    fn sync_engine(&mut self) {
+        let Some(c) = &mut self.connected else {
+            return;
+        };
+        if c.synced_profile != self.profile {
+            c.synced_profile = self.profile.clone();
+            c.engine.set_profile(self.profile.clone());
+        }
+        let plugins = PluginSyncKey::of(&self.plugins);
+        if c.synced_plugins.as_ref() != Some(&plugins) {
+            c.synced_plugins = Some(plugins);
+            c.engine.set_plugins(self.plugins.clone());
+        }
+        if c.synced_schedules.as_ref() != Some(&self.schedules) {
+            c.synced_schedules = Some(self.schedules.clone());
+            c.engine.set_schedules(self.schedules.clone());
+        }
+        let webhooks = (self.webhook_settings.clone(), self.webhooks.clone());
+        if c.synced_webhooks.as_ref() != Some(&webhooks) {
+            c.engine.set_webhooks(webhooks.0.clone(), webhooks.1.clone());
+            c.synced_webhooks = Some(webhooks);
+        }
+        let typed_triggers = (self.text_expander.clone(), self.typed_triggers.clone());
+        if c.synced_typed_triggers.as_ref() != Some(&typed_triggers) {
+            c.engine
+                .set_typed_triggers(typed_triggers.0.clone(), typed_triggers.1.clone());
+            c.synced_typed_triggers = Some(typed_triggers);
+        }
+        if c.synced_process_watchers.as_ref() != Some(&self.process_watchers) {
+            c.synced_process_watchers = Some(self.process_watchers.clone());
+            c.engine.set_process_watchers(self.process_watchers.clone());
+        }
+        let midi = (self.midi_settings.clone(), self.midi_triggers.clone());
+        if c.synced_midi.as_ref() != Some(&midi) {
+            c.engine.set_midi_triggers(midi.0.clone(), midi.1.clone());
+            c.synced_midi = Some(midi);
+        }
+        let osc = (self.osc_settings.clone(), self.osc_triggers.clone());
+        if c.synced_osc.as_ref() != Some(&osc) {
+            c.engine.set_osc_triggers(osc.0.clone(), osc.1.clone());
+            c.synced_osc = Some(osc);
+        }
+        if c.synced_companion.as_ref() != Some(&self.companion_settings) {
+            c.synced_companion = Some(self.companion_settings.clone());
+            c.engine.set_companion(self.companion_settings.clone());
+        }
+        if c.synced_notification_mirror != Some(self.notification_mirror) {
+            c.synced_notification_mirror = Some(self.notification_mirror);
+            c.engine.set_notification_mirror(self.notification_mirror);
+        }
+        let locked: Vec<ProfileId> = if self.kiosk.enabled() && !self.kiosk_unlocked {
+            let locked = self.kiosk.locked_profiles.iter().copied();
+            locked.map(ProfileId).collect()
+        } else {
+            vec![]
+        };
+        if c.synced_locked_profiles.as_ref() != Some(&locked) {
+            c.engine.set_locked_profiles(locked.clone());
+            c.synced_locked_profiles = Some(locked);
+        }
+        let surface = self
+            .profile
+            .as_ref()
+            .and_then(|p| surface_of(&self.surfaces, p, c.id))
+            .cloned();
+        if c.synced_surface != surface {
+            // The other decks are drawn on once the engine has them open.
+            c.surface_caches.clear();
+            let key_count = surface.as_ref().map_or(c.key_count, Surface::key_count);
+            c.pressed = vec![false; usize::from(key_count)];
+            c.engine.set_surface(surface.clone());
+            c.synced_surface = surface;
+        }
+    }
 
-        let mut col = column![
-            text(format!("Key {idx}")).size(20),
-            text(if is_down {
-                "State: Pressed"
-            } else {
-                "State: Released"
+    /// The shortest `refresh_ms` of the profile's monitoring bindings.
+    fn monitoring_interval(&self) -> Duration {
+        let Some(p) = &self.profile else {
+            return monitoring::DEFAULT_INTERVAL;
+        };
+        let keys = p.keys.iter().filter_map(|k| k.action.as_ref());
+        let strip = p.touch_strip.bindings().into_iter().flatten();
+        keys.chain(strip)
+            .filter_map(|b| match b {
+                ActionBinding::Builtin(BuiltinAction::SystemMonitoring { refresh_ms, .. }) => {
+                    *refresh_ms
+                }
+                _ => None,
             })
-            .size(13)
-            .style(color_text_muted()),
-        ]
-        .spacing(6);
-
-        col = col.push(horizontal_rule(1));
-
-        col = col.push(text("Label").size(14));
-        col = col.push(text_input("Label", &self.edit_label).on_input(Message::LabelChanged));
-
-        col = col.push(horizontal_rule(1));
+            .min()
+            .map(Duration::from_millis)
+            .unwrap_or(monitoring::DEFAULT_INTERVAL)
+    }
 
-        col = col.push(text("Action").size(14));
-        col = col.push(self.view_action_editor());
+    /// Panels of the touch strip dashboard of `profile`, if it has a monitoring binding.
+    fn strip_dashboard(&self, profile: &Profile) -> Option<Dashboard> {
+        let BuiltinAction::SystemMonitoring { kind, metrics, .. } = strip_monitoring(profile)?
+        else {
+            return None;
+        };
+        let metrics = if metrics.is_empty() {
+            std::slice::from_ref(kind)
+        } else {
+            metrics
+        };
+        let history = self.monitoring.history();
+        let panels = metrics.iter().map(|m| self.monitoring_panel(m, &history)).collect();
+        Some(Dashboard { panels })
+    }
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Display (LCD)").size(14));
-        col = col.push(self.view_display_editor());
+    fn monitoring_panel(&self, kind: &actions::MonitorKind, history: &[Sample]) -> Panel {
+        use actions::MonitorKind;
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(
-            row![button(text("Save"))
-                .on_press(Message::SaveProfile)
-                .style(iced::theme::Button::Primary),]
-            .spacing(8),
-        );
+        let values: Vec<f64> = history.iter().map(|s| s.value(kind).unwrap_or(0.0)).collect();
+        // Network traffic is graphed against the busiest sample shown, at least 1 KB/s.
+        let full = self
+            .sys_snapshot
+            .full_scale(kind)
+            .unwrap_or_else(|| values.iter().copied().fold(1024.0, f64::max));
+        let history = values
+            .iter()
+            .map(|v| (v / full.max(f64::MIN_POSITIVE) * 100.0).clamp(0.0, 100.0) as u8)
+            .collect();
 
-        col.into()
+        let named = |prefix: &str, name: &Option<String>| match name {
+            Some(name) => format!("{prefix} {name}"),
+            None => prefix.to_string(),
+        };
+        let numbered = |prefix: &str, index: &Option<u32>| match index {
+            Some(i) => format!("{prefix} {i}"),
+            None => prefix.to_string(),
+        };
+        let (label, rgb) = match kind {
+            MonitorKind::Cpu => ("CPU".to_string(), [88, 166, 255]),
+            MonitorKind::Memory => ("RAM".to_string(), [163, 113, 247]),
+            MonitorKind::LoadAverage => ("LOAD".to_string(), [210, 153, 34]),
+            MonitorKind::NetworkRx { interface } => (named("RX", interface), [63, 185, 80]),
+            MonitorKind::NetworkTx { interface } => (named("TX", interface), [219, 109, 40]),
+            MonitorKind::DiskUsage { mount } => (named("DISK", mount), [139, 148, 158]),
+            MonitorKind::Temperature { sensor } => (named("TEMP", sensor), [248, 81, 73]),
+            MonitorKind::GpuUsage { gpu } => (numbered("GPU", gpu), [118, 185, 0]),
+            MonitorKind::GpuMemory { gpu } => (numbered("VRAM", gpu), [57, 197, 187]),
+        };
+        Panel {
+            label,
+            value: monitor_value(&self.sys_snapshot, kind),
+            history,
+            rgb,
+        }
     }
 
-    fn view_dial_inspector(&self, idx: usize) -> Element<'_, Message> {
-        let mut col = column![text(format!("Dial {idx}")).size(20)]
-            .spacing(6);
+    fn current_action_choice(&self) -> Option<ActionChoice> {
+        let b = match self.selected_binding()?.as_ref()? {
+            ActionBinding::Plugin(p) => p,
+            ActionBinding::Builtin(_) => return None,
+        };
+        self.actions
+            .iter()
+            .find(|a| a.plugin_id == b.plugin_id && a.action_id == b.action_id)
+            .cloned()
+    }
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Label").size(14));
-        col = col.push(text_input("Label", &self.edit_label).on_input(Message::LabelChanged));
+    fn view_action_settings(&self) -> Element<'_, Message> {
+        let Some(binding) = self.selected_binding().and_then(|b| b.as_ref()) else {
+            return text("No action bound.").into();
+        };
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Binding target").size(14));
-        col = col.push(pick_list(
-            vec![BindingTarget::DialPress, BindingTarget::DialRotate],
-            Some(self.selected_binding_target),
-            Message::BindingTargetPicked,
-        ));
+        let ActionBinding::Plugin(binding) = binding else {
+            // Builtin actions have their own editor (added in `ui-config` todo).
+            return text("Builtin action settings are not shown here yet.").into();
+        };
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Action").size(14));
-        col = col.push(self.view_action_editor());
+        let Some((_plugin, action_def)) =
+            find_action_def_by_ids(&self.plugins, &binding.plugin_id, &binding.action_id)
+        else {
+            return text("Action not found (plugin missing or manifest changed).").into();
+        };
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Display (LCD)").size(14));
-        col = col.push(self.view_display_editor());
+        if action_def.settings.is_empty() {
+            return text("This action has no settings.").into();
+        }
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(
-            row![button(text("Save"))
-                .on_press(Message::SaveProfile)
-                .style(iced::theme::Button::Primary),]
-            .spacing(8),
-        );
+        let mut col = column![text("Settings").size(16)].spacing(8);
+
+        for field in &action_def.settings {
+            col = col.push(self.view_setting_field(field, &binding.settings));
+        }
 
         col.into()
     }
 
-    fn view_touch_strip_inspector(&self) -> Element<'_, Message> {
-        let mut col = column![text("Touch strip").size(20)].spacing(6);
+    fn view_setting_field(
+        &self,
+        field: &SettingField,
+        settings: &serde_json::Value,
+    ) -> Element<'_, Message> {
+        let key = field.key.clone();
+        let label = field.label.clone();
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Binding target").size(14));
-        col = col.push(pick_list(
-            vec![BindingTarget::TouchTap, BindingTarget::TouchDrag],
-            Some(self.selected_binding_target),
-            Message::BindingTargetPicked,
-        ));
+        match field.ty {
+            SettingType::String => {
+                let current = settings
+                    .get(&field.key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let input =
+                    text_input("", &current).on_input(move |v| Message::SettingStringChanged {
+                        key: key.clone(),
+                        value: v,
+                    });
+                column![text(label), input].spacing(4).into()
+            }
+            SettingType::Boolean => {
+                let current = settings
+                    .get(&field.key)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                checkbox(label, current)
+                    .on_toggle(move |v| Message::SettingBoolChanged {
+                        key: key.clone(),
+                        value: v,
+                    })
+                    .into()
+            }
+            SettingType::Number => {
+                let current = settings
+                    .get(&field.key)
+                    .and_then(|v| v.as_f64())
+                    .map(|n| n.to_string())
+                    .or_else(|| {
+                        settings
+                            .get(&field.key)
+                            .and_then(|v| v.as_str())
+                            .map(|s| s.to_string())
+                    })
+                    .unwrap_or_default();
+                let input =
+                    text_input("", &current).on_input(move |v| Message::SettingNumberChanged {
+                        key: key.clone(),
+                        value: v,
+                    });
+                column![text(label), input].spacing(4).into()
+            }
+        }
+    }
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Action").size(14));
-        col = col.push(self.view_action_editor());
+    fn render_markdown(&self, md: &str, source: Option<&MarketplaceSource>) -> Element<'_, Message> {
+        use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(text("Display (LCD)").size(14));
-        col = col.push(self.view_display_editor());
+        let mut opts = Options::empty();
+        opts.insert(Options::ENABLE_TABLES);
+        opts.insert(Options::ENABLE_STRIKETHROUGH);
+        opts.insert(Options::ENABLE_FOOTNOTES);
+        opts.insert(Options::ENABLE_TASKLISTS);
 
-        col = col.push(horizontal_rule(1));
-        col = col.push(
-            row![button(text("Save"))
-                .on_press(Message::SaveProfile)
-                .style(iced::theme::Button::Primary),]
-            .spacing(8),
-        );
+        let parser = Parser::new_ext(md, opts);
 
-        col.into()
-    }
+        let mut elems: Vec<Element<Message>> = vec![];
 
-    fn selected_binding(&self) -> Option<&Option<ActionBinding>> {
-        let p = self.profile.as_ref()?;
-        let sel = self.selected_control?;
-        match (sel, self.selected_binding_target) {
-            (SelectedControl::Key(idx), BindingTarget::KeyPress) => Some(&p.keys.get(idx)?.action),
-            (SelectedControl::Dial(idx), BindingTarget::DialPress) => Some(&p.dials.get(idx)?.press),
-            (SelectedControl::Dial(idx), BindingTarget::DialRotate) => Some(&p.dials.get(idx)?.rotate),
-            (SelectedControl::TouchStrip, BindingTarget::TouchTap) => Some(&p.touch_strip.tap),
-            (SelectedControl::TouchStrip, BindingTarget::TouchDrag) => Some(&p.touch_strip.drag),
-            _ => None,
+        let mut cur = String::new();
+        let mut cur_link: Option<String> = None;
+        let mut heading_level: Option<u8> = None;
+        let mut in_code_block = false;
+        let mut code_buf = String::new();
+
+        fn flush_paragraph(
+            elems: &mut Vec<Element<Message>>,
+            text_buf: &mut String,
+            link: &mut Option<String>,
+            heading_level: &mut Option<u8>,
+        ) {
+            let s = text_buf.trim();
+            if s.is_empty() {
+                *text_buf = String::new();
+                *link = None;
+                *heading_level = None;
+                return;
+            }
+
+            // Heading
+            if let Some(level) = *heading_level {
+                let size = match level {
+                    1 => 18,
+                    2 => 16,
+                    3 => 15,
+                    _ => 14,
+                };
+                elems.push(text(s.to_string()).size(size).into());
+                *text_buf = String::new();
+                *link = None;
+                *heading_level = None;
+                return;
+            }
+
+            // Link-only line (basic)
+            if let Some(url) = link.take() {
+                elems.push(
+                    button(text(s.to_string()))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::OpenUrl(url))
+                        .into(),
+                );
+                *text_buf = String::new();
+                *heading_level = None;
+                return;
+            }
+
+            elems.push(text(s.to_string()).size(12).into());
+            *text_buf = String::new();
+            *heading_level = None;
         }
-    }
 
-    fn selected_binding_mut(&mut self) -> Option<&mut Option<ActionBinding>> {
-        let p = self.profile.as_mut()?;
-        let sel = self.selected_control?;
-        match (sel, self.selected_binding_target) {
-            (SelectedControl::Key(idx), BindingTarget::KeyPress) => Some(&mut p.keys.get_mut(idx)?.action),
-            (SelectedControl::Dial(idx), BindingTarget::DialPress) => Some(&mut p.dials.get_mut(idx)?.press),
-            (SelectedControl::Dial(idx), BindingTarget::DialRotate) => Some(&mut p.dials.get_mut(idx)?.rotate),
-            (SelectedControl::TouchStrip, BindingTarget::TouchTap) => Some(&mut p.touch_strip.tap),
-            (SelectedControl::TouchStrip, BindingTarget::TouchDrag) => Some(&mut p.touch_strip.drag),
-            _ => None,
+        for ev in parser {
+            match ev {
+                Event::Start(Tag::Heading { level, .. }) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                    heading_level = Some(level as u8);
+                }
+                Event::End(TagEnd::Heading { .. }) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                }
+                Event::Start(Tag::Paragraph) => {}
+                Event::End(TagEnd::Paragraph) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                }
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    cur_link = Some(dest_url.to_string());
+                }
+                Event::End(TagEnd::Link) => {
+                    // keep link; flush on paragraph end
+                }
+                Event::Start(Tag::CodeBlock(_)) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                    in_code_block = true;
+                    code_buf.clear();
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    in_code_block = false;
+                    let s = code_buf.trim_end().to_string();
+                    if !s.is_empty() {
+                        elems.push(
+                            container(text(s).size(12).style(self.color_text_muted()))
+                                .padding(10)
+                                .style(panel())
+                                .into(),
+                        );
+                    }
+                    code_buf.clear();
+                }
+                Event::Text(t) => {
+                    if in_code_block {
+                        code_buf.push_str(&t);
+                    } else {
+                        cur.push_str(&t);
+                    }
+                }
+                Event::Code(t) => {
+                    if in_code_block {
+                        code_buf.push_str(&t);
+                    } else {
+                        cur.push('`');
+                        cur.push_str(&t);
+                        cur.push('`');
+                    }
+                }
+                Event::SoftBreak | Event::HardBreak => {
+                    if in_code_block {
+                        code_buf.push('\n');
+                    } else {
+                        cur.push('\n');
+                    }
+                }
+                Event::Start(Tag::List(_)) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                }
+                Event::End(TagEnd::List(_)) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                }
+                Event::Start(Tag::Item) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                    cur.push_str("• ");
+                }
+                Event::End(TagEnd::Item) => {
+                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+                }
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    // Try to show image if it's a renderable format; otherwise provide a link button.
+                    let raw = dest_url.to_string();
+                    if is_renderable_image_url(&raw) {
+                        if let Some(src) = source {
+                            if let Some(url) = resolve_marketplace_asset_url(src, &raw) {
+                                elems.push(
+                                    button(text(format!("Open image: {}", truncate(&url, 60))))
+                                        .style(iced::theme::Button::Secondary)
+                                        .on_press(Message::OpenUrl(url))
+                                        .into(),
+                                );
+                            }
+                        } else {
+                            elems.push(
+                                button(text(format!("Open image: {}", truncate(&raw, 60))))
+                                    .style(iced::theme::Button::Secondary)
+                                    .on_press(Message::OpenUrl(raw.clone()))
+                                    .into(),
+                            );
+                        }
+                    } else {
+                        elems.push(
+                            button(text(format!("Open image (unsupported): {}", truncate(&raw, 60))))
+                                .style(iced::theme::Button::Secondary)
+                                .on_press(Message::OpenUrl(raw.clone()))
+                                .into(),
+                        );
+                    }
+                }
+                _ => {}
+            }
         }
-    }
 
-    fn set_selected_plugin_setting(&mut self, key: String, value: serde_json::Value) {
-        let Some(slot) = self.selected_binding_mut() else {
-            return;
-        };
-        let Some(ActionBinding::Plugin(binding)) = slot.as_mut() else {
-            return;
-        };
+        flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
 
-        if let Some(obj) = binding.settings.as_object_mut() {
-            obj.insert(key, value);
-            return;
-        }
+        let out = elems
+            .into_iter()
+            .fold(column![].spacing(6), |col, el| col.push(el));
 
-        let mut map = serde_json::Map::new();
-        map.insert(key, value);
-        binding.settings = serde_json::Value::Object(map);
+        // Important: do NOT wrap markdown in its own scrollable, because the details panel already
+        // has a scroll container. Nested scrollables prevent mouse-wheel scrolling from working.
+        out.into()
     }
+}
 
-    fn view_display_editor(&self) -> Element<'_, Message> {
-        let color = parse_bg_rgb(&self.edit_bg_rgb)
-            .map(|[r, g, b]| Color::from_rgb8(r, g, b))
-            .unwrap_or(Color::TRANSPARENT);
+#[derive(Debug, Clone, Copy)]
+struct DeckKeyStyle {
+    pressed: bool,
+    /// A soundboard sound bound to the key is playing.
+    playing: bool,
+    /// The key records the screen and a recording is running.
+    recording: bool,
+    selected: bool,
+    /// The selected key while the grid has keyboard focus; drawn with a focus ring.
+    focused: bool,
+    drop_hover: bool,
+    /// The key comes unchanged from the base profile; drawn dimmed.
+    inherited: bool,
+    /// The key's binding uses a plugin that is not installed; drawn with a red border.
+    missing_plugin: bool,
+    /// The button has no press handler of its own, so iced draws it as disabled.
+    mouse_over: bool,
+    /// How much the key is used compared to the most used one, while the heatmap is on.
+    heat: Option<f32>,
+    radius: f32,
+}
 
-        column![
-            text("Background Color").size(12).style(color_text_muted()),
-            row![
-                container(text(""))
-                    .width(Length::Fixed(30.0))
-                    .height(Length::Fixed(30.0))
-                    .style(iced::theme::Container::Custom(Box::new(move |_theme: &Theme| {
-                        iced::widget::container::Appearance {
-                            background: Some(Background::Color(color)),
-                            border: Border {
-                                radius: 4.0.into(),
-                                width: 1.0,
-                                color: Color::from_rgba8(255, 255, 255, 0.2),
-                            },
-                            ..Default::default()
-                        }
-                    }))),
-                button(text("Pick Color"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::OpenColorPicker),
-                button(text("Clear"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::BgRgbChanged(String::new())),
-            ]
-            .spacing(8)
-            .align_items(Alignment::Center),
-            text("Icon").size(12).style(color_text_muted()),
-            row![
-                text_input("No icon selected", &self.edit_icon_path)
-                    .on_input(Message::IconPathChanged)
-                    .width(Length::Fill),
-                button(text("Browse…"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::OpenIconPicker),
-            ]
-            .spacing(8)
-            .align_items(Alignment::Center),
-            text("Text (optional)").size(12).style(color_text_muted()),
-            text_input("", &self.edit_display_text).on_input(Message::DisplayTextChanged),
-        ]
-        .spacing(6)
-        .into()
-    }
+impl iced::widget::button::StyleSheet for DeckKeyStyle {
+    type Style = Theme;
 
-    fn view_action_editor(&self) -> Element<'_, Message> {
-        let Some(binding) = self.selected_binding() else {
-            return text("Select a control to edit its action.").into();
-        };
+    fn active(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
+        let palette = theme.extended_palette();
 
-        let mode = match binding {
-            None => ActionModeChoice::None,
-            Some(ActionBinding::Plugin(_)) => ActionModeChoice::Plugin,
-            Some(ActionBinding::Builtin(_)) => ActionModeChoice::Builtin,
+        let bg = if self.pressed {
+            palette.success.weak.color
+        } else if self.recording {
+            palette.danger.weak.color
+        } else if self.playing {
+            palette.primary.weak.color
+        } else if let Some(heat) = self.heat {
+            // From the plain key color for unused keys to the danger color for the most used.
+            let (cold, hot) = (palette.background.strong.color, palette.danger.base.color);
+            Color::from_rgb(
+                cold.r + (hot.r - cold.r) * heat,
+                cold.g + (hot.g - cold.g) * heat,
+                cold.b + (hot.b - cold.b) * heat,
+            )
+        } else {
+            palette.background.strong.color
         };
 
-        let mode_picker = pick_list(
-            vec![
-                ActionModeChoice::None,
-                ActionModeChoice::Plugin,
-                ActionModeChoice::Builtin,
-            ],
-            Some(mode),
-            Message::ActionModePicked,
-        );
-
-        let mut col = column![row![
-            text("Mode").size(12).style(color_text_muted()),
-            mode_picker
-        ]
-        .spacing(10)
-        .align_items(Alignment::Center)]
-        .spacing(8);
-
-        match binding {
-            None => {
-                col = col.push(text("No action bound.").style(color_text_muted()));
-            }
-            Some(ActionBinding::Plugin(_)) => {
-                let current = self.current_action_choice();
-                col = col.push(pick_list(
-                    self.actions.clone(),
-                    current,
-                    Message::ActionSelected,
-                ));
-                col = col.push(self.view_action_settings());
-            }
-            Some(ActionBinding::Builtin(b)) => {
-                let current_kind = match b {
-                    BuiltinAction::Macro { .. } => BuiltinKindChoice::Macro,
-                    BuiltinAction::IssueCommand { .. } => BuiltinKindChoice::IssueCommand,
-                    BuiltinAction::KeyboardInput { .. } => BuiltinKindChoice::KeyboardInput,
-                    BuiltinAction::PlaySound { .. } => BuiltinKindChoice::PlaySound,
-                    BuiltinAction::SwitchProfile { .. } => BuiltinKindChoice::SwitchProfile,
-                    BuiltinAction::DeviceBrightness { .. } => BuiltinKindChoice::DeviceBrightness,
-                    BuiltinAction::SystemMonitoring { .. } => BuiltinKindChoice::SystemMonitoring,
-                };
-
-                col = col.push(pick_list(
-                    vec![
-                        BuiltinKindChoice::Macro,
-                        BuiltinKindChoice::IssueCommand,
-                        BuiltinKindChoice::KeyboardInput,
-                        BuiltinKindChoice::PlaySound,
-                        BuiltinKindChoice::SwitchProfile,
-                        BuiltinKindChoice::DeviceBrightness,
-                        BuiltinKindChoice::SystemMonitoring,
-                    ],
-                    Some(current_kind),
-                    Message::BuiltinKindPicked,
-                ));
-
-                col = col.push(self.view_builtin_settings(b));
-            }
-        }
-
-        col.into()
-    }
-
-    fn view_builtin_settings(&self, b: &BuiltinAction) -> Element<'_, Message> {
-        match b {
-            BuiltinAction::Macro { steps } => self.view_macro_editor(steps),
-            BuiltinAction::IssueCommand {
-                command,
-                cwd,
-                timeout_ms,
-            } => {
-                let timeout = timeout_ms.map(|v| v.to_string()).unwrap_or_default();
-                column![
-                    text("Command").size(12).style(color_text_muted()),
-                    text_input("bash command…", command).on_input(Message::BuiltinIssueCommandChanged),
-                    text("Working dir (optional)").size(12).style(color_text_muted()),
-                    text_input("", cwd.as_deref().unwrap_or("")).on_input(Message::BuiltinIssueCwdChanged),
-                    text("Timeout ms (optional)").size(12).style(color_text_muted()),
-                    text_input("", &timeout).on_input(Message::BuiltinIssueTimeoutChanged),
-                    text("Runs via `bash -lc` (Linux MVP).").size(12).style(color_text_muted()),
-                ]
-                .spacing(6)
-                .into()
-            }
-            BuiltinAction::KeyboardInput { text: input_text, keys } => {
-                let keys_s = keys.join(" ");
-                column![
-                    text("Text (optional)").size(12).style(color_text_muted()),
-                    text_input("", input_text.as_deref().unwrap_or(""))
-                        .on_input(Message::BuiltinKeyboardTextChanged),
-                    text("Keys (space-separated, optional)").size(12).style(color_text_muted()),
-                    text_input("e.g. -k Return", &keys_s).on_input(Message::BuiltinKeyboardKeysChanged),
-                    text("Linux MVP uses external tool: env `RIVERDECK_KEYBOARD_TOOL` (default: wtype).")
-                        .size(12)
-                        .style(color_text_muted()),
-                ]
-                .spacing(6)
-                .into()
-            }
-            BuiltinAction::PlaySound { path } => column![
-                text("Audio file path").size(12).style(color_text_muted()),
-                text_input("/path/to/file.wav", path).on_input(Message::BuiltinPlaySoundPathChanged),
-            ]
-            .spacing(6)
-            .into(),
-            BuiltinAction::SwitchProfile { mode } => {
-                let mut choices = vec![SwitchProfileChoice::Next, SwitchProfileChoice::Prev];
-                for p in &self.profiles {
-                    choices.push(SwitchProfileChoice::To(p.id));
-                }
-
-                let selected = match mode {
-                    actions::SwitchProfileMode::Next => SwitchProfileChoice::Next,
-                    actions::SwitchProfileMode::Prev => SwitchProfileChoice::Prev,
-                    actions::SwitchProfileMode::To { profile_id } => {
-                        SwitchProfileChoice::To(ProfileId(*profile_id))
-                    }
-                };
-
-                column![
-                    text("Mode").size(12).style(color_text_muted()),
-                    pick_list(choices, Some(selected), Message::BuiltinSwitchProfilePicked),
-                ]
-                .spacing(6)
-                .into()
-            }
-            BuiltinAction::DeviceBrightness { mode } => {
-                let (m, v) = match mode {
-                    actions::BrightnessMode::Set { percent } => (BrightnessModeChoice::Set, *percent as i32),
-                    actions::BrightnessMode::Increase { delta } => {
-                        (BrightnessModeChoice::Increase, *delta as i32)
-                    }
-                    actions::BrightnessMode::Decrease { delta } => {
-                        (BrightnessModeChoice::Decrease, *delta as i32)
-                    }
-                };
+        let border_color = if self.drop_hover {
+            palette.primary.base.color
+        } else if self.focused {
+            palette.background.base.text
+        } else if self.selected {
+            palette.primary.base.color
+        } else if self.missing_plugin {
+            palette.danger.base.color
+        } else if self.pressed {
+            palette.success.base.color
+        } else if self.recording {
+            palette.danger.strong.color
+        } else if self.playing {
+            palette.primary.strong.color
+        } else {
+            palette.background.strong.color
+        };
 
-                column![
-                    text("Mode").size(12).style(color_text_muted()),
-                    pick_list(
-                        vec![
-                            BrightnessModeChoice::Set,
-                            BrightnessModeChoice::Increase,
-                            BrightnessModeChoice::Decrease,
-                        ],
-                        Some(m),
-                        Message::BuiltinBrightnessModePicked,
-                    ),
-                    slider(0..=100, v, Message::BuiltinBrightnessValueChanged),
-                ]
-                .spacing(6)
-                .into()
+        let text_color = if self.inherited {
+            Color {
+                a: 0.45,
+                ..palette.background.base.text
             }
-            BuiltinAction::SystemMonitoring { kind, .. } => {
-                let k = match kind {
-                    actions::MonitorKind::Cpu => MonitorKindChoice::Cpu,
-                    actions::MonitorKind::Memory => MonitorKindChoice::Memory,
-                    actions::MonitorKind::LoadAverage => MonitorKindChoice::LoadAverage,
-                };
+        } else {
+            palette.background.base.text
+        };
 
-                column![
-                    text("Metric").size(12).style(color_text_muted()),
-                    pick_list(
-                        vec![
-                            MonitorKindChoice::Cpu,
-                            MonitorKindChoice::Memory,
-                            MonitorKindChoice::LoadAverage,
-                        ],
-                        Some(k),
-                        Message::BuiltinMonitorKindPicked,
-                    ),
-                    text("Displayed live in preview (device rendering later).")
-                        .size(12)
-                        .style(color_text_muted()),
-                ]
-                .spacing(6)
-                .into()
-            }
+        iced::widget::button::Appearance {
+            background: Some(Background::Color(bg)),
+            text_color,
+            border: Border {
+                color: border_color,
+                width: if self.focused {
+                    3.0
+                } else if self.selected || self.missing_plugin {
+                    2.0
+                } else {
+                    1.0
+                },
+                radius: self.radius.into(),
+            },
+            shadow: Shadow {
+                color: Color::from_rgba8(0, 0, 0, 0.28),
+                offset: iced::Vector::new(0.0, 4.0),
+                blur_radius: 16.0,
+            },
+            ..Default::default()
         }
     }
 
-    fn view_macro_editor(&self, steps: &[actions::MacroStep]) -> Element<'_, Message> {
-        let mut col = column![
-            row![
-                text("Macro steps").size(12).style(color_text_muted()),
-                horizontal_space(),
-                button(text("+")).style(iced::theme::Button::Secondary).on_press(Message::MacroAddStep),
-            ]
-            .align_items(Alignment::Center)
-            .spacing(8),
-        ]
-        .spacing(8);
-
-        if steps.is_empty() {
-            col = col.push(text("No steps yet.").style(color_text_muted()));
-            return col.into();
+    fn hovered(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
+        let palette = theme.extended_palette();
+        let mut a = self.active(theme);
+        if !self.pressed && !self.playing && !self.recording && self.heat.is_none() {
+            a.background = Some(Background::Color(palette.background.base.color));
         }
+        a
+    }
 
-        for (i, s) in steps.iter().enumerate() {
-            let kind = match s.action.as_ref() {
-                ActionBinding::Plugin(_) => MacroStepKindChoice::PluginAction,
-                ActionBinding::Builtin(BuiltinAction::IssueCommand { .. }) => {
-                    MacroStepKindChoice::IssueCommand
-                }
-                _ => MacroStepKindChoice::PluginAction,
-            };
-
-            let delay = s.delay_ms.map(|d| d.to_string()).unwrap_or_default();
-            let kind_picker = pick_list(
-                vec![MacroStepKindChoice::PluginAction, MacroStepKindChoice::IssueCommand],
-                Some(kind),
-                move |k| Message::MacroStepKindPicked { idx: i, kind: k },
-            );
+    fn pressed(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
+        let mut a = self.active(theme);
+        a.shadow = Shadow::default();
+        a
+    }
 
-            let controls = row![
-                button(text("^"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::MacroMoveStepUp(i)),
-                button(text("v"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::MacroMoveStepDown(i)),
-                button(text("X"))
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::MacroRemoveStep(i)),
-            ]
-            .spacing(6);
+    fn disabled(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
+        if self.mouse_over {
+            self.hovered(theme)
+        } else {
+            self.active(theme)
+        }
+    }
+}
 
-            let delay_input = text_input("Delay ms", &delay).on_input(move |v| {
-                Message::MacroStepDelayChanged {
-                    idx: i,
-                    value: v,
-                }
-            });
+fn app_background() -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
+        let p = theme.extended_palette();
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(p.background.base.color)),
+            text_color: Some(p.background.base.text),
+            border: Border::default(),
+            shadow: Shadow::default(),
+        }
+    }))
+}
 
-            let editor: Element<Message> = match s.action.as_ref() {
-                ActionBinding::Plugin(p) => {
-                    let current = self
-                        .actions
-                        .iter()
-                        .find(|a| a.plugin_id == p.plugin_id && a.action_id == p.action_id)
-                        .cloned();
-                    pick_list(self.actions.clone(), current, move |c| {
-                        Message::MacroStepPluginPicked { idx: i, choice: c }
-                    })
-                    .into()
-                }
-                ActionBinding::Builtin(BuiltinAction::IssueCommand { command, .. }) => {
-                    text_input("bash command…", command).on_input(move |v| {
-                        Message::MacroStepCommandChanged { idx: i, value: v }
-                    })
-                    .into()
-                }
-                _ => text("Unsupported step type (edit by changing kind).")
-                    .style(color_text_muted())
-                    .into(),
-            };
+fn panel() -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
+        let p = theme.extended_palette();
+        let mut shade = p.background.weak.color;
+        shade.a = 0.92;
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(shade)),
+            text_color: Some(p.background.base.text),
+            border: Border::default(),
+            shadow: Shadow {
+                // Subtle shading (keep it sleek; dividers do most of the separation).
+                color: Color::from_rgba8(0, 0, 0, 0.12),
+                offset: iced::Vector::new(0.0, 4.0),
+                blur_radius: 12.0,
+            },
+        }
+    }))
+}
 
-            col = col.push(
-                container(
-                    column![
-                        row![
-                            controls,
-                            horizontal_space(),
-                            text(format!("Step {}", i + 1)).style(color_text_muted()),
-                        ]
-                        .align_items(Alignment::Center),
-                        row![text("Kind").size(12).style(color_text_muted()), kind_picker]
-                            .spacing(10)
-                            .align_items(Alignment::Center),
-                        row![text("Delay").size(12).style(color_text_muted()), delay_input]
-                            .spacing(10)
-                            .align_items(Alignment::Center),
-                        editor,
-                    ]
-                    .spacing(6),
-                )
-                .padding(10)
-                .style(panel()),
-            );
+fn callout_card(radius: f32) -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
+        let p = theme.extended_palette();
+        let mut shade = p.background.strong.color;
+        shade.a = 0.96;
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(shade)),
+            text_color: Some(p.background.base.text),
+            border: Border {
+                radius: radius.into(),
+                width: 1.0,
+                color: Color::from_rgba8(255, 255, 255, 0.12),
+            },
+            shadow: Shadow {
+                color: Color::from_rgba8(0, 0, 0, 0.18),
+                offset: iced::Vector::new(0.0, 6.0),
+                blur_radius: 14.0,
+            },
         }
+    }))
+}
 
-        col.into()
-    }
+fn error_banner() -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(|_theme: &Theme| {
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(Color::from_rgb8(70, 10, 10))),
+            text_color: Some(Color::from_rgb8(255, 190, 190)),
+            border: Border {
+                radius: 0.0.into(),
+                width: 1.0,
+                color: Color::from_rgb8(120, 30, 30),
+            },
+            shadow: Shadow::default(),
+        }
+    }))
+}
 
-    fn set_selected_action_mode(&mut self, mode: ActionModeChoice) {
-        match mode {
-            ActionModeChoice::None => {
-                if let Some(slot) = self.selected_binding_mut() {
-                    *slot = None;
-                }
-            }
-            ActionModeChoice::Plugin => {
-                // Keep existing plugin binding if present; otherwise choose first available action.
-                if matches!(
-                    self.selected_binding().and_then(|b| b.as_ref()),
-                    Some(ActionBinding::Plugin(_))
-                ) {
-                    return;
-                }
-                let Some(first) = self.actions.first().cloned() else {
-                    self.error = Some("No plugin actions available. Install a plugin first.".to_string());
-                    if let Some(slot) = self.selected_binding_mut() {
-                        *slot = None;
-                    }
-                    return;
-                };
-                let settings = default_settings_for_action(&self.plugins, &first);
-                if let Some(slot) = self.selected_binding_mut() {
-                    *slot = Some(ActionBinding::Plugin(PluginActionBinding {
-                        plugin_id: first.plugin_id,
-                        action_id: first.action_id,
-                        settings,
-                    }));
-                }
-            }
-            ActionModeChoice::Builtin => {
-                if matches!(
-                    self.selected_binding().and_then(|b| b.as_ref()),
-                    Some(ActionBinding::Builtin(_))
-                ) {
-                    return;
-                }
-                if let Some(slot) = self.selected_binding_mut() {
-                    *slot = Some(ActionBinding::Builtin(BuiltinAction::IssueCommand {
-                        command: String::new(),
-                        cwd: None,
-                        timeout_ms: None,
-                    }));
-                }
-            }
+fn divider_style() -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
+        let p = theme.extended_palette();
+        let mut c = p.background.strong.color;
+        c.a = 0.65;
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(c)),
+            text_color: None,
+            border: Border::default(),
+            shadow: Shadow::default(),
         }
-    }
+    }))
+}
 
-    fn assign_dragged_action_to_key(&mut self, idx: usize, dragged: DraggedAction) {
-        let Some(p) = &mut self.profile else {
-            self.error = Some("[Action] No profile loaded.".to_string());
-            return;
-        };
-        let Some(k) = p.keys.get_mut(idx) else {
-            return;
-        };
+fn v_divider() -> Element<'static, Message> {
+    container(text(""))
+        .width(Length::Fixed(1.0))
+        .height(Length::Fill)
+        .style(divider_style())
+        .into()
+}
+
+fn h_divider() -> Element<'static, Message> {
+    container(text(""))
+        .width(Length::Fill)
+        .height(Length::Fixed(1.0))
+        .style(divider_style())
+        .into()
+}
+
+fn default_marketplace_sources() -> Vec<MarketplaceSource> {
+    // Built-in default: Rivul/OpenAction marketplace catalogue
+    // (as used by https://marketplace.rivul.us/).
+    let mut out = vec![MarketplaceSource {
+        name: "Rivul (OpenAction)".to_string(),
+        index_url: "https://openactionapi.github.io/plugins/catalogue.json".to_string(),
+        icon_base_url: Some("https://openactionapi.github.io/plugins/icons/".to_string()),
+    }];
 
-        match dragged {
-            DraggedAction::Plugin(choice) => {
-                let settings = default_settings_for_action(&self.plugins, &choice);
-                k.action = Some(ActionBinding::Plugin(PluginActionBinding {
-                    plugin_id: choice.plugin_id,
-                    action_id: choice.action_id,
-                    settings,
-                }));
+    // Optional user-provided list:
+    // OPENACTION_MARKETPLACES="Name|https://...;Other|https://..."
+    // Optional third field:
+    // OPENACTION_MARKETPLACES="Name|https://...|https://icons/;Other|https://..."
+    if let Ok(raw) = std::env::var("OPENACTION_MARKETPLACES") {
+        for part in raw.split(';') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
             }
-            DraggedAction::Builtin(kind) => {
-                k.action = Some(ActionBinding::Builtin(match kind {
-                    BuiltinKindChoice::Macro => BuiltinAction::Macro { steps: vec![] },
-                    BuiltinKindChoice::IssueCommand => BuiltinAction::IssueCommand {
-                        command: String::new(),
-                        cwd: None,
-                        timeout_ms: None,
-                    },
-                    BuiltinKindChoice::KeyboardInput => BuiltinAction::KeyboardInput {
-                        text: None,
-                        keys: vec![],
-                    },
-                    BuiltinKindChoice::PlaySound => BuiltinAction::PlaySound {
-                        path: String::new(),
-                    },
-                    BuiltinKindChoice::SwitchProfile => BuiltinAction::SwitchProfile {
-                        mode: actions::SwitchProfileMode::Next,
-                    },
-                    BuiltinKindChoice::DeviceBrightness => BuiltinAction::DeviceBrightness {
-                        mode: actions::BrightnessMode::Set { percent: 30 },
-                    },
-                    BuiltinKindChoice::SystemMonitoring => BuiltinAction::SystemMonitoring {
-                        kind: actions::MonitorKind::Cpu,
-                        refresh_ms: Some(500),
-                    },
-                }));
+            let mut it = part.split('|');
+            let name = it.next().unwrap_or("").trim();
+            let url = it.next().unwrap_or("").trim();
+            let icon_base_url = it.next().map(|s| s.trim()).filter(|s| !s.is_empty());
+            if name.is_empty() || url.is_empty() {
+                continue;
+            }
+            let src = MarketplaceSource {
+                name: name.to_string(),
+                index_url: url.to_string(),
+                icon_base_url: icon_base_url.map(|s| s.to_string()),
+            };
+            if !out.contains(&src) {
+                out.push(src);
             }
         }
     }
 
-    fn set_selected_builtin_kind(&mut self, kind: BuiltinKindChoice) {
-        let Some(slot) = self.selected_binding_mut() else {
-            return;
-        };
-        *slot = Some(ActionBinding::Builtin(match kind {
-            BuiltinKindChoice::Macro => BuiltinAction::Macro { steps: vec![] },
-            BuiltinKindChoice::IssueCommand => BuiltinAction::IssueCommand {
-                command: String::new(),
-                cwd: None,
-                timeout_ms: None,
-            },
-            BuiltinKindChoice::KeyboardInput => BuiltinAction::KeyboardInput {
-                text: None,
-                keys: vec![],
-            },
-            BuiltinKindChoice::PlaySound => BuiltinAction::PlaySound {
-                path: String::new(),
-            },
-            BuiltinKindChoice::SwitchProfile => BuiltinAction::SwitchProfile {
-                mode: actions::SwitchProfileMode::Next,
-            },
-            BuiltinKindChoice::DeviceBrightness => BuiltinAction::DeviceBrightness {
-                mode: actions::BrightnessMode::Set { percent: 30 },
-            },
-            BuiltinKindChoice::SystemMonitoring => BuiltinAction::SystemMonitoring {
-                kind: actions::MonitorKind::Cpu,
-                refresh_ms: Some(500),
-            },
-        }));
-    }
+    out
+}
 
-    fn update_selected_builtin(&mut self, f: impl FnOnce(&mut BuiltinAction)) {
-        let Some(slot) = self.selected_binding_mut() else {
-            return;
+/// Returns (key_size_px, gap_px, padding_px, deck_radius_px)
+/// Shortcuts of the editor window. Only sees presses no widget handled, so they don't fire
+/// while a text field has focus (except Tab and Up/Down, which text fields pass on).
+fn editor_key_press(
+    key: iced::keyboard::Key,
+    modifiers: iced::keyboard::Modifiers,
+) -> Option<Message> {
+    use iced::keyboard::{key::Named, Key};
+
+    if modifiers.command() {
+        let Key::Character(c) = key else {
+            return None;
         };
-        let Some(ActionBinding::Builtin(b)) = slot.as_mut() else {
-            return;
+        let edit = match c.to_lowercase().as_str() {
+            "c" => KeyEdit::Copy,
+            "x" => KeyEdit::Cut,
+            "v" => KeyEdit::Paste,
+            "d" => KeyEdit::Duplicate,
+            "=" | "+" => return Some(Message::InterfaceEdited(InterfaceEdit::ZoomIn)),
+            "-" => return Some(Message::InterfaceEdited(InterfaceEdit::ZoomOut)),
+            "0" => return Some(Message::InterfaceEdited(InterfaceEdit::ResetZoom)),
+            _ => return None,
         };
-        f(b)
+        return Some(Message::KeyEdited(edit));
     }
-
-    fn selected_builtin_brightness_value(&self) -> Option<i32> {
-        let b = self.selected_binding()?.as_ref()?;
-        let ActionBinding::Builtin(BuiltinAction::DeviceBrightness { mode }) = b else {
-            return None;
-        };
-        Some(match mode {
-            actions::BrightnessMode::Set { percent } => *percent as i32,
-            actions::BrightnessMode::Increase { delta } => *delta as i32,
-            actions::BrightnessMode::Decrease { delta } => *delta as i32,
-        })
+    if modifiers.alt() {
+        return None;
     }
+    match key {
+        Key::Named(Named::ArrowLeft) => Some(Message::MoveKeyFocus(-1, 0)),
+        Key::Named(Named::ArrowRight) => Some(Message::MoveKeyFocus(1, 0)),
+        Key::Named(Named::ArrowUp) => Some(Message::MoveKeyFocus(0, -1)),
+        Key::Named(Named::ArrowDown) => Some(Message::MoveKeyFocus(0, 1)),
+        Key::Named(Named::Enter) => Some(Message::OpenInspector),
+        Key::Named(Named::Tab) => Some(Message::CycleFocus {
+            backwards: modifiers.shift(),
+        }),
+        _ => None,
+    }
+}
 
-    fn macro_add_step(&mut self) {
-        let default_plugin = self.actions.first().cloned();
-        let default_settings = default_plugin
-            .as_ref()
-            .map(|c| default_settings_for_action(&self.plugins, c));
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-
-            let action = if let Some(first) = default_plugin {
-                ActionBinding::Plugin(PluginActionBinding {
-                    plugin_id: first.plugin_id,
-                    action_id: first.action_id,
-                    settings: default_settings.unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new())),
-                })
-            } else {
-                ActionBinding::Builtin(BuiltinAction::IssueCommand {
-                    command: String::new(),
-                    cwd: None,
-                    timeout_ms: None,
-                })
-            };
+/// The key `dx` columns and `dy` rows away from `idx`, stopping at the edges of the grid.
+fn grid_neighbor(idx: usize, caps: &DeviceCapabilities, dx: i32, dy: i32) -> usize {
+    let (cols, rows) = (usize::from(caps.key_grid.0), usize::from(caps.key_grid.1));
+    let key_count = caps.key_count;
+    let col = ((idx % cols) as i32 + dx).clamp(0, cols as i32 - 1) as usize;
+    let row = ((idx / cols) as i32 + dy).clamp(0, rows as i32 - 1) as usize;
+    (row * cols + col).min(usize::from(key_count).saturating_sub(1))
+}
 
-            steps.push(actions::MacroStep {
-                action: Box::new(action),
-                delay_ms: None,
-            });
-        });
+fn deck_metrics(key_count: u8) -> (f32, f32, f32, f32) {
+    match key_count {
+        // Slightly tighter + less rounded for a modern, cleaner feel.
+        8 => (72.0, 10.0, 18.0, 18.0),
+        32 => (62.0, 9.0, 16.0, 16.0),
+        6 => (80.0, 12.0, 18.0, 18.0),
+        _ => (74.0, 10.0, 18.0, 18.0),
     }
+}
 
-    fn macro_remove_step(&mut self, idx: usize) {
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-            if idx < steps.len() {
-                steps.remove(idx);
-            }
-        });
-    }
+fn deck_body_style(radius: f32) -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
+        let p = theme.extended_palette();
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(p.background.strong.color)),
+            text_color: Some(p.background.base.text),
+            border: Border {
+                radius: radius.into(),
+                width: 1.0,
+                color: Color::from_rgba8(255, 255, 255, 0.06),
+            },
+            shadow: Shadow {
+                color: Color::from_rgba8(0, 0, 0, 0.32),
+                offset: iced::Vector::new(0.0, 16.0),
+                blur_radius: 42.0,
+            },
+        }
+    }))
+}
 
-    fn macro_move_step(&mut self, idx: usize, up: bool) {
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-            if steps.is_empty() || idx >= steps.len() {
-                return;
-            }
-            if up {
-                if idx == 0 {
-                    return;
+/// The keys of `profile` laid out as on its device, with their backgrounds and texts.
+fn view_profile_preview(profile: &Profile) -> Element<'static, Message> {
+    let caps = DeviceCapabilities::for_key_count(profile.key_count);
+    let cols = usize::from(caps.key_grid.0).max(1);
+    let size = (360.0 / cols as f32 - 6.0).min(56.0);
+    let mut grid = column![].spacing(6);
+    for keys in profile.keys.chunks(cols) {
+        let mut line = row![].spacing(6);
+        for k in keys {
+            let color = match k.appearance.background {
+                storage::profiles::Background::Solid { rgb: [r, g, b] } => {
+                    Color::from_rgb8(r, g, b)
                 }
-                steps.swap(idx, idx - 1);
-            } else {
-                if idx + 1 >= steps.len() {
-                    return;
-                }
-                steps.swap(idx, idx + 1);
-            }
-        });
-    }
-
-    fn macro_set_step_kind(&mut self, idx: usize, kind: MacroStepKindChoice) {
-        let default_plugin = self.actions.first().cloned();
-        let default_settings = default_plugin
-            .as_ref()
-            .map(|c| default_settings_for_action(&self.plugins, c));
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-            let Some(step) = steps.get_mut(idx) else {
-                return;
+                storage::profiles::Background::None => Color::BLACK,
             };
-            step.action = Box::new(match kind {
-                MacroStepKindChoice::PluginAction => {
-                    if let Some(first) = default_plugin {
-                        ActionBinding::Plugin(PluginActionBinding {
-                            plugin_id: first.plugin_id,
-                            action_id: first.action_id,
-                            settings: default_settings.unwrap_or_else(|| {
-                                serde_json::Value::Object(serde_json::Map::new())
-                            }),
-                        })
-                    } else {
-                        ActionBinding::Builtin(BuiltinAction::IssueCommand {
-                            command: String::new(),
-                            cwd: None,
-                            timeout_ms: None,
-                        })
-                    }
-                }
-                MacroStepKindChoice::IssueCommand => ActionBinding::Builtin(BuiltinAction::IssueCommand {
-                    command: String::new(),
-                    cwd: None,
-                    timeout_ms: None,
-                }),
-            });
-        });
+            let label = k.appearance.text.clone().unwrap_or_else(|| k.label.clone());
+            line = line.push(
+                container(text(label).size(10))
+                    .width(Length::Fixed(size))
+                    .height(Length::Fixed(size))
+                    .center_x()
+                    .center_y()
+                    .style(preview_key_style(color)),
+            );
+        }
+        grid = grid.push(line);
     }
+    container(grid)
+        .padding(12)
+        .style(deck_body_style(12.0))
+        .into()
+}
 
-    fn macro_set_step_delay(&mut self, idx: usize, value: String) {
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-            let Some(step) = steps.get_mut(idx) else {
-                return;
-            };
-            let s = value.trim();
-            step.delay_ms = if s.is_empty() { None } else { s.parse::<u64>().ok() };
-        });
+/// Where the decks of `surface` are in its grid, one color per deck.
+fn view_surface_layout(surface: &Surface) -> Element<'static, Message> {
+    let (columns, rows) = surface.grid();
+    let mut grid = column![].spacing(2);
+    for row_index in 0..rows {
+        let mut line = row![].spacing(2);
+        for column_index in 0..columns {
+            let key = u16::from(row_index) * u16::from(columns) + u16::from(column_index);
+            let deck = u8::try_from(key)
+                .ok()
+                .and_then(|k| surface.device_key(k))
+                .and_then(|(d, _)| surface.members.iter().position(|m| m.device == d.0));
+            let color = deck.map_or(Color::from_rgba8(255, 255, 255, 0.04), surface_member_color);
+            line = line.push(
+                container(text(""))
+                    .width(Length::Fixed(14.0))
+                    .height(Length::Fixed(14.0))
+                    .style(preview_key_style(color)),
+            );
+        }
+        grid = grid.push(line);
     }
+    grid.into()
+}
+
+fn surface_member_color(i: usize) -> Color {
+    const COLORS: [(u8, u8, u8); 6] = [
+        (0x3b, 0x82, 0xf6),
+        (0x22, 0xc5, 0x5e),
+        (0xf5, 0x9e, 0x0b),
+        (0xa8, 0x55, 0xf7),
+        (0xef, 0x44, 0x44),
+        (0x14, 0xb8, 0xa6),
+    ];
+    let (r, g, b) = COLORS[i % COLORS.len()];
+    Color::from_rgb8(r, g, b)
+}
+
+fn preview_key_style(color: Color) -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(move |_theme: &Theme| {
+        iced::widget::container::Appearance {
+            background: Some(Background::Color(color)),
+            text_color: Some(Color::WHITE),
+            border: Border {
+                radius: 6.0.into(),
+                width: 1.0,
+                color: Color::from_rgba8(255, 255, 255, 0.12),
+            },
+            ..Default::default()
+        }
+    }))
+}
 
-    fn macro_set_step_plugin(&mut self, idx: usize, choice: ActionChoice) {
-        let settings = default_settings_for_action(&self.plugins, &choice);
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-            let Some(step) = steps.get_mut(idx) else {
-                return;
-            };
-            step.action = Box::new(ActionBinding::Plugin(PluginActionBinding {
-                plugin_id: choice.plugin_id,
-                action_id: choice.action_id,
-                settings,
-            }));
-        });
+/// Sidebar control for a sandboxed plugin's network permission (empty when not applicable).
+fn network_permission_button(p: &InstalledPlugin) -> Element<'static, Message> {
+    if !p.sandboxed || !p.manifest.permissions.network {
+        return text("").into();
     }
+    let label = match p.network_granted {
+        Some(true) => "Net: allowed",
+        Some(false) => "Net: denied",
+        None => "Net: ask",
+    };
+    button(text(label).size(12))
+        .style(iced::theme::Button::Text)
+        .on_press(Message::ShowPermissionPrompt(p.manifest.id.clone()))
+        .into()
+}
 
-    fn macro_set_step_command(&mut self, idx: usize, value: String) {
-        self.update_selected_builtin(|b| {
-            let BuiltinAction::Macro { steps } = b else {
-                return;
-            };
-            let Some(step) = steps.get_mut(idx) else {
-                return;
-            };
-            step.action = Box::new(ActionBinding::Builtin(BuiltinAction::IssueCommand {
-                command: value,
-                cwd: None,
-                timeout_ms: None,
-            }));
-        });
+fn compatibility_badge(
+    p: &MarketplacePlugin,
+    compat: Compatibility,
+) -> Option<Element<'static, Message>> {
+    let label = match compat {
+        Compatibility::Compatible | Compatibility::Unknown => return None,
+        Compatibility::UnsupportedOs => {
+            format!("Not available for this OS ({})", p.supported_os().join(", "))
+        }
+        Compatibility::UnsupportedApi => format!(
+            "Requires OpenAction API {}",
+            p.api_versions.join(", ")
+        ),
+    };
+    Some(
+        text(label)
+            .size(12)
+            .style(Color::from_rgb8(255, 160, 160))
+            .into(),
+    )
+}
+
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    if max_chars == 0 {
+        return String::new();
+    }
+    let mut out = String::new();
+    for (i, ch) in s.chars().enumerate() {
+        if i >= max_chars {
+            break;
+        }
+        out.push(ch);
+    }
+    if s.chars().count() > max_chars && max_chars >= 2 {
+        out.pop();
+        out.push('…');
     }
+    out
+}
 
-    fn view_deck_preview(&self, key_count: u8, pressed: &[bool]) -> Element<'_, Message> {
-        let (cols, rows) = deck_grid_dims(key_count);
-        let (key, gap, pad, radius) = deck_metrics(key_count);
+async fn diagnose_hid_async() -> Option<HidrawDiagnosis> {
+    tokio::task::spawn_blocking(transport_hid::diagnostics::diagnose)
+        .await
+        .ok()
+        .flatten()
+}
 
-        let mut grid = column![].spacing(gap as u16);
-        for r in 0..rows {
-            let mut line = row![].spacing(gap as u16);
-            for c in 0..cols {
-                let idx = r * cols + c;
-                if idx >= key_count as usize {
-                    break;
+async fn install_udev_rule_async() -> Result<(), String> {
+    // pkexec blocks on the polkit prompt; keep it off the async executor.
+    tokio::task::spawn_blocking(transport_hid::diagnostics::install_udev_rule)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// The HID interfaces of connected Elgato devices, for the Device Test view's report monitor.
+async fn list_hid_interfaces_async() -> Result<Vec<device_test::HidInterface>, String> {
+    tokio::task::spawn_blocking(|| {
+        let hid = transport_hid::HidContext::new().map_err(|e| e.to_string())?;
+        let interfaces = hid
+            .list_all()
+            .into_iter()
+            .filter(|d| d.vendor_id == transport_hid::diagnostics::ELGATO_VENDOR_ID)
+            .map(|d| {
+                let mut label = d
+                    .product_string
+                    .unwrap_or_else(|| format!("Elgato device {:04x}", d.product_id));
+                if let Some(serial) = d.serial_number.filter(|s| !s.is_empty()) {
+                    label.push_str(&format!(" ({serial})"));
                 }
-                line = line.push(self.view_deck_key(idx, pressed));
-            }
-            grid = grid.push(line);
+                if let Some(interface) = d.interface_number {
+                    label.push_str(&format!(" · interface {interface}"));
+                }
+                device_test::HidInterface {
+                    label,
+                    path: d.path,
+                }
+            })
+            .collect();
+        Ok(interfaces)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Draw `pattern` on every key; returns how long writing each key's image took.
+async fn write_test_pattern_async(
+    controller: DeviceController,
+    pattern: render::test_patterns::TestPattern,
+    key_count: u8,
+    (width, height): (u32, u32),
+) -> Result<Vec<Duration>, String> {
+    let images = tokio::task::spawn_blocking(move || {
+        (0..key_count)
+            .map(|key| render::test_patterns::pattern_jpeg(pattern, key, width, height))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    let mut times = Vec::with_capacity(images.len());
+    for (key, jpeg) in (0..key_count).zip(images) {
+        let started = Instant::now();
+        controller
+            .set_key_image_jpeg(key, jpeg)
+            .await
+            .map_err(|e| e.to_string())?;
+        times.push(started.elapsed());
+    }
+    Ok(times)
+}
+
+async fn list_devices_async() -> Result<Vec<DiscoveredDevice>, String> {
+    let svc = HidDeviceService::new().map_err(|e| e.to_string())?;
+    svc.list_devices().await.map_err(|e| e.to_string())
+}
+
+async fn list_profiles_async() -> Result<Vec<ProfileMeta>, String> {
+    storage::profiles::list_profiles().map_err(|e| e.to_string())
+}
+
+async fn load_profile_async(id: ProfileId) -> Result<Profile, String> {
+    let path = storage::profiles::profile_path(id).map_err(|e| e.to_string())?;
+    storage::profiles::load_profile(&path).map_err(|e| {
+        // Say where the file is wrong, not just what serde stumbled over first.
+        let mut msg = e.to_string();
+        for d in profile_diagnostics(&path) {
+            msg.push_str(&format!("\n{d}"));
         }
+        msg
+    })
+}
 
-        // Stream Deck+ preview: keys + touch strip + 4 dials.
-        let content = if key_count == 8 {
-            let strip_h = 52.0;
-            let dial_size = 56.0;
-            let strip_selected = self.selected_control == Some(SelectedControl::TouchStrip);
+async fn validate_profile_async(id: ProfileId) -> (ProfileId, Vec<Diagnostic>) {
+    let diagnostics = match storage::profiles::profile_path(id) {
+        Ok(path) => profile_diagnostics(&path),
+        Err(_) => vec![],
+    };
+    (id, diagnostics)
+}
 
-            let strip = container(text("Touch strip").size(12).style(color_text_muted()))
-            .width(Length::Fill)
-            .height(Length::Fixed(strip_h))
-            .center_x()
-            .center_y()
-            .style(iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
-                let p = theme.extended_palette();
-                iced::widget::container::Appearance {
-                    background: Some(Background::Color(p.background.base.color)),
-                    text_color: Some(p.background.base.text),
-                    border: Border {
-                        // Square strip (sleek / hardware-like)
-                        radius: 0.0.into(),
-                        width: 1.0,
-                        color: if strip_selected {
-                            Color::from_rgba8(255, 255, 255, 0.22)
-                        } else {
-                            Color::from_rgba8(255, 255, 255, 0.08)
-                        },
-                    },
-                    shadow: Shadow::default(),
-                }
-            })));
+/// Problems in the profile file at `path`; none when it can't be read.
+fn profile_diagnostics(path: &std::path::Path) -> Vec<Diagnostic> {
+    // Without the plugin list, don't report plugins as missing.
+    let installed: Option<Vec<String>> = openaction::registry::list_installed()
+        .ok()
+        .map(|plugins| plugins.into_iter().map(|p| p.manifest.id).collect());
+    let plugin_installed = |id: &str| match &installed {
+        Some(ids) => ids.iter().any(|p| p == id),
+        None => true,
+    };
+    storage::validate::validate_profile_file(path, &plugin_installed).unwrap_or_default()
+}
 
-            let dial = |idx: usize| {
-                let dial_selected = self.selected_control == Some(SelectedControl::Dial(idx));
-                let label = self
-                    .profile
-                    .as_ref()
-                    .and_then(|p| p.dials.get(idx))
-                    .map(|d| d.label.clone())
-                    .filter(|s| !s.trim().is_empty())
-                    .unwrap_or_else(|| format!("Dial {}", idx + 1));
-                container(text(label).size(11).style(color_text_muted()))
-                    .width(Length::Fixed(dial_size))
-                    .height(Length::Fixed(dial_size))
-                    .center_x()
-                    .center_y()
-                    .style(iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
-                        let p = theme.extended_palette();
-                        iced::widget::container::Appearance {
-                            background: Some(Background::Color(p.background.base.color)),
-                            text_color: Some(p.background.base.text),
-                            border: Border {
-                                // Fully round knobs.
-                                radius: 999.0.into(),
-                                width: 1.0,
-                                color: if dial_selected {
-                                    Color::from_rgba8(255, 255, 255, 0.22)
-                                } else {
-                                    Color::from_rgba8(255, 255, 255, 0.08)
-                                },
-                            },
-                            shadow: Shadow::default(),
-                        }
-                    })))
-            };
+/// Resolve the keys `p` inherits from its (new) base profile, then save it.
+async fn set_base_profile_async(mut p: Profile) -> Result<Profile, String> {
+    storage::profiles::apply_base_profile(&mut p).map_err(|e| e.to_string())?;
+    storage::profiles::save_profile(&p).map_err(|e| e.to_string())?;
+    Ok(p)
+}
 
-            let strip = mouse_area(strip).on_press(Message::SelectControl(SelectedControl::TouchStrip));
-            let dials = container(
-                row![
-                    mouse_area(dial(0)).on_press(Message::SelectControl(SelectedControl::Dial(0))),
-                    mouse_area(dial(1)).on_press(Message::SelectControl(SelectedControl::Dial(1))),
-                    mouse_area(dial(2)).on_press(Message::SelectControl(SelectedControl::Dial(2))),
-                    mouse_area(dial(3)).on_press(Message::SelectControl(SelectedControl::Dial(3))),
-                ]
-                .spacing(12)
-                .align_items(Alignment::Center),
-            )
-            .width(Length::Fill)
-            .center_x();
+async fn create_profile_async(name: &str, key_count: u8) -> Result<Profile, String> {
+    let p = storage::profiles::create_profile(name, key_count).map_err(|e| e.to_string())?;
+    storage::profiles::save_profile(&p).map_err(|e| e.to_string())?;
+    Ok(p)
+}
 
-            column![grid, strip, dials].spacing(gap as u16)
-        } else {
-            grid
-        };
+async fn save_profile_async(profile: Profile) -> Result<(), String> {
+    storage::profiles::save_profile(&profile).map_err(|e| e.to_string())
+}
 
-        let width = (cols as f32 * key) + ((cols - 1) as f32 * gap) + 2.0 * pad;
-        let base_height = (rows as f32 * key) + ((rows - 1) as f32 * gap) + 2.0 * pad;
-        let extra = if key_count == 8 {
-            // Touch strip + dials + gaps between sections
-            52.0 + 56.0 + (gap * 2.0)
-        } else {
-            0.0
-        };
+async fn list_plugins_async() -> Result<Vec<InstalledPlugin>, String> {
+    openaction::registry::list_installed().map_err(|e| e.to_string())
+}
 
-        let deck = container(content)
-            .padding(pad as u16)
-            .style(deck_body_style(radius))
-            .width(Length::Fixed(width))
-            .height(Length::Fixed(base_height + extra));
+/// Thumbnails for the actions panel; icons that fail to load are left out.
+async fn load_action_icons_async(
+    paths: Vec<std::path::PathBuf>,
+) -> Vec<(std::path::PathBuf, iced::widget::image::Handle)> {
+    paths
+        .into_iter()
+        .filter_map(|path| match render::lcd::load_icon(&path, ACTION_ICON_SIZE) {
+            Ok(icon) => {
+                let (w, h) = icon.dimensions();
+                let handle = iced::widget::image::Handle::from_pixels(w, h, icon.into_raw());
+                Some((path, handle))
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "failed to load action icon");
+                None
+            }
+        })
+        .collect()
+}
 
-        deck.into()
+async fn install_plugin_async(path: String) -> Result<(), String> {
+    use std::path::Path;
+    let path = Path::new(&path);
+    if path.is_dir() {
+        return openaction::registry::install_local_dir(path).map_err(|e| e.to_string());
     }
+    // Anything else is taken for an archive (.zip, .tar.gz, .streamdeckplugin).
+    let verification = Verification::default();
+    openaction::installer::install_from_file(path, None, &verification)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    fn view_deck_key(&self, idx: usize, pressed: &[bool]) -> Element<'_, Message> {
-        let is_pressed = pressed.get(idx).copied().unwrap_or(false);
-        let is_selected = self.selected_control == Some(SelectedControl::Key(idx));
-        let is_drop_hover = self.drag.dragging.is_some() && self.drag.over_key == Some(idx);
-        let (key, _gap, _pad, _radius) =
-            deck_metrics(self.connected.as_ref().map(|c| c.key_count).unwrap_or(15));
-
-        let label = self
-            .profile
-            .as_ref()
-            .and_then(|p| p.keys.get(idx))
-            .map(|k| k.label.as_str())
-            .unwrap_or("");
+async fn set_plugin_enabled_async(plugin_id: String, enabled: bool) -> Result<(), String> {
+    openaction::registry::set_enabled(&plugin_id, enabled).map_err(|e| e.to_string())
+}
 
-        let action_hint = self
-            .profile
-            .as_ref()
-            .and_then(|p| p.keys.get(idx))
-            .and_then(|k| k.action.as_ref())
-            .and_then(|a| self.binding_hint(a));
+async fn set_sandbox_enabled_async(enabled: bool) -> Result<(), String> {
+    openaction::registry::set_sandbox_enabled(enabled).map_err(|e| e.to_string())
+}
 
-        let max_title = if key <= 64.0 { 10 } else { 14 };
-        let max_sub = if key <= 64.0 { 12 } else { 18 };
+async fn set_network_permission_async(plugin_id: String, allow: bool) -> Result<(), String> {
+    openaction::registry::set_network_permission(&plugin_id, allow).map_err(|e| e.to_string())
+}
 
-        let title = if label.is_empty() {
-            action_hint.clone().unwrap_or_else(|| format!("Key {idx}"))
-        } else {
-            label.to_string()
-        };
+async fn set_plugin_launch_async(plugin_id: String, launch: PluginLaunch) -> Result<(), String> {
+    openaction::registry::set_launch(&plugin_id, launch).map_err(|e| e.to_string())
+}
 
-        let subtitle = if label.is_empty() { None } else { action_hint };
-        let title = truncate(&title, max_title);
-        let subtitle = subtitle.map(|s| truncate(&s, max_sub));
+async fn uninstall_plugin_async(plugin_id: String) -> Result<UninstallReport, String> {
+    openaction::registry::uninstall(&plugin_id).map_err(|e| e.to_string())
+}
 
-        let content = column![
-            text(title)
-                .size(12)
-                .width(Length::Fill)
-                .horizontal_alignment(Horizontal::Center),
-            subtitle
-                .map(|s| {
-                    text(s)
-                        .size(10)
-                        .style(color_text_muted())
-                        .width(Length::Fill)
-                        .horizontal_alignment(Horizontal::Center)
-                })
-                .unwrap_or_else(|| text("").size(10))
-        ]
-        .spacing(2)
-        .align_items(Alignment::Center);
+async fn install_marketplace_async(
+    url: String,
+    expected_id: String,
+    verification: Verification,
+) -> Result<(), MarketplaceInstallError> {
+    openaction::installer::install_from_url(&url, Some(&expected_id), &verification)
+        .await
+        .map(|_| ())
+        .map_err(|e| MarketplaceInstallError {
+            verification_failed: e.downcast_ref::<VerificationError>().is_some(),
+            message: e.to_string(),
+        })
+}
 
-        let key_btn = button(container(content).center_x().center_y())
-            .width(Length::Fixed(key))
-            .height(Length::Fixed(key))
-            .padding(0)
-            .on_press(Message::SelectControl(SelectedControl::Key(idx)))
-            .style(iced::theme::Button::custom(DeckKeyStyle {
-                pressed: is_pressed,
-                selected: is_selected,
-                drop_hover: is_drop_hover,
-            }))
-            ;
+async fn install_marketplace_from_repo_async(
+    repo_url: String,
+    expected_id: String,
+    verification: Verification,
+    github_token: Option<String>,
+) -> Result<(), MarketplaceInstallError> {
+    let url = resolve_github_release_asset_url_async(&repo_url, github_token.as_deref())
+        .await
+        .map_err(|message| MarketplaceInstallError {
+            message,
+            verification_failed: false,
+        })?;
+    install_marketplace_async(url, expected_id, verification).await
+}
 
-        // Drop target surface: release mouse over a key to assign the currently dragged action.
-        mouse_area(key_btn)
-            .on_enter(Message::DragOverKey(Some(idx)))
-            .on_exit(Message::DragOverKey(None))
-            .on_release(Message::DropOnKey(idx))
-            .into()
-    }
+async fn fetch_marketplace_details_async(
+    plugin: MarketplacePlugin,
+    github_token: Option<String>,
+) -> Result<MarketplaceDetails, String> {
+    let mut out = MarketplaceDetails {
+        repository: plugin.repository.clone(),
+        ..MarketplaceDetails::default()
+    };
 
-    fn action_label(&self, plugin_id: &str, action_id: &str) -> Option<String> {
-        let (_plugin, action) = find_action_def_by_ids(&self.plugins, plugin_id, action_id)?;
-        Some(action.name.clone())
-    }
+    let Some(repo_url) = plugin.repository.as_deref() else {
+        return Ok(out);
+    };
+    let Some((owner, repo)) = parse_github_owner_repo(repo_url) else {
+        return Ok(out);
+    };
 
-    fn binding_hint(&self, binding: &ActionBinding) -> Option<String> {
-        match binding {
-            ActionBinding::Plugin(p) => self.action_label(&p.plugin_id, &p.action_id),
-            ActionBinding::Builtin(b) => Some(match b {
-                actions::BuiltinAction::Macro { .. } => "Macro".to_string(),
-                actions::BuiltinAction::IssueCommand { .. } => "Issue Command".to_string(),
-                actions::BuiltinAction::KeyboardInput { .. } => "Keyboard Input".to_string(),
-                actions::BuiltinAction::PlaySound { .. } => "Play Sound".to_string(),
-                actions::BuiltinAction::SwitchProfile { .. } => "Switch Profile".to_string(),
-                actions::BuiltinAction::DeviceBrightness { .. } => "Device Brightness".to_string(),
-                actions::BuiltinAction::SystemMonitoring { kind, .. } => match kind {
-                    actions::MonitorKind::Cpu => format!("CPU {:.0}%", self.sys_snapshot.cpu_percent),
-                    actions::MonitorKind::Memory => {
-                        let used = self.sys_snapshot.mem_used as f32 / (1024.0 * 1024.0);
-                        let total = self.sys_snapshot.mem_total as f32 / (1024.0 * 1024.0);
-                        format!("Mem {:.0}/{:.0}MB", used, total)
-                    }
-                    actions::MonitorKind::LoadAverage => {
-                        let (a, b, c) = self.sys_snapshot.load;
-                        format!("Load {:.2} {:.2} {:.2}", a, b, c)
-                    }
-                },
-            }),
+    // README + images
+    if let Ok((branch, md)) = fetch_github_readme_md_async(&owner, &repo).await {
+        out.readme_md = Some(md.clone());
+        for raw in extract_readme_image_urls(&md).into_iter().take(24) {
+            if !is_renderable_image_url(&raw) {
+                continue;
+            }
+            if let Some(abs) = resolve_github_readme_asset_url(&owner, &repo, &branch, &raw) {
+                if !out.image_urls.contains(&abs) {
+                    out.image_urls.push(abs);
+                }
+            }
         }
     }
 
-    fn refresh_system_snapshot(&mut self) {
-        // Lightweight periodic refresh for UI display (no device rendering yet).
-        // Keep it conservative to avoid adding overhead.
-        if self.sys_last_refresh.elapsed() < Duration::from_millis(500) {
-            return;
+    // Releases: total download counts + best asset URL for install.
+    match fetch_github_releases_info_async(&owner, &repo, github_token.as_deref()).await {
+        Ok((count, asset)) => {
+            out.total_downloads = Some(count);
+            out.resolved_download_url = asset;
         }
-        self.sys_last_refresh = Instant::now();
+        Err(e) => out.github_error = Some(e),
+    }
 
-        use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind};
-        let refresh = RefreshKind::nothing()
-            .with_cpu(CpuRefreshKind::everything())
-            .with_memory(MemoryRefreshKind::everything());
-        self.sys.refresh_specifics(refresh);
+    Ok(out)
+}
 
-        // CPU usage: average across all CPUs.
-        let cpus = self.sys.cpus();
-        let cpu_percent = if cpus.is_empty() {
-            0.0
-        } else {
-            cpus.iter().map(|c| c.cpu_usage()).sum::<f32>() / (cpus.len() as f32)
-        };
+async fn fetch_marketplace_async(url: String) -> Result<MarketplaceIndex, String> {
+    openaction::marketplace::fetch_index(&url)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-        let mem_total = self.sys.total_memory();
-        let mem_used = self.sys.used_memory();
+async fn fetch_profile_gallery_async(url: String) -> Result<ProfileGallery, String> {
+    openaction::profile_gallery::fetch_gallery(&url)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-        let load = sysinfo::System::load_average();
-        self.sys_snapshot = SystemSnapshot {
-            cpu_percent,
-            mem_used,
-            mem_total,
-            load: (load.one, load.five, load.fifteen),
-        };
-    }
+async fn fetch_gallery_profile_async(entry: GalleryProfile) -> Result<Profile, String> {
+    openaction::profile_gallery::fetch_profile(&entry)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
 
-    fn current_action_choice(&self) -> Option<ActionChoice> {
-        let b = match self.selected_binding()?.as_ref()? {
-            ActionBinding::Plugin(p) => p,
-            ActionBinding::Builtin(_) => return None,
+/// Install `plugins` one after another, then save `profile`.
+async fn import_gallery_profile_async(
+    profile: Profile,
+    plugins: Vec<PluginDownload>,
+    github_token: Option<String>,
+) -> Result<Profile, String> {
+    install_plugin_downloads_async(plugins, github_token).await?;
+    storage::profiles::save_profile(&profile).map_err(|e| e.to_string())?;
+    Ok(profile)
+}
+
+/// The plugins of `ids` the marketplace `sources` offer for this platform, each from the first
+/// source that has it. Fails only when no source could be read.
+async fn find_plugin_downloads_async(
+    sources: Vec<MarketplaceSource>,
+    ids: Vec<String>,
+) -> Result<Vec<PluginDownload>, String> {
+    let mut found: Vec<PluginDownload> = vec![];
+    let mut last_error = None;
+    let mut read_any = false;
+    for src in &sources {
+        let index = match openaction::marketplace::fetch_index(&src.index_url).await {
+            Ok(index) => index,
+            Err(e) => {
+                tracing::warn!(source = %src.name, error = %e, "failed to read marketplace");
+                last_error = Some(format!("{}: {e}", src.name));
+                continue;
+            }
         };
-        self.actions
-            .iter()
-            .find(|a| a.plugin_id == b.plugin_id && a.action_id == b.action_id)
-            .cloned()
+        read_any = true;
+        for p in index.plugins {
+            let wanted = ids.contains(&p.id) && !found.iter().any(|d| d.plugin.id == p.id);
+            if !wanted || p.compatibility().is_incompatible() {
+                continue;
+            }
+            let url = resolve_marketplace_download_url(src, &p);
+            let from_repo = p.repository.as_deref().and_then(parse_github_owner_repo);
+            if url.is_some() || from_repo.is_some() {
+                found.push(PluginDownload { plugin: p, url });
+            }
+        }
+    }
+    match last_error {
+        Some(e) if !read_any => Err(e),
+        _ => Ok(found),
     }
+}
 
-    fn view_action_settings(&self) -> Element<'_, Message> {
-        let Some(binding) = self.selected_binding().and_then(|b| b.as_ref()) else {
-            return text("No action bound.").into();
+/// Install `plugins` one after another. Stops at the first plugin that fails to install.
+async fn install_plugin_downloads_async(
+    plugins: Vec<PluginDownload>,
+    github_token: Option<String>,
+) -> Result<(), String> {
+    for d in plugins {
+        let verification = Verification::from_marketplace(&d.plugin);
+        let id = d.plugin.id.clone();
+        let res = match (d.url, d.plugin.repository) {
+            (Some(url), _) => install_marketplace_async(url, id, verification).await,
+            (None, Some(repo)) => {
+                let token = github_token.clone();
+                install_marketplace_from_repo_async(repo, id, verification, token).await
+            }
+            (None, None) => continue,
         };
+        res.map_err(|e| format!("Installing {} failed: {}", d.plugin.name, e.message))?;
+    }
+    Ok(())
+}
 
-        let ActionBinding::Plugin(binding) = binding else {
-            // Builtin actions have their own editor (added in `ui-config` todo).
-            return text("Builtin action settings are not shown here yet.").into();
-        };
+async fn fetch_icon_async(downloader: IconDownloader, url: String) -> Result<Vec<u8>, String> {
+    downloader.fetch(&url).await.map_err(|e| e.to_string())
+}
 
-        let Some((_plugin, action_def)) =
-            find_action_def_by_ids(&self.plugins, &binding.plugin_id, &binding.action_id)
-        else {
-            return text("Action not found (plugin missing or manifest changed).").into();
-        };
+async fn open_url_async(url: String) -> Result<(), String> {
+    if url.trim().is_empty() {
+        return Ok(());
+    }
+    engine::launcher::open(url).await.map_err(|e| e.to_string())
+}
 
-        if action_def.settings.is_empty() {
-            return text("This action has no settings.").into();
+fn marketplace_icon_url(source: &MarketplaceSource, plugin: &MarketplacePlugin) -> Option<String> {
+    if let Some(url) = &plugin.icon_url {
+        if !url.trim().is_empty() {
+            return Some(url.clone());
         }
+    }
+    let base = source.icon_base_url.as_ref()?.trim();
+    if base.is_empty() {
+        return None;
+    }
+    let mut out = base.to_string();
+    if !out.ends_with('/') {
+        out.push('/');
+    }
+    out.push_str(&plugin.id);
+    out.push_str(".png");
+    Some(out)
+}
 
-        let mut col = column![text("Settings").size(16)].spacing(8);
+fn resolve_marketplace_download_url(
+    source: &MarketplaceSource,
+    plugin: &MarketplacePlugin,
+) -> Option<String> {
+    let raw = plugin.download_url.as_ref()?.trim();
+    if raw.is_empty() {
+        return None;
+    }
 
-        for field in &action_def.settings {
-            col = col.push(self.view_setting_field(field, &binding.settings));
-        }
+    // Absolute.
+    if reqwest::Url::parse(raw).is_ok() {
+        return Some(raw.to_string());
+    }
 
-        col.into()
+    // Relative: resolve against the marketplace index URL.
+    let base = reqwest::Url::parse(source.index_url.trim()).ok()?;
+    base.join(raw).ok().map(|u| u.to_string())
+}
+
+fn resolve_marketplace_asset_url(source: &MarketplaceSource, raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
     }
 
-    fn view_setting_field(
-        &self,
-        field: &SettingField,
-        settings: &serde_json::Value,
-    ) -> Element<'_, Message> {
-        let key = field.key.clone();
-        let label = field.label.clone();
+    // Absolute.
+    if reqwest::Url::parse(raw).is_ok() {
+        return Some(raw.to_string());
+    }
 
-        match field.ty {
-            SettingType::String => {
-                let current = settings
-                    .get(&field.key)
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let input =
-                    text_input("", &current).on_input(move |v| Message::SettingStringChanged {
-                        key: key.clone(),
-                        value: v,
-                    });
-                column![text(label), input].spacing(4).into()
-            }
-            SettingType::Boolean => {
-                let current = settings
-                    .get(&field.key)
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                checkbox(label, current)
-                    .on_toggle(move |v| Message::SettingBoolChanged {
-                        key: key.clone(),
-                        value: v,
-                    })
-                    .into()
-            }
-            SettingType::Number => {
-                let current = settings
-                    .get(&field.key)
-                    .and_then(|v| v.as_f64())
-                    .map(|n| n.to_string())
-                    .or_else(|| {
-                        settings
-                            .get(&field.key)
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())
-                    })
-                    .unwrap_or_default();
-                let input =
-                    text_input("", &current).on_input(move |v| Message::SettingNumberChanged {
-                        key: key.clone(),
-                        value: v,
-                    });
-                column![text(label), input].spacing(4).into()
+    // Relative: resolve against the marketplace index URL.
+    let base = reqwest::Url::parse(source.index_url.trim()).ok()?;
+    base.join(raw).ok().map(|u| u.to_string())
+}
+
+fn parse_github_owner_repo(repo_url: &str) -> Option<(String, String)> {
+    let url = reqwest::Url::parse(repo_url.trim()).ok()?;
+    if url.host_str()? != "github.com" {
+        return None;
+    }
+    let mut segs = url.path_segments()?;
+    let owner = segs.next()?.trim().to_string();
+    let repo = segs.next()?.trim().trim_end_matches(".git").to_string();
+    if owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+    Some((owner, repo))
+}
+
+async fn fetch_github_readme_md_async(owner: &str, repo: &str) -> Result<(String, String), String> {
+    let candidates = [
+        ("main", "README.md"),
+        ("main", "readme.md"),
+        ("master", "README.md"),
+        ("master", "readme.md"),
+    ];
+    for (branch, file) in candidates {
+        let url = format!(
+            "https://raw.githubusercontent.com/{owner}/{repo}/{branch}/{file}"
+        );
+        match openaction::marketplace::fetch_cached(&url).await {
+            Ok(fetched) => {
+                let md = String::from_utf8(fetched.bytes)
+                    .map_err(|_| "README is not utf-8".to_string())?;
+                return Ok((branch.to_string(), md));
             }
+            Err(_) => continue,
         }
     }
+    Err("README not found (tried main/master README.md/readme.md)".to_string())
+}
 
-    fn render_markdown(&self, md: &str, source: Option<&MarketplaceSource>) -> Element<'_, Message> {
-        use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+fn extract_readme_image_urls(md: &str) -> Vec<String> {
+    let mut out = Vec::new();
 
-        let mut opts = Options::empty();
-        opts.insert(Options::ENABLE_TABLES);
-        opts.insert(Options::ENABLE_STRIKETHROUGH);
-        opts.insert(Options::ENABLE_FOOTNOTES);
-        opts.insert(Options::ENABLE_TASKLISTS);
+    // Markdown: ![alt](url)
+    let bytes = md.as_bytes();
+    let mut i = 0;
+    while i + 3 < bytes.len() {
+        if bytes[i] == b'!' && bytes[i + 1] == b'[' {
+            // find "]("
+            if let Some(close_bracket) = md[i + 2..].find("](") {
+                let start = i + 2 + close_bracket + 2; // points at '('
+                // actual url begins after '('
+                let url_start = start + 1;
+                if url_start >= md.len() {
+                    break;
+                }
+                if let Some(end_rel) = md[url_start..].find(')') {
+                    let url_raw = md[url_start..url_start + end_rel].trim();
+                    // strip optional title: "url \"title\""
+                    let url_only = url_raw
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or("")
+                        .trim()
+                        .trim_matches('<')
+                        .trim_matches('>');
+                    if !url_only.is_empty() {
+                        out.push(url_only.to_string());
+                    }
+                    i = url_start + end_rel + 1;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
 
-        let parser = Parser::new_ext(md, opts);
+    // HTML: <img src="...">
+    let mut rest = md;
+    while let Some(pos) = rest.find("src=\"") {
+        rest = &rest[pos + 5..];
+        if let Some(end) = rest.find('"') {
+            let url = rest[..end].trim();
+            if !url.is_empty() {
+                out.push(url.to_string());
+            }
+            rest = &rest[end + 1..];
+        } else {
+            break;
+        }
+    }
 
-        let mut elems: Vec<Element<Message>> = vec![];
+    // De-dupe, preserve order
+    let mut uniq = Vec::new();
+    for u in out {
+        if !uniq.contains(&u) {
+            uniq.push(u);
+        }
+    }
+    uniq
+}
 
-        let mut cur = String::new();
-        let mut cur_link: Option<String> = None;
-        let mut heading_level: Option<u8> = None;
-        let mut in_code_block = false;
-        let mut code_buf = String::new();
+fn is_renderable_image_url(url: &str) -> bool {
+    let u = url.trim().to_ascii_lowercase();
+    if u.is_empty() {
+        return false;
+    }
+    // Accept image-like URLs, even if they lack an extension (e.g. shields.io badges).
+    // We'll sniff bytes after download to decide svg vs png vs jpeg.
+    u.contains("://")
+}
 
-        fn flush_paragraph(
-            elems: &mut Vec<Element<Message>>,
-            text_buf: &mut String,
-            link: &mut Option<String>,
-            heading_level: &mut Option<u8>,
-        ) {
-            let s = text_buf.trim();
-            if s.is_empty() {
-                *text_buf = String::new();
-                *link = None;
-                *heading_level = None;
-                return;
-            }
+fn is_png_bytes(bytes: &[u8]) -> bool {
+    bytes.len() >= 8
+        && bytes[0..8] == [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]
+}
 
-            // Heading
-            if let Some(level) = *heading_level {
-                let size = match level {
-                    1 => 18,
-                    2 => 16,
-                    3 => 15,
-                    _ => 14,
-                };
-                elems.push(text(s.to_string()).size(size).into());
-                *text_buf = String::new();
-                *link = None;
-                *heading_level = None;
-                return;
-            }
+fn is_jpeg_bytes(bytes: &[u8]) -> bool {
+    bytes.len() >= 3 && bytes[0] == 0xFF && bytes[1] == 0xD8 && bytes[2] == 0xFF
+}
 
-            // Link-only line (basic)
-            if let Some(url) = link.take() {
-                elems.push(
-                    button(text(s.to_string()))
-                        .style(iced::theme::Button::Secondary)
-                        .on_press(Message::OpenUrl(url))
-                        .into(),
-                );
-                *text_buf = String::new();
-                *heading_level = None;
-                return;
-            }
+fn is_gif_bytes(bytes: &[u8]) -> bool {
+    bytes.len() >= 6 && (&bytes[0..6] == b"GIF87a" || &bytes[0..6] == b"GIF89a")
+}
 
-            elems.push(text(s.to_string()).size(12).into());
-            *text_buf = String::new();
-            *heading_level = None;
-        }
+fn is_webp_bytes(bytes: &[u8]) -> bool {
+    // RIFF....WEBP
+    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP"
+}
 
-        for ev in parser {
-            match ev {
-                Event::Start(Tag::Heading { level, .. }) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                    heading_level = Some(level as u8);
-                }
-                Event::End(TagEnd::Heading { .. }) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                }
-                Event::Start(Tag::Paragraph) => {}
-                Event::End(TagEnd::Paragraph) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                }
-                Event::Start(Tag::Link { dest_url, .. }) => {
-                    cur_link = Some(dest_url.to_string());
-                }
-                Event::End(TagEnd::Link) => {
-                    // keep link; flush on paragraph end
-                }
-                Event::Start(Tag::CodeBlock(_)) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                    in_code_block = true;
-                    code_buf.clear();
-                }
-                Event::End(TagEnd::CodeBlock) => {
-                    in_code_block = false;
-                    let s = code_buf.trim_end().to_string();
-                    if !s.is_empty() {
-                        elems.push(
-                            container(text(s).size(12).style(color_text_muted()))
-                                .padding(10)
-                                .style(panel())
-                                .into(),
-                        );
-                    }
-                    code_buf.clear();
-                }
-                Event::Text(t) => {
-                    if in_code_block {
-                        code_buf.push_str(&t);
-                    } else {
-                        cur.push_str(&t);
-                    }
-                }
-                Event::Code(t) => {
-                    if in_code_block {
-                        code_buf.push_str(&t);
-                    } else {
-                        cur.push('`');
-                        cur.push_str(&t);
-                        cur.push('`');
-                    }
-                }
-                Event::SoftBreak | Event::HardBreak => {
-                    if in_code_block {
-                        code_buf.push('\n');
-                    } else {
-                        cur.push('\n');
-                    }
-                }
-                Event::Start(Tag::List(_)) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                }
-                Event::End(TagEnd::List(_)) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                }
-                Event::Start(Tag::Item) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                    cur.push_str("• ");
-                }
-                Event::End(TagEnd::Item) => {
-                    flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
-                }
-                Event::Start(Tag::Image { dest_url, .. }) => {
-                    // Try to show image if it's a renderable format; otherwise provide a link button.
-                    let raw = dest_url.to_string();
-                    if is_renderable_image_url(&raw) {
-                        if let Some(src) = source {
-                            if let Some(url) = resolve_marketplace_asset_url(src, &raw) {
-                                elems.push(
-                                    button(text(format!("Open image: {}", truncate(&url, 60))))
-                                        .style(iced::theme::Button::Secondary)
-                                        .on_press(Message::OpenUrl(url))
-                                        .into(),
-                                );
-                            }
-                        } else {
-                            elems.push(
-                                button(text(format!("Open image: {}", truncate(&raw, 60))))
-                                    .style(iced::theme::Button::Secondary)
-                                    .on_press(Message::OpenUrl(raw.clone()))
-                                    .into(),
-                            );
-                        }
-                    } else {
-                        elems.push(
-                            button(text(format!("Open image (unsupported): {}", truncate(&raw, 60))))
-                                .style(iced::theme::Button::Secondary)
-                                .on_press(Message::OpenUrl(raw.clone()))
-                                .into(),
-                        );
-                    }
+fn decode_raster_to_png_bytes(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    use ::image::codecs::png::PngEncoder;
+    use ::image::{ColorType, ImageEncoder};
+
+    let img =
+        ::image::load_from_memory(bytes).map_err(|e| format!("image decode failed: {e}"))?;
+    let rgba = img.to_rgba8();
+    let (w, h) = rgba.dimensions();
+
+    let mut out = Vec::new();
+    let enc = PngEncoder::new(&mut out);
+    enc.write_image(&rgba, w, h, ColorType::Rgba8.into())
+        .map_err(|e| format!("png encode failed: {e}"))?;
+    Ok(out)
+}
+
+fn is_svg_bytes(bytes: &[u8]) -> bool {
+    // Best-effort sniff: look for "<svg" within the first ~1KB (after optional XML decl/whitespace).
+    let head = &bytes[..bytes.len().min(1024)];
+    let s = String::from_utf8_lossy(head).to_ascii_lowercase();
+    s.contains("<svg") && !s.contains("<html")
+}
+
+fn validate_svg_bytes(bytes: &[u8]) -> Result<(), String> {
+    // Avoid noisy runtime errors: if parsing fails, treat as a failed image load.
+    use usvg::TreeParsing;
+    let opt = usvg::Options::default();
+    usvg::Tree::from_data(bytes, &opt)
+        .map(|_| ())
+        .map_err(|e: usvg::Error| e.to_string())
+}
+
+fn resolve_github_readme_asset_url(owner: &str, repo: &str, branch: &str, raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+
+    // Already absolute.
+    if reqwest::Url::parse(raw).is_ok() {
+        // Convert GitHub blob URLs to raw URLs.
+        if raw.starts_with("https://github.com/") && raw.contains("/blob/") {
+            // https://github.com/{owner}/{repo}/blob/{branch}/path -> raw.githubusercontent.com/{owner}/{repo}/{branch}/path
+            let prefix = format!("https://github.com/{owner}/{repo}/blob/");
+            if let Some(rest) = raw.strip_prefix(&prefix) {
+                let mut it = rest.splitn(2, '/');
+                let b = it.next().unwrap_or(branch);
+                let path = it.next().unwrap_or("");
+                return Some(format!(
+                    "https://raw.githubusercontent.com/{owner}/{repo}/{b}/{path}"
+                ));
+            }
+        }
+        return Some(raw.to_string());
+    }
+
+    // Root-relative within repo.
+    if raw.starts_with('/') {
+        return Some(format!(
+            "https://raw.githubusercontent.com/{owner}/{repo}/{branch}{}",
+            raw
+        ));
+    }
+
+    // Relative path.
+    let rel = raw.trim_start_matches("./");
+    Some(format!(
+        "https://raw.githubusercontent.com/{owner}/{repo}/{branch}/{rel}"
+    ))
+}
+
+async fn fetch_github_releases_info_async(
+    owner: &str,
+    repo: &str,
+    token: Option<&str>,
+) -> Result<(u64, Option<String>), String> {
+    let releases = openaction::github::releases(owner, repo, token)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut total: u64 = 0;
+    for rel in &releases {
+        if let Some(assets) = rel.get("assets").and_then(|a| a.as_array()) {
+            for a in assets {
+                if let Some(c) = a.get("download_count").and_then(|c| c.as_u64()) {
+                    total += c;
                 }
-                _ => {}
             }
         }
+    }
 
-        flush_paragraph(&mut elems, &mut cur, &mut cur_link, &mut heading_level);
+    // Pick best installable asset from the latest release (first in list).
+    let asset_url = releases
+        .first()
+        .and_then(|rel| rel.get("assets").and_then(|a| a.as_array()))
+        .and_then(|assets| openaction::github::release_asset_url(assets));
 
-        let out = elems
-            .into_iter()
-            .fold(column![].spacing(6), |col, el| col.push(el));
+    Ok((total, asset_url))
+}
 
-        // Important: do NOT wrap markdown in its own scrollable, because the details panel already
-        // has a scroll container. Nested scrollables prevent mouse-wheel scrolling from working.
-        out.into()
+async fn resolve_github_release_asset_url_async(
+    repo_url: &str,
+    token: Option<&str>,
+) -> Result<String, String> {
+    let (owner, repo) =
+        parse_github_owner_repo(repo_url).ok_or_else(|| "unsupported repository url".to_string())?;
+    let (_count, asset) = fetch_github_releases_info_async(&owner, &repo, token).await?;
+    asset.ok_or_else(|| "no downloadable release archive found on GitHub".to_string())
+}
+
+fn parse_bg_rgb(raw: &str) -> Option<[u8; 3]> {
+    let s = raw.trim();
+    if s.is_empty() {
+        return None;
+    }
+    let parts: Vec<&str> = s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()).collect();
+    if parts.len() != 3 {
+        return None;
     }
+    let r = parts[0].parse::<u8>().ok()?;
+    let g = parts[1].parse::<u8>().ok()?;
+    let b = parts[2].parse::<u8>().ok()?;
+    Some([r, g, b])
 }
 
-#[derive(Debug, Clone, Copy)]
-struct DeckKeyStyle {
-    pressed: bool,
-    selected: bool,
-    drop_hover: bool,
+fn build_action_choices(plugins: &[InstalledPlugin]) -> Vec<ActionChoice> {
+    let mut out = vec![];
+    for p in plugins.iter().filter(|p| p.enabled) {
+        for a in &p.manifest.actions {
+            out.push(ActionChoice {
+                plugin_id: p.manifest.id.clone(),
+                action_id: a.id.clone(),
+                label: format!("{}: {}", p.manifest.name, a.name),
+                icon: p.action_icon(a),
+            });
+        }
+    }
+    out.sort_by(|a, b| a.label.to_lowercase().cmp(&b.label.to_lowercase()));
+    out
 }
 
-impl iced::widget::button::StyleSheet for DeckKeyStyle {
-    type Style = Theme;
+/// A key showing no icon of its own takes the icon of the plugin action assigned to it.
+fn apply_default_action_icon(
+    appearance: &mut storage::profiles::Appearance,
+    choice: &ActionChoice,
+) {
+    if appearance.icon_path.is_none() {
+        appearance.icon_path = choice.icon.as_ref().map(|p| p.display().to_string());
+    }
+}
 
-    fn active(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
-        let palette = theme.extended_palette();
+fn find_action_def_by_ids<'a>(
+    plugins: &'a [InstalledPlugin],
+    plugin_id: &str,
+    action_id: &str,
+) -> Option<(&'a InstalledPlugin, &'a ActionDefinition)> {
+    let plugin = plugins.iter().find(|p| p.manifest.id == plugin_id)?;
+    let action = plugin.manifest.actions.iter().find(|a| a.id == action_id)?;
+    Some((plugin, action))
+}
 
-        let bg = if self.pressed {
-            palette.success.weak.color
-        } else {
-            palette.background.strong.color
-        };
+fn default_settings_for_action(
+    plugins: &[InstalledPlugin],
+    choice: &ActionChoice,
+) -> serde_json::Value {
+    let Some((_plugin, action)) =
+        find_action_def_by_ids(plugins, &choice.plugin_id, &choice.action_id)
+    else {
+        return serde_json::Value::Object(serde_json::Map::new());
+    };
 
-        let border_color = if self.drop_hover {
-            palette.primary.base.color
-        } else if self.selected {
-            palette.primary.base.color
-        } else if self.pressed {
-            palette.success.base.color
+    let mut map = serde_json::Map::new();
+    for f in &action.settings {
+        let v = if let Some(d) = &f.default {
+            d.clone()
         } else {
-            palette.background.strong.color
+            match f.ty {
+                SettingType::String => serde_json::Value::String(String::new()),
+                SettingType::Boolean => serde_json::Value::Bool(false),
+                SettingType::Number => serde_json::Value::Null,
+            }
         };
+        map.insert(f.key.clone(), v);
+    }
+    serde_json::Value::Object(map)
+}
 
-        iced::widget::button::Appearance {
-            background: Some(Background::Color(bg)),
-            text_color: palette.background.base.text,
-            border: Border {
-                color: border_color,
-                width: if self.selected { 2.0 } else { 1.0 },
-                radius: 8.0.into(),
-            },
-            shadow: Shadow {
-                color: Color::from_rgba8(0, 0, 0, 0.28),
-                offset: iced::Vector::new(0.0, 4.0),
-                blur_radius: 16.0,
-            },
-            ..Default::default()
+async fn connect_device_async(
+    id: app_core::ids::DeviceId,
+    events_slot: Arc<std::sync::Mutex<Option<Receiver<DeviceEvent>>>>,
+) -> Result<ConnectedInfo, String> {
+    let svc = HidDeviceService::new().map_err(|e| e.to_string())?;
+    let dev = svc.connect(id).await.map_err(|e| e.to_string())?;
+    let controller = dev.controller();
+
+    {
+        let mut guard = events_slot
+            .lock()
+            .map_err(|_| "events mutex poisoned".to_string())?;
+        *guard = Some(dev.events);
+    }
+
+    Ok(ConnectedInfo {
+        id: dev.id,
+        name: dev.name,
+        key_count: dev.key_count,
+        capabilities: dev.capabilities,
+        controller,
+        events_slot,
+    })
+}
+
+/// Image and title a plugin action drew on a key, over the key's own.
+#[derive(Debug, Clone, Default)]
+struct PluginKeyLook {
+    image: Option<std::path::PathBuf>,
+    title: Option<String>,
+}
+
+/// Live values drawn over the appearance of the profile's controls.
+struct LiveValues {
+    playing_sounds: BTreeSet<u8>,
+    counters: BTreeMap<u8, i64>,
+    pomodoros: BTreeMap<u8, PomodoroTimer>,
+    plugin_keys: BTreeMap<u8, PluginKeyLook>,
+    variables: BTreeMap<String, String>,
+    entities: BTreeMap<String, EntityState>,
+    playback: Option<Playback>,
+    screen_recording: bool,
+    flashing: HashMap<Slot, render::lcd::Flash>,
+    /// Drawn across the touch strip for its `system_monitoring` binding.
+    dashboard: Option<Dashboard>,
+    weather: HashMap<WeatherQuery, Result<WeatherReport, String>>,
+    /// Time shown by `clock` keys.
+    now: chrono::DateTime<chrono::Utc>,
+    /// Key showing a desktop notification, and the notification's summary.
+    notification: Option<(u8, String)>,
+    do_not_disturb: Option<bool>,
+    radios: BTreeMap<actions::RadioKind, bool>,
+    power_holds: BTreeMap<u8, Instant>,
+    git: BTreeMap<String, Result<GitStatus, String>>,
+    containers: Option<Result<Vec<Container>, String>>,
+    ci_runs: BTreeMap<CiQuery, Result<Option<CiRun>, String>>,
+    vscode_tasks: HashMap<(String, String), TaskStatus>,
+    lights: HashMap<actions::LightTarget, Result<LightState, String>>,
+    meetings: BTreeMap<actions::MeetingApp, Result<MeetingState, String>>,
+}
+
+/// Every display surface of `profile`, sized for a device with `caps`, with the live values
+/// drawn over it.
+fn display_images(
+    profile: &Profile,
+    caps: &DeviceCapabilities,
+    live: &LiveValues,
+) -> Vec<(Slot, LcdImage)> {
+    let (key_w, key_h) = caps.key_size;
+
+    let mut images: Vec<(Slot, LcdImage)> = vec![];
+
+    // Keys
+    for (idx, k) in profile.keys.iter().enumerate() {
+        let mut image = lcd_image(key_w, key_h, &k.appearance, live);
+        if let Some(look) = live.plugin_keys.get(&(idx as u8)) {
+            if let Some(path) = &look.image {
+                image.icon_path = Some(path.clone());
+            }
+            if let Some(title) = &look.title {
+                image.text = Some(title.clone());
+            }
+        }
+        if live.playing_sounds.contains(&(idx as u8)) {
+            image.background_rgb = Some(SOUND_PLAYING_RGB);
+        }
+        if live.screen_recording && k.action.as_ref().is_some_and(is_screen_record) {
+            image.background_rgb = Some(RECORDING_RGB);
+        }
+        let do_not_disturb = k.action.as_ref().is_some_and(is_do_not_disturb);
+        if let Some(on) = live.do_not_disturb.filter(|_| do_not_disturb) {
+            if on {
+                image.background_rgb = Some(DO_NOT_DISTURB_RGB);
+            }
+            let state = if on { "On" } else { "Off" };
+            image.text = Some(match image.text {
+                Some(t) => format!("{t}\n{state}"),
+                None => format!("DND {state}"),
+            });
+        }
+        if let Some(pressed_at) = live.power_holds.get(&(idx as u8)) {
+            let held = pressed_at.elapsed().as_millis() * 100 / engine::POWER_HOLD.as_millis();
+            image.progress = Some(held.min(100) as u8);
+        }
+        let radio = k.action.as_ref().and_then(radio_of);
+        if let Some((radio, on)) = radio.and_then(|r| Some((r, *live.radios.get(&r)?))) {
+            if on {
+                image.background_rgb = Some(RADIO_ON_RGB);
+            }
+            let state = if on { "On" } else { "Off" };
+            image.text = Some(match image.text {
+                Some(t) => format!("{t}\n{state}"),
+                None => format!("{} {state}", RadioChoice::of(radio)),
+            });
+        }
+        if let Some(repo) = k.action.as_ref().and_then(git_repo_of) {
+            show_git(&mut image, live.git.get(&repo));
+        }
+        if let Some(query) = k.action.as_ref().and_then(ci_query) {
+            show_ci_run(&mut image, live.ci_runs.get(&query));
+        }
+        let container = k.action.as_ref().and_then(container_of);
+        if let Some((container, listed)) = container.zip(live.containers.as_ref()) {
+            show_container(&mut image, listed, &container);
+        }
+        if let Some(light) = k.action.as_ref().and_then(light_of) {
+            show_light(&mut image, live.lights.get(&light));
+        }
+        if let Some((app, command)) = k.action.as_ref().and_then(meeting_of) {
+            show_meeting(&mut image, live.meetings.get(&app), command);
+        }
+        let task = k.action.as_ref().and_then(vscode_task_of);
+        if let Some(status) = task.and_then(|task| live.vscode_tasks.get(&task)) {
+            let (rgb, state) = match status {
+                TaskStatus::Running => (VSCODE_TASK_RUNNING_RGB, "Running"),
+                TaskStatus::Succeeded => (VSCODE_TASK_SUCCEEDED_RGB, "OK"),
+                TaskStatus::Failed => (VSCODE_TASK_FAILED_RGB, "Failed"),
+            };
+            image.background_rgb = Some(rgb);
+            image.text = Some(match image.text {
+                Some(t) => format!("{t}\n{state}"),
+                None => state.to_string(),
+            });
+        }
+        if let Some(ActionBinding::Builtin(BuiltinAction::Counter { .. })) = &k.action {
+            let value = live.counters.get(&(idx as u8)).copied().unwrap_or(0);
+            image.text = Some(match image.text {
+                Some(t) => format!("{t} {value}"),
+                None => value.to_string(),
+            });
+        }
+        let clock = match &k.action {
+            Some(ActionBinding::Builtin(b)) => clock::key_text(b, live.now),
+            _ => None,
+        };
+        if let Some(time) = clock {
+            // The key's text names the clock, e.g. the city.
+            let time = time.unwrap_or_else(|_| "Bad zone".to_string());
+            image.text = Some(match image.text {
+                Some(t) => format!("{t}\n{time}"),
+                None => time,
+            });
+        }
+        if let Some((query, _)) = k.action.as_ref().and_then(weather_query) {
+            show_weather(&mut image, live.weather.get(&query));
         }
+        let pomodoro = match &k.action {
+            Some(ActionBinding::Builtin(b)) => {
+                pomodoro::key_look(b, live.pomodoros.get(&(idx as u8)), SystemTime::now())
+            }
+            _ => None,
+        };
+        if let Some(look) = pomodoro {
+            image.background_rgb = Some(look.background_rgb);
+            image.text = Some(match image.text {
+                Some(t) => format!("{t}\n{}", look.text),
+                None => look.text,
+            });
+        }
+        let play_pause = k.action.as_ref().is_some_and(is_play_pause);
+        if let Some(playback) = live.playback.as_ref().filter(|_| play_pause) {
+            show_playback(&mut image, playback, playback.progress());
+        }
+        if let Some((_, summary)) = live
+            .notification
+            .as_ref()
+            .filter(|(key, _)| *key == idx as u8)
+        {
+            show_notification(&mut image, summary);
+        }
+        images.push((Slot::Key(idx as u8), image));
     }
 
-    fn hovered(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
-        let palette = theme.extended_palette();
-        let mut a = self.active(theme);
-        if !self.pressed {
-            a.background = Some(Background::Color(palette.background.base.color));
+    // Stream Deck+ extras: square dial icons as tall as the strip, drawn onto its segments.
+    if let Some((strip_w, strip_h)) = caps.lcd_strip_size {
+        for (idx, d) in profile.dials.iter().enumerate().take(caps.dials.into()) {
+            let mut image = lcd_image(strip_h, strip_h, &d.appearance, live);
+            let bindings = [&d.rotate, &d.press].into_iter().flatten();
+            if let Some(light) = bindings.filter_map(light_of).next() {
+                show_light(&mut image, live.lights.get(&light));
+            }
+            images.push((Slot::Dial(idx as u8), image));
+        }
+        let strip = &profile.touch_strip;
+        let mut image = lcd_image(strip_w, strip_h, &strip.appearance, live);
+        let shows_playback = strip.bindings().into_iter().flatten().any(is_spotify);
+        if let Some(p) = live.playback.as_ref().filter(|_| shows_playback) {
+            let text = format!("{} – {}  {}", p.title, p.artists, p.progress());
+            show_playback(&mut image, p, text);
         }
-        a
+        image.dashboard = live.dashboard.clone();
+        images.push((Slot::TouchStrip, image));
     }
 
-    fn pressed(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
-        let mut a = self.active(theme);
-        a.shadow = Shadow::default();
-        a
+    for (slot, image) in &mut images {
+        image.flash = live.flashing.get(slot).copied();
     }
+    images
 }
 
-fn app_background() -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
-        let p = theme.extended_palette();
-        iced::widget::container::Appearance {
-            background: Some(Background::Color(p.background.base.color)),
-            text_color: Some(p.background.base.text),
-            border: Border::default(),
-            shadow: Shadow::default(),
-        }
-    }))
+/// The surface `profile` targets, if `device` is one of its decks.
+fn surface_of<'a>(
+    surfaces: &'a [Surface],
+    profile: &Profile,
+    device: app_core::ids::DeviceId,
+) -> Option<&'a Surface> {
+    let id = profile.surface?;
+    surfaces
+        .iter()
+        .find(|s| s.id == id)
+        .filter(|s| s.member(device).is_some())
 }
 
-fn panel() -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
-        let p = theme.extended_palette();
-        let mut shade = p.background.weak.color;
-        shade.a = 0.92;
-        iced::widget::container::Appearance {
-            background: Some(Background::Color(shade)),
-            text_color: Some(p.background.base.text),
-            border: Border::default(),
-            shadow: Shadow {
-                // Subtle shading (keep it sleek; dividers do most of the separation).
-                color: Color::from_rgba8(0, 0, 0, 0.12),
-                offset: iced::Vector::new(0.0, 4.0),
-                blur_radius: 12.0,
-            },
-        }
-    }))
+/// The connected deck as a member of a surface, with its top-left key at `column`, `row`.
+fn surface_member(c: &ConnectedUi, column: u8, row: u8) -> storage::surfaces::SurfaceMember {
+    storage::surfaces::SurfaceMember {
+        device: c.id.0,
+        name: c.name.clone(),
+        column,
+        row,
+        columns: c.capabilities.key_grid.0,
+        rows: c.capabilities.key_grid.1,
+    }
 }
 
-fn callout_card() -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
-        let p = theme.extended_palette();
-        let mut shade = p.background.strong.color;
-        shade.a = 0.96;
-        iced::widget::container::Appearance {
-            background: Some(Background::Color(shade)),
-            text_color: Some(p.background.base.text),
-            border: Border {
-                radius: 10.0.into(),
-                width: 1.0,
-                color: Color::from_rgba8(255, 255, 255, 0.12),
-            },
-            shadow: Shadow {
-                color: Color::from_rgba8(0, 0, 0, 0.18),
-                offset: iced::Vector::new(0.0, 6.0),
-                blur_radius: 14.0,
+/// The part of a surface profile's `images` that `device` shows, as its own keys. Only the deck
+/// the app is connected to (`own`) keeps the dials and touch strip.
+fn deck_images(
+    surface: &Surface,
+    device: app_core::ids::DeviceId,
+    own: bool,
+    images: Vec<(Slot, LcdImage)>,
+) -> Vec<(Slot, LcdImage)> {
+    images
+        .into_iter()
+        .filter_map(|(slot, image)| match slot {
+            Slot::Key(key) => match surface.device_key(key)? {
+                (d, key) if d == device => Some((Slot::Key(key), image)),
+                _ => None,
             },
+            _ if own => Some((slot, image)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render `images` for the live preview; the device gets the same JPEGs from the cache.
+async fn render_previews_async(
+    cache: Arc<std::sync::Mutex<ImageCache>>,
+    images: Vec<(Slot, LcdImage)>,
+) -> Result<Vec<(Slot, iced::widget::image::Handle)>, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut out = vec![];
+        for (slot, image) in images {
+            let jpeg = cache
+                .lock()
+                .map_err(|_| "display cache mutex poisoned".to_string())?
+                .render(&image)
+                .map_err(|e| e.to_string())?;
+            out.push((slot, iced::widget::image::Handle::from_memory(jpeg.to_vec())));
         }
-    }))
+        Ok(out)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
-fn error_banner() -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(|_theme: &Theme| {
-        iced::widget::container::Appearance {
-            background: Some(Background::Color(Color::from_rgb8(70, 10, 10))),
-            text_color: Some(Color::from_rgb8(255, 190, 190)),
-            border: Border {
-                radius: 0.0.into(),
-                width: 1.0,
-                color: Color::from_rgb8(120, 30, 30),
-            },
-            shadow: Shadow::default(),
+async fn apply_displays_async(
+    controller: DeviceController,
+    cache: Arc<std::sync::Mutex<ImageCache>>,
+    images: Vec<(Slot, LcdImage)>,
+) -> Result<(), String> {
+    // Unchanged slots are skipped; the lock is never held across a device write.
+    let mut writes = vec![];
+    {
+        let mut cache = cache
+            .lock()
+            .map_err(|_| "display cache mutex poisoned".to_string())?;
+        for (slot, image) in images {
+            if let Some(jpeg) = cache.prepare(slot, &image).map_err(|e| e.to_string())? {
+                writes.push((slot, jpeg));
+            }
+        }
+    }
+
+    // Every write is queued before any is awaited, so the device's frame scheduler can send
+    // the whole update in one frame instead of one frame per key.
+    let results = futures::future::join_all(writes.into_iter().map(|(slot, jpeg)| {
+        let controller = &controller;
+        async move {
+            let res = match slot {
+                Slot::Key(key) => controller.set_key_image_jpeg(key, jpeg).await,
+                Slot::Dial(dial) => controller.set_dial_image_jpeg(dial, jpeg).await,
+                Slot::TouchStrip => controller.set_touch_strip_image_jpeg(jpeg).await,
+            };
+            (slot, res)
         }
     }))
+    .await;
+
+    let mut cache = cache
+        .lock()
+        .map_err(|_| "display cache mutex poisoned".to_string())?;
+    let mut first_error = None;
+    for (slot, res) in results {
+        match res {
+            Ok(()) => cache.commit(slot),
+            Err(e) => {
+                cache.discard(slot);
+                first_error.get_or_insert(e.to_string());
+            }
+        }
+    }
+    tracing::debug!(stats = ?cache.stats(), "display cache");
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
 }
 
-fn divider_style() -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(|theme: &Theme| {
-        let p = theme.extended_palette();
-        let mut c = p.background.strong.color;
-        c.a = 0.65;
-        iced::widget::container::Appearance {
-            background: Some(Background::Color(c)),
-            text_color: None,
-            border: Border::default(),
-            shadow: Shadow::default(),
+/// Key background while a soundboard sound plays.
+const SOUND_PLAYING_RGB: [u8; 3] = [46, 160, 67];
+/// Background while the shown Home Assistant entity is on/open/playing.
+const ENTITY_ACTIVE_RGB: [u8; 3] = [214, 148, 36];
+/// Background of `screen_record` keys while recording.
+const RECORDING_RGB: [u8; 3] = [200, 40, 40];
+/// Background of `do_not_disturb` keys while do-not-disturb is on.
+const DO_NOT_DISTURB_RGB: [u8; 3] = [110, 60, 170];
+/// Background of `radio` keys while their radio is on.
+const RADIO_ON_RGB: [u8; 3] = [30, 110, 200];
+/// Backgrounds of `ci_status` keys by the state of the latest run.
+const CI_PASSING_RGB: [u8; 3] = [40, 140, 70];
+const CI_FAILING_RGB: [u8; 3] = [180, 40, 40];
+const CI_RUNNING_RGB: [u8; 3] = [190, 130, 20];
+/// Backgrounds of `container` keys while their container runs and while it is stopped.
+const CONTAINER_RUNNING_RGB: [u8; 3] = [40, 140, 70];
+const CONTAINER_STOPPED_RGB: [u8; 3] = [170, 40, 40];
+/// Background of `git` keys whose repository has uncommitted changes.
+const GIT_DIRTY_RGB: [u8; 3] = [170, 100, 30];
+/// Backgrounds of `vs_code` task keys while the task runs, and after it succeeded or failed.
+const VSCODE_TASK_RUNNING_RGB: [u8; 3] = [190, 130, 20];
+const VSCODE_TASK_SUCCEEDED_RGB: [u8; 3] = [40, 140, 70];
+const VSCODE_TASK_FAILED_RGB: [u8; 3] = [180, 40, 40];
+/// Backgrounds of `meeting` keys while the microphone or camera they switch is on, and off.
+const MEETING_LIVE_RGB: [u8; 3] = [40, 140, 70];
+const MEETING_MUTED_RGB: [u8; 3] = [180, 40, 40];
+/// How long a surface flashes after its binding fired.
+const FLASH_DURATION: Duration = Duration::from_millis(150);
+/// Background of the key showing a mirrored desktop notification.
+const NOTIFICATION_RGB: [u8; 3] = [52, 84, 150];
+
+fn lcd_image(
+    width: u32,
+    height: u32,
+    appearance: &storage::profiles::Appearance,
+    live: &LiveValues,
+) -> LcdImage {
+    let mut image = LcdImage {
+        width,
+        height,
+        background_rgb: match appearance.background {
+            storage::profiles::Background::Solid { rgb } => Some(rgb),
+            storage::profiles::Background::None => None,
+        },
+        icon_path: appearance.icon_path.as_ref().map(std::path::PathBuf::from),
+        text: appearance
+            .text
+            .as_deref()
+            .map(|t| variables::render_template(t, |name| live.variables.get(name).cloned())),
+        text_scale: appearance.text_size.map(TextSize::scale),
+        flash: None,
+        progress: None,
+        dashboard: None,
+    };
+    // On/off-like states tint the background; others (sensor values) are appended to the text.
+    let entity = appearance.entity_id.as_ref().and_then(|id| live.entities.get(id));
+    match entity {
+        Some(state) if state.is_binary() => {
+            if state.is_active() {
+                image.background_rgb = Some(ENTITY_ACTIVE_RGB);
+            }
         }
-    }))
+        Some(state) => {
+            image.text = Some(match image.text {
+                Some(t) => format!("{t} {}", state.display()),
+                None => state.display(),
+            });
+        }
+        None => {}
+    }
+    image
 }
 
-fn v_divider() -> Element<'static, Message> {
-    container(text(""))
-        .width(Length::Fixed(1.0))
-        .height(Length::Fill)
-        .style(divider_style())
-        .into()
+/// Whether any display text of `profile` has a `{{variable}}` placeholder.
+fn profile_shows_variables(profile: &Profile) -> bool {
+    let mut texts = profile
+        .keys
+        .iter()
+        .map(|k| &k.appearance)
+        .chain(profile.dials.iter().map(|d| &d.appearance))
+        .chain(std::iter::once(&profile.touch_strip.appearance))
+        .filter_map(|a| a.text.as_deref());
+    texts.any(variables::has_placeholders)
 }
 
-fn h_divider() -> Element<'static, Message> {
-    container(text(""))
-        .width(Length::Fill)
-        .height(Length::Fixed(1.0))
-        .style(divider_style())
-        .into()
+/// Album art (when there is one) and `text` instead of the control's own icon and text.
+fn show_playback(image: &mut LcdImage, playback: &Playback, text: String) {
+    if let Some(art) = &playback.album_art {
+        image.icon_path = Some(art.clone());
+    }
+    image.text = Some(text);
 }
 
-fn default_marketplace_sources() -> Vec<MarketplaceSource> {
-    // Built-in default: Rivul/OpenAction marketplace catalogue
-    // (as used by https://marketplace.rivul.us/).
-    let mut out = vec![MarketplaceSource {
-        name: "Rivul (OpenAction)".to_string(),
-        index_url: "https://openactionapi.github.io/plugins/catalogue.json".to_string(),
-        icon_base_url: Some("https://openactionapi.github.io/plugins/icons/".to_string()),
-    }];
+fn is_screen_record(binding: &ActionBinding) -> bool {
+    matches!(binding, ActionBinding::Builtin(BuiltinAction::ScreenRecord { .. }))
+}
 
-    // Optional user-provided list:
-    // OPENACTION_MARKETPLACES="Name|https://...;Other|https://..."
-    // Optional third field:
-    // OPENACTION_MARKETPLACES="Name|https://...|https://icons/;Other|https://..."
-    if let Ok(raw) = std::env::var("OPENACTION_MARKETPLACES") {
-        for part in raw.split(';') {
-            let part = part.trim();
-            if part.is_empty() {
-                continue;
-            }
-            let mut it = part.split('|');
-            let name = it.next().unwrap_or("").trim();
-            let url = it.next().unwrap_or("").trim();
-            let icon_base_url = it.next().map(|s| s.trim()).filter(|s| !s.is_empty());
-            if name.is_empty() || url.is_empty() {
-                continue;
-            }
-            let src = MarketplaceSource {
-                name: name.to_string(),
-                index_url: url.to_string(),
-                icon_base_url: icon_base_url.map(|s| s.to_string()),
-            };
-            if !out.contains(&src) {
-                out.push(src);
-            }
-        }
+fn is_do_not_disturb(binding: &ActionBinding) -> bool {
+    matches!(binding, ActionBinding::Builtin(BuiltinAction::DoNotDisturb { .. }))
+}
+
+/// The radio a `radio` binding switches.
+fn radio_of(binding: &ActionBinding) -> Option<actions::RadioKind> {
+    match binding {
+        ActionBinding::Builtin(BuiltinAction::Radio { radio }) => Some(*radio),
+        _ => None,
     }
+}
 
-    out
+/// Runs a `ci_status` binding shows.
+fn ci_query(binding: &ActionBinding) -> Option<CiQuery> {
+    match binding {
+        ActionBinding::Builtin(b) => CiQuery::of(b),
+        _ => None,
+    }
 }
 
-fn deck_grid_dims(key_count: u8) -> (usize, usize) {
-    match key_count {
-        8 => (4, 2),
-        6 => (3, 2),
-        32 => (8, 4),
-        _ => (5, 3),
+/// Container a `container` binding controls.
+fn container_of(binding: &ActionBinding) -> Option<String> {
+    match binding {
+        ActionBinding::Builtin(BuiltinAction::Container { container, .. }) => {
+            Some(container.trim().to_string())
+        }
+        _ => None,
     }
 }
 
-/// Returns (key_size_px, gap_px, padding_px, deck_radius_px)
-fn deck_metrics(key_count: u8) -> (f32, f32, f32, f32) {
-    match key_count {
-        // Slightly tighter + less rounded for a modern, cleaner feel.
-        8 => (72.0, 10.0, 18.0, 18.0),
-        32 => (62.0, 9.0, 16.0, 16.0),
-        6 => (80.0, 12.0, 18.0, 18.0),
-        _ => (74.0, 10.0, 18.0, 18.0),
+/// Light a `light` binding switches or dims.
+fn light_of(binding: &ActionBinding) -> Option<actions::LightTarget> {
+    match binding {
+        ActionBinding::Builtin(BuiltinAction::Light { light, .. }) => Some(light.clone()),
+        _ => None,
     }
 }
 
-fn deck_body_style(radius: f32) -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(move |theme: &Theme| {
-        let p = theme.extended_palette();
-        iced::widget::container::Appearance {
-            background: Some(Background::Color(p.background.strong.color)),
-            text_color: Some(p.background.base.text),
-            border: Border {
-                radius: radius.into(),
-                width: 1.0,
-                color: Color::from_rgba8(255, 255, 255, 0.06),
-            },
-            shadow: Shadow {
-                color: Color::from_rgba8(0, 0, 0, 0.32),
-                offset: iced::Vector::new(0.0, 16.0),
-                blur_radius: 42.0,
-            },
-        }
-    }))
+/// App and command of a `meeting` binding.
+fn meeting_of(binding: &ActionBinding) -> Option<(actions::MeetingApp, actions::MeetingCommand)> {
+    match binding {
+        ActionBinding::Builtin(BuiltinAction::Meeting { app, command }) => Some((*app, *command)),
+        _ => None,
+    }
 }
 
-fn color_text_muted() -> Color {
-    // Keep muted text aligned with the chosen theme, without hard-coding a random gray.
-    let p = Theme::TokyoNightStorm.extended_palette();
-    let base = p.background.base.text;
-    Color { a: 0.72, ..base }
+/// Repository a `git` binding shows.
+fn git_repo_of(binding: &ActionBinding) -> Option<String> {
+    match binding {
+        ActionBinding::Builtin(BuiltinAction::Git { repo, .. }) => Some(repo.trim().to_string()),
+        _ => None,
+    }
 }
 
-fn truncate(s: &str, max_chars: usize) -> String {
-    if max_chars == 0 {
-        return String::new();
+/// Folder and label of the task a `vs_code` binding runs.
+fn vscode_task_of(binding: &ActionBinding) -> Option<(String, String)> {
+    match binding {
+        ActionBinding::Builtin(BuiltinAction::VsCode {
+            workspace,
+            command: actions::VsCodeCommand::RunTask(label),
+        }) => Some((workspace.clone(), label.clone())),
+        _ => None,
     }
-    let mut out = String::new();
-    for (i, ch) in s.chars().enumerate() {
-        if i >= max_chars {
-            break;
+}
+
+fn is_spotify(binding: &ActionBinding) -> bool {
+    matches!(binding, ActionBinding::Builtin(BuiltinAction::Spotify { .. }))
+}
+
+fn is_play_pause(binding: &ActionBinding) -> bool {
+    matches!(
+        binding,
+        ActionBinding::Builtin(BuiltinAction::Spotify {
+            command: actions::SpotifyCommand::PlayPause
+        })
+    )
+}
+
+/// The `system_monitoring` binding of the touch strip, which draws a dashboard across it.
+fn strip_monitoring(profile: &Profile) -> Option<&BuiltinAction> {
+    let mut bindings = profile.touch_strip.bindings().into_iter().flatten();
+    bindings.find_map(|b| match b {
+        ActionBinding::Builtin(m @ BuiltinAction::SystemMonitoring { .. }) => Some(m),
+        _ => None,
+    })
+}
+
+/// Current value of `kind` in `sample`, e.g. `42%` or `1.2 MB/s`.
+fn monitor_value(sample: &Sample, kind: &actions::MonitorKind) -> String {
+    use actions::MonitorKind;
+
+    let Some(value) = sample.value(kind) else {
+        return "n/a".to_string();
+    };
+    let gib = 1024.0 * 1024.0 * 1024.0;
+    match kind {
+        MonitorKind::Cpu => format!("{value:.0}%"),
+        MonitorKind::Memory => {
+            let total = sample.mem_total as f64 / gib;
+            format!("{:.1}/{total:.0} GB", value / gib)
+        }
+        MonitorKind::LoadAverage => format!("{value:.2}"),
+        MonitorKind::NetworkRx { .. } | MonitorKind::NetworkTx { .. } => format_rate(value),
+        MonitorKind::DiskUsage { .. } => {
+            let total = sample.full_scale(kind).unwrap_or(0.0);
+            format!("{:.0}%", value / total.max(1.0) * 100.0)
+        }
+        // The display font has no degree sign.
+        MonitorKind::Temperature { .. } => format!("{value:.0} C"),
+        MonitorKind::GpuUsage { .. } => format!("{value:.0}%"),
+        MonitorKind::GpuMemory { .. } => {
+            let total = sample.full_scale(kind).unwrap_or(0.0) / gib;
+            format!("{:.1}/{total:.0} GB", value / gib)
         }
-        out.push(ch);
     }
-    if s.chars().count() > max_chars && max_chars >= 2 {
-        out.pop();
-        out.push('…');
+}
+
+/// `bytes_per_sec` as e.g. `1.2 MB/s`.
+fn format_rate(bytes_per_sec: f64) -> String {
+    match bytes_per_sec {
+        b if b >= 1024.0 * 1024.0 => format!("{:.1} MB/s", b / (1024.0 * 1024.0)),
+        b if b >= 1024.0 => format!("{:.0} KB/s", b / 1024.0),
+        b => format!("{b:.0} B/s"),
     }
-    out
 }
 
-async fn list_devices_async() -> Result<Vec<DiscoveredDevice>, String> {
-    let svc = HidDeviceService::new().map_err(|e| e.to_string())?;
-    svc.list_devices().await.map_err(|e| e.to_string())
+fn profile_shows_clock(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| matches!(b, ActionBinding::Builtin(BuiltinAction::Clock { .. })))
 }
 
-async fn list_profiles_async() -> Result<Vec<ProfileMeta>, String> {
-    storage::profiles::list_profiles().map_err(|e| e.to_string())
+/// Whether a key of `profile` shows the desktop's do-not-disturb.
+fn profile_shows_dnd(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(is_do_not_disturb)
 }
 
-async fn load_profile_async(id: ProfileId) -> Result<Profile, String> {
-    let path = storage::profiles::profile_path(id).map_err(|e| e.to_string())?;
-    storage::profiles::load_profile(&path).map_err(|e| e.to_string())
+/// Whether a key of `profile` shows GitHub Actions runs.
+fn profile_shows_ci(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| ci_query(b).is_some())
 }
 
-async fn create_profile_async(name: &str, key_count: u8) -> Result<Profile, String> {
-    let p = storage::profiles::create_profile(name, key_count).map_err(|e| e.to_string())?;
-    storage::profiles::save_profile(&p).map_err(|e| e.to_string())?;
-    Ok(p)
+/// Whether a key of `profile` shows a container.
+fn profile_shows_containers(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| container_of(b).is_some())
 }
 
-async fn save_profile_async(profile: Profile) -> Result<(), String> {
-    storage::profiles::save_profile(&profile).map_err(|e| e.to_string())
+/// Lights the keys and dials of `profile` switch or dim.
+fn profile_lights(profile: &Profile) -> BTreeSet<actions::LightTarget> {
+    let keys = profile.keys.iter().map(|k| &k.action);
+    let dials = profile.dials.iter().flat_map(|d| [&d.press, &d.rotate]);
+    keys.chain(dials).flatten().filter_map(light_of).collect()
 }
 
-async fn list_plugins_async() -> Result<Vec<InstalledPlugin>, String> {
-    openaction::registry::list_installed().map_err(|e| e.to_string())
+/// Whether a key or dial of `profile` shows a light.
+fn profile_shows_lights(profile: &Profile) -> bool {
+    !profile_lights(profile).is_empty()
 }
 
-async fn install_plugin_async(path: String) -> Result<(), String> {
-    use std::path::Path;
-    openaction::registry::install_local_dir(Path::new(&path)).map_err(|e| e.to_string())
+/// Whether a key of `profile` controls a meeting app.
+fn profile_shows_meetings(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| meeting_of(b).is_some())
 }
 
-async fn install_marketplace_async(url: String, expected_id: String) -> Result<(), String> {
-    openaction::installer::install_from_url(&url, Some(&expected_id))
-        .await
-        .map(|_| ())
-        .map_err(|e| e.to_string())
+/// Whether a key of `profile` shows a git repository.
+fn profile_shows_git(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| git_repo_of(b).is_some())
 }
 
-async fn install_marketplace_from_repo_async(
-    repo_url: String,
-    expected_id: String,
-) -> Result<(), String> {
-    let url = resolve_github_release_asset_url_async(&repo_url).await?;
-    install_marketplace_async(url, expected_id).await
+/// Whether a key of `profile` switches a radio.
+fn profile_shows_radios(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| radio_of(b).is_some())
+}
+
+/// Location and units of a `weather` report.
+type WeatherQuery = (String, actions::WeatherUnits);
+
+/// What a `weather` binding shows, with how old its report may get before it is fetched again.
+fn weather_query(binding: &ActionBinding) -> Option<(WeatherQuery, Duration)> {
+    let ActionBinding::Builtin(BuiltinAction::Weather {
+        location,
+        units,
+        refresh_min,
+    }) = binding
+    else {
+        return None;
+    };
+    let refresh = refresh_min.map_or(weather::DEFAULT_REFRESH, |m| {
+        Duration::from_secs(u64::from(m.max(1)) * 60)
+    });
+    Some(((location.trim().to_string(), *units), refresh))
+}
+
+fn profile_shows_weather(profile: &Profile) -> bool {
+    let mut bindings = profile.keys.iter().filter_map(|k| k.action.as_ref());
+    bindings.any(|b| weather_query(b).is_some())
+}
+
+/// The summary of a desktop notification across the whole key, in the smallest text.
+fn show_notification(image: &mut LcdImage, summary: &str) {
+    let columns = (image.width / 8).saturating_sub(1).max(1) as usize;
+    let rows = (image.height.saturating_sub(8) / 10).max(1) as usize;
+    image.background_rgb = Some(NOTIFICATION_RGB);
+    image.icon_path = None;
+    image.text_scale = Some(1);
+    image.text = Some(wrap_words(summary, columns, rows));
+}
+
+/// `text` broken into lines of at most `width` characters at spaces, keeping the first
+/// `max_lines`. Words longer than a line are split.
+fn wrap_words(text: &str, width: usize, max_lines: usize) -> String {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let chars: Vec<char> = word.chars().collect();
+        for piece in chars.chunks(width.max(1)) {
+            let line = lines.last_mut().expect("lines are never empty");
+            let len = line.chars().count();
+            if len == 0 {
+                line.extend(piece);
+            } else if len + 1 + piece.len() <= width {
+                line.push(' ');
+                line.extend(piece);
+            } else {
+                lines.push(piece.iter().collect());
+            }
+        }
+    }
+    lines.truncate(max_lines);
+    lines.join("\n")
+}
+
+/// The conditions icon and temperature of `report` instead of the key's own icon, after its
+/// text. A report from the cache stays up while offline; `n/a` when there is none, `...`
+/// until the first fetch is done.
+fn show_weather(image: &mut LcdImage, report: Option<&Result<WeatherReport, String>>) {
+    let value = match report {
+        Some(Ok(r)) => {
+            if let Some(icon) = &r.icon_path {
+                image.icon_path = Some(icon.clone());
+            }
+            r.temperature_text()
+        }
+        Some(Err(_)) => "n/a".to_string(),
+        None => "...".to_string(),
+    };
+    image.text = Some(match image.text.take() {
+        Some(t) => format!("{t} {value}"),
+        None => value,
+    });
 }
 
-async fn fetch_marketplace_details_async(plugin: MarketplacePlugin) -> Result<MarketplaceDetails, String> {
-    let mut out = MarketplaceDetails {
-        repository: plugin.repository.clone(),
-        ..MarketplaceDetails::default()
+/// Draw the state of a workflow's latest run onto its `ci_status` key.
+fn show_ci_run(image: &mut LcdImage, run: Option<&Result<Option<CiRun>, String>>) {
+    let value = match run {
+        Some(Ok(Some(run))) => {
+            image.background_rgb = match run.state {
+                RunState::Success => Some(CI_PASSING_RGB),
+                RunState::Failure => Some(CI_FAILING_RGB),
+                RunState::Queued | RunState::InProgress => Some(CI_RUNNING_RGB),
+                RunState::Cancelled | RunState::Other => image.background_rgb,
+            };
+            run.state.label()
+        }
+        Some(Ok(None)) => "No runs",
+        Some(Err(_)) => "n/a",
+        None => "...",
     };
+    image.text = Some(match image.text.take() {
+        Some(t) => format!("{t}\n{value}"),
+        None => value.to_string(),
+    });
+}
 
-    let Some(repo_url) = plugin.repository.as_deref() else {
-        return Ok(out);
+/// Draw whether `container` runs onto its key, from the `listed` containers.
+fn show_container(image: &mut LcdImage, listed: &Result<Vec<Container>, String>, container: &str) {
+    let value = match listed {
+        Ok(containers) => match containers.iter().find(|c| c.is(container)) {
+            Some(c) if c.running => {
+                image.background_rgb = Some(CONTAINER_RUNNING_RGB);
+                "Running"
+            }
+            Some(_) => {
+                image.background_rgb = Some(CONTAINER_STOPPED_RGB);
+                "Stopped"
+            }
+            None => "Missing",
+        },
+        Err(_) => "n/a",
     };
-    let Some((owner, repo)) = parse_github_owner_repo(repo_url) else {
-        return Ok(out);
+    image.text = Some(match image.text.take() {
+        Some(t) => format!("{t}\n{value}"),
+        None => value.to_string(),
+    });
+}
+
+/// Draw a light's brightness onto its key or dial, as a percentage and a ring filled as far.
+fn show_light(image: &mut LcdImage, state: Option<&Result<LightState, String>>) {
+    let value = match state {
+        Some(Ok(s)) if s.on => {
+            image.progress = Some(s.brightness);
+            format!("{}%", s.brightness)
+        }
+        Some(Ok(_)) => "Off".to_string(),
+        Some(Err(_)) => "n/a".to_string(),
+        None => "...".to_string(),
     };
+    image.text = Some(match image.text.take() {
+        Some(t) => format!("{t}\n{value}"),
+        None => value,
+    });
+}
 
-    // README + images
-    if let Ok((branch, md)) = fetch_github_readme_md_async(&owner, &repo).await {
-        out.readme_md = Some(md.clone());
-        for raw in extract_readme_image_urls(&md).into_iter().take(24) {
-            if !is_renderable_image_url(&raw) {
-                continue;
-            }
-            if let Some(abs) = resolve_github_readme_asset_url(&owner, &repo, &branch, &raw) {
-                if !out.image_urls.contains(&abs) {
-                    out.image_urls.push(abs);
-                }
+/// Draw what a `meeting` key's `command` would change onto it: whether the microphone or camera
+/// is on when the app tells, else whether a meeting is on.
+fn show_meeting(
+    image: &mut LcdImage,
+    state: Option<&Result<MeetingState, String>>,
+    command: actions::MeetingCommand,
+) {
+    let value = match state {
+        Some(Ok(s)) if s.in_meeting => {
+            let live = match command {
+                actions::MeetingCommand::ToggleMute => s.muted.map(|muted| !muted),
+                actions::MeetingCommand::ToggleVideo => s.video_on,
+                actions::MeetingCommand::Leave => None,
+            };
+            image.background_rgb = match live {
+                Some(true) => Some(MEETING_LIVE_RGB),
+                Some(false) => Some(MEETING_MUTED_RGB),
+                None => image.background_rgb,
+            };
+            match (command, live) {
+                (actions::MeetingCommand::ToggleMute, Some(true)) => "Mic on",
+                (actions::MeetingCommand::ToggleMute, Some(false)) => "Muted",
+                (actions::MeetingCommand::ToggleVideo, Some(true)) => "Cam on",
+                (actions::MeetingCommand::ToggleVideo, Some(false)) => "Cam off",
+                _ => "In call",
             }
         }
-    }
+        Some(Ok(_)) => "No call",
+        Some(Err(_)) => "n/a",
+        None => "...",
+    };
+    image.text = Some(match image.text.take() {
+        Some(t) => format!("{t}\n{value}"),
+        None => value.to_string(),
+    });
+}
 
-    // Releases: total download counts + best asset URL for install.
-    if let Ok((count, asset)) = fetch_github_releases_info_async(&owner, &repo).await {
-        out.total_downloads = Some(count);
-        out.resolved_download_url = asset;
-    }
+/// Draw a repository's status onto a `git` key, below the key's own text.
+fn show_git(image: &mut LcdImage, status: Option<&Result<GitStatus, String>>) {
+    let value = match status {
+        Some(Ok(s)) => {
+            if s.dirty {
+                image.background_rgb = Some(GIT_DIRTY_RGB);
+            }
+            s.text()
+        }
+        Some(Err(_)) => "No repo".to_string(),
+        None => "...".to_string(),
+    };
+    image.text = Some(match image.text.take() {
+        Some(t) => format!("{t}\n{value}"),
+        None => value,
+    });
+}
 
-    Ok(out)
+/// Whether a display of `profile` shows Spotify playback (Play / Pause keys, the touch strip).
+fn profile_shows_playback(profile: &Profile) -> bool {
+    let strip = &profile.touch_strip;
+    profile.keys.iter().filter_map(|k| k.action.as_ref()).any(is_play_pause)
+        || strip.bindings().into_iter().flatten().any(is_spotify)
 }
 
-async fn fetch_marketplace_async(url: String) -> Result<Vec<MarketplacePlugin>, String> {
-    openaction::marketplace::fetch_plugins(&url)
-        .await
-        .map_err(|e| e.to_string())
+/// Whether a display of `profile` shows `entity_id` (any entity for `None`).
+fn profile_shows_entity(profile: &Profile, entity_id: Option<&str>) -> bool {
+    let mut shown = profile
+        .keys
+        .iter()
+        .map(|k| &k.appearance)
+        .chain(profile.dials.iter().map(|d| &d.appearance))
+        .chain(std::iter::once(&profile.touch_strip.appearance))
+        .filter_map(|a| a.entity_id.as_deref());
+    match entity_id {
+        Some(entity_id) => shown.any(|id| id == entity_id),
+        None => shown.next().is_some(),
+    }
 }
 
-async fn fetch_icon_async(url: String) -> Result<Vec<u8>, String> {
-    openaction::marketplace::fetch_bytes(&url)
-        .await
+async fn load_counters_async(id: ProfileId) -> Result<(ProfileId, BTreeMap<u8, i64>), String> {
+    storage::counters::load_counters(id)
+        .map(|counters| (id, counters))
         .map_err(|e| e.to_string())
 }
 
-async fn sleep_ms_async(ms: u64) {
-    tokio::time::sleep(Duration::from_millis(ms)).await
+async fn reset_counter_async(
+    id: ProfileId,
+    key: u8,
+) -> Result<(ProfileId, BTreeMap<u8, i64>), String> {
+    storage::counters::reset_counter(id, key).map_err(|e| e.to_string())?;
+    load_counters_async(id).await
 }
 
-async fn issue_command_async(
-    command: String,
-    cwd: Option<String>,
-    timeout_ms: Option<u64>,
-) -> Result<(), String> {
-    use tokio::process::Command;
-
-    let mut cmd = Command::new("bash");
-    cmd.arg("-lc").arg(command);
-    if let Some(cwd) = cwd {
-        cmd.current_dir(cwd);
-    }
-    cmd.stdin(std::process::Stdio::null());
-    cmd.stdout(std::process::Stdio::null());
-    cmd.stderr(std::process::Stdio::null());
-
-    let fut = async move {
-        let status = cmd.status().await.map_err(|e| e.to_string())?;
-        if status.success() {
-            Ok(())
-        } else {
-            Err(format!("Command exited with status: {status}"))
-        }
-    };
-
-    if let Some(ms) = timeout_ms {
-        tokio::time::timeout(Duration::from_millis(ms), fut)
-            .await
-            .map_err(|_| "Command timed out".to_string())?
-    } else {
-        fut.await
-    }
+async fn load_pomodoros_async(
+    id: ProfileId,
+) -> Result<(ProfileId, BTreeMap<u8, PomodoroTimer>), String> {
+    storage::pomodoro::load_timers(id)
+        .map(|timers| (id, timers))
+        .map_err(|e| e.to_string())
 }
 
-async fn keyboard_input_async(text: Option<String>, keys: Vec<String>) -> Result<(), String> {
-    // Linux MVP: delegate to an external tool.
-    // Configure with RIVERDECK_KEYBOARD_TOOL, default: wtype
-    let tool = std::env::var("RIVERDECK_KEYBOARD_TOOL").unwrap_or_else(|_| "wtype".to_string());
+async fn reset_pomodoro_async(
+    id: ProfileId,
+    key: u8,
+) -> Result<(ProfileId, BTreeMap<u8, PomodoroTimer>), String> {
+    storage::pomodoro::reset_timer(id, key).map_err(|e| e.to_string())?;
+    load_pomodoros_async(id).await
+}
 
-    if let Some(text) = text {
-        let cmd = format!("{tool} {}", shell_escape(&text));
-        return issue_command_async(cmd, None, Some(5_000)).await;
-    }
+async fn load_schedules_async() -> Result<Vec<Schedule>, String> {
+    storage::schedules::load_schedules().map_err(|e| e.to_string())
+}
 
-    if keys.is_empty() {
-        return Ok(());
-    }
+async fn save_schedules_async(schedules: Vec<Schedule>) -> Result<(), String> {
+    storage::schedules::save_schedules(&schedules).map_err(|e| e.to_string())
+}
 
-    // Treat `keys` as tool arguments (e.g. for wtype: `-k Return`).
-    let args = keys
-        .into_iter()
-        .map(|k| shell_escape(&k))
-        .collect::<Vec<_>>()
-        .join(" ");
-    let cmd = format!("{tool} {args}");
-    issue_command_async(cmd, None, Some(5_000)).await
+async fn load_webhooks_async() -> Result<Vec<Webhook>, String> {
+    storage::webhooks::load_webhooks().map_err(|e| e.to_string())
 }
 
-async fn open_url_async(url: String) -> Result<(), String> {
-    let url = url.trim().to_string();
-    if url.is_empty() {
-        return Ok(());
-    }
+async fn save_webhooks_async(webhooks: Vec<Webhook>) -> Result<(), String> {
+    storage::webhooks::save_webhooks(&webhooks).map_err(|e| e.to_string())
+}
 
-    #[cfg(target_os = "windows")]
-    let cmd = format!("start {}", shell_escape(&url));
-    #[cfg(target_os = "macos")]
-    let cmd = format!("open {}", shell_escape(&url));
-    #[cfg(all(unix, not(target_os = "macos")))]
-    let cmd = format!("xdg-open {}", shell_escape(&url));
+async fn load_typed_triggers_async() -> Result<Vec<TypedTrigger>, String> {
+    storage::typed_triggers::load_typed_triggers().map_err(|e| e.to_string())
+}
 
-    issue_command_async(cmd, None, Some(5_000)).await
+async fn save_typed_triggers_async(triggers: Vec<TypedTrigger>) -> Result<(), String> {
+    storage::typed_triggers::save_typed_triggers(&triggers).map_err(|e| e.to_string())
 }
 
-fn shell_escape(s: &str) -> String {
-    // Minimal, safe shell escaping for bash -lc.
-    // Wrap in single quotes and escape internal single quotes.
-    let mut out = String::from("'");
-    for ch in s.chars() {
-        if ch == '\'' {
-            out.push_str("'\\''");
-        } else {
-            out.push(ch);
-        }
-    }
-    out.push('\'');
-    out
+async fn load_process_watchers_async() -> Result<Vec<ProcessWatcher>, String> {
+    storage::process_watchers::load_process_watchers().map_err(|e| e.to_string())
 }
 
-async fn play_sound_async(path: String) -> Result<(), String> {
-    // Use a blocking thread because rodio decoding + playback is blocking.
-    tokio::task::spawn_blocking(move || -> Result<(), String> {
-        use std::fs::File;
-        use std::io::BufReader;
+async fn save_process_watchers_async(watchers: Vec<ProcessWatcher>) -> Result<(), String> {
+    storage::process_watchers::save_process_watchers(&watchers).map_err(|e| e.to_string())
+}
 
-        let (_stream, handle) = rodio::OutputStream::try_default().map_err(|e| e.to_string())?;
-        let sink = rodio::Sink::try_new(&handle).map_err(|e| e.to_string())?;
+async fn load_midi_triggers_async() -> Result<Vec<MidiTrigger>, String> {
+    storage::midi_triggers::load_midi_triggers().map_err(|e| e.to_string())
+}
 
-        let f = File::open(&path).map_err(|e| e.to_string())?;
-        let src = rodio::Decoder::new(BufReader::new(f)).map_err(|e| e.to_string())?;
-        sink.append(src);
-        sink.sleep_until_end();
-        Ok(())
-    })
-    .await
-    .map_err(|e| e.to_string())?
+async fn save_midi_triggers_async(triggers: Vec<MidiTrigger>) -> Result<(), String> {
+    storage::midi_triggers::save_midi_triggers(&triggers).map_err(|e| e.to_string())
 }
 
-fn marketplace_icon_url(source: &MarketplaceSource, plugin: &MarketplacePlugin) -> Option<String> {
-    if let Some(url) = &plugin.icon_url {
-        if !url.trim().is_empty() {
-            return Some(url.clone());
-        }
-    }
-    let base = source.icon_base_url.as_ref()?.trim();
-    if base.is_empty() {
-        return None;
-    }
-    let mut out = base.to_string();
-    if !out.ends_with('/') {
-        out.push('/');
-    }
-    out.push_str(&plugin.id);
-    out.push_str(".png");
-    Some(out)
+async fn load_osc_triggers_async() -> Result<Vec<OscTrigger>, String> {
+    storage::osc_triggers::load_osc_triggers().map_err(|e| e.to_string())
 }
 
-fn resolve_marketplace_download_url(
-    source: &MarketplaceSource,
-    plugin: &MarketplacePlugin,
-) -> Option<String> {
-    let raw = plugin.download_url.as_ref()?.trim();
-    if raw.is_empty() {
-        return None;
-    }
+async fn save_osc_triggers_async(triggers: Vec<OscTrigger>) -> Result<(), String> {
+    storage::osc_triggers::save_osc_triggers(&triggers).map_err(|e| e.to_string())
+}
 
-    // Absolute.
-    if reqwest::Url::parse(raw).is_ok() {
-        return Some(raw.to_string());
-    }
+async fn load_activity_async() -> Result<VecDeque<ActivityEntry>, String> {
+    storage::activity::load_activity().map_err(|e| e.to_string())
+}
 
-    // Relative: resolve against the marketplace index URL.
-    let base = reqwest::Url::parse(source.index_url.trim()).ok()?;
-    base.join(raw).ok().map(|u| u.to_string())
+async fn save_activity_async(entries: VecDeque<ActivityEntry>) -> Result<(), String> {
+    storage::activity::save_activity(&entries).map_err(|e| e.to_string())
 }
 
-fn resolve_marketplace_asset_url(source: &MarketplaceSource, raw: &str) -> Option<String> {
-    let raw = raw.trim();
-    if raw.is_empty() {
-        return None;
-    }
+async fn load_usage_async() -> Result<UsageFile, String> {
+    storage::usage::load_usage().map_err(|e| e.to_string())
+}
 
-    // Absolute.
-    if reqwest::Url::parse(raw).is_ok() {
-        return Some(raw.to_string());
-    }
+async fn save_usage_async(usage: UsageFile) -> Result<(), String> {
+    storage::usage::save_usage(&usage).map_err(|e| e.to_string())
+}
 
-    // Relative: resolve against the marketplace index URL.
-    let base = reqwest::Url::parse(source.index_url.trim()).ok()?;
-    base.join(raw).ok().map(|u| u.to_string())
+async fn save_webhook_settings_async(webhook: WebhookSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.webhook = webhook)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
-fn parse_github_owner_repo(repo_url: &str) -> Option<(String, String)> {
-    let url = reqwest::Url::parse(repo_url.trim()).ok()?;
-    if url.host_str()? != "github.com" {
-        return None;
-    }
-    let mut segs = url.path_segments()?;
-    let owner = segs.next()?.trim().to_string();
-    let repo = segs.next()?.trim().trim_end_matches(".git").to_string();
-    if owner.is_empty() || repo.is_empty() {
-        return None;
-    }
-    Some((owner, repo))
+async fn save_notification_mirror_async(
+    notification_mirror: NotificationMirrorSettings,
+) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.notification_mirror = notification_mirror)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
-async fn fetch_github_readme_md_async(owner: &str, repo: &str) -> Result<(String, String), String> {
-    let candidates = [
-        ("main", "README.md"),
-        ("main", "readme.md"),
-        ("master", "README.md"),
-        ("master", "readme.md"),
-    ];
-    for (branch, file) in candidates {
-        let url = format!(
-            "https://raw.githubusercontent.com/{owner}/{repo}/{branch}/{file}"
-        );
-        match openaction::marketplace::fetch_bytes(&url).await {
-            Ok(bytes) => {
-                let md = String::from_utf8(bytes).map_err(|_| "README is not utf-8".to_string())?;
-                return Ok((branch.to_string(), md));
-            }
-            Err(_) => continue,
-        }
-    }
-    Err("README not found (tried main/master README.md/readme.md)".to_string())
+async fn save_text_expander_settings_async(
+    text_expander: TextExpanderSettings,
+) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.text_expander = text_expander)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
-fn extract_readme_image_urls(md: &str) -> Vec<String> {
-    let mut out = Vec::new();
+async fn save_midi_settings_async(midi: MidiSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.midi = midi)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    // Markdown: ![alt](url)
-    let bytes = md.as_bytes();
-    let mut i = 0;
-    while i + 3 < bytes.len() {
-        if bytes[i] == b'!' && bytes[i + 1] == b'[' {
-            // find "]("
-            if let Some(close_bracket) = md[i + 2..].find("](") {
-                let start = i + 2 + close_bracket + 2; // points at '('
-                // actual url begins after '('
-                let url_start = start + 1;
-                if url_start >= md.len() {
-                    break;
-                }
-                if let Some(end_rel) = md[url_start..].find(')') {
-                    let url_raw = md[url_start..url_start + end_rel].trim();
-                    // strip optional title: "url \"title\""
-                    let url_only = url_raw
-                        .split_whitespace()
-                        .next()
-                        .unwrap_or("")
-                        .trim()
-                        .trim_matches('<')
-                        .trim_matches('>');
-                    if !url_only.is_empty() {
-                        out.push(url_only.to_string());
-                    }
-                    i = url_start + end_rel + 1;
-                    continue;
-                }
-            }
-        }
-        i += 1;
-    }
+async fn save_osc_settings_async(osc: OscSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.osc = osc)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    // HTML: <img src="...">
-    let mut rest = md;
-    while let Some(pos) = rest.find("src=\"") {
-        rest = &rest[pos + 5..];
-        if let Some(end) = rest.find('"') {
-            let url = rest[..end].trim();
-            if !url.is_empty() {
-                out.push(url.to_string());
-            }
-            rest = &rest[end + 1..];
-        } else {
-            break;
-        }
-    }
+async fn save_companion_settings_async(companion: CompanionSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.companion = companion)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    // De-dupe, preserve order
-    let mut uniq = Vec::new();
-    for u in out {
-        if !uniq.contains(&u) {
-            uniq.push(u);
-        }
-    }
-    uniq
+async fn save_marketplace_settings_async(marketplace: MarketplaceSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.marketplace = marketplace)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
-fn is_renderable_image_url(url: &str) -> bool {
-    let u = url.trim().to_ascii_lowercase();
-    if u.is_empty() {
-        return false;
-    }
-    // Accept image-like URLs, even if they lack an extension (e.g. shields.io badges).
-    // We'll sniff bytes after download to decide svg vs png vs jpeg.
-    u.contains("://")
+async fn save_secret_async(store: SecretStore, name: String, value: String) -> Result<(), String> {
+    // The keyring may ask to be unlocked; keep it off the async executor.
+    tokio::task::spawn_blocking(move || store.set(&name, &value))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|_| ())
+        .map_err(|e| format!("{e:#}"))
 }
 
-fn is_png_bytes(bytes: &[u8]) -> bool {
-    bytes.len() >= 8
-        && bytes[0..8] == [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]
+async fn remove_secret_async(store: SecretStore, name: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || store.remove(&name))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{e:#}"))
 }
 
-fn is_jpeg_bytes(bytes: &[u8]) -> bool {
-    bytes.len() >= 3 && bytes[0] == 0xFF && bytes[1] == 0xD8 && bytes[2] == 0xFF
+async fn save_sync_settings_async(sync: SyncSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.sync = sync)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
-fn is_gif_bytes(bytes: &[u8]) -> bool {
-    bytes.len() >= 6 && (&bytes[0..6] == b"GIF87a" || &bytes[0..6] == b"GIF89a")
+async fn load_surfaces_async() -> Result<Vec<Surface>, String> {
+    storage::surfaces::load_surfaces().map_err(|e| e.to_string())
+}
+
+async fn save_surfaces_async(surfaces: Vec<Surface>) -> Result<(), String> {
+    storage::surfaces::save_surfaces(&surfaces).map_err(|e| format!("{e:#}"))
 }
 
-fn is_webp_bytes(bytes: &[u8]) -> bool {
-    // RIFF....WEBP
-    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP"
+async fn create_surface_profile_async(surface: Surface) -> Result<Profile, String> {
+    let name = format!("{} profile", surface.name);
+    let mut p =
+        storage::profiles::create_profile(&name, surface.key_count()).map_err(|e| e.to_string())?;
+    p.surface = Some(surface.id);
+    storage::profiles::save_profile(&p).map_err(|e| e.to_string())?;
+    Ok(p)
 }
 
-fn decode_raster_to_png_bytes(bytes: &[u8]) -> Result<Vec<u8>, String> {
-    use ::image::codecs::png::PngEncoder;
-    use ::image::{ColorType, ImageEncoder};
+async fn save_kiosk_settings_async(kiosk: KioskSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.kiosk = kiosk)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    let img =
-        ::image::load_from_memory(bytes).map_err(|e| format!("image decode failed: {e}"))?;
-    let rgba = img.to_rgba8();
-    let (w, h) = rgba.dimensions();
+async fn sync_async(folder: std::path::PathBuf) -> Result<SyncReport, String> {
+    storage::sync::sync(&folder).map_err(|e| format!("{e:#}"))
+}
 
-    let mut out = Vec::new();
-    let enc = PngEncoder::new(&mut out);
-    enc.write_image(&rgba, w, h, ColorType::Rgba8.into())
-        .map_err(|e| format!("png encode failed: {e}"))?;
-    Ok(out)
+async fn resolve_sync_conflict_async(
+    folder: std::path::PathBuf,
+    file: String,
+    resolution: Resolution,
+) -> Result<(String, Resolution), String> {
+    storage::sync::resolve(&folder, &file, resolution).map_err(|e| format!("{e:#}"))?;
+    Ok((file, resolution))
 }
 
-fn is_svg_bytes(bytes: &[u8]) -> bool {
-    // Best-effort sniff: look for "<svg" within the first ~1KB (after optional XML decl/whitespace).
-    let head = &bytes[..bytes.len().min(1024)];
-    let s = String::from_utf8_lossy(head).to_ascii_lowercase();
-    s.contains("<svg") && !s.contains("<html")
+/// One line on what a sync did.
+fn sync_summary(report: &SyncReport) -> String {
+    let counts = [
+        (report.pulled.len(), "received"),
+        (report.pushed.len(), "sent"),
+        (report.merged.len(), "merged"),
+        (report.conflicts.len(), "in conflict"),
+    ];
+    let parts: Vec<String> = counts
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, what)| format!("{n} {what}"))
+        .collect();
+    if parts.is_empty() {
+        return "In sync.".to_string();
+    }
+    format!("Files last synced: {}.", parts.join(", "))
 }
 
-fn validate_svg_bytes(bytes: &[u8]) -> Result<(), String> {
-    // Avoid noisy runtime errors: if parsing fails, treat as a failed image load.
-    use usvg::TreeParsing;
-    let opt = usvg::Options::default();
-    usvg::Tree::from_data(bytes, &opt)
+async fn save_home_assistant_settings_async(
+    home_assistant: HomeAssistantSettings,
+) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.home_assistant = home_assistant)
         .map(|_| ())
-        .map_err(|e: usvg::Error| e.to_string())
+        .map_err(|e| e.to_string())
 }
 
-fn resolve_github_readme_asset_url(owner: &str, repo: &str, branch: &str, raw: &str) -> Option<String> {
-    let raw = raw.trim();
-    if raw.is_empty() {
-        return None;
-    }
+async fn save_lighting_settings_async(
+    lighting: storage::settings::LightingSettings,
+) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.lighting = lighting)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    // Already absolute.
-    if reqwest::Url::parse(raw).is_ok() {
-        // Convert GitHub blob URLs to raw URLs.
-        if raw.starts_with("https://github.com/") && raw.contains("/blob/") {
-            // https://github.com/{owner}/{repo}/blob/{branch}/path -> raw.githubusercontent.com/{owner}/{repo}/{branch}/path
-            let prefix = format!("https://github.com/{owner}/{repo}/blob/");
-            if let Some(rest) = raw.strip_prefix(&prefix) {
-                let mut it = rest.splitn(2, '/');
-                let b = it.next().unwrap_or(branch);
-                let path = it.next().unwrap_or("");
-                return Some(format!(
-                    "https://raw.githubusercontent.com/{owner}/{repo}/{b}/{path}"
-                ));
-            }
-        }
-        return Some(raw.to_string());
-    }
+async fn fetch_weather_async(
+    weather: Weather,
+    query: WeatherQuery,
+    max_age: Duration,
+) -> (WeatherQuery, Result<WeatherReport, String>) {
+    let report = weather
+        .current(&query.0, query.1, max_age)
+        .await
+        .map_err(|e| format!("{e:#}"));
+    (query, report)
+}
 
-    // Root-relative within repo.
-    if raw.starts_with('/') {
-        return Some(format!(
-            "https://raw.githubusercontent.com/{owner}/{repo}/{branch}{}",
-            raw
-        ));
-    }
+async fn do_not_disturb_async() -> Result<bool, String> {
+    engine::do_not_disturb::enabled()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
 
-    // Relative path.
-    let rel = raw.trim_start_matches("./");
-    Some(format!(
-        "https://raw.githubusercontent.com/{owner}/{repo}/{branch}/{rel}"
-    ))
+async fn meeting_state_async(
+    meetings: Meetings,
+    app: actions::MeetingApp,
+) -> (actions::MeetingApp, Result<MeetingState, String>) {
+    let state = meetings.state(app).await.map_err(|e| format!("{e:#}"));
+    (app, state)
 }
 
-async fn fetch_github_releases_info_async(owner: &str, repo: &str) -> Result<(u64, Option<String>), String> {
-    let url = format!("https://api.github.com/repos/{owner}/{repo}/releases");
-    let client = reqwest::Client::builder()
-        .user_agent("RiverDeck-Redux/0.1 (Marketplace)")
-        .build()
-        .map_err(|e| e.to_string())?;
+async fn radio_async(radio: actions::RadioKind) -> (actions::RadioKind, Result<bool, String>) {
+    let on = engine::platform::radio_enabled(radio)
+        .await
+        .map_err(|e| format!("{e:#}"));
+    (radio, on)
+}
 
-    let resp = client
-        .get(url)
-        .header("Accept", "application/vnd.github+json")
-        .send()
+async fn latest_ci_run_async(
+    github_actions: GithubActions,
+    query: CiQuery,
+) -> (CiQuery, Result<Option<CiRun>, String>) {
+    let run = github_actions
+        .latest_run(&query)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| format!("{e:#}"));
+    (query, run)
+}
 
-    if !resp.status().is_success() {
-        return Err(format!("GitHub API error: {}", resp.status()));
-    }
+async fn list_containers_async() -> Result<Vec<Container>, String> {
+    engine::docker::containers()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
 
-    let v: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
-    let releases = v.as_array().ok_or_else(|| "unexpected GitHub API response".to_string())?;
+async fn list_midi_output_ports_async() -> Result<Vec<String>, String> {
+    engine::midi::output_ports().map_err(|e| format!("{e:#}"))
+}
 
-    let mut total: u64 = 0;
-    for rel in releases {
-        if let Some(assets) = rel.get("assets").and_then(|a| a.as_array()) {
-            for a in assets {
-                if let Some(c) = a.get("download_count").and_then(|c| c.as_u64()) {
-                    total += c;
-                }
-            }
-        }
-    }
+async fn list_midi_input_ports_async() -> Result<Vec<String>, String> {
+    engine::midi::input_ports().map_err(|e| format!("{e:#}"))
+}
 
-    // Pick best installable asset from the latest release (first in list).
-    let asset_url = releases
-        .first()
-        .and_then(|rel| rel.get("assets").and_then(|a| a.as_array()))
-        .and_then(|assets| pick_best_github_asset_url(assets));
+async fn list_serial_ports_async() -> Result<Vec<String>, String> {
+    engine::serial::ports().map_err(|e| format!("{e:#}"))
+}
 
-    Ok((total, asset_url))
+async fn light_state_async(
+    lights: Lights,
+    light: actions::LightTarget,
+) -> (actions::LightTarget, Result<LightState, String>) {
+    let state = lights.state(&light).await.map_err(|e| format!("{e:#}"));
+    (light, state)
 }
 
-fn pick_best_github_asset_url(assets: &[serde_json::Value]) -> Option<String> {
-    // Gather candidates (archive/plugin-bundle assets only).
-    let mut cands: Vec<(i32, String)> = vec![];
-    for a in assets {
-        let name = a.get("name").and_then(|n| n.as_str()).unwrap_or("").to_ascii_lowercase();
-        let url = a
-            .get("browser_download_url")
-            .and_then(|u| u.as_str())
-            .unwrap_or("")
-            .to_string();
-        if url.is_empty() {
-            continue;
-        }
-        // Common archive formats + known plugin bundle extensions.
-        let is_supported = name.ends_with(".zip")
-            || name.ends_with(".tar.gz")
-            || name.ends_with(".tgz")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".opendeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin")
-            || name.ends_with(".streamdeckplugin");
-        if !is_supported {
-            continue;
-        }
-        let mut score = 0;
-        // Prefer platform matches.
-        if cfg!(windows) {
-            if name.contains("windows") || name.contains("win") {
-                score += 50;
-            }
-        } else if cfg!(target_os = "macos") {
-            if name.contains("mac") || name.contains("darwin") || name.contains("osx") {
-                score += 50;
-            }
-        } else {
-            if name.contains("linux") {
-                score += 50;
-            }
-        }
-        // Prefer common packaging formats slightly.
-        if name.ends_with(".zip") {
-            score += 10;
-        }
-        if name.ends_with(".streamdeckplugin") {
-            score += 12;
-        }
-        cands.push((score, url));
-    }
-    cands.sort_by(|a, b| b.0.cmp(&a.0));
-    cands.first().map(|(_, u)| u.clone())
+async fn discover_lights_async() -> Result<Vec<Discovered>, String> {
+    lighting::discover(LIGHT_DISCOVERY_WAIT)
+        .await
+        .map_err(|e| format!("{e:#}"))
 }
 
-async fn resolve_github_release_asset_url_async(repo_url: &str) -> Result<String, String> {
-    let (owner, repo) =
-        parse_github_owner_repo(repo_url).ok_or_else(|| "unsupported repository url".to_string())?;
-    let (_count, asset) = fetch_github_releases_info_async(&owner, &repo).await?;
-    asset.ok_or_else(|| "no downloadable release archive found on GitHub".to_string())
+async fn list_hue_lights_async(lights: Lights) -> Result<Vec<HueLight>, String> {
+    lights.hue_lights().await.map_err(|e| format!("{e:#}"))
 }
 
-fn parse_bg_rgb(raw: &str) -> Option<[u8; 3]> {
-    let s = raw.trim();
-    if s.is_empty() {
-        return None;
-    }
-    let parts: Vec<&str> = s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()).collect();
-    if parts.len() != 3 {
-        return None;
+async fn pair_hue_async(lights: Lights, bridge: String) -> Result<(String, String), String> {
+    match lights.pair_hue(&bridge).await {
+        Ok(key) => Ok((bridge, key)),
+        Err(e) => Err(format!("{e:#}")),
     }
-    let r = parts[0].parse::<u8>().ok()?;
-    let g = parts[1].parse::<u8>().ok()?;
-    let b = parts[2].parse::<u8>().ok()?;
-    Some([r, g, b])
 }
 
-async fn invoke_action_async(
-    plugin: InstalledPlugin,
-    action_id: String,
-    control: InvocationControl,
-    event: InvocationEvent,
-    settings: serde_json::Value,
-) -> Result<(), String> {
-    let rt = ActionRuntime::new();
-    rt.invoke(
-        &plugin,
-        &action_id,
-        control,
-        event,
-        settings,
-    )
+async fn git_status_async(repo: String) -> (String, Result<GitStatus, String>) {
+    let status = engine::git::status(&repo)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| format!("{e:#}"));
+    (repo, status)
 }
 
-fn build_action_choices(plugins: &[InstalledPlugin]) -> Vec<ActionChoice> {
-    let mut out = vec![];
-    for p in plugins {
-        for a in &p.manifest.actions {
-            out.push(ActionChoice {
-                plugin_id: p.manifest.id.clone(),
-                action_id: a.id.clone(),
-                label: format!("{}: {}", p.manifest.name, a.name),
-            });
-        }
-    }
-    out.sort_by(|a, b| a.label.to_lowercase().cmp(&b.label.to_lowercase()));
-    out
+async fn resume_spotify_async(spotify: Spotify) -> Result<(), String> {
+    spotify.resume().map_err(|e| format!("{e:#}"))
 }
 
-fn find_action_def_by_ids<'a>(
-    plugins: &'a [InstalledPlugin],
-    plugin_id: &str,
-    action_id: &str,
-) -> Option<(&'a InstalledPlugin, &'a ActionDefinition)> {
-    let plugin = plugins.iter().find(|p| p.manifest.id == plugin_id)?;
-    let action = plugin.manifest.actions.iter().find(|a| a.id == action_id)?;
-    Some((plugin, action))
+async fn spotify_sign_in_async(spotify: Spotify, client_id: String) -> Result<(), String> {
+    spotify
+        .sign_in(&client_id)
+        .await
+        .map_err(|e| format!("Spotify sign-in failed: {e:#}"))
 }
 
-fn default_settings_for_action(
-    plugins: &[InstalledPlugin],
-    choice: &ActionChoice,
-) -> serde_json::Value {
-    let Some((_plugin, action)) =
-        find_action_def_by_ids(plugins, &choice.plugin_id, &choice.action_id)
-    else {
-        return serde_json::Value::Object(serde_json::Map::new());
-    };
-
-    let mut map = serde_json::Map::new();
-    for f in &action.settings {
-        let v = if let Some(d) = &f.default {
-            d.clone()
-        } else {
-            match f.ty {
-                SettingType::String => serde_json::Value::String(String::new()),
-                SettingType::Boolean => serde_json::Value::Bool(false),
-                SettingType::Number => serde_json::Value::Null,
-            }
-        };
-        map.insert(f.key.clone(), v);
-    }
-    serde_json::Value::Object(map)
+async fn spotify_sign_out_async(spotify: Spotify) -> Result<(), String> {
+    spotify.sign_out().await.map_err(|e| format!("{e:#}"))
 }
 
-async fn connect_device_async(
-    id: app_core::ids::DeviceId,
-    events_slot: Arc<std::sync::Mutex<Option<Receiver<DeviceEvent>>>>,
-) -> Result<ConnectedInfo, String> {
-    let svc = HidDeviceService::new().map_err(|e| e.to_string())?;
-    let dev = svc.connect(id).await.map_err(|e| e.to_string())?;
-    let controller = dev.controller();
+async fn list_installed_apps_async() -> Result<Vec<AppEntry>, String> {
+    tokio::task::spawn_blocking(engine::launcher::installed_apps)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    {
-        let mut guard = events_slot
-            .lock()
-            .map_err(|_| "events mutex poisoned".to_string())?;
-        *guard = Some(dev.events);
-    }
+async fn list_vscode_folders_async() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(engine::vscode::recent_workspaces)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    Ok(ConnectedInfo {
-        id: dev.id,
-        name: dev.name,
-        key_count: dev.key_count,
-        controller,
-        events_slot,
+async fn list_vscode_tasks_async(folder: String) -> Result<(String, Vec<String>), String> {
+    tokio::task::spawn_blocking(move || {
+        let tasks = engine::vscode::tasks(&folder).map_err(|e| format!("{e:#}"))?;
+        Ok((folder, tasks.into_iter().map(|t| t.label).collect()))
     })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
-async fn apply_displays_async(controller: DeviceController, profile: Profile) -> Result<(), String> {
-    use std::path::PathBuf;
-
-    let (key_w, key_h) = match profile.key_count {
-        6 => (80, 80),
-        32 => (96, 96),
-        8 => (120, 120), // Stream Deck+ (best-effort default)
-        _ => (72, 72),
-    };
-
-    // Keys
-    for (idx, k) in profile.keys.iter().enumerate() {
-        let bg = match k.appearance.background {
-            storage::profiles::Background::Solid { rgb } => Some(rgb),
-            storage::profiles::Background::None => None,
-        };
-        let icon_path = k
-            .appearance
-            .icon_path
-            .as_ref()
-            .map(|s| PathBuf::from(s));
-        let icon_ref = icon_path.as_deref();
-        let text = k.appearance.text.as_deref();
-
-        let jpeg = render::lcd::render_lcd_jpeg(
-            key_w,
-            key_h,
-            bg,
-            icon_ref,
-            text,
-        )
-        .map_err(|e| e.to_string())?;
+async fn list_audio_devices_async() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(audio::output_devices)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
 
-        controller
-            .set_key_image_jpeg(idx as u8, jpeg)
-            .await
-            .map_err(|e| e.to_string())?;
-    }
+async fn save_audio_device_async(device: Option<String>) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.audio_output_device = device)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-    // Stream Deck+ extras (best-effort sizes; device protocol may differ by firmware).
-    if profile.key_count == 8 {
-        // Dials
-        for (idx, d) in profile.dials.iter().enumerate().take(4) {
-            let bg = match d.appearance.background {
-                storage::profiles::Background::Solid { rgb } => Some(rgb),
-                storage::profiles::Background::None => None,
-            };
-            let icon_path = d
-                .appearance
-                .icon_path
-                .as_ref()
-                .map(|s| PathBuf::from(s));
-            let icon_ref = icon_path.as_deref();
-            let text = d.appearance.text.as_deref();
+async fn load_themes_async() -> Result<Vec<UserTheme>, String> {
+    tokio::task::spawn_blocking(storage::themes::list_themes)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
 
-            let jpeg = render::lcd::render_lcd_jpeg(100, 100, bg, icon_ref, text)
-                .map_err(|e| e.to_string())?;
+async fn save_interface_async(interface: InterfaceSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.interface = interface)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-            controller
-                .set_dial_image_jpeg(idx as u8, jpeg)
-                .await
-                .map_err(|e| e.to_string())?;
-        }
+async fn save_macro_settings_async(macros: MacroSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.macros = macros)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-        // Touch strip
-        let bg = match profile.touch_strip.appearance.background {
-            storage::profiles::Background::Solid { rgb } => Some(rgb),
-            storage::profiles::Background::None => None,
-        };
-        let icon_path = profile
-            .touch_strip
-            .appearance
-            .icon_path
-            .as_ref()
-            .map(|s| PathBuf::from(s));
-        let icon_ref = icon_path.as_deref();
-        let text = profile.touch_strip.appearance.text.as_deref();
+async fn save_touch_gestures_async(gestures: TouchGestureSettings) -> Result<(), String> {
+    storage::settings::update_settings(|s| s.touch_gestures = gestures)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
 
-        let jpeg = render::lcd::render_lcd_jpeg(800, 100, bg, icon_ref, text)
-            .map_err(|e| e.to_string())?;
+async fn save_device_brightness_async(device: u64, percent: u8) -> Result<(), String> {
+    storage::settings::update_settings(|s| {
+        s.device_brightness.insert(device, percent);
+    })
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
 
-        controller
-            .set_touch_strip_image_jpeg(jpeg)
-            .await
-            .map_err(|e| e.to_string())?;
-    }
+async fn clear_device_async(controller: DeviceController) -> Result<(), String> {
+    controller.clear_all().await.map_err(|e| e.to_string())
+}
 
-    Ok(())
+async fn clear_key_async(controller: DeviceController, key: u8) -> Result<(), String> {
+    controller.clear_key(key).await.map_err(|e| e.to_string())
 }
 
 async fn set_brightness_async(controller: DeviceController, percent: u8) -> Result<(), String> {
@@ -0,0 +1,55 @@
+//! Look of `pomodoro` keys: the phase and the time left, on a background in the phase's colour.
+//!
+//! A running phase is drawn in full colour; a paused one, or one waiting for a press to start,
+//! in a darker shade.
+
+use std::time::SystemTime;
+
+use actions::BuiltinAction;
+use storage::pomodoro::{PomodoroLengths, PomodoroPhase, PomodoroTimer};
+
+const WORK_RGB: [u8; 3] = [200, 56, 46];
+const SHORT_BREAK_RGB: [u8; 3] = [46, 150, 84];
+const LONG_BREAK_RGB: [u8; 3] = [44, 110, 196];
+
+/// Text and background of a `pomodoro` key.
+pub struct KeyLook {
+    /// The phase (or `Paused`) above the time left.
+    pub text: String,
+    pub background_rgb: [u8; 3],
+}
+
+/// What the `pomodoro` binding `action` shows with `timer` (`None` before it first ran) at
+/// `now`; `None` for other actions.
+pub fn key_look(
+    action: &BuiltinAction,
+    timer: Option<&PomodoroTimer>,
+    now: SystemTime,
+) -> Option<KeyLook> {
+    let lengths = PomodoroLengths::of(action)?;
+    let timer = timer.copied().unwrap_or_default();
+    // Rounded up, so the key reads 0:00 only when the phase is over.
+    let secs = timer.remaining(&lengths, now).as_millis().div_ceil(1000);
+    let time = format!("{}:{:02}", secs / 60, secs % 60);
+    let paused = !timer.is_running() && timer.remaining_ms.is_some();
+    let status = if paused {
+        "Paused"
+    } else {
+        timer.phase.label()
+    };
+
+    let rgb = match timer.phase {
+        PomodoroPhase::Work => WORK_RGB,
+        PomodoroPhase::ShortBreak => SHORT_BREAK_RGB,
+        PomodoroPhase::LongBreak => LONG_BREAK_RGB,
+    };
+    let background_rgb = if timer.is_running() {
+        rgb
+    } else {
+        rgb.map(|c| c / 2)
+    };
+    Some(KeyLook {
+        text: format!("{status}\n{time}"),
+        background_rgb,
+    })
+}
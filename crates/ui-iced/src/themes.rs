@@ -0,0 +1,84 @@
+//! Window themes: the built-in dark and light themes and the user's theme files.
+//!
+//! Theme files are read by [`storage::themes`]; this module turns them into iced themes. The
+//! app picks them up again whenever a file in the themes folder changes, so edits show up live.
+
+use std::fmt;
+
+use iced::theme::Palette;
+use iced::{Color, Theme};
+use storage::settings::ThemeSetting;
+use storage::themes::{ThemeColors, ThemeRadii, UserTheme};
+
+/// The theme in effect, with the corner radii of the app's own widgets.
+#[derive(Debug, Clone)]
+pub struct ActiveTheme {
+    pub theme: Theme,
+    pub radii: ThemeRadii,
+}
+
+impl ActiveTheme {
+    /// Resolve `setting`; a theme file that is gone (or unreadable) falls back to the dark theme.
+    pub fn resolve(setting: &ThemeSetting, user_themes: &[UserTheme]) -> Self {
+        let builtin = |theme| Self {
+            theme,
+            radii: ThemeRadii::default(),
+        };
+        match setting {
+            ThemeSetting::Dark => builtin(Theme::TokyoNightStorm),
+            ThemeSetting::Light => builtin(Theme::TokyoNightLight),
+            ThemeSetting::Custom(id) => match user_themes.iter().find(|t| &t.id == id) {
+                Some(t) => Self {
+                    theme: Theme::custom(t.name().to_string(), palette(&t.theme.colors)),
+                    radii: t.theme.radii,
+                },
+                None => builtin(Theme::TokyoNightStorm),
+            },
+        }
+    }
+}
+
+fn palette(colors: &ThemeColors) -> Palette {
+    let rgb = |[r, g, b]: [u8; 3]| Color::from_rgb8(r, g, b);
+    Palette {
+        background: rgb(colors.background),
+        text: rgb(colors.text),
+        primary: rgb(colors.primary),
+        success: rgb(colors.success),
+        danger: rgb(colors.danger),
+    }
+}
+
+/// Entry of the theme picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeChoice {
+    pub setting: ThemeSetting,
+    pub label: String,
+}
+
+impl ThemeChoice {
+    /// The built-in themes followed by the user's theme files.
+    pub fn all(user_themes: &[UserTheme]) -> Vec<Self> {
+        let mut out = vec![
+            Self {
+                setting: ThemeSetting::Dark,
+                label: "Dark".to_string(),
+            },
+            Self {
+                setting: ThemeSetting::Light,
+                label: "Light".to_string(),
+            },
+        ];
+        out.extend(user_themes.iter().map(|t| Self {
+            setting: ThemeSetting::Custom(t.id.clone()),
+            label: t.name().to_string(),
+        }));
+        out
+    }
+}
+
+impl fmt::Display for ThemeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
@@ -0,0 +1,215 @@
+//! System tray icon (Linux, StatusNotifierItem via `ksni`).
+//!
+//! The tray keeps the app reachable while the window is closed and the engine keeps dispatching
+//! actions. Menu activations are forwarded to the app as [`TrayCommand`]s; the app pushes what
+//! the menu shows as a [`TrayState`].
+
+use app_core::ids::ProfileId;
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Debug, Clone)]
+pub enum TrayCommand {
+    ShowWindow,
+    SwitchProfile(ProfileId),
+    SetPaused(bool),
+    Quit,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrayState {
+    /// `(id, name)` of the profiles for the connected device.
+    pub profiles: Vec<(ProfileId, String)>,
+    pub active_profile: Option<ProfileId>,
+    pub paused: bool,
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::TrayHandle;
+
+#[cfg(not(target_os = "linux"))]
+pub use unsupported::TrayHandle;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use ksni::menu::{CheckmarkItem, RadioGroup, RadioItem, StandardItem, SubMenu};
+    use ksni::MenuItem;
+
+    use super::{ProfileId, TrayCommand, TrayState, UnboundedSender};
+
+    struct RiverDeckTray {
+        state: TrayState,
+        commands: UnboundedSender<TrayCommand>,
+        /// A StatusNotifierWatcher (tray host) is running, so the icon is actually visible.
+        available: Arc<AtomicBool>,
+    }
+
+    impl RiverDeckTray {
+        fn send(&self, cmd: TrayCommand) {
+            // The app may be shutting down; nothing to do then.
+            let _ = self.commands.send(cmd);
+        }
+
+        fn profile_items(&self) -> Vec<MenuItem<Self>> {
+            if self.state.profiles.is_empty() {
+                return vec![StandardItem {
+                    label: "No profiles".to_string(),
+                    enabled: false,
+                    ..Default::default()
+                }
+                .into()];
+            }
+            let ids: Vec<ProfileId> = self.state.profiles.iter().map(|(id, _)| *id).collect();
+            let selected = self
+                .state
+                .active_profile
+                .and_then(|active| ids.iter().position(|id| *id == active))
+                .unwrap_or(usize::MAX);
+            vec![RadioGroup {
+                selected,
+                select: Box::new(move |tray: &mut Self, idx| {
+                    if let Some(id) = ids.get(idx) {
+                        tray.send(TrayCommand::SwitchProfile(*id));
+                    }
+                }),
+                options: self
+                    .state
+                    .profiles
+                    .iter()
+                    .map(|(_, name)| RadioItem {
+                        label: name.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+            .into()]
+        }
+    }
+
+    impl ksni::Tray for RiverDeckTray {
+        fn id(&self) -> String {
+            "riverdeck-redux".to_string()
+        }
+
+        fn title(&self) -> String {
+            "RiverDeck-Redux".to_string()
+        }
+
+        fn icon_name(&self) -> String {
+            "input-keyboard".to_string()
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayCommand::ShowWindow);
+        }
+
+        fn watcher_online(&self) {
+            self.available.store(true, Ordering::Relaxed);
+        }
+
+        fn watcher_offine(&self) -> bool {
+            self.available.store(false, Ordering::Relaxed);
+            // Keep the service alive so the icon reappears when a tray host starts.
+            true
+        }
+
+        fn menu(&self) -> Vec<MenuItem<Self>> {
+            vec![
+                StandardItem {
+                    label: "Show window".to_string(),
+                    activate: Box::new(|tray: &mut Self| tray.send(TrayCommand::ShowWindow)),
+                    ..Default::default()
+                }
+                .into(),
+                SubMenu {
+                    label: "Profile".to_string(),
+                    submenu: self.profile_items(),
+                    ..Default::default()
+                }
+                .into(),
+                CheckmarkItem {
+                    label: "Pause actions".to_string(),
+                    checked: self.state.paused,
+                    activate: Box::new(|tray: &mut Self| {
+                        tray.send(TrayCommand::SetPaused(!tray.state.paused))
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+                MenuItem::Separator,
+                StandardItem {
+                    label: "Quit".to_string(),
+                    icon_name: "application-exit".to_string(),
+                    activate: Box::new(|tray: &mut Self| tray.send(TrayCommand::Quit)),
+                    ..Default::default()
+                }
+                .into(),
+            ]
+        }
+    }
+
+    pub struct TrayHandle {
+        handle: ksni::Handle<RiverDeckTray>,
+        available: Arc<AtomicBool>,
+        state: TrayState,
+    }
+
+    impl TrayHandle {
+        /// Register the tray icon on the session bus (runs on its own thread).
+        pub fn spawn(commands: UnboundedSender<TrayCommand>) -> Option<Self> {
+            let available = Arc::new(AtomicBool::new(false));
+            let service = ksni::TrayService::new(RiverDeckTray {
+                state: TrayState::default(),
+                commands,
+                available: available.clone(),
+            });
+            let handle = service.handle();
+            service.spawn();
+            Some(Self {
+                handle,
+                available,
+                state: TrayState::default(),
+            })
+        }
+
+        /// Whether a tray host is showing the icon (otherwise hiding the window would strand it).
+        pub fn is_available(&self) -> bool {
+            self.available.load(Ordering::Relaxed)
+        }
+
+        pub fn set_state(&mut self, state: TrayState) {
+            if state == self.state {
+                return;
+            }
+            self.state = state.clone();
+            self.handle.update(move |tray| tray.state = state);
+        }
+    }
+
+    impl Drop for TrayHandle {
+        fn drop(&mut self) {
+            self.handle.shutdown();
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use super::{TrayCommand, TrayState, UnboundedSender};
+
+    pub struct TrayHandle;
+
+    impl TrayHandle {
+        pub fn spawn(_commands: UnboundedSender<TrayCommand>) -> Option<Self> {
+            None
+        }
+
+        pub fn is_available(&self) -> bool {
+            false
+        }
+
+        pub fn set_state(&mut self, _state: TrayState) {}
+    }
+}
@@ -0,0 +1,133 @@
+//! Variables shared between actions.
+//!
+//! A persisted name/value store: `set_variable` actions write to it, `{{name}}` templates in key
+//! text and action fields read from it, and subscribers are told about every change so keys
+//! showing a variable can re-render.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// A variable was set (`Some`) or removed (`None`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableChange {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Default)]
+struct Inner {
+    values: BTreeMap<String, String>,
+    subscribers: Vec<mpsc::UnboundedSender<VariableChange>>,
+}
+
+/// Handle to the store. Cheap to clone; all clones share the same values.
+#[derive(Clone, Default)]
+pub struct VariableStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl VariableStore {
+    /// Load the persisted variables (starting empty if they can't be read).
+    pub fn load() -> Self {
+        let values = storage::variables::load_variables().unwrap_or_else(|e| {
+            warn!(error = %e, "failed to load variables; starting empty");
+            BTreeMap::new()
+        });
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                values,
+                subscribers: vec![],
+            })),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.lock().values.get(name).cloned()
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, String> {
+        self.lock().values.clone()
+    }
+
+    /// Set (`Some`) or remove (`None`) a variable, persist the store and notify subscribers.
+    ///
+    /// Setting a variable to its current value is a no-op. If the store can't be saved, the
+    /// variable keeps its old value and no one is notified.
+    pub fn set(&self, name: &str, value: Option<String>) -> anyhow::Result<()> {
+        let mut inner = self.lock();
+        let previous = match &value {
+            Some(v) => inner.values.insert(name.to_string(), v.clone()),
+            None => inner.values.remove(name),
+        };
+        if previous == value {
+            return Ok(());
+        }
+        if let Err(e) = storage::variables::save_variables(&inner.values) {
+            // Keep the store matching what is on disk.
+            match previous {
+                Some(v) => inner.values.insert(name.to_string(), v),
+                None => inner.values.remove(name),
+            };
+            return Err(e);
+        }
+        debug!(name, ?value, "variable changed");
+
+        let change = VariableChange {
+            name: name.to_string(),
+            value,
+        };
+        inner
+            .subscribers
+            .retain(|tx| tx.send(change.clone()).is_ok());
+        Ok(())
+    }
+
+    /// Receive every change from now on.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<VariableChange> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.lock().subscribers.push(tx);
+        rx
+    }
+
+    /// Expand `{{name}}` placeholders in `template` with the current values.
+    pub fn render(&self, template: &str) -> String {
+        let inner = self.lock();
+        render_template(template, |name| inner.values.get(name).cloned())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("variable store mutex poisoned")
+    }
+}
+
+/// Expand `{{name}}` placeholders using `lookup`; unknown variables expand to nothing.
+///
+/// Whitespace around the name is ignored and an unterminated `{{` is kept as-is.
+pub fn render_template(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        if let Some(value) = lookup(after[..end].trim()) {
+            out.push_str(&value);
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether `template` contains any placeholder (i.e. may change with the variables).
+pub fn has_placeholders(template: &str) -> bool {
+    template
+        .find("{{")
+        .is_some_and(|start| template[start + 2..].contains("}}"))
+}
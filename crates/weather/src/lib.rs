@@ -0,0 +1,365 @@
+//! Current weather from Open-Meteo, which needs no account or API key.
+//!
+//! Place names are looked up with Open-Meteo's geocoder; `latitude,longitude` is used as is.
+//! Reports are kept in memory and in the cache directory, so keys keep showing the last report
+//! while offline and right after a restart. Conditions icons are drawn once into the cache
+//! directory, for use as key icons.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use actions::WeatherUnits;
+use anyhow::Context;
+use render::weather::WeatherIcon;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// How often reports are fetched when a binding sets no `refresh_min`.
+pub const DEFAULT_REFRESH: Duration = Duration::from_secs(15 * 60);
+/// Size of the icon files; keys scale them down.
+const ICON_SIZE: u32 = 144;
+/// Geocoder results considered when the location names a country or region after a comma.
+const GEOCODING_CANDIDATES: &str = "10";
+
+/// Current conditions at a place.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// Name of the place as found by the geocoder, or the coordinates given.
+    pub place: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// In the units asked for.
+    pub temperature: f64,
+    pub units: WeatherUnits,
+    /// WMO weather interpretation code.
+    pub code: u8,
+    pub is_day: bool,
+    /// Seconds since the Unix epoch.
+    pub fetched_at: u64,
+    /// Read from the cache because the last fetch failed.
+    #[serde(skip)]
+    pub stale: bool,
+    /// Icon of the conditions, drawn into the cache directory.
+    #[serde(skip)]
+    pub icon_path: Option<PathBuf>,
+}
+
+impl Report {
+    /// Rounded temperature and unit, e.g. `13C`; the display font has no degree sign.
+    pub fn temperature_text(&self) -> String {
+        let unit = match self.units {
+            WeatherUnits::Metric => "C",
+            WeatherUnits::Imperial => "F",
+        };
+        // Adding zero turns -0 into 0.
+        format!("{:.0}{unit}", self.temperature.round() + 0.0)
+    }
+
+    /// The conditions in words, e.g. `Light rain`.
+    pub fn conditions(&self) -> &'static str {
+        match self.code {
+            0 => "Clear",
+            1 => "Mainly clear",
+            2 => "Partly cloudy",
+            3 => "Overcast",
+            45 | 48 => "Fog",
+            51 | 53 | 55 => "Drizzle",
+            56 | 57 => "Freezing drizzle",
+            61 => "Light rain",
+            63 => "Rain",
+            65 => "Heavy rain",
+            66 | 67 => "Freezing rain",
+            71 | 73 | 75 | 77 => "Snow",
+            80..=82 => "Showers",
+            85 | 86 => "Snow showers",
+            95 => "Thunderstorm",
+            96 | 99 => "Thunderstorm, hail",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn icon(&self) -> WeatherIcon {
+        match (self.code, self.is_day) {
+            (0 | 1, true) => WeatherIcon::Clear,
+            (0 | 1, false) => WeatherIcon::ClearNight,
+            (2, true) => WeatherIcon::PartlyCloudy,
+            (2, false) => WeatherIcon::PartlyCloudyNight,
+            (45 | 48, _) => WeatherIcon::Fog,
+            (51..=57, _) => WeatherIcon::Drizzle,
+            (61..=67 | 80..=82, _) => WeatherIcon::Rain,
+            (71..=77 | 85 | 86, _) => WeatherIcon::Snow,
+            (95..=99, _) => WeatherIcon::Thunderstorm,
+            _ => WeatherIcon::Cloudy,
+        }
+    }
+
+    /// Time since the report was fetched.
+    pub fn age(&self) -> Duration {
+        let fetched = SystemTime::UNIX_EPOCH + Duration::from_secs(self.fetched_at);
+        fetched.elapsed().unwrap_or_default()
+    }
+}
+
+/// Reports by lowercased location and units.
+type Reports = HashMap<(String, WeatherUnits), Report>;
+
+struct Shared {
+    http: reqwest::Client,
+    reports: Mutex<Reports>,
+}
+
+/// Handle to the client. Cheap to clone; all clones share one cache.
+#[derive(Clone)]
+pub struct Weather {
+    shared: Arc<Shared>,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Weather {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent("RiverDeck-Redux/0.1 (Weather)")
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            shared: Arc::new(Shared {
+                http,
+                reports: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// The current weather at `location`, fetched when the cached report is older than
+    /// `max_age`. When fetching fails the cached report is returned with
+    /// [`Report::stale`] set; the error only when nothing was ever fetched for `location`.
+    pub async fn current(
+        &self,
+        location: &str,
+        units: WeatherUnits,
+        max_age: Duration,
+    ) -> anyhow::Result<Report> {
+        let location = location.trim();
+        anyhow::ensure!(!location.is_empty(), "no location set");
+        let key = (location.to_lowercase(), units);
+        let cached = self.cached(&key);
+        if let Some(report) = cached.as_ref().filter(|r| !r.stale && r.age() < max_age) {
+            return Ok(report.clone());
+        }
+
+        match self.fetch(location, units, cached.as_ref()).await {
+            Ok(mut report) => {
+                report.icon_path = icon_file(report.icon());
+                if let Err(e) = save_report(&key, &report) {
+                    warn!(location, error = %format!("{e:#}"), "failed to cache weather report");
+                }
+                lock(&self.shared.reports).insert(key, report.clone());
+                Ok(report)
+            }
+            Err(e) => {
+                let Some(mut report) = cached else {
+                    return Err(e);
+                };
+                let error = format!("{e:#}");
+                warn!(location, %error, "weather fetch failed; showing the cached report");
+                report.stale = true;
+                lock(&self.shared.reports).insert(key, report.clone());
+                Ok(report)
+            }
+        }
+    }
+
+    /// The report in memory, else the one cached on disk by an earlier run.
+    fn cached(&self, key: &(String, WeatherUnits)) -> Option<Report> {
+        if let Some(report) = lock(&self.shared.reports).get(key) {
+            return Some(report.clone());
+        }
+        let mut report = load_report(key)?;
+        report.icon_path = icon_file(report.icon());
+        lock(&self.shared.reports).insert(key.clone(), report.clone());
+        Some(report)
+    }
+
+    async fn fetch(
+        &self,
+        location: &str,
+        units: WeatherUnits,
+        cached: Option<&Report>,
+    ) -> anyhow::Result<Report> {
+        // The place does not move; only look it up the first time.
+        let (place, latitude, longitude) = match (cached, coordinates(location)) {
+            (Some(r), _) => (r.place.clone(), r.latitude, r.longitude),
+            (None, Some((lat, lon))) => (location.to_string(), lat, lon),
+            (None, None) => self.geocode(location).await?,
+        };
+
+        let unit = match units {
+            WeatherUnits::Metric => "celsius",
+            WeatherUnits::Imperial => "fahrenheit",
+        };
+        let body: Value = self
+            .shared
+            .http
+            .get(FORECAST_URL)
+            .query(&[
+                ("latitude", latitude.to_string()),
+                ("longitude", longitude.to_string()),
+                ("current", "temperature_2m,weather_code,is_day".to_string()),
+                ("temperature_unit", unit.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let current = &body["current"];
+        let temperature = current["temperature_2m"]
+            .as_f64()
+            .context("the forecast has no temperature")?;
+        let code = current["weather_code"]
+            .as_u64()
+            .and_then(|c| u8::try_from(c).ok())
+            .context("the forecast has no weather code")?;
+
+        Ok(Report {
+            place,
+            latitude,
+            longitude,
+            temperature,
+            units,
+            code,
+            is_day: current["is_day"].as_u64() != Some(0),
+            fetched_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            stale: false,
+            icon_path: None,
+        })
+    }
+
+    /// Name and coordinates of the place called `location`. A country or region after a comma
+    /// (`Springfield, Illinois`) picks among places of the same name.
+    async fn geocode(&self, location: &str) -> anyhow::Result<(String, f64, f64)> {
+        let (name, qualifier) = match location.split_once(',') {
+            Some((name, qualifier)) => (name.trim(), qualifier.trim().to_lowercase()),
+            None => (location, String::new()),
+        };
+        let body: Value = self
+            .shared
+            .http
+            .get(GEOCODING_URL)
+            .query(&[
+                ("name", name),
+                ("count", GEOCODING_CANDIDATES),
+                ("format", "json"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let results = body["results"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+        let matches = |place: &&Value| {
+            ["country", "country_code", "admin1"].iter().any(|field| {
+                place[field]
+                    .as_str()
+                    .is_some_and(|v| v.to_lowercase() == qualifier)
+            })
+        };
+        let place = results
+            .iter()
+            .find(|p| qualifier.is_empty() || matches(p))
+            .or(results.first())
+            .with_context(|| format!("no place called \"{name}\" found"))?;
+
+        let latitude = place["latitude"]
+            .as_f64()
+            .context("the place has no latitude")?;
+        let longitude = place["longitude"]
+            .as_f64()
+            .context("the place has no longitude")?;
+        let place_name = place["name"].as_str().unwrap_or(name).to_string();
+        Ok((place_name, latitude, longitude))
+    }
+}
+
+fn lock(reports: &Mutex<Reports>) -> std::sync::MutexGuard<'_, Reports> {
+    reports.lock().expect("weather cache mutex poisoned")
+}
+
+/// `latitude,longitude` in degrees.
+fn coordinates(location: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = location.split_once(',')?;
+    let lat: f64 = lat.trim().parse().ok()?;
+    let lon: f64 = lon.trim().parse().ok()?;
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+}
+
+fn report_path(key: &(String, WeatherUnits)) -> anyhow::Result<PathBuf> {
+    let (location, units) = key;
+    let stem: String = location
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let units = match units {
+        WeatherUnits::Metric => "metric",
+        WeatherUnits::Imperial => "imperial",
+    };
+    let dir = storage::paths::cache_dir()?.join("weather");
+    Ok(dir.join(format!("{stem}-{units}.json")))
+}
+
+fn load_report(key: &(String, WeatherUnits)) -> Option<Report> {
+    let text = std::fs::read_to_string(report_path(key).ok()?).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn save_report(key: &(String, WeatherUnits), report: &Report) -> anyhow::Result<()> {
+    let path = report_path(key)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Written aside and renamed, so a crash never leaves half a report.
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(report)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// The PNG of `icon` in the cache directory, drawn the first time it is needed.
+fn icon_file(icon: WeatherIcon) -> Option<PathBuf> {
+    let draw = || -> anyhow::Result<PathBuf> {
+        let dir = storage::paths::cache_dir()?.join("weather").join("icons");
+        let path = dir.join(format!("{}.png", icon.name()));
+        if !path.exists() {
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(&path, render::weather::weather_icon_png(icon, ICON_SIZE)?)?;
+        }
+        Ok(path)
+    };
+    match draw() {
+        Ok(path) => Some(path),
+        Err(e) => {
+            warn!(icon = icon.name(), error = %format!("{e:#}"), "failed to draw weather icon");
+            None
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Arbitrary zip entry names: a name that is accepted always lands inside the staging
+//! directory.
+#![no_main]
+
+use std::path::{Component, Path};
+
+use libfuzzer_sys::fuzz_target;
+use openaction::installer::sanitize_rel_path;
+
+fuzz_target!(|name: &str| {
+    let Ok(rel) = sanitize_rel_path(name) else {
+        return;
+    };
+    assert!(
+        rel.components().all(|c| matches!(c, Component::Normal(_))),
+        "{name:?} -> {rel:?}"
+    );
+    let staging = Path::new("/tmp/riverdeck-staging");
+    assert!(
+        staging.join(&rel).starts_with(staging),
+        "{name:?} -> {rel:?}"
+    );
+});
@@ -0,0 +1,65 @@
+//! Arbitrary input reports for every input protocol: parsing must not panic, and updates only
+//! name controls the deck has, each going down before it goes up.
+#![no_main]
+
+use device::input::{emit_plus_events, parse_key_states_v1, InputParser, InputUpdate};
+use libfuzzer_sys::fuzz_target;
+
+/// Product id and key count of a deck of each input protocol.
+const DECKS: [(u16, u8); 5] = [
+    (0x0060, 15),
+    (0x0063, 6),
+    (0x0080, 15),
+    (0x006c, 32),
+    (0x0084, 8),
+];
+
+/// Dials of the Stream Deck+.
+const DIALS: usize = 4;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&pick, mut rest)) = data.split_first() else {
+        return;
+    };
+    let (product_id, key_count) = DECKS[usize::from(pick) % DECKS.len()];
+    let mut parser = InputParser::new(product_id, key_count);
+    let mut keys = vec![false; usize::from(key_count)];
+    let mut dials = [false; DIALS];
+
+    // The rest is a series of reports, each preceded by its length.
+    while let Some((&len, tail)) = rest.split_first() {
+        let (report, tail) = tail.split_at(usize::from(len).min(tail.len()));
+        rest = tail;
+
+        for update in parser.parse(report) {
+            match update {
+                InputUpdate::KeyDown(key) | InputUpdate::KeyUp(key) => {
+                    let down = matches!(update, InputUpdate::KeyDown(_));
+                    let pressed = &mut keys[usize::from(key)];
+                    assert_ne!(*pressed, down, "{update:?} repeated");
+                    *pressed = down;
+                }
+                InputUpdate::DialDown(dial) | InputUpdate::DialUp(dial) => {
+                    let down = matches!(update, InputUpdate::DialDown(_));
+                    let pressed = &mut dials[usize::from(dial)];
+                    assert_ne!(*pressed, down, "{update:?} repeated");
+                    *pressed = down;
+                }
+                InputUpdate::DialTurn(dial, steps) => {
+                    assert!(usize::from(dial) < DIALS && steps != 0, "{update:?}");
+                }
+                InputUpdate::TouchPress { .. }
+                | InputUpdate::TouchLongPress { .. }
+                | InputUpdate::TouchSwipe { .. } => {}
+            }
+        }
+
+        for mirrored in [false, true] {
+            let states = parse_key_states_v1(report, usize::from(key_count), mirrored);
+            assert_eq!(states.len(), usize::from(key_count));
+        }
+        let mut plus_keys = [false; 8];
+        let mut plus_dials = [false; DIALS];
+        emit_plus_events(report, &mut plus_keys, &mut plus_dials);
+    }
+});
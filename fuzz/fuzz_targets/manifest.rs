@@ -0,0 +1,18 @@
+//! Arbitrary `manifest.json` text, in either schema: parsing must not panic, and what it
+//! parses to reads back the same in our schema.
+#![no_main]
+
+use std::path::Path;
+
+use libfuzzer_sys::fuzz_target;
+use openaction::manifest::PluginManifest;
+
+fuzz_target!(|raw: &str| {
+    let plugin_dir = Path::new("/tmp/riverdeck-plugins/fuzz.sdPlugin");
+    let Ok(manifest) = PluginManifest::parse(raw, plugin_dir) else {
+        return;
+    };
+    let json = serde_json::to_string(&manifest).expect("a parsed manifest serializes");
+    let again = PluginManifest::parse(&json, plugin_dir).expect("a serialized manifest parses");
+    assert_eq!(serde_json::to_string(&again).unwrap(), json);
+});
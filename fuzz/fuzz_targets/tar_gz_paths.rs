@@ -0,0 +1,39 @@
+//! `.tar.gz` archives with arbitrary entry names, extracted the way plugins are installed:
+//! nothing may be written outside the staging directory.
+#![no_main]
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use libfuzzer_sys::fuzz_target;
+use openaction::installer::extract_tar_gz;
+
+/// A `.tar.gz` archive of one-byte files named `names`. The names are written into the headers
+/// as they are, since the `tar` builder refuses names with `..`.
+fn tar_gz(names: &[&[u8]]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+    for name in names {
+        let mut header = tar::Header::new_old();
+        let field = &mut header.as_old_mut().name;
+        let len = name.len().min(field.len());
+        field[..len].copy_from_slice(&name[..len]);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(1);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"x"[..]).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+fuzz_target!(|names: Vec<&[u8]>| {
+    let root = tempfile::tempdir().unwrap();
+    let staging = root.path().join("staging");
+    std::fs::create_dir(&staging).unwrap();
+    let _ = extract_tar_gz(&tar_gz(&names), &staging);
+
+    let beside: Vec<_> = std::fs::read_dir(root.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(beside, ["staging"], "{names:?}");
+});